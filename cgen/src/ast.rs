@@ -0,0 +1,666 @@
+use std::fmt::{self, Display, Write};
+
+/// The integer types of the generated programs. Both are 32 bits wide on every target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    Int,
+    Unsigned,
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => f.write_str("int"),
+            Ty::Unsigned => f.write_str("unsigned"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VarKind {
+    Scalar(Ty),
+    Array(Ty, usize),
+    Pointer(Ty),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    pub name: String,
+    pub kind: VarKind,
+    /// The initial value of a scalar, globals without one are zero.
+    pub init: Option<i64>,
+}
+
+/// A function without side effects: it only reads globals and only writes its own locals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub ret: Ty,
+    pub params: Vec<(String, Ty)>,
+    pub body: Vec<Stmt>,
+    pub ret_value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub globals: Vec<Global>,
+    pub functions: Vec<Function>,
+    /// The body of `main`, without the checksum of the globals that is printed at the end.
+    pub main: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    DeclScalar {
+        name: String,
+        ty: Ty,
+        init: Expr,
+    },
+    /// An uninitialized local array, its elements are assigned by the following statements.
+    DeclArray {
+        name: String,
+        ty: Ty,
+        len: usize,
+    },
+    DeclPointer {
+        name: String,
+        ty: Ty,
+        target: LValue,
+    },
+    Assign {
+        lhs: LValue,
+        rhs: Expr,
+    },
+    /// `pointer = &target;`
+    PointerAssign {
+        pointer: String,
+        target: LValue,
+    },
+    /// `lvalue++;` or `lvalue--;`, only used for unsigned lvalues.
+    Increment {
+        lvalue: LValue,
+        decrement: bool,
+    },
+    If {
+        cond: Expr,
+        then: Vec<Stmt>,
+        /// No `else` is printed if this is empty.
+        otherwise: Vec<Stmt>,
+    },
+    /// `for (int var = 0; var < count; var++) body`
+    For {
+        var: String,
+        count: u32,
+        body: Vec<Stmt>,
+    },
+    /// `int var = 0; while (var < count) { body var++; }`, so `body` can't contain a `continue`
+    /// for this loop.
+    While {
+        var: String,
+        count: u32,
+        body: Vec<Stmt>,
+    },
+    Switch {
+        value: Expr,
+        cases: Vec<Case>,
+    },
+    Break,
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    /// `None` for the `default` case.
+    pub value: Option<i64>,
+    pub body: Vec<Stmt>,
+    /// Whether the case ends with a `break`, otherwise it falls through.
+    pub is_break: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LValue {
+    Var(String),
+    /// Printed as `array[(unsigned)(index) % len]`, so it's always in bounds.
+    Index {
+        array: String,
+        len: usize,
+        index: Box<Expr>,
+    },
+    Deref(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    LogAnd,
+    LogOr,
+}
+
+impl BinOp {
+    pub const ALL: [BinOp; 18] = [
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::Mul,
+        BinOp::Div,
+        BinOp::Rem,
+        BinOp::Shl,
+        BinOp::Shr,
+        BinOp::BitAnd,
+        BinOp::BitOr,
+        BinOp::BitXor,
+        BinOp::Lt,
+        BinOp::Le,
+        BinOp::Gt,
+        BinOp::Ge,
+        BinOp::Eq,
+        BinOp::Ne,
+        BinOp::LogAnd,
+        BinOp::LogOr,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::LogAnd => "&&",
+            BinOp::LogOr => "||",
+        }
+    }
+
+    /// Whether the result is an `int` truth value, instead of a value of the operand type.
+    pub fn is_boolean(self) -> bool {
+        matches!(
+            self,
+            BinOp::Lt
+                | BinOp::Le
+                | BinOp::Gt
+                | BinOp::Ge
+                | BinOp::Eq
+                | BinOp::Ne
+                | BinOp::LogAnd
+                | BinOp::LogOr
+        )
+    }
+
+    /// The `safe_*` helper for the operation on `int`s, if it can be undefined.
+    fn helper(self) -> Option<&'static str> {
+        match self {
+            BinOp::Add => Some("safe_add"),
+            BinOp::Sub => Some("safe_sub"),
+            BinOp::Mul => Some("safe_mul"),
+            BinOp::Div => Some("safe_div"),
+            BinOp::Rem => Some("safe_mod"),
+            BinOp::Shl => Some("safe_shl"),
+            BinOp::Shr => Some("safe_shr"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Plus,
+    BitNot,
+    LogNot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Literal(Ty, i64),
+    Read(LValue),
+    Binary {
+        op: BinOp,
+        /// The type of the operands.
+        ty: Ty,
+        /// Use the `safe_*` helper instead of masking the operands, for `int` operations that
+        /// could be undefined.
+        safe: bool,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnOp,
+        ty: Ty,
+        operand: Box<Expr>,
+    },
+    Cast(Ty, Box<Expr>),
+    Cond {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    Call(String, Vec<Expr>),
+    /// `(lhs == rhs)` or `(lhs != rhs)` of two pointers.
+    PointerCompare {
+        lhs: String,
+        rhs: String,
+        negate: bool,
+    },
+}
+
+const HELPERS: [(&str, &str); 7] = [
+    (
+        "safe_add",
+        "int safe_add(int a, int b) {
+    if ((b > 0 && a > 2147483647 - b) || (b < 0 && a < -2147483647 - 1 - b))
+        return a;
+    return a + b;
+}",
+    ),
+    (
+        "safe_sub",
+        "int safe_sub(int a, int b) {
+    if ((b < 0 && a > 2147483647 + b) || (b > 0 && a < -2147483647 - 1 + b))
+        return a;
+    return a - b;
+}",
+    ),
+    (
+        "safe_mul",
+        "int safe_mul(int a, int b) {
+    if (a > 0) {
+        if (b > 0) {
+            if (a > 2147483647 / b)
+                return a;
+        } else if (b < (-2147483647 - 1) / a) {
+            return a;
+        }
+    } else if (b > 0) {
+        if (a < (-2147483647 - 1) / b)
+            return a;
+    } else if (a != 0 && b < 2147483647 / a) {
+        return a;
+    }
+    return a * b;
+}",
+    ),
+    (
+        "safe_div",
+        "int safe_div(int a, int b) {
+    if (b == 0 || (a == -2147483647 - 1 && b == -1))
+        return a;
+    return a / b;
+}",
+    ),
+    (
+        "safe_mod",
+        "int safe_mod(int a, int b) {
+    if (b == 0 || (a == -2147483647 - 1 && b == -1))
+        return a;
+    return a % b;
+}",
+    ),
+    (
+        "safe_shl",
+        "int safe_shl(int a, int b) {
+    if (a < 0 || b < 0 || b >= 31 || a > (2147483647 >> b))
+        return a;
+    return a << b;
+}",
+    ),
+    (
+        "safe_shr",
+        "int safe_shr(int a, int b) {
+    if (b < 0 || b >= 32)
+        return a;
+    return a >> b;
+}",
+    ),
+];
+
+impl Program {
+    /// Calls `f` for every expression of the program, including nested ones.
+    pub fn for_each_expr(&self, f: &mut impl FnMut(&Expr)) {
+        for function in &self.functions {
+            for_each_expr_in_block(&function.body, f);
+            for_each_expr_in_expr(&function.ret_value, f);
+        }
+        for_each_expr_in_block(&self.main, f);
+    }
+
+    fn uses_helper(&self, helper: &str) -> bool {
+        let mut used = false;
+        self.for_each_expr(&mut |expr| {
+            if let Expr::Binary {
+                op,
+                ty: Ty::Int,
+                safe: true,
+                ..
+            } = expr
+            {
+                used |= op.helper() == Some(helper);
+            }
+        });
+        used
+    }
+}
+
+fn for_each_expr_in_block(block: &[Stmt], f: &mut impl FnMut(&Expr)) {
+    for stmt in block {
+        match stmt {
+            Stmt::DeclScalar { init: expr, .. } => for_each_expr_in_expr(expr, f),
+            Stmt::DeclPointer { target, .. }
+            | Stmt::PointerAssign { target, .. }
+            | Stmt::Increment { lvalue: target, .. } => for_each_expr_in_lvalue(target, f),
+            Stmt::Assign { lhs, rhs } => {
+                for_each_expr_in_lvalue(lhs, f);
+                for_each_expr_in_expr(rhs, f);
+            }
+            Stmt::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                for_each_expr_in_expr(cond, f);
+                for_each_expr_in_block(then, f);
+                for_each_expr_in_block(otherwise, f);
+            }
+            Stmt::For { body, .. } | Stmt::While { body, .. } => for_each_expr_in_block(body, f),
+            Stmt::Switch { value, cases } => {
+                for_each_expr_in_expr(value, f);
+                for case in cases {
+                    for_each_expr_in_block(&case.body, f);
+                }
+            }
+            Stmt::DeclArray { .. } | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+}
+
+fn for_each_expr_in_lvalue(lvalue: &LValue, f: &mut impl FnMut(&Expr)) {
+    if let LValue::Index { index, .. } = lvalue {
+        for_each_expr_in_expr(index, f);
+    }
+}
+
+fn for_each_expr_in_expr(expr: &Expr, f: &mut impl FnMut(&Expr)) {
+    f(expr);
+    match expr {
+        Expr::Read(lvalue) => for_each_expr_in_lvalue(lvalue, f),
+        Expr::Binary { lhs, rhs, .. } => {
+            for_each_expr_in_expr(lhs, f);
+            for_each_expr_in_expr(rhs, f);
+        }
+        Expr::Unary { operand, .. } | Expr::Cast(_, operand) => for_each_expr_in_expr(operand, f),
+        Expr::Cond {
+            cond,
+            then,
+            otherwise,
+        } => {
+            for_each_expr_in_expr(cond, f);
+            for_each_expr_in_expr(then, f);
+            for_each_expr_in_expr(otherwise, f);
+        }
+        Expr::Call(_, args) => {
+            for arg in args {
+                for_each_expr_in_expr(arg, f);
+            }
+        }
+        Expr::Literal(..) | Expr::PointerCompare { .. } => {}
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#include <stdio.h>")?;
+
+        for (name, definition) in HELPERS {
+            if self.uses_helper(name) {
+                write!(f, "\n{definition}\n")?;
+            }
+        }
+
+        if !self.globals.is_empty() {
+            writeln!(f)?;
+        }
+        for global in &self.globals {
+            match (&global.kind, global.init) {
+                (VarKind::Scalar(ty), Some(init)) => writeln!(f, "{ty} {} = {init};", global.name)?,
+                (VarKind::Scalar(ty), None) => writeln!(f, "{ty} {};", global.name)?,
+                (VarKind::Array(ty, len), _) => writeln!(f, "{ty} {}[{len}];", global.name)?,
+                (VarKind::Pointer(ty), _) => writeln!(f, "{ty}* {};", global.name)?,
+            }
+        }
+
+        for function in &self.functions {
+            let params = function
+                .params
+                .iter()
+                .map(|(name, ty)| format!("{ty} {name}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "\n{} {}({params}) {{", function.ret, function.name)?;
+            fmt_block(f, &function.body, 1)?;
+            writeln!(f, "    return {};", function.ret_value)?;
+            writeln!(f, "}}")?;
+        }
+
+        writeln!(f, "\nint main() {{")?;
+        fmt_block(f, &self.main, 1)?;
+        writeln!(f, "    unsigned crc = (unsigned)0;")?;
+        for global in &self.globals {
+            match global.kind {
+                VarKind::Scalar(_) => writeln!(
+                    f,
+                    "    crc = crc * (unsigned)31 + (unsigned){};",
+                    global.name
+                )?,
+                VarKind::Array(_, len) => writeln!(
+                    f,
+                    "    for (int i = 0; i < {len}; i++)\n        \
+                     crc = crc * (unsigned)31 + (unsigned){}[i];",
+                    global.name
+                )?,
+                // The pointers point to globals that are already part of the checksum.
+                VarKind::Pointer(_) => {}
+            }
+        }
+        writeln!(
+            f,
+            "    printf(\"checksum = %d %d\\n\", (int)(crc >> 16), (int)(crc & (unsigned)65535));"
+        )?;
+        writeln!(f, "    return 0;")?;
+        writeln!(f, "}}")
+    }
+}
+
+fn fmt_block(f: &mut fmt::Formatter<'_>, block: &[Stmt], depth: usize) -> fmt::Result {
+    for stmt in block {
+        fmt_stmt(f, stmt, depth)?;
+    }
+    Ok(())
+}
+
+fn fmt_stmt(f: &mut fmt::Formatter<'_>, stmt: &Stmt, depth: usize) -> fmt::Result {
+    let indent = "    ".repeat(depth);
+    match stmt {
+        Stmt::DeclScalar { name, ty, init } => writeln!(f, "{indent}{ty} {name} = {init};"),
+        Stmt::DeclArray { name, ty, len } => writeln!(f, "{indent}{ty} {name}[{len}];"),
+        Stmt::DeclPointer { name, ty, target } => {
+            writeln!(f, "{indent}{ty}* {name} = &{target};")
+        }
+        Stmt::Assign { lhs, rhs } => writeln!(f, "{indent}{lhs} = {rhs};"),
+        Stmt::PointerAssign { pointer, target } => writeln!(f, "{indent}{pointer} = &{target};"),
+        // Prefix, as `*p++` would increment the pointer.
+        Stmt::Increment { lvalue, decrement } => {
+            writeln!(
+                f,
+                "{indent}{}{lvalue};",
+                if *decrement { "--" } else { "++" }
+            )
+        }
+        Stmt::If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            writeln!(f, "{indent}if ({cond}) {{")?;
+            fmt_block(f, then, depth + 1)?;
+            if !otherwise.is_empty() {
+                writeln!(f, "{indent}}} else {{")?;
+                fmt_block(f, otherwise, depth + 1)?;
+            }
+            writeln!(f, "{indent}}}")
+        }
+        Stmt::For { var, count, body } => {
+            writeln!(
+                f,
+                "{indent}for (int {var} = 0; {var} < {count}; {var}++) {{"
+            )?;
+            fmt_block(f, body, depth + 1)?;
+            writeln!(f, "{indent}}}")
+        }
+        Stmt::While { var, count, body } => {
+            writeln!(f, "{indent}int {var} = 0;")?;
+            writeln!(f, "{indent}while ({var} < {count}) {{")?;
+            fmt_block(f, body, depth + 1)?;
+            writeln!(f, "{indent}    {var}++;")?;
+            writeln!(f, "{indent}}}")
+        }
+        Stmt::Switch { value, cases } => {
+            writeln!(f, "{indent}switch ({value}) {{")?;
+            // The bodies are blocks, because a case can't be followed by a declaration.
+            for case in cases {
+                match case.value {
+                    Some(value) => writeln!(f, "{indent}case {value}: {{")?,
+                    None => writeln!(f, "{indent}default: {{")?,
+                }
+                fmt_block(f, &case.body, depth + 1)?;
+                if case.is_break {
+                    writeln!(f, "{indent}    break;")?;
+                }
+                writeln!(f, "{indent}}}")?;
+            }
+            writeln!(f, "{indent}}}")
+        }
+        Stmt::Break => writeln!(f, "{indent}break;"),
+        Stmt::Continue => writeln!(f, "{indent}continue;"),
+    }
+}
+
+impl Display for LValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LValue::Var(name) => f.write_str(name),
+            LValue::Index { array, len, index } => {
+                write!(f, "{array}[(unsigned)({index}) % {len}]")
+            }
+            LValue::Deref(pointer) => write!(f, "*{pointer}"),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(Ty::Int, value) if *value < 0 => write!(f, "({value})"),
+            Expr::Literal(Ty::Int, value) => write!(f, "{value}"),
+            Expr::Literal(Ty::Unsigned, value) => write!(f, "(unsigned){value}"),
+            Expr::Read(LValue::Deref(pointer)) => write!(f, "(*{pointer})"),
+            Expr::Read(lvalue) => write!(f, "{lvalue}"),
+            Expr::Binary {
+                op,
+                ty,
+                safe,
+                lhs,
+                rhs,
+            } => fmt_binary(f, *op, *ty, *safe, lhs, rhs),
+            Expr::Unary {
+                op: UnOp::Neg,
+                ty: Ty::Int,
+                operand,
+            } => write!(f, "(-({operand} & 2147483647))"),
+            Expr::Unary { op, operand, .. } => {
+                let symbol = match op {
+                    UnOp::Neg => '-',
+                    UnOp::Plus => '+',
+                    UnOp::BitNot => '~',
+                    UnOp::LogNot => '!',
+                };
+                write!(f, "({symbol}{operand})")
+            }
+            // The value is masked so that the conversion to `int` doesn't depend on the target.
+            Expr::Cast(Ty::Int, operand) => {
+                write!(f, "((int)((unsigned)({operand}) & (unsigned)2147483647))")
+            }
+            Expr::Cast(Ty::Unsigned, operand) => write!(f, "((unsigned)({operand}))"),
+            Expr::Cond {
+                cond,
+                then,
+                otherwise,
+            } => write!(f, "({cond} ? {then} : {otherwise})"),
+            Expr::Call(function, args) => {
+                write!(f, "{function}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                f.write_char(')')
+            }
+            Expr::PointerCompare { lhs, rhs, negate } => {
+                write!(f, "({lhs} {} {rhs})", if *negate { "!=" } else { "==" })
+            }
+        }
+    }
+}
+
+/// Prints the operation so that it can't be undefined, whatever the values of the operands are.
+fn fmt_binary(
+    f: &mut fmt::Formatter<'_>,
+    op: BinOp,
+    ty: Ty,
+    safe: bool,
+    lhs: &Expr,
+    rhs: &Expr,
+) -> fmt::Result {
+    let symbol = op.symbol();
+    match (ty, op.helper()) {
+        (Ty::Int, Some(helper)) if safe => write!(f, "{helper}({lhs}, {rhs})"),
+        (Ty::Int, Some(_)) => match op {
+            // The results stay below 2^31 in absolute value.
+            BinOp::Add | BinOp::Sub => write!(f, "(({lhs} & 65535) {symbol} ({rhs} & 65535))"),
+            BinOp::Mul => write!(f, "(({lhs} & 32767) * ({rhs} & 65535))"),
+            BinOp::Div | BinOp::Rem => write!(f, "({lhs} {symbol} (({rhs} & 255) | 1))"),
+            BinOp::Shl => write!(f, "(({lhs} & 65535) << ({rhs} & 15))"),
+            _ => write!(f, "({lhs} >> ({rhs} & 31))"),
+        },
+        (Ty::Unsigned, Some(_)) => match op {
+            BinOp::Div | BinOp::Rem => write!(f, "({lhs} {symbol} ({rhs} | (unsigned)1))"),
+            BinOp::Shl | BinOp::Shr => write!(f, "({lhs} {symbol} ({rhs} & (unsigned)31))"),
+            _ => write!(f, "({lhs} {symbol} {rhs})"),
+        },
+        (_, None) => write!(f, "({lhs} {symbol} {rhs})"),
+    }
+}
@@ -0,0 +1,578 @@
+use crate::ast::*;
+use crate::rng::Rng;
+
+/// Limits on the size of the generated programs.
+#[derive(Debug, Clone)]
+pub struct GenerateOpts {
+    /// The number of globals, besides one `int` and one `unsigned` scalar that every program has.
+    pub max_globals: usize,
+    pub max_functions: usize,
+    pub max_params: usize,
+    /// The number of statements in a block, not counting the assignments that initialize arrays.
+    pub max_block_len: usize,
+    /// How deep `if`, loop and `switch` statements are nested.
+    pub max_stmt_depth: usize,
+    pub max_expr_depth: usize,
+    pub max_loop_count: u32,
+}
+
+impl Default for GenerateOpts {
+    fn default() -> Self {
+        Self {
+            max_globals: 8,
+            max_functions: 4,
+            max_params: 3,
+            max_block_len: 5,
+            max_stmt_depth: 3,
+            max_expr_depth: 4,
+            max_loop_count: 5,
+        }
+    }
+}
+
+/// Generates a random, well-defined program. The same seed and options always give the same
+/// program.
+pub fn generate(seed: u64, opts: &GenerateOpts) -> Program {
+    let mut generator = Generator {
+        rng: Rng::new(seed),
+        opts,
+        next_id: 0,
+        globals: Vec::new(),
+        functions: Vec::new(),
+        scopes: Vec::new(),
+        in_function: false,
+        contexts: Vec::new(),
+    };
+    generator.program()
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    name: String,
+    kind: VarKind,
+    writable: bool,
+    /// The number of scopes around the variable, 0 for globals. A pointer may only point to
+    /// variables with a depth lower than or equal to its own, so the target outlives it.
+    depth: usize,
+}
+
+/// The statements that `break` or `continue` may apply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    For,
+    While,
+    Switch,
+}
+
+struct Generator<'a> {
+    rng: Rng,
+    opts: &'a GenerateOpts,
+    next_id: usize,
+    globals: Vec<Var>,
+    /// The name, return type and parameter types of the functions generated so far, which are
+    /// the ones the current function may call.
+    functions: Vec<(String, Ty, Vec<Ty>)>,
+    scopes: Vec<Vec<Var>>,
+    /// Functions may not write globals, so that expressions don't have side effects.
+    in_function: bool,
+    contexts: Vec<Context>,
+}
+
+impl Generator<'_> {
+    fn program(&mut self) -> Program {
+        let mut globals = Vec::new();
+        let extra_globals = self.rng.range(0..=self.opts.max_globals);
+        for i in 0..2 + extra_globals {
+            let ty = match i {
+                0 => Ty::Int,
+                1 => Ty::Unsigned,
+                _ => self.ty(),
+            };
+            let kind = match self.rng.below(10) {
+                0..=1 if i >= 2 => VarKind::Array(ty, self.array_len()),
+                2 if i >= 2 => VarKind::Pointer(ty),
+                _ => VarKind::Scalar(ty),
+            };
+            let init = match kind {
+                VarKind::Scalar(ty) if self.rng.chance(70) => Some(self.literal_value(ty)),
+                _ => None,
+            };
+            let name = self.fresh("g");
+            globals.push(Global {
+                name: name.clone(),
+                kind: kind.clone(),
+                init,
+            });
+            self.globals.push(Var {
+                name,
+                kind,
+                writable: true,
+                depth: 0,
+            });
+        }
+
+        let mut functions = Vec::new();
+        for _ in 0..self.rng.range(0..=self.opts.max_functions) {
+            functions.push(self.function());
+        }
+
+        // Every global pointer points to a global before anything else happens.
+        let mut main = Vec::new();
+        for global in self.globals.clone() {
+            if let VarKind::Pointer(ty) = global.kind {
+                let target = self.pointer_target(ty, 0);
+                main.push(Stmt::PointerAssign {
+                    pointer: global.name,
+                    target,
+                });
+            }
+        }
+        self.scopes.push(Vec::new());
+        self.block_contents(&mut main, 0);
+        self.scopes.pop();
+
+        Program {
+            globals,
+            functions,
+            main,
+        }
+    }
+
+    fn function(&mut self) -> Function {
+        let name = self.fresh("func");
+        let ret = self.ty();
+        let params: Vec<_> = (0..self.rng.range(0..=self.opts.max_params))
+            .map(|_| (self.fresh("l"), self.ty()))
+            .collect();
+
+        self.in_function = true;
+        self.scopes.push(
+            params
+                .iter()
+                .map(|(name, ty)| Var {
+                    name: name.clone(),
+                    kind: VarKind::Scalar(*ty),
+                    writable: true,
+                    depth: 1,
+                })
+                .collect(),
+        );
+        let mut body = Vec::new();
+        self.block_contents(&mut body, 1);
+        let ret_value = self.expr(ret, 0);
+        self.scopes.pop();
+        self.in_function = false;
+
+        let param_types = params.iter().map(|(_, ty)| *ty).collect();
+        self.functions.push((name.clone(), ret, param_types));
+        Function {
+            name,
+            ret,
+            params,
+            body,
+            ret_value,
+        }
+    }
+
+    fn block(&mut self, depth: usize) -> Vec<Stmt> {
+        let mut block = Vec::new();
+        self.scopes.push(Vec::new());
+        self.block_contents(&mut block, depth);
+        self.scopes.pop();
+        block
+    }
+
+    fn block_contents(&mut self, block: &mut Vec<Stmt>, depth: usize) {
+        for _ in 0..self.rng.range(1..=self.opts.max_block_len) {
+            // Nothing after a `break` or `continue` would be executed.
+            if !self.stmt(block, depth) {
+                break;
+            }
+        }
+    }
+
+    /// Adds one statement (or a few, to initialize an array) to `block`. Returns `false` if the
+    /// statement was a `break` or `continue`.
+    fn stmt(&mut self, block: &mut Vec<Stmt>, depth: usize) -> bool {
+        let nested = depth < self.opts.max_stmt_depth;
+        match self.rng.below(100) {
+            0..=34 => self.assign(block),
+            35..=49 => self.decl(block),
+            50..=61 if nested => {
+                let cond = self.expr(Ty::Int, 0);
+                let then = self.block(depth + 1);
+                let otherwise = match self.rng.chance(50) {
+                    true => self.block(depth + 1),
+                    false => Vec::new(),
+                };
+                block.push(Stmt::If {
+                    cond,
+                    then,
+                    otherwise,
+                });
+            }
+            62..=69 if nested => {
+                let var = self.fresh("i");
+                let count = self.loop_count();
+                self.contexts.push(Context::For);
+                self.scopes.push(vec![self.read_only_int(var.clone())]);
+                let body = self.block(depth + 1);
+                self.scopes.pop();
+                self.contexts.pop();
+                block.push(Stmt::For { var, count, body });
+            }
+            70..=75 if nested => {
+                let var = self.fresh("i");
+                let count = self.loop_count();
+                self.contexts.push(Context::While);
+                let body = self.block(depth + 1);
+                self.contexts.pop();
+                // The counter is declared before the loop, so it's visible after it.
+                let counter = self.read_only_int(var.clone());
+                self.scopes.last_mut().unwrap().push(counter);
+                block.push(Stmt::While { var, count, body });
+            }
+            76..=81 if nested => {
+                let value = Expr::Binary {
+                    op: BinOp::BitAnd,
+                    ty: Ty::Int,
+                    safe: false,
+                    lhs: Box::new(self.expr(Ty::Int, 1)),
+                    rhs: Box::new(Expr::Literal(Ty::Int, 7)),
+                };
+                let mut values: Vec<Option<i64>> = (0..8).map(Some).collect();
+                values.push(None);
+                let mut cases = Vec::new();
+                self.contexts.push(Context::Switch);
+                for _ in 0..self.rng.range(1..=4) {
+                    let value = values.remove(self.rng.below(values.len()));
+                    let body = match self.rng.chance(80) {
+                        true => self.block(depth + 1),
+                        false => Vec::new(),
+                    };
+                    let is_break = self.rng.chance(70);
+                    cases.push(Case {
+                        value,
+                        body,
+                        is_break,
+                    });
+                }
+                self.contexts.pop();
+                block.push(Stmt::Switch { value, cases });
+            }
+            82..=89 => match self.lvalue(Some(Ty::Unsigned), true) {
+                Some(lvalue) => block.push(Stmt::Increment {
+                    lvalue,
+                    decrement: self.rng.chance(50),
+                }),
+                None => self.assign(block),
+            },
+            90..=95 if !self.in_function => {
+                let pointers = self.vars(|var| matches!(var.kind, VarKind::Pointer(_)));
+                if pointers.is_empty() {
+                    self.assign(block);
+                } else {
+                    let pointer = self.rng.choose(&pointers).clone();
+                    let VarKind::Pointer(ty) = pointer.kind else {
+                        unreachable!()
+                    };
+                    let target = self.pointer_target(ty, pointer.depth);
+                    block.push(Stmt::PointerAssign {
+                        pointer: pointer.name,
+                        target,
+                    });
+                }
+            }
+            96..=99 if !self.contexts.is_empty() => {
+                let innermost_loop = self.contexts.iter().rev().find(|c| **c != Context::Switch);
+                // A `continue` would skip the increment of the counter of a `while` loop.
+                if innermost_loop == Some(&Context::For) && self.rng.chance(50) {
+                    block.push(Stmt::Continue);
+                } else {
+                    block.push(Stmt::Break);
+                }
+                return false;
+            }
+            _ => self.assign(block),
+        }
+        true
+    }
+
+    fn assign(&mut self, block: &mut Vec<Stmt>) {
+        let ty = self.ty();
+        match self.lvalue(Some(ty), true) {
+            Some(lhs) => {
+                let rhs = self.expr(ty, 0);
+                block.push(Stmt::Assign { lhs, rhs });
+            }
+            None => self.decl(block),
+        }
+    }
+
+    fn decl(&mut self, block: &mut Vec<Stmt>) {
+        let ty = self.ty();
+        let name = self.fresh("l");
+        let depth = self.scopes.len();
+        let kind = match self.rng.below(10) {
+            0..=1 => {
+                let len = self.array_len();
+                block.push(Stmt::DeclArray {
+                    name: name.clone(),
+                    ty,
+                    len,
+                });
+                for i in 0..len {
+                    let rhs = self.expr(ty, 1);
+                    block.push(Stmt::Assign {
+                        lhs: LValue::Index {
+                            array: name.clone(),
+                            len,
+                            index: Box::new(Expr::Literal(Ty::Int, i as i64)),
+                        },
+                        rhs,
+                    });
+                }
+                VarKind::Array(ty, len)
+            }
+            // A pointer in a function could be used to modify a global.
+            2 if !self.in_function => {
+                let target = self.pointer_target(ty, depth);
+                block.push(Stmt::DeclPointer {
+                    name: name.clone(),
+                    ty,
+                    target,
+                });
+                VarKind::Pointer(ty)
+            }
+            _ => {
+                let init = self.expr(ty, 0);
+                block.push(Stmt::DeclScalar {
+                    name: name.clone(),
+                    ty,
+                    init,
+                });
+                VarKind::Scalar(ty)
+            }
+        };
+        self.scopes.last_mut().unwrap().push(Var {
+            name,
+            kind,
+            writable: true,
+            depth,
+        });
+    }
+
+    fn expr(&mut self, ty: Ty, depth: usize) -> Expr {
+        if depth >= self.opts.max_expr_depth || self.rng.chance(25) {
+            return self.leaf(ty, depth);
+        }
+        let choice = self.rng.below(100);
+        if choice < 40 {
+            return self.binary(ty, depth);
+        }
+        if choice < 55 && ty == Ty::Int {
+            let op = *self.rng.choose(&BinOp::ALL[10..]);
+            let operand_ty = self.ty();
+            return Expr::Binary {
+                op,
+                ty: operand_ty,
+                safe: false,
+                lhs: Box::new(self.expr(operand_ty, depth + 1)),
+                rhs: Box::new(self.expr(operand_ty, depth + 1)),
+            };
+        }
+        if choice < 65 {
+            let op = *self
+                .rng
+                .choose(&[UnOp::Neg, UnOp::Plus, UnOp::BitNot, UnOp::LogNot]);
+            let operand_ty = if op == UnOp::LogNot { self.ty() } else { ty };
+            let operand = Box::new(self.expr(operand_ty, depth + 1));
+            return match op {
+                UnOp::LogNot if ty == Ty::Unsigned => {
+                    let not = Expr::Unary {
+                        op,
+                        ty: operand_ty,
+                        operand,
+                    };
+                    Expr::Cast(Ty::Unsigned, Box::new(not))
+                }
+                _ => Expr::Unary {
+                    op,
+                    ty: operand_ty,
+                    operand,
+                },
+            };
+        }
+        if choice < 75 {
+            let from = match ty {
+                Ty::Int => Ty::Unsigned,
+                Ty::Unsigned => Ty::Int,
+            };
+            return Expr::Cast(ty, Box::new(self.expr(from, depth + 1)));
+        }
+        if choice < 83 {
+            return Expr::Cond {
+                cond: Box::new(self.expr(Ty::Int, depth + 1)),
+                then: Box::new(self.expr(ty, depth + 1)),
+                otherwise: Box::new(self.expr(ty, depth + 1)),
+            };
+        }
+        if choice < 93 && !self.functions.is_empty() {
+            let (name, ret, params) = self.rng.choose(&self.functions).clone();
+            let args = params
+                .into_iter()
+                .map(|param| self.expr(param, depth + 1))
+                .collect();
+            let call = Expr::Call(name, args);
+            return match ret == ty {
+                true => call,
+                false => Expr::Cast(ty, Box::new(call)),
+            };
+        }
+        let pointers = self.vars(|var| matches!(var.kind, VarKind::Pointer(_)));
+        if ty == Ty::Int && !pointers.is_empty() {
+            let lhs = self.rng.choose(&pointers).clone();
+            let same_ty: Vec<_> = pointers.iter().filter(|var| var.kind == lhs.kind).collect();
+            let rhs = self.rng.choose(&same_ty).name.clone();
+            return Expr::PointerCompare {
+                lhs: lhs.name,
+                rhs,
+                negate: self.rng.chance(50),
+            };
+        }
+        self.binary(ty, depth)
+    }
+
+    fn binary(&mut self, ty: Ty, depth: usize) -> Expr {
+        Expr::Binary {
+            op: *self.rng.choose(&BinOp::ALL[..10]),
+            ty,
+            safe: self.rng.chance(30),
+            lhs: Box::new(self.expr(ty, depth + 1)),
+            rhs: Box::new(self.expr(ty, depth + 1)),
+        }
+    }
+
+    fn leaf(&mut self, ty: Ty, depth: usize) -> Expr {
+        if self.rng.chance(30) {
+            return Expr::Literal(ty, self.literal_value(ty));
+        }
+        match self.lvalue_with_depth(Some(ty), false, depth) {
+            Some(lvalue) => Expr::Read(lvalue),
+            None => Expr::Literal(ty, self.literal_value(ty)),
+        }
+    }
+
+    /// Returns a random lvalue of type `ty` (or any type), or `None` if there is none.
+    fn lvalue(&mut self, ty: Option<Ty>, writable: bool) -> Option<LValue> {
+        self.lvalue_with_depth(ty, writable, 0)
+    }
+
+    fn lvalue_with_depth(
+        &mut self,
+        ty: Option<Ty>,
+        writable: bool,
+        depth: usize,
+    ) -> Option<LValue> {
+        let in_function = self.in_function;
+        let candidates = self.vars(|var| {
+            let var_ty = match var.kind {
+                VarKind::Scalar(ty) | VarKind::Array(ty, _) | VarKind::Pointer(ty) => ty,
+            };
+            let is_writable = var.writable
+                && !(in_function && (var.depth == 0 || matches!(var.kind, VarKind::Pointer(_))));
+            ty.is_none_or(|ty| ty == var_ty) && (!writable || is_writable)
+        });
+        if candidates.is_empty() {
+            return None;
+        }
+        let var = self.rng.choose(&candidates).clone();
+        Some(match var.kind {
+            VarKind::Scalar(_) => LValue::Var(var.name),
+            VarKind::Array(_, len) => {
+                // Indices are kept small, they're reduced modulo the length anyway.
+                let min_depth = self.opts.max_expr_depth.saturating_sub(2);
+                let index = self.expr(Ty::Int, depth.max(min_depth));
+                LValue::Index {
+                    array: var.name,
+                    len,
+                    index: Box::new(index),
+                }
+            }
+            VarKind::Pointer(_) => LValue::Deref(var.name),
+        })
+    }
+
+    /// Returns the address of a scalar or array element of type `ty`, with a depth of at most
+    /// `max_depth`. There is always one, because every program has a global of each type.
+    fn pointer_target(&mut self, ty: Ty, max_depth: usize) -> LValue {
+        let candidates = self.vars(|var| {
+            var.writable
+                && var.depth <= max_depth
+                && matches!(var.kind, VarKind::Scalar(t) | VarKind::Array(t, _) if t == ty)
+        });
+        let var = self.rng.choose(&candidates).clone();
+        match var.kind {
+            VarKind::Array(_, len) => LValue::Index {
+                array: var.name,
+                len,
+                index: Box::new(Expr::Literal(Ty::Int, self.rng.below(len) as i64)),
+            },
+            _ => LValue::Var(var.name),
+        }
+    }
+
+    /// The visible variables for which `filter` returns `true`.
+    fn vars(&self, filter: impl Fn(&Var) -> bool) -> Vec<Var> {
+        self.globals
+            .iter()
+            .chain(self.scopes.iter().flatten())
+            .filter(|var| filter(var))
+            .cloned()
+            .collect()
+    }
+
+    fn read_only_int(&self, name: String) -> Var {
+        Var {
+            name,
+            kind: VarKind::Scalar(Ty::Int),
+            writable: false,
+            depth: self.scopes.len(),
+        }
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}_{}", self.next_id)
+    }
+
+    fn ty(&mut self) -> Ty {
+        match self.rng.chance(60) {
+            true => Ty::Int,
+            false => Ty::Unsigned,
+        }
+    }
+
+    fn array_len(&mut self) -> usize {
+        *self.rng.choose(&[1, 2, 3, 4, 5, 8, 10])
+    }
+
+    fn loop_count(&mut self) -> u32 {
+        self.rng.range(0..=self.opts.max_loop_count as usize) as u32
+    }
+
+    fn literal_value(&mut self, ty: Ty) -> i64 {
+        const INTERESTING: [i64; 16] = [
+            0, 1, 2, 3, 7, 8, 15, 16, 31, 32, 255, 256, 32767, 65535, 65536, 2147483647,
+        ];
+        let value = match self.rng.below(3) {
+            0 => self.rng.below(10) as i64,
+            1 => *self.rng.choose(&INTERESTING),
+            _ => (self.rng.next_u64() & 0x7fff_ffff) as i64,
+        };
+        match ty {
+            Ty::Int if self.rng.chance(30) => -value,
+            _ => value,
+        }
+    }
+}
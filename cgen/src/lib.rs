@@ -0,0 +1,34 @@
+//! A generator of random C programs for differential testing of the compiler, in the spirit of
+//! Csmith.
+//!
+//! The programs only use the subset of C that the compiler supports: `int` and `unsigned int`
+//! scalars, arrays and pointers, the operators of `ir::BinaryOp` and the unary, logical,
+//! comparison and conditional operators, `if`, `for`, `while` and `switch` statements, globals and
+//! (side effect free) functions. At the end, `main` prints a checksum of all globals.
+//!
+//! Every program is well-defined, so all targets have to print the same checksum:
+//!
+//!  - Operations that could overflow, divide by zero or shift by an invalid amount either mask
+//!    their operands so they can't, or call a `safe_*` helper that checks the operands first.
+//!  - Array indices are reduced modulo the length of the array, and pointers only ever point to
+//!    scalars or array elements that outlive them.
+//!  - Expressions have no side effects (functions don't modify globals), so the unspecified order
+//!    of evaluation doesn't matter. Every loop has a constant trip count.
+//!
+//! A [`Program`] is a tree rather than text, so a failing program can be shrunk with [`reduce`],
+//! which tries to remove statements, globals and functions and to simplify expressions.
+//! Reductions may make a program ill-formed or undefined, so the predicate must reject those,
+//! e.g. by running it in the reference interpreter first.
+
+mod ast;
+mod generate;
+mod reduce;
+mod rng;
+
+pub use ast::*;
+pub use generate::{generate, GenerateOpts};
+pub use reduce::reduce;
+pub use rng::Rng;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,386 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use wait_timeout::ChildExt;
+
+use comp_lib::{
+    compile::{self, CompileOptsBuilder, Target},
+    interpreter::Interpreter,
+};
+
+use std::{
+    io::{Read, Write},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    process::{Command as Process, Stdio},
+    time::{Duration, SystemTime},
+};
+
+/// The step limit of the reference interpreter, generated programs need far less.
+const INTERPRETER_STEPS: u64 = 10_000_000;
+/// The step limit of the MIPS simulator, which executes a lot more (and smaller) steps.
+const MIPS_STEPS: u64 = 200_000_000;
+const LLI_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the program generated from a seed
+    Generate { seed: u64 },
+    /// Generate programs and compare their output for both targets, with and without const
+    /// folding. Programs that give a wrong output are reduced and saved.
+    Test(TestArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct TestArgs {
+    /// The seed of the first program, the next programs use the following seeds. Defaults to the
+    /// current time.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// The number of programs to test
+    #[arg(short = 'n', long, default_value_t = 100)]
+    count: u64,
+
+    /// The directory to save the failing programs in
+    #[arg(short = 'o', long, default_value = "cgen-failures")]
+    out_dir: PathBuf,
+
+    /// Save the failing programs as they were generated, without reducing them first
+    #[arg(long)]
+    no_reduce: bool,
+
+    /// The lli binary used to run the LLVM IR. Defaults to $LLI_BIN, or `lli`.
+    #[arg(long)]
+    lli: Option<PathBuf>,
+}
+
+/// A way to compile and run a program whose output is compared with the reference interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Config {
+    target: Target,
+    const_fold: bool,
+}
+
+const CONFIGS: [Config; 4] = [
+    Config {
+        target: Target::X86_64,
+        const_fold: true,
+    },
+    Config {
+        target: Target::X86_64,
+        const_fold: false,
+    },
+    Config {
+        target: Target::Mips,
+        const_fold: true,
+    },
+    Config {
+        target: Target::Mips,
+        const_fold: false,
+    },
+];
+
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target = match self.target {
+            Target::X86_64 => "x86-64 (lli)",
+            Target::Mips => "mips (simulator)",
+            Target::RiscV32 | Target::Wasm32 => unreachable!(),
+        };
+        match self.const_fold {
+            true => f.write_str(target),
+            false => write!(f, "{target} --skip const-fold"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureKind {
+    CompileError,
+    Panic,
+    RuntimeError,
+    WrongOutput,
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FailureKind::CompileError => "compile error",
+            FailureKind::Panic => "compiler panic",
+            FailureKind::RuntimeError => "runtime error",
+            FailureKind::WrongOutput => "wrong output",
+        })
+    }
+}
+
+struct Failure {
+    kind: FailureKind,
+    message: String,
+}
+
+impl Failure {
+    fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    match Args::parse().command {
+        Command::Generate { seed } => {
+            print!("{}", cgen::generate(seed, &cgen::GenerateOpts::default()));
+            Ok(())
+        }
+        Command::Test(args) => test(&args),
+    }
+}
+
+fn test(args: &TestArgs) -> Result<()> {
+    let first_seed = match args.seed {
+        Some(seed) => seed,
+        None => SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("the system time is before the unix epoch")?
+            .as_secs(),
+    };
+    let lli = args
+        .lli
+        .clone()
+        .or_else(|| std::env::var_os("LLI_BIN").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("lli"));
+    let runner = Runner { lli };
+
+    // Panics of the compiler are reported as failures, their messages would only clutter the
+    // output.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let opts = cgen::GenerateOpts::default();
+    let mut failed = 0;
+    let mut invalid = 0;
+    for seed in first_seed..first_seed + args.count {
+        let program = cgen::generate(seed, &opts);
+        let Some(expected) = runner.reference(&program.to_string()) else {
+            // This is a bug in the generator (or the interpreter) rather than in the compiler.
+            eprintln!("seed {seed}: the generated program isn't well-defined");
+            invalid += 1;
+            continue;
+        };
+
+        let failures = runner.failures(&program.to_string(), &expected);
+        if failures.is_empty() {
+            continue;
+        }
+        failed += 1;
+        for (config, failure) in &failures {
+            match failure.kind {
+                FailureKind::WrongOutput => eprintln!("seed {seed}: {config}: wrong output"),
+                kind => eprintln!("seed {seed}: {config}: {kind}: {}", failure.message),
+            }
+        }
+
+        let signature: Vec<_> = failures.iter().map(|(c, f)| (*c, f.kind)).collect();
+        let program = match args.no_reduce {
+            true => program,
+            false => cgen::reduce(&program, |candidate| {
+                runner.reproduces(&candidate.to_string(), &signature)
+            }),
+        };
+        let path = save(&args.out_dir, seed, &program, &runner, &signature)?;
+        eprintln!("seed {seed}: saved to {}", path.display());
+    }
+
+    eprintln!(
+        "tested {} programs: {failed} failed, {invalid} weren't well-defined",
+        args.count
+    );
+    if failed > 0 || invalid > 0 {
+        bail!("some programs failed");
+    }
+    Ok(())
+}
+
+struct Runner {
+    lli: PathBuf,
+}
+
+impl Runner {
+    /// Returns the output of the program in the reference interpreter, or `None` if the program
+    /// doesn't compile or isn't well-defined.
+    fn reference(&self, source: &str) -> Option<String> {
+        let opts = CompileOptsBuilder::new()
+            .const_fold(false)
+            .for_assignments()
+            .build()
+            .unwrap();
+        let root = catch_panic(|| compile::compile_to_ir(source, &opts))
+            .ok()?
+            .into_value()?;
+
+        let mut output = Vec::new();
+        let code = Interpreter::new(&root, Target::X86_64, std::io::empty(), &mut output)
+            .with_step_limit(INTERPRETER_STEPS)
+            .run()
+            .ok()?;
+        (code == 0).then(|| String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Runs the program with every configuration, and returns the ones that don't print
+    /// `expected`.
+    fn failures(&self, source: &str, expected: &str) -> Vec<(Config, Failure)> {
+        CONFIGS
+            .iter()
+            .filter_map(|&config| {
+                let failure = match self.run(config, source) {
+                    Ok(output) if output == expected => return None,
+                    Ok(output) => Failure::new(FailureKind::WrongOutput, output),
+                    Err(failure) => failure,
+                };
+                Some((config, failure))
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the program is well-defined and fails the same way with the same
+    /// configurations as the signature.
+    fn reproduces(&self, source: &str, signature: &[(Config, FailureKind)]) -> bool {
+        let Some(expected) = self.reference(source) else {
+            return false;
+        };
+        signature
+            .iter()
+            .all(|&(config, kind)| match self.run(config, source) {
+                Ok(output) => kind == FailureKind::WrongOutput && output != expected,
+                Err(failure) => failure.kind == kind,
+            })
+    }
+
+    /// Compiles and runs the program, and returns what it printed.
+    fn run(&self, config: Config, source: &str) -> Result<String, Failure> {
+        let opts = CompileOptsBuilder::new()
+            .target(config.target)
+            .const_fold(config.const_fold)
+            .for_assignments()
+            .build()
+            .unwrap();
+        let output = catch_panic(|| compile::compile(source, "cgen.c", &opts))
+            .map_err(|message| Failure::new(FailureKind::Panic, message))?
+            .into_value()
+            .ok_or_else(|| Failure::new(FailureKind::CompileError, "the program didn't compile"))?;
+
+        match config.target {
+            Target::X86_64 => self.run_lli(output),
+            Target::Mips => run_mips(&output),
+            Target::RiscV32 | Target::Wasm32 => unreachable!(),
+        }
+    }
+
+    fn run_lli(&self, ir: Vec<u8>) -> Result<String, Failure> {
+        let runtime_error = |message: String| Failure::new(FailureKind::RuntimeError, message);
+
+        let mut lli = Process::new(&self.lli)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| runtime_error(format!("couldn't start lli: {err}")))?;
+        let mut stdin = lli.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || stdin.write_all(&ir));
+        let mut stdout = lli.stdout.take().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let status = match lli.wait_timeout(LLI_TIMEOUT) {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                let _ = lli.kill();
+                let _ = lli.wait();
+                return Err(runtime_error("lli timed out".to_string()));
+            }
+            Err(err) => return Err(runtime_error(format!("couldn't wait for lli: {err}"))),
+        };
+        let _ = writer.join();
+        let output = reader
+            .join()
+            .unwrap()
+            .map_err(|err| runtime_error(format!("couldn't read the output of lli: {err}")))?;
+
+        if !status.success() {
+            return Err(runtime_error(format!("lli exited with {status}")));
+        }
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+}
+
+fn run_mips(asm: &[u8]) -> Result<String, Failure> {
+    let runtime_error = |message: String| Failure::new(FailureKind::RuntimeError, message);
+
+    let program = mips_sim::Program::assemble(&String::from_utf8_lossy(asm))
+        .map_err(|err| runtime_error(format!("couldn't assemble: {err}")))?;
+    let mut output = Vec::new();
+    let exit = mips_sim::Machine::new(&program, std::io::empty(), &mut output)
+        .with_step_limit(MIPS_STEPS)
+        .run()
+        .map_err(|err| runtime_error(err.to_string()))?;
+    if exit.code != 0 {
+        return Err(runtime_error(format!("exited with code {}", exit.code)));
+    }
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Runs `f`, and returns the message of its panic if it panics.
+fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_string()
+        }
+    })
+}
+
+/// Saves the program in the format of the test files, so it can be added as a regression test
+/// once it's fixed.
+fn save(
+    dir: &Path,
+    seed: u64,
+    program: &cgen::Program,
+    runner: &Runner,
+    signature: &[(Config, FailureKind)],
+) -> Result<PathBuf> {
+    let source = program.to_string();
+    // The reduced program is always well-defined, that's part of what makes it interesting.
+    let expected = runner.reference(&source).unwrap_or_default();
+
+    let mut file = String::from("//output:\n");
+    for line in expected.lines() {
+        file.push_str(&format!("//{line}\n"));
+    }
+    file.push_str(&format!(
+        "\n// Generated by `cgen generate {seed}`, fails with:\n"
+    ));
+    for (config, kind) in signature {
+        file.push_str(&format!("//  - {config}: {kind}\n"));
+    }
+    file.push('\n');
+    file.push_str(&source);
+
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    let path = dir.join(format!("seed-{seed}.c"));
+    std::fs::write(&path, file)
+        .with_context(|| format!("Failed to write to {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,278 @@
+use crate::ast::*;
+
+/// An edit of a program at one of its sites, e.g. the removal of the `site`-th statement. Returns
+/// `false` if the program has no such site.
+type Pass = fn(&mut Program, usize) -> bool;
+
+/// The passes in the order they're tried, the ones that remove the most code come first.
+const PASSES: [Pass; 7] = [
+    remove_function,
+    remove_global,
+    remove_stmt,
+    remove_case,
+    inline_stmt,
+    reduce_loop_count,
+    simplify_expr,
+];
+
+/// Shrinks `program` as long as `is_interesting` keeps returning `true` for the smaller program,
+/// e.g. because it still shows a miscompilation, and returns the smallest interesting program it
+/// finds.
+///
+/// Every edit makes the program smaller, so this always terminates. The edits don't keep the
+/// program well-formed or well-defined: a removed declaration can still be used, or a removed
+/// `safe_*` check can make an expression overflow, so `is_interesting` has to reject those.
+pub fn reduce(program: &Program, mut is_interesting: impl FnMut(&Program) -> bool) -> Program {
+    let mut best = program.clone();
+    loop {
+        let mut progress = false;
+        for pass in PASSES {
+            let mut site = 0;
+            loop {
+                let mut candidate = best.clone();
+                if !pass(&mut candidate, site) {
+                    break;
+                }
+                if candidate != best && is_interesting(&candidate) {
+                    // The next site now has the same index.
+                    best = candidate;
+                    progress = true;
+                } else {
+                    site += 1;
+                }
+            }
+        }
+        if !progress {
+            return best;
+        }
+    }
+}
+
+/// Counts down to the site that is edited.
+struct Cursor {
+    site: usize,
+    done: bool,
+}
+
+impl Cursor {
+    fn new(site: usize) -> Self {
+        Self { site, done: false }
+    }
+
+    /// Returns `true` if the current site is the one to edit.
+    fn hit(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        if self.site == 0 {
+            self.done = true;
+        } else {
+            self.site -= 1;
+        }
+        self.done
+    }
+}
+
+fn remove_function(program: &mut Program, site: usize) -> bool {
+    let exists = site < program.functions.len();
+    if exists {
+        program.functions.remove(site);
+    }
+    exists
+}
+
+fn remove_global(program: &mut Program, site: usize) -> bool {
+    let exists = site < program.globals.len();
+    if exists {
+        program.globals.remove(site);
+    }
+    exists
+}
+
+fn remove_stmt(program: &mut Program, site: usize) -> bool {
+    let mut cursor = Cursor::new(site);
+    for_each_block_mut(program, &mut |block| {
+        if let Some(i) = (0..block.len()).find(|_| cursor.hit()) {
+            block.remove(i);
+        }
+    });
+    cursor.done
+}
+
+fn remove_case(program: &mut Program, site: usize) -> bool {
+    let mut cursor = Cursor::new(site);
+    for_each_block_mut(program, &mut |block| {
+        for stmt in block {
+            if let Stmt::Switch { cases, .. } = stmt {
+                if let Some(i) = (0..cases.len()).find(|_| cursor.hit()) {
+                    cases.remove(i);
+                }
+            }
+        }
+    });
+    cursor.done
+}
+
+/// Replaces an `if`, loop or `switch` statement by one of its bodies.
+fn inline_stmt(program: &mut Program, site: usize) -> bool {
+    let mut cursor = Cursor::new(site);
+    for_each_block_mut(program, &mut |block| {
+        for i in 0..block.len() {
+            let mut bodies = match &mut block[i] {
+                Stmt::If {
+                    then, otherwise, ..
+                } => vec![then, otherwise],
+                Stmt::For { body, .. } | Stmt::While { body, .. } => vec![body],
+                Stmt::Switch { cases, .. } => cases.iter_mut().map(|case| &mut case.body).collect(),
+                _ => Vec::new(),
+            };
+            if let Some(j) = (0..bodies.len()).find(|_| cursor.hit()) {
+                let body = std::mem::take(bodies[j]);
+                block.splice(i..=i, body);
+                return;
+            }
+        }
+    });
+    cursor.done
+}
+
+fn reduce_loop_count(program: &mut Program, site: usize) -> bool {
+    let mut cursor = Cursor::new(site);
+    for_each_block_mut(program, &mut |block| {
+        for stmt in block {
+            if let Stmt::For { count, .. } | Stmt::While { count, .. } = stmt {
+                if *count > 1 && cursor.hit() {
+                    *count = 1;
+                }
+            }
+        }
+    });
+    cursor.done
+}
+
+/// Replaces an expression by one of its operands, or by `0`.
+fn simplify_expr(program: &mut Program, site: usize) -> bool {
+    let mut cursor = Cursor::new(site);
+    for_each_expr_mut(program, &mut |expr| {
+        let mut replacements: Vec<Expr> = match expr {
+            Expr::Read(LValue::Index { index, .. }) => vec![(**index).clone()],
+            Expr::Binary { lhs, rhs, .. } => vec![(**lhs).clone(), (**rhs).clone()],
+            Expr::Unary { operand, .. } | Expr::Cast(_, operand) => vec![(**operand).clone()],
+            Expr::Cond {
+                cond,
+                then,
+                otherwise,
+            } => vec![(**cond).clone(), (**then).clone(), (**otherwise).clone()],
+            Expr::Call(_, args) => args.clone(),
+            Expr::Read(_) | Expr::Literal(..) | Expr::PointerCompare { .. } => Vec::new(),
+        };
+        if !matches!(expr, Expr::Literal(..)) {
+            replacements.push(Expr::Literal(Ty::Int, 0));
+        }
+        if let Some(i) = (0..replacements.len()).find(|_| cursor.hit()) {
+            *expr = replacements.swap_remove(i);
+        }
+    });
+    cursor.done
+}
+
+/// Calls `f` for the body of every function and of `main`, and for every nested block, before the
+/// blocks nested in it.
+fn for_each_block_mut(program: &mut Program, f: &mut impl FnMut(&mut Vec<Stmt>)) {
+    for function in &mut program.functions {
+        walk_block(&mut function.body, f);
+    }
+    walk_block(&mut program.main, f);
+}
+
+fn walk_block(block: &mut Vec<Stmt>, f: &mut impl FnMut(&mut Vec<Stmt>)) {
+    f(block);
+    for stmt in block {
+        match stmt {
+            Stmt::If {
+                then, otherwise, ..
+            } => {
+                walk_block(then, f);
+                walk_block(otherwise, f);
+            }
+            Stmt::For { body, .. } | Stmt::While { body, .. } => walk_block(body, f),
+            Stmt::Switch { cases, .. } => {
+                for case in cases {
+                    walk_block(&mut case.body, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Calls `f` for every expression, before the expressions nested in it.
+fn for_each_expr_mut(program: &mut Program, f: &mut impl FnMut(&mut Expr)) {
+    for function in &mut program.functions {
+        walk_block(&mut function.body, &mut |block| {
+            for stmt in block {
+                walk_stmt_exprs(stmt, f);
+            }
+        });
+        walk_expr(&mut function.ret_value, f);
+    }
+    walk_block(&mut program.main, &mut |block| {
+        for stmt in block {
+            walk_stmt_exprs(stmt, f);
+        }
+    });
+}
+
+/// Walks the expressions of the statement itself, not those of nested statements.
+fn walk_stmt_exprs(stmt: &mut Stmt, f: &mut impl FnMut(&mut Expr)) {
+    match stmt {
+        Stmt::DeclScalar { init: expr, .. }
+        | Stmt::If { cond: expr, .. }
+        | Stmt::Switch { value: expr, .. } => walk_expr(expr, f),
+        Stmt::Assign { lhs, rhs } => {
+            walk_lvalue(lhs, f);
+            walk_expr(rhs, f);
+        }
+        Stmt::DeclPointer { target, .. }
+        | Stmt::PointerAssign { target, .. }
+        | Stmt::Increment { lvalue: target, .. } => walk_lvalue(target, f),
+        Stmt::DeclArray { .. }
+        | Stmt::For { .. }
+        | Stmt::While { .. }
+        | Stmt::Break
+        | Stmt::Continue => {}
+    }
+}
+
+fn walk_lvalue(lvalue: &mut LValue, f: &mut impl FnMut(&mut Expr)) {
+    if let LValue::Index { index, .. } = lvalue {
+        walk_expr(index, f);
+    }
+}
+
+fn walk_expr(expr: &mut Expr, f: &mut impl FnMut(&mut Expr)) {
+    f(expr);
+    match expr {
+        Expr::Read(lvalue) => walk_lvalue(lvalue, f),
+        Expr::Binary { lhs, rhs, .. } => {
+            walk_expr(lhs, f);
+            walk_expr(rhs, f);
+        }
+        Expr::Unary { operand, .. } | Expr::Cast(_, operand) => walk_expr(operand, f),
+        Expr::Cond {
+            cond,
+            then,
+            otherwise,
+        } => {
+            walk_expr(cond, f);
+            walk_expr(then, f);
+            walk_expr(otherwise, f);
+        }
+        Expr::Call(_, args) => {
+            for arg in args {
+                walk_expr(arg, f);
+            }
+        }
+        Expr::Literal(..) | Expr::PointerCompare { .. } => {}
+    }
+}
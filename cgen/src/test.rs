@@ -0,0 +1,66 @@
+use super::*;
+
+fn generate_default(seed: u64) -> Program {
+    generate(seed, &GenerateOpts::default())
+}
+
+#[test]
+fn generation_is_deterministic() {
+    for seed in 0..20 {
+        assert_eq!(generate_default(seed), generate_default(seed));
+    }
+    assert_ne!(
+        generate_default(1).to_string(),
+        generate_default(2).to_string()
+    );
+}
+
+#[test]
+fn programs_cover_the_subset() {
+    let sources: String = (0..50)
+        .map(|seed| generate_default(seed).to_string())
+        .collect();
+    for construct in [
+        "switch (", "case ", "for (", "while (", "if (", "? ", "[", "(*", "&", "safe_", "<<", ">>",
+        "%", "!=", "&&", "||", "~",
+    ] {
+        assert!(
+            sources.contains(construct),
+            "no program contains `{construct}`"
+        );
+    }
+}
+
+#[test]
+fn programs_print_a_checksum() {
+    let source = generate_default(7).to_string();
+    assert!(source.starts_with("#include <stdio.h>\n"));
+    assert!(source.contains("printf(\"checksum = %d %d\\n\""));
+}
+
+#[test]
+fn reduce_keeps_an_uninteresting_program() {
+    let program = generate_default(3);
+    assert_eq!(reduce(&program, |_| false), program);
+}
+
+#[test]
+fn reduce_shrinks_to_the_interesting_part() {
+    let has_switch = |program: &Program| program.to_string().contains("switch (");
+    let program = (0..).map(generate_default).find(has_switch).unwrap();
+
+    let reduced = reduce(&program, has_switch);
+    assert!(reduced.globals.is_empty());
+    // The switch may be in a function that can't be removed without removing the switch.
+    let blocks: Vec<_> = (reduced.functions.iter().map(|f| f.body.as_slice()))
+        .chain([reduced.main.as_slice()])
+        .filter(|block| !block.is_empty())
+        .collect();
+    assert!(
+        matches!(
+            blocks.as_slice(),
+            [[Stmt::Switch { value: Expr::Literal(_, 0), cases }]] if cases.is_empty()
+        ),
+        "{reduced}"
+    );
+}
@@ -1,9 +1,13 @@
 use crate::util::PathOrStd;
 
-use comp_lib::compile::{self, CompileOpts, CompileOptsBuilder, CompileOptsErr};
+use comp_lib::{
+    compile::{self, CompileOpts, CompileOptsBuilder, CompileOptsErr},
+    diagnostic::Code,
+    passes::format,
+};
 
 use anyhow::{bail, Context};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use codespan_reporting::files::SimpleFile;
 
 use std::{fs::File, io::Read};
@@ -13,18 +17,26 @@ pub enum OutputFormat {
     AntlrTree,
     AstDot,
     AstRustDbg,
+    AstC,
     IrDot,
     IrRustDbg,
+    C,
     SymbolTableAscii,
     LlvmIr,
     MipsDbg,
     MipsAsm,
+    MipsObject,
+    X86Asm,
+    RiscvAsm,
+    Wat,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 pub enum Target {
     X86_64,
     Mips,
+    Riscv32,
+    Wasm32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -33,74 +45,245 @@ pub enum SkippablePasses {
     ControlFlowAnalysis,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiagnosticFormat {
+    /// Rendered with the source, for people
+    Human,
+    /// A versioned JSON document, see the README for the layout
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning dashboards
+    Sarif,
+}
+
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Args {
-    /// The input file, use `-` for std in.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The input files, use `-` for std in. Multiple inputs, or MIPS objects, are linked together.
     #[arg(default_value = "-")]
-    input_path: PathOrStd,
+    pub input_paths: Vec<PathOrStd>,
 
     /// The compile target. Defaults to x86-64
     #[arg(short = 't', long, value_name = "TARGET", value_enum)]
     target: Option<Target>,
 
-    /// The output format. Default based on the target, llvm-ir for X86_64 and mips-asm for MIPS.
+    /// The output format. Default based on the target, llvm-ir for X86_64, mips-asm for MIPS,
+    /// riscv-asm for RISC-V and wat for WebAssembly.
     #[arg(short = 'e', long, value_name = "FORMAT", value_enum)]
     emit: Option<OutputFormat>,
 
+    /// Compile to a MIPS object without linking. If the inputs are objects, they are merged into a
+    /// single object (e.g. a library archive).
+    #[arg(short = 'c', long, conflicts_with = "emit")]
+    compile_only: bool,
+
+    /// Use the standard O32 calling convention for MIPS, to link with code from other tools.
+    #[arg(long)]
+    o32: bool,
+
+    /// Trap when an array is indexed outside of its bounds, for MIPS and RISC-V.
+    #[arg(long)]
+    bounds_check: bool,
+
     /// Zero or more passes to skip
     #[arg(long = "skip", value_name = "PASS", value_enum)]
     skips: Vec<SkippablePasses>,
 
+    /// Apply the suggested fixes that don't change what the program does to the input files,
+    /// before compiling them.
+    #[arg(long)]
+    pub fix: bool,
+
     /// The output file, use `-` for std out.
     #[arg(short = 'o', long = "output", default_value = "-")]
     output_path: PathOrStd,
+
+    #[command(flatten)]
+    warnings: WarningArgs,
+
+    /// How diagnostics are written to std err.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value = "human")]
+    diagnostic_format: DiagnosticFormat,
+
+    /// Print a long-form explanation of a diagnostic code with an example, e.g. `#0011` or
+    /// `undeclared-ident`, instead of compiling.
+    #[arg(long, value_name = "CODE")]
+    pub explain: Option<Code>,
+}
+
+/// The flags that decide which diagnostics are reported, like the ones of gcc.
+#[derive(Debug, clap::Args)]
+struct WarningArgs {
+    /// Enable (`-W<name>`), disable (`-Wno-<name>`) or upgrade (`-Werror=<name>`) a diagnostic or
+    /// a group like `all`, `extra` or `conversion`. `-Werror` upgrades all warnings to errors.
+    #[arg(short = 'W', value_name = "WARNING")]
+    flags: Vec<String>,
+
+    /// Don't report warnings, unless they are upgraded to errors.
+    #[arg(short = 'w')]
+    inhibit: bool,
+
+    /// Stop reporting errors after this many errors per input, 0 means no limit.
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    max_errors: usize,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compile and link the inputs for MIPS, and run the program in the built-in simulator. The
+    /// program reads from std in and writes to std out, and its exit code is the exit code of comp.
+    Run(RunArgs),
+    /// Start a language server that communicates over std in and std out, for editor integration.
+    Lsp,
+    /// Format C sources. Files are rewritten in place, std in is formatted to std out.
+    Fmt(FmtArgs),
 }
 
-pub fn open_input_source(args: &Args) -> anyhow::Result<SimpleFile<String, String>> {
-    match &args.input_path {
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// The input files, use `-` for std in. Multiple inputs, or MIPS objects, are linked together.
+    #[arg(required = true)]
+    input_paths: Vec<PathOrStd>,
+
+    /// Use the standard O32 calling convention for MIPS.
+    #[arg(long)]
+    o32: bool,
+
+    /// Trap when an array is indexed outside of its bounds.
+    #[arg(long)]
+    bounds_check: bool,
+
+    /// Zero or more passes to skip
+    #[arg(long = "skip", value_name = "PASS", value_enum)]
+    skips: Vec<SkippablePasses>,
+
+    /// Stop the program with an error after this many instructions.
+    #[arg(long, value_name = "STEPS")]
+    pub max_steps: Option<u64>,
+
+    #[command(flatten)]
+    warnings: WarningArgs,
+
+    /// How diagnostics are written to std err.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value = "human")]
+    diagnostic_format: DiagnosticFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BraceStyle {
+    /// Opening braces on the same line, `} else {`
+    Attach,
+    /// Opening braces on their own line
+    Allman,
+    /// Opening braces of functions on their own line, the others on the same line
+    Linux,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FmtArgs {
+    /// The files to format, use `-` for std in.
+    #[arg(default_value = "-")]
+    pub input_paths: Vec<PathOrStd>,
+
+    /// Don't write anything, but exit with an error if any input isn't formatted.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Where opening braces are placed.
+    #[arg(long, value_name = "STYLE", value_enum, default_value = "attach")]
+    brace_style: BraceStyle,
+
+    /// The number of spaces per indentation level.
+    #[arg(long, value_name = "WIDTH", default_value_t = 4)]
+    indent_width: usize,
+
+    /// Lines longer than this are broken where possible.
+    #[arg(long, value_name = "WIDTH", default_value_t = 100)]
+    max_width: usize,
+
+    /// How diagnostics are written to std err.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value = "human")]
+    diagnostic_format: DiagnosticFormat,
+}
+
+pub enum Input {
+    Source(SimpleFile<String, String>),
+    Object(mips_ir::Root),
+}
+
+pub fn open_inputs(args: &Args) -> anyhow::Result<Vec<Input>> {
+    args.input_paths.iter().map(open_input).collect()
+}
+
+pub fn open_run_inputs(args: &RunArgs) -> anyhow::Result<Vec<Input>> {
+    args.input_paths.iter().map(open_input).collect()
+}
+
+fn open_input(input_path: &PathOrStd) -> anyhow::Result<Input> {
+    let (name, bytes) = match input_path {
         PathOrStd::Path(path) => {
             if !path.exists() {
                 bail!("Input file `{}` doesn't exist", path.display());
             }
             let mut handle = File::open(path)
                 .with_context(|| format!("Failed to open input file `{}`", path.display()))?;
-            let mut s = String::new();
+            let mut bytes = Vec::new();
             handle
-                .read_to_string(&mut s)
+                .read_to_end(&mut bytes)
                 .with_context(|| format!("Failed to read from input file `{}`", path.display()))?;
 
-            Ok(SimpleFile::new(
+            (
                 path.file_name().unwrap().to_string_lossy().into_owned(),
-                s,
-            ))
+                bytes,
+            )
         }
         PathOrStd::StdStream => {
             let mut handle = std::io::stdin().lock();
-            let mut s = String::new();
+            let mut bytes = Vec::new();
             handle
-                .read_to_string(&mut s)
+                .read_to_end(&mut bytes)
                 .context("Failed to read from stdin")?;
 
-            Ok(SimpleFile::new("stdin stream".to_owned(), s))
+            ("stdin stream".to_owned(), bytes)
         }
+    };
+
+    if mips_ir::object::is_object(&bytes) {
+        let root = mips_ir::object::read_object(bytes.as_slice())
+            .with_context(|| format!("Failed to read object `{name}`"))?;
+        return Ok(Input::Object(root));
     }
+
+    let source = String::from_utf8(bytes)
+        .with_context(|| format!("Input `{name}` is neither valid UTF-8 nor an object"))?;
+    Ok(Input::Source(SimpleFile::new(name, source)))
 }
 
-pub fn extract_compile_opts(args: &Args) -> Result<CompileOpts, CompileOptsErr> {
+/// If `linking` is `true`, the target defaults to MIPS, since only MIPS objects can be linked.
+pub fn extract_compile_opts(args: &Args, linking: bool) -> Result<CompileOpts, CompileOptsErr> {
     let opts = CompileOptsBuilder::new();
 
-    let opts = if let Some(format) = args.emit {
+    let opts = if args.compile_only {
+        opts.output_format(compile::OutputFormat::MipsObject)
+    } else if let Some(format) = args.emit {
         let format = match format {
             OutputFormat::AntlrTree => compile::OutputFormat::AntlrTree,
             OutputFormat::AstDot => compile::OutputFormat::AstDot,
             OutputFormat::AstRustDbg => compile::OutputFormat::AstRustDbg,
+            OutputFormat::AstC => compile::OutputFormat::AstC,
             OutputFormat::IrDot => compile::OutputFormat::IrDot,
             OutputFormat::IrRustDbg => compile::OutputFormat::IrRustDbg,
+            OutputFormat::C => compile::OutputFormat::IrC,
             OutputFormat::SymbolTableAscii => compile::OutputFormat::SymbolTableAscii,
             OutputFormat::LlvmIr => compile::OutputFormat::LlvmIr,
             OutputFormat::MipsDbg => compile::OutputFormat::MipsDbg,
             OutputFormat::MipsAsm => compile::OutputFormat::MipsAsm,
+            OutputFormat::MipsObject => compile::OutputFormat::MipsObject,
+            OutputFormat::X86Asm => compile::OutputFormat::X86Asm,
+            OutputFormat::RiscvAsm => compile::OutputFormat::RiscVAsm,
+            OutputFormat::Wat => compile::OutputFormat::Wat,
         };
         opts.output_format(format)
     } else {
@@ -111,16 +294,83 @@ pub fn extract_compile_opts(args: &Args) -> Result<CompileOpts, CompileOptsErr>
         let target = match target {
             Target::X86_64 => compile::Target::X86_64,
             Target::Mips => compile::Target::Mips,
+            Target::Riscv32 => compile::Target::RiscV32,
+            Target::Wasm32 => compile::Target::Wasm32,
         };
         opts.target(target)
+    } else if linking || args.compile_only {
+        opts.target(compile::Target::Mips)
     } else {
         opts
     };
 
-    opts.for_assignments()
+    let opts = opts
+        .for_assignments()
+        .o32_abi(args.o32)
+        .bounds_check(args.bounds_check)
+        .const_fold(!args.skips.contains(&SkippablePasses::ConstFold))
+        .analyze_control_flow(!args.skips.contains(&SkippablePasses::ControlFlowAnalysis));
+    with_warnings(opts, &args.warnings).build()
+}
+
+/// The inputs of `comp run` are always compiled to MIPS objects and linked.
+pub fn extract_run_opts(args: &RunArgs) -> Result<CompileOpts, CompileOptsErr> {
+    let opts = CompileOptsBuilder::new()
+        .target(compile::Target::Mips)
+        .output_format(compile::OutputFormat::MipsAsm)
+        .for_assignments()
+        .o32_abi(args.o32)
+        .bounds_check(args.bounds_check)
         .const_fold(!args.skips.contains(&SkippablePasses::ConstFold))
-        .analyze_control_flow(!args.skips.contains(&SkippablePasses::ControlFlowAnalysis))
-        .build()
+        .analyze_control_flow(!args.skips.contains(&SkippablePasses::ControlFlowAnalysis));
+    with_warnings(opts, &args.warnings).build()
+}
+
+fn with_warnings(opts: CompileOptsBuilder, args: &WarningArgs) -> CompileOptsBuilder {
+    args.flags
+        .iter()
+        .fold(opts, |opts, flag| opts.warning_flag(flag))
+        .inhibit_warnings(args.inhibit)
+        .max_errors(args.max_errors)
+}
+
+pub fn extract_format_opts(args: &FmtArgs) -> format::FormatOpts {
+    format::FormatOpts {
+        brace_style: match args.brace_style {
+            BraceStyle::Attach => format::BraceStyle::Attach,
+            BraceStyle::Allman => format::BraceStyle::Allman,
+            BraceStyle::Linux => format::BraceStyle::Linux,
+        },
+        indent_width: args.indent_width,
+        max_width: args.max_width,
+    }
+}
+
+/// The diagnostic format of the subcommand, or of the compiler itself.
+pub fn extract_diagnostic_format(args: &Args) -> DiagnosticFormat {
+    match &args.command {
+        Some(Command::Run(run_args)) => run_args.diagnostic_format,
+        Some(Command::Fmt(fmt_args)) => fmt_args.diagnostic_format,
+        Some(Command::Lsp) | None => args.diagnostic_format,
+    }
+}
+
+/// Reads a source to format, the name is used in diagnostics.
+pub fn read_source(input_path: &PathOrStd) -> anyhow::Result<SimpleFile<String, String>> {
+    match input_path {
+        PathOrStd::Path(path) => {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read input file `{}`", path.display()))?;
+            Ok(SimpleFile::new(path.display().to_string(), source))
+        }
+        PathOrStd::StdStream => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .context("Failed to read from stdin")?;
+            Ok(SimpleFile::new("stdin stream".to_owned(), source))
+        }
+    }
 }
 
 pub fn open_output(args: &Args) -> anyhow::Result<Box<dyn std::io::Write>> {
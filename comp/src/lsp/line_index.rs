@@ -0,0 +1,54 @@
+use lsp_types::{Position, Range};
+
+use comp_lib::diagnostic::Span;
+
+/// Converts between byte offsets in a source and LSP positions, which count lines and UTF-16 code
+/// units.
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// The byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let character = self.source[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(line as u32, character as u32)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start()), self.position(span.excl_end()))
+    }
+
+    /// Positions past the end of a line are clamped to the end of the line.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.source.len();
+        };
+        let line = self.source[line_start..].split('\n').next().unwrap();
+        let mut character = 0;
+        for (i, c) in line.char_indices() {
+            if character >= position.character as usize {
+                return line_start + i;
+            }
+            character += c.len_utf16();
+        }
+        line_start + line.len()
+    }
+}
@@ -0,0 +1,315 @@
+//! A language server that speaks LSP over stdio, for editor integration.
+
+mod line_index;
+#[cfg(test)]
+mod test;
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use comp_lib::{
+    compile::{self, CompileOpts, CompileOptsBuilder},
+    diagnostic::{Code, Diagnostic, DiagnosticKind},
+    inspectors::symbols::{SymbolIndex, SymbolKind},
+};
+use line_index::LineIndex;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as _},
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, NumberOrString, OneOf, PublishDiagnosticsParams, ReferenceParams,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+
+/// Runs the language server on stdin and stdout until the client shuts it down.
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn serve(connection: Connection) -> Result<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection: &connection,
+        opts: CompileOptsBuilder::new().build()?,
+        // Folding would remove references to variables.
+        index_opts: CompileOptsBuilder::new().const_fold(false).build()?,
+        documents: HashMap::new(),
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                server.handle_request(request)?;
+            }
+            Message::Notification(notification) => server.handle_notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// An open file and the result of its last analysis.
+struct Document {
+    /// The last version that could be lowered to the IR, which the index refers to.
+    source: String,
+    /// `None` if no version could be lowered to the IR yet, e.g. because of a syntax error.
+    index: Option<SymbolIndex>,
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    /// The options of `comp`, so the diagnostics are the ones it reports.
+    opts: CompileOpts,
+    index_opts: CompileOpts,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server<'_> {
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.clone().as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidChangeTextDocumentParams>(
+                    DidChangeTextDocument::METHOD,
+                )?;
+                // Only full syncs are supported, so the last change contains the whole source.
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.update(document.uri, change.text, Some(document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidCloseTextDocumentParams>(
+                    DidCloseTextDocument::METHOD,
+                )?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish_diagnostics(params.text_document.uri, Vec::new(), None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Analyzes the new source of a document and publishes its diagnostics.
+    fn update(&mut self, uri: Url, source: String, version: Option<i32>) -> Result<()> {
+        let res = compile::compile_to_ir(&source, &self.opts);
+        let line_index = LineIndex::new(&source);
+        let diagnostics = res
+            .diagnostics()
+            .map(|(kind, diagnostic)| to_lsp_diagnostic(&uri, &line_index, kind, diagnostic))
+            .collect();
+
+        // The folded IR still has the declarations if folding was needed, e.g. for an array size.
+        let unfolded = compile::compile_to_ir(&source, &self.index_opts);
+        match unfolded.value().or(res.value()) {
+            Some(ir) => {
+                let index = Some(SymbolIndex::new(ir, &source));
+                self.documents
+                    .insert(uri.clone(), Document { source, index });
+            }
+            // Keeps the index of the last version that could be analyzed, e.g. while typing.
+            None => {
+                self.documents.entry(uri.clone()).or_insert(Document {
+                    source,
+                    index: None,
+                });
+            }
+        }
+        self.publish_diagnostics(uri, diagnostics, version)
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, version);
+        self.connection
+            .sender
+            .send(Notification::new(PublishDiagnostics::METHOD.to_owned(), params).into())?;
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<()> {
+        let id = request.id.clone();
+        let response = match request.method.clone().as_str() {
+            HoverRequest::METHOD => {
+                let params: HoverParams = request.extract(HoverRequest::METHOD)?.1;
+                Response::new_ok(id, self.hover(params.text_document_position_params))
+            }
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = request.extract(GotoDefinition::METHOD)?.1;
+                Response::new_ok(id, self.definition(params.text_document_position_params))
+            }
+            References::METHOD => {
+                let params: ReferenceParams = request.extract(References::METHOD)?.1;
+                Response::new_ok(id, self.references(params))
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams =
+                    request.extract(DocumentSymbolRequest::METHOD)?.1;
+                Response::new_ok(id, self.document_symbols(params))
+            }
+            method => Response::new_err(
+                id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{method}`"),
+            ),
+        };
+        self.connection.sender.send(response.into())?;
+        Ok(())
+    }
+
+    /// Returns the document, its analysis and the byte offset of the position.
+    fn lookup(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Option<(&Document, &SymbolIndex, usize)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        let index = document.index.as_ref()?;
+        let offset = LineIndex::new(&document.source).offset(position.position);
+        Some((document, index, offset))
+    }
+
+    /// Shows the declaration of a symbol, or the type of the expression under the cursor.
+    fn hover(&self, position: TextDocumentPositionParams) -> Option<Hover> {
+        let (document, index, offset) = self.lookup(&position)?;
+        let line_index = LineIndex::new(&document.source);
+        let (value, span) = match index.symbol_at(offset) {
+            Some((symbol, span)) => (symbol.declaration.clone(), span),
+            None => {
+                let (span, ty) = index.type_at(offset)?;
+                (ty.to_string(), span)
+            }
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```c\n{value}\n```"),
+            }),
+            range: Some(line_index.range(span)),
+        })
+    }
+
+    fn definition(&self, position: TextDocumentPositionParams) -> Option<GotoDefinitionResponse> {
+        let (document, index, offset) = self.lookup(&position)?;
+        let (symbol, _) = index.symbol_at(offset)?;
+        let range = LineIndex::new(&document.source).range(symbol.ident_span);
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            position.text_document.uri,
+            range,
+        )))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let position = params.text_document_position;
+        let (document, index, offset) = self.lookup(&position)?;
+        let (symbol, _) = index.symbol_at(offset)?;
+        let line_index = LineIndex::new(&document.source);
+        let declaration = params
+            .context
+            .include_declaration
+            .then_some(&symbol.ident_span);
+        Some(
+            declaration
+                .into_iter()
+                .chain(&symbol.references)
+                .map(|span| {
+                    Location::new(position.text_document.uri.clone(), line_index.range(*span))
+                })
+                .collect(),
+        )
+    }
+
+    /// Lists the functions and global variables.
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let index = document.index.as_ref()?;
+        let line_index = LineIndex::new(&document.source);
+        let symbols = index
+            .symbols()
+            .filter_map(|symbol| {
+                let kind = match symbol.kind {
+                    SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+                    SymbolKind::GlobalVar => lsp_types::SymbolKind::VARIABLE,
+                    SymbolKind::Param | SymbolKind::LocalVar => return None,
+                };
+                // The `deprecated` field is deprecated, but has to be initialized.
+                #[allow(deprecated)]
+                let document_symbol = DocumentSymbol {
+                    name: symbol.name.clone(),
+                    detail: Some(symbol.declaration.clone()),
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range: line_index.range(symbol.span),
+                    selection_range: line_index.range(symbol.ident_span),
+                    children: None,
+                };
+                Some(document_symbol)
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
+
+fn to_lsp_diagnostic(
+    uri: &Url,
+    line_index: &LineIndex,
+    kind: DiagnosticKind,
+    diagnostic: &Diagnostic,
+) -> lsp_types::Diagnostic {
+    let severity = match kind {
+        DiagnosticKind::Rec => lsp_types::DiagnosticSeverity::WARNING,
+        DiagnosticKind::Err => lsp_types::DiagnosticSeverity::ERROR,
+    };
+    let code = (diagnostic.code() != &Code::Unspecified)
+        .then(|| NumberOrString::String(diagnostic.code().to_string()));
+    let message = match diagnostic.main_span_message() {
+        Some(span_message) => format!("{}\n{span_message}", diagnostic.message()),
+        None => diagnostic.message().clone(),
+    };
+    let related_information = diagnostic
+        .additional_spans()
+        .map(|(span, message)| lsp_types::DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), line_index.range(*span)),
+            message: message.cloned().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    lsp_types::Diagnostic::new(
+        line_index.range(*diagnostic.main_span()),
+        Some(severity),
+        code,
+        Some("comp".to_owned()),
+        message,
+        (!related_information.is_empty()).then_some(related_information),
+        None,
+    )
+}
@@ -0,0 +1,240 @@
+use super::*;
+use lsp_server::RequestId;
+use lsp_types::{
+    notification::{Exit, Initialized, Notification as _},
+    request::{Initialize, Shutdown},
+    DidChangeTextDocumentParams, DidOpenTextDocumentParams, Position,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    VersionedTextDocumentIdentifier,
+};
+use serde_json::{json, Value};
+
+const SOURCE: &str = "int counter = 0;
+
+int next(int step) {
+    counter = counter + step;
+    return counter;
+}
+
+int main() {
+    int a = next(2);
+    return next(a);
+}
+";
+
+/// Plays the client side of a session with the server, which runs on another thread.
+struct Client {
+    connection: Connection,
+    server: Option<std::thread::JoinHandle<Result<()>>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let (client, server) = Connection::memory();
+        let server = std::thread::spawn(move || serve(server));
+        let mut client = Client {
+            connection: client,
+            server: Some(server),
+            next_id: 0,
+        };
+        let capabilities = client.request::<Initialize>(json!({ "capabilities": {} }));
+        assert_eq!(capabilities["capabilities"]["hoverProvider"], json!(true));
+        client.notify::<Initialized>(json!({}));
+        client
+    }
+
+    fn request<R: lsp_types::request::Request>(&mut self, params: impl serde::Serialize) -> Value {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let request = Request::new(id.clone(), R::METHOD.to_owned(), params);
+        self.connection.sender.send(request.into()).unwrap();
+        match self.connection.receiver.recv().unwrap() {
+            Message::Response(response) => {
+                assert_eq!(response.id, id);
+                assert!(response.error.is_none(), "{:?}", response.error);
+                response.result.unwrap_or(Value::Null)
+            }
+            message => panic!("expected a response, got {message:?}"),
+        }
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: impl serde::Serialize) {
+        let notification = Notification::new(N::METHOD.to_owned(), params);
+        self.connection.sender.send(notification.into()).unwrap();
+    }
+
+    /// Waits for the diagnostics the server publishes after a document changed.
+    fn diagnostics(&self) -> Vec<lsp_types::Diagnostic> {
+        match self.connection.receiver.recv().unwrap() {
+            Message::Notification(notification) => {
+                notification
+                    .extract::<PublishDiagnosticsParams>(PublishDiagnostics::METHOD)
+                    .unwrap()
+                    .diagnostics
+            }
+            message => panic!("expected diagnostics, got {message:?}"),
+        }
+    }
+
+    fn open(&self, source: &str) -> Vec<lsp_types::Diagnostic> {
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri(), "c".to_owned(), 1, source.to_owned()),
+        });
+        self.diagnostics()
+    }
+
+    fn change(&self, source: &str) -> Vec<lsp_types::Diagnostic> {
+        self.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri(), 2),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: source.to_owned(),
+            }],
+        });
+        self.diagnostics()
+    }
+
+    /// The position of the `nth` occurrence of `pattern` in `SOURCE`.
+    fn at(&self, pattern: &str, nth: usize) -> Value {
+        self.at_in(SOURCE, pattern, nth)
+    }
+
+    /// The position of the `nth` occurrence of `pattern` in `source`.
+    fn at_in(&self, source: &str, pattern: &str, nth: usize) -> Value {
+        let offset = source.match_indices(pattern).nth(nth).unwrap().0;
+        json!({
+            "textDocument": TextDocumentIdentifier::new(uri()),
+            "position": LineIndex::new(source).position(offset),
+        })
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        self.request::<Shutdown>(());
+        self.notify::<Exit>(());
+        self.server.take().unwrap().join().unwrap().unwrap();
+    }
+}
+
+fn uri() -> Url {
+    Url::parse("file:///test.c").unwrap()
+}
+
+/// Returns the `(line, character)` of the start of every range in `locations`.
+fn starts(locations: &Value) -> Vec<(u64, u64)> {
+    locations
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| {
+            let start = &location["range"]["start"];
+            (
+                start["line"].as_u64().unwrap(),
+                start["character"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn publishes_diagnostics() {
+    let client = Client::start();
+    assert!(client.open(SOURCE).is_empty());
+
+    let diagnostics = client.change("int main() {\n    return x;\n}\n");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(
+        diagnostic.severity,
+        Some(lsp_types::DiagnosticSeverity::ERROR)
+    );
+    assert_eq!(
+        diagnostic.code,
+        Some(NumberOrString::String(Code::UndeclaredIdent.to_string()))
+    );
+    assert_eq!(
+        diagnostic.range,
+        lsp_types::Range::new(Position::new(1, 11), Position::new(1, 12))
+    );
+}
+
+#[test]
+fn analyzes_constant_array_sizes() {
+    let mut client = Client::start();
+    let source = "int main() {\n    int a[2 * 4];\n    a[0] = 1;\n    return a[0];\n}\n";
+    assert!(client.open(source).is_empty());
+    let hover = client.request::<HoverRequest>(client.at_in(source, "a[0]", 1));
+    assert_eq!(hover["contents"]["value"], "```c\nint a[8]\n```");
+
+    // The index of the last version that could be analyzed is kept while there's a syntax error.
+    assert!(!client.change(&source.replace("a[0];", "a[0]")).is_empty());
+    let hover = client.request::<HoverRequest>(client.at_in(source, "a[0]", 1));
+    assert_eq!(hover["contents"]["value"], "```c\nint a[8]\n```");
+}
+
+#[test]
+fn hovers_symbols_and_expressions() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let hover = client.request::<HoverRequest>(client.at("next(2)", 0));
+    assert_eq!(hover["contents"]["value"], "```c\nint next(int)\n```");
+    let hover = client.request::<HoverRequest>(client.at("step;", 0));
+    assert_eq!(hover["contents"]["value"], "```c\nint step\n```");
+    // `+` isn't part of an identifier, so the type of the whole addition is shown.
+    let hover = client.request::<HoverRequest>(client.at("+", 0));
+    assert_eq!(hover["contents"]["value"], "```c\nint\n```");
+    assert_eq!(
+        hover["range"]["start"],
+        json!({ "line": 3, "character": 14 })
+    );
+}
+
+#[test]
+fn finds_definitions_and_references() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let definition = client.request::<GotoDefinition>(client.at("a)", 0));
+    assert_eq!(starts(&json!([definition])), [(8, 8)]);
+
+    let mut params = client.at("counter;", 0);
+    params["context"] = json!({ "includeDeclaration": true });
+    let references = client.request::<References>(params);
+    assert_eq!(starts(&references), [(0, 4), (3, 4), (3, 14), (4, 11)]);
+}
+
+#[test]
+fn lists_document_symbols() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let symbols = client.request::<DocumentSymbolRequest>(json!({
+        "textDocument": TextDocumentIdentifier::new(uri()),
+    }));
+    let symbols: Vec<_> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap(),
+                symbol["detail"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        symbols,
+        [
+            ("counter", "int counter"),
+            ("next", "int next(int)"),
+            ("main", "int main(void)"),
+        ]
+    );
+}
@@ -1,36 +1,226 @@
 mod cli;
+mod lsp;
 mod report;
 mod util;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 
-use comp_lib::compile;
+use codespan_reporting::files::SimpleFile;
+use comp_lib::{compile, diagnostic, passes::format};
 use compile::compile;
 use std::io::Write;
 
 fn main() -> Result<()> {
     let args = cli::Args::parse();
 
-    // Doing this now to early report errors
-    let compile_opts = cli::extract_compile_opts(&args)?;
+    let mut reporter = report::Reporter::new(cli::extract_diagnostic_format(&args));
+    let res = run_command(&args, &mut reporter);
+    reporter.finish();
+    match res {
+        // The document of a machine readable format should be the only output on std err.
+        Err(err) if !reporter.is_human() && err.is::<report::Failed>() => std::process::exit(1),
+        res => res,
+    }
+}
 
-    let source = cli::open_input_source(&args)?;
-    let source_name = source.name().clone();
+fn run_command(args: &cli::Args, reporter: &mut report::Reporter) -> Result<()> {
+    if let Some(code) = args.explain {
+        print!("{code} {}\n\n{}", code.name(), code.explanation());
+        return Ok(());
+    }
+
+    match &args.command {
+        Some(cli::Command::Run(run_args)) => return run(run_args, reporter),
+        Some(cli::Command::Lsp) => return lsp::run(),
+        Some(cli::Command::Fmt(fmt_args)) => return fmt(fmt_args, reporter),
+        None => {}
+    }
 
-    let res = compile(source.source(), &source_name, &compile_opts);
+    let mut inputs = cli::open_inputs(args)?;
+    let linking = !matches!(inputs.as_slice(), [cli::Input::Source(_)]);
 
-    if !res.is_ok() {
-        report::eprint_aggregate(&res, &source);
+    // Doing this now to early report errors
+    let compile_opts = cli::extract_compile_opts(args, linking)?;
+
+    if args.fix {
+        fix(args, &mut inputs, &compile_opts, reporter)?;
     }
 
-    let Some(output) = res.into_value() else {
-        bail!("couldn't compile due to the previous errors");
+    let output = if linking {
+        link(inputs, &compile_opts, reporter)?
+    } else {
+        let Some(cli::Input::Source(source)) = inputs.into_iter().next() else {
+            unreachable!()
+        };
+        let source_name = source.name().clone();
+
+        let res = compile(source.source(), &source_name, &compile_opts);
+
+        if !res.is_ok() {
+            reporter.report(&res, &source);
+        }
+
+        let Some(output) = res.into_value() else {
+            return Err(report::Failed("compile").into());
+        };
+        output
     };
 
-    cli::open_output(&args)?
+    cli::open_output(args)?
         .write_all(&output)
         .with_context(|| "Failed to write to output".to_string())?;
 
     Ok(())
 }
+
+/// Applies the safe fixes to the sources, and writes the fixed sources to their input files.
+fn fix(
+    args: &cli::Args,
+    inputs: &mut [cli::Input],
+    compile_opts: &compile::CompileOpts,
+    reporter: &report::Reporter,
+) -> Result<()> {
+    for (input_path, input) in args.input_paths.iter().zip(inputs) {
+        let (util::PathOrStd::Path(path), cli::Input::Source(source)) = (input_path, input) else {
+            continue;
+        };
+
+        let res = compile::compile_to_ir(source.source(), compile_opts);
+        let fixes = res
+            .diagnostics()
+            .flat_map(|(_, diagnostic)| diagnostic.fixes())
+            .filter(|fix| fix.is_safe());
+        let (fixed, applied) = diagnostic::apply_fixes(source.source(), fixes);
+        if applied == 0 {
+            continue;
+        }
+
+        std::fs::write(path, &fixed)
+            .with_context(|| format!("Failed to write to `{}`", path.display()))?;
+        if reporter.is_human() {
+            eprintln!("applied {applied} fix(es) to `{}`", path.display());
+        }
+        *source = SimpleFile::new(source.name().clone(), fixed);
+    }
+    Ok(())
+}
+
+/// Compiles all sources to objects, and links them together with the other objects.
+fn link(
+    inputs: Vec<cli::Input>,
+    compile_opts: &compile::CompileOpts,
+    reporter: &mut report::Reporter,
+) -> Result<Vec<u8>> {
+    let objects = compile_objects(inputs, compile_opts, reporter)?;
+    compile::link(objects, compile_opts).map_err(|err| anyhow!("couldn't link: {err}"))
+}
+
+/// Compiles, links and runs the inputs in the MIPS simulator. Exits the process with the exit code
+/// of the program.
+fn run(args: &cli::RunArgs, reporter: &mut report::Reporter) -> Result<()> {
+    let inputs = cli::open_run_inputs(args)?;
+    let compile_opts = cli::extract_run_opts(args)?;
+
+    let objects = compile_objects(inputs, &compile_opts, reporter)?;
+    // The process exits when the program is done, so the diagnostics are written now.
+    reporter.finish();
+    let root = mips_ir::link(objects).map_err(|err| anyhow!("couldn't link: {err}"))?;
+    let program = mips_sim::Program::from_root(&root)
+        .map_err(|err| anyhow!("couldn't load the program: {err}"))?;
+
+    let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+    let machine = mips_sim::Machine::new(&program, std::io::stdin().lock(), &mut output);
+    let mut machine = match args.max_steps {
+        Some(max_steps) => machine.with_step_limit(max_steps),
+        None => machine,
+    };
+    let result = machine.run();
+    output.flush().context("Failed to write to output")?;
+
+    let exit = result.map_err(|err| anyhow!("{err}"))?;
+    std::process::exit(exit.code);
+}
+
+/// Formats the inputs, or only checks whether they are formatted with `--check`.
+fn fmt(args: &cli::FmtArgs, reporter: &mut report::Reporter) -> Result<()> {
+    let opts = cli::extract_format_opts(args);
+
+    let mut failed = false;
+    let mut unformatted = 0;
+    for input_path in &args.input_paths {
+        let source = cli::read_source(input_path)?;
+
+        let res = format::format_source(source.source(), &opts);
+        if !res.is_ok() {
+            reporter.report(&res, &source);
+        }
+        let Some(formatted) = res.into_value() else {
+            failed = true;
+            continue;
+        };
+
+        if args.check {
+            if formatted != *source.source() {
+                eprintln!("`{}` isn't formatted", source.name());
+                unformatted += 1;
+            }
+            continue;
+        }
+        match input_path {
+            util::PathOrStd::Path(path) => {
+                if formatted != *source.source() {
+                    std::fs::write(path, formatted)
+                        .with_context(|| format!("Failed to write to `{}`", path.display()))?;
+                }
+            }
+            util::PathOrStd::StdStream => std::io::stdout()
+                .write_all(formatted.as_bytes())
+                .context("Failed to write to output")?,
+        }
+    }
+
+    if failed {
+        return Err(report::Failed("format").into());
+    }
+    if unformatted != 0 {
+        bail!("{unformatted} input(s) aren't formatted");
+    }
+    Ok(())
+}
+
+/// Compiles all sources to MIPS objects, the objects among the inputs are kept as they are.
+fn compile_objects(
+    inputs: Vec<cli::Input>,
+    compile_opts: &compile::CompileOpts,
+    reporter: &mut report::Reporter,
+) -> Result<Vec<mips_ir::Root>> {
+    let mut objects = Vec::with_capacity(inputs.len());
+    let mut failed = false;
+    for input in inputs {
+        match input {
+            cli::Input::Source(source) => {
+                let source_name = source.name().clone();
+
+                let res =
+                    compile::compile_to_mips_object(source.source(), &source_name, compile_opts);
+
+                if !res.is_ok() {
+                    reporter.report(&res, &source);
+                }
+
+                match res.into_value() {
+                    Some(object) => objects.push(object),
+                    None => failed = true,
+                }
+            }
+            cli::Input::Object(object) => objects.push(object),
+        }
+    }
+
+    if failed {
+        return Err(report::Failed("compile").into());
+    }
+
+    Ok(objects)
+}
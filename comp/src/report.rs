@@ -1,13 +1,301 @@
 use codespan_reporting::{
     diagnostic::{Label, Severity},
+    files::Files,
     term::{self, termcolor::WriteColor},
 };
-use comp_lib::diagnostic::{AggregateResult, Code, DiagnosticKind};
+use comp_lib::diagnostic::{self, AggregateResult, Code, Diagnostic, DiagnosticKind, Fix, Span};
 use is_terminal::IsTerminal;
+use serde_json::{json, Value};
 
-pub fn eprint_aggregate<'files, T, F>(aggregate: &AggregateResult<T>, files: &'files F)
+use crate::cli::DiagnosticFormat;
+
+/// The version of the layout of the JSON format, bumped on every incompatible change.
+const JSON_VERSION: u32 = 2;
+
+/// The error when an input couldn't be processed because of the reported diagnostics.
+#[derive(Debug)]
+pub struct Failed(pub &'static str);
+
+impl std::fmt::Display for Failed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "couldn't {} due to the previous errors", self.0)
+    }
+}
+
+impl std::error::Error for Failed {}
+
+/// Writes the diagnostics of all inputs to std err.
+///
+/// Human readable diagnostics are written immediately, the machine readable formats are
+/// collected into one document that is written by [`Reporter::finish`].
+pub struct Reporter {
+    format: DiagnosticFormat,
+    /// The diagnostics in the JSON format.
+    diagnostics: Vec<Value>,
+    finished: bool,
+}
+
+impl Reporter {
+    pub fn new(format: DiagnosticFormat) -> Self {
+        Self {
+            format,
+            diagnostics: Vec::new(),
+            finished: false,
+        }
+    }
+
+    pub fn is_human(&self) -> bool {
+        self.format == DiagnosticFormat::Human
+    }
+
+    pub fn report<'files, T, F>(&mut self, aggregate: &AggregateResult<T>, files: &'files F)
+    where
+        F: Files<'files, FileId = ()>,
+    {
+        match self.format {
+            DiagnosticFormat::Human => eprint_aggregate(aggregate, files),
+            DiagnosticFormat::Json | DiagnosticFormat::Sarif => {
+                self.diagnostics.extend(
+                    aggregate
+                        .diagnostics()
+                        .map(|(kind, diagnostic)| to_json(files, kind, diagnostic)),
+                );
+            }
+        }
+    }
+
+    /// Writes the document of a machine readable format, once all inputs are reported.
+    pub fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        let document = match self.format {
+            DiagnosticFormat::Human => return,
+            DiagnosticFormat::Json => self.json_document(),
+            DiagnosticFormat::Sarif => self.sarif_document(),
+        };
+        eprintln!("{document:#}");
+    }
+
+    fn json_document(&self) -> Value {
+        json!({
+            "version": JSON_VERSION,
+            "diagnostics": self.diagnostics,
+        })
+    }
+
+    fn sarif_document(&self) -> Value {
+        let mut rules: Vec<_> = self
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| {
+                Some((diagnostic["code"].as_str()?, diagnostic["name"].as_str()?))
+            })
+            .collect();
+        rules.sort_unstable();
+        rules.dedup();
+
+        let results: Vec<_> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let file = &diagnostic["file"];
+                let related: Vec<_> = diagnostic["related"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .map(|(id, span)| {
+                        let mut location = sarif_location(file, span);
+                        location["id"] = json!(id);
+                        location
+                    })
+                    .collect();
+                let mut result = json!({
+                    "level": diagnostic["severity"],
+                    "message": { "text": diagnostic["message"] },
+                    "locations": [sarif_location(file, &diagnostic["span"])],
+                    "relatedLocations": related,
+                });
+                if let Some(id) = diagnostic["code"].as_str() {
+                    result["ruleId"] = json!(id);
+                    result["ruleIndex"] = json!(rules.iter().position(|(rule, _)| *rule == id));
+                }
+                let fixes = diagnostic["fixes"].as_array().unwrap();
+                if !fixes.is_empty() {
+                    result["fixes"] = fixes.iter().map(|fix| sarif_fix(file, fix)).collect();
+                }
+                result
+            })
+            .collect();
+
+        let rules: Vec<_> = rules
+            .iter()
+            .map(|(id, name)| json!({ "id": id, "name": name }))
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "comp",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "columnKind": "unicodeCodePoints",
+                "results": results,
+            }],
+        })
+    }
+}
+
+fn to_json<'files, F>(files: &'files F, kind: DiagnosticKind, diagnostic: &Diagnostic) -> Value
+where
+    F: Files<'files, FileId = ()>,
+{
+    let severity = match kind {
+        DiagnosticKind::Rec => "warning",
+        DiagnosticKind::Err => "error",
+    };
+    let (code, name) = match diagnostic.code() {
+        Code::Unspecified => (Value::Null, Value::Null),
+        code => (json!(code.to_string()), json!(code.name())),
+    };
+    let related: Vec<_> = diagnostic
+        .additional_spans()
+        .map(|(span, message)| span_to_json(files, *span, message))
+        .collect();
+    let fixes: Vec<_> = diagnostic
+        .fixes()
+        .iter()
+        .map(|fix| {
+            let edits: Vec<_> = fix
+                .edits()
+                .iter()
+                .map(|(span, replacement)| {
+                    json!({
+                        "span": span_to_json(files, *span, None),
+                        "replacement": replacement,
+                    })
+                })
+                .collect();
+            json!({
+                "message": fix.message(),
+                "safe": fix.is_safe(),
+                "edits": edits,
+            })
+        })
+        .collect();
+    json!({
+        "file": files.name(()).unwrap().to_string(),
+        "severity": severity,
+        "code": code,
+        "name": name,
+        "message": diagnostic.message(),
+        "span": span_to_json(files, *diagnostic.main_span(), diagnostic.main_span_message()),
+        "related": related,
+        "fixes": fixes,
+    })
+}
+
+/// The lines and columns start at 1, columns count unicode code points and the end is exclusive.
+fn span_to_json<'files, F>(files: &'files F, span: Span, message: Option<&String>) -> Value
+where
+    F: Files<'files, FileId = ()>,
+{
+    let position = |offset| {
+        let location = files.location((), offset).unwrap();
+        json!({
+            "offset": offset,
+            "line": location.line_number,
+            "column": location.column_number,
+        })
+    };
+    json!({
+        "start": position(span.start()),
+        "end": position(span.excl_end()),
+        "message": message,
+    })
+}
+
+fn sarif_region(span: &Value) -> Value {
+    let (start, end) = (&span["start"], &span["end"]);
+    json!({
+        "startLine": start["line"],
+        "startColumn": start["column"],
+        "endLine": end["line"],
+        "endColumn": end["column"],
+        "byteOffset": start["offset"],
+        "byteLength": end["offset"].as_u64().unwrap() - start["offset"].as_u64().unwrap(),
+    })
+}
+
+fn sarif_location(file: &Value, span: &Value) -> Value {
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": file },
+            "region": sarif_region(span),
+        },
+    });
+    if !span["message"].is_null() {
+        location["message"] = json!({ "text": span["message"] });
+    }
+    location
+}
+
+fn sarif_fix(file: &Value, fix: &Value) -> Value {
+    let replacements: Vec<_> = fix["edits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edit| {
+            json!({
+                "deletedRegion": sarif_region(&edit["span"]),
+                "insertedContent": { "text": edit["replacement"] },
+            })
+        })
+        .collect();
+    json!({
+        "description": { "text": fix["message"] },
+        "artifactChanges": [{
+            "artifactLocation": { "uri": file },
+            "replacements": replacements,
+        }],
+    })
+}
+
+/// A note with the lines of the source that the fix changes, after the fix.
+fn fix_note(source: &str, fix: &Fix) -> String {
+    let start = fix.edits().iter().map(|(span, _)| span.start()).min();
+    let end = fix.edits().iter().map(|(span, _)| span.excl_end()).max();
+    let line_start = source[..start.unwrap_or(0)]
+        .rfind('\n')
+        .map_or(0, |i| i + 1);
+    let end = end.unwrap_or(0);
+    let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i);
+
+    let (fixed, _) = diagnostic::apply_fixes(source, [fix]);
+    let lines = &fixed[line_start..fixed.len() - (source.len() - line_end)];
+    let indent = lines
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut note = format!("help: {}:", fix.message());
+    for line in lines.lines() {
+        note += "\n    ";
+        note += line.get(indent..).unwrap_or_default();
+    }
+    note
+}
+
+fn eprint_aggregate<'files, T, F>(aggregate: &AggregateResult<T>, files: &'files F)
 where
-    F: codespan_reporting::files::Files<'files, FileId = ()>,
+    F: Files<'files, FileId = ()>,
 {
     let mut writer = if std::io::stderr().is_terminal() {
         term::termcolor::StandardStream::stderr(term::termcolor::ColorChoice::Always)
@@ -59,9 +347,17 @@ where
             labels.push(l);
         }
 
+        let source = files.source(()).unwrap();
+        let notes = d
+            .fixes()
+            .iter()
+            .map(|fix| fix_note(source.as_ref(), fix))
+            .collect();
+
         let mut diagnostic = codespan_reporting::diagnostic::Diagnostic::new(severity)
             .with_message(d.message())
-            .with_labels(labels);
+            .with_labels(labels)
+            .with_notes(notes);
 
         if d.code() != &Code::Unspecified {
             diagnostic = diagnostic.with_code(d.code().to_string())
@@ -70,3 +366,97 @@ where
         term::emit(&mut writer, &config, files, &diagnostic).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan_reporting::files::SimpleFile;
+    use comp_lib::compile::{self, CompileOptsBuilder};
+
+    fn report(format: DiagnosticFormat, source: &str) -> Reporter {
+        let opts = CompileOptsBuilder::new().build().unwrap();
+        let res = compile::compile_to_ir(source, &opts);
+        let mut reporter = Reporter::new(format);
+        reporter.report(
+            &res,
+            &SimpleFile::new("test.c".to_owned(), source.to_owned()),
+        );
+        reporter
+    }
+
+    #[test]
+    fn json_document() {
+        let reporter = report(DiagnosticFormat::Json, "int main() {\n    return x;\n}\n");
+        let document = reporter.json_document();
+        assert_eq!(document["version"], JSON_VERSION);
+        let diagnostics = document["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic["file"], "test.c");
+        assert_eq!(diagnostic["severity"], "error");
+        assert_eq!(diagnostic["code"], Code::UndeclaredIdent.to_string());
+        assert_eq!(diagnostic["name"], "undeclared-ident");
+        assert_eq!(
+            diagnostic["span"]["start"],
+            json!({ "offset": 24, "line": 2, "column": 12 })
+        );
+        assert_eq!(
+            diagnostic["span"]["end"],
+            json!({ "offset": 25, "line": 2, "column": 13 })
+        );
+    }
+
+    #[test]
+    fn fixes() {
+        let source = "int main() {\n    int *p = 5;\n    return 0;\n}\n";
+        let reporter = report(DiagnosticFormat::Json, source);
+        let document = reporter.json_document();
+        let fix = &document["diagnostics"][0]["fixes"][0];
+        assert_eq!(fix["safe"], false);
+        assert_eq!(fix["edits"][0]["replacement"], "(int *)(");
+        assert_eq!(fix["edits"][0]["span"]["start"]["column"], 14);
+
+        let res = compile::compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+        let (_, diagnostic) = res.diagnostics().next().unwrap();
+        assert_eq!(
+            fix_note(source, &diagnostic.fixes()[0]),
+            "help: cast to `int *` explicitly:\n    int *p = (int *)(5);"
+        );
+
+        let sarif = report(DiagnosticFormat::Sarif, source).sarif_document();
+        let replacement =
+            &sarif["runs"][0]["results"][0]["fixes"][0]["artifactChanges"][0]["replacements"][1];
+        assert_eq!(replacement["insertedContent"]["text"], ")");
+        assert_eq!(replacement["deletedRegion"]["byteOffset"], 27);
+        assert_eq!(replacement["deletedRegion"]["byteLength"], 0);
+    }
+
+    #[test]
+    fn sarif_document() {
+        let reporter = report(DiagnosticFormat::Sarif, "int main() {\n    return x;\n}\n");
+        let document = reporter.sarif_document();
+        assert_eq!(document["version"], "2.1.0");
+        let run = &document["runs"][0];
+        let code = Code::UndeclaredIdent.to_string();
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], code);
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["name"],
+            "undeclared-ident"
+        );
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], code);
+        assert_eq!(result["ruleIndex"], 0);
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"],
+            json!({
+                "startLine": 2,
+                "startColumn": 12,
+                "endLine": 2,
+                "endColumn": 13,
+                "byteOffset": 24,
+                "byteLength": 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::{fs, process::Command};
+
+#[test]
+fn applies_safe_fixes() {
+    let dir = std::env::temp_dir().join(format!("comp-fix-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.c");
+    fs::write(
+        &input,
+        "char last(long l) { return l; }
+int main() { long l = 300; char c = l + 1; int *p = 5; return last(l) + c; }
+",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_comp"))
+        .arg(&input)
+        .arg("--fix")
+        .args(["-o", "-"])
+        .output()
+        .unwrap();
+    let fixed = fs::read_to_string(&input).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    // The cast of the incompatible assign isn't safe, so it isn't applied.
+    assert_eq!(
+        fixed,
+        "char last(long l) { return (char)l; }
+int main() { long l = 300; char c = (char)(l + 1); int *p = 5; return last(l) + c; }
+"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("applied 2 fix(es)"));
+}
@@ -1,6 +1,6 @@
 lexer grammar CLexer;
 
-channels { COMMENTS }
+channels { COMMENTS, PRAGMAS }
 
 SINGLELINE_COMMENT: '//' ( ~[\n\r] )* -> channel(COMMENTS);
 MULTILINE_COMMENT: '/*' ( . )*? '*/' -> channel(COMMENTS);
@@ -78,6 +78,7 @@ KW_DEFAULT: 'default';
 KW_RETURN: 'return';
 
 INCLUDE: '#include' [ \t]* '<stdio.h>' [ \t]* EOL;
+PRAGMA: '#pragma' ( ~[\n\r] )* -> channel(PRAGMAS);
 
 IDENT: [_a-zA-Z][_a-zA-Z0-9]*;
 
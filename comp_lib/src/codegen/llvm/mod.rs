@@ -54,6 +54,18 @@ mod llvm_ir_builder {
                     operating_system: "none".to_owned(),
                     environment: None,
                 }),
+                crate::compile::Target::RiscV32 => module.set_target_triple(lir::TargetTriple {
+                    architecture: "riscv32".to_owned(),
+                    vendor: "unknown".to_owned(),
+                    operating_system: "none".to_owned(),
+                    environment: None,
+                }),
+                crate::compile::Target::Wasm32 => module.set_target_triple(lir::TargetTriple {
+                    architecture: "wasm32".to_owned(),
+                    vendor: "unknown".to_owned(),
+                    operating_system: "unknown".to_owned(),
+                    environment: None,
+                }),
             }
 
             let mut builder = Self {
@@ -83,6 +95,10 @@ mod llvm_ir_builder {
                 builder.add_function_definition(ident.as_str(), function);
             }
 
+            // Catch bugs in the builder here, instead of when the output is run by LLVM.
+            #[cfg(debug_assertions)]
+            builder.module.verify().ice();
+
             format!("{}", builder.module.display(lir::FmtOpts::default()))
         }
 
@@ -352,8 +352,50 @@ impl BinaryExprGenerator<'_, '_, '_, '_, '_> {
     }
 
     pub fn add_ir_add(mut self, builder: Builder) -> (Builder, MipsCondOrValue) {
+        let (builder, left, right) = self.eval_inner(builder);
+        self.add_values(builder, left, right)
+    }
+
+    /// Same as [`Self::add_ir_add`], but traps if one operand is an array and the other one isn't
+    /// a valid index of it. Only used for the address of an array subscript that is dereferenced,
+    /// since the address one past the end of an array can still be computed.
+    pub fn add_ir_checked_add(mut self, builder: Builder) -> (Builder, MipsCondOrValue) {
         let (mut builder, left, right) = self.eval_inner(builder);
 
+        let index = match (array_length(self.left), array_length(self.right)) {
+            (Some(length), None) => Some((&right, &self.right.ty, length)),
+            (None, Some(length)) => Some((&left, &self.left.ty, length)),
+            _ => None,
+        };
+        if let Some((index, index_ty, length)) = index {
+            let index_reg = match index {
+                MipsValue::Imm(imm) => {
+                    self.function_generator
+                        .imm_to_reg(&mut builder, *imm, index_ty)
+                }
+                MipsValue::Reg(reg) => *reg,
+                MipsValue::FReg(_) => unreachable!("ICE: floating array index"),
+            };
+            let length_reg = self
+                .function_generator
+                .load_int_constant(&mut builder, length as i128);
+            // A negative index is a large unsigned value, so it traps as well.
+            builder.bb.add_instruction(mir::instr::trap_if(
+                mir::TrapCond::GeU,
+                index_reg,
+                length_reg,
+            ));
+        }
+
+        self.add_values(builder, left, right)
+    }
+
+    fn add_values(
+        mut self,
+        mut builder: Builder,
+        left: MipsValue,
+        right: MipsValue,
+    ) -> (Builder, MipsCondOrValue) {
         let left_is_ptr = matches!(
             &self.left.ty,
             ctype::CType::Scalar(ctype::Scalar::Pointer(..))
@@ -909,6 +951,17 @@ impl BinaryExprGenerator<'_, '_, '_, '_, '_> {
     }
 }
 
+/// The length of the array that the operand decays from, if it is an array.
+fn array_length(operand: &ir::ExprNode) -> Option<u128> {
+    match &operand.expr {
+        ir::Expr::LvalueDeref(lvalue) => match &lvalue.ty {
+            ctype::CType::Aggregate(ctype::Aggregate::Array(array)) => Some(array.length),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 enum PointerArithOp {
     Add,
     Sub,
@@ -923,18 +976,9 @@ fn pointer_arith(
     size: u128,
     op_type: PointerArithOp,
 ) {
-    let size_reg = function_generator.new_register();
-    if (size >> 32) > 0 {
-        // TODO give nice Diagnostic error somehow
-        todo!("ICE: to big pointer type");
-    } else if (size >> 16) > 0 {
-        builder
-            .bb
-            .add_instruction(mir::instr::load_upper(size_reg, (size >> 16) as u16));
-    }
-    builder
-        .bb
-        .add_instruction(mir::instr::or_imm(size_reg, mir::Reg::ZERO, size as u16));
+    // Addresses are only 32 bits wide, so the offset wraps around the same way when only the lower
+    // 32 bits of the size are used.
+    let size_reg = function_generator.load_int_constant(builder, size as i128);
 
     builder
         .bb
@@ -958,7 +958,21 @@ impl<'g, 'i, 's> FunctionGenerator<'g, 'i, 's> {
         builder: Builder,
         expr: &ir::ExprNode,
     ) -> (Builder, MipsLvalue) {
-        let (builder, value) = self.add_ir_expr_node(builder, expr);
+        let (builder, value) = match &expr.expr {
+            // An array subscript `a[i]` is `*(a + i)`
+            ir::Expr::Binary(left, ir::BinaryOp::Add, right)
+                if self.root_generator.bounds_check =>
+            {
+                let generator = BinaryExprGenerator {
+                    function_generator: self,
+                    left,
+                    right,
+                    to_type: &expr.ty,
+                };
+                generator.add_ir_checked_add(builder)
+            }
+            _ => self.add_ir_expr_node(builder, expr),
+        };
         let MipsCondOrValue::Value(MipsValue::Reg(reg)) = value else {
             unreachable!()
         };
@@ -12,17 +12,20 @@ pub struct Generator<'i, 's> {
     root: mir::Root,
     ir: &'i ir::Root,
     source: &'s str,
+    /// Trap when an array is indexed outside of its bounds.
+    bounds_check: bool,
     float_constants: HashMap<u32, mir::Label>,
     double_constants: HashMap<u64, mir::Label>,
     string_constants: HashMap<Vec<u8>, mir::Label>,
 }
 
 impl<'i, 's> Generator<'i, 's> {
-    pub fn new(ir: &'i ir::Root, source: &'s str) -> Self {
+    pub fn new(ir: &'i ir::Root, source: &'s str, bounds_check: bool) -> Self {
         Self {
             root: mir::Root::new(),
             ir,
             source,
+            bounds_check,
             float_constants: HashMap::new(),
             double_constants: HashMap::new(),
             string_constants: HashMap::new(),
@@ -57,9 +60,8 @@ impl<'i, 's> Generator<'i, 's> {
             let function = FunctionGenerator::new(self, ident, function).generate();
             let label = function.label().clone();
             self.root.add_function(function);
-            if ident == "main" {
-                self.root.export_label(label);
-            }
+            // Functions have external linkage, so they should be visible to other objects.
+            self.root.export_label(label);
         }
     }
 
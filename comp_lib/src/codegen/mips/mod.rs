@@ -1,16 +1,56 @@
 mod generator;
 
-use crate::{diagnostic::AggregateResult, ir, settings::Settings};
+use crate::{
+    diagnostic::{AggregateResult, DiagnosticBuilder},
+    ir,
+    settings::{Settings, Target},
+};
 use generator::Generator;
 use mips_ir as mir;
 
 pub fn build_from_ir(
     ir: &ir::Root,
-    _settings: &Settings,
+    settings: &Settings,
     _filename: &str,
     source: &str,
 ) -> AggregateResult<mips_ir::Root> {
-    let mut root = Generator::new(ir, source).generate();
-    mir::compile_and_link(&mut root);
+    let mut root = Generator::new(ir, source, settings.bounds_check).generate();
+    root.set_calling_convention(calling_convention(settings));
+    root.set_isa(isa(settings));
+    match mir::compile_and_link(&mut root) {
+        Ok(()) => AggregateResult::new_ok(root),
+        // The linker needs a `main` function, which isn't required for the other output formats.
+        Err(reason) => {
+            AggregateResult::new_err(DiagnosticBuilder::new(0..0).build_link_error(&reason))
+        }
+    }
+}
+
+/// Same as [`build_from_ir`], but doesn't link the result. The returned object can be linked with
+/// other objects using [`mips_ir::link`].
+pub fn build_object_from_ir(
+    ir: &ir::Root,
+    settings: &Settings,
+    _filename: &str,
+    source: &str,
+) -> AggregateResult<mips_ir::Root> {
+    let mut root = Generator::new(ir, source, settings.bounds_check).generate();
+    root.set_calling_convention(calling_convention(settings));
+    root.set_isa(isa(settings));
+    mir::compile(&mut root);
     AggregateResult::new_ok(root)
 }
+
+fn calling_convention(settings: &Settings) -> mir::CallingConvention {
+    match settings.o32_abi {
+        true => mir::CallingConvention::O32,
+        false => mir::CallingConvention::Stack,
+    }
+}
+
+fn isa(settings: &Settings) -> mir::Isa {
+    match settings.target {
+        Target::RiscV32 => mir::Isa::RiscV32,
+        Target::Mips | Target::X86_64 | Target::Wasm32 => mir::Isa::Mips32,
+    }
+}
@@ -1,2 +1,4 @@
 pub mod llvm;
 pub mod mips;
+pub mod wasm;
+pub mod x86_64;
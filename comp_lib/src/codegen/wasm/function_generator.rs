@@ -0,0 +1,796 @@
+use super::util::{self, Class};
+use super::ModuleGenerator;
+use crate::ir::{self, ctype::CType, table::ItemId};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Switch statements use a `br_table` if it has at most this many entries per case, and a chain
+/// of comparisons otherwise.
+const MAX_TABLE_ENTRIES_PER_CASE: i64 = 4;
+
+/// Generates the code of a function that has a body.
+pub fn generate(module: &mut ModuleGenerator, ident: &str, function: &ir::FunctionNode) -> String {
+    let mut generator = FunctionGenerator {
+        module,
+        function,
+        body: String::new(),
+        depth: 2,
+        places: HashMap::new(),
+        locals: Vec::new(),
+        frame_size: 0,
+        label_count: 0,
+        break_labels: Vec::new(),
+        continue_labels: Vec::new(),
+    };
+    generator.assign_places();
+    let return_class = generator.class(&function.return_type);
+
+    generator.open("block $return");
+    generator.block(function.body.as_ref().unwrap());
+    generator.close();
+
+    let settings = generator.module.settings;
+    let frame_size = generator.frame_size.next_multiple_of(16);
+
+    let mut out = String::from("\n");
+    let span = &generator.module.source[std::ops::Range::from(function.original_span)];
+    for line in function
+        .comments
+        .iter()
+        .flat_map(|c| c.lines())
+        .chain(span.lines())
+    {
+        writeln!(out, "  ;; {line}").unwrap();
+    }
+
+    // Unlike imports, the params are named (by their index).
+    write!(out, "  (func ${ident} (export \"{ident}\")").unwrap();
+    for (i, param) in function.params.iter().enumerate() {
+        let class = util::scalar_class(&param.ty, settings);
+        write!(out, " (param $p{i} {})", class.val_type()).unwrap();
+    }
+    if function.is_vararg {
+        out += " (param $varargs i32)";
+    }
+    if let Some(class) = return_class {
+        write!(out, " (result {})", class.val_type()).unwrap();
+    }
+    out += "\n";
+
+    if frame_size > 0 {
+        out += "    (local $fp i32)\n";
+    }
+    if let Some(class) = return_class {
+        writeln!(out, "    (local $ret {})", class.val_type()).unwrap();
+    }
+    for (name, class) in &generator.locals {
+        writeln!(out, "    (local {name} {})", class.val_type()).unwrap();
+    }
+
+    if frame_size > 0 {
+        out += "    global.get $__stack_pointer\n";
+        writeln!(out, "    i32.const {frame_size}").unwrap();
+        out += "    i32.sub\n";
+        out += "    local.tee $fp\n";
+        out += "    global.set $__stack_pointer\n";
+    }
+    out += &generator.param_stores();
+    out += &generator.body;
+    if frame_size > 0 {
+        out += "    local.get $fp\n";
+        writeln!(out, "    i32.const {frame_size}").unwrap();
+        out += "    i32.add\n";
+        out += "    global.set $__stack_pointer\n";
+    }
+    // Falling of the end of a function returns 0 (locals start at zero), which is needed for
+    // main and harmless for the others.
+    if return_class.is_some() {
+        out += "    local.get $ret\n";
+    }
+    out += "  )\n";
+    out
+}
+
+/// Where a local variable is stored.
+#[derive(Debug, Clone)]
+enum Place {
+    /// A WebAssembly local (or param) with the given name.
+    Local(String),
+    /// A slot in the stack frame, at the given offset from `$fp`.
+    Frame(u64),
+}
+
+/// An lvalue that is being accessed.
+enum Lvalue {
+    /// A WebAssembly local with the given name.
+    Local(String),
+    /// A location in memory. The address is on the operand stack, the offset still needs to be
+    /// added to it.
+    Memory(u64),
+}
+
+struct FunctionGenerator<'m, 'a> {
+    module: &'m mut ModuleGenerator<'a>,
+    function: &'m ir::FunctionNode,
+    body: String,
+    /// The nesting depth of the instructions, used for indentation.
+    depth: usize,
+    places: HashMap<ItemId, Place>,
+    /// The locals that aren't params, with their class.
+    locals: Vec<(String, Class)>,
+    frame_size: u64,
+    label_count: usize,
+    break_labels: Vec<String>,
+    continue_labels: Vec<String>,
+}
+
+impl<'m, 'a> FunctionGenerator<'m, 'a> {
+    /// Decides where every variable is stored. Scalars whose address is never taken are kept in
+    /// WebAssembly locals, everything else gets a slot in the stack frame.
+    fn assign_places(&mut self) {
+        let settings = self.module.settings;
+        for (i, param) in self.function.params.iter().enumerate() {
+            let Some(id) = param.ident else { continue };
+            if !self.function.table.get(id).needs_address {
+                self.places.insert(id, Place::Local(format!("$p{i}")));
+            }
+        }
+
+        for (id, item) in self.function.table.iter() {
+            if self.places.contains_key(&id) {
+                continue;
+            }
+            let place = match util::ctype_class(&item.ty, settings) {
+                Some(class) if !item.needs_address => {
+                    let name = format!("$l{}", self.locals.len());
+                    self.locals.push((name.clone(), class));
+                    Place::Local(name)
+                }
+                _ => {
+                    let size = util::ctype_size(&item.ty, settings);
+                    let align = util::ctype_align(&item.ty, settings);
+                    Place::Frame(self.allocate_frame(size, align))
+                }
+            };
+            self.places.insert(id, place);
+        }
+    }
+
+    /// Returns the instructions that copy the params stored in the stack frame to their slot.
+    fn param_stores(&self) -> String {
+        let mut out = String::new();
+        for (i, param) in self.function.params.iter().enumerate() {
+            let Some(id) = param.ident else { continue };
+            if let Place::Frame(offset) = self.places[&id] {
+                let class = util::scalar_class(&param.ty, self.module.settings);
+                out += "    local.get $fp\n";
+                writeln!(out, "    local.get $p{i}").unwrap();
+                writeln!(out, "    {}{}", class.store(), memarg(offset)).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Reserves `size` bytes in the stack frame and returns their offset from `$fp`.
+    fn allocate_frame(&mut self, size: u64, align: u64) -> u64 {
+        let offset = self.frame_size.next_multiple_of(align);
+        self.frame_size = offset + size;
+        offset
+    }
+
+    fn new_temp(&mut self, class: Class) -> String {
+        let name = format!("$t{}", self.locals.len());
+        self.locals.push((name.clone(), class));
+        name
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label_count += 1;
+        format!("$L{}", self.label_count)
+    }
+
+    fn emit(&mut self, instruction: impl AsRef<str>) {
+        for _ in 0..self.depth {
+            self.body += "  ";
+        }
+        self.body += instruction.as_ref();
+        self.body.push('\n');
+    }
+
+    /// Emits the start of a `block`, `loop` or `if`, and indents the instructions that follow.
+    fn open(&mut self, instruction: impl AsRef<str>) {
+        self.emit(instruction);
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.emit("end");
+    }
+
+    fn class(&self, ty: &CType) -> Option<Class> {
+        util::ctype_class(ty, self.module.settings)
+    }
+
+    fn block(&mut self, block: &ir::BlockNode) {
+        for stmt_node in &block.stmts {
+            self.stmt(stmt_node);
+        }
+    }
+
+    fn stmt(&mut self, stmt_node: &ir::StmtNode) {
+        let source = self.module.source;
+        for line in stmt_node
+            .comments
+            .iter()
+            .flat_map(|c| c.lines())
+            .chain(source[std::ops::Range::from(stmt_node.span)].lines())
+        {
+            self.emit(format!(";; {line}"));
+        }
+        match &stmt_node.stmt {
+            ir::Stmt::Expr(node) => {
+                self.expr(node);
+                if node.ty != CType::Void {
+                    self.emit("drop");
+                }
+            }
+            ir::Stmt::IfStmt(node) => self.if_stmt(node),
+            ir::Stmt::SwitchStmt(node) => self.switch_stmt(node),
+            ir::Stmt::LoopStmt(node) => self.loop_stmt(node),
+            ir::Stmt::Break => {
+                let label = self.break_labels.last().unwrap().clone();
+                self.emit(format!("br {label}"));
+            }
+            ir::Stmt::Continue => {
+                let label = self.continue_labels.last().unwrap().clone();
+                self.emit(format!("br {label}"));
+            }
+            ir::Stmt::Return(node) => {
+                if let Some(node) = node {
+                    self.expr(node);
+                    self.emit("local.set $ret");
+                }
+                self.emit("br $return");
+            }
+        }
+    }
+
+    fn if_stmt(&mut self, node: &ir::IfStmtNode) {
+        self.condition(&node.condition);
+        self.open("if");
+        self.block(&node.if_branch);
+        if let Some(else_branch) = &node.else_branch {
+            self.depth -= 1;
+            self.emit("else");
+            self.depth += 1;
+            self.block(else_branch);
+        }
+        self.close();
+    }
+
+    /// Every case gets a block, nested so that breaking out of the block of a case ends up at the
+    /// start of its body. The bodies are placed in order so that cases fall through.
+    fn switch_stmt(&mut self, node: &ir::SwitchStmtNode) {
+        let class = self.class(&node.expr.ty).unwrap();
+        let end_label = self.new_label();
+        let case_labels: Vec<_> = node.cases.iter().map(|_| self.new_label()).collect();
+
+        let mut default_label = &end_label;
+        let mut values = Vec::new();
+        for (case, label) in node.cases.iter().zip(&case_labels) {
+            match &case.data {
+                ir::SwitchStmtCase::Case { label: value, .. } => {
+                    // Compared as the 32-bit value, but ordered by the value of the C type.
+                    let value = class.normalize(*value);
+                    let ordered = match class {
+                        Class::Int { signed: false, .. } => value as u32 as i64,
+                        _ => value as i64,
+                    };
+                    values.push((ordered, value, label));
+                }
+                ir::SwitchStmtCase::Default { .. } => default_label = label,
+            }
+        }
+
+        self.open(format!("block {end_label}"));
+        for label in case_labels.iter().rev() {
+            self.open(format!("block {label}"));
+        }
+
+        self.expr(&node.expr);
+        let min = values.iter().map(|(ordered, ..)| *ordered).min();
+        let max = values.iter().map(|(ordered, ..)| *ordered).max();
+        match min.zip(max) {
+            Some((min, max)) if max - min < MAX_TABLE_ENTRIES_PER_CASE * values.len() as i64 => {
+                if min != 0 {
+                    self.emit(format!("i32.const {}", min as i32));
+                    self.emit("i32.sub");
+                }
+                let mut targets = Vec::new();
+                for index in min..=max {
+                    let label = values
+                        .iter()
+                        .find(|(ordered, ..)| *ordered == index)
+                        .map_or(default_label, |(.., label)| label);
+                    targets.push(label.as_str());
+                }
+                targets.push(default_label);
+                self.emit(format!("br_table {}", targets.join(" ")));
+            }
+            _ => {
+                let temp = self.new_temp(class);
+                self.emit(format!("local.set {temp}"));
+                for (_, value, label) in &values {
+                    self.emit(format!("local.get {temp}"));
+                    self.emit(format!("i32.const {value}"));
+                    self.emit("i32.eq");
+                    self.emit(format!("br_if {label}"));
+                }
+                self.emit(format!("br {default_label}"));
+            }
+        }
+
+        self.break_labels.push(end_label);
+        for case in &node.cases {
+            self.close();
+            match &case.data {
+                ir::SwitchStmtCase::Case { body, .. } | ir::SwitchStmtCase::Default { body } => {
+                    self.block(body)
+                }
+            }
+        }
+        self.break_labels.pop();
+        self.close();
+    }
+
+    fn loop_stmt(&mut self, node: &ir::LoopStmtNode) {
+        let break_label = self.new_label();
+        let loop_label = self.new_label();
+        let continue_label = self.new_label();
+
+        self.open(format!("block {break_label}"));
+        self.open(format!("loop {loop_label}"));
+        if let Some(condition) = &node.condition {
+            self.condition(condition);
+            self.emit("i32.eqz");
+            self.emit(format!("br_if {break_label}"));
+        }
+
+        self.break_labels.push(break_label);
+        self.continue_labels.push(continue_label.clone());
+        self.open(format!("block {continue_label}"));
+        self.block(&node.body);
+        self.close();
+        self.break_labels.pop();
+        self.continue_labels.pop();
+
+        if let Some(continuation) = &node.continuation {
+            self.expr(continuation);
+            if continuation.ty != CType::Void {
+                self.emit("drop");
+            }
+        }
+        self.emit(format!("br {loop_label}"));
+        self.close();
+        self.close();
+    }
+
+    /// Evaluates the scalar expression to an `i32` that is nonzero if the expression is.
+    fn condition(&mut self, node: &ir::ExprNode) {
+        self.expr(node);
+        if self.class(&node.ty).unwrap().is_floating() {
+            self.truthy(&node.ty);
+        }
+    }
+
+    /// Converts the scalar value of type `ty` to 1 if it's nonzero, and to 0 otherwise.
+    fn truthy(&mut self, ty: &CType) {
+        match self.class(ty).unwrap() {
+            Class::Int { .. } => {
+                self.emit("i32.eqz");
+                self.emit("i32.eqz");
+            }
+            class => {
+                // NaN is unequal to everything and counts as true.
+                let prefix = class.val_type();
+                self.emit(format!("{prefix}.const 0"));
+                self.emit(format!("{prefix}.ne"));
+            }
+        }
+    }
+
+    /// Sign- or zero-extends the integer of class `class` to 32 bits.
+    fn normalize(&mut self, class: Class) {
+        match class {
+            Class::Int {
+                size: 1,
+                signed: true,
+            } => self.emit("i32.extend8_s"),
+            Class::Int {
+                size: 1,
+                signed: false,
+            } => {
+                self.emit("i32.const 255");
+                self.emit("i32.and");
+            }
+            Class::Int {
+                size: 2,
+                signed: true,
+            } => self.emit("i32.extend16_s"),
+            Class::Int {
+                size: 2,
+                signed: false,
+            } => {
+                self.emit("i32.const 65535");
+                self.emit("i32.and");
+            }
+            _ => {}
+        }
+    }
+
+    /// Evaluates the expression, the value ends up on the operand stack.
+    fn expr(&mut self, node: &ir::ExprNode) {
+        match &node.expr {
+            ir::Expr::LvalueDeref(lvalue) => match self.class(&lvalue.ty) {
+                Some(class) => match self.lvalue(lvalue) {
+                    Lvalue::Local(name) => self.emit(format!("local.get {name}")),
+                    Lvalue::Memory(offset) => {
+                        self.emit(format!("{}{}", class.load(), memarg(offset)))
+                    }
+                },
+                // Arrays decay to the address of their first element.
+                None => self.reference(lvalue),
+            },
+            ir::Expr::Constant(constant) => self.constant(&node.ty, constant),
+            ir::Expr::FunctionCall(ident, args) => self.call(ident, args),
+            ir::Expr::PostfixInc(lvalue) => self.inc_dec(lvalue, true, false),
+            ir::Expr::PostfixDec(lvalue) => self.inc_dec(lvalue, false, false),
+            ir::Expr::PrefixInc(lvalue) => self.inc_dec(lvalue, true, true),
+            ir::Expr::PrefixDec(lvalue) => self.inc_dec(lvalue, false, true),
+            ir::Expr::Reference(lvalue) => self.reference(lvalue),
+            ir::Expr::UnaryArith(op, inner) => self.unary(&node.ty, op, inner),
+            ir::Expr::Binary(lhs, op, rhs) => self.binary(&node.ty, lhs, op, rhs),
+            ir::Expr::Relation(lhs, op, rhs) => self.relation(lhs, op, rhs),
+            ir::Expr::LogicalAnd(lhs, rhs) => self.logical(lhs, rhs, true),
+            ir::Expr::LogicalOr(lhs, rhs) => self.logical(lhs, rhs, false),
+            ir::Expr::Assign(lvalue, value) => {
+                let class = self.class(&lvalue.ty).unwrap();
+                match self.lvalue(lvalue) {
+                    Lvalue::Local(name) => {
+                        self.expr(value);
+                        self.emit(format!("local.tee {name}"));
+                    }
+                    Lvalue::Memory(offset) => {
+                        let temp = self.new_temp(class);
+                        self.expr(value);
+                        self.emit(format!("local.tee {temp}"));
+                        self.emit(format!("{}{}", class.store(), memarg(offset)));
+                        self.emit(format!("local.get {temp}"));
+                    }
+                }
+            }
+            ir::Expr::Cast(inner) => self.cast(&node.ty, inner),
+        }
+    }
+
+    /// Starts accessing the lvalue. For lvalues in memory, this pushes their address.
+    fn lvalue(&mut self, lvalue: &ir::LvalueExprNode) -> Lvalue {
+        match &lvalue.expr {
+            ir::LvalueExpr::Ident(id) => match &self.places[id] {
+                Place::Local(name) => Lvalue::Local(name.clone()),
+                Place::Frame(offset) => {
+                    let offset = *offset;
+                    self.emit("local.get $fp");
+                    Lvalue::Memory(offset)
+                }
+            },
+            ir::LvalueExpr::GlobalIdent(ident) => {
+                let address = self.module.globals[ident.as_str()];
+                self.emit(format!("i32.const {address}"));
+                Lvalue::Memory(0)
+            }
+            ir::LvalueExpr::Dereference(pointer) => {
+                self.expr(pointer);
+                Lvalue::Memory(0)
+            }
+        }
+    }
+
+    /// Computes the address of the lvalue.
+    fn reference(&mut self, lvalue: &ir::LvalueExprNode) {
+        match self.lvalue(lvalue) {
+            Lvalue::Local(name) => {
+                panic!("ICE: taking the address of {name}, which isn't in memory")
+            }
+            Lvalue::Memory(0) => {}
+            Lvalue::Memory(offset) => {
+                self.emit(format!("i32.const {offset}"));
+                self.emit("i32.add");
+            }
+        }
+    }
+
+    fn constant(&mut self, ty: &CType, constant: &ir::Constant) {
+        match (self.class(ty).unwrap(), constant) {
+            (class @ Class::Int { .. }, ir::Constant::Integer(value)) => {
+                self.emit(format!("i32.const {}", class.normalize(*value)));
+            }
+            (class @ Class::Int { .. }, ir::Constant::Float(value)) => {
+                self.emit(format!("i32.const {}", class.normalize(*value as i128)));
+            }
+            (Class::Int { .. }, ir::Constant::String(string)) => {
+                let address = self.module.add_string(string);
+                self.emit(format!("i32.const {address}"));
+            }
+            (class, ir::Constant::Float(value)) => self.float_const(class, *value),
+            (class, ir::Constant::Integer(value)) => self.float_const(class, *value as f64),
+            (_, ir::Constant::String(_)) => panic!("ICE: string constant of type {ty}"),
+        }
+    }
+
+    fn float_const(&mut self, class: Class, value: f64) {
+        let literal = util::float_literal(value, class);
+        self.emit(format!("{}.const {literal}", class.val_type()));
+    }
+
+    fn call(&mut self, ident: &str, args: &[ir::ExprNode]) {
+        let ir = self.module.ir;
+        let (ident, callee) = ir.functions.get_key_value(ident).unwrap();
+        if callee.body.is_none() {
+            self.module.imports.insert(ident);
+        }
+
+        let (fixed, varargs) = args.split_at(callee.params.len());
+        for arg in fixed {
+            self.expr(arg);
+        }
+
+        if callee.is_vararg {
+            if varargs.is_empty() {
+                self.emit("i32.const 0");
+            } else {
+                // Every argument is aligned to its size, in a buffer that is aligned to 8 bytes.
+                let classes: Vec<_> = varargs
+                    .iter()
+                    .map(|arg| self.class(&arg.ty).unwrap())
+                    .collect();
+                let mut size: u64 = 0;
+                let offsets: Vec<_> = classes
+                    .iter()
+                    .map(|class| {
+                        let offset = size.next_multiple_of(class.size());
+                        size = offset + class.size();
+                        offset
+                    })
+                    .collect();
+                let buffer = self.allocate_frame(size, 8);
+                for ((arg, class), offset) in varargs.iter().zip(classes).zip(offsets) {
+                    self.emit("local.get $fp");
+                    self.expr(arg);
+                    self.emit(format!("{}{}", class.store(), memarg(buffer + offset)));
+                }
+                self.emit("local.get $fp");
+                if buffer > 0 {
+                    self.emit(format!("i32.const {buffer}"));
+                    self.emit("i32.add");
+                }
+            }
+        }
+
+        self.emit(format!("call ${ident}"));
+    }
+
+    fn inc_dec(&mut self, lvalue: &ir::LvalueExprNode, is_inc: bool, is_prefix: bool) {
+        let class = self.class(&lvalue.ty).unwrap();
+        let ty = class.val_type();
+        let op = if is_inc { "add" } else { "sub" };
+        let step = if is_pointer(&lvalue.ty) {
+            util::pointee_size(&lvalue.ty, self.module.settings)
+        } else {
+            1
+        };
+        let step = match class {
+            Class::Int { .. } => format!("i32.const {step}"),
+            _ => format!("{ty}.const 1"),
+        };
+
+        match self.lvalue(lvalue) {
+            Lvalue::Local(name) => {
+                self.emit(format!("local.get {name}"));
+                if !is_prefix {
+                    self.emit(format!("local.get {name}"));
+                }
+                self.emit(step);
+                self.emit(format!("{ty}.{op}"));
+                self.normalize(class);
+                if is_prefix {
+                    self.emit(format!("local.tee {name}"));
+                } else {
+                    self.emit(format!("local.set {name}"));
+                }
+            }
+            Lvalue::Memory(offset) => {
+                let address = self.new_temp(Class::Int {
+                    size: 4,
+                    signed: false,
+                });
+                let value = self.new_temp(class);
+                self.emit(format!("local.tee {address}"));
+                self.emit(format!("local.get {address}"));
+                self.emit(format!("{}{}", class.load(), memarg(offset)));
+                if !is_prefix {
+                    self.emit(format!("local.tee {value}"));
+                }
+                self.emit(step);
+                self.emit(format!("{ty}.{op}"));
+                self.normalize(class);
+                if is_prefix {
+                    self.emit(format!("local.tee {value}"));
+                }
+                self.emit(format!("{}{}", class.store(), memarg(offset)));
+                self.emit(format!("local.get {value}"));
+            }
+        }
+    }
+
+    fn unary(&mut self, ty: &CType, op: &ir::UnaryOp, inner: &ir::ExprNode) {
+        self.expr(inner);
+        match (op, self.class(ty).unwrap()) {
+            (ir::UnaryOp::Neg, class @ Class::Int { .. }) => {
+                self.emit("i32.const -1");
+                self.emit("i32.mul");
+                self.normalize(class);
+            }
+            (ir::UnaryOp::Neg, class) => self.emit(format!("{}.neg", class.val_type())),
+            (ir::UnaryOp::BitNot, class) => {
+                self.emit("i32.const -1");
+                self.emit("i32.xor");
+                self.normalize(class);
+            }
+            (ir::UnaryOp::Not, _) => {
+                self.truthy(&inner.ty);
+                self.emit("i32.eqz");
+            }
+        }
+    }
+
+    fn binary(&mut self, ty: &CType, lhs: &ir::ExprNode, op: &ir::BinaryOp, rhs: &ir::ExprNode) {
+        let class = self.class(ty).unwrap();
+        let settings = self.module.settings;
+
+        if class.is_floating() {
+            self.expr(lhs);
+            self.expr(rhs);
+            let op = match op {
+                ir::BinaryOp::Add => "add",
+                ir::BinaryOp::Sub => "sub",
+                ir::BinaryOp::Mul => "mul",
+                ir::BinaryOp::Div => "div",
+                op => panic!("ICE: {} on floating point values", op.long_name()),
+            };
+            self.emit(format!("{}.{op}", class.val_type()));
+            return;
+        }
+
+        let lhs_is_pointer = is_pointer(&lhs.ty);
+        let rhs_is_pointer = is_pointer(&rhs.ty);
+        let signed = matches!(class, Class::Int { signed: true, .. });
+        self.expr(lhs);
+        if rhs_is_pointer && !lhs_is_pointer {
+            let size = util::pointee_size(&rhs.ty, settings);
+            self.emit(format!("i32.const {size}"));
+            self.emit("i32.mul");
+        }
+        self.expr(rhs);
+        if lhs_is_pointer && !rhs_is_pointer {
+            let size = util::pointee_size(&lhs.ty, settings);
+            self.emit(format!("i32.const {size}"));
+            self.emit("i32.mul");
+        }
+
+        let instruction = match op {
+            ir::BinaryOp::Add => "i32.add",
+            ir::BinaryOp::Sub => "i32.sub",
+            ir::BinaryOp::Mul => "i32.mul",
+            ir::BinaryOp::Div if signed => "i32.div_s",
+            ir::BinaryOp::Div => "i32.div_u",
+            ir::BinaryOp::Rem if signed => "i32.rem_s",
+            ir::BinaryOp::Rem => "i32.rem_u",
+            ir::BinaryOp::ShiftLeft => "i32.shl",
+            // Like the LLVM backend, right shifts are logical.
+            ir::BinaryOp::ShiftRight => "i32.shr_u",
+            ir::BinaryOp::Bitwise(ir::BitwiseOp::And) => "i32.and",
+            ir::BinaryOp::Bitwise(ir::BitwiseOp::Or) => "i32.or",
+            ir::BinaryOp::Bitwise(ir::BitwiseOp::Xor) => "i32.xor",
+        };
+        self.emit(instruction);
+        if lhs_is_pointer && rhs_is_pointer {
+            let size = util::pointee_size(&lhs.ty, settings).max(1);
+            self.emit(format!("i32.const {size}"));
+            self.emit("i32.div_s");
+        }
+        self.normalize(class);
+    }
+
+    fn relation(&mut self, lhs: &ir::ExprNode, op: &ir::RelationOp, rhs: &ir::ExprNode) {
+        let class = self.class(&lhs.ty).unwrap();
+        self.expr(lhs);
+        self.expr(rhs);
+        let op = match op {
+            ir::RelationOp::Eq => "eq",
+            ir::RelationOp::Ne => "ne",
+            ir::RelationOp::Lt => "lt",
+            ir::RelationOp::Le => "le",
+            ir::RelationOp::Gt => "gt",
+            ir::RelationOp::Ge => "ge",
+        };
+        let suffix = match (class, op) {
+            (_, "eq" | "ne") | (Class::Float | Class::Double, _) => "",
+            (Class::Int { signed: true, .. }, _) => "_s",
+            (Class::Int { signed: false, .. }, _) => "_u",
+        };
+        self.emit(format!("{}.{op}{suffix}", class.val_type()));
+    }
+
+    /// Short circuiting `&&` (if `is_and`) or `||`.
+    fn logical(&mut self, lhs: &ir::ExprNode, rhs: &ir::ExprNode, is_and: bool) {
+        self.expr(lhs);
+        self.truthy(&lhs.ty);
+        self.open("if (result i32)");
+        if is_and {
+            self.expr(rhs);
+            self.truthy(&rhs.ty);
+        } else {
+            self.emit("i32.const 1");
+        }
+        self.depth -= 1;
+        self.emit("else");
+        self.depth += 1;
+        if is_and {
+            self.emit("i32.const 0");
+        } else {
+            self.expr(rhs);
+            self.truthy(&rhs.ty);
+        }
+        self.close();
+    }
+
+    fn cast(&mut self, ty: &CType, inner: &ir::ExprNode) {
+        self.expr(inner);
+        let Some(to) = self.class(ty) else { return };
+        // Arrays only appear here after decaying to a pointer, in which case the value is
+        // already the address.
+        let Some(from) = self.class(&inner.ty) else {
+            return;
+        };
+        match (from, to) {
+            (Class::Int { .. }, Class::Int { .. }) => self.normalize(to),
+            (Class::Int { signed, .. }, to) => {
+                let sign = if signed { "s" } else { "u" };
+                self.emit(format!("{}.convert_i32_{sign}", to.val_type()));
+            }
+            (from, Class::Int { size, signed }) => {
+                // Saturating, so out of range values don't trap.
+                let sign = if signed || size < 4 { "s" } else { "u" };
+                self.emit(format!("i32.trunc_sat_{}_{sign}", from.val_type()));
+                self.normalize(to);
+            }
+            (Class::Float, Class::Double) => self.emit("f64.promote_f32"),
+            (Class::Double, Class::Float) => self.emit("f32.demote_f64"),
+            _ => {}
+        }
+    }
+}
+
+fn is_pointer(ty: &CType) -> bool {
+    matches!(ty, CType::Scalar(crate::ir::ctype::Scalar::Pointer(_)))
+}
+
+/// The immediate of a load or store with the given offset.
+fn memarg(offset: u64) -> String {
+    match offset {
+        0 => String::new(),
+        offset => format!(" offset={offset}"),
+    }
+}
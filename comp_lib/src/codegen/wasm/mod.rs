@@ -0,0 +1,221 @@
+//! Generates WebAssembly in the text format (WAT), e.g. to run programs in a browser.
+//!
+//! Like the x86-64 backend, expressions are evaluated on the operand stack of the WebAssembly
+//! machine. Scalar locals whose address is never taken are WebAssembly locals, all other locals
+//! live in a stack frame in linear memory. Global variables and string literals are placed in
+//! data segments at the start of the memory, the stack grows down from the end of it.
+//!
+//! Functions that are declared but not defined (like `printf` and `scanf`) are imported from the
+//! `env` module. Variadic arguments are stored in a buffer in the stack frame of the caller, and a
+//! pointer to that buffer is passed after the fixed arguments (see the README for the layout).
+
+mod function_generator;
+mod util;
+
+use crate::{
+    diagnostic::AggregateResult,
+    ir::{self, ctype::CType},
+    settings::Settings,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use util::Class;
+
+/// The address of the first global variable, low addresses are kept free so that null pointers
+/// never point to valid data.
+const DATA_START: u64 = 1024;
+
+/// The size of the linear memory stack in bytes.
+const STACK_SIZE: u64 = 1 << 20;
+
+const PAGE_SIZE: u64 = 1 << 16;
+
+pub fn build_from_ir(
+    ir: &ir::Root,
+    settings: &Settings,
+    filename: &str,
+    source: &str,
+) -> AggregateResult<String> {
+    AggregateResult::new_ok(ModuleGenerator::generate(ir, settings, filename, source))
+}
+
+struct ModuleGenerator<'a> {
+    ir: &'a ir::Root,
+    settings: &'a Settings,
+    source: &'a str,
+    /// The address of every global variable.
+    globals: HashMap<&'a str, u64>,
+    /// The string literals with their address.
+    strings: Vec<(Vec<u8>, u64)>,
+    /// The first free address after the globals and string literals.
+    data_end: u64,
+    /// The declared functions that are called, these are imported from the host.
+    imports: BTreeSet<&'a str>,
+}
+
+impl<'a> ModuleGenerator<'a> {
+    fn generate(
+        ir: &'a ir::Root,
+        settings: &'a Settings,
+        filename: &str,
+        source: &'a str,
+    ) -> String {
+        let mut generator = Self {
+            ir,
+            settings,
+            source,
+            globals: HashMap::new(),
+            strings: Vec::new(),
+            data_end: DATA_START,
+            imports: BTreeSet::new(),
+        };
+
+        // Sorted to get deterministic output.
+        let mut vars: Vec<_> = ir.vars.iter().collect();
+        vars.sort_unstable_by_key(|(ident, _)| ident.as_str());
+        let mut global_data = String::new();
+        for (ident, var) in vars {
+            global_data += &generator.global_var(ident, var);
+        }
+
+        let mut functions: Vec<_> = ir.functions.iter().collect();
+        functions.sort_unstable_by_key(|(ident, _)| ident.as_str());
+        let mut function_code = String::new();
+        for (ident, function) in functions {
+            if function.body.is_some() {
+                function_code += &function_generator::generate(&mut generator, ident, function);
+            }
+        }
+
+        let mut out = String::new();
+        writeln!(out, ";; {}", filename.escape_default()).unwrap();
+        out += "(module\n";
+        for ident in &generator.imports {
+            let signature = generator.signature(&ir.functions[*ident]);
+            writeln!(
+                out,
+                "  (import \"env\" \"{ident}\" (func ${ident}{signature}))"
+            )
+            .unwrap();
+        }
+
+        let stack_top =
+            (generator.data_end.next_multiple_of(16) + STACK_SIZE).next_multiple_of(PAGE_SIZE);
+        writeln!(
+            out,
+            "  (memory (export \"memory\") {})",
+            stack_top / PAGE_SIZE
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  (global $__stack_pointer (mut i32) (i32.const {stack_top}))"
+        )
+        .unwrap();
+
+        out += &global_data;
+        for (string, address) in &generator.strings {
+            writeln!(
+                out,
+                "  (data (i32.const {address}) \"{}\")",
+                util::escape_bytes(string)
+            )
+            .unwrap();
+        }
+
+        out += &function_code;
+        out += ")\n";
+        out
+    }
+
+    /// Places the global variable in memory and returns its data segment, if it's initialized.
+    fn global_var(&mut self, ident: &'a str, var: &ir::GlobalVarNode) -> String {
+        let size = util::ctype_size(&var.ty, self.settings);
+        let align = util::ctype_align(&var.ty, self.settings);
+        let address = self.allocate(size.max(1), align);
+        self.globals.insert(ident, address);
+
+        let mut out = String::from("\n");
+        let span = &self.source[std::ops::Range::from(var.original_span)];
+        for line in var
+            .comments
+            .iter()
+            .flat_map(|c| c.lines())
+            .chain(span.lines())
+        {
+            writeln!(out, "  ;; {line}").unwrap();
+        }
+        match &var.value {
+            Some(value) => {
+                let bytes = self.constant_data(&var.ty, value, size);
+                writeln!(
+                    out,
+                    "  (data (i32.const {address}) \"{}\")",
+                    util::escape_bytes(&bytes)
+                )
+                .unwrap();
+            }
+            // The memory is zero-initialized.
+            None => writeln!(out, "  ;; {ident} is at {address}").unwrap(),
+        }
+        out
+    }
+
+    /// Returns the bytes of the constant as stored in memory (little-endian).
+    fn constant_data(&mut self, ty: &CType, value: &ir::Constant, size: u64) -> Vec<u8> {
+        match (value, util::ctype_class(ty, self.settings)) {
+            (ir::Constant::String(string), None) => {
+                // A char array initialized by a string literal, the rest is zero.
+                string[..string.len().min(size as usize)].to_vec()
+            }
+            (ir::Constant::String(string), Some(_)) => {
+                (self.add_string(string) as u32).to_le_bytes().to_vec()
+            }
+            (ir::Constant::Float(value), Some(Class::Float)) => {
+                (*value as f32).to_le_bytes().to_vec()
+            }
+            (ir::Constant::Float(value), Some(Class::Double)) => value.to_le_bytes().to_vec(),
+            (ir::Constant::Integer(value), Some(class @ Class::Int { size, .. })) => {
+                class.normalize(*value).to_le_bytes()[..size as usize].to_vec()
+            }
+            (value, _) => panic!("ICE: global of type {ty} can't be initialized with {value:?}"),
+        }
+    }
+
+    /// Adds the string literal to the module and returns its address.
+    fn add_string(&mut self, string: &[u8]) -> u64 {
+        if let Some((_, address)) = self.strings.iter().find(|(s, _)| s == string) {
+            return *address;
+        }
+        let address = self.allocate(string.len() as u64, 1);
+        self.strings.push((string.to_vec(), address));
+        address
+    }
+
+    /// Reserves `size` bytes of memory after the data placed so far.
+    fn allocate(&mut self, size: u64, align: u64) -> u64 {
+        let address = self.data_end.next_multiple_of(align);
+        self.data_end = address + size;
+        address
+    }
+
+    /// Returns the params and result of the function type, e.g. ` (param i32 f64) (result i32)`.
+    /// Variadic functions get an extra param, the pointer to the buffer with the variadic
+    /// arguments.
+    fn signature(&self, function: &ir::FunctionNode) -> String {
+        let mut out = String::new();
+        let params: Vec<_> = function
+            .params
+            .iter()
+            .map(|param| util::scalar_class(&param.ty, self.settings).val_type())
+            .chain(function.is_vararg.then_some("i32"))
+            .collect();
+        if !params.is_empty() {
+            write!(out, " (param {})", params.join(" ")).unwrap();
+        }
+        if let Some(class) = util::ctype_class(&function.return_type, self.settings) {
+            write!(out, " (result {})", class.val_type()).unwrap();
+        }
+        out
+    }
+}
@@ -0,0 +1,183 @@
+use crate::ir::ctype::{self, CType};
+use crate::settings::Settings;
+
+/// How a scalar value is kept on the WebAssembly operand stack. Integers and pointers are `i32`
+/// values, sign- or zero-extended from their size. `float` and `double` map to `f32` and `f64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Int { size: u64, signed: bool },
+    Float,
+    Double,
+}
+
+impl Class {
+    pub fn is_floating(self) -> bool {
+        matches!(self, Class::Float | Class::Double)
+    }
+
+    /// The WebAssembly value type, which is also the prefix of its instructions.
+    pub fn val_type(self) -> &'static str {
+        match self {
+            Class::Int { .. } => "i32",
+            Class::Float => "f32",
+            Class::Double => "f64",
+        }
+    }
+
+    /// The size in bytes of a value of this class in linear memory.
+    pub fn size(self) -> u64 {
+        match self {
+            Class::Int { size, .. } => size,
+            Class::Float => 4,
+            Class::Double => 8,
+        }
+    }
+
+    /// Converts the value to the 32-bit representation of this class, i.e. truncates and then
+    /// sign- or zero-extends it.
+    pub fn normalize(self, value: i128) -> i32 {
+        match self {
+            Class::Int {
+                size: 1,
+                signed: true,
+            } => value as i8 as i32,
+            Class::Int {
+                size: 1,
+                signed: false,
+            } => value as u8 as i32,
+            Class::Int {
+                size: 2,
+                signed: true,
+            } => value as i16 as i32,
+            Class::Int {
+                size: 2,
+                signed: false,
+            } => value as u16 as i32,
+            _ => value as i32,
+        }
+    }
+
+    /// The instruction that loads a value of this class from linear memory.
+    pub fn load(self) -> &'static str {
+        match self {
+            Class::Int {
+                size: 1,
+                signed: true,
+            } => "i32.load8_s",
+            Class::Int {
+                size: 1,
+                signed: false,
+            } => "i32.load8_u",
+            Class::Int {
+                size: 2,
+                signed: true,
+            } => "i32.load16_s",
+            Class::Int {
+                size: 2,
+                signed: false,
+            } => "i32.load16_u",
+            Class::Int { .. } => "i32.load",
+            Class::Float => "f32.load",
+            Class::Double => "f64.load",
+        }
+    }
+
+    /// The instruction that stores a value of this class in linear memory.
+    pub fn store(self) -> &'static str {
+        match self {
+            Class::Int { size: 1, .. } => "i32.store8",
+            Class::Int { size: 2, .. } => "i32.store16",
+            Class::Int { .. } => "i32.store",
+            Class::Float => "f32.store",
+            Class::Double => "f64.store",
+        }
+    }
+}
+
+/// Returns the class of a scalar type, or `None` for void and aggregates.
+pub fn ctype_class(ctype: &CType, settings: &Settings) -> Option<Class> {
+    match ctype {
+        CType::Scalar(ctype::Scalar::Arithmetic(arithmetic)) => Some(match arithmetic {
+            ctype::Arithmetic::Float => Class::Float,
+            ctype::Arithmetic::Double | ctype::Arithmetic::LongDouble => Class::Double,
+            _ => Class::Int {
+                size: arithmetic.size_in_bits(settings) as u64 / 8,
+                signed: arithmetic.is_signed(),
+            },
+        }),
+        CType::Scalar(ctype::Scalar::Pointer(_)) => Some(Class::Int {
+            size: 4,
+            signed: false,
+        }),
+        CType::Aggregate(_) | CType::Void => None,
+    }
+}
+
+/// Same as [`ctype_class`], but panics if the type isn't a scalar.
+pub fn scalar_class(ctype: &CType, settings: &Settings) -> Class {
+    ctype_class(ctype, settings).unwrap_or_else(|| panic!("ICE: {ctype} should be a scalar type"))
+}
+
+/// Returns the size in bytes of the type.
+pub fn ctype_size(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Scalar(_) => scalar_class(ctype, settings).size(),
+        CType::Aggregate(ctype::Aggregate::Array(array)) => {
+            ctype_size(&array.inner, settings) * array.length as u64
+        }
+        CType::Void => 0,
+    }
+}
+
+/// Returns the alignment in bytes of the type.
+pub fn ctype_align(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Aggregate(ctype::Aggregate::Array(array)) => ctype_align(&array.inner, settings),
+        CType::Void => 1,
+        scalar => ctype_size(scalar, settings),
+    }
+}
+
+/// Returns the size in bytes of the type a pointer points to. Panics if the type isn't a pointer.
+pub fn pointee_size(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Scalar(ctype::Scalar::Pointer(ctype::Pointer { inner, .. })) => {
+            ctype_size(inner, settings)
+        }
+        _ => panic!("ICE: {ctype} should be a pointer type"),
+    }
+}
+
+/// Formats a floating point value as a WebAssembly literal that converts back to the same value.
+pub fn float_literal(value: f64, class: Class) -> String {
+    // Converting to `f32` first, since a large double can become infinite as a float.
+    let rounded = match class {
+        Class::Float => value as f32 as f64,
+        _ => value,
+    };
+    if rounded.is_nan() {
+        "nan".to_owned()
+    } else if rounded.is_infinite() {
+        if rounded > 0.0 { "inf" } else { "-inf" }.to_owned()
+    } else if class == Class::Float {
+        format!("{:e}", value as f32)
+    } else {
+        format!("{value:e}")
+    }
+}
+
+/// Escapes bytes for a WebAssembly string literal.
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            b' '..=b'~' => out.push(byte as char),
+            _ => out += &format!("\\{byte:02x}"),
+        }
+    }
+    out
+}
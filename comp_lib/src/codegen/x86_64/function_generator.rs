@@ -0,0 +1,1223 @@
+use super::util::{self, Class};
+use super::ModuleGenerator;
+use crate::ir::{self, ctype, ctype::CType, table::ItemId};
+use mips_ir as mir;
+use mir::x86_64::{self as x86, Instruction, Terminator, X86_64};
+use mir::BlockRef;
+use std::collections::HashSet;
+
+/// Generates the assembly of a function that has a body.
+pub fn generate(module: &mut ModuleGenerator, ident: &str, function: &ir::FunctionNode) -> String {
+    let mut out = String::from("\n");
+    let span = &module.source[std::ops::Range::from(function.original_span)];
+    for line in function
+        .comments
+        .iter()
+        .flat_map(|c| c.lines())
+        .chain(span.lines())
+    {
+        out += "# ";
+        out += line;
+        out.push('\n');
+    }
+
+    let mut function = FunctionGenerator::new(module, ident, function).generate();
+    x86::compile(&mut function);
+    x86::X86_64Outputter::new(&mut out)
+        .with_comments(true)
+        .write_function(&function)
+        .unwrap();
+    out
+}
+
+struct FunctionGenerator<'m, 'a> {
+    module: &'m mut ModuleGenerator<'a>,
+    function: mir::Function<X86_64>,
+    ir: &'m ir::FunctionNode,
+    register_classes: ir::table::Table<Class>,
+    /// The variables whose address is taken. They're kept in a stack slot, and their register
+    /// holds the address of the slot.
+    reference_values: HashSet<ItemId>,
+    next_reg: u32,
+    next_float_reg: u32,
+    next_double_reg: u32,
+}
+
+#[derive(Debug)]
+struct Builder {
+    bb: mir::BBBuilder<X86_64>,
+    var_registers: ir::table::Table<mir::AnyReg>,
+    expr_res_stack: Vec<(mir::AnyReg, Class)>,
+    continue_label: Option<mir::BlockId>,
+    break_label: Option<mir::BlockId>,
+}
+
+/// Where the value of an lvalue is.
+#[derive(Debug, Clone, Copy)]
+enum Lvalue {
+    /// In memory at the address in the register.
+    Address(mir::Reg),
+    /// In the register of the variable.
+    Reg(ItemId),
+}
+
+impl Builder {
+    fn get_all_registers(&self) -> Vec<mir::AnyReg> {
+        self.var_registers
+            .items()
+            .cloned()
+            .chain(self.expr_res_stack.iter().map(|(reg, _)| *reg))
+            .collect()
+    }
+
+    fn create_block_ref(&self, label: mir::BlockId) -> BlockRef {
+        BlockRef::new(label, self.get_all_registers())
+    }
+
+    /// Keeps the value alive while other expressions are generated, which may start new blocks.
+    fn push_to_expr_stack(&mut self, reg: mir::AnyReg, class: Class) {
+        self.expr_res_stack.push((reg, class));
+    }
+
+    fn pop_off_expr_stack(&mut self) -> mir::AnyReg {
+        self.expr_res_stack.pop().unwrap().0
+    }
+}
+
+impl<'m, 'a> FunctionGenerator<'m, 'a> {
+    fn new(module: &'m mut ModuleGenerator<'a>, ident: &str, ir: &'m ir::FunctionNode) -> Self {
+        let settings = module.settings;
+        let mut reference_values = HashSet::new();
+        let register_classes = ir.table.map_with_id(|id, item| {
+            if item.needs_address {
+                reference_values.insert(id);
+                Class::POINTER
+            } else {
+                util::scalar_class(&item.ty, settings)
+            }
+        });
+
+        let params_info = ir
+            .params
+            .iter()
+            .map(|param| util::stack_info(&param.ty, settings))
+            .collect();
+
+        Self {
+            module,
+            function: mir::Function::for_arch(ident.into(), params_info),
+            ir,
+            register_classes,
+            reference_values,
+            next_reg: 0,
+            next_float_reg: 0,
+            next_double_reg: 0,
+        }
+    }
+
+    fn generate(mut self) -> mir::Function<X86_64> {
+        let var_registers = self
+            .register_classes
+            .clone()
+            .map(|class| self.new_register_of_class(*class));
+
+        // The params whose address is taken are passed in a new register, and stored in their
+        // slot below.
+        let params: Vec<_> = self
+            .ir
+            .params
+            .iter()
+            .map(|param| match param.ident {
+                Some(id) if !self.reference_values.contains(&id) => *var_registers.get(id),
+                _ => self.new_register_of_class(self.class(&param.ty)),
+            })
+            .collect();
+
+        let bbbuilder = self.function.start_entry_block(params.clone());
+        let mut builder = Builder {
+            bb: bbbuilder,
+            var_registers,
+            expr_res_stack: Vec::new(),
+            continue_label: None,
+            break_label: None,
+        };
+
+        // Every variable gets a value in the entry block, so it's defined on every path.
+        let param_ids: HashSet<_> = self.ir.params.iter().filter_map(|p| p.ident).collect();
+        for (id, item) in self.ir.table.iter() {
+            let reg = *builder.var_registers.get(id);
+            if self.reference_values.contains(&id) {
+                let stack_info = util::stack_info(&item.ty, self.module.settings);
+                let slot = self.function.create_stack_slot(stack_info);
+                builder
+                    .bb
+                    .add_instruction(Instruction::LoadStackAddress(as_reg(reg), slot));
+            } else if !param_ids.contains(&id) {
+                self.load_zero(&mut builder, reg);
+            }
+        }
+        for (param, &value) in self.ir.params.iter().zip(&params) {
+            let Some(id) = param.ident else { continue };
+            if self.reference_values.contains(&id) {
+                let address = as_reg(*builder.var_registers.get(id));
+                self.store(&mut builder, &param.ty, value, address);
+            }
+        }
+
+        if let Some(block) = &self.ir.body {
+            builder = self.add_ir_block_node(builder, block);
+        }
+
+        // Falling off the end of a function returns 0, which is needed for main and harmless for
+        // the others. Implicit floating point returns are undefined.
+        let implicit_return_value = match self.ir.return_type {
+            CType::Void => None,
+            ref ty => match self.class(ty) {
+                Class::Int { .. } => {
+                    let reg = self.new_register();
+                    builder.bb.add_instruction(Instruction::LoadImm(reg, 0));
+                    Some(reg.into())
+                }
+                Class::Float | Class::Double => None,
+            },
+        };
+        self.function.add_block(
+            builder
+                .bb
+                .terminate(Terminator::Return(implicit_return_value)),
+        );
+        self.function
+    }
+
+    /// Creates a new builder with arguments for all the items, taking the continue and break
+    /// labels from the given `prev_builder`
+    fn create_new_builder(&mut self, prev_builder: &Builder) -> (mir::BlockId, Builder) {
+        let label = self.function.create_block_label();
+        (label, self.create_builder_with_label(label, prev_builder))
+    }
+
+    /// Creates a new builder with arguments for all the items, taking the continue and break
+    /// labels from the given `prev_builder`
+    fn create_builder_with_label(
+        &mut self,
+        label: mir::BlockId,
+        prev_builder: &Builder,
+    ) -> Builder {
+        let registers = self
+            .register_classes
+            .clone()
+            .map(|class| self.new_register_of_class(*class));
+
+        let expr_regs: Vec<_> = prev_builder
+            .expr_res_stack
+            .iter()
+            .map(|(_, class)| (self.new_register_of_class(*class), *class))
+            .collect();
+
+        let new_block_builder = self.function.start_block(
+            label,
+            registers
+                .items()
+                .cloned()
+                .chain(expr_regs.iter().map(|(reg, _)| *reg))
+                .collect(),
+        );
+
+        Builder {
+            bb: new_block_builder,
+            var_registers: registers,
+            expr_res_stack: expr_regs,
+            continue_label: prev_builder.continue_label,
+            break_label: prev_builder.break_label,
+        }
+    }
+
+    fn add_ir_block_node(&mut self, mut builder: Builder, block_node: &ir::BlockNode) -> Builder {
+        for stmt_node in &block_node.stmts {
+            builder = self.add_ir_stmt_node(builder, stmt_node);
+        }
+        builder
+    }
+
+    fn add_ir_stmt_node(&mut self, mut builder: Builder, stmt_node: &ir::StmtNode) -> Builder {
+        if let Some(comments) = stmt_node.comments.clone() {
+            builder.bb.add_instruction(Instruction::Comment(comments));
+        }
+        let source = self.module.source;
+        for line in source[std::ops::Range::from(stmt_node.span)].lines() {
+            builder
+                .bb
+                .add_instruction(Instruction::Comment(line.to_string()));
+        }
+        match &stmt_node.stmt {
+            ir::Stmt::Expr(node) => self.add_ir_expr_node(builder, node).0,
+            ir::Stmt::IfStmt(node) => self.add_ir_if(builder, node),
+            ir::Stmt::SwitchStmt(node) => self.add_ir_switch(builder, node),
+            ir::Stmt::LoopStmt(node) => self.add_ir_loop(builder, node),
+            ir::Stmt::Break => self.add_ir_break(builder),
+            ir::Stmt::Continue => self.add_ir_continue(builder),
+            ir::Stmt::Return(node) => self.add_ir_return(builder, node.as_ref()),
+        }
+    }
+
+    /// Generates the expression. Integers and pointers end up in a general purpose register,
+    /// sign- or zero-extended to 64 bits, floating point values in an sse register.
+    fn add_ir_expr_node(
+        &mut self,
+        mut builder: Builder,
+        expr_node: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        use ir::Expr as E;
+        match &expr_node.expr {
+            E::LvalueDeref(lvalue) => self.add_ir_lvalue_deref(builder, lvalue),
+            E::Constant(constant) => {
+                let value = self.add_ir_constant(&mut builder, constant, &expr_node.ty);
+                (builder, value)
+            }
+            E::FunctionCall(ident, arguments) => {
+                self.add_ir_function_call(builder, ident, arguments, &expr_node.ty)
+            }
+            E::PostfixInc(lvalue) => self.add_ir_inc_dec(builder, lvalue, true, false),
+            E::PostfixDec(lvalue) => self.add_ir_inc_dec(builder, lvalue, false, false),
+            E::PrefixInc(lvalue) => self.add_ir_inc_dec(builder, lvalue, true, true),
+            E::PrefixDec(lvalue) => self.add_ir_inc_dec(builder, lvalue, false, true),
+            E::Reference(lvalue) => match self.add_ir_lvalue_node(builder, lvalue) {
+                (builder, Lvalue::Address(reg)) => (builder, reg.into()),
+                (_, Lvalue::Reg(_)) => {
+                    unreachable!("ICE: created a register lvalue, but still needed to get address")
+                }
+            },
+            E::UnaryArith(op, inner) => self.add_ir_unary(builder, &expr_node.ty, op, inner),
+            E::Binary(lhs, op, rhs) => self.add_ir_binary(builder, &expr_node.ty, lhs, op, rhs),
+            E::Relation(lhs, op, rhs) => self.add_ir_relation(builder, lhs, op, rhs),
+            E::LogicalAnd(lhs, rhs) => self.add_ir_logical(builder, lhs, rhs, true),
+            E::LogicalOr(lhs, rhs) => self.add_ir_logical(builder, lhs, rhs, false),
+            E::Assign(lvalue, value) => self.add_ir_assign(builder, lvalue, value),
+            E::Cast(inner) => self.add_ir_cast(builder, inner, &expr_node.ty),
+        }
+    }
+
+    fn add_ir_lvalue_deref(
+        &mut self,
+        builder: Builder,
+        lvalue: &ir::LvalueExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        let (mut builder, lvalue_value) = self.add_ir_lvalue_node(builder, lvalue);
+        let value = match lvalue_value {
+            // Arrays decay to the address of their first element.
+            Lvalue::Address(address)
+                if util::ctype_class(&lvalue.ty, self.settings()).is_none() =>
+            {
+                address.into()
+            }
+            lvalue_value => self.read_lvalue(&mut builder, lvalue_value, &lvalue.ty),
+        };
+        (builder, value)
+    }
+
+    fn add_ir_constant(
+        &mut self,
+        builder: &mut Builder,
+        constant: &ir::Constant,
+        ty: &CType,
+    ) -> mir::AnyReg {
+        match (self.class(ty), constant) {
+            (class @ Class::Int { .. }, &ir::Constant::Integer(value)) => {
+                self.load_int(builder, class.normalize(value)).into()
+            }
+            (class @ Class::Int { .. }, &ir::Constant::Float(value)) => self
+                .load_int(builder, class.normalize(value as i128))
+                .into(),
+            (Class::Int { .. }, ir::Constant::String(string)) => {
+                let label = self.module.add_string(string);
+                let reg = self.new_register();
+                builder
+                    .bb
+                    .add_instruction(Instruction::LoadAddress(reg, label.into()));
+                reg.into()
+            }
+            (Class::Float, &ir::Constant::Float(value)) => self
+                .load_float(builder, mir::FFmt::S, (value as f32).to_bits().into())
+                .into(),
+            (Class::Float, &ir::Constant::Integer(value)) => self
+                .load_float(builder, mir::FFmt::S, (value as f32).to_bits().into())
+                .into(),
+            (Class::Double, &ir::Constant::Float(value)) => self
+                .load_float(builder, mir::FFmt::D, value.to_bits())
+                .into(),
+            (Class::Double, &ir::Constant::Integer(value)) => self
+                .load_float(builder, mir::FFmt::D, (value as f64).to_bits())
+                .into(),
+            (_, ir::Constant::String(_)) => panic!("ICE: string constant of type {ty}"),
+        }
+    }
+
+    fn add_ir_function_call(
+        &mut self,
+        mut builder: Builder,
+        ident: &str,
+        arguments: &[ir::ExprNode],
+        to_type: &CType,
+    ) -> (Builder, mir::AnyReg) {
+        let callee = &self.module.ir.functions[ident];
+        let (variadic, external) = (callee.is_vararg, callee.body.is_none());
+
+        for argument in arguments {
+            let (b, value) = self.add_ir_expr_node(builder, argument);
+            builder = b;
+            let class = self.class(&argument.ty);
+            builder.push_to_expr_stack(value, class);
+        }
+        let mut reg_arguments: Vec<_> = arguments
+            .iter()
+            .map(|_| builder.pop_off_expr_stack())
+            .collect();
+        reg_arguments.reverse();
+
+        let return_reg = match to_type {
+            CType::Void => None,
+            ty => Some(self.new_register_of_class(self.class(ty))),
+        };
+        builder.bb.add_instruction(Instruction::Call(x86::Call {
+            label: ident.into(),
+            arguments: reg_arguments,
+            return_reg,
+            variadic,
+            external,
+        }));
+
+        let value = match return_reg {
+            // The callee doesn't have to extend small integers.
+            Some(mir::AnyReg::R(reg)) => self.normalize(&mut builder, reg, to_type).into(),
+            Some(freg) => freg,
+            // This value will not be used, if the lower_ast step did its job correctly.
+            None => self.load_int(&mut builder, 0).into(),
+        };
+        (builder, value)
+    }
+
+    fn add_ir_inc_dec(
+        &mut self,
+        builder: Builder,
+        lvalue: &ir::LvalueExprNode,
+        is_inc: bool,
+        is_prefix: bool,
+    ) -> (Builder, mir::AnyReg) {
+        let (mut builder, lvalue_value) = self.add_ir_lvalue_node(builder, lvalue);
+        let old = self.read_lvalue(&mut builder, lvalue_value, &lvalue.ty);
+        let new = match (self.class(&lvalue.ty), old) {
+            (Class::Int { .. }, mir::AnyReg::R(old)) => {
+                let step = match is_pointer(&lvalue.ty) {
+                    true => util::pointee_size(&lvalue.ty, self.settings()),
+                    false => 1,
+                };
+                let step = self.load_int(&mut builder, step as i64);
+                let op = match is_inc {
+                    true => x86::BinaryOp::Add,
+                    false => x86::BinaryOp::Sub,
+                };
+                let new = self.new_register();
+                builder
+                    .bb
+                    .add_instruction(Instruction::Binary(op, new, old, step));
+                self.normalize(&mut builder, new, &lvalue.ty).into()
+            }
+            (class, mir::AnyReg::F(old)) => {
+                let fmt = class.ffmt();
+                let one = match fmt {
+                    mir::FFmt::S => self.load_float(&mut builder, fmt, 1f32.to_bits().into()),
+                    mir::FFmt::D => self.load_float(&mut builder, fmt, 1f64.to_bits()),
+                };
+                let op = match is_inc {
+                    true => x86::FBinaryOp::Add,
+                    false => x86::FBinaryOp::Sub,
+                };
+                let new = self.new_ffmt_register(fmt);
+                builder
+                    .bb
+                    .add_instruction(Instruction::FBinary(op, fmt, new, old, one));
+                new.into()
+            }
+            _ => unreachable!("ICE: value in a register of the wrong kind"),
+        };
+        self.write_lvalue(&mut builder, lvalue_value, &lvalue.ty, new);
+        (builder, if is_prefix { new } else { old })
+    }
+
+    fn add_ir_unary(
+        &mut self,
+        builder: Builder,
+        ty: &CType,
+        op: &ir::UnaryOp,
+        inner: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        let (mut builder, value) = self.add_ir_expr_node(builder, inner);
+        let value = match (op, value) {
+            (ir::UnaryOp::Neg, mir::AnyReg::R(value)) => {
+                let reg = self.new_register();
+                builder.bb.add_instruction(Instruction::Neg(reg, value));
+                self.normalize(&mut builder, reg, ty).into()
+            }
+            (ir::UnaryOp::Neg, mir::AnyReg::F(value)) => {
+                let fmt = self.class(ty).ffmt();
+                let freg = self.new_ffmt_register(fmt);
+                builder
+                    .bb
+                    .add_instruction(Instruction::FNeg(fmt, freg, value));
+                freg.into()
+            }
+            (ir::UnaryOp::BitNot, mir::AnyReg::R(value)) => {
+                let reg = self.new_register();
+                builder.bb.add_instruction(Instruction::Not(reg, value));
+                self.normalize(&mut builder, reg, ty).into()
+            }
+            (ir::UnaryOp::BitNot, mir::AnyReg::F(_)) => {
+                unreachable!("ICE: bitwise not on a floating point value")
+            }
+            (ir::UnaryOp::Not, mir::AnyReg::R(value)) => {
+                let zero = self.load_int(&mut builder, 0);
+                let reg = self.new_register();
+                builder
+                    .bb
+                    .add_instruction(Instruction::SetCond(x86::Cond::Eq, reg, value, zero));
+                reg.into()
+            }
+            (ir::UnaryOp::Not, mir::AnyReg::F(value)) => {
+                let fmt = self.class(&inner.ty).ffmt();
+                let zero = self.load_float(&mut builder, fmt, 0);
+                let reg = self.new_register();
+                // NaN is unordered, so it counts as true.
+                builder.bb.add_instruction(Instruction::FSetCond(
+                    x86::FCond::Eq,
+                    fmt,
+                    reg,
+                    value,
+                    zero,
+                ));
+                reg.into()
+            }
+        };
+        (builder, value)
+    }
+
+    /// Generates the operands of a binary expression or relation.
+    fn add_ir_operands(
+        &mut self,
+        builder: Builder,
+        lhs: &ir::ExprNode,
+        rhs: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg, mir::AnyReg) {
+        let (mut builder, lhs_value) = self.add_ir_expr_node(builder, lhs);
+        let class = self.class(&lhs.ty);
+        builder.push_to_expr_stack(lhs_value, class);
+        let (mut builder, rhs_value) = self.add_ir_expr_node(builder, rhs);
+        let lhs_value = builder.pop_off_expr_stack();
+        (builder, lhs_value, rhs_value)
+    }
+
+    fn add_ir_binary(
+        &mut self,
+        builder: Builder,
+        ty: &CType,
+        lhs: &ir::ExprNode,
+        op: &ir::BinaryOp,
+        rhs: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        let class = self.class(ty);
+        let (mut builder, lhs_value, rhs_value) = self.add_ir_operands(builder, lhs, rhs);
+
+        let (lhs_value, rhs_value) = match (lhs_value, rhs_value) {
+            (mir::AnyReg::F(lhs_value), mir::AnyReg::F(rhs_value)) => {
+                let op = match op {
+                    ir::BinaryOp::Add => x86::FBinaryOp::Add,
+                    ir::BinaryOp::Sub => x86::FBinaryOp::Sub,
+                    ir::BinaryOp::Mul => x86::FBinaryOp::Mul,
+                    ir::BinaryOp::Div => x86::FBinaryOp::Div,
+                    op => panic!("ICE: {} on floating point values", op.long_name()),
+                };
+                let fmt = class.ffmt();
+                let freg = self.new_ffmt_register(fmt);
+                builder
+                    .bb
+                    .add_instruction(Instruction::FBinary(op, fmt, freg, lhs_value, rhs_value));
+                return (builder, freg.into());
+            }
+            (mir::AnyReg::R(lhs_value), mir::AnyReg::R(rhs_value)) => (lhs_value, rhs_value),
+            _ => unreachable!("ICE: operands in registers of different kinds"),
+        };
+
+        let settings = self.settings();
+        let signed = matches!(class, Class::Int { signed: true, .. });
+        let reg = match op {
+            ir::BinaryOp::Sub if is_pointer(&rhs.ty) => {
+                let size = util::pointee_size(&lhs.ty, settings).max(1);
+                let difference =
+                    self.binary(&mut builder, x86::BinaryOp::Sub, lhs_value, rhs_value);
+                let size = self.load_int(&mut builder, size as i64);
+                self.binary(&mut builder, x86::BinaryOp::DivS, difference, size)
+            }
+            ir::BinaryOp::Add | ir::BinaryOp::Sub if is_pointer(&lhs.ty) => {
+                let size = util::pointee_size(&lhs.ty, settings);
+                let size = self.load_int(&mut builder, size as i64);
+                let offset = self.binary(&mut builder, x86::BinaryOp::Mul, rhs_value, size);
+                let op = match op {
+                    ir::BinaryOp::Add => x86::BinaryOp::Add,
+                    _ => x86::BinaryOp::Sub,
+                };
+                self.binary(&mut builder, op, lhs_value, offset)
+            }
+            ir::BinaryOp::Add if is_pointer(&rhs.ty) => {
+                let size = util::pointee_size(&rhs.ty, settings);
+                let size = self.load_int(&mut builder, size as i64);
+                let offset = self.binary(&mut builder, x86::BinaryOp::Mul, lhs_value, size);
+                self.binary(&mut builder, x86::BinaryOp::Add, offset, rhs_value)
+            }
+            op => {
+                let op = match (op, signed) {
+                    (ir::BinaryOp::Add, _) => x86::BinaryOp::Add,
+                    (ir::BinaryOp::Sub, _) => x86::BinaryOp::Sub,
+                    (ir::BinaryOp::Mul, _) => x86::BinaryOp::Mul,
+                    (ir::BinaryOp::Div, true) => x86::BinaryOp::DivS,
+                    (ir::BinaryOp::Div, false) => x86::BinaryOp::DivU,
+                    (ir::BinaryOp::Rem, true) => x86::BinaryOp::RemS,
+                    (ir::BinaryOp::Rem, false) => x86::BinaryOp::RemU,
+                    (ir::BinaryOp::ShiftLeft, _) => x86::BinaryOp::ShiftLeft,
+                    (ir::BinaryOp::ShiftRight, true) => x86::BinaryOp::ShiftRightArithmetic,
+                    (ir::BinaryOp::ShiftRight, false) => x86::BinaryOp::ShiftRightLogical,
+                    (ir::BinaryOp::Bitwise(ir::BitwiseOp::And), _) => x86::BinaryOp::And,
+                    (ir::BinaryOp::Bitwise(ir::BitwiseOp::Or), _) => x86::BinaryOp::Or,
+                    (ir::BinaryOp::Bitwise(ir::BitwiseOp::Xor), _) => x86::BinaryOp::Xor,
+                };
+                self.binary(&mut builder, op, lhs_value, rhs_value)
+            }
+        };
+        let reg = self.normalize(&mut builder, reg, ty);
+        (builder, reg.into())
+    }
+
+    fn add_ir_relation(
+        &mut self,
+        builder: Builder,
+        lhs: &ir::ExprNode,
+        op: &ir::RelationOp,
+        rhs: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        let class = self.class(&lhs.ty);
+        let (mut builder, lhs_value, rhs_value) = self.add_ir_operands(builder, lhs, rhs);
+        let reg = self.new_register();
+        let instruction = match (class, lhs_value, rhs_value) {
+            (Class::Int { signed, .. }, mir::AnyReg::R(lhs_value), mir::AnyReg::R(rhs_value)) => {
+                let cond = match (op, signed) {
+                    (ir::RelationOp::Eq, _) => x86::Cond::Eq,
+                    (ir::RelationOp::Ne, _) => x86::Cond::Ne,
+                    (ir::RelationOp::Lt, true) => x86::Cond::LtS,
+                    (ir::RelationOp::Le, true) => x86::Cond::LeS,
+                    (ir::RelationOp::Gt, true) => x86::Cond::GtS,
+                    (ir::RelationOp::Ge, true) => x86::Cond::GeS,
+                    (ir::RelationOp::Lt, false) => x86::Cond::LtU,
+                    (ir::RelationOp::Le, false) => x86::Cond::LeU,
+                    (ir::RelationOp::Gt, false) => x86::Cond::GtU,
+                    (ir::RelationOp::Ge, false) => x86::Cond::GeU,
+                };
+                Instruction::SetCond(cond, reg, lhs_value, rhs_value)
+            }
+            (class, mir::AnyReg::F(lhs_value), mir::AnyReg::F(rhs_value)) => {
+                let cond = match op {
+                    ir::RelationOp::Eq => x86::FCond::Eq,
+                    ir::RelationOp::Ne => x86::FCond::Ne,
+                    ir::RelationOp::Lt => x86::FCond::Lt,
+                    ir::RelationOp::Le => x86::FCond::Le,
+                    ir::RelationOp::Gt => x86::FCond::Gt,
+                    ir::RelationOp::Ge => x86::FCond::Ge,
+                };
+                Instruction::FSetCond(cond, class.ffmt(), reg, lhs_value, rhs_value)
+            }
+            _ => unreachable!("ICE: operands in registers of different kinds"),
+        };
+        builder.bb.add_instruction(instruction);
+        (builder, reg.into())
+    }
+
+    /// Short circuiting `&&` (if `is_and`) or `||`.
+    fn add_ir_logical(
+        &mut self,
+        builder: Builder,
+        lhs: &ir::ExprNode,
+        rhs: &ir::ExprNode,
+        is_and: bool,
+    ) -> (Builder, mir::AnyReg) {
+        let (end_label, mut end_builder) = self.create_new_builder(&builder);
+        let out_reg = self.new_register();
+        end_builder.bb.add_argument(out_reg.into());
+
+        let mut value_setter = |value: i64| {
+            let (label, mut builder) = self.create_new_builder(&builder);
+            let out_reg = self.new_register();
+            builder
+                .bb
+                .add_instruction(Instruction::LoadImm(out_reg, value));
+            let mut to_end = builder.create_block_ref(end_label);
+            to_end.arguments.push(out_reg.into());
+            self.function
+                .add_block(builder.bb.terminate(Terminator::Jump(to_end)));
+            label
+        };
+
+        let set_one_label = value_setter(1);
+        let set_zero_label = value_setter(0);
+
+        let rhs_label = self.function.create_block_label();
+        let (mut builder, lhs_value) = self.add_ir_expr_node(builder, lhs);
+
+        let (truthy, falsy) = match is_and {
+            true => (rhs_label, set_zero_label),
+            false => (set_one_label, rhs_label),
+        };
+        let branch = self.make_condition(&mut builder, lhs_value, &lhs.ty, truthy, falsy);
+
+        let rhs_builder = self.create_builder_with_label(rhs_label, &builder);
+
+        self.function.add_block(builder.bb.terminate(branch));
+
+        let (mut rhs_builder, rhs_value) = self.add_ir_expr_node(rhs_builder, rhs);
+        let branch = self.make_condition(
+            &mut rhs_builder,
+            rhs_value,
+            &rhs.ty,
+            set_one_label,
+            set_zero_label,
+        );
+        self.function.add_block(rhs_builder.bb.terminate(branch));
+
+        (end_builder, out_reg.into())
+    }
+
+    fn add_ir_assign(
+        &mut self,
+        builder: Builder,
+        lvalue: &ir::LvalueExprNode,
+        value: &ir::ExprNode,
+    ) -> (Builder, mir::AnyReg) {
+        let (mut builder, lvalue_value) = self.add_ir_lvalue_node(builder, lvalue);
+        if let Lvalue::Address(address) = lvalue_value {
+            builder.push_to_expr_stack(address.into(), Class::POINTER);
+        }
+        let (mut builder, value) = self.add_ir_expr_node(builder, value);
+        let lvalue_value = match lvalue_value {
+            Lvalue::Address(_) => Lvalue::Address(as_reg(builder.pop_off_expr_stack())),
+            lvalue_value => lvalue_value,
+        };
+        self.write_lvalue(&mut builder, lvalue_value, &lvalue.ty, value);
+        (builder, value)
+    }
+
+    fn add_ir_cast(
+        &mut self,
+        builder: Builder,
+        inner: &ir::ExprNode,
+        to_type: &CType,
+    ) -> (Builder, mir::AnyReg) {
+        let (mut builder, value) = self.add_ir_expr_node(builder, inner);
+        let settings = self.settings();
+        // Arrays only appear here after decaying to a pointer, in which case the value is already
+        // the address.
+        let (Some(from), Some(to)) = (
+            util::ctype_class(&inner.ty, settings),
+            util::ctype_class(to_type, settings),
+        ) else {
+            return (builder, value);
+        };
+
+        let value = match (from, to, value) {
+            (Class::Int { .. }, Class::Int { .. }, mir::AnyReg::R(reg)) => {
+                self.normalize(&mut builder, reg, to_type).into()
+            }
+            (Class::Int { size, signed }, to, mir::AnyReg::R(reg)) => {
+                let fmt = to.ffmt();
+                let freg = self.new_ffmt_register(fmt);
+                builder.bb.add_instruction(Instruction::IntToFloat {
+                    fmt,
+                    // Smaller unsigned integers are zero-extended, so they're converted correctly
+                    // as signed 64-bit integers.
+                    unsigned: size == 8 && !signed,
+                    dst: freg,
+                    src: reg,
+                });
+                freg.into()
+            }
+            (from, Class::Int { size, signed }, mir::AnyReg::F(freg)) => {
+                let reg = self.new_register();
+                builder.bb.add_instruction(Instruction::FloatToInt {
+                    fmt: from.ffmt(),
+                    unsigned: size == 8 && !signed,
+                    dst: reg,
+                    src: freg,
+                });
+                self.normalize(&mut builder, reg, to_type).into()
+            }
+            (from, to, mir::AnyReg::F(freg)) if from == to => freg.into(),
+            (from, to, mir::AnyReg::F(freg)) => {
+                let fmt = to.ffmt();
+                let out = self.new_ffmt_register(fmt);
+                builder
+                    .bb
+                    .add_instruction(Instruction::FConvert(fmt, from.ffmt(), out, freg));
+                out.into()
+            }
+            _ => unreachable!("ICE: value in a register of the wrong kind"),
+        };
+        (builder, value)
+    }
+
+    fn add_ir_lvalue_node(
+        &mut self,
+        mut builder: Builder,
+        lvalue: &ir::LvalueExprNode,
+    ) -> (Builder, Lvalue) {
+        match &lvalue.expr {
+            ir::LvalueExpr::Ident(id) => {
+                let value = match self.reference_values.contains(id) {
+                    true => Lvalue::Address(as_reg(*builder.var_registers.get(*id))),
+                    false => Lvalue::Reg(*id),
+                };
+                (builder, value)
+            }
+            ir::LvalueExpr::GlobalIdent(ident) => {
+                let reg = self.new_register();
+                builder
+                    .bb
+                    .add_instruction(Instruction::LoadAddress(reg, ident.as_str().into()));
+                (builder, Lvalue::Address(reg))
+            }
+            ir::LvalueExpr::Dereference(pointer) => {
+                let (builder, value) = self.add_ir_expr_node(builder, pointer);
+                (builder, Lvalue::Address(as_reg(value)))
+            }
+        }
+    }
+
+    fn read_lvalue(&mut self, builder: &mut Builder, lvalue: Lvalue, ty: &CType) -> mir::AnyReg {
+        match lvalue {
+            Lvalue::Address(address) => self.load(builder, ty, address),
+            Lvalue::Reg(id) => *builder.var_registers.get(id),
+        }
+    }
+
+    fn write_lvalue(
+        &mut self,
+        builder: &mut Builder,
+        lvalue: Lvalue,
+        ty: &CType,
+        value: mir::AnyReg,
+    ) {
+        match lvalue {
+            Lvalue::Address(address) => self.store(builder, ty, value, address),
+            Lvalue::Reg(id) => *builder.var_registers.get_mut(id) = value,
+        }
+    }
+
+    /// Creates a terminator that will jump to the falsy branch if the value is 0, and otherwise
+    /// fall through to the truthy branch.
+    fn make_condition(
+        &mut self,
+        builder: &mut Builder,
+        value: mir::AnyReg,
+        ty: &CType,
+        truthy: mir::BlockId,
+        falsy: mir::BlockId,
+    ) -> Terminator {
+        let value = match value {
+            mir::AnyReg::R(reg) => reg,
+            mir::AnyReg::F(freg) => self.float_truthy(builder, freg, ty),
+        };
+        let zero = self.load_int(builder, 0);
+        let truthy_ref = builder.create_block_ref(truthy);
+        let falsy_ref = builder.create_block_ref(falsy);
+        Terminator::BranchIf(x86::Cond::Eq, value, zero, falsy_ref, truthy_ref)
+    }
+
+    /// Returns 1 if the floating point value is nonzero, and 0 otherwise.
+    fn float_truthy(&mut self, builder: &mut Builder, value: mir::FReg, ty: &CType) -> mir::Reg {
+        let fmt = self.class(ty).ffmt();
+        let zero = self.load_float(builder, fmt, 0);
+        let reg = self.new_register();
+        // NaN is unordered, so it counts as true.
+        builder
+            .bb
+            .add_instruction(Instruction::FSetCond(x86::FCond::Ne, fmt, reg, value, zero));
+        reg
+    }
+
+    fn add_ir_if(&mut self, builder: Builder, node: &ir::IfStmtNode) -> Builder {
+        let (mut start_builder, cond_value) = self.add_ir_expr_node(builder, &node.condition);
+
+        let end_label = self.function.create_block_label();
+
+        let (if_case_label, if_case_builder) = self.create_new_builder(&start_builder);
+        let if_case_builder = self.add_ir_block_node(if_case_builder, &node.if_branch);
+
+        let falsy_label = if let Some(else_branch) = &node.else_branch {
+            let (else_case_label, else_case_builder) = self.create_new_builder(&start_builder);
+            let else_case_builder = self.add_ir_block_node(else_case_builder, else_branch);
+
+            let to_end_ref = Terminator::Jump(else_case_builder.create_block_ref(end_label));
+            self.function
+                .add_block(else_case_builder.bb.terminate(to_end_ref));
+
+            else_case_label
+        } else {
+            end_label
+        };
+
+        let to_end_ref = Terminator::Jump(if_case_builder.create_block_ref(end_label));
+        self.function
+            .add_block(if_case_builder.bb.terminate(to_end_ref));
+
+        let end_builder = self.create_builder_with_label(end_label, &start_builder);
+
+        let branching_terminator = self.make_condition(
+            &mut start_builder,
+            cond_value,
+            &node.condition.ty,
+            if_case_label,
+            falsy_label,
+        );
+        self.function
+            .add_block(start_builder.bb.terminate(branching_terminator));
+
+        end_builder
+    }
+
+    fn add_ir_switch(&mut self, builder: Builder, node: &ir::SwitchStmtNode) -> Builder {
+        let class = self.class(&node.expr.ty);
+        let (builder, value) = self.add_ir_expr_node(builder, &node.expr);
+        let value = as_reg(value);
+
+        let (end_label, end_builder) = self.create_new_builder(&builder);
+
+        let mut cases_builder: Option<Builder> = None;
+        let mut switch_builder_and_expr_reg = (builder, value);
+
+        let mut default_label = None;
+
+        for case in &node.cases {
+            let cur_label = self.function.create_block_label();
+
+            let cur_builder = if let Some(prev_builder) = cases_builder {
+                let cur_builder = self.create_builder_with_label(cur_label, &prev_builder);
+
+                let jump_to_cur = Terminator::Jump(prev_builder.create_block_ref(cur_label));
+                self.function
+                    .add_block(prev_builder.bb.terminate(jump_to_cur));
+
+                cur_builder
+            } else {
+                let mut cur_builder =
+                    self.create_builder_with_label(cur_label, &switch_builder_and_expr_reg.0);
+                cur_builder.break_label = Some(end_label);
+                cur_builder
+            };
+
+            let body = match &case.data {
+                ir::SwitchStmtCase::Case { label, body } => {
+                    let (mut switch_builder, switch_expr_reg) = switch_builder_and_expr_reg;
+
+                    let (next_switch_label, mut next_switch_builder) =
+                        self.create_new_builder(&switch_builder);
+                    let label_reg = self.load_int(&mut switch_builder, class.normalize(*label));
+
+                    let eq_ref = switch_builder.create_block_ref(cur_label);
+                    let mut neq_ref = switch_builder.create_block_ref(next_switch_label);
+                    neq_ref.arguments.push(switch_expr_reg.into());
+
+                    let term = Terminator::BranchIf(
+                        x86::Cond::Eq,
+                        switch_expr_reg,
+                        label_reg,
+                        eq_ref,
+                        neq_ref,
+                    );
+                    self.function.add_block(switch_builder.bb.terminate(term));
+
+                    let next_switch_expr_reg = self.new_register();
+                    next_switch_builder
+                        .bb
+                        .add_argument(next_switch_expr_reg.into());
+
+                    switch_builder_and_expr_reg = (next_switch_builder, next_switch_expr_reg);
+
+                    body
+                }
+                ir::SwitchStmtCase::Default { body } => {
+                    default_label = Some(cur_label);
+                    body
+                }
+            };
+            let cur_builder = self.add_ir_block_node(cur_builder, body);
+
+            cases_builder = Some(cur_builder);
+        }
+
+        if let Some(case_builder) = cases_builder {
+            let jump_to_end = Terminator::Jump(case_builder.create_block_ref(end_label));
+            self.function
+                .add_block(case_builder.bb.terminate(jump_to_end));
+        }
+
+        let last_jump = default_label.unwrap_or(end_label);
+
+        let switch_builder = switch_builder_and_expr_reg.0;
+        let jump_to_last = Terminator::Jump(switch_builder.create_block_ref(last_jump));
+        self.function
+            .add_block(switch_builder.bb.terminate(jump_to_last));
+
+        end_builder
+    }
+
+    fn add_ir_loop(&mut self, builder: Builder, node: &ir::LoopStmtNode) -> Builder {
+        let (start_label, start_builder) = self.create_new_builder(&builder);
+
+        let to_start = Terminator::Jump(builder.create_block_ref(start_label));
+        self.function.add_block(builder.bb.terminate(to_start));
+
+        let (end_label, end_builder) = self.create_new_builder(&start_builder);
+
+        let continuation_label = match &node.continuation {
+            Some(_) => self.function.create_block_label(),
+            None => start_label,
+        };
+
+        if let Some(continuation_node) = &node.continuation {
+            let continuation_builder =
+                self.create_builder_with_label(continuation_label, &start_builder);
+            let continuation_builder = self
+                .add_ir_expr_node(continuation_builder, continuation_node)
+                .0;
+
+            let continuation_terminator =
+                Terminator::Jump(continuation_builder.create_block_ref(start_label));
+            self.function
+                .add_block(continuation_builder.bb.terminate(continuation_terminator));
+        }
+
+        let mut body_builder = if let Some(condition_node) = &node.condition {
+            let (mut start_builder, cond_value) =
+                self.add_ir_expr_node(start_builder, condition_node);
+
+            let (body_label, body_builder) = self.create_new_builder(&start_builder);
+
+            let branching_terminator = self.make_condition(
+                &mut start_builder,
+                cond_value,
+                &condition_node.ty,
+                body_label,
+                end_label,
+            );
+            self.function
+                .add_block(start_builder.bb.terminate(branching_terminator));
+
+            body_builder
+        } else {
+            start_builder
+        };
+
+        body_builder.continue_label = Some(continuation_label);
+        body_builder.break_label = Some(end_label);
+
+        let body_builder = self.add_ir_block_node(body_builder, &node.body);
+
+        let terminator = Terminator::Jump(body_builder.create_block_ref(continuation_label));
+        self.function
+            .add_block(body_builder.bb.terminate(terminator));
+
+        end_builder
+    }
+
+    fn add_ir_break(&mut self, builder: Builder) -> Builder {
+        let new_builder = self.create_new_builder(&builder).1;
+
+        let term_ref = builder
+            .break_label
+            .expect("ICE: break while not in loop/switch");
+        let terminator = Terminator::Jump(builder.create_block_ref(term_ref));
+        self.function.add_block(builder.bb.terminate(terminator));
+
+        new_builder
+    }
+
+    fn add_ir_continue(&mut self, builder: Builder) -> Builder {
+        let new_builder = self.create_new_builder(&builder).1;
+
+        let term_ref = builder
+            .continue_label
+            .expect("ICE: continue while not in loop");
+        let terminator = Terminator::Jump(builder.create_block_ref(term_ref));
+        self.function.add_block(builder.bb.terminate(terminator));
+
+        new_builder
+    }
+
+    fn add_ir_return(&mut self, builder: Builder, node: Option<&ir::ExprNode>) -> Builder {
+        let (builder, reg) = match node {
+            Some(node) => {
+                let (builder, value) = self.add_ir_expr_node(builder, node);
+                (builder, Some(value))
+            }
+            None => (builder, None),
+        };
+
+        let new_builder = self.create_new_builder(&builder).1;
+
+        self.function
+            .add_block(builder.bb.terminate(Terminator::Return(reg)));
+
+        new_builder
+    }
+
+    /// Loads a value of type `ty` from `address`.
+    fn load(&mut self, builder: &mut Builder, ty: &CType, address: mir::Reg) -> mir::AnyReg {
+        match self.class(ty) {
+            Class::Int { size, signed } => {
+                let reg = self.new_register();
+                builder.bb.add_instruction(Instruction::Load {
+                    size: x86::Size::from_bytes(size.into()),
+                    signed,
+                    dst: reg,
+                    base: address,
+                    offset: 0,
+                });
+                reg.into()
+            }
+            class => {
+                let fmt = class.ffmt();
+                let freg = self.new_ffmt_register(fmt);
+                builder
+                    .bb
+                    .add_instruction(Instruction::FLoad(fmt, freg, address, 0));
+                freg.into()
+            }
+        }
+    }
+
+    /// Stores the value of type `ty` at `address`.
+    fn store(&mut self, builder: &mut Builder, ty: &CType, value: mir::AnyReg, address: mir::Reg) {
+        let instruction = match (self.class(ty), value) {
+            (Class::Int { size, .. }, mir::AnyReg::R(value)) => {
+                Instruction::Store(x86::Size::from_bytes(size.into()), value, address, 0)
+            }
+            (class, mir::AnyReg::F(value)) => Instruction::FStore(class.ffmt(), value, address, 0),
+            _ => unreachable!("ICE: value in a register of the wrong kind"),
+        };
+        builder.bb.add_instruction(instruction);
+    }
+
+    /// Sign- or zero-extends the integer of type `ty` in `reg` to 64 bits.
+    fn normalize(&mut self, builder: &mut Builder, reg: mir::Reg, ty: &CType) -> mir::Reg {
+        match self.class(ty) {
+            Class::Int { size, signed } if size < 8 => {
+                let out = self.new_register();
+                builder.bb.add_instruction(Instruction::Extend {
+                    size: x86::Size::from_bytes(size.into()),
+                    signed,
+                    dst: out,
+                    src: reg,
+                });
+                out
+            }
+            _ => reg,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        builder: &mut Builder,
+        op: x86::BinaryOp,
+        lhs: mir::Reg,
+        rhs: mir::Reg,
+    ) -> mir::Reg {
+        let reg = self.new_register();
+        builder
+            .bb
+            .add_instruction(Instruction::Binary(op, reg, lhs, rhs));
+        reg
+    }
+
+    fn load_int(&mut self, builder: &mut Builder, value: i64) -> mir::Reg {
+        let reg = self.new_register();
+        builder.bb.add_instruction(Instruction::LoadImm(reg, value));
+        reg
+    }
+
+    /// Loads the floating point value of the format with the given bits.
+    fn load_float(&mut self, builder: &mut Builder, fmt: mir::FFmt, bits: u64) -> mir::FReg {
+        let freg = self.new_ffmt_register(fmt);
+        builder
+            .bb
+            .add_instruction(Instruction::FLoadImm(fmt, freg, bits));
+        freg
+    }
+
+    /// Sets the register of a variable that isn't initialized yet to 0.
+    fn load_zero(&mut self, builder: &mut Builder, reg: mir::AnyReg) {
+        let instruction = match reg {
+            mir::AnyReg::R(reg) => Instruction::LoadImm(reg, 0),
+            mir::AnyReg::F(freg) if freg.is_double() => {
+                Instruction::FLoadImm(mir::FFmt::D, freg, 0)
+            }
+            mir::AnyReg::F(freg) => Instruction::FLoadImm(mir::FFmt::S, freg, 0),
+        };
+        builder.bb.add_instruction(instruction);
+    }
+
+    fn settings(&self) -> &'a crate::settings::Settings {
+        self.module.settings
+    }
+
+    fn class(&self, ty: &CType) -> Class {
+        util::scalar_class(ty, self.settings())
+    }
+
+    fn new_register_of_class(&mut self, class: Class) -> mir::AnyReg {
+        match class {
+            Class::Int { .. } => self.new_register().into(),
+            Class::Float => self.new_float_register().into(),
+            Class::Double => self.new_double_register().into(),
+        }
+    }
+
+    fn new_register(&mut self) -> mir::Reg {
+        let n = self.next_reg;
+        self.next_reg += 1;
+        mir::Reg::Virtual(n)
+    }
+
+    fn new_ffmt_register(&mut self, fmt: mir::FFmt) -> mir::FReg {
+        match fmt {
+            mir::FFmt::S => self.new_float_register(),
+            mir::FFmt::D => self.new_double_register(),
+        }
+    }
+
+    fn new_float_register(&mut self) -> mir::FReg {
+        let n = self.next_float_reg;
+        self.next_float_reg += 1;
+        mir::FReg::VirtualSingle(n)
+    }
+
+    fn new_double_register(&mut self) -> mir::FReg {
+        let n = self.next_double_reg;
+        self.next_double_reg += 1;
+        mir::FReg::VirtualDouble(n)
+    }
+}
+
+fn as_reg(reg: mir::AnyReg) -> mir::Reg {
+    match reg {
+        mir::AnyReg::R(reg) => reg,
+        mir::AnyReg::F(_) => unreachable!("ICE: used a float register to hold an integer"),
+    }
+}
+
+fn is_pointer(ty: &CType) -> bool {
+    matches!(ty, CType::Scalar(ctype::Scalar::Pointer(_)))
+}
@@ -0,0 +1,162 @@
+//! Generates GNU-syntax x86-64 assembly for the System V ABI.
+//!
+//! Functions are built as a [`mips_ir::Function`] with x86-64 instructions, in the same way as the
+//! MIPS backend: every variable gets a virtual register, which is passed along as a block argument.
+//! Variables whose address is taken are kept in a stack slot instead. The CFG, liveness and
+//! register allocation passes of `mips_ir` then assign the registers, after which
+//! [`mips_ir::x86_64::X86_64Outputter`] writes the function. Global data is written here.
+
+mod function_generator;
+mod util;
+
+use crate::{
+    diagnostic::AggregateResult,
+    ir::{self, ctype::CType},
+    settings::Settings,
+};
+use std::fmt::Write;
+use util::Class;
+
+pub fn build_from_ir(
+    ir: &ir::Root,
+    settings: &Settings,
+    filename: &str,
+    source: &str,
+) -> AggregateResult<String> {
+    AggregateResult::new_ok(ModuleGenerator::generate(ir, settings, filename, source))
+}
+
+struct ModuleGenerator<'a> {
+    ir: &'a ir::Root,
+    settings: &'a Settings,
+    source: &'a str,
+    /// The string literals, the index is used in the label `.Lstr.<index>`.
+    strings: Vec<Vec<u8>>,
+}
+
+impl<'a> ModuleGenerator<'a> {
+    fn generate(
+        ir: &'a ir::Root,
+        settings: &'a Settings,
+        filename: &str,
+        source: &'a str,
+    ) -> String {
+        let mut generator = Self {
+            ir,
+            settings,
+            source,
+            strings: Vec::new(),
+        };
+
+        let mut out = String::new();
+        writeln!(out, "\t.file\t\"{}\"", filename.escape_default()).unwrap();
+
+        // Sorted to get deterministic output.
+        let mut vars: Vec<_> = ir.vars.iter().collect();
+        vars.sort_unstable_by_key(|(ident, _)| ident.as_str());
+        for (ident, var) in vars {
+            out += &generator.global_var(ident, var);
+        }
+
+        let mut functions: Vec<_> = ir.functions.iter().collect();
+        functions.sort_unstable_by_key(|(ident, _)| ident.as_str());
+        for (ident, function) in functions {
+            if function.body.is_some() {
+                out += &function_generator::generate(&mut generator, ident, function);
+            }
+        }
+
+        if !generator.strings.is_empty() {
+            out += "\n\t.section\t.rodata\n";
+            for (i, string) in generator.strings.iter().enumerate() {
+                writeln!(out, ".Lstr.{i}:").unwrap();
+                writeln!(out, "\t.byte\t{}", join(string)).unwrap();
+            }
+        }
+
+        out += "\n\t.section\t.note.GNU-stack,\"\",@progbits\n";
+        out
+    }
+
+    fn global_var(&mut self, ident: &str, var: &ir::GlobalVarNode) -> String {
+        let mut out = String::from("\n");
+        let span = &self.source[std::ops::Range::from(var.original_span)];
+        for line in var
+            .comments
+            .iter()
+            .flat_map(|c| c.lines())
+            .chain(span.lines())
+        {
+            writeln!(out, "# {line}").unwrap();
+        }
+        let size = util::ctype_size(&var.ty, self.settings);
+        let align = util::ctype_align(&var.ty, self.settings);
+        match &var.value {
+            Some(value) => {
+                out += "\t.data\n";
+                writeln!(out, "\t.p2align\t{}", align.trailing_zeros()).unwrap();
+                writeln!(out, "{ident}:").unwrap();
+                self.constant_data(&mut out, &var.ty, value, size);
+            }
+            None => {
+                out += "\t.bss\n";
+                writeln!(out, "\t.p2align\t{}", align.trailing_zeros()).unwrap();
+                writeln!(out, "{ident}:").unwrap();
+                writeln!(out, "\t.zero\t{}", size.max(1)).unwrap();
+            }
+        }
+        out
+    }
+
+    fn constant_data(&mut self, out: &mut String, ty: &CType, value: &ir::Constant, size: u64) {
+        match (value, util::ctype_class(ty, self.settings)) {
+            (ir::Constant::String(string), None) => {
+                // A char array initialized by a string literal, padded with zeros.
+                writeln!(out, "\t.byte\t{}", join(string)).unwrap();
+                if size > string.len() as u64 {
+                    writeln!(out, "\t.zero\t{}", size - string.len() as u64).unwrap();
+                }
+            }
+            (ir::Constant::String(string), Some(_)) => {
+                let label = self.add_string(string);
+                writeln!(out, "\t.quad\t{label}").unwrap();
+            }
+            (ir::Constant::Float(value), Some(Class::Float)) => {
+                writeln!(out, "\t.long\t{:#x}", (*value as f32).to_bits()).unwrap();
+            }
+            (ir::Constant::Float(value), Some(Class::Double)) => {
+                writeln!(out, "\t.quad\t{:#x}", value.to_bits()).unwrap();
+            }
+            (ir::Constant::Integer(value), Some(class @ Class::Int { size, .. })) => {
+                let directive = match size {
+                    1 => "byte",
+                    2 => "short",
+                    4 => "long",
+                    _ => "quad",
+                };
+                writeln!(out, "\t.{directive}\t{}", class.normalize(*value)).unwrap();
+            }
+            (value, _) => panic!("ICE: global of type {ty} can't be initialized with {value:?}"),
+        }
+    }
+
+    /// Adds the string literal to the module and returns its label.
+    fn add_string(&mut self, string: &[u8]) -> String {
+        let index = match self.strings.iter().position(|s| s == string) {
+            Some(index) => index,
+            None => {
+                self.strings.push(string.to_vec());
+                self.strings.len() - 1
+            }
+        };
+        format!(".Lstr.{index}")
+    }
+}
+
+fn join(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,131 @@
+use crate::ir::ctype::{self, CType};
+use crate::settings::Settings;
+use mips_ir as mir;
+
+/// How a scalar value is stored in a register. Integers and pointers are kept in a general purpose
+/// register, sign- or zero-extended to 64 bits. Floating point values are kept in an sse register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Int { size: u64, signed: bool },
+    Float,
+    Double,
+}
+
+impl Class {
+    /// The class of pointers.
+    pub const POINTER: Self = Class::Int {
+        size: 8,
+        signed: false,
+    };
+
+    /// The format of a floating point class.
+    pub fn ffmt(self) -> mir::FFmt {
+        match self {
+            Class::Float => mir::FFmt::S,
+            Class::Double => mir::FFmt::D,
+            Class::Int { .. } => panic!("ICE: integers have no floating point format"),
+        }
+    }
+
+    /// Converts the value to the 64-bit representation of this class, i.e. truncates and then
+    /// sign- or zero-extends it.
+    pub fn normalize(self, value: i128) -> i64 {
+        match self {
+            Class::Int {
+                size: 1,
+                signed: true,
+            } => value as i8 as i64,
+            Class::Int {
+                size: 1,
+                signed: false,
+            } => value as u8 as i64,
+            Class::Int {
+                size: 2,
+                signed: true,
+            } => value as i16 as i64,
+            Class::Int {
+                size: 2,
+                signed: false,
+            } => value as u16 as i64,
+            Class::Int {
+                size: 4,
+                signed: true,
+            } => value as i32 as i64,
+            Class::Int {
+                size: 4,
+                signed: false,
+            } => value as u32 as i64,
+            _ => value as i64,
+        }
+    }
+}
+
+/// Returns the class of a scalar type, or `None` for void and aggregates.
+pub fn ctype_class(ctype: &CType, settings: &Settings) -> Option<Class> {
+    match ctype {
+        CType::Scalar(ctype::Scalar::Arithmetic(arithmetic)) => Some(match arithmetic {
+            ctype::Arithmetic::Float => Class::Float,
+            ctype::Arithmetic::Double | ctype::Arithmetic::LongDouble => Class::Double,
+            _ => Class::Int {
+                size: arithmetic.size_in_bits(settings) as u64 / 8,
+                signed: arithmetic.is_signed(),
+            },
+        }),
+        CType::Scalar(ctype::Scalar::Pointer(_)) => Some(Class::Int {
+            size: 8,
+            signed: false,
+        }),
+        CType::Aggregate(_) | CType::Void => None,
+    }
+}
+
+/// Same as [`ctype_class`], but panics if the type isn't a scalar.
+pub fn scalar_class(ctype: &CType, settings: &Settings) -> Class {
+    ctype_class(ctype, settings).unwrap_or_else(|| panic!("ICE: {ctype} should be a scalar type"))
+}
+
+/// Returns the size in bytes of the type.
+pub fn ctype_size(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Scalar(_) => match scalar_class(ctype, settings) {
+            Class::Int { size, .. } => size,
+            Class::Float => 4,
+            Class::Double => 8,
+        },
+        CType::Aggregate(ctype::Aggregate::Array(array)) => {
+            ctype_size(&array.inner, settings) * array.length as u64
+        }
+        CType::Void => 0,
+    }
+}
+
+/// Returns the alignment in bytes of the type.
+pub fn ctype_align(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Aggregate(ctype::Aggregate::Array(array)) => ctype_align(&array.inner, settings),
+        CType::Void => 1,
+        scalar => ctype_size(scalar, settings),
+    }
+}
+
+/// Returns the size in bytes of the type a pointer points to. Panics if the type isn't a pointer.
+pub fn pointee_size(ctype: &CType, settings: &Settings) -> u64 {
+    match ctype {
+        CType::Scalar(ctype::Scalar::Pointer(ctype::Pointer { inner, .. })) => {
+            ctype_size(inner, settings)
+        }
+        _ => panic!("ICE: {ctype} should be a pointer type"),
+    }
+}
+
+/// Returns how a value of the type is stored on the stack.
+pub fn stack_info(ctype: &CType, settings: &Settings) -> mir::StackInfo {
+    mir::StackInfo {
+        size: ctype_size(ctype, settings) as u128,
+        alignment: mir::AlignBoundary(ctype_align(ctype, settings).trailing_zeros()),
+        signed: matches!(
+            ctype_class(ctype, settings),
+            Some(Class::Int { signed: true, .. })
+        ),
+    }
+}
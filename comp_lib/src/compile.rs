@@ -1,11 +1,10 @@
-use std::collections::HashSet;
+use std::collections::LinkedList;
 
-pub use crate::settings::Target;
+pub use crate::settings::{Settings, Target};
 use crate::{
-    codegen,
-    diagnostic::{AggregateResult, Code},
-    inspectors, passes,
-    settings::Settings,
+    ast, codegen,
+    diagnostic::{levels, AggregateResult, Code, DiagnosticKind, DiagnosticLevels, Level},
+    inspectors, ir, passes,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -13,12 +12,18 @@ pub enum OutputFormat {
     AntlrTree,
     AstDot,
     AstRustDbg,
+    AstC,
     IrDot,
     IrRustDbg,
+    IrC,
     SymbolTableAscii,
     LlvmIr,
     MipsDbg,
     MipsAsm,
+    MipsObject,
+    X86Asm,
+    RiscVAsm,
+    Wat,
 }
 
 impl std::fmt::Display for OutputFormat {
@@ -27,12 +32,18 @@ impl std::fmt::Display for OutputFormat {
             OutputFormat::AntlrTree => "antlr tree",
             OutputFormat::AstDot => "ast dot",
             OutputFormat::AstRustDbg => "ast rust dbg",
+            OutputFormat::AstC => "ast c",
             OutputFormat::IrDot => "ir dot",
             OutputFormat::IrRustDbg => "ir rust dbg",
+            OutputFormat::IrC => "ir c",
             OutputFormat::SymbolTableAscii => "symbol table",
             OutputFormat::LlvmIr => "llvm ir",
             OutputFormat::MipsDbg => "mips dbg",
             OutputFormat::MipsAsm => "mips assembly",
+            OutputFormat::MipsObject => "mips object",
+            OutputFormat::X86Asm => "x86-64 assembly",
+            OutputFormat::RiscVAsm => "risc-v assembly",
+            OutputFormat::Wat => "webassembly text",
         };
         write!(f, "{name}")
     }
@@ -44,21 +55,41 @@ pub struct CompileOpts {
     settings: Settings,
     const_fold: bool,
     analyze_control_flow: bool,
-    upgrade_to_err: HashSet<Code>,
+    diagnostic_levels: DiagnosticLevels,
+    max_errors: usize,
+}
+
+impl CompileOpts {
+    pub fn target(&self) -> Target {
+        self.settings.target
+    }
+
+    /// The settings for the passes that depend on the target, e.g. to call
+    /// [`build_ir_from_ast`](crate::passes::lower_ast::build_ir_from_ast) directly.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CompileOptsBuilder {
     output_format: Option<OutputFormat>,
     target: Target,
+    o32_abi: bool,
+    bounds_check: bool,
     const_fold: bool,
     analyze_control_flow: bool,
-    upgrade_to_err: HashSet<Code>,
+    diagnostic_levels: DiagnosticLevels,
+    warning_flags: Vec<String>,
+    max_errors: usize,
 }
 
 #[derive(Debug, Clone)]
 pub enum CompileOptsErr {
     IncompatibleFormatAndTarget(OutputFormat, Target),
+    O32AbiWithoutMips(Target),
+    BoundsCheckWithoutMips(Target),
+    UnknownWarningFlag(levels::UnknownFlag),
 }
 
 impl std::fmt::Display for CompileOptsErr {
@@ -67,6 +98,16 @@ impl std::fmt::Display for CompileOptsErr {
             CompileOptsErr::IncompatibleFormatAndTarget(format, target) => {
                 write!(f, "Can't use the {format} format with the {target} target.")
             }
+            CompileOptsErr::O32AbiWithoutMips(target) => {
+                write!(f, "Can't use the O32 ABI with the {target} target.")
+            }
+            CompileOptsErr::BoundsCheckWithoutMips(target) => {
+                write!(
+                    f,
+                    "Can't check array bounds at runtime with the {target} target."
+                )
+            }
+            CompileOptsErr::UnknownWarningFlag(err) => write!(f, "{err}."),
         }
     }
 }
@@ -78,9 +119,13 @@ impl Default for CompileOptsBuilder {
         Self {
             output_format: None,
             target: Target::X86_64,
+            o32_abi: false,
+            bounds_check: false,
             const_fold: true,
             analyze_control_flow: true,
-            upgrade_to_err: HashSet::default(),
+            diagnostic_levels: DiagnosticLevels::new(),
+            warning_flags: Vec::new(),
+            max_errors: 0,
         }
     }
 }
@@ -102,6 +147,20 @@ impl CompileOptsBuilder {
         self
     }
 
+    /// Generate calls following the standard MIPS O32 ABI, so the output can be linked with code
+    /// from other tools. By default all arguments are passed on the stack.
+    pub fn o32_abi(mut self, o32_abi: bool) -> Self {
+        self.o32_abi = o32_abi;
+        self
+    }
+
+    /// Trap at runtime when an array is indexed outside of its bounds, only supported by the MIPS
+    /// and RISC-V targets. Out of bounds constant indices are always reported at compile time.
+    pub fn bounds_check(mut self, bounds_check: bool) -> Self {
+        self.bounds_check = bounds_check;
+        self
+    }
+
     /// Set const folding
     pub fn const_fold(mut self, const_fold: bool) -> Self {
         self.const_fold = const_fold;
@@ -119,23 +178,62 @@ impl CompileOptsBuilder {
     /// - [`Code::IncompatibleAssign`]
     /// - [`Code::MultiByteChar`]
     pub fn for_assignments(mut self) -> Self {
-        self.upgrade_to_err.insert(Code::UnspecifiedType);
-        self.upgrade_to_err.insert(Code::IncompatibleAssign);
-        self.upgrade_to_err.insert(Code::IncompatibleReturn);
-        self.upgrade_to_err.insert(Code::IncompatibleArg);
-        self.upgrade_to_err.insert(Code::MultiByteChar);
+        for code in [
+            Code::UnspecifiedType,
+            Code::IncompatibleAssign,
+            Code::IncompatibleReturn,
+            Code::IncompatibleArg,
+            Code::MultiByteChar,
+        ] {
+            self.diagnostic_levels.set_error(code, true);
+        }
         self
     }
 
     pub fn with_code_to_upgrade(mut self, code: Code) -> Self {
-        self.upgrade_to_err.insert(code);
+        self.diagnostic_levels.set_error(code, true);
+        self
+    }
+
+    /// Applies a `-W` flag without the `-W`, e.g. `no-conversion` or `error`, after the upgrades
+    /// above. See [`levels`] for the syntax.
+    pub fn warning_flag(mut self, flag: impl Into<String>) -> Self {
+        self.warning_flags.push(flag.into());
+        self
+    }
+
+    /// Drops all warnings that aren't upgraded to an error, like `-w`.
+    pub fn inhibit_warnings(mut self, inhibit_warnings: bool) -> Self {
+        self.diagnostic_levels
+            .set_inhibit_warnings(inhibit_warnings);
+        self
+    }
+
+    /// Only report the first `max_errors` errors, 0 means there is no limit.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
         self
     }
 
     pub fn build(self) -> Result<CompileOpts, CompileOptsErr> {
         let output_format = match self.output_format {
             Some(format) => match (&self.target, &format) {
-                (Target::X86_64, OutputFormat::MipsAsm | OutputFormat::MipsDbg) => {
+                (
+                    Target::X86_64,
+                    OutputFormat::MipsAsm | OutputFormat::MipsDbg | OutputFormat::MipsObject,
+                )
+                | (Target::Mips, OutputFormat::X86Asm | OutputFormat::RiscVAsm)
+                | (Target::X86_64, OutputFormat::RiscVAsm)
+                | (Target::RiscV32, OutputFormat::MipsAsm | OutputFormat::X86Asm)
+                | (Target::X86_64 | Target::Mips | Target::RiscV32, OutputFormat::Wat)
+                | (
+                    Target::Wasm32,
+                    OutputFormat::MipsAsm
+                    | OutputFormat::MipsDbg
+                    | OutputFormat::MipsObject
+                    | OutputFormat::X86Asm
+                    | OutputFormat::RiscVAsm,
+                ) => {
                     return Err(CompileOptsErr::IncompatibleFormatAndTarget(
                         format,
                         self.target,
@@ -146,67 +244,129 @@ impl CompileOptsBuilder {
             None => match &self.target {
                 Target::X86_64 => OutputFormat::LlvmIr,
                 Target::Mips => OutputFormat::MipsAsm,
+                Target::RiscV32 => OutputFormat::RiscVAsm,
+                Target::Wasm32 => OutputFormat::Wat,
             },
         };
+        if self.o32_abi && matches!(self.target, Target::RiscV32 | Target::Wasm32) {
+            return Err(CompileOptsErr::O32AbiWithoutMips(self.target));
+        }
+        if self.bounds_check && matches!(self.target, Target::X86_64 | Target::Wasm32) {
+            return Err(CompileOptsErr::BoundsCheckWithoutMips(self.target));
+        }
+        let mut diagnostic_levels = self.diagnostic_levels;
+        for flag in &self.warning_flags {
+            diagnostic_levels
+                .apply_flag(flag)
+                .map_err(CompileOptsErr::UnknownWarningFlag)?;
+        }
         let settings = Settings {
             target: self.target,
+            o32_abi: self.o32_abi,
+            bounds_check: self.bounds_check,
         };
         Ok(CompileOpts {
             output_format,
             settings,
             const_fold: self.const_fold,
             analyze_control_flow: self.analyze_control_flow,
-            upgrade_to_err: self.upgrade_to_err,
+            diagnostic_levels,
+            max_errors: self.max_errors,
         })
     }
 }
 
 pub fn compile(source: &str, source_name: &str, opts: &CompileOpts) -> AggregateResult<Vec<u8>> {
     let mut res = run_compile(source, source_name, opts);
-    res.upgrade_diagnostics(|d| opts.upgrade_to_err.contains(d.code()));
+    apply_diagnostic_levels(&mut res, source, opts);
     res
 }
 
+/// Compiles the source to an unlinked MIPS object, regardless of the output format of `opts`. The
+/// object can be linked with other objects using [`link`].
+pub fn compile_to_mips_object(
+    source: &str,
+    source_name: &str,
+    opts: &CompileOpts,
+) -> AggregateResult<mips_ir::Root> {
+    let recovery = passes::parse::recover(source);
+    let res = build_ast(&recovery, opts).and_then(|ast| build_ir(&ast, &recovery.source, opts));
+    let mut res = recovery.apply(res).and_then(|ir| {
+        codegen::mips::build_object_from_ir(&ir, &opts.settings, source_name, source)
+    });
+    apply_diagnostic_levels(&mut res, source, opts);
+    res
+}
+
+/// Compiles the source to the IR, e.g. to run it with the
+/// [`Interpreter`](crate::interpreter::Interpreter). The output format of `opts` is ignored.
+pub fn compile_to_ir(source: &str, opts: &CompileOpts) -> AggregateResult<ir::Root> {
+    let recovery = passes::parse::recover(source);
+    let res = build_ast(&recovery, opts).and_then(|ast| build_ir(&ast, &recovery.source, opts));
+    let mut res = recovery.apply(res);
+    apply_diagnostic_levels(&mut res, source, opts);
+    res
+}
+
+/// Links MIPS objects into a program in the output format of `opts`. If the output format is
+/// [`OutputFormat::MipsObject`], the objects are only merged into a single object (e.g. to create
+/// a library archive). Objects compiled for the RISC-V target can only be linked to
+/// [`OutputFormat::RiscVAsm`] (or merged).
+pub fn link(objects: Vec<mips_ir::Root>, opts: &CompileOpts) -> Result<Vec<u8>, String> {
+    match opts.output_format {
+        OutputFormat::MipsObject => mips_ir::merge(objects).map(|root| write_mips_object(&root)),
+        OutputFormat::MipsAsm | OutputFormat::MipsDbg => {
+            let root = mips_ir::link(objects)?;
+            if root.isa() != mips_ir::Isa::Mips32 {
+                return Err(format!(
+                    "can't link RISC-V objects to the {}",
+                    opts.output_format
+                ));
+            }
+            Ok(write_mips_asm(&root, opts.output_format))
+        }
+        OutputFormat::RiscVAsm => {
+            let root = mips_ir::link(objects)?;
+            if root.isa() != mips_ir::Isa::RiscV32 {
+                return Err(format!(
+                    "can't link MIPS objects to the {}",
+                    opts.output_format
+                ));
+            }
+            Ok(write_riscv_asm(&root))
+        }
+        format => Err(format!("can't link objects to the {format} format")),
+    }
+}
+
 fn run_compile(source: &str, source_name: &str, opts: &CompileOpts) -> AggregateResult<Vec<u8>> {
     if opts.output_format == OutputFormat::AntlrTree {
         let antlr_tree = passes::parse::parse_to_antlr_tree(source);
         return antlr_tree.map(String::into_bytes);
     }
 
-    let cst = passes::parse::parse_to_cst(source);
-
-    let mut ast = cst.and_then(|cst| passes::lower_cst::lower(&cst));
-
-    if opts.const_fold {
-        if let Some(ast) = ast.value_mut() {
-            passes::const_fold::const_fold(ast);
-        }
-    }
+    let recovery = passes::parse::recover(source);
+    let ast = build_ast(&recovery, opts);
 
     match opts.output_format {
         OutputFormat::AstDot => {
-            return ast.map(|ast| inspectors::dot::inspect_ast(&ast).into_bytes());
+            return recovery.apply(ast.map(|ast| inspectors::dot::inspect_ast(&ast).into_bytes()));
         }
         OutputFormat::AstRustDbg => {
-            return ast.map(|ast| format!("{ast:#?}\n").into_bytes());
+            return recovery.apply(ast.map(|ast| format!("{ast:#?}\n").into_bytes()));
+        }
+        OutputFormat::AstC => {
+            return recovery.apply(ast.map(|ast| inspectors::c::inspect_ast(&ast).into_bytes()));
         }
         _ => {}
     }
 
-    let mut res = ast.and_then(|ast| passes::lower_ast::build_ir_from_ast(&ast, &opts.settings));
-
-    if opts.analyze_control_flow {
-        if let Some(ir) = res.value_mut() {
-            let extra_diags = passes::dead_code_removal::remove_dead_code(ir);
-            for diag in extra_diags {
-                res.add_rec_diagnostic(diag);
-            }
-        }
-    }
+    let res = recovery.apply(ast.and_then(|ast| build_ir(&ast, &recovery.source, opts)));
 
     match opts.output_format {
         OutputFormat::IrDot => res.map(|ir| inspectors::dot::inspect_ir(&ir).into_bytes()),
         OutputFormat::IrRustDbg => res.map(|ir| format!("{ir:#?}\n").into_bytes()),
+        OutputFormat::IrC => res.map(|ir| inspectors::c::inspect_ir(&ir, source).into_bytes()),
         OutputFormat::SymbolTableAscii => res.map(|ir| {
             let mut s = String::new();
             for (name, funcion) in ir.functions {
@@ -241,36 +401,35 @@ fn run_compile(source: &str, source_name: &str, opts: &CompileOpts) -> Aggregate
                 codegen::mips::build_from_ir(&ir, &opts.settings, source_name, source)
             });
 
-            let config = if opts.output_format == OutputFormat::MipsDbg {
-                mips_ir::MipsOutputConfig {
-                    use_register_names: true,
-                    allow_virtuals: true,
-                    allow_hidden_instructions: true,
-                    show_block_arguments: true,
-                    show_all_blocks: true,
-                    show_comments: true,
-                }
-            } else {
-                mips_ir::MipsOutputConfig {
-                    use_register_names: true,
-                    allow_virtuals: false,
-                    allow_hidden_instructions: false,
-                    show_block_arguments: false,
-                    show_all_blocks: false,
-                    show_comments: true,
-                }
-            };
+            mips_ir.map(|mir| write_mips_asm(&mir, opts.output_format))
+        }
+        OutputFormat::RiscVAsm => {
+            let mips_ir = res.and_then(|ir| {
+                codegen::mips::build_from_ir(&ir, &opts.settings, source_name, source)
+            });
 
-            mips_ir.map(|mir| {
-                let mut output = String::new();
+            mips_ir.map(|mir| write_riscv_asm(&mir))
+        }
+        OutputFormat::MipsObject => {
+            let mips_ir = res.and_then(|ir| {
+                codegen::mips::build_object_from_ir(&ir, &opts.settings, source_name, source)
+            });
+
+            mips_ir.map(|mir| write_mips_object(&mir))
+        }
+        OutputFormat::X86Asm => {
+            let asm = res.and_then(|ir| {
+                codegen::x86_64::build_from_ir(&ir, &opts.settings, source_name, source)
+            });
 
-                mips_ir::MipsOutputter::new(&mut output)
-                    .with_config(config)
-                    .write_root(&mir)
-                    .unwrap();
+            asm.map(String::into_bytes)
+        }
+        OutputFormat::Wat => {
+            let wat = res.and_then(|ir| {
+                codegen::wasm::build_from_ir(&ir, &opts.settings, source_name, source)
+            });
 
-                output.into_bytes()
-            })
+            wat.map(String::into_bytes)
         }
         _ => unreachable!(
             "Format {:?} should have been handled before",
@@ -278,3 +437,142 @@ fn run_compile(source: &str, source_name: &str, opts: &CompileOpts) -> Aggregate
         ),
     }
 }
+
+/// Drops, keeps or upgrades the recoverable diagnostics, following the levels of the options and
+/// the `#pragma comp diagnostic` lines of the source.
+fn apply_diagnostic_levels<T>(res: &mut AggregateResult<T>, source: &str, opts: &CompileOpts) {
+    let regions = passes::pragma::level_regions(source, &opts.diagnostic_levels);
+    for (_, diagnostic) in regions.diagnostics() {
+        res.add_rec_diagnostic(diagnostic.clone());
+    }
+    let regions = regions.into_value().unwrap();
+
+    res.reclassify_diagnostics(|d| {
+        match regions.levels_at(d.main_span().start()).level(*d.code()) {
+            Level::Ignored => None,
+            Level::Warning => Some(DiagnosticKind::Rec),
+            Level::Error => Some(DiagnosticKind::Err),
+        }
+    });
+    if opts.max_errors > 0 {
+        res.truncate_errors(opts.max_errors);
+    }
+}
+
+/// Builds the AST of the recovered source, which has no syntax errors. The syntax errors have to be
+/// added with [`Recovery::apply`](passes::parse::Recovery::apply) after the passes that should
+/// still check the rest of the source.
+fn build_ast(
+    recovery: &passes::parse::Recovery<'_>,
+    opts: &CompileOpts,
+) -> AggregateResult<ast::Ast> {
+    let cst = passes::parse::parse_to_cst(&recovery.source);
+
+    let mut ast = cst.and_then(|cst| passes::lower_cst::lower(&cst));
+
+    let diagnostics = match ast.value_mut() {
+        Some(ast) if opts.const_fold => passes::const_fold::const_fold(ast, &opts.settings),
+        Some(ast) => passes::const_fold::check_const_exprs(ast, &opts.settings),
+        None => LinkedList::new(),
+    };
+    for diagnostic in diagnostics {
+        ast.add_rec_diagnostic(diagnostic);
+    }
+
+    ast
+}
+
+fn build_ir(ast: &ast::Ast, source: &str, opts: &CompileOpts) -> AggregateResult<ir::Root> {
+    let mut res = passes::lower_ast::build_ir_from_ast(ast, &opts.settings);
+
+    if let Some(ir) = res.value() {
+        let extra_diags = passes::format_string::check_format_strings(ir, source);
+        for diag in extra_diags {
+            res.add_rec_diagnostic(diag);
+        }
+    }
+    if let Some(ir) = res.value() {
+        let extra_diags = passes::unused::find_unused(ir, source);
+        for diag in extra_diags {
+            res.add_rec_diagnostic(diag);
+        }
+    }
+    if let Some(ir) = res.value() {
+        let extra_diags = passes::access::find_invalid_accesses(ir, source);
+        for diag in extra_diags {
+            res.add_rec_diagnostic(diag);
+        }
+    }
+
+    if opts.analyze_control_flow {
+        if let Some(ir) = res.value_mut() {
+            let extra_diags = passes::dead_code_removal::remove_dead_code(ir);
+            for diag in extra_diags {
+                res.add_rec_diagnostic(diag);
+            }
+        }
+        if res.value().is_some() {
+            // The uses found while lowering are only kept when the IR is incomplete
+            res.reclassify_diagnostics(|diagnostic| match diagnostic.code() {
+                Code::UsingUninit => None,
+                _ => Some(DiagnosticKind::Rec),
+            });
+        }
+        if let Some(ir) = res.value() {
+            let extra_diags = passes::uninit::find_uninit_uses(ir, source);
+            for diag in extra_diags {
+                res.add_rec_diagnostic(diag);
+            }
+        }
+    }
+
+    res
+}
+
+fn write_mips_asm(mir: &mips_ir::Root, format: OutputFormat) -> Vec<u8> {
+    let config = if format == OutputFormat::MipsDbg {
+        mips_ir::MipsOutputConfig {
+            use_register_names: true,
+            allow_virtuals: true,
+            allow_hidden_instructions: true,
+            show_block_arguments: true,
+            show_all_blocks: true,
+            show_comments: true,
+        }
+    } else {
+        mips_ir::MipsOutputConfig {
+            use_register_names: true,
+            allow_virtuals: false,
+            allow_hidden_instructions: false,
+            show_block_arguments: false,
+            show_all_blocks: false,
+            show_comments: true,
+        }
+    };
+
+    let mut output = String::new();
+
+    mips_ir::MipsOutputter::new(&mut output)
+        .with_config(config)
+        .write_root(mir)
+        .unwrap();
+
+    output.into_bytes()
+}
+
+fn write_riscv_asm(mir: &mips_ir::Root) -> Vec<u8> {
+    let mut output = String::new();
+
+    mips_ir::RiscVOutputter::new(&mut output)
+        .with_comments(true)
+        .write_root(mir)
+        .unwrap();
+
+    output.into_bytes()
+}
+
+fn write_mips_object(mir: &mips_ir::Root) -> Vec<u8> {
+    let mut output = Vec::new();
+    mips_ir::object::write_object(mir, &mut output).expect("ICE: writing to a Vec can't fail");
+    output
+}
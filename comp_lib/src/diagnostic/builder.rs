@@ -1,4 +1,4 @@
-use super::{Code, Diagnostic, Span};
+use super::{Code, Diagnostic, Fix, Span};
 use crate::ir;
 
 impl DiagnosticBuilder {
@@ -6,6 +6,7 @@ impl DiagnosticBuilder {
         Self {
             span: span.into(),
             additional_spans: Vec::new(),
+            fixes: Vec::new(),
         }
     }
 
@@ -52,12 +53,56 @@ impl DiagnosticBuilder {
         self.add_additional_span(param.span, Some("parameter defined here".to_owned()));
     }
 
+    /// Suggests edits of the source that fix the diagnostic, see [`Fix::is_safe`] for `is_safe`.
+    pub fn add_fix(&mut self, message: String, edits: Vec<(Span, String)>, is_safe: bool) {
+        self.fixes.push(Fix {
+            message,
+            edits,
+            is_safe,
+        });
+    }
+
+    /// Suggests inserting `prefix` before the span.
+    fn add_prefix_fix(&mut self, message: String, span: Span, prefix: &str, is_safe: bool) {
+        let start = span.start()..span.start();
+        self.add_fix(message, vec![(start.into(), prefix.to_owned())], is_safe);
+    }
+
+    /// Suggests surrounding the span with `prefix` and `)`.
+    fn add_parenthesized_fix(&mut self, message: String, span: Span, prefix: &str, is_safe: bool) {
+        let start = span.start()..span.start();
+        let end = span.excl_end()..span.excl_end();
+        self.add_fix(
+            message,
+            vec![
+                (start.into(), prefix.to_owned()),
+                (end.into(), ")".to_owned()),
+            ],
+            is_safe,
+        );
+    }
+
+    /// Suggests casting the expression to `ty`, the expression is only parenthesized if it has to.
+    fn add_cast_fix(
+        &mut self,
+        message: String,
+        expr: &ir::expr::ExprNode,
+        ty: &ir::ctype::CType,
+        is_safe: bool,
+    ) {
+        match needs_parentheses(expr) {
+            true => self.add_parenthesized_fix(message, expr.span, &format!("({ty})("), is_safe),
+            false => self.add_prefix_fix(message, expr.span, &format!("({ty})"), is_safe),
+        }
+    }
+
     fn build_custom(self, code: Code, message: String) -> Diagnostic {
         Diagnostic {
             code,
             message,
             main_span: (self.span, None),
             additional_spans: self.additional_spans,
+            fixes: self.fixes,
         }
     }
 
@@ -196,8 +241,21 @@ impl DiagnosticBuilder {
         )
     }
 
-    pub fn build_need_lvalue(mut self, expr_type: &str, inner_span: Span) -> Diagnostic {
-        // IDEA: If the inner expr is a pointer, suggest dereferencing.
+    pub fn build_need_lvalue(
+        mut self,
+        expr_type: &str,
+        inner_span: Span,
+        inner_is_pointer: bool,
+    ) -> Diagnostic {
+        if inner_is_pointer {
+            // The operand is a unary expression, so `*` can always be put before it, but a postfix
+            // operator binds stronger than `*`
+            let message = "dereference the pointer".to_owned();
+            match inner_span.excl_end() <= self.span.start() {
+                true => self.add_parenthesized_fix(message, inner_span, "(*", false),
+                false => self.add_prefix_fix(message, inner_span, "*", false),
+            }
+        }
         self.add_additional_span(
             inner_span,
             Some("this expression is not a lvalue, but needs to be".to_owned()),
@@ -250,9 +308,34 @@ impl DiagnosticBuilder {
             to_expr.span,
             Some(format!("while this has type: `{}`", to_expr.ty)),
         );
+        // The explicit cast does the same conversion, so it's safe to apply.
+        self.add_cast_fix(
+            format!("cast to `{}` explicitly", to_expr.ty),
+            from_expr,
+            &to_expr.ty,
+            true,
+        );
         self.build_custom(Code::LossyImplicitAssign, message)
     }
 
+    /// `to_span` is where the type that the constant is converted to comes from, `converted` is
+    /// the value after the conversion if it is defined.
+    pub fn build_constant_conversion(
+        mut self,
+        to_span: Span,
+        to_ty: &ir::ctype::CType,
+        converted: Option<i128>,
+    ) -> Diagnostic {
+        self.add_additional_span(to_span, Some(format!("converted to `{to_ty}` here")));
+        let message = match converted {
+            Some(converted) => {
+                format!("implicit conversion to `{to_ty}` changes the constant to {converted}")
+            }
+            None => format!("constant is out of range for `{to_ty}`"),
+        };
+        self.build_custom(Code::ConstantConversion, message)
+    }
+
     pub fn build_incompatible_assign(
         mut self,
         from_expr: &ir::expr::ExprNode,
@@ -263,6 +346,22 @@ impl DiagnosticBuilder {
             to_expr.span,
             Some(format!("while this has type: `{}`", to_expr.ty)),
         );
+        use ir::ctype::{CType, Scalar};
+        let is_floating =
+            |ty: &CType| matches!(ty, CType::Scalar(Scalar::Arithmetic(a)) if a.is_floating());
+        let is_pointer = |ty: &CType| matches!(ty, CType::Scalar(Scalar::Pointer(_)));
+        // Floats and pointers can't be cast to each other.
+        if !(is_floating(&from_expr.ty) && is_pointer(&to_expr.ty)
+            || is_pointer(&from_expr.ty) && is_floating(&to_expr.ty))
+        {
+            // The cast only silences the error, so it's never applied automatically.
+            self.add_cast_fix(
+                format!("cast to `{}` explicitly", to_expr.ty),
+                from_expr,
+                &to_expr.ty,
+                false,
+            );
+        }
         self.build_custom(
             Code::IncompatibleAssign,
             format!(
@@ -280,9 +379,25 @@ impl DiagnosticBuilder {
         )
     }
 
-    pub fn build_assign_const_loss(mut self, with_const: Span, without_const: Span) -> Diagnostic {
-        self.add_additional_span(with_const, Some("this points to a const value".to_owned()));
-        self.add_additional_span(without_const, Some("while this doesn't".to_owned()));
+    pub fn build_assign_const_loss(
+        mut self,
+        with_const: &ir::expr::ExprNode,
+        without_const: &ir::expr::LvalueExprNode,
+    ) -> Diagnostic {
+        self.add_additional_span(
+            with_const.span,
+            Some("this points to a const value".to_owned()),
+        );
+        self.add_additional_span(without_const.span, Some("while this doesn't".to_owned()));
+        self.add_cast_fix(
+            format!(
+                "cast to `{}` to discard the const qualifier explicitly",
+                without_const.ty
+            ),
+            with_const,
+            &without_const.ty,
+            false,
+        );
         self.build_custom(
             Code::AssignConstLoss,
             "assign loses const qualifier".to_owned(),
@@ -309,6 +424,12 @@ impl DiagnosticBuilder {
         };
         self.add_ir_expr_type(from_expr);
         self.add_ir_return_type(to_span, to_type);
+        self.add_cast_fix(
+            format!("cast to `{to_type}` explicitly"),
+            from_expr,
+            to_type,
+            true,
+        );
         self.build_custom(Code::LossyImplicitReturn, message)
     }
 
@@ -363,6 +484,12 @@ impl DiagnosticBuilder {
         };
         self.add_ir_expr_type(from_expr);
         self.add_ir_param_type(param);
+        self.add_cast_fix(
+            format!("cast to `{}` explicitly", param.ty),
+            from_expr,
+            &param.ty,
+            true,
+        );
         self.build_custom(Code::LossyImplicitArg, message)
     }
 
@@ -423,15 +550,26 @@ impl DiagnosticBuilder {
         )
     }
 
-    pub fn build_too_big_constant(self, value: i128) -> Diagnostic {
+    pub fn build_too_big_constant(self, value: impl std::fmt::Display) -> Diagnostic {
         self.build_custom(
             Code::TooBigConstant,
             format!("constant {value} cannot be correctly represented in any c type"),
         )
     }
 
-    pub fn build_undeclared_ident(self, name: &str) -> Diagnostic {
-        // IDEA: give possible alternative names that are close
+    /// `in_scope` are the names that could be used instead, a similar one is suggested.
+    pub fn build_undeclared_ident<'a>(
+        mut self,
+        name: &str,
+        in_scope: impl IntoIterator<Item = &'a str>,
+    ) -> Diagnostic {
+        if let Some(similar) = similar_name(name, in_scope) {
+            self.add_fix(
+                format!("a similar name exists: `{similar}`"),
+                vec![(self.span, similar.to_owned())],
+                false,
+            );
+        }
         self.build_custom(
             Code::UndeclaredIdent,
             format!("identifier `{name}` isn't declared in this scope"),
@@ -454,13 +592,209 @@ impl DiagnosticBuilder {
         )
     }
 
-    pub fn build_usign_uninit(self, name: &str) -> Diagnostic {
+    /// `declaration` is the span of the identifier in the declaration of the variable.
+    pub fn build_usign_uninit(mut self, name: &str, declaration: Span) -> Diagnostic {
+        self.add_additional_span(
+            declaration,
+            Some(format!("`{name}` is declared here without a value")),
+        );
+        self.build_custom(Code::UsingUninit, format!("`{name}` is used uninitialized"))
+    }
+
+    /// `path` are the branches taken on a path where the variable isn't initialized, e.g. the
+    /// span of a condition with `if this condition is false`.
+    pub fn build_maybe_uninit(
+        mut self,
+        name: &str,
+        declaration: Span,
+        path: &[(Span, &str)],
+    ) -> Diagnostic {
+        self.add_additional_span(
+            declaration,
+            Some(format!("`{name}` is declared here without a value")),
+        );
+        for (span, branch) in path {
+            self.add_additional_span(*span, Some(format!("`{name}` isn't initialized {branch}")));
+        }
+        self.build_custom(
+            Code::MaybeUninit,
+            format!("`{name}` may be used uninitialized"),
+        )
+    }
+
+    pub fn build_format_missing_arg(self, conversion: &str, function: &str) -> Diagnostic {
+        self.build_custom(
+            Code::FormatArgCount,
+            format!("`{conversion}` has no matching argument in this call to `{function}`"),
+        )
+    }
+
+    pub fn build_format_extra_args(mut self, format: Span, function: &str) -> Diagnostic {
+        self.add_additional_span(
+            format,
+            Some("the format string doesn't use these arguments".to_owned()),
+        );
+        self.build_custom(
+            Code::FormatArgCount,
+            format!("more arguments than the format string of `{function}` uses"),
+        )
+    }
+
+    pub fn build_format_arg_type(
+        mut self,
+        conversion: &str,
+        expected: &str,
+        arg: &ir::ExprNode,
+    ) -> Diagnostic {
+        self.add_ir_expr_type(arg);
+        self.build_custom(
+            Code::FormatArgType,
+            format!(
+                "`{conversion}` expects an argument of type `{expected}`, but the argument has type `{}`",
+                arg.ty
+            ),
+        )
+    }
+
+    /// `fixable` if taking the address of the argument gives the type the conversion expects.
+    pub fn build_scanf_missing_address(
+        mut self,
+        conversion: &str,
+        arg: &ir::ExprNode,
+        fixable: bool,
+    ) -> Diagnostic {
+        self.add_ir_expr_type(arg);
+        if fixable {
+            let start = arg.span.start();
+            self.add_fix(
+                "pass the address of the variable".to_owned(),
+                vec![((start..start).into(), "&".to_owned())],
+                false,
+            );
+        }
+        self.build_custom(
+            Code::ScanfMissingAddress,
+            format!(
+                "`{conversion}` needs a pointer to store the value in, but the argument has type `{}`",
+                arg.ty
+            ),
+        )
+    }
+
+    pub fn build_unsupported_format(self, conversion: &str, function: &str) -> Diagnostic {
         self.build_custom(
-            Code::UsingUninit,
-            format!("`{name}` is possibly uninitialized"),
+            Code::UnsupportedFormat,
+            format!("`{conversion}` isn't supported by the `{function}` of the MIPS runtime"),
         )
     }
 
+    pub fn build_unused_var(mut self, name: &str, last_assign: Option<Span>) -> Diagnostic {
+        let message = match last_assign {
+            Some(last_assign) => {
+                self.add_additional_span(last_assign, Some("last assigned here".to_owned()));
+                format!("`{name}` is assigned but its value is never used")
+            }
+            None => format!("`{name}` is never used"),
+        };
+        self.build_custom(Code::UnusedVariable, message)
+    }
+
+    pub fn build_unused_param(mut self, name: &str, last_assign: Option<Span>) -> Diagnostic {
+        let message = match last_assign {
+            Some(last_assign) => {
+                self.add_additional_span(last_assign, Some("last assigned here".to_owned()));
+                format!("parameter `{name}` is assigned but its value is never used")
+            }
+            None => format!("parameter `{name}` is never used"),
+        };
+        self.build_custom(Code::UnusedParameter, message)
+    }
+
+    /// `comparison` is the span of a `==` that was probably meant to be a `=`.
+    pub fn build_no_effect(mut self, comparison: Option<Span>) -> Diagnostic {
+        if let Some(comparison) = comparison {
+            self.add_fix(
+                "use `=` to assign instead of comparing".to_owned(),
+                vec![(comparison, "=".to_owned())],
+                false,
+            );
+        }
+        self.build_custom(Code::NoEffect, "statement has no effect".to_owned())
+    }
+
+    /// `array` is the name and declaration of the array, if it is a variable, otherwise `array_span`
+    /// is the span of the array that is indexed.
+    pub fn build_array_out_of_bounds(
+        mut self,
+        index: i128,
+        length: u128,
+        array: Option<(&str, Span)>,
+        array_span: Span,
+    ) -> Diagnostic {
+        let elements = match length {
+            1 => "1 element".to_owned(),
+            length => format!("{length} elements"),
+        };
+        match array {
+            Some((name, declaration)) => self.add_additional_span(
+                declaration,
+                Some(format!("`{name}` is declared here with {elements}")),
+            ),
+            None => {
+                self.add_additional_span(array_span, Some(format!("this array has {elements}")))
+            }
+        }
+        let position = match index < 0 {
+            true => "before the start",
+            false => "past the end",
+        };
+        self.build_custom(
+            Code::ArrayOutOfBounds,
+            format!("array index {index} is {position} of the array"),
+        )
+    }
+
+    /// `null_assign` is the span of the assignment of the null pointer.
+    pub fn build_null_deref(mut self, name: &str, null_assign: Span) -> Diagnostic {
+        self.add_additional_span(null_assign, Some(format!("`{name}` is set to null here")));
+        self.build_custom(
+            Code::NullDereference,
+            format!("`{name}` is dereferenced while it is null"),
+        )
+    }
+
+    /// `wrapped` is the value the result wraps around to.
+    pub fn build_integer_overflow(self, ty: ir::ctype::Arithmetic, wrapped: i128) -> Diagnostic {
+        self.build_custom(
+            Code::IntegerOverflow,
+            format!("integer overflow in expression of type `{ty}` results in {wrapped}"),
+        )
+    }
+
+    pub fn build_division_by_zero(self, is_modulo: bool) -> Diagnostic {
+        let operation = match is_modulo {
+            true => "modulo",
+            false => "division",
+        };
+        self.build_custom(Code::DivisionByZero, format!("{operation} by zero"))
+    }
+
+    /// `ty` is the promoted type of the value that is shifted, which has `bits` bits.
+    pub fn build_shift_count_overflow(
+        self,
+        count: i128,
+        ty: ir::ctype::Arithmetic,
+        bits: u32,
+    ) -> Diagnostic {
+        let message = match count < 0 {
+            true => format!("shift count {count} is negative"),
+            false => {
+                format!("shift count {count} is not less than the width of `{ty}` ({bits} bits)")
+            }
+        };
+        self.build_custom(Code::ShiftCountOverflow, message)
+    }
+
     pub fn build_invalid_break(self) -> Diagnostic {
         self.build_custom(
             Code::InvalidJumpStmt,
@@ -599,6 +933,13 @@ impl DiagnosticBuilder {
         return_type: &ir::ctype::CType,
     ) -> Diagnostic {
         self.add_ir_return_type(return_span, return_type);
+        // Zero is a valid value for all arithmetic and pointer types.
+        let end = self.span.excl_end();
+        self.add_fix(
+            "return a value".to_owned(),
+            vec![((end..end).into(), " 0".to_owned())],
+            false,
+        );
         self.build_custom(
             Code::NoReturnValue,
             format!("empty return used in function returning `{}`", return_type),
@@ -742,11 +1083,68 @@ impl DiagnosticBuilder {
         self.add_additional_span(array_span, None);
         self.build_custom(Code::VoidArray, "array of incomplete type void".to_string())
     }
+
+    pub fn build_link_error(self, reason: &str) -> Diagnostic {
+        self.build_custom(Code::LinkError, format!("linking failed: {reason}"))
+    }
+
+    pub fn build_invalid_pragma(self, reason: &str) -> Diagnostic {
+        self.build_custom(
+            Code::InvalidPragma,
+            format!("invalid diagnostic pragma: {reason}"),
+        )
+    }
+}
+
+/// The name closest to `name`, if it is close enough to be a typo.
+fn similar_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Whether the expression has to be parenthesized to be the operand of a cast. An operator that is
+/// already parenthesized in the source has a span that starts before its operands. A constant
+/// can be the result of folding any expression, so it's always parenthesized.
+fn needs_parentheses(expr: &ir::expr::ExprNode) -> bool {
+    use ir::expr::Expr;
+    let first_operand = match &expr.expr {
+        Expr::Binary(left, _, right)
+        | Expr::Relation(left, _, right)
+        | Expr::LogicalAnd(left, right)
+        | Expr::LogicalOr(left, right) => left.span.start().min(right.span.start()),
+        Expr::Assign(left, _) => left.span.start(),
+        // An implicit cast has the span of its operand
+        Expr::Cast(inner) if inner.span == expr.span => return needs_parentheses(inner),
+        Expr::Constant(_) => return true,
+        _ => return false,
+    };
+    first_operand == expr.span.start()
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(a_char != *b_char);
+            current.push(substitute.min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
 }
 
 pub struct DiagnosticBuilder {
     span: Span,
     additional_spans: Vec<(Span, Option<String>)>,
+    fixes: Vec<Fix>,
 }
 
 #[derive(Debug, Copy, Clone)]
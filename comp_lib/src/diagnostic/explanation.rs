@@ -0,0 +1,92 @@
+//! The long-form explanations of the codes, as shown by `comp --explain`.
+//!
+//! Every explanation is a Markdown file in `explanations/`, named after the [name](Code::name) of
+//! its code. Apart from [`Code::Unspecified`], the first `c` code block is an erroneous example and
+//! the second one the corrected example.
+
+use super::Code;
+
+impl Code {
+    /// A long-form explanation of the code with an example, in Markdown.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Code::Unspecified => include_str!("explanations/unspecified.md"),
+            Code::SyntaxError => include_str!("explanations/syntax-error.md"),
+            Code::Unimplemented => include_str!("explanations/unimplemented.md"),
+            Code::DuplicateQualifier => include_str!("explanations/duplicate-qualifier.md"),
+            Code::MultiByteChar => include_str!("explanations/multichar.md"),
+            Code::UnspecifiedType => include_str!("explanations/implicit-int.md"),
+            Code::UnknownEscapeSequence => include_str!("explanations/unknown-escape-sequence.md"),
+            Code::IncompleteEscapeSequence => {
+                include_str!("explanations/incomplete-escape-sequence.md")
+            }
+            Code::EscapeSequenceOutOfRange => {
+                include_str!("explanations/escape-sequence-out-of-range.md")
+            }
+            Code::EmbeddedNullInString => include_str!("explanations/embedded-null.md"),
+            Code::NeedConst => include_str!("explanations/need-const.md"),
+            Code::UnexpectedType => include_str!("explanations/unexpected-type.md"),
+            Code::NeedLvalue => include_str!("explanations/need-lvalue.md"),
+            Code::InvalidCast => include_str!("explanations/invalid-cast.md"),
+            Code::LossyImplicitAssign => include_str!("explanations/lossy-assign.md"),
+            Code::IncompatibleAssign => include_str!("explanations/incompatible-assign.md"),
+            Code::TooBigConstant => include_str!("explanations/too-big-constant.md"),
+            Code::UndeclaredIdent => include_str!("explanations/undeclared-ident.md"),
+            Code::AlreadyDefined => include_str!("explanations/already-defined.md"),
+            Code::UsingUninit => include_str!("explanations/uninitialized.md"),
+            Code::DuplicateDefault => include_str!("explanations/duplicate-default.md"),
+            Code::InvalidJumpStmt => include_str!("explanations/invalid-jump.md"),
+            Code::SwitchCaseNotFolded => include_str!("explanations/case-not-constant.md"),
+            Code::SwitchCaseNotInt => include_str!("explanations/case-not-int.md"),
+            Code::Unreachable => include_str!("explanations/unreachable-code.md"),
+            Code::InvalidArraySize => include_str!("explanations/invalid-array-size.md"),
+            Code::NonConstGlobalInitializer => {
+                include_str!("explanations/non-const-global-initializer.md")
+            }
+            Code::AssignConstLoss => include_str!("explanations/assign-discards-const.md"),
+            Code::LossyImplicitReturn => include_str!("explanations/lossy-return.md"),
+            Code::IncompatibleReturn => include_str!("explanations/incompatible-return.md"),
+            Code::ReturnConstLoss => include_str!("explanations/return-discards-const.md"),
+            Code::UndeclaredFunction => include_str!("explanations/undeclared-function.md"),
+            Code::WrongAmountOfArgs => include_str!("explanations/wrong-amount-of-args.md"),
+            Code::LossyImplicitArg => include_str!("explanations/lossy-arg.md"),
+            Code::IncompatibleArg => include_str!("explanations/incompatible-arg.md"),
+            Code::ArgConstLoss => include_str!("explanations/arg-discards-const.md"),
+            Code::IncompatibleSpecifiers => include_str!("explanations/incompatible-specifiers.md"),
+            Code::QualifiedVoid => include_str!("explanations/qualified-void.md"),
+            Code::VoidUsed => include_str!("explanations/void-used.md"),
+            Code::VoidVariable => include_str!("explanations/void-variable.md"),
+            Code::ValueReturnInVoid => include_str!("explanations/return-value-in-void.md"),
+            Code::NoReturnValue => include_str!("explanations/no-return-value.md"),
+            Code::NotAlwaysReturn => include_str!("explanations/missing-return.md"),
+            Code::IncompatibleGlobalDef => include_str!("explanations/incompatible-global-def.md"),
+            Code::IncompatibleFunctionRedef => {
+                include_str!("explanations/incompatible-function-redef.md")
+            }
+            Code::ClashingGlobalName => include_str!("explanations/clashing-global-name.md"),
+            Code::IncompatibleVariableRedef => {
+                include_str!("explanations/incompatible-variable-redef.md")
+            }
+            Code::MultipleVariableDef => include_str!("explanations/multiple-variable-def.md"),
+            Code::MultipleFunctionDef => include_str!("explanations/multiple-function-def.md"),
+            Code::MultipleSameCase => include_str!("explanations/duplicate-case.md"),
+            Code::VoidArray => include_str!("explanations/void-array.md"),
+            Code::LinkError => include_str!("explanations/link-error.md"),
+            Code::InvalidPragma => include_str!("explanations/invalid-pragma.md"),
+            Code::MaybeUninit => include_str!("explanations/maybe-uninitialized.md"),
+            Code::FormatArgCount => include_str!("explanations/format-arg-count.md"),
+            Code::FormatArgType => include_str!("explanations/format-arg-type.md"),
+            Code::ScanfMissingAddress => include_str!("explanations/scanf-missing-address.md"),
+            Code::UnsupportedFormat => include_str!("explanations/unsupported-format.md"),
+            Code::UnusedVariable => include_str!("explanations/unused-variable.md"),
+            Code::UnusedParameter => include_str!("explanations/unused-parameter.md"),
+            Code::NoEffect => include_str!("explanations/unused-value.md"),
+            Code::ArrayOutOfBounds => include_str!("explanations/array-bounds.md"),
+            Code::NullDereference => include_str!("explanations/null-dereference.md"),
+            Code::IntegerOverflow => include_str!("explanations/overflow.md"),
+            Code::DivisionByZero => include_str!("explanations/div-by-zero.md"),
+            Code::ShiftCountOverflow => include_str!("explanations/shift-count-overflow.md"),
+            Code::ConstantConversion => include_str!("explanations/constant-conversion.md"),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Decides how the recoverable diagnostics are reported, following the `-W` flags of gcc.
+//!
+//! A flag is written without the `-W`, e.g. `conversion`, `no-uninitialized` or
+//! `error=incompatible-assign`, and names a [`Code`] by its [name](Code::name) or a group of codes.
+//! Non-recoverable diagnostics are always reported as errors, since there is no value to continue
+//! with.
+
+use super::Code;
+use std::collections::HashMap;
+
+/// How a recoverable diagnostic is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Ignored,
+    Warning,
+    Error,
+}
+
+/// The codes that are reported as warnings when nothing upgrades them.
+const WARNINGS: &[Code] = &[
+    Code::DuplicateQualifier,
+    Code::MultiByteChar,
+    Code::UnspecifiedType,
+    Code::UnknownEscapeSequence,
+    Code::IncompleteEscapeSequence,
+    Code::EscapeSequenceOutOfRange,
+    Code::EmbeddedNullInString,
+    Code::LossyImplicitAssign,
+    Code::IncompatibleAssign,
+    Code::UsingUninit,
+    Code::Unreachable,
+    Code::AssignConstLoss,
+    Code::LossyImplicitReturn,
+    Code::IncompatibleReturn,
+    Code::ReturnConstLoss,
+    Code::LossyImplicitArg,
+    Code::IncompatibleArg,
+    Code::ArgConstLoss,
+    Code::NotAlwaysReturn,
+    Code::InvalidPragma,
+    Code::MaybeUninit,
+    Code::FormatArgCount,
+    Code::FormatArgType,
+    Code::ScanfMissingAddress,
+    Code::UnsupportedFormat,
+    Code::ArrayOutOfBounds,
+    Code::NullDereference,
+    Code::IntegerOverflow,
+    Code::DivisionByZero,
+    Code::ShiftCountOverflow,
+    Code::ConstantConversion,
+];
+
+/// The warnings that are only reported when they are enabled, with `-Wextra`, `-Wunused` or their
+/// own name.
+const OFF_BY_DEFAULT: &[Code] = &[Code::UnusedVariable, Code::UnusedParameter, Code::NoEffect];
+
+const EXTRA_WARNINGS: [Code; WARNINGS.len() + OFF_BY_DEFAULT.len()] =
+    concat(WARNINGS, OFF_BY_DEFAULT);
+
+const fn concat<const N: usize>(first: &[Code], second: &[Code]) -> [Code; N] {
+    let mut codes = [Code::Unspecified; N];
+    let mut i = 0;
+    while i < N {
+        codes[i] = match i < first.len() {
+            true => first[i],
+            false => second[i - first.len()],
+        };
+        i += 1;
+    }
+    codes
+}
+
+/// The named groups of codes, `-Wall` has the warnings that are enabled by default and `-Wextra`
+/// also the ones that aren't.
+pub const GROUPS: &[(&str, &[Code])] = &[
+    (
+        "conversion",
+        &[
+            Code::LossyImplicitAssign,
+            Code::LossyImplicitReturn,
+            Code::LossyImplicitArg,
+            Code::ConstantConversion,
+        ],
+    ),
+    (
+        "discarded-qualifiers",
+        &[
+            Code::AssignConstLoss,
+            Code::ReturnConstLoss,
+            Code::ArgConstLoss,
+        ],
+    ),
+    (
+        "incompatible-types",
+        &[
+            Code::IncompatibleAssign,
+            Code::IncompatibleReturn,
+            Code::IncompatibleArg,
+        ],
+    ),
+    (
+        "escape-sequences",
+        &[
+            Code::UnknownEscapeSequence,
+            Code::IncompleteEscapeSequence,
+            Code::EscapeSequenceOutOfRange,
+            Code::EmbeddedNullInString,
+        ],
+    ),
+    (
+        "format",
+        &[
+            Code::FormatArgCount,
+            Code::FormatArgType,
+            Code::ScanfMissingAddress,
+            Code::UnsupportedFormat,
+        ],
+    ),
+    (
+        "unused",
+        &[Code::UnusedVariable, Code::UnusedParameter, Code::NoEffect],
+    ),
+    ("all", WARNINGS),
+    ("extra", &EXTRA_WARNINGS),
+];
+
+/// The codes with the given name, which is either the name of a code or a group.
+pub fn codes_by_name(name: &str) -> Option<Vec<Code>> {
+    match GROUPS.iter().find(|(group, _)| *group == name) {
+        Some((_, codes)) => Some(codes.to_vec()),
+        None => Code::from_name(name).map(|code| vec![code]),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownFlag(pub String);
+
+impl std::fmt::Display for UnknownFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown warning option `-W{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFlag {}
+
+/// The level of every code, only codes that were changed are stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagnosticLevels {
+    enabled: HashMap<Code, bool>,
+    as_error: HashMap<Code, bool>,
+    warnings_as_errors: bool,
+    inhibit_warnings: bool,
+}
+
+impl DiagnosticLevels {
+    /// Every warning except the [off by default](OFF_BY_DEFAULT) ones is enabled and no warning is
+    /// an error.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn level(&self, code: Code) -> Level {
+        let as_error = *self.as_error.get(&code).unwrap_or(&self.warnings_as_errors);
+        if as_error && self.is_enabled(code) {
+            Level::Error
+        } else if self.inhibit_warnings || !self.is_enabled(code) {
+            Level::Ignored
+        } else {
+            Level::Warning
+        }
+    }
+
+    fn is_enabled(&self, code: Code) -> bool {
+        *self
+            .enabled
+            .get(&code)
+            .unwrap_or(&!OFF_BY_DEFAULT.contains(&code))
+    }
+
+    /// Enables or disables a code, like `-W<name>` and `-Wno-<name>`.
+    pub fn enable(&mut self, code: Code, enabled: bool) {
+        self.enabled.insert(code, enabled);
+    }
+
+    /// Reports a code as an error or a warning, like `-Werror=<name>` and `-Wno-error=<name>`.
+    /// Reporting it as an error also enables the code.
+    pub fn set_error(&mut self, code: Code, as_error: bool) {
+        if as_error {
+            self.enable(code, true);
+        }
+        self.as_error.insert(code, as_error);
+    }
+
+    /// Reports all warnings as errors, like `-Werror`.
+    pub fn set_warnings_as_errors(&mut self, warnings_as_errors: bool) {
+        self.warnings_as_errors = warnings_as_errors;
+    }
+
+    /// Drops all warnings that aren't reported as errors, like `-w`.
+    pub fn set_inhibit_warnings(&mut self, inhibit_warnings: bool) {
+        self.inhibit_warnings = inhibit_warnings;
+    }
+
+    /// Applies a flag without the `-W`, e.g. `no-conversion`.
+    pub fn apply_flag(&mut self, flag: &str) -> Result<(), UnknownFlag> {
+        let (enabled, name) = match flag.strip_prefix("no-") {
+            Some(name) => (false, name),
+            None => (true, flag),
+        };
+        if name == "error" {
+            self.set_warnings_as_errors(enabled);
+            return Ok(());
+        }
+        let (as_error, name) = match name.strip_prefix("error=") {
+            Some(name) => (true, name),
+            None => (false, name),
+        };
+        let codes = codes_by_name(name).ok_or_else(|| UnknownFlag(flag.to_owned()))?;
+        for code in codes {
+            if as_error {
+                self.set_error(code, enabled);
+            } else {
+                self.enable(code, enabled);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
 pub mod builder;
+mod explanation;
+pub mod levels;
 
 use std::{
     collections::LinkedList,
     fmt::{Debug, Display},
+    str::FromStr,
 };
 
 pub use builder::DiagnosticBuilder;
+pub use levels::{DiagnosticLevels, Level};
 
-#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct Span {
     start: usize,
     length: usize,
@@ -118,13 +122,180 @@ pub enum Code {
     MultipleFunctionDef,
     MultipleSameCase,
     VoidArray,
+    LinkError,
+    InvalidPragma,
+    MaybeUninit,
+    FormatArgCount,
+    FormatArgType,
+    ScanfMissingAddress,
+    UnsupportedFormat,
+    UnusedVariable,
+    UnusedParameter,
+    NoEffect,
+    ArrayOutOfBounds,
+    NullDereference,
+    IntegerOverflow,
+    DivisionByZero,
+    ShiftCountOverflow,
+    ConstantConversion,
 }
 
 impl Code {
+    /// All codes, in the order of their numeric code.
+    pub const ALL: [Code; 67] = [
+        Code::Unspecified,
+        Code::SyntaxError,
+        Code::Unimplemented,
+        Code::DuplicateQualifier,
+        Code::MultiByteChar,
+        Code::UnspecifiedType,
+        Code::UnknownEscapeSequence,
+        Code::IncompleteEscapeSequence,
+        Code::EscapeSequenceOutOfRange,
+        Code::EmbeddedNullInString,
+        Code::NeedConst,
+        Code::UnexpectedType,
+        Code::NeedLvalue,
+        Code::InvalidCast,
+        Code::LossyImplicitAssign,
+        Code::IncompatibleAssign,
+        Code::TooBigConstant,
+        Code::UndeclaredIdent,
+        Code::AlreadyDefined,
+        Code::UsingUninit,
+        Code::DuplicateDefault,
+        Code::InvalidJumpStmt,
+        Code::SwitchCaseNotFolded,
+        Code::SwitchCaseNotInt,
+        Code::Unreachable,
+        Code::InvalidArraySize,
+        Code::NonConstGlobalInitializer,
+        Code::AssignConstLoss,
+        Code::LossyImplicitReturn,
+        Code::IncompatibleReturn,
+        Code::ReturnConstLoss,
+        Code::UndeclaredFunction,
+        Code::WrongAmountOfArgs,
+        Code::LossyImplicitArg,
+        Code::IncompatibleArg,
+        Code::ArgConstLoss,
+        Code::IncompatibleSpecifiers,
+        Code::QualifiedVoid,
+        Code::VoidUsed,
+        Code::VoidVariable,
+        Code::ValueReturnInVoid,
+        Code::NoReturnValue,
+        Code::NotAlwaysReturn,
+        Code::IncompatibleGlobalDef,
+        Code::IncompatibleFunctionRedef,
+        Code::ClashingGlobalName,
+        Code::IncompatibleVariableRedef,
+        Code::MultipleVariableDef,
+        Code::MultipleFunctionDef,
+        Code::MultipleSameCase,
+        Code::VoidArray,
+        Code::LinkError,
+        Code::InvalidPragma,
+        Code::MaybeUninit,
+        Code::FormatArgCount,
+        Code::FormatArgType,
+        Code::ScanfMissingAddress,
+        Code::UnsupportedFormat,
+        Code::UnusedVariable,
+        Code::UnusedParameter,
+        Code::NoEffect,
+        Code::ArrayOutOfBounds,
+        Code::NullDereference,
+        Code::IntegerOverflow,
+        Code::DivisionByZero,
+        Code::ShiftCountOverflow,
+        Code::ConstantConversion,
+    ];
+
     /// Get a unique numeric code for this `Code`
     fn as_code(&self) -> u32 {
         *self as u32
     }
+
+    /// The human readable name, as used by the `-W` flags and `#pragma comp diagnostic`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Code::Unspecified => "unspecified",
+            Code::SyntaxError => "syntax-error",
+            Code::Unimplemented => "unimplemented",
+            Code::DuplicateQualifier => "duplicate-qualifier",
+            Code::MultiByteChar => "multichar",
+            Code::UnspecifiedType => "implicit-int",
+            Code::UnknownEscapeSequence => "unknown-escape-sequence",
+            Code::IncompleteEscapeSequence => "incomplete-escape-sequence",
+            Code::EscapeSequenceOutOfRange => "escape-sequence-out-of-range",
+            Code::EmbeddedNullInString => "embedded-null",
+            Code::NeedConst => "need-const",
+            Code::UnexpectedType => "unexpected-type",
+            Code::NeedLvalue => "need-lvalue",
+            Code::InvalidCast => "invalid-cast",
+            Code::LossyImplicitAssign => "lossy-assign",
+            Code::IncompatibleAssign => "incompatible-assign",
+            Code::TooBigConstant => "too-big-constant",
+            Code::UndeclaredIdent => "undeclared-ident",
+            Code::AlreadyDefined => "already-defined",
+            Code::UsingUninit => "uninitialized",
+            Code::DuplicateDefault => "duplicate-default",
+            Code::InvalidJumpStmt => "invalid-jump",
+            Code::SwitchCaseNotFolded => "case-not-constant",
+            Code::SwitchCaseNotInt => "case-not-int",
+            Code::Unreachable => "unreachable-code",
+            Code::InvalidArraySize => "invalid-array-size",
+            Code::NonConstGlobalInitializer => "non-const-global-initializer",
+            Code::AssignConstLoss => "assign-discards-const",
+            Code::LossyImplicitReturn => "lossy-return",
+            Code::IncompatibleReturn => "incompatible-return",
+            Code::ReturnConstLoss => "return-discards-const",
+            Code::UndeclaredFunction => "undeclared-function",
+            Code::WrongAmountOfArgs => "wrong-amount-of-args",
+            Code::LossyImplicitArg => "lossy-arg",
+            Code::IncompatibleArg => "incompatible-arg",
+            Code::ArgConstLoss => "arg-discards-const",
+            Code::IncompatibleSpecifiers => "incompatible-specifiers",
+            Code::QualifiedVoid => "qualified-void",
+            Code::VoidUsed => "void-used",
+            Code::VoidVariable => "void-variable",
+            Code::ValueReturnInVoid => "return-value-in-void",
+            Code::NoReturnValue => "no-return-value",
+            Code::NotAlwaysReturn => "missing-return",
+            Code::IncompatibleGlobalDef => "incompatible-global-def",
+            Code::IncompatibleFunctionRedef => "incompatible-function-redef",
+            Code::ClashingGlobalName => "clashing-global-name",
+            Code::IncompatibleVariableRedef => "incompatible-variable-redef",
+            Code::MultipleVariableDef => "multiple-variable-def",
+            Code::MultipleFunctionDef => "multiple-function-def",
+            Code::MultipleSameCase => "duplicate-case",
+            Code::VoidArray => "void-array",
+            Code::LinkError => "link-error",
+            Code::InvalidPragma => "invalid-pragma",
+            Code::MaybeUninit => "maybe-uninitialized",
+            Code::FormatArgCount => "format-arg-count",
+            Code::FormatArgType => "format-arg-type",
+            Code::ScanfMissingAddress => "scanf-missing-address",
+            Code::UnsupportedFormat => "unsupported-format",
+            Code::UnusedVariable => "unused-variable",
+            Code::UnusedParameter => "unused-parameter",
+            Code::NoEffect => "unused-value",
+            Code::ArrayOutOfBounds => "array-bounds",
+            Code::NullDereference => "null-dereference",
+            Code::IntegerOverflow => "overflow",
+            Code::DivisionByZero => "div-by-zero",
+            Code::ShiftCountOverflow => "shift-count-overflow",
+            Code::ConstantConversion => "constant-conversion",
+        }
+    }
+
+    /// The code with the given [`name`](Code::name).
+    pub fn from_name(name: &str) -> Option<Code> {
+        Code::ALL
+            .into_iter()
+            .find(|code| *code != Code::Unspecified && code.name() == name)
+    }
 }
 
 impl Display for Code {
@@ -133,12 +304,41 @@ impl Display for Code {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct UnknownCode(pub String);
+
+impl Display for UnknownCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown diagnostic code `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCode {}
+
+impl FromStr for Code {
+    type Err = UnknownCode;
+
+    /// Parses a code as displayed, e.g. `#0011`, with or without the `#`, or by its name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let code = if !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(|n| Code::ALL.get(n as usize).copied())
+        } else {
+            Code::from_name(s)
+        };
+        code.ok_or_else(|| UnknownCode(s.to_owned()))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Diagnostic {
     code: Code,
     message: String,
     main_span: (Span, Option<String>),
     additional_spans: Vec<(Span, Option<String>)>,
+    fixes: Vec<Fix>,
 }
 
 impl Diagnostic {
@@ -165,6 +365,69 @@ impl Diagnostic {
     pub fn additional_spans_len(&self) -> usize {
         self.additional_spans.len()
     }
+
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+}
+
+/// A suggested edit of the source that fixes a diagnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    message: String,
+    /// The spans to replace, empty spans insert the text.
+    edits: Vec<(Span, String)>,
+    is_safe: bool,
+}
+
+impl Fix {
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+
+    pub fn edits(&self) -> &[(Span, String)] {
+        &self.edits
+    }
+
+    /// A safe fix can be applied without looking at it, e.g. the explicit cast of a lossy
+    /// conversion, which does the same as the implicit one. A fix that only silences the
+    /// diagnostic, e.g. a cast of an incompatible assignment, isn't safe.
+    pub fn is_safe(&self) -> bool {
+        self.is_safe
+    }
+}
+
+/// Applies the fixes to the source, returning the new source and the number of applied fixes.
+/// A fix that overlaps a fix before it is skipped.
+pub fn apply_fixes<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let overlap = |(a, _): &(Span, String), (b, _): &(Span, String)| {
+        a.start() == b.start() || (a.start() < b.excl_end() && b.start() < a.excl_end())
+    };
+
+    let mut edits: Vec<&(Span, String)> = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        if fix
+            .edits
+            .iter()
+            .any(|new| edits.iter().any(|old| overlap(new, old)))
+        {
+            continue;
+        }
+        edits.extend(&fix.edits);
+        applied += 1;
+    }
+
+    edits.sort_by_key(|(span, _)| span.start());
+    let mut fixed = String::with_capacity(source.len());
+    let mut end = 0;
+    for (span, text) in edits {
+        fixed += &source[end..span.start()];
+        fixed += text;
+        end = span.excl_end();
+    }
+    fixed += &source[end..];
+    (fixed, applied)
 }
 
 /// Specifies the possibles types of diagnostics.
@@ -409,6 +672,53 @@ impl<T> AggregateResult<T> {
         }
     }
 
+    /// Runs `f` for all recoverable diagnostics. A diagnostic is dropped if `f` returns `None`
+    /// and turned into an error if it returns [`DiagnosticKind::Err`], which will also make the
+    /// `AggregateResult` itself an _err_.
+    pub fn reclassify_diagnostics<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Diagnostic) -> Option<DiagnosticKind>,
+    {
+        for (kind, diagnostic) in std::mem::take(&mut self.diagnostics) {
+            let kind = match kind {
+                DiagnosticKind::Rec => match f(&diagnostic) {
+                    Some(kind) => kind,
+                    None => continue,
+                },
+                DiagnosticKind::Err => kind,
+            };
+            if kind == DiagnosticKind::Err {
+                self.value = None;
+            }
+            self.diagnostics.push_back((kind, diagnostic));
+        }
+    }
+
+    /// Drops the non-recoverable diagnostics after the first `max` ones, returning how many were
+    /// dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero, since an _err_ result needs at least one non-recoverable
+    /// diagnostic.
+    pub fn truncate_errors(&mut self, max: usize) -> usize {
+        assert!(max > 0, "an err result needs at least one error");
+        let mut errors = 0;
+        let len = self.diagnostics.len();
+        self.diagnostics = std::mem::take(&mut self.diagnostics)
+            .into_iter()
+            .filter(|(kind, _)| {
+                if *kind == DiagnosticKind::Err {
+                    errors += 1;
+                    errors <= max
+                } else {
+                    true
+                }
+            })
+            .collect();
+        len - self.diagnostics.len()
+    }
+
     /// Maps an `AggregateResult<T, E>` to `AggregateResult<U, E>` by applying a function to a
     /// contained value, leaving diagnostics untouched.
     #[must_use]
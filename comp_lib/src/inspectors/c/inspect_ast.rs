@@ -0,0 +1,257 @@
+use super::{
+    binary, call, cast, char_literal, declaration, float_literal, for_header, integer_literal,
+    operand, postfix, prec, prefix, string_literal, CWriter, Printed,
+};
+use crate::{
+    ast::{
+        self, ArrayDeclaration, ArrayDeclarationNode, BinaryOperator, Declaration, Expression,
+        ExpressionNode, ExternalDeclaration, Literal, QualifiedTypeNode, Statement, StatementNode,
+        SwitchCase, UnaryOperator,
+    },
+    ir::ctype::CType,
+};
+
+pub(super) fn inspect(ast: &ast::Ast) -> String {
+    let mut writer = CWriter::default();
+    for (i, node) in ast.global_declarations.iter().enumerate() {
+        if i > 0 {
+            writer.out.push('\n');
+        }
+        writer.comments(&node.comments);
+        match &node.data {
+            ExternalDeclaration::FunctionDefinition(function) => {
+                let prototype = function_declarator(
+                    &function.return_type,
+                    &function.ident.data,
+                    &function.params,
+                    function.is_vararg,
+                );
+                writer.line(format!("{prototype} {{"));
+                indented_block(&mut writer, &function.body);
+                writer.line("}");
+            }
+            ExternalDeclaration::Declaration(decl) => {
+                let decl = declaration_text(decl);
+                writer.line(format!("{decl};"));
+            }
+        }
+    }
+    writer.out
+}
+
+fn qualified_declaration(
+    ty: &QualifiedTypeNode,
+    ident: &str,
+    array_parts: &[ArrayDeclarationNode],
+) -> String {
+    let mut declarator = ident.to_owned();
+    for array_part in array_parts {
+        match &array_part.data {
+            ArrayDeclaration::Unknown => declarator += "[]",
+            ArrayDeclaration::Known(length) => {
+                declarator = format!("{declarator}[{}]", expr(length).0);
+            }
+        }
+    }
+    declaration(
+        &CType::from_ast_type(&ty.unqualified.data),
+        ty.is_const.is_some(),
+        &declarator,
+    )
+}
+
+fn type_name(ty: &QualifiedTypeNode) -> String {
+    qualified_declaration(ty, "", &[])
+}
+
+fn function_declarator(
+    return_type: &QualifiedTypeNode,
+    ident: &str,
+    params: &[ast::FunctionParamNode],
+    is_vararg: bool,
+) -> String {
+    let mut params: Vec<_> = params
+        .iter()
+        .map(|param| {
+            let ident = param.ident.as_ref().map_or("", |ident| ident.data.as_str());
+            qualified_declaration(&param.type_name, ident, &param.array_parts)
+        })
+        .collect();
+    if is_vararg {
+        params.push("...".to_owned());
+    } else if params.is_empty() {
+        params.push("void".to_owned());
+    }
+    qualified_declaration(return_type, &format!("{ident}({})", params.join(", ")), &[])
+}
+
+/// The declaration without the trailing `;`.
+fn declaration_text(decl: &Declaration) -> String {
+    match decl {
+        Declaration::Variable(var) => {
+            let declaration =
+                qualified_declaration(&var.type_name, &var.ident.data, &var.array_parts);
+            match &var.initializer {
+                Some((_, initializer)) => {
+                    format!(
+                        "{declaration} = {}",
+                        operand(expr(initializer), prec::ASSIGN)
+                    )
+                }
+                None => declaration,
+            }
+        }
+        Declaration::FunctionDeclaration(function) => function_declarator(
+            &function.return_type,
+            &function.ident.data,
+            &function.params,
+            function.is_vararg,
+        ),
+    }
+}
+
+fn indented_block(writer: &mut CWriter, block: &ast::BlockStatementNode) {
+    writer.indent += 1;
+    for stmt in &block.stmts {
+        statement(writer, stmt);
+    }
+    writer.indent -= 1;
+}
+
+fn statement(writer: &mut CWriter, stmt: &StatementNode) {
+    writer.comments(&stmt.comments);
+    match &stmt.data {
+        Statement::Declaration(decl) => writer.line(format!("{};", declaration_text(decl))),
+        Statement::Expression(e) => writer.line(format!("{};", expr(e).0)),
+        Statement::If(if_stmt) => {
+            writer.line(format!("if ({}) {{", expr(&if_stmt.condition).0));
+            indented_block(writer, &if_stmt.if_body);
+            if let Some(else_body) = &if_stmt.else_body {
+                writer.line("} else {");
+                indented_block(writer, else_body);
+            }
+            writer.line("}");
+        }
+        Statement::Switch(switch) => {
+            writer.line(format!("switch ({}) {{", expr(&switch.expr).0));
+            for case in &switch.cases {
+                let body = match case {
+                    SwitchCase::Expr(case) => {
+                        writer.line(format!("case {}:", expr(&case.expr).0));
+                        &case.body
+                    }
+                    SwitchCase::Default(case) => {
+                        writer.line("default:");
+                        &case.body
+                    }
+                };
+                indented_block(writer, body);
+            }
+            writer.line("}");
+        }
+        Statement::While(while_stmt) => {
+            writer.line(format!("while ({}) {{", expr(&while_stmt.condition).0));
+            indented_block(writer, &while_stmt.body);
+            writer.line("}");
+        }
+        Statement::For(for_stmt) => {
+            let init = match for_stmt.init.as_deref().map(|init| &init.data) {
+                Some(Statement::Declaration(decl)) => declaration_text(decl),
+                Some(Statement::Expression(e)) => expr(e).0,
+                Some(other) => unreachable!("for loop initialized by {other:?}"),
+                None => String::new(),
+            };
+            let condition = for_stmt.condition.as_ref().map(|e| expr(e).0);
+            let iter = for_stmt.iter.as_ref().map(|e| expr(e).0);
+            writer.line(for_header(
+                &init,
+                condition.as_deref().unwrap_or_default(),
+                iter.as_deref().unwrap_or_default(),
+            ));
+            indented_block(writer, &for_stmt.body);
+            writer.line("}");
+        }
+        Statement::Break => writer.line("break;"),
+        Statement::Continue => writer.line("continue;"),
+        Statement::Return(_, None) => writer.line("return;"),
+        Statement::Return(_, Some(e)) => writer.line(format!("return {};", expr(e).0)),
+        Statement::BlockStatement(block) => {
+            writer.line("{");
+            indented_block(writer, block);
+            writer.line("}");
+        }
+    }
+}
+
+fn expr(node: &ExpressionNode) -> Printed {
+    match &node.data {
+        Expression::Assignment(left, _, right) => (
+            format!(
+                "{} = {}",
+                operand(expr(left), prec::UNARY),
+                operand(expr(right), prec::ASSIGN)
+            ),
+            prec::ASSIGN,
+        ),
+        Expression::Binary(left, op, right) => {
+            use BinaryOperator as B;
+            let (op, prec) = match op.data {
+                B::Star => ("*", prec::MULTIPLICATIVE),
+                B::Slash => ("/", prec::MULTIPLICATIVE),
+                B::Percent => ("%", prec::MULTIPLICATIVE),
+                B::Plus => ("+", prec::ADDITIVE),
+                B::Minus => ("-", prec::ADDITIVE),
+                B::DoubleAngleLeft => ("<<", prec::SHIFT),
+                B::DoubleAngleRight => (">>", prec::SHIFT),
+                B::AngleLeft => ("<", prec::RELATIONAL),
+                B::AngleRight => (">", prec::RELATIONAL),
+                B::AngleLeftEquals => ("<=", prec::RELATIONAL),
+                B::AngleRightEquals => (">=", prec::RELATIONAL),
+                B::DoubleEquals => ("==", prec::EQUALITY),
+                B::BangEquals => ("!=", prec::EQUALITY),
+                B::Ampersand => ("&", prec::BITWISE_AND),
+                B::Caret => ("^", prec::BITWISE_XOR),
+                B::Pipe => ("|", prec::BITWISE_OR),
+                B::DoubleAmpersand => ("&&", prec::LOGICAL_AND),
+                B::DoublePipe => ("||", prec::LOGICAL_OR),
+            };
+            binary(expr(left), op, prec, expr(right))
+        }
+        Expression::ArraySubscript(array, index) => (
+            format!("{}[{}]", operand(expr(array), prec::POSTFIX), expr(index).0),
+            prec::POSTFIX,
+        ),
+        Expression::Unary(op, inner) => {
+            use UnaryOperator as U;
+            let op = match op.data {
+                U::DoublePlusPostfix => return postfix(expr(inner), "++"),
+                U::DoubleMinusPostfix => return postfix(expr(inner), "--"),
+                U::Bang => "!",
+                U::Plus => "+",
+                U::Minus => "-",
+                U::DoublePlusPrefix => "++",
+                U::DoubleMinusPrefix => "--",
+                U::Tilde => "~",
+                U::Ampersand => "&",
+                U::Star => "*",
+            };
+            prefix(op, expr(inner))
+        }
+        Expression::Cast(ty, inner) => cast(&type_name(ty), expr(inner)),
+        Expression::FunctionCall(function_call) => call(
+            &function_call.ident.data,
+            function_call.args.iter().map(expr),
+        ),
+        Expression::Literal(literal) => match &literal.data {
+            Literal::Hex(value) if *value >= 0 => (format!("{value:#X}"), prec::POSTFIX),
+            Literal::Octal(value) if *value > 0 => (format!("0{value:o}"), prec::POSTFIX),
+            Literal::Dec(value) | Literal::Hex(value) | Literal::Octal(value) => {
+                integer_literal(*value, "")
+            }
+            Literal::Char(value) => char_literal(*value),
+            Literal::Float(value) => float_literal(*value),
+            Literal::String(bytes) => string_literal(bytes),
+        },
+        Expression::Ident(ident) => (ident.data.clone(), prec::POSTFIX),
+    }
+}
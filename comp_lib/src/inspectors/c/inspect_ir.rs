@@ -0,0 +1,321 @@
+use super::{
+    binary, call, cast, char_literal, declaration, float_literal, for_header, integer_literal,
+    operand, postfix, prec, prefix, string_literal, type_name, CWriter, Printed,
+};
+use crate::ir::{
+    self,
+    ctype::{Arithmetic, CType, Scalar},
+    table::ItemId,
+    BinaryOp, BitwiseOp, Constant, Expr, ExprNode, LvalueExpr, LvalueExprNode, RelationOp, Stmt,
+    StmtNode, SwitchStmtCase, UnaryOp,
+};
+use std::collections::{HashMap, HashSet};
+
+pub(super) fn inspect(ir: &ir::Root, source: &str) -> String {
+    // Sorted in source order to get deterministic output.
+    let mut functions: Vec<_> = ir.functions.iter().collect();
+    functions.sort_by_key(|(ident, function)| (function.original_span.start(), ident.as_str()));
+    let mut vars: Vec<_> = ir.vars.iter().collect();
+    vars.sort_by_key(|(ident, var)| (var.original_span.start(), ident.as_str()));
+
+    let globals: HashSet<&str> = ir
+        .functions
+        .keys()
+        .chain(ir.vars.keys())
+        .map(String::as_str)
+        .collect();
+    let mut writer = CWriter::default();
+
+    // All functions are declared first, so they can be used before their definition.
+    for (ident, function) in &functions {
+        writer.comments(&function.comments);
+        writer.line(format!("{};", signature(ident, function, &HashMap::new())));
+    }
+
+    if !vars.is_empty() {
+        writer.out.push('\n');
+    }
+    for (ident, var) in vars {
+        writer.comments(&var.comments);
+        let declaration = declaration(&var.ty, var.is_const, ident);
+        match &var.value {
+            Some(value) => writer.line(format!("{declaration} = {};", constant(value, &var.ty).0)),
+            None => writer.line(format!("{declaration};")),
+        }
+    }
+
+    for (ident, function) in functions {
+        let Some(body) = &function.body else {
+            continue;
+        };
+        let mut printer = FunctionPrinter {
+            names: local_names(function, source, &globals),
+            writer,
+        };
+        printer.writer.out.push('\n');
+        printer
+            .writer
+            .line(format!("{} {{", signature(ident, function, &printer.names)));
+        printer.writer.indent += 1;
+
+        let params: HashSet<ItemId> = function
+            .params
+            .iter()
+            .filter_map(|param| param.ident)
+            .collect();
+        let mut declared_locals = false;
+        for (id, item) in function.table.iter() {
+            if !params.contains(&id) {
+                // Locals are only initialized by assignments in the IR, so they can't be const.
+                let local = declaration(&item.ty, false, &printer.names[&id]);
+                printer.writer.line(format!("{local};"));
+                declared_locals = true;
+            }
+        }
+        if declared_locals && !body.stmts.is_empty() {
+            printer.writer.out.push('\n');
+        }
+
+        printer.block(body);
+        printer.writer.indent -= 1;
+        printer.writer.line("}");
+        writer = printer.writer;
+    }
+
+    writer.out
+}
+
+/// Names every variable in the table of the function after its identifier in the source and its
+/// id, so that variables that shadow each other get a different name.
+fn local_names(
+    function: &ir::FunctionNode,
+    source: &str,
+    globals: &HashSet<&str>,
+) -> HashMap<ItemId, String> {
+    function
+        .table
+        .iter()
+        .enumerate()
+        .map(|(index, (id, item))| {
+            let ident = &source[std::ops::Range::<usize>::from(item.ident_span)];
+            let mut name = format!("{ident}_{index}");
+            while globals.contains(name.as_str()) {
+                name.push('_');
+            }
+            (id, name)
+        })
+        .collect()
+}
+
+/// The declarator of the function, the parameters are named if they are in `names`.
+fn signature(ident: &str, function: &ir::FunctionNode, names: &HashMap<ItemId, String>) -> String {
+    let mut params: Vec<_> = function
+        .params
+        .iter()
+        .map(|param| {
+            let name = param
+                .ident
+                .and_then(|id| names.get(&id))
+                .map_or("", String::as_str);
+            declaration(&param.ty, param.is_const, name)
+        })
+        .collect();
+    if function.is_vararg {
+        params.push("...".to_owned());
+    } else if params.is_empty() {
+        params.push("void".to_owned());
+    }
+    declaration(
+        &function.return_type,
+        false,
+        &format!("{ident}({})", params.join(", ")),
+    )
+}
+
+fn constant(constant: &Constant, ty: &CType) -> Printed {
+    let cast_constant =
+        |(text, _): Printed| (format!("(({}){text})", type_name(ty)), prec::POSTFIX);
+    match (constant, ty) {
+        (Constant::Integer(value), CType::Scalar(Scalar::Arithmetic(arithmetic))) => {
+            match arithmetic {
+                Arithmetic::SignedInt => integer_literal(*value, ""),
+                Arithmetic::UnsignedInt => integer_literal(*value, "U"),
+                Arithmetic::SignedLongInt => integer_literal(*value, "L"),
+                Arithmetic::UnsignedLongInt => integer_literal(*value, "UL"),
+                Arithmetic::Char if (0..128).contains(value) => char_literal(*value as u8),
+                Arithmetic::Double => float_literal(*value as f64),
+                arithmetic if arithmetic.is_floating() => {
+                    cast_constant(float_literal(*value as f64))
+                }
+                _ => cast_constant(integer_literal(*value, "")),
+            }
+        }
+        (Constant::Integer(value), _) => cast_constant(integer_literal(*value, "")),
+        (Constant::Float(value), CType::Scalar(Scalar::Arithmetic(Arithmetic::Double))) => {
+            float_literal(*value)
+        }
+        (Constant::Float(value), _) => cast_constant(float_literal(*value)),
+        (Constant::String(bytes), _) => {
+            // The terminating null byte is implicit in a C string literal.
+            let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+            string_literal(bytes)
+        }
+    }
+}
+
+struct FunctionPrinter {
+    names: HashMap<ItemId, String>,
+    writer: CWriter,
+}
+
+impl FunctionPrinter {
+    fn block(&mut self, block: &ir::BlockNode) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn indented_block(&mut self, block: &ir::BlockNode) {
+        self.writer.indent += 1;
+        self.block(block);
+        self.writer.indent -= 1;
+    }
+
+    fn stmt(&mut self, stmt: &StmtNode) {
+        self.writer.comments(&stmt.comments);
+        match &stmt.stmt {
+            Stmt::Expr(expr) => {
+                let expr = self.expr(expr).0;
+                self.writer.line(format!("{expr};"));
+            }
+            Stmt::IfStmt(if_stmt) => {
+                let condition = self.expr(&if_stmt.condition).0;
+                self.writer.line(format!("if ({condition}) {{"));
+                self.indented_block(&if_stmt.if_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.writer.line("} else {");
+                    self.indented_block(else_branch);
+                }
+                self.writer.line("}");
+            }
+            Stmt::SwitchStmt(switch) => {
+                let expr = self.expr(&switch.expr).0;
+                self.writer.line(format!("switch ({expr}) {{"));
+                for case in &switch.cases {
+                    let body = match &case.data {
+                        SwitchStmtCase::Case { label, body } => {
+                            let label = constant(&Constant::Integer(*label), &switch.expr.ty).0;
+                            self.writer.line(format!("case {label}:"));
+                            body
+                        }
+                        SwitchStmtCase::Default { body } => {
+                            self.writer.line("default:");
+                            body
+                        }
+                    };
+                    self.indented_block(body);
+                }
+                self.writer.line("}");
+            }
+            Stmt::LoopStmt(loop_stmt) => {
+                let condition = loop_stmt
+                    .condition
+                    .as_ref()
+                    .map(|condition| self.expr(condition).0);
+                let continuation = loop_stmt
+                    .continuation
+                    .as_ref()
+                    .map(|continuation| self.expr(continuation).0);
+                let header = match (condition, continuation) {
+                    (Some(condition), None) => format!("while ({condition}) {{"),
+                    (condition, continuation) => for_header(
+                        "",
+                        condition.as_deref().unwrap_or_default(),
+                        continuation.as_deref().unwrap_or_default(),
+                    ),
+                };
+                self.writer.line(header);
+                self.indented_block(&loop_stmt.body);
+                self.writer.line("}");
+            }
+            Stmt::Break => self.writer.line("break;"),
+            Stmt::Continue => self.writer.line("continue;"),
+            Stmt::Return(None) => self.writer.line("return;"),
+            Stmt::Return(Some(expr)) => {
+                let expr = self.expr(expr).0;
+                self.writer.line(format!("return {expr};"));
+            }
+        }
+    }
+
+    fn expr(&self, expr: &ExprNode) -> Printed {
+        match &expr.expr {
+            // Arrays decay to a pointer to their first element by themselves.
+            Expr::LvalueDeref(lvalue) => self.lvalue(lvalue),
+            Expr::Constant(value) => constant(value, &expr.ty),
+            Expr::FunctionCall(ident, args) => call(ident, args.iter().map(|arg| self.expr(arg))),
+            Expr::PostfixInc(lvalue) => postfix(self.lvalue(lvalue), "++"),
+            Expr::PostfixDec(lvalue) => postfix(self.lvalue(lvalue), "--"),
+            Expr::PrefixInc(lvalue) => prefix("++", self.lvalue(lvalue)),
+            Expr::PrefixDec(lvalue) => prefix("--", self.lvalue(lvalue)),
+            Expr::Reference(lvalue) => prefix("&", self.lvalue(lvalue)),
+            Expr::UnaryArith(op, inner) => {
+                let op = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::BitNot => "~",
+                    UnaryOp::Not => "!",
+                };
+                prefix(op, self.expr(inner))
+            }
+            Expr::Binary(left, op, right) => {
+                let (op, prec) = match op {
+                    BinaryOp::Mul => ("*", prec::MULTIPLICATIVE),
+                    BinaryOp::Div => ("/", prec::MULTIPLICATIVE),
+                    BinaryOp::Rem => ("%", prec::MULTIPLICATIVE),
+                    BinaryOp::Add => ("+", prec::ADDITIVE),
+                    BinaryOp::Sub => ("-", prec::ADDITIVE),
+                    BinaryOp::ShiftLeft => ("<<", prec::SHIFT),
+                    BinaryOp::ShiftRight => (">>", prec::SHIFT),
+                    BinaryOp::Bitwise(BitwiseOp::And) => ("&", prec::BITWISE_AND),
+                    BinaryOp::Bitwise(BitwiseOp::Xor) => ("^", prec::BITWISE_XOR),
+                    BinaryOp::Bitwise(BitwiseOp::Or) => ("|", prec::BITWISE_OR),
+                };
+                binary(self.expr(left), op, prec, self.expr(right))
+            }
+            Expr::Relation(left, op, right) => {
+                let (op, prec) = match op {
+                    RelationOp::Eq => ("==", prec::EQUALITY),
+                    RelationOp::Ne => ("!=", prec::EQUALITY),
+                    RelationOp::Lt => ("<", prec::RELATIONAL),
+                    RelationOp::Gt => (">", prec::RELATIONAL),
+                    RelationOp::Ge => (">=", prec::RELATIONAL),
+                    RelationOp::Le => ("<=", prec::RELATIONAL),
+                };
+                binary(self.expr(left), op, prec, self.expr(right))
+            }
+            Expr::LogicalAnd(left, right) => {
+                binary(self.expr(left), "&&", prec::LOGICAL_AND, self.expr(right))
+            }
+            Expr::LogicalOr(left, right) => {
+                binary(self.expr(left), "||", prec::LOGICAL_OR, self.expr(right))
+            }
+            Expr::Assign(lvalue, value) => (
+                format!(
+                    "{} = {}",
+                    operand(self.lvalue(lvalue), prec::UNARY),
+                    operand(self.expr(value), prec::ASSIGN)
+                ),
+                prec::ASSIGN,
+            ),
+            Expr::Cast(inner) => cast(&type_name(&expr.ty), self.expr(inner)),
+        }
+    }
+
+    fn lvalue(&self, lvalue: &LvalueExprNode) -> Printed {
+        match &lvalue.expr {
+            LvalueExpr::Ident(id) => (self.names[id].clone(), prec::POSTFIX),
+            LvalueExpr::GlobalIdent(ident) => (ident.clone(), prec::POSTFIX),
+            LvalueExpr::Dereference(pointer) => prefix("*", self.expr(pointer)),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Prints the AST or the IR back as C source, to inspect what the passes did to a program.
+//!
+//! The AST is printed as it was written, apart from the constant expressions that were folded. The
+//! IR is printed with every implicit conversion as an explicit cast, array subscripts as pointer
+//! arithmetic and every local variable declared at the start of its function with a unique name.
+//! Both can be compiled by another C compiler, e.g. to check the lowering against gcc.
+
+mod inspect_ast;
+mod inspect_ir;
+
+use crate::{
+    ast,
+    ir::{
+        self,
+        ctype::{Aggregate, Array, CType, Pointer, Scalar},
+    },
+};
+
+pub fn inspect_ast(ast: &ast::Ast) -> String {
+    inspect_ast::inspect(ast)
+}
+
+pub fn inspect_ir(ir: &ir::Root, source: &str) -> String {
+    inspect_ir::inspect(ir, source)
+}
+
+/// The precedence levels of the C operators, an expression printed with a lower precedence than
+/// its parent needs parentheses.
+mod prec {
+    pub const ASSIGN: u8 = 2;
+    pub const LOGICAL_OR: u8 = 4;
+    pub const LOGICAL_AND: u8 = 5;
+    pub const BITWISE_OR: u8 = 6;
+    pub const BITWISE_XOR: u8 = 7;
+    pub const BITWISE_AND: u8 = 8;
+    pub const EQUALITY: u8 = 9;
+    pub const RELATIONAL: u8 = 10;
+    pub const SHIFT: u8 = 11;
+    pub const ADDITIVE: u8 = 12;
+    pub const MULTIPLICATIVE: u8 = 13;
+    pub const UNARY: u8 = 14;
+    pub const POSTFIX: u8 = 15;
+}
+
+/// A printed expression and the precedence of its outermost operator.
+type Printed = (String, u8);
+
+#[derive(Default)]
+struct CWriter {
+    out: String,
+    indent: usize,
+}
+
+impl CWriter {
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.out += "    ";
+        }
+        self.out += line.as_ref();
+        self.out.push('\n');
+    }
+
+    fn comments(&mut self, comments: &Option<String>) {
+        for line in comments.iter().flat_map(|comments| comments.lines()) {
+            self.line(format!("//{line}"));
+        }
+    }
+}
+
+/// Adds parentheses if the expression binds less tightly than `min`.
+fn operand((text, prec): Printed, min: u8) -> String {
+    if prec < min {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+fn prefix(op: &str, operand_expr: Printed) -> Printed {
+    let text = operand(operand_expr, prec::UNARY);
+    // `- -x` must not become `--x`.
+    let merges = matches!(
+        (op.chars().last(), text.chars().next()),
+        (Some('-'), Some('-')) | (Some('+'), Some('+')) | (Some('&'), Some('&'))
+    );
+    if merges {
+        (format!("{op}({text})"), prec::UNARY)
+    } else {
+        (format!("{op}{text}"), prec::UNARY)
+    }
+}
+
+fn postfix(operand_expr: Printed, op: &str) -> Printed {
+    (
+        format!("{}{op}", operand(operand_expr, prec::POSTFIX)),
+        prec::POSTFIX,
+    )
+}
+
+/// Prints a left associative binary operator.
+fn binary(left: Printed, op: &str, prec: u8, right: Printed) -> Printed {
+    (
+        format!("{} {op} {}", operand(left, prec), operand(right, prec + 1)),
+        prec,
+    )
+}
+
+fn cast(type_name: &str, expr: Printed) -> Printed {
+    (
+        format!("({type_name}){}", operand(expr, prec::UNARY)),
+        prec::UNARY,
+    )
+}
+
+fn call(ident: &str, args: impl Iterator<Item = Printed>) -> Printed {
+    let args: Vec<_> = args.map(|arg| operand(arg, prec::ASSIGN)).collect();
+    (format!("{ident}({})", args.join(", ")), prec::POSTFIX)
+}
+
+fn for_header(init: &str, condition: &str, iter: &str) -> String {
+    let mut header = format!("for ({init};");
+    if !condition.is_empty() {
+        header = header + " " + condition;
+    }
+    header.push(';');
+    if !iter.is_empty() {
+        header = header + " " + iter;
+    }
+    header + ") {"
+}
+
+fn integer_literal(value: i128, suffix: &str) -> Printed {
+    let abs = value.unsigned_abs();
+    let text = if value >= 0 {
+        format!("{abs}{suffix}")
+    } else if abs.is_power_of_two() && abs >= 1 << 31 {
+        // The minimum of a signed type can't be written as a negated literal, since the literal
+        // itself doesn't fit in the type.
+        format!("(-{}{suffix} - 1)", abs - 1)
+    } else {
+        format!("(-{abs}{suffix})")
+    };
+    (text, prec::POSTFIX)
+}
+
+fn float_literal(value: f64) -> Printed {
+    let text = if value.is_nan() {
+        "(0.0 / 0.0)".to_owned()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("({sign}1.0 / 0.0)")
+    } else if value.is_sign_negative() {
+        format!("(-{:?})", value.abs())
+    } else {
+        format!("{value:?}")
+    };
+    (text, prec::POSTFIX)
+}
+
+fn escape_byte(byte: u8, quote: u8) -> String {
+    match byte {
+        b'\\' => "\\\\".to_owned(),
+        b'\n' => "\\n".to_owned(),
+        b'\t' => "\\t".to_owned(),
+        // Avoids trigraphs.
+        b'?' => "\\?".to_owned(),
+        byte if byte == quote => format!("\\{}", byte as char),
+        b' '..=b'~' => (byte as char).to_string(),
+        // Octal escapes have at most three digits, so a following digit isn't part of it.
+        byte => format!("\\{byte:03o}"),
+    }
+}
+
+fn char_literal(byte: u8) -> Printed {
+    (format!("'{}'", escape_byte(byte, b'\'')), prec::POSTFIX)
+}
+
+fn string_literal(bytes: &[u8]) -> Printed {
+    let escaped: String = bytes.iter().map(|byte| escape_byte(*byte, b'"')).collect();
+    (format!("\"{escaped}\""), prec::POSTFIX)
+}
+
+/// Declares `declarator` (e.g. an identifier, or empty for a type name) with type `ty`.
+///
+/// `is_const` applies to the outermost type, or the elements of an array.
+fn declaration(ty: &CType, is_const: bool, declarator: &str) -> String {
+    match ty {
+        CType::Scalar(Scalar::Arithmetic(arithmetic)) => {
+            with_specifiers(&arithmetic.to_string(), is_const, declarator)
+        }
+        CType::Void => with_specifiers("void", is_const, declarator),
+        CType::Scalar(Scalar::Pointer(Pointer { inner, inner_const })) => {
+            let pointer = match (is_const, declarator.is_empty()) {
+                (true, true) => "*const".to_owned(),
+                (true, false) => format!("*const {declarator}"),
+                (false, _) => format!("*{declarator}"),
+            };
+            let pointer = match **inner {
+                CType::Aggregate(_) => format!("({pointer})"),
+                _ => pointer,
+            };
+            declaration(inner, *inner_const, &pointer)
+        }
+        CType::Aggregate(Aggregate::Array(Array { inner, length })) => {
+            declaration(inner, is_const, &format!("{declarator}[{length}]"))
+        }
+    }
+}
+
+fn with_specifiers(type_specifier: &str, is_const: bool, declarator: &str) -> String {
+    let qualifier = if is_const { "const " } else { "" };
+    if declarator.is_empty() {
+        format!("{qualifier}{type_specifier}")
+    } else {
+        format!("{qualifier}{type_specifier} {declarator}")
+    }
+}
+
+/// The name of the type as used in casts.
+fn type_name(ty: &CType) -> String {
+    declaration(ty, false, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::ctype::Arithmetic;
+
+    fn int() -> CType {
+        CType::Scalar(Scalar::Arithmetic(Arithmetic::SignedInt))
+    }
+
+    fn pointer(inner: CType, inner_const: bool) -> CType {
+        CType::Scalar(Scalar::Pointer(Pointer {
+            inner: Box::new(inner),
+            inner_const,
+        }))
+    }
+
+    fn array(inner: CType, length: u128) -> CType {
+        CType::Aggregate(Aggregate::Array(Array {
+            inner: Box::new(inner),
+            length,
+        }))
+    }
+
+    #[test]
+    fn declarations() {
+        let tests = [
+            (int(), false, "int x"),
+            (int(), true, "const int x"),
+            (pointer(int(), true), false, "const int *x"),
+            (pointer(int(), false), true, "int *const x"),
+            (pointer(pointer(int(), false), true), false, "int *const *x"),
+            (array(array(int(), 3), 2), false, "int x[2][3]"),
+            (array(pointer(int(), false), 2), false, "int *x[2]"),
+            (pointer(array(int(), 3), false), false, "int (*x)[3]"),
+        ];
+        for (ty, is_const, expected) in tests {
+            assert_eq!(declaration(&ty, is_const, "x"), expected);
+        }
+        assert_eq!(type_name(&pointer(array(int(), 3), false)), "int (*)[3]");
+        assert_eq!(type_name(&pointer(int(), true)), "const int *");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(integer_literal(-5, "").0, "(-5)");
+        assert_eq!(integer_literal(i32::MIN as i128, "").0, "(-2147483647 - 1)");
+        assert_eq!(integer_literal(7, "UL").0, "7UL");
+        assert_eq!(float_literal(-0.5).0, "(-0.5)");
+        assert_eq!(float_literal(f64::INFINITY).0, "(1.0 / 0.0)");
+        assert_eq!(char_literal(b'\'').0, "'\\''");
+        assert_eq!(string_literal(b"a\"?\n\x01").0, "\"a\\\"\\?\\n\\001\"");
+        assert_eq!(
+            prefix("-", prefix("--", ("x".to_owned(), prec::POSTFIX))).0,
+            "-(--x)"
+        );
+    }
+}
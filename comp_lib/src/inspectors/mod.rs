@@ -1 +1,3 @@
+pub mod c;
 pub mod dot;
+pub mod symbols;
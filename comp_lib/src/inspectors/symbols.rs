@@ -0,0 +1,391 @@
+//! An index of the symbols in the IR, where they're referenced and the types of all expressions,
+//! e.g. for editor integration.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    diagnostic::Span,
+    ir::{
+        self,
+        ctype::CType,
+        expr::{Expr, ExprNode, LvalueExpr, LvalueExprNode},
+        table::ItemId,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    GlobalVar,
+    Param,
+    LocalVar,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The declaration in C syntax, e.g. `int f(int, char *)` or `const int a[3]`.
+    pub declaration: String,
+    /// The span of the whole declaration/definition.
+    pub span: Span,
+    /// The span of the identifier in the declaration/definition.
+    pub ident_span: Span,
+    /// The spans of all identifiers that refer to this symbol, excluding `ident_span`.
+    pub references: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    /// The span and type of every (lvalue) expression. An expression always comes after the
+    /// expressions it's part of.
+    expr_types: Vec<(Span, CType)>,
+}
+
+impl SymbolIndex {
+    /// The names of local variables aren't kept in the IR, so they're taken from `source`.
+    pub fn new(ir: &ir::Root, source: &str) -> Self {
+        let mut builder = IndexBuilder {
+            index: SymbolIndex::default(),
+            source,
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            locals: HashMap::new(),
+        };
+        builder.build(ir);
+        builder.index
+    }
+
+    /// All functions and global variables, followed by the params and local variables.
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Returns the symbol whose identifier, or one of its references, contains `offset`, together
+    /// with the span of that identifier.
+    pub fn symbol_at(&self, offset: usize) -> Option<(&Symbol, Span)> {
+        self.symbols.iter().find_map(|symbol| {
+            std::iter::once(&symbol.ident_span)
+                .chain(&symbol.references)
+                .find(|span| contains(**span, offset))
+                .map(|span| (symbol, *span))
+        })
+    }
+
+    /// Returns the span and the type of the innermost expression that contains `offset`.
+    pub fn type_at(&self, offset: usize) -> Option<(Span, &CType)> {
+        self.expr_types
+            .iter()
+            .rev()
+            .filter(|(span, _)| contains(*span, offset))
+            .min_by_key(|(span, _)| span.len())
+            .map(|(span, ty)| (*span, ty))
+    }
+}
+
+/// The end of the span is included, so that e.g. a cursor right after an identifier is on it.
+fn contains(span: Span, offset: usize) -> bool {
+    span.start() <= offset && offset <= span.excl_end()
+}
+
+struct IndexBuilder<'s> {
+    index: SymbolIndex,
+    source: &'s str,
+    globals: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+    /// The symbols of the function that's being indexed.
+    locals: HashMap<ItemId, usize>,
+}
+
+impl IndexBuilder<'_> {
+    fn build(&mut self, ir: &ir::Root) {
+        for (name, var) in &ir.vars {
+            self.add_symbol(Symbol {
+                name: name.clone(),
+                kind: SymbolKind::GlobalVar,
+                declaration: declaration(name, &var.ty, var.is_const),
+                span: var.original_span,
+                ident_span: var.ident_span,
+                references: Vec::new(),
+            });
+        }
+        for (name, function) in &ir.functions {
+            self.add_symbol(Symbol {
+                name: name.clone(),
+                kind: SymbolKind::Function,
+                declaration: function_declaration(name, function),
+                span: function.original_span,
+                ident_span: function.ident_span,
+                references: Vec::new(),
+            });
+        }
+        // Sorted so that the order of the symbols doesn't depend on the order of the hash maps.
+        self.index
+            .symbols
+            .sort_by_key(|symbol| symbol.ident_span.start());
+        self.globals = self.symbol_ids(SymbolKind::GlobalVar);
+        self.functions = self.symbol_ids(SymbolKind::Function);
+
+        let mut functions: Vec<_> = ir.functions.values().collect();
+        functions.sort_by_key(|function| function.ident_span.start());
+        for function in functions {
+            self.function(function);
+        }
+    }
+
+    fn symbol_ids(&self, kind: SymbolKind) -> HashMap<String, usize> {
+        self.index
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.kind == kind)
+            .map(|(id, symbol)| (symbol.name.clone(), id))
+            .collect()
+    }
+
+    fn add_symbol(&mut self, symbol: Symbol) -> usize {
+        self.index.symbols.push(symbol);
+        self.index.symbols.len() - 1
+    }
+
+    fn add_reference(&mut self, symbol: usize, span: Span) {
+        let symbol = &mut self.index.symbols[symbol];
+        // The initialization of a variable is an assignment to its identifier.
+        if symbol.ident_span != span {
+            symbol.references.push(span);
+        }
+    }
+
+    fn function(&mut self, function: &ir::FunctionNode) {
+        self.locals.clear();
+        for (item_id, item) in function.table.iter() {
+            let kind = match function.params.iter().any(|p| p.ident == Some(item_id)) {
+                true => SymbolKind::Param,
+                false => SymbolKind::LocalVar,
+            };
+            let name = self.source[Range::<usize>::from(item.ident_span)].to_owned();
+            let id = self.add_symbol(Symbol {
+                declaration: declaration(&name, &item.ty, item.is_const),
+                name,
+                kind,
+                span: item.original_span,
+                ident_span: item.ident_span,
+                references: Vec::new(),
+            });
+            self.locals.insert(item_id, id);
+        }
+        if let Some(body) = &function.body {
+            self.block(body);
+        }
+    }
+
+    fn block(&mut self, block: &ir::BlockNode) {
+        for stmt in &block.stmts {
+            match &stmt.stmt {
+                ir::Stmt::Expr(expr) | ir::Stmt::Return(Some(expr)) => self.expr(expr),
+                ir::Stmt::IfStmt(if_stmt) => {
+                    self.expr(&if_stmt.condition);
+                    self.block(&if_stmt.if_branch);
+                    if let Some(else_branch) = &if_stmt.else_branch {
+                        self.block(else_branch);
+                    }
+                }
+                ir::Stmt::SwitchStmt(switch_stmt) => {
+                    self.expr(&switch_stmt.expr);
+                    for case in &switch_stmt.cases {
+                        match &case.data {
+                            ir::SwitchStmtCase::Case { body, .. }
+                            | ir::SwitchStmtCase::Default { body } => self.block(body),
+                        }
+                    }
+                }
+                ir::Stmt::LoopStmt(loop_stmt) => {
+                    if let Some(condition) = &loop_stmt.condition {
+                        self.expr(condition);
+                    }
+                    self.block(&loop_stmt.body);
+                    if let Some(continuation) = &loop_stmt.continuation {
+                        self.expr(continuation);
+                    }
+                }
+                ir::Stmt::Break | ir::Stmt::Continue | ir::Stmt::Return(None) => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ExprNode) {
+        self.index.expr_types.push((expr.span, expr.ty.clone()));
+        match &expr.expr {
+            Expr::Constant(_) => {}
+            Expr::LvalueDeref(inner)
+            | Expr::PostfixInc(inner)
+            | Expr::PostfixDec(inner)
+            | Expr::PrefixInc(inner)
+            | Expr::PrefixDec(inner)
+            | Expr::Reference(inner) => self.lvalue(inner),
+            Expr::FunctionCall(name, args) => {
+                // Only direct function calls are supported, so the call starts with the identifier.
+                let start = expr.span.start();
+                if let Some(&id) = self.functions.get(name) {
+                    self.add_reference(id, (start..start + name.len()).into());
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::UnaryArith(_, inner) | Expr::Cast(inner) => self.expr(inner),
+            Expr::Binary(lhs, _, rhs)
+            | Expr::Relation(lhs, _, rhs)
+            | Expr::LogicalAnd(lhs, rhs)
+            | Expr::LogicalOr(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Assign(lhs, rhs) => {
+                self.lvalue(lhs);
+                self.expr(rhs);
+            }
+        }
+    }
+
+    fn lvalue(&mut self, lvalue: &LvalueExprNode) {
+        self.index.expr_types.push((lvalue.span, lvalue.ty.clone()));
+        match &lvalue.expr {
+            LvalueExpr::Ident(item_id) => {
+                if let Some(&id) = self.locals.get(item_id) {
+                    self.add_reference(id, lvalue.span);
+                }
+            }
+            LvalueExpr::GlobalIdent(name) => {
+                if let Some(&id) = self.globals.get(name) {
+                    self.add_reference(id, lvalue.span);
+                }
+            }
+            LvalueExpr::Dereference(inner) => self.expr(inner),
+        }
+    }
+}
+
+/// Formats the declaration of a variable, e.g. `const char *a[2]`.
+fn declaration(name: &str, ty: &CType, is_const: bool) -> String {
+    let ty = ty.to_string();
+    let qualifier = if is_const { "const " } else { "" };
+    // The array parts come after the name.
+    match ty.find('[') {
+        Some(i) => format!("{qualifier}{}{name}{}", with_separator(&ty[..i]), &ty[i..]),
+        None => format!("{qualifier}{}{name}", with_separator(&ty)),
+    }
+}
+
+fn function_declaration(name: &str, function: &ir::FunctionNode) -> String {
+    let mut params: Vec<_> = function.params.iter().map(|p| p.ty.to_string()).collect();
+    if function.is_vararg {
+        params.push("...".to_owned());
+    }
+    let params = match params.is_empty() {
+        true => "void".to_owned(),
+        false => params.join(", "),
+    };
+    format!(
+        "{}{name}({params})",
+        with_separator(&function.return_type.to_string())
+    )
+}
+
+/// Pointer types already end with a `*`, which is written right before the name.
+fn with_separator(ty: &str) -> String {
+    match ty.ends_with('*') {
+        true => ty.to_owned(),
+        false => format!("{ty} "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{passes, settings::Settings};
+
+    fn index(source: &str) -> SymbolIndex {
+        let ast = passes::parse::parse_to_cst(source)
+            .and_then(|cst| passes::lower_cst::lower(&cst))
+            .into_value()
+            .unwrap();
+        let settings = Settings {
+            target: crate::settings::Target::X86_64,
+            o32_abi: false,
+            bounds_check: false,
+        };
+        let ir = passes::lower_ast::build_ir_from_ast(&ast, &settings)
+            .into_value()
+            .unwrap();
+        SymbolIndex::new(&ir, source)
+    }
+
+    fn spans<'a>(source: &'a str, spans: &[Span]) -> Vec<&'a str> {
+        spans
+            .iter()
+            .map(|span| &source[Range::<usize>::from(*span)])
+            .collect()
+    }
+
+    const SOURCE: &str = "int g = 3;
+int f(int a, char *b);
+int f(int a, char *b) {
+    int c[2];
+    g = a + *b;
+    return f(g, b) + c[1];
+}
+";
+
+    #[test]
+    fn finds_symbols_and_references() {
+        let index = index(SOURCE);
+        let symbols: Vec<_> = index
+            .symbols()
+            .map(|s| (s.name.as_str(), s.kind, s.declaration.as_str()))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                ("g", SymbolKind::GlobalVar, "int g"),
+                ("f", SymbolKind::Function, "int f(int, char *)"),
+                ("a", SymbolKind::Param, "int a"),
+                ("b", SymbolKind::Param, "char *b"),
+                ("c", SymbolKind::LocalVar, "int c[2]"),
+            ]
+        );
+
+        let g = index.symbols().next().unwrap();
+        assert_eq!(spans(SOURCE, &g.references), ["g", "g"]);
+
+        let (f, span) = index.symbol_at(SOURCE.rfind("f(g").unwrap()).unwrap();
+        assert_eq!(spans(SOURCE, &[span]), ["f"]);
+        assert_eq!(f.name, "f");
+        // Points to the definition, not the first declaration.
+        assert_eq!(
+            f.ident_span.start(),
+            SOURCE.find("f(int a, char *b) {").unwrap()
+        );
+        assert_eq!(f.references.len(), 1);
+
+        let (b, _) = index.symbol_at(SOURCE.rfind('b').unwrap()).unwrap();
+        assert_eq!((b.name.as_str(), b.references.len()), ("b", 2));
+    }
+
+    #[test]
+    fn finds_innermost_expression_type() {
+        let index = index(SOURCE);
+        let type_at = |offset| {
+            let (span, ty) = index.type_at(offset).unwrap();
+            (spans(SOURCE, &[span])[0], ty.to_string())
+        };
+        let deref = SOURCE.find("*b;").unwrap();
+        assert_eq!(type_at(deref), ("*b", "char".to_owned()));
+        assert_eq!(type_at(deref + 1), ("b", "char *".to_owned()));
+        assert_eq!(
+            type_at(SOURCE.find("c[1]").unwrap()),
+            ("c", "int[2]".to_owned())
+        );
+    }
+}
@@ -0,0 +1,573 @@
+//! The `printf` and `scanf` functions, which behave like the ones of the C standard library.
+
+use super::{
+    memory::Pointer,
+    value::{normalize, pointer_size, Class, Value},
+    ErrorKind, Interpreter,
+};
+use std::io::{BufRead, Write};
+
+impl<R: BufRead, W: Write> Interpreter<'_, R, W> {
+    pub(super) fn printf(&mut self, args: &[Value]) -> Result<Value, ErrorKind> {
+        let format = self.memory.read_string(pointer_arg(args[0], 's')?, None)?;
+        let mut args = args[1..].iter().copied();
+        let mut out = Vec::new();
+
+        let mut i = 0;
+        while i < format.len() {
+            let c = format[i];
+            i += 1;
+            if c != b'%' {
+                out.push(c);
+                continue;
+            }
+
+            let mut spec = Spec::default();
+            while let Some(&flag) = format.get(i).filter(|c| b"-+ #0".contains(c)) {
+                match flag {
+                    b'-' => spec.left = true,
+                    b'+' => spec.plus = true,
+                    b' ' => spec.space = true,
+                    b'#' => spec.alternate = true,
+                    _ => spec.zero = true,
+                }
+                i += 1;
+            }
+            if format.get(i) == Some(&b'*') {
+                i += 1;
+                let width = int_arg(args.next(), '*')?;
+                spec.left |= width < 0;
+                spec.width = width.unsigned_abs() as usize;
+            } else {
+                spec.width = parse_number(&format, &mut i).unwrap_or(0);
+            }
+            if format.get(i) == Some(&b'.') {
+                i += 1;
+                if format.get(i) == Some(&b'*') {
+                    i += 1;
+                    let precision = int_arg(args.next(), '*')?;
+                    // A negative precision is taken as if it was omitted.
+                    spec.precision = (precision >= 0).then_some(precision as usize);
+                } else {
+                    spec.precision = Some(parse_number(&format, &mut i).unwrap_or(0));
+                }
+            }
+            let length = parse_length(&format, &mut i);
+            let Some(&conversion) = format.get(i) else {
+                return Err(ErrorKind::InvalidFormat("incomplete conversion".to_owned()));
+            };
+            i += 1;
+            let conversion = conversion as char;
+
+            let (prefix, body) = match conversion {
+                'd' | 'i' => {
+                    let size = self.int_size(length);
+                    let value = normalize(int_arg(args.next(), conversion)?, size, true);
+                    let sign = if value < 0 {
+                        "-"
+                    } else if spec.plus {
+                        "+"
+                    } else if spec.space {
+                        " "
+                    } else {
+                        ""
+                    };
+                    (
+                        sign.to_owned(),
+                        spec.digits(value.unsigned_abs().to_string()),
+                    )
+                }
+                'u' | 'o' | 'x' | 'X' => {
+                    let size = self.int_size(length);
+                    let value = normalize(int_arg(args.next(), conversion)?, size, false);
+                    let digits = match conversion {
+                        'u' => value.to_string(),
+                        'o' => format!("{value:o}"),
+                        'x' => format!("{value:x}"),
+                        _ => format!("{value:X}"),
+                    };
+                    let mut digits = spec.digits(digits);
+                    let mut prefix = String::new();
+                    if spec.alternate && conversion == 'o' && !digits.starts_with('0') {
+                        digits.insert(0, '0');
+                    } else if spec.alternate && matches!(conversion, 'x' | 'X') && value != 0 {
+                        prefix = format!("0{conversion}");
+                    }
+                    (prefix, digits)
+                }
+                'c' => {
+                    let value = int_arg(args.next(), conversion)?;
+                    spec.zero = false;
+                    (
+                        String::new(),
+                        String::from_utf8_lossy(&[value as u8]).into_owned(),
+                    )
+                }
+                's' => {
+                    let pointer = pointer_arg(next_arg(args.next(), conversion)?, conversion)?;
+                    let string = self.memory.read_string(pointer, spec.precision)?;
+                    spec.zero = false;
+                    (String::new(), String::from_utf8_lossy(&string).into_owned())
+                }
+                'p' => {
+                    let pointer = pointer_arg(next_arg(args.next(), conversion)?, conversion)?;
+                    spec.zero = false;
+                    match pointer.is_null() {
+                        true => (String::new(), "(nil)".to_owned()),
+                        false => (
+                            "0x".to_owned(),
+                            format!("{:x}", self.memory.address(pointer)?),
+                        ),
+                    }
+                }
+                'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                    let value = match next_arg(args.next(), conversion)? {
+                        Value::Float(value) => value,
+                        _ => return Err(ErrorKind::FormatArgumentMismatch(conversion)),
+                    };
+                    let sign = if value.is_sign_negative() {
+                        "-"
+                    } else if spec.plus {
+                        "+"
+                    } else if spec.space {
+                        " "
+                    } else {
+                        ""
+                    };
+                    let body = spec.float(value.abs(), conversion);
+                    if !value.is_finite() {
+                        spec.zero = false;
+                    }
+                    (sign.to_owned(), body)
+                }
+                '%' => {
+                    out.push(b'%');
+                    continue;
+                }
+                _ => {
+                    return Err(ErrorKind::InvalidFormat(format!(
+                        "unsupported conversion `%{conversion}`"
+                    )))
+                }
+            };
+            out.extend(spec.pad(prefix, body).into_bytes());
+        }
+
+        self.output.write_all(&out).map_err(io_error)?;
+        Ok(Value::Int(out.len() as i128))
+    }
+
+    pub(super) fn scanf(&mut self, args: &[Value]) -> Result<Value, ErrorKind> {
+        let format = self.memory.read_string(pointer_arg(args[0], 's')?, None)?;
+        let mut args = args[1..].iter().copied();
+        self.output.flush().map_err(io_error)?;
+
+        let mut assigned = 0;
+        let mut input_failure = false;
+        let mut i = 0;
+        while i < format.len() {
+            let c = format[i];
+            i += 1;
+            if c.is_ascii_whitespace() {
+                self.skip_whitespace()?;
+                continue;
+            }
+            if c != b'%' || format.get(i) == Some(&b'%') {
+                if c == b'%' {
+                    i += 1;
+                    self.skip_whitespace()?;
+                }
+                match self.peek()? {
+                    Some(input) if input == c => self.input.consume(1),
+                    Some(_) => break,
+                    None => {
+                        input_failure = true;
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let suppress = format.get(i) == Some(&b'*');
+            if suppress {
+                i += 1;
+            }
+            let width = parse_number(&format, &mut i).filter(|&width| width > 0);
+            let length = parse_length(&format, &mut i);
+            let Some(&conversion) = format.get(i) else {
+                return Err(ErrorKind::InvalidFormat("incomplete conversion".to_owned()));
+            };
+            i += 1;
+            let conversion = conversion as char;
+
+            if conversion != 'c' {
+                self.skip_whitespace()?;
+            }
+            if self.peek()?.is_none() {
+                input_failure = true;
+                break;
+            }
+
+            let bytes = match conversion {
+                'd' | 'i' | 'u' | 'o' | 'x' | 'X' => {
+                    let Some(value) = self.scan_int(conversion, width.unwrap_or(usize::MAX))?
+                    else {
+                        break;
+                    };
+                    let size = self.int_size(length);
+                    let signed = matches!(conversion, 'd' | 'i');
+                    if !suppress {
+                        let pointer = pointer_arg(next_arg(args.next(), conversion)?, conversion)?;
+                        let value = Value::Int(normalize(value, size, signed));
+                        self.memory
+                            .write(pointer, Class::Int { size, signed }, value)?;
+                    }
+                    None
+                }
+                'f' | 'e' | 'g' | 'E' | 'G' => {
+                    let Some(value) = self.scan_float(width.unwrap_or(usize::MAX))? else {
+                        break;
+                    };
+                    if !suppress {
+                        let pointer = pointer_arg(next_arg(args.next(), conversion)?, conversion)?;
+                        let class = match length {
+                            Length::Long | Length::LongDouble => Class::Double,
+                            _ => Class::Float,
+                        };
+                        self.memory.write(pointer, class, Value::Float(value))?;
+                    }
+                    None
+                }
+                's' => {
+                    let mut string = Vec::new();
+                    while string.len() < width.unwrap_or(usize::MAX) {
+                        match self.peek()? {
+                            Some(c) if !c.is_ascii_whitespace() => {
+                                string.push(c);
+                                self.input.consume(1);
+                            }
+                            _ => break,
+                        }
+                    }
+                    string.push(0);
+                    Some(string)
+                }
+                'c' => {
+                    let mut string = Vec::new();
+                    while string.len() < width.unwrap_or(1) {
+                        match self.peek()? {
+                            Some(c) => {
+                                string.push(c);
+                                self.input.consume(1);
+                            }
+                            None => break,
+                        }
+                    }
+                    Some(string)
+                }
+                _ => {
+                    return Err(ErrorKind::InvalidFormat(format!(
+                        "unsupported conversion `%{conversion}`"
+                    )))
+                }
+            };
+            if let (Some(bytes), false) = (bytes, suppress) {
+                let pointer = pointer_arg(next_arg(args.next(), conversion)?, conversion)?;
+                let byte = Class::Int {
+                    size: 1,
+                    signed: false,
+                };
+                for (i, c) in bytes.into_iter().enumerate() {
+                    let value = Value::Int(c as i128);
+                    self.memory
+                        .write(pointer.offset_by(i as i64), byte, value)?;
+                }
+            }
+            if !suppress {
+                assigned += 1;
+            }
+        }
+
+        Ok(Value::Int(match input_failure && assigned == 0 {
+            true => -1,
+            false => assigned,
+        }))
+    }
+
+    /// Returns the size of the integer type of a length modifier.
+    fn int_size(&self, length: Length) -> u64 {
+        match length {
+            Length::Char => 1,
+            Length::Short => 2,
+            Length::None | Length::LongDouble => 4,
+            // `long` is as large as a pointer on the supported targets.
+            Length::Long => pointer_size(&self.settings),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, ErrorKind> {
+        let buf = self.input.fill_buf().map_err(io_error)?;
+        Ok(buf.first().copied())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ErrorKind> {
+        while self.peek()?.is_some_and(|c| c.is_ascii_whitespace()) {
+            self.input.consume(1);
+        }
+        Ok(())
+    }
+
+    /// Consumes the next byte if `accept` returns `true` for it, and there are less than `max`
+    /// bytes in `text`.
+    fn accept(
+        &mut self,
+        text: &mut String,
+        max: usize,
+        accept: impl Fn(u8) -> bool,
+    ) -> Result<bool, ErrorKind> {
+        if text.len() >= max {
+            return Ok(false);
+        }
+        match self.peek()? {
+            Some(c) if accept(c) => {
+                text.push(c as char);
+                self.input.consume(1);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Reads an integer of at most `max` characters, or `None` if there is no integer.
+    fn scan_int(&mut self, conversion: char, max: usize) -> Result<Option<i128>, ErrorKind> {
+        let mut text = String::new();
+        self.accept(&mut text, max, |c| c == b'+' || c == b'-')?;
+        let digits_start = text.len();
+        let mut radix = match conversion {
+            'o' => 8,
+            'x' | 'X' => 16,
+            _ => 10,
+        };
+        if matches!(conversion, 'i' | 'x' | 'X') && self.accept(&mut text, max, |c| c == b'0')? {
+            if self.accept(&mut text, max, |c| c == b'x' || c == b'X')? {
+                radix = 16;
+            } else if conversion == 'i' {
+                radix = 8;
+            }
+        }
+        while self.accept(&mut text, max, |c| (c as char).is_digit(radix))? {}
+
+        let digits = text[digits_start..]
+            .trim_start_matches("0x")
+            .trim_start_matches("0X");
+        let digits = match digits {
+            "" if text[digits_start..].starts_with('0') => "0",
+            digits => digits,
+        };
+        let Some(magnitude) = digits.chars().try_fold(0i128, |value, c| {
+            let digit = c.to_digit(radix)? as i128;
+            Some(value.saturating_mul(radix as i128).saturating_add(digit))
+        }) else {
+            return Ok(None);
+        };
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(match text.starts_with('-') {
+            true => -magnitude,
+            false => magnitude,
+        }))
+    }
+
+    /// Reads a decimal floating point number of at most `max` characters, or `None` if there is no
+    /// number.
+    fn scan_float(&mut self, max: usize) -> Result<Option<f64>, ErrorKind> {
+        let mut text = String::new();
+        self.accept(&mut text, max, |c| c == b'+' || c == b'-')?;
+        let mut digits = 0;
+        while self.accept(&mut text, max, |c| c.is_ascii_digit())? {
+            digits += 1;
+        }
+        if self.accept(&mut text, max, |c| c == b'.')? {
+            while self.accept(&mut text, max, |c| c.is_ascii_digit())? {
+                digits += 1;
+            }
+        }
+        if digits == 0 {
+            return Ok(None);
+        }
+        if self.accept(&mut text, max, |c| c == b'e' || c == b'E')? {
+            self.accept(&mut text, max, |c| c == b'+' || c == b'-')?;
+            if !self.accept(&mut text, max, |c| c.is_ascii_digit())? {
+                return Ok(None);
+            }
+            while self.accept(&mut text, max, |c| c.is_ascii_digit())? {}
+        }
+        Ok(text.parse().ok())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Length {
+    None,
+    Char,
+    Short,
+    Long,
+    LongDouble,
+}
+
+fn parse_length(format: &[u8], i: &mut usize) -> Length {
+    let (length, len) = match &format[*i..] {
+        [b'h', b'h', ..] => (Length::Char, 2),
+        [b'h', ..] => (Length::Short, 1),
+        [b'l', ..] => (Length::Long, 1),
+        [b'L', ..] => (Length::LongDouble, 1),
+        _ => (Length::None, 0),
+    };
+    *i += len;
+    length
+}
+
+fn parse_number(format: &[u8], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    while format.get(*i).is_some_and(u8::is_ascii_digit) {
+        *i += 1;
+    }
+    std::str::from_utf8(&format[start..*i]).ok()?.parse().ok()
+}
+
+/// A printf conversion specification.
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Applies the precision, the minimum number of digits, to the digits of an integer.
+    fn digits(&mut self, digits: String) -> String {
+        match self.precision {
+            Some(precision) => {
+                // The 0 flag is ignored if there is a precision.
+                self.zero = false;
+                if precision == 0 && digits == "0" {
+                    String::new()
+                } else {
+                    format!("{digits:0>precision$}")
+                }
+            }
+            None => digits,
+        }
+    }
+
+    /// Formats a positive floating point number.
+    fn float(&self, value: f64, conversion: char) -> String {
+        let upper = conversion.is_ascii_uppercase();
+        if !value.is_finite() {
+            let text = if value.is_nan() { "nan" } else { "inf" };
+            return if upper {
+                text.to_uppercase()
+            } else {
+                text.to_owned()
+            };
+        }
+        let precision = self.precision.unwrap_or(6);
+        let text = match conversion.to_ascii_lowercase() {
+            'f' => self.with_point(format!("{value:.precision$}")),
+            'e' => self.with_point(exponential(value, precision)),
+            _ => {
+                // %g uses %e if the exponent is less than -4 or at least the precision.
+                let precision = precision.max(1);
+                let exponent = exponential(value, precision - 1);
+                let exp: i32 = exponent[exponent.find('e').unwrap() + 1..].parse().unwrap();
+                let text = if exp < -4 || exp >= precision as i32 {
+                    exponent
+                } else {
+                    format!("{value:.*}", (precision as i32 - 1 - exp) as usize)
+                };
+                match self.alternate {
+                    true => self.with_point(text),
+                    false => strip_trailing_zeros(text),
+                }
+            }
+        };
+        if upper {
+            text.to_uppercase()
+        } else {
+            text
+        }
+    }
+
+    /// The # flag makes sure there is always a decimal point.
+    fn with_point(&self, text: String) -> String {
+        if !self.alternate || text.contains('.') {
+            return text;
+        }
+        match text.find('e') {
+            Some(e) => format!("{}.{}", &text[..e], &text[e..]),
+            None => text + ".",
+        }
+    }
+
+    /// Pads `prefix` (e.g. the sign) and `body` to the width.
+    fn pad(&self, prefix: String, body: String) -> String {
+        let len = prefix.len() + body.len();
+        let padding = self.width.saturating_sub(len);
+        if self.left {
+            format!("{prefix}{body}{}", " ".repeat(padding))
+        } else if self.zero {
+            format!("{prefix}{}{body}", "0".repeat(padding))
+        } else {
+            format!("{}{prefix}{body}", " ".repeat(padding))
+        }
+    }
+}
+
+/// Formats the number like `%e`, e.g. `1.500000e+02`.
+fn exponential(value: f64, precision: usize) -> String {
+    let text = format!("{value:.precision$e}");
+    let (mantissa, exponent) = text.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+}
+
+/// Removes the trailing zeros of the fraction, and the decimal point if nothing is left of it.
+fn strip_trailing_zeros(text: String) -> String {
+    let (number, exponent) = match text.find('e') {
+        Some(e) => text.split_at(e),
+        None => (text.as_str(), ""),
+    };
+    if !number.contains('.') {
+        return text;
+    }
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{number}{exponent}")
+}
+
+fn next_arg(arg: Option<Value>, conversion: char) -> Result<Value, ErrorKind> {
+    arg.ok_or(ErrorKind::MissingFormatArgument(conversion))
+}
+
+fn int_arg(arg: Option<Value>, conversion: char) -> Result<i128, ErrorKind> {
+    match next_arg(arg, conversion)? {
+        Value::Int(value) => Ok(value),
+        _ => Err(ErrorKind::FormatArgumentMismatch(conversion)),
+    }
+}
+
+fn pointer_arg(arg: Value, conversion: char) -> Result<Pointer, ErrorKind> {
+    match arg {
+        Value::Pointer(pointer) => Ok(pointer),
+        _ => Err(ErrorKind::FormatArgumentMismatch(conversion)),
+    }
+}
+
+fn io_error(err: std::io::Error) -> ErrorKind {
+    ErrorKind::Io(err.to_string())
+}
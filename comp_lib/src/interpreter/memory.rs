@@ -0,0 +1,281 @@
+//! Every variable and string literal is a separate allocation. Pointers remember the allocation
+//! they point into, so out-of-bounds accesses and accesses to variables of functions that already
+//! returned can be detected. Pointers stored in memory keep this information as well.
+
+use super::{
+    value::{normalize, Class, Value},
+    ErrorKind,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// The first address handed out, so that no allocation is at (or close to) the null pointer.
+const FIRST_ADDRESS: u64 = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AllocId(u64);
+
+/// A pointer into an allocation. Pointers without an allocation are the null pointer (offset 0)
+/// or made from an integer that isn't the address of any allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pointer {
+    alloc: Option<AllocId>,
+    offset: i64,
+}
+
+impl Pointer {
+    pub const NULL: Pointer = Pointer {
+        alloc: None,
+        offset: 0,
+    };
+
+    pub fn new(alloc: AllocId) -> Self {
+        Self {
+            alloc: Some(alloc),
+            offset: 0,
+        }
+    }
+
+    pub fn is_null(self) -> bool {
+        self == Self::NULL
+    }
+
+    /// Returns the distance in bytes from `origin` to this pointer, or `None` if they don't point
+    /// into the same allocation.
+    pub fn offset_from(self, origin: Pointer) -> Option<i64> {
+        (self.alloc == origin.alloc).then(|| self.offset.wrapping_sub(origin.offset))
+    }
+
+    /// Moves the pointer `bytes` bytes, which may be negative.
+    pub fn offset_by(self, bytes: i64) -> Self {
+        Self {
+            alloc: self.alloc,
+            offset: self.offset.wrapping_add(bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocKind {
+    Variable,
+    StringLiteral,
+}
+
+struct Allocation {
+    address: u64,
+    kind: AllocKind,
+    bytes: Vec<u8>,
+    init: Vec<bool>,
+    /// The pointers stored in this allocation by offset.
+    pointers: BTreeMap<u64, Pointer>,
+}
+
+pub struct Memory {
+    pointer_size: u64,
+    /// The live allocations, ids of freed allocations are never reused.
+    allocations: HashMap<AllocId, Allocation>,
+    next_id: u64,
+    /// The live allocations by address.
+    addresses: BTreeMap<u64, AllocId>,
+    next_address: u64,
+}
+
+impl Memory {
+    pub fn new(pointer_size: u64) -> Self {
+        Self {
+            pointer_size,
+            allocations: HashMap::new(),
+            next_id: 0,
+            addresses: BTreeMap::new(),
+            next_address: FIRST_ADDRESS,
+        }
+    }
+
+    /// Allocates `size` uninitialized bytes.
+    pub fn allocate(&mut self, size: u64, kind: AllocKind) -> AllocId {
+        self.allocate_with(vec![0; size as usize], false, kind)
+    }
+
+    /// Allocates memory initialized with `bytes`.
+    pub fn allocate_bytes(&mut self, bytes: Vec<u8>, kind: AllocKind) -> AllocId {
+        self.allocate_with(bytes, true, kind)
+    }
+
+    fn allocate_with(&mut self, bytes: Vec<u8>, init: bool, kind: AllocKind) -> AllocId {
+        let id = AllocId(self.next_id);
+        self.next_id += 1;
+        let address = self.next_address;
+        // Leave a gap, so that a pointer one past the end isn't the address of the next
+        // allocation.
+        self.next_address = (address + bytes.len() as u64 + 1).next_multiple_of(16);
+        self.addresses.insert(address, id);
+        let allocation = Allocation {
+            address,
+            kind,
+            init: vec![init; bytes.len()],
+            bytes,
+            pointers: BTreeMap::new(),
+        };
+        self.allocations.insert(id, allocation);
+        id
+    }
+
+    /// Returns the address that the next allocation will get.
+    pub fn next_address(&self) -> u64 {
+        self.next_address
+    }
+
+    /// Frees the allocations, and reuses their addresses if `reset_address` is given. Like a
+    /// stack, this should only be done if all allocations after that address are freed.
+    pub fn free(&mut self, ids: impl IntoIterator<Item = AllocId>, reset_address: Option<u64>) {
+        for id in ids {
+            if let Some(allocation) = self.allocations.remove(&id) {
+                self.addresses.remove(&allocation.address);
+            }
+        }
+        if let Some(address) = reset_address {
+            self.next_address = address;
+        }
+    }
+
+    /// Returns the numeric address of the pointer, e.g. to cast it to an integer.
+    pub fn address(&self, pointer: Pointer) -> Result<u64, ErrorKind> {
+        match pointer.alloc {
+            Some(id) => match self.allocations.get(&id) {
+                Some(allocation) => Ok(allocation.address.wrapping_add(pointer.offset as u64)),
+                None => Err(ErrorKind::DanglingPointer),
+            },
+            None => Ok(pointer.offset as u64),
+        }
+    }
+
+    /// Converts an integer to a pointer. The pointer can only be dereferenced if it's the address
+    /// of a byte in a live allocation.
+    pub fn pointer_from_address(&self, address: u64) -> Pointer {
+        if let Some((&start, &id)) = self.addresses.range(..=address).next_back() {
+            if address - start < self.allocations[&id].bytes.len() as u64 {
+                return Pointer {
+                    alloc: Some(id),
+                    offset: (address - start) as i64,
+                };
+            }
+        }
+        Pointer {
+            alloc: None,
+            offset: address as i64,
+        }
+    }
+
+    /// Checks that `size` bytes can be accessed at the pointer.
+    fn check(&self, pointer: Pointer, size: u64) -> Result<(&Allocation, usize), ErrorKind> {
+        let Some(id) = pointer.alloc else {
+            return Err(if pointer.is_null() {
+                ErrorKind::NullDereference
+            } else {
+                ErrorKind::InvalidPointer(pointer.offset as u64)
+            });
+        };
+        let allocation = self
+            .allocations
+            .get(&id)
+            .ok_or(ErrorKind::DanglingPointer)?;
+        let len = allocation.bytes.len() as u64;
+        if pointer.offset < 0 || pointer.offset as u64 + size > len {
+            return Err(ErrorKind::OutOfBounds {
+                offset: pointer.offset,
+                size,
+                len,
+            });
+        }
+        Ok((allocation, pointer.offset as usize))
+    }
+
+    pub fn read(&self, pointer: Pointer, class: Class) -> Result<Value, ErrorKind> {
+        let size = self.size(class);
+        let (allocation, offset) = self.check(pointer, size)?;
+        let range = offset..offset + size as usize;
+        if allocation.init[range.clone()].contains(&false) {
+            return Err(ErrorKind::UninitializedRead);
+        }
+        if class == Class::Pointer {
+            if let Some(pointer) = allocation.pointers.get(&(offset as u64)) {
+                return Ok(Value::Pointer(*pointer));
+            }
+        }
+        let bits = allocation.bytes[range]
+            .iter()
+            .rev()
+            .fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+        Ok(match class {
+            Class::Int { size, signed } => Value::Int(normalize(bits as i128, size, signed)),
+            Class::Float => Value::Float(f32::from_bits(bits as u32) as f64),
+            Class::Double => Value::Float(f64::from_bits(bits)),
+            Class::Pointer => Value::Pointer(self.pointer_from_address(bits)),
+        })
+    }
+
+    pub fn write(&mut self, pointer: Pointer, class: Class, value: Value) -> Result<(), ErrorKind> {
+        let size = self.size(class);
+        let (allocation, offset) = self.check(pointer, size)?;
+        if allocation.kind == AllocKind::StringLiteral {
+            return Err(ErrorKind::ModifiedStringLiteral);
+        }
+        let bits = match (class, value) {
+            (Class::Int { .. }, Value::Int(value)) => value as u64,
+            (Class::Float, Value::Float(value)) => (value as f32).to_bits() as u64,
+            (Class::Double, Value::Float(value)) => value.to_bits(),
+            (Class::Pointer, Value::Pointer(pointer)) => self.address(pointer)?,
+            (class, value) => panic!("ICE: can't store {value:?} as {class:?}"),
+        };
+        let pointer_size = self.pointer_size;
+        let allocation = self.allocations.get_mut(&pointer.alloc.unwrap()).unwrap();
+        for (i, byte) in bits.to_le_bytes()[..size as usize].iter().enumerate() {
+            allocation.bytes[offset + i] = *byte;
+            allocation.init[offset + i] = true;
+        }
+        // The bytes of stored pointers that are overwritten are just integers now.
+        let first_overlapping = (offset as u64 + 1).saturating_sub(pointer_size);
+        let overlapping: Vec<u64> = allocation
+            .pointers
+            .range(first_overlapping..offset as u64 + size)
+            .map(|(&offset, _)| offset)
+            .collect();
+        for offset in overlapping {
+            allocation.pointers.remove(&offset);
+        }
+        if let Value::Pointer(pointer) = value {
+            allocation.pointers.insert(offset as u64, pointer);
+        }
+        Ok(())
+    }
+
+    /// Reads a null-terminated string, without the null byte. If `max_len` is given, at most that
+    /// many bytes are read and the string doesn't have to be null-terminated.
+    pub fn read_string(
+        &self,
+        pointer: Pointer,
+        max_len: Option<usize>,
+    ) -> Result<Vec<u8>, ErrorKind> {
+        let byte = Class::Int {
+            size: 1,
+            signed: false,
+        };
+        let mut string = Vec::new();
+        while max_len.is_none_or(|max_len| string.len() < max_len) {
+            match self.read(pointer.offset_by(string.len() as i64), byte)? {
+                Value::Int(0) => break,
+                Value::Int(c) => string.push(c as u8),
+                _ => unreachable!(),
+            }
+        }
+        Ok(string)
+    }
+
+    pub fn size(&self, class: Class) -> u64 {
+        match class {
+            Class::Int { size, .. } => size,
+            Class::Float => 4,
+            Class::Double => 8,
+            Class::Pointer => self.pointer_size,
+        }
+    }
+}
@@ -0,0 +1,856 @@
+//! A reference interpreter that executes an [`ir::Root`] directly, with the semantics of C.
+//!
+//! It doesn't depend on any of the backends, so it can be used as the golden model for them: the
+//! output of a program compiled by a backend should be the output of the interpreter. Undefined
+//! behaviour the interpreter runs into is reported as a [`RuntimeError`] with the span of the
+//! offending expression, instead of doing whatever the hardware happens to do.
+//!
+//! Some details of the model:
+//!
+//!  - Every variable (and string literal) is a separate allocation, see [`memory`]. All variables
+//!    of a function are allocated when it's called, and freed when it returns.
+//!  - Integer and pointer sizes follow the [`Target`], e.g. `long` is 32 bits for MIPS.
+//!  - The functions `printf` and `scanf` are built in, if they aren't defined by the program.
+//!  - `main` is called without arguments, and returns 0 if it doesn't return a value.
+
+mod builtins;
+mod memory;
+mod value;
+
+use crate::{
+    diagnostic::Span,
+    ir::{
+        self,
+        ctype::{self, CType},
+        table::ItemId,
+    },
+    settings::{Settings, Target},
+};
+use memory::{AllocId, AllocKind, Memory, Pointer};
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+use value::{ctype_size, fits, normalize, pointee_size, pointer_size, Class, Value};
+
+/// The maximum depth of nested function calls.
+const MAX_CALL_DEPTH: usize = 10_000;
+/// Every call of the interpreted program takes several (large, in debug builds) stack frames of
+/// the interpreter, so it runs on its own thread with a stack that fits [`MAX_CALL_DEPTH`] calls.
+const STACK_SIZE: usize = 512 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    /// The span of the expression or statement that caused the error.
+    pub span: Span,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Access of `size` bytes at `offset` in an object of `len` bytes.
+    OutOfBounds {
+        offset: i64,
+        size: u64,
+        len: u64,
+    },
+    NullDereference,
+    /// Dereference of a pointer made from an integer that isn't the address of an object.
+    InvalidPointer(u64),
+    /// Use of a pointer to a variable of a function that already returned.
+    DanglingPointer,
+    UninitializedRead,
+    SignedOverflow,
+    DivisionByZero,
+    /// A shift by a negative amount, or by at least the width of the type.
+    InvalidShift(i128),
+    /// A floating point value that doesn't fit in the integer type it's converted to.
+    FloatToIntOverflow,
+    ModifiedStringLiteral,
+    /// Subtraction of pointers that don't point into the same object.
+    PointerSubtraction,
+    /// The value of a call to a function that returned without a value is used.
+    MissingReturnValue,
+    /// A `printf` or `scanf` conversion without a matching argument.
+    MissingFormatArgument(char),
+    /// A `printf` or `scanf` argument with the wrong type for its conversion.
+    FormatArgumentMismatch(char),
+    InvalidFormat(String),
+    /// A call to a function that is declared but not defined.
+    UndefinedFunction(String),
+    /// The step limit set with [`Interpreter::with_step_limit`] was reached.
+    StepLimit(u64),
+    StackOverflow,
+    /// Reading the input or writing the output failed.
+    Io(String),
+}
+
+impl ErrorKind {
+    /// Returns `true` if the error is undefined behaviour of the program, as opposed to e.g. a
+    /// limit of the interpreter.
+    pub fn is_undefined_behavior(&self) -> bool {
+        !matches!(
+            self,
+            ErrorKind::UndefinedFunction(_)
+                | ErrorKind::StepLimit(_)
+                | ErrorKind::StackOverflow
+                | ErrorKind::Io(_)
+        )
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::OutOfBounds { offset, size, len } => write!(
+                f,
+                "out-of-bounds access of {size} bytes at offset {offset} of an object of {len} \
+                 bytes"
+            ),
+            ErrorKind::NullDereference => f.write_str("null pointer dereference"),
+            ErrorKind::InvalidPointer(address) => {
+                write!(f, "dereference of invalid pointer 0x{address:x}")
+            }
+            ErrorKind::DanglingPointer => {
+                f.write_str("use of a pointer to a variable that no longer exists")
+            }
+            ErrorKind::UninitializedRead => f.write_str("read of uninitialized memory"),
+            ErrorKind::SignedOverflow => f.write_str("signed integer overflow"),
+            ErrorKind::DivisionByZero => f.write_str("division by zero"),
+            ErrorKind::InvalidShift(amount) => write!(f, "shift by invalid amount {amount}"),
+            ErrorKind::FloatToIntOverflow => {
+                f.write_str("floating point value out of range of the integer type")
+            }
+            ErrorKind::ModifiedStringLiteral => f.write_str("modification of a string literal"),
+            ErrorKind::PointerSubtraction => {
+                f.write_str("subtraction of pointers into different objects")
+            }
+            ErrorKind::MissingReturnValue => {
+                f.write_str("use of the value of a function that didn't return a value")
+            }
+            ErrorKind::MissingFormatArgument(c) => write!(f, "missing argument for `%{c}`"),
+            ErrorKind::FormatArgumentMismatch(c) => {
+                write!(f, "argument of the wrong type for `%{c}`")
+            }
+            ErrorKind::InvalidFormat(msg) => write!(f, "invalid format string: {msg}"),
+            ErrorKind::UndefinedFunction(name) => write!(f, "function `{name}` is not defined"),
+            ErrorKind::StepLimit(limit) => write!(f, "reached the limit of {limit} steps"),
+            ErrorKind::StackOverflow => {
+                write!(f, "more than {MAX_CALL_DEPTH} nested function calls")
+            }
+            ErrorKind::Io(err) => write!(f, "I/O error: {err}"),
+        }?;
+        write!(f, " at {:?}", self.span)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+trait At<T> {
+    fn at(self, span: Span) -> Result<T, RuntimeError>;
+}
+
+impl<T> At<T> for Result<T, ErrorKind> {
+    fn at(self, span: Span) -> Result<T, RuntimeError> {
+        self.map_err(|kind| RuntimeError { span, kind })
+    }
+}
+
+/// How a statement ended.
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// The variables of a function call.
+struct Frame<'a> {
+    function: &'a ir::FunctionNode,
+    locals: HashMap<ItemId, Pointer>,
+}
+
+/// Executes an [`ir::Root`]. The builtin `printf` and `scanf` read from `input` and write to
+/// `output`.
+///
+/// ```skip
+/// let mut output = Vec::new();
+/// let code = Interpreter::new(&root, Target::X86_64, std::io::empty(), &mut output)
+///     .with_step_limit(1_000_000)
+///     .run()?;
+/// ```
+pub struct Interpreter<'a, R, W> {
+    root: &'a ir::Root,
+    settings: Settings,
+    input: R,
+    output: W,
+    step_limit: Option<u64>,
+    steps: u64,
+    depth: usize,
+    memory: Memory,
+    globals: HashMap<&'a str, AllocId>,
+    /// Equal string literals share their allocation.
+    string_literals: HashMap<&'a [u8], AllocId>,
+}
+
+impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
+    pub fn new(root: &'a ir::Root, target: Target, input: R, output: W) -> Self {
+        let settings = Settings {
+            target,
+            o32_abi: false,
+            bounds_check: false,
+        };
+        Self {
+            root,
+            memory: Memory::new(pointer_size(&settings)),
+            settings,
+            input,
+            output,
+            step_limit: None,
+            steps: 0,
+            depth: 0,
+            globals: HashMap::new(),
+            string_literals: HashMap::new(),
+        }
+    }
+
+    /// Stops the program with [`ErrorKind::StepLimit`] when it tries to execute more than `limit`
+    /// statements (or loop iterations), e.g. to catch infinite loops.
+    pub fn with_step_limit(self, limit: u64) -> Self {
+        Self {
+            step_limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Runs `main`, and returns its exit code.
+    pub fn run(&mut self) -> Result<i32, RuntimeError>
+    where
+        R: Send,
+        W: Send,
+    {
+        std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || self.run_main())
+                .expect("failed to spawn the interpreter thread")
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    fn run_main(&mut self) -> Result<i32, RuntimeError> {
+        let main = match self.root.functions.get("main") {
+            Some(main) if !main.is_declaration() => main,
+            _ => {
+                return Err(RuntimeError {
+                    span: Span::default(),
+                    kind: ErrorKind::UndefinedFunction("main".to_owned()),
+                })
+            }
+        };
+
+        self.allocate_globals()?;
+
+        let args = main
+            .params
+            .iter()
+            .map(|param| convert(&self.memory, Value::Int(0), &param.ty, &self.settings))
+            .collect::<Result<Vec<_>, _>>()
+            .at(main.original_span)?;
+        let result = self.call("main", main, args, main.original_span)?;
+        self.output
+            .flush()
+            .map_err(|err| ErrorKind::Io(err.to_string()))
+            .at(main.original_span)?;
+
+        match result {
+            Value::Int(code) => Ok(code as i32),
+            _ => Ok(0),
+        }
+    }
+
+    fn allocate_globals(&mut self) -> Result<(), RuntimeError> {
+        // Sorted, so that the addresses don't depend on the order of the hash map.
+        let mut globals: Vec<_> = self.root.vars.iter().collect();
+        globals.sort_by_key(|(name, _)| *name);
+
+        for (name, global) in globals {
+            let size = ctype_size(&global.ty, &self.settings);
+            let id = self
+                .memory
+                .allocate_bytes(vec![0; size as usize], AllocKind::Variable);
+            self.globals.insert(name.as_str(), id);
+
+            let value = match (&global.value, &global.ty) {
+                (None, _) => continue,
+                (Some(ir::Constant::String(string)), CType::Aggregate(_)) => {
+                    let byte = Class::Int {
+                        size: 1,
+                        signed: false,
+                    };
+                    for (i, c) in string.iter().take(size as usize).enumerate() {
+                        self.memory
+                            .write(
+                                Pointer::new(id).offset_by(i as i64),
+                                byte,
+                                Value::Int(*c as i128),
+                            )
+                            .at(global.original_span)?;
+                    }
+                    continue;
+                }
+                (Some(ir::Constant::String(string)), _) => {
+                    Value::Pointer(self.string_literal(string))
+                }
+                (Some(ir::Constant::Integer(value)), _) => Value::Int(*value),
+                (Some(ir::Constant::Float(value)), _) => Value::Float(*value),
+            };
+            let value =
+                convert(&self.memory, value, &global.ty, &self.settings).at(global.original_span)?;
+            self.store(Pointer::new(id), &global.ty, value)
+                .at(global.original_span)?;
+        }
+        Ok(())
+    }
+
+    fn string_literal(&mut self, string: &'a [u8]) -> Pointer {
+        let id = match self.string_literals.get(string) {
+            Some(id) => *id,
+            None => {
+                let id = self
+                    .memory
+                    .allocate_bytes(string.to_vec(), AllocKind::StringLiteral);
+                self.string_literals.insert(string, id);
+                id
+            }
+        };
+        Pointer::new(id)
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        function: &'a ir::FunctionNode,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let Some(body) = &function.body else {
+            return match name {
+                "printf" => self.printf(&args).at(span),
+                "scanf" => self.scanf(&args).at(span),
+                _ => Err(ErrorKind::UndefinedFunction(name.to_owned())).at(span),
+            };
+        };
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(ErrorKind::StackOverflow).at(span);
+        }
+
+        // Like a stack, the variables of the call get the same addresses as the ones of the
+        // previous call that returned.
+        let base = self.memory.next_address();
+        let mut frame = Frame {
+            function,
+            locals: HashMap::new(),
+        };
+        let mut allocations = Vec::new();
+        let mut array_params = HashMap::new();
+        for (param, arg) in function.params.iter().zip(&args) {
+            if let (Some(id), CType::Aggregate(_)) = (param.ident, &param.ty) {
+                // Arrays are passed as a pointer to their first element.
+                let Value::Pointer(pointer) = arg else {
+                    panic!("ICE: array arguments should be pointers")
+                };
+                array_params.insert(id, *pointer);
+            }
+        }
+        for (id, item) in function.table.iter() {
+            let pointer = match array_params.get(&id) {
+                Some(pointer) => *pointer,
+                None => {
+                    let size = ctype_size(&item.ty, &self.settings);
+                    let alloc = self.memory.allocate(size, AllocKind::Variable);
+                    allocations.push(alloc);
+                    Pointer::new(alloc)
+                }
+            };
+            frame.locals.insert(id, pointer);
+        }
+
+        self.depth += 1;
+        let result = self.call_body(&frame, &args, body);
+        self.depth -= 1;
+        self.memory.free(allocations, Some(base));
+
+        match result? {
+            Flow::Return(value) => Ok(value),
+            // Falling off the end of `main` returns 0.
+            _ if name == "main" => Ok(Value::Int(0)),
+            _ => Ok(Value::Void),
+        }
+    }
+
+    fn call_body(
+        &mut self,
+        frame: &Frame<'a>,
+        args: &[Value],
+        body: &'a ir::BlockNode,
+    ) -> Result<Flow, RuntimeError> {
+        for (param, arg) in frame.function.params.iter().zip(args) {
+            if let (Some(id), false) = (param.ident, is_array(&param.ty)) {
+                self.store(frame.locals[&id], &param.ty, *arg)
+                    .at(param.span)?;
+            }
+        }
+        self.block(frame, body)
+    }
+
+    fn step(&mut self, span: Span) -> Result<(), RuntimeError> {
+        if let Some(limit) = self.step_limit.filter(|limit| self.steps >= *limit) {
+            return Err(ErrorKind::StepLimit(limit)).at(span);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn block(&mut self, frame: &Frame<'a>, block: &'a ir::BlockNode) -> Result<Flow, RuntimeError> {
+        for stmt in &block.stmts {
+            match self.stmt(frame, stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn stmt(&mut self, frame: &Frame<'a>, stmt: &'a ir::StmtNode) -> Result<Flow, RuntimeError> {
+        self.step(stmt.span)?;
+        match &stmt.stmt {
+            ir::Stmt::Expr(expr) => {
+                self.discarded_expr(frame, expr)?;
+                Ok(Flow::Normal)
+            }
+            ir::Stmt::IfStmt(if_stmt) => {
+                let condition = self.expr(frame, &if_stmt.condition)?;
+                if is_true(condition) {
+                    self.block(frame, &if_stmt.if_branch)
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    self.block(frame, else_branch)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            ir::Stmt::SwitchStmt(switch) => {
+                let Value::Int(value) = self.expr(frame, &switch.expr)? else {
+                    panic!("ICE: the controlling expression of a switch should be an integer")
+                };
+                let start = switch
+                    .cases
+                    .iter()
+                    .position(|case| {
+                        matches!(case.data, ir::SwitchStmtCase::Case { label, .. } if label == value)
+                    })
+                    .or_else(|| {
+                        switch.cases.iter().position(|case| {
+                            matches!(case.data, ir::SwitchStmtCase::Default { .. })
+                        })
+                    });
+                let Some(start) = start else {
+                    return Ok(Flow::Normal);
+                };
+                // Execution falls through to the next cases until a break.
+                for case in &switch.cases[start..] {
+                    let body = match &case.data {
+                        ir::SwitchStmtCase::Case { body, .. } => body,
+                        ir::SwitchStmtCase::Default { body } => body,
+                    };
+                    match self.block(frame, body)? {
+                        Flow::Normal => {}
+                        Flow::Break => return Ok(Flow::Normal),
+                        flow => return Ok(flow),
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            ir::Stmt::LoopStmt(loop_stmt) => {
+                loop {
+                    if let Some(condition) = &loop_stmt.condition {
+                        if !is_true(self.expr(frame, condition)?) {
+                            break;
+                        }
+                    }
+                    match self.block(frame, &loop_stmt.body)? {
+                        Flow::Normal | Flow::Continue => {}
+                        Flow::Break => break,
+                        flow @ Flow::Return(_) => return Ok(flow),
+                    }
+                    if let Some(continuation) = &loop_stmt.continuation {
+                        self.discarded_expr(frame, continuation)?;
+                    }
+                    self.step(loop_stmt.span)?;
+                }
+                Ok(Flow::Normal)
+            }
+            ir::Stmt::Break => Ok(Flow::Break),
+            ir::Stmt::Continue => Ok(Flow::Continue),
+            ir::Stmt::Return(None) => Ok(Flow::Return(Value::Void)),
+            ir::Stmt::Return(Some(expr)) => {
+                let value = self.expr(frame, expr)?;
+                let value = convert(
+                    &self.memory,
+                    value,
+                    &frame.function.return_type,
+                    &self.settings,
+                )
+                .at(expr.span)?;
+                Ok(Flow::Return(value))
+            }
+        }
+    }
+
+    /// Evaluates an expression of which the value isn't used, which may be a call to a function
+    /// that doesn't return a value.
+    fn discarded_expr(
+        &mut self,
+        frame: &Frame<'a>,
+        expr: &'a ir::ExprNode,
+    ) -> Result<(), RuntimeError> {
+        match &expr.expr {
+            ir::Expr::FunctionCall(name, args) => {
+                self.function_call(frame, name, args, expr.span)?;
+            }
+            ir::Expr::Cast(inner) if expr.ty == CType::Void => self.discarded_expr(frame, inner)?,
+            _ => {
+                self.expr(frame, expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn function_call(
+        &mut self,
+        frame: &Frame<'a>,
+        name: &str,
+        args: &'a [ir::ExprNode],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let function = self
+            .root
+            .functions
+            .get(name)
+            .unwrap_or_else(|| panic!("ICE: function `{name}` should be declared"));
+        let args = args
+            .iter()
+            .map(|arg| self.expr(frame, arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.call(name, function, args, span)
+    }
+
+    fn expr(&mut self, frame: &Frame<'a>, expr: &'a ir::ExprNode) -> Result<Value, RuntimeError> {
+        let span = expr.span;
+        match &expr.expr {
+            ir::Expr::LvalueDeref(lvalue) => {
+                let pointer = self.place(frame, lvalue)?;
+                self.load(pointer, &lvalue.ty).at(span)
+            }
+            ir::Expr::Constant(ir::Constant::String(string)) => {
+                Ok(Value::Pointer(self.string_literal(string)))
+            }
+            ir::Expr::Constant(ir::Constant::Integer(value)) => {
+                convert(&self.memory, Value::Int(*value), &expr.ty, &self.settings).at(span)
+            }
+            ir::Expr::Constant(ir::Constant::Float(value)) => {
+                convert(&self.memory, Value::Float(*value), &expr.ty, &self.settings).at(span)
+            }
+            ir::Expr::FunctionCall(name, args) => {
+                match self.function_call(frame, name, args, span)? {
+                    Value::Void if expr.ty != CType::Void => {
+                        Err(ErrorKind::MissingReturnValue).at(span)
+                    }
+                    value => Ok(value),
+                }
+            }
+            ir::Expr::PostfixInc(lvalue) => self.increment(frame, lvalue, 1, false),
+            ir::Expr::PostfixDec(lvalue) => self.increment(frame, lvalue, -1, false),
+            ir::Expr::PrefixInc(lvalue) => self.increment(frame, lvalue, 1, true),
+            ir::Expr::PrefixDec(lvalue) => self.increment(frame, lvalue, -1, true),
+            ir::Expr::Reference(lvalue) => Ok(Value::Pointer(self.place(frame, lvalue)?)),
+            ir::Expr::UnaryArith(op, inner) => {
+                let value = self.expr(frame, inner)?;
+                match (op, value) {
+                    (ir::UnaryOp::Not, value) => Ok(Value::Int(!is_true(value) as i128)),
+                    (ir::UnaryOp::Neg, Value::Int(value)) => {
+                        self.int_result(-value, &expr.ty).at(span)
+                    }
+                    (ir::UnaryOp::Neg, Value::Float(value)) => {
+                        convert(&self.memory, Value::Float(-value), &expr.ty, &self.settings)
+                            .at(span)
+                    }
+                    (ir::UnaryOp::BitNot, Value::Int(value)) => {
+                        convert(&self.memory, Value::Int(!value), &expr.ty, &self.settings).at(span)
+                    }
+                    (op, value) => panic!("ICE: invalid operand {value:?} for {op:?}"),
+                }
+            }
+            ir::Expr::Binary(left, op, right) => {
+                let left_value = self.expr(frame, left)?;
+                let right_value = self.expr(frame, right)?;
+                self.binary(expr, left, left_value, op, right, right_value)
+                    .at(span)
+            }
+            ir::Expr::Relation(left, op, right) => {
+                let left = self.expr(frame, left)?;
+                let right = self.expr(frame, right)?;
+                use std::cmp::Ordering;
+                let ordering = match (left, right) {
+                    (Value::Int(l), Value::Int(r)) => Some(l.cmp(&r)),
+                    (Value::Float(l), Value::Float(r)) => l.partial_cmp(&r),
+                    (Value::Pointer(l), Value::Pointer(r)) => {
+                        let l = self.memory.address(l).at(span)?;
+                        let r = self.memory.address(r).at(span)?;
+                        Some(l.cmp(&r))
+                    }
+                    (l, r) => panic!("ICE: can't compare {l:?} and {r:?}"),
+                };
+                // Every comparison with NaN is false, except `!=`.
+                let result = match (op, ordering) {
+                    (ir::RelationOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+                    (_, None) => false,
+                    (ir::RelationOp::Eq, Some(ordering)) => ordering.is_eq(),
+                    (ir::RelationOp::Lt, Some(ordering)) => ordering.is_lt(),
+                    (ir::RelationOp::Gt, Some(ordering)) => ordering.is_gt(),
+                    (ir::RelationOp::Ge, Some(ordering)) => ordering.is_ge(),
+                    (ir::RelationOp::Le, Some(ordering)) => ordering.is_le(),
+                };
+                Ok(Value::Int(result as i128))
+            }
+            ir::Expr::LogicalAnd(left, right) => {
+                let result = is_true(self.expr(frame, left)?) && is_true(self.expr(frame, right)?);
+                Ok(Value::Int(result as i128))
+            }
+            ir::Expr::LogicalOr(left, right) => {
+                let result = is_true(self.expr(frame, left)?) || is_true(self.expr(frame, right)?);
+                Ok(Value::Int(result as i128))
+            }
+            ir::Expr::Assign(lvalue, rhs) => {
+                // Like the backends, the left-hand side is evaluated first.
+                let pointer = self.place(frame, lvalue)?;
+                let value = self.expr(frame, rhs)?;
+                self.store(pointer, &lvalue.ty, value).at(span)?;
+                Ok(value)
+            }
+            ir::Expr::Cast(inner) => {
+                let value = self.expr(frame, inner)?;
+                convert(&self.memory, value, &expr.ty, &self.settings).at(span)
+            }
+        }
+    }
+
+    /// Returns a pointer to the object the lvalue designates.
+    fn place(
+        &mut self,
+        frame: &Frame<'a>,
+        lvalue: &'a ir::LvalueExprNode,
+    ) -> Result<Pointer, RuntimeError> {
+        match &lvalue.expr {
+            ir::LvalueExpr::Ident(id) => Ok(frame.locals[id]),
+            ir::LvalueExpr::GlobalIdent(name) => Ok(Pointer::new(self.globals[name.as_str()])),
+            ir::LvalueExpr::Dereference(inner) => match self.expr(frame, inner)? {
+                Value::Pointer(pointer) => Ok(pointer),
+                value => panic!("ICE: can't dereference {value:?}"),
+            },
+        }
+    }
+
+    /// Loads a value of type `ty`. Arrays are converted to a pointer to their first element.
+    fn load(&self, pointer: Pointer, ty: &CType) -> Result<Value, ErrorKind> {
+        match Class::of(ty, &self.settings) {
+            Some(class) => self.memory.read(pointer, class),
+            None if is_array(ty) => Ok(Value::Pointer(pointer)),
+            None => panic!("ICE: can't load a value of type {ty}"),
+        }
+    }
+
+    fn store(&mut self, pointer: Pointer, ty: &CType, value: Value) -> Result<(), ErrorKind> {
+        let Some(class) = Class::of(ty, &self.settings) else {
+            panic!("ICE: can't store a value of type {ty}")
+        };
+        self.memory.write(pointer, class, value)
+    }
+
+    fn increment(
+        &mut self,
+        frame: &Frame<'a>,
+        lvalue: &'a ir::LvalueExprNode,
+        amount: i128,
+        prefix: bool,
+    ) -> Result<Value, RuntimeError> {
+        let span = lvalue.span;
+        let pointer = self.place(frame, lvalue)?;
+        let old = self.load(pointer, &lvalue.ty).at(span)?;
+        let new = match old {
+            Value::Int(value) => self.int_result(value + amount, &lvalue.ty).at(span)?,
+            Value::Float(value) => convert(
+                &self.memory,
+                Value::Float(value + amount as f64),
+                &lvalue.ty,
+                &self.settings,
+            )
+            .at(span)?,
+            Value::Pointer(p) => {
+                let size = pointee_size(&lvalue.ty, &self.settings) as i64;
+                Value::Pointer(p.offset_by(amount as i64 * size))
+            }
+            Value::Void => panic!("ICE: can't increment void"),
+        };
+        self.store(pointer, &lvalue.ty, new).at(span)?;
+        Ok(if prefix { new } else { old })
+    }
+
+    fn binary(
+        &self,
+        expr: &ir::ExprNode,
+        left: &ir::ExprNode,
+        left_value: Value,
+        op: &ir::BinaryOp,
+        right: &ir::ExprNode,
+        right_value: Value,
+    ) -> Result<Value, ErrorKind> {
+        use ir::BinaryOp as B;
+        match (left_value, right_value) {
+            (Value::Int(l), Value::Int(r)) => {
+                let (size, signed) = match Class::of(&expr.ty, &self.settings) {
+                    Some(Class::Int { size, signed }) => (size, signed),
+                    _ => panic!("ICE: integer operation with type {}", expr.ty),
+                };
+                let result = match op {
+                    B::Mul => l * r,
+                    B::Div | B::Rem if r == 0 => return Err(ErrorKind::DivisionByZero),
+                    // The remainder of e.g. `INT_MIN % -1` is undefined as well, since the quotient
+                    // overflows.
+                    B::Rem if !fits(l / r, size, signed) => return Err(ErrorKind::SignedOverflow),
+                    B::Div => l / r,
+                    B::Rem => l % r,
+                    B::Add => l + r,
+                    B::Sub => l - r,
+                    B::ShiftLeft | B::ShiftRight if r < 0 || r >= 8 * size as i128 => {
+                        return Err(ErrorKind::InvalidShift(r))
+                    }
+                    B::ShiftLeft => l << r,
+                    B::ShiftRight => l >> r,
+                    B::Bitwise(ir::BitwiseOp::And) => l & r,
+                    B::Bitwise(ir::BitwiseOp::Or) => l | r,
+                    B::Bitwise(ir::BitwiseOp::Xor) => l ^ r,
+                };
+                self.int_result(result, &expr.ty)
+            }
+            (Value::Float(l), Value::Float(r)) => {
+                let result = match op {
+                    B::Mul => l * r,
+                    B::Div => l / r,
+                    B::Add => l + r,
+                    B::Sub => l - r,
+                    op => panic!("ICE: {} of floats", op.long_name()),
+                };
+                convert(&self.memory, Value::Float(result), &expr.ty, &self.settings)
+            }
+            (Value::Pointer(p), Value::Int(n)) => {
+                let size = pointee_size(&left.ty, &self.settings) as i64;
+                match op {
+                    B::Add => Ok(Value::Pointer(p.offset_by(n as i64 * size))),
+                    B::Sub => Ok(Value::Pointer(p.offset_by(-(n as i64) * size))),
+                    op => panic!("ICE: {} of a pointer and an integer", op.long_name()),
+                }
+            }
+            (Value::Int(n), Value::Pointer(p)) => {
+                let size = pointee_size(&right.ty, &self.settings) as i64;
+                match op {
+                    B::Add => Ok(Value::Pointer(p.offset_by(n as i64 * size))),
+                    op => panic!("ICE: {} of an integer and a pointer", op.long_name()),
+                }
+            }
+            (Value::Pointer(l), Value::Pointer(r)) => {
+                let size = pointee_size(&left.ty, &self.settings) as i64;
+                match (op, l.offset_from(r)) {
+                    (B::Sub, Some(difference)) => convert(
+                        &self.memory,
+                        Value::Int((difference / size) as i128),
+                        &expr.ty,
+                        &self.settings,
+                    ),
+                    (B::Sub, None) => Err(ErrorKind::PointerSubtraction),
+                    (op, _) => panic!("ICE: {} of pointers", op.long_name()),
+                }
+            }
+            (l, r) => panic!(
+                "ICE: invalid operands {l:?} and {r:?} for {}",
+                op.long_name()
+            ),
+        }
+    }
+
+    /// Checks that the result of an integer operation fits in its type. Signed overflow is
+    /// undefined, unsigned integers wrap around.
+    fn int_result(&self, value: i128, ty: &CType) -> Result<Value, ErrorKind> {
+        match Class::of(ty, &self.settings) {
+            Some(Class::Int { size, signed: true }) if !fits(value, size, true) => {
+                Err(ErrorKind::SignedOverflow)
+            }
+            Some(Class::Int { size, signed }) => Ok(Value::Int(normalize(value, size, signed))),
+            _ => panic!("ICE: integer operation with type {ty}"),
+        }
+    }
+}
+
+fn is_true(value: Value) -> bool {
+    match value {
+        Value::Int(value) => value != 0,
+        Value::Float(value) => value != 0.0,
+        Value::Pointer(pointer) => !pointer.is_null(),
+        Value::Void => panic!("ICE: void used as a condition"),
+    }
+}
+
+/// Converts a value to the type `ty`, like a cast.
+fn convert(
+    memory: &Memory,
+    value: Value,
+    ty: &CType,
+    settings: &Settings,
+) -> Result<Value, ErrorKind> {
+    let Some(class) = Class::of(ty, settings) else {
+        return match ty {
+            CType::Void => Ok(Value::Void),
+            _ => panic!("ICE: can't convert to {ty}"),
+        };
+    };
+    Ok(match (class, value) {
+        (Class::Int { size, signed }, Value::Int(value)) => {
+            Value::Int(normalize(value, size, signed))
+        }
+        (Class::Int { size, signed }, Value::Float(value)) => {
+            let value = value.trunc();
+            if value.is_nan() || !fits(value as i128, size, signed) {
+                return Err(ErrorKind::FloatToIntOverflow);
+            }
+            Value::Int(value as i128)
+        }
+        (Class::Int { size, signed }, Value::Pointer(pointer)) => {
+            Value::Int(normalize(memory.address(pointer)? as i128, size, signed))
+        }
+        (Class::Float, Value::Int(value)) => Value::Float(value as f32 as f64),
+        (Class::Float, Value::Float(value)) => Value::Float(value as f32 as f64),
+        (Class::Double, Value::Int(value)) => Value::Float(value as f64),
+        (Class::Double, Value::Float(value)) => Value::Float(value),
+        (Class::Pointer, Value::Pointer(pointer)) => Value::Pointer(pointer),
+        (Class::Pointer, Value::Int(value)) => {
+            let address = normalize(value, pointer_size(settings), false) as u64;
+            Value::Pointer(memory.pointer_from_address(address))
+        }
+        (class, value) => panic!("ICE: can't convert {value:?} to {class:?}"),
+    })
+}
+
+fn is_array(ty: &CType) -> bool {
+    matches!(ty, CType::Aggregate(ctype::Aggregate::Array(_)))
+}
@@ -0,0 +1,100 @@
+use super::memory::Pointer;
+use crate::{
+    ir::ctype::{self, CType},
+    settings::{Settings, Target},
+};
+
+/// A scalar value. Integers are kept in the range of their type, floats are rounded to `f32`
+/// precision if their type is `float`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+    Pointer(Pointer),
+    /// The result of a function without a return value.
+    Void,
+}
+
+/// How a scalar type is represented in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Int { size: u64, signed: bool },
+    Float,
+    Double,
+    Pointer,
+}
+
+impl Class {
+    /// Returns `None` for arrays and `void`.
+    pub fn of(ty: &CType, settings: &Settings) -> Option<Class> {
+        match ty {
+            CType::Scalar(ctype::Scalar::Arithmetic(arith)) => Some(arith_class(*arith, settings)),
+            CType::Scalar(ctype::Scalar::Pointer(_)) => Some(Class::Pointer),
+            CType::Aggregate(_) | CType::Void => None,
+        }
+    }
+
+    pub fn size(self, settings: &Settings) -> u64 {
+        match self {
+            Class::Int { size, .. } => size,
+            Class::Float => 4,
+            Class::Double => 8,
+            Class::Pointer => pointer_size(settings),
+        }
+    }
+}
+
+pub fn arith_class(arith: ctype::Arithmetic, settings: &Settings) -> Class {
+    use ctype::Arithmetic as A;
+    match arith {
+        A::Float => Class::Float,
+        A::Double | A::LongDouble => Class::Double,
+        int => Class::Int {
+            size: int.size_in_bits(settings) as u64 / 8,
+            signed: int.is_signed(),
+        },
+    }
+}
+
+pub fn pointer_size(settings: &Settings) -> u64 {
+    match settings.target {
+        Target::X86_64 => 8,
+        Target::Mips | Target::RiscV32 | Target::Wasm32 => 4,
+    }
+}
+
+/// Returns the size in bytes of the type.
+pub fn ctype_size(ty: &CType, settings: &Settings) -> u64 {
+    match ty {
+        CType::Aggregate(ctype::Aggregate::Array(array)) => {
+            ctype_size(&array.inner, settings) * array.length as u64
+        }
+        CType::Void => 0,
+        scalar => Class::of(scalar, settings).unwrap().size(settings),
+    }
+}
+
+/// Returns the size in bytes of the type a pointer points to. Panics if the type isn't a pointer.
+pub fn pointee_size(ty: &CType, settings: &Settings) -> u64 {
+    match ty {
+        CType::Scalar(ctype::Scalar::Pointer(ctype::Pointer { inner, .. })) => {
+            ctype_size(inner, settings)
+        }
+        _ => panic!("ICE: {ty} should be a pointer type"),
+    }
+}
+
+/// Truncates the value to `size` bytes, and sign- or zero-extends it again.
+pub fn normalize(value: i128, size: u64, signed: bool) -> i128 {
+    let bits = 128 - 8 * size as u32;
+    if signed {
+        (value << bits) >> bits
+    } else {
+        ((value << bits) as u128 >> bits) as i128
+    }
+}
+
+/// Returns `true` if the value can be represented by an integer of `size` bytes.
+pub fn fits(value: i128, size: u64, signed: bool) -> bool {
+    normalize(value, size, signed) == value
+}
@@ -6,6 +6,7 @@ pub mod codegen;
 pub mod compile;
 pub mod diagnostic;
 pub mod inspectors;
+pub mod interpreter;
 pub mod passes;
 mod settings;
 
@@ -0,0 +1,258 @@
+//! Finds accesses that are always invalid: array subscripts with a constant index outside of the
+//! array, and dereferences of local pointers that were set to null earlier in straight-line code.
+//!
+//! An array subscript `a[i]` is `*(a + i)` in the IR, so every addition of an array and a constant
+//! is checked. The address one past the end of an array can still be computed, so only accessing
+//! it is reported, not e.g. `&a[4]` for an `int a[4]`.
+//!
+//! A pointer is only known to be null until the next `if`, loop or `switch`, and pointers whose
+//! address is taken are never tracked, since they can be changed through that address.
+
+use std::collections::{HashMap, LinkedList};
+
+use crate::{
+    diagnostic::{Diagnostic, DiagnosticBuilder, Span},
+    ir::{
+        ctype::{self, CType},
+        table::ItemId,
+        BinaryOp, BlockNode, Constant, Expr, ExprNode, FunctionNode, LvalueExpr, LvalueExprNode,
+        Root, Stmt, SwitchStmtCase,
+    },
+};
+
+pub fn find_invalid_accesses(root: &Root, source: &str) -> LinkedList<Diagnostic> {
+    let mut functions: Vec<_> = root.functions.values().collect();
+    functions.sort_by_key(|function| function.original_span.start());
+
+    let mut diagnostics = LinkedList::new();
+    for function in functions {
+        let Some(body) = &function.body else {
+            continue;
+        };
+        let mut checker = Checker {
+            root,
+            function,
+            source,
+            nulls: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        checker.block(body);
+        checker
+            .diagnostics
+            .sort_by_key(|diagnostic| diagnostic.main_span().start());
+        diagnostics.extend(checker.diagnostics);
+    }
+    diagnostics
+}
+
+struct Checker<'a> {
+    root: &'a Root,
+    function: &'a FunctionNode,
+    source: &'a str,
+    /// The local pointers that are null at the current point, with the span of the assignment that
+    /// set them to null.
+    nulls: HashMap<ItemId, Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn block(&mut self, block: &BlockNode) {
+        for stmt in &block.stmts {
+            match &stmt.stmt {
+                Stmt::Expr(expr) => self.expr(expr),
+                Stmt::IfStmt(if_stmt) => {
+                    self.expr(&if_stmt.condition);
+                    let nulls = self.nulls.clone();
+                    self.block(&if_stmt.if_branch);
+                    if let Some(else_branch) = &if_stmt.else_branch {
+                        self.nulls = nulls;
+                        self.block(else_branch);
+                    }
+                    self.nulls.clear();
+                }
+                Stmt::SwitchStmt(switch) => {
+                    self.expr(&switch.expr);
+                    for case in &switch.cases {
+                        // A case can also be reached by falling through the previous one
+                        self.nulls.clear();
+                        match &case.data {
+                            SwitchStmtCase::Case { body, .. }
+                            | SwitchStmtCase::Default { body } => self.block(body),
+                        }
+                    }
+                    self.nulls.clear();
+                }
+                Stmt::LoopStmt(loop_stmt) => {
+                    // The body can also be reached from the end of the previous iteration
+                    self.nulls.clear();
+                    if let Some(condition) = &loop_stmt.condition {
+                        self.expr(condition);
+                    }
+                    self.block(&loop_stmt.body);
+                    if let Some(continuation) = &loop_stmt.continuation {
+                        self.expr(continuation);
+                    }
+                    self.nulls.clear();
+                }
+                Stmt::Return(Some(value)) => self.expr(value),
+                Stmt::Break | Stmt::Continue | Stmt::Return(None) => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ExprNode) {
+        match &expr.expr {
+            Expr::LvalueDeref(lvalue) => self.lvalue(lvalue, true),
+            Expr::Constant(_) => {}
+            Expr::FunctionCall(_, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            Expr::PostfixInc(lvalue)
+            | Expr::PostfixDec(lvalue)
+            | Expr::PrefixInc(lvalue)
+            | Expr::PrefixDec(lvalue) => {
+                self.lvalue(lvalue, true);
+                self.write(lvalue, None);
+            }
+            Expr::Reference(lvalue) => self.lvalue(lvalue, false),
+            Expr::UnaryArith(_, inner) | Expr::Cast(inner) => self.expr(inner),
+            Expr::Binary(left, op, right) => {
+                self.expr(left);
+                self.expr(right);
+                if *op == BinaryOp::Add {
+                    self.index(left, right, false);
+                }
+            }
+            Expr::Relation(left, _, right)
+            | Expr::LogicalAnd(left, right)
+            | Expr::LogicalOr(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Assign(to, value) => {
+                self.expr(value);
+                self.lvalue(to, true);
+                let null = is_null(value).then_some(expr.span);
+                self.write(to, null);
+            }
+        }
+    }
+
+    /// Checks an lvalue that is accessed, or that only has its address taken if `accessed` is
+    /// false.
+    fn lvalue(&mut self, lvalue: &LvalueExprNode, accessed: bool) {
+        let LvalueExpr::Dereference(pointer) = &lvalue.expr else {
+            return;
+        };
+        self.expr(pointer);
+        if !accessed {
+            return;
+        }
+        if let Expr::Binary(left, BinaryOp::Add, right) = &pointer.expr {
+            self.index(left, right, true);
+        }
+        if let Some((id, null_assign)) = self.null_pointer(pointer) {
+            let name = self.name(self.function.table.get(id).ident_span);
+            let diagnostic =
+                DiagnosticBuilder::new(lvalue.span).build_null_deref(name, null_assign);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Checks the addition of an array and a constant index. The index one past the end is only
+    /// reported if the element is `accessed`, every other index outside of the array is reported
+    /// when the address is computed.
+    fn index(&mut self, left: &ExprNode, right: &ExprNode, accessed: bool) {
+        let ((array, length), index) = match (decayed_array(left), decayed_array(right)) {
+            (Some(array), None) => (array, right),
+            (None, Some(array)) => (array, left),
+            _ => return,
+        };
+        let Some(value) = constant_int(index) else {
+            return;
+        };
+        let is_invalid = match accessed {
+            true => value == length as i128,
+            false => value < 0 || value > length as i128,
+        };
+        if !is_invalid {
+            return;
+        }
+
+        let declaration = match &array.expr {
+            LvalueExpr::Ident(id) => Some(self.function.table.get(*id).ident_span),
+            LvalueExpr::GlobalIdent(name) => self.root.vars.get(name).map(|var| var.ident_span),
+            LvalueExpr::Dereference(_) => None,
+        };
+        let declaration = declaration.map(|span| (self.name(span), span));
+        let diagnostic = DiagnosticBuilder::new(index.span).build_array_out_of_bounds(
+            value,
+            length,
+            declaration,
+            array.span,
+        );
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Updates the null pointers for a write to `to`, `null` is the span of the assignment if the
+    /// new value is null.
+    fn write(&mut self, to: &LvalueExprNode, null: Option<Span>) {
+        let LvalueExpr::Ident(id) = to.expr else {
+            return;
+        };
+        let item = self.function.table.get(id);
+        match null {
+            Some(span) if !item.needs_address => {
+                self.nulls.insert(id, span);
+            }
+            _ => {
+                self.nulls.remove(&id);
+            }
+        }
+    }
+
+    /// The local pointer that is null in the address of a dereference, e.g. `p` in `*p` or `p[2]`.
+    fn null_pointer(&self, pointer: &ExprNode) -> Option<(ItemId, Span)> {
+        match &pointer.expr {
+            Expr::Cast(inner) => self.null_pointer(inner),
+            Expr::Binary(left, BinaryOp::Add | BinaryOp::Sub, right) => {
+                self.null_pointer(left).or_else(|| self.null_pointer(right))
+            }
+            Expr::LvalueDeref(lvalue) => match lvalue.expr {
+                LvalueExpr::Ident(id) => self.nulls.get(&id).map(|span| (id, *span)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn name(&self, ident_span: Span) -> &str {
+        &self.source[std::ops::Range::<usize>::from(ident_span)]
+    }
+}
+
+/// The array and its length if the expression is an array that decays to a pointer.
+fn decayed_array(expr: &ExprNode) -> Option<(&LvalueExprNode, u128)> {
+    match &expr.expr {
+        Expr::LvalueDeref(lvalue) => match &lvalue.ty {
+            CType::Aggregate(ctype::Aggregate::Array(array)) => Some((lvalue, array.length)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn constant_int(expr: &ExprNode) -> Option<i128> {
+    match &expr.expr {
+        Expr::Cast(inner) => constant_int(inner),
+        Expr::Constant(Constant::Integer(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Whether the value is a null pointer constant, which is a `0` cast to the pointer type.
+fn is_null(value: &ExprNode) -> bool {
+    matches!(value.ty, CType::Scalar(ctype::Scalar::Pointer(_))) && constant_int(value) == Some(0)
+}
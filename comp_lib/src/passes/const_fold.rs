@@ -1,33 +1,91 @@
-use crate::ast::{
-    ArrayDeclaration, Ast, BinaryOperator, BinaryOperatorNode, BlockStatementNode, Declaration,
-    Expression, ExpressionNode, ExternalDeclaration, FunctionDefinition, Literal, LiteralNode,
-    Statement, SwitchStatement, UnaryOperator, UnaryOperatorNode, VariableDeclaration,
+//! Folds constant expressions in the AST. Every folded value keeps the arithmetic type it has in
+//! C, so the result wraps around like it would at runtime on the target, and signed overflow is
+//! reported. Divisions by zero and shifts by a count that is out of range aren't folded, they are
+//! reported when building the IR, where the type of the other operand is also known when it isn't
+//! constant.
+
+use std::collections::LinkedList;
+
+use crate::{
+    ast::{
+        ArrayDeclaration, Ast, BinaryOperator, BinaryOperatorNode, BlockStatementNode, Declaration,
+        Expression, ExpressionNode, ExternalDeclaration, FunctionDefinition, Literal, LiteralNode,
+        QualifiedTypeNode, Statement, SwitchStatement, UnaryOperator, UnaryOperatorNode,
+        UnqualifiedType, UnqualifiedTypeNode, VariableDeclaration,
+    },
+    diagnostic::{Diagnostic, DiagnosticBuilder, Span},
+    ir::ctype::{Arithmetic, CType, Scalar},
+    settings::Settings,
 };
 
-pub fn const_fold(ast: &mut Ast) {
-    Folder::new().fold(ast)
+pub fn const_fold(ast: &mut Ast, settings: &Settings) -> LinkedList<Diagnostic> {
+    let mut folder = Folder::new(settings, true);
+    folder.fold(ast);
+    folder.diagnostics
+}
+
+/// Reports the same diagnostics as [`const_fold`], without replacing anything. The AST is only
+/// borrowed mutably to share the traversal with folding, it's left unchanged.
+pub fn check_const_exprs(ast: &mut Ast, settings: &Settings) -> LinkedList<Diagnostic> {
+    let mut folder = Folder::new(settings, false);
+    folder.fold(ast);
+    folder.diagnostics
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    number: Number,
+    ty: Arithmetic,
+    /// The value depends on a variable, of which the value was known from its declaration.
+    propagated: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
-enum Value {
+enum Number {
     Int(i128),
     Float(f64),
 }
 
-struct Folder {}
+impl Value {
+    fn new(number: Number, ty: Arithmetic) -> Self {
+        Value {
+            number,
+            ty,
+            propagated: false,
+        }
+    }
 
-impl Folder {
-    fn new() -> Self {
-        Folder {}
+    fn is_true(&self) -> bool {
+        match self.number {
+            Number::Int(i) => i != 0,
+            Number::Float(f) => f != 0.0,
+        }
     }
+}
+
+struct Folder<'s> {
+    settings: &'s Settings,
+    diagnostics: LinkedList<Diagnostic>,
+    /// Whether folded expressions are replaced, or only checked.
+    replace: bool,
+}
 
-    fn fold(self, ast: &mut Ast) {
+impl<'s> Folder<'s> {
+    fn new(settings: &'s Settings, replace: bool) -> Self {
+        Folder {
+            settings,
+            diagnostics: LinkedList::new(),
+            replace,
+        }
+    }
+
+    fn fold(&mut self, ast: &mut Ast) {
         for external_decl in &mut ast.global_declarations {
             self.fold_external_declaration(&mut external_decl.data);
         }
     }
 
-    fn fold_external_declaration(&self, exdecl: &mut ExternalDeclaration) {
+    fn fold_external_declaration(&mut self, exdecl: &mut ExternalDeclaration) {
         match exdecl {
             ExternalDeclaration::FunctionDefinition(FunctionDefinition { body, .. }) => {
                 self.fold_block_statement(body);
@@ -39,20 +97,19 @@ impl Folder {
     }
 
     fn fold_declaration<'a>(
-        &self,
+        &mut self,
         declaration: &'a mut Declaration,
         last_assign: &Option<(&'a str, Value)>,
     ) -> Option<(&'a str, Value)> {
         match declaration {
             Declaration::Variable(VariableDeclaration {
+                type_name,
                 ident,
                 initializer,
                 array_parts,
-                ..
             }) => {
                 let res = initializer.as_mut().and_then(|initializer| {
-                    self.fold_expr_node(&mut initializer.1, last_assign)
-                        .map(|v| (ident.data.as_str(), v))
+                    self.fold_converted_expr_node(&mut initializer.1, last_assign)
                 });
                 if !array_parts.is_empty() {
                     for array_part in array_parts {
@@ -62,13 +119,19 @@ impl Folder {
                     }
                     return None;
                 }
-                res
+                // The variable has the value after the implicit conversion to its type
+                match CType::from_ast_type(&type_name.unqualified.data) {
+                    CType::Scalar(Scalar::Arithmetic(ty)) => res
+                        .and_then(|value| self.convert(value, ty))
+                        .map(|value| (ident.data.as_str(), value)),
+                    _ => None,
+                }
             }
             Declaration::FunctionDeclaration(_) => None,
         }
     }
 
-    fn fold_block_statement(&self, bs: &mut BlockStatementNode) {
+    fn fold_block_statement(&mut self, bs: &mut BlockStatementNode) {
         let mut last_assign = None;
         for statement in &mut bs.stmts {
             last_assign = self.fold_statement(&mut statement.data, last_assign);
@@ -76,7 +139,7 @@ impl Folder {
     }
 
     fn fold_statement<'a>(
-        &self,
+        &mut self,
         statement: &'a mut Statement,
         last_assign: Option<(&'a str, Value)>,
     ) -> Option<(&'a str, Value)> {
@@ -115,7 +178,7 @@ impl Folder {
             Statement::Break => {}
             Statement::Continue => {}
             Statement::Return(_, Some(expr_node)) => {
-                self.fold_expr_node(expr_node, &last_assign);
+                self.fold_converted_expr_node(expr_node, &last_assign);
             }
             Statement::Return(_, None) => {}
             Statement::BlockStatement(bs) => {
@@ -125,11 +188,11 @@ impl Folder {
         None
     }
 
-    fn fold_switch(&self, switch: &mut SwitchStatement) {
+    fn fold_switch(&mut self, switch: &mut SwitchStatement) {
         for case in &mut switch.cases {
             let body = match case {
                 crate::ast::SwitchCase::Expr(case) => {
-                    self.fold_expr(&mut case.expr.data, &None);
+                    self.fold_expr(&mut case.expr, &None);
                     &mut case.body
                 }
                 crate::ast::SwitchCase::Default(case) => &mut case.body,
@@ -140,7 +203,7 @@ impl Folder {
     }
 
     fn fold_expr_node(
-        &self,
+        &mut self,
         expr_node: &mut ExpressionNode,
         last_assign: &Option<(&str, Value)>,
     ) -> Option<Value> {
@@ -149,151 +212,252 @@ impl Folder {
             return self.fold_literal(&lit.data);
         }
 
-        let folded = self.fold_expr(&mut expr_node.data, last_assign)?;
-        replace_with_literal(expr_node, folded);
+        let folded = self.fold_expr(expr_node, last_assign)?;
+        self.replace_with_literal(expr_node, folded);
         Some(folded)
     }
 
+    /// Like [`Self::fold_expr_node`], for an expression that is implicitly converted. A value that
+    /// depends on a variable isn't replaced, so the conversion is checked the same way as without
+    /// folding, e.g. `char c = i;` stays lossy even if `i` is known to be small.
+    fn fold_converted_expr_node(
+        &mut self,
+        expr_node: &mut ExpressionNode,
+        last_assign: &Option<(&str, Value)>,
+    ) -> Option<Value> {
+        if let Expression::Literal(ref lit) = expr_node.data {
+            return self.fold_literal(&lit.data);
+        }
+
+        let folded = self.fold_expr(expr_node, last_assign)?;
+        if !folded.propagated {
+            self.replace_with_literal(expr_node, folded);
+        }
+        Some(folded)
+    }
+
+    /// Folds the expression without replacing it, only the parts of it that can't be folded
+    /// further are replaced.
     fn fold_expr(
-        &self,
-        expr: &mut Expression,
+        &mut self,
+        expr_node: &mut ExpressionNode,
         last_assign: &Option<(&str, Value)>,
     ) -> Option<Value> {
-        match expr {
+        let span = expr_node.span;
+        match &mut expr_node.data {
             Expression::Assignment(_, _, rhs) => {
-                let folded = self.fold_expr(&mut rhs.data, last_assign)?;
-                replace_with_literal(rhs, folded);
+                self.fold_converted_expr_node(rhs, last_assign);
                 None // Assignment expression itself is not const-folded
             }
-            Expression::Binary(lhs, op, rhs) => self.fold_binary_op(op, lhs, rhs, last_assign),
+            Expression::Binary(lhs, op, rhs) => {
+                self.fold_binary_op(span, op, lhs, rhs, last_assign)
+            }
             Expression::ArraySubscript(lhs, rhs) => {
-                if let Some(folded) = self.fold_expr(&mut lhs.data, last_assign) {
-                    replace_with_literal(lhs, folded);
+                if let Some(folded) = self.fold_expr(lhs, last_assign) {
+                    self.replace_with_literal(lhs, folded);
                 }
-                if let Some(folded) = self.fold_expr(&mut rhs.data, last_assign) {
-                    replace_with_literal(rhs, folded);
+                if let Some(folded) = self.fold_expr(rhs, last_assign) {
+                    self.replace_with_literal(rhs, folded);
                 }
                 None // ArraySubscript expression itself is not const-folded
             }
-            Expression::Unary(op, expr) => self.fold_unary_op(op, expr, last_assign),
+            Expression::Unary(op, expr) => self.fold_unary_op(span, op, expr, last_assign),
             Expression::Cast(_, expr_node) => {
-                let inner_folded = self.fold_expr(&mut expr_node.data, last_assign)?;
-                replace_with_literal(expr_node, inner_folded);
+                let inner_folded = self.fold_expr(expr_node, last_assign)?;
+                self.replace_with_literal(expr_node, inner_folded);
                 None // Cast expression itself is not const-folded
             }
             Expression::FunctionCall(fc) => {
                 for arg in &mut fc.args {
-                    if let Some(folded) = self.fold_expr(&mut arg.data, last_assign) {
-                        replace_with_literal(arg, folded);
-                    }
+                    self.fold_converted_expr_node(arg, last_assign);
                 }
                 None // Function call expression itself is not const-folded
             }
             // This case should be unreachable, since it is handled in fold_expr_node already.
             Expression::Literal(lit) => self.fold_literal(&lit.data),
-            Expression::Ident(ident) => last_assign
-                .as_ref()
-                .and_then(|(name, value)| (*name == ident.data).then_some(*value)),
+            Expression::Ident(ident) => last_assign.as_ref().and_then(|(name, value)| {
+                (*name == ident.data).then_some(Value {
+                    propagated: true,
+                    ..*value
+                })
+            }),
         }
     }
 
     fn fold_binary_op(
-        &self,
+        &mut self,
+        span: Span,
         op_node: &mut BinaryOperatorNode,
         lhs_node: &mut ExpressionNode,
         rhs_node: &mut ExpressionNode,
         last_assign: &Option<(&str, Value)>,
     ) -> Option<Value> {
-        let folded1 = self.fold_expr(&mut lhs_node.data, last_assign)?;
-        let folded2 = self.fold_expr(&mut rhs_node.data, last_assign)?;
-
-        macro_rules! do_op_custom {
-            (|$a:ident, $b:ident| $op_i:expr $(; $op_f:expr)?) => {{
-                use Value::*;
-                #[allow(unreachable_patterns)]
-                match (&folded1, &folded2) {
-                    (&Int($a), &Int($b)) => $op_i,
-                $(
-                    (&Int($a), &Float($b)) => { let $a = $a as f64; $op_f }
-                    (&Float($a), &Int($b)) => { let $b = $b as f64; $op_f }
-                    (&Float($a), &Float($b)) => $op_f,
-                )?
-                    _ => None
-                }
-            }};
-        }
-
-        macro_rules! do_op {
-            (|$a:ident, $b:ident| $op:expr) => {
-                do_op_custom!(|$a, $b| Some(Int(($op) as i128)); Some(Float($op)))
-            };
-            (int; |$a:ident, $b:ident| $op:expr) => {
-                do_op_custom!(|$a, $b| Some(Int(($op) as i128)))
-            };
-            (bool; |$a:ident, $b:ident| $op_i:expr $(; $op_f:expr)?) => {
-                do_op_custom!(
-                    |$a, $b| Some(Int(($op_i) as i128))
-                    $(; Some(Int(($op_f) as i128)))?
-                )
-            };
-        }
+        // Both sides are folded, even if one of them isn't constant
+        let folded1 = self.fold_expr(lhs_node, last_assign);
+        let folded2 = self.fold_expr(rhs_node, last_assign);
 
-        let folded = match op_node.data {
-            BinaryOperator::Plus => do_op!(|a, b| a + b),
-            BinaryOperator::Minus => do_op!(|a, b| a - b),
-            BinaryOperator::Star => do_op!(|a, b| a * b),
-            BinaryOperator::Slash => do_op!(|a, b| a / b),
-            BinaryOperator::Pipe => do_op!(int; |a, b| a | b),
-            BinaryOperator::Caret => do_op!(int; |a, b| a ^ b),
-            BinaryOperator::Ampersand => do_op!(int; |a, b| a & b),
-            BinaryOperator::AngleLeft => do_op!(bool; |a, b| a < b),
-            BinaryOperator::AngleRight => do_op!(bool; |a, b| a > b),
-            BinaryOperator::DoubleEquals => do_op!(bool; |a, b| a == b),
-            BinaryOperator::DoubleAmpersand => {
-                do_op!(bool; |a, b| (a != 0 && b != 0); (a != 0.0 && b != 0.0))
-            }
-            BinaryOperator::DoublePipe => {
-                do_op!(bool; |a, b| (a != 0 || b != 0); (a != 0.0 || b != 0.0))
+        let folded = match (folded1, folded2) {
+            (Some(left), Some(right)) => {
+                self.binary_op(&op_node.data, left, right, span)
+                    .map(|value| Value {
+                        propagated: left.propagated || right.propagated,
+                        ..value
+                    })
             }
-            BinaryOperator::BangEquals => do_op!(bool; |a, b| a != b),
-            BinaryOperator::Percent => do_op!(int; |a, b| a % b),
-            BinaryOperator::AngleLeftEquals => do_op!(bool; |a, b| a <= b),
-            BinaryOperator::AngleRightEquals => do_op!(bool; |a, b| a >= b),
-            BinaryOperator::DoubleAngleLeft => do_op!(int; |a, b| a << b),
-            BinaryOperator::DoubleAngleRight => None,
+            _ => None,
         };
 
         folded.or_else(|| {
-            replace_with_literal(lhs_node, folded1);
-            replace_with_literal(rhs_node, folded2);
+            if let Some(folded1) = folded1 {
+                self.replace_with_literal(lhs_node, folded1);
+            }
+            if let Some(folded2) = folded2 {
+                self.replace_with_literal(rhs_node, folded2);
+            }
             None
         })
     }
 
+    /// `span` is the span of the whole expression.
+    fn binary_op(
+        &mut self,
+        op: &BinaryOperator,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Option<Value> {
+        use {BinaryOperator as B, Number::*};
+
+        match op {
+            B::DoubleAmpersand => return Some(self.truth(left.is_true() && right.is_true())),
+            B::DoublePipe => return Some(self.truth(left.is_true() || right.is_true())),
+            B::DoubleAngleLeft | B::DoubleAngleRight => return self.shift(op, left, right, span),
+            _ => {}
+        }
+
+        let ty = Arithmetic::usual_arithmetic_conversions(&left.ty, &right.ty, self.settings);
+        let left = self.convert(left, ty)?;
+        let right = self.convert(right, ty)?;
+        match (left.number, right.number) {
+            (Int(a), Int(b)) => {
+                // Operands have at most 64 bits, so only the product of two large unsigned
+                // values doesn't fit and it wraps around to the same low bits
+                let result = match op {
+                    B::Plus => a + b,
+                    B::Minus => a - b,
+                    B::Star => a.wrapping_mul(b),
+                    B::Slash | B::Percent if b == 0 => return None,
+                    B::Slash => a / b,
+                    B::Percent => a % b,
+                    B::Pipe => a | b,
+                    B::Caret => a ^ b,
+                    B::Ampersand => a & b,
+                    B::AngleLeft => return Some(self.truth(a < b)),
+                    B::AngleRight => return Some(self.truth(a > b)),
+                    B::DoubleEquals => return Some(self.truth(a == b)),
+                    B::BangEquals => return Some(self.truth(a != b)),
+                    B::AngleLeftEquals => return Some(self.truth(a <= b)),
+                    B::AngleRightEquals => return Some(self.truth(a >= b)),
+                    B::DoubleAmpersand
+                    | B::DoublePipe
+                    | B::DoubleAngleLeft
+                    | B::DoubleAngleRight => {
+                        unreachable!("ICE: handled before the conversions")
+                    }
+                };
+                Some(self.wrap(result, ty, span))
+            }
+            (Float(a), Float(b)) => {
+                let result = match op {
+                    B::Plus => a + b,
+                    B::Minus => a - b,
+                    B::Star => a * b,
+                    B::Slash if b == 0.0 => return None,
+                    B::Slash => a / b,
+                    B::AngleLeft => return Some(self.truth(a < b)),
+                    B::AngleRight => return Some(self.truth(a > b)),
+                    B::DoubleEquals => return Some(self.truth(a == b)),
+                    B::BangEquals => return Some(self.truth(a != b)),
+                    B::AngleLeftEquals => return Some(self.truth(a <= b)),
+                    B::AngleRightEquals => return Some(self.truth(a >= b)),
+                    // Not valid for floating types, this is reported when building the IR
+                    _ => return None,
+                };
+                Some(Value::new(Float(self.round(result, ty)), ty))
+            }
+            _ => unreachable!("ICE: both operands have the same type after the conversions"),
+        }
+    }
+
+    /// The type of a shift is the promoted type of the left operand (3.3.7).
+    fn shift(
+        &mut self,
+        op: &BinaryOperator,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Option<Value> {
+        let ty = left.ty.promote(self.settings);
+        let (Number::Int(value), Number::Int(count)) =
+            (self.convert(left, ty)?.number, right.number)
+        else {
+            // Not valid for floating types, this is reported when building the IR
+            return None;
+        };
+
+        if count < 0 || count >= ty.size_in_bits(self.settings) as i128 {
+            return None;
+        }
+
+        match op {
+            BinaryOperator::DoubleAngleLeft => {
+                let result = value << count;
+                // Shifting a one into the sign bit, like `1 << 31`, is too common to report
+                let range = ty.int_range(self.settings);
+                if ty.is_signed() && (result < *range.start() || result > 2 * *range.end() + 1) {
+                    let wrapped = ty.wrap_int(result, self.settings);
+                    let diagnostic =
+                        DiagnosticBuilder::new(span).build_integer_overflow(ty, wrapped);
+                    self.diagnostics.push_back(diagnostic);
+                }
+                Some(Value::new(
+                    Number::Int(ty.wrap_int(result, self.settings)),
+                    ty,
+                ))
+            }
+            // The value has its sign, so this is an arithmetic shift for signed types
+            _ => Some(Value::new(Number::Int(value >> count), ty)),
+        }
+    }
+
     fn fold_unary_op(
-        &self,
+        &mut self,
+        span: Span,
         op_node: &mut UnaryOperatorNode,
         expr_node: &mut ExpressionNode,
         last_assign: &Option<(&str, Value)>,
     ) -> Option<Value> {
-        let inner_folded = self.fold_expr(&mut expr_node.data, last_assign)?;
+        let inner_folded = self.fold_expr(expr_node, last_assign)?;
 
-        use Value::*;
+        use Number::*;
 
+        let promoted = inner_folded.ty.promote(self.settings);
         let folded = match op_node.data {
-            UnaryOperator::Bang => Some(match inner_folded {
-                Int(i) => Int((i == 0) as i128),
-                Float(f) => Int((f == 0.0) as i128),
-            }),
-            UnaryOperator::Plus => Some(inner_folded),
-            UnaryOperator::Minus => Some(match inner_folded {
-                Int(i) => Int(-i),
-                Float(f) => Float(-f),
-            }),
+            UnaryOperator::Bang => Some(self.truth(!inner_folded.is_true())),
+            UnaryOperator::Plus => self.convert(inner_folded, promoted),
+            UnaryOperator::Minus => {
+                self.convert(inner_folded, promoted)
+                    .map(|value| match value.number {
+                        Int(i) => self.wrap(-i, promoted, span),
+                        Float(f) => Value::new(Float(-f), promoted),
+                    })
+            }
             UnaryOperator::Star => None,
-            UnaryOperator::Tilde => match inner_folded {
-                Int(i) => Some(Int(!i)),
-                Float(_) => None,
+            UnaryOperator::Tilde => match self.convert(inner_folded, promoted) {
+                Some(Value {
+                    number: Int(i), ty, ..
+                }) => Some(Value::new(Int(ty.wrap_int(!i, self.settings)), ty)),
+                _ => None,
             },
             UnaryOperator::DoublePlusPrefix
             | UnaryOperator::DoubleMinusPrefix
@@ -305,29 +469,133 @@ impl Folder {
             }
         };
 
-        folded.or_else(|| {
-            replace_with_literal(expr_node, inner_folded);
-            None
-        })
+        match folded {
+            Some(value) => Some(Value {
+                propagated: inner_folded.propagated,
+                ..value
+            }),
+            None => {
+                self.replace_with_literal(expr_node, inner_folded);
+                None
+            }
+        }
     }
 
     fn fold_literal(&self, literal: &Literal) -> Option<Value> {
-        match literal {
-            Literal::Dec(i) | Literal::Hex(i) | Literal::Octal(i) => Some(Value::Int(*i)),
-            Literal::Char(i) => Some(Value::Int(*i as i128)),
-            Literal::Float(f) => Some(Value::Float(*f)),
-            Literal::String(_) => None,
+        let (value, types) = match literal {
+            Literal::Dec(i) => (*i, Arithmetic::int_constant_types(true)),
+            Literal::Hex(i) | Literal::Octal(i) => (*i, Arithmetic::int_constant_types(false)),
+            Literal::Char(i) => return Some(Value::new(Number::Int(*i as i128), Arithmetic::Char)),
+            Literal::Float(f) => return Some(Value::new(Number::Float(*f), Arithmetic::Double)),
+            Literal::String(_) => return None,
+        };
+        // A constant that is too big for every type is reported when building the IR
+        let ty = self.int_constant_type(value, types)?;
+        Some(Value::new(Number::Int(value), ty))
+    }
+
+    fn int_constant_type(&self, value: i128, types: &[Arithmetic]) -> Option<Arithmetic> {
+        types
+            .iter()
+            .copied()
+            .find(|ty| ty.int_range(self.settings).contains(&value))
+    }
+
+    /// Converts the value to another arithmetic type, [`None`] if the result is undefined.
+    fn convert(&self, value: Value, ty: Arithmetic) -> Option<Value> {
+        let number = match (value.number, ty.is_integral()) {
+            (Number::Int(i), true) => Number::Int(ty.wrap_int(i, self.settings)),
+            (Number::Int(i), false) => Number::Float(self.round(i as f64, ty)),
+            (Number::Float(f), false) => Number::Float(self.round(f, ty)),
+            (Number::Float(f), true) => {
+                let range = ty.int_range(self.settings);
+                let truncated = f.trunc();
+                if truncated < *range.start() as f64 || truncated >= (*range.end() + 1) as f64 {
+                    return None;
+                }
+                Number::Int(truncated as i128)
+            }
+        };
+        Some(Value {
+            number,
+            ty,
+            propagated: value.propagated,
+        })
+    }
+
+    /// Rounds a floating value to the precision of the floating type.
+    fn round(&self, value: f64, ty: Arithmetic) -> f64 {
+        match ty.size_in_bits(self.settings) {
+            32 => value as f32 as f64,
+            _ => value,
         }
     }
+
+    /// Wraps the exact result of an operation around to the type, a signed type that can't
+    /// represent the result overflows.
+    fn wrap(&mut self, result: i128, ty: Arithmetic, span: Span) -> Value {
+        let wrapped = ty.wrap_int(result, self.settings);
+        if ty.is_signed() && wrapped != result {
+            let diagnostic = DiagnosticBuilder::new(span).build_integer_overflow(ty, wrapped);
+            self.diagnostics.push_back(diagnostic);
+        }
+        Value::new(Number::Int(wrapped), ty)
+    }
+
+    /// The result of a relational, equality or logical operator, which is an `int` (3.3.8).
+    fn truth(&self, value: bool) -> Value {
+        Value::new(Number::Int(value as i128), Arithmetic::SignedInt)
+    }
+
+    /// Replaces the expression with a literal of the value. A cast keeps the type of the value if
+    /// the literal would have another one.
+    fn replace_with_literal(&self, expr_node: &mut ExpressionNode, value: Value) {
+        if !self.replace {
+            return;
+        }
+        let span = expr_node.span;
+        let (lit, lit_ty) = match value.number {
+            Number::Int(i) if value.ty == Arithmetic::Char => {
+                (Literal::Char(i as u8), Some(value.ty))
+            }
+            Number::Int(i) => (
+                Literal::Dec(i),
+                self.int_constant_type(i, Arithmetic::int_constant_types(true)),
+            ),
+            Number::Float(f) => (Literal::Float(f), Some(Arithmetic::Double)),
+        };
+        let literal = Expression::Literal(LiteralNode { span, data: lit });
+        expr_node.data = match lit_ty == Some(value.ty) {
+            true => literal,
+            false => Expression::Cast(
+                type_node(value.ty, span),
+                Box::new(ExpressionNode {
+                    span,
+                    data: literal,
+                }),
+            ),
+        };
+    }
 }
 
-fn replace_with_literal(expr_node: &mut ExpressionNode, value: Value) {
-    let lit = match value {
-        Value::Int(i) => Literal::Dec(i),
-        Value::Float(f) => Literal::Float(f),
+fn type_node(ty: Arithmetic, span: Span) -> QualifiedTypeNode {
+    let data = match ty {
+        Arithmetic::Float => UnqualifiedType::Float,
+        Arithmetic::Double => UnqualifiedType::Double,
+        Arithmetic::LongDouble => UnqualifiedType::LongDouble,
+        Arithmetic::Char => UnqualifiedType::Char,
+        Arithmetic::SignedChar => UnqualifiedType::SignedChar,
+        Arithmetic::SignedShortInt => UnqualifiedType::SignedShortInt,
+        Arithmetic::SignedInt => UnqualifiedType::SignedInt,
+        Arithmetic::SignedLongInt => UnqualifiedType::SignedLongInt,
+        Arithmetic::UnsignedChar => UnqualifiedType::UnsignedChar,
+        Arithmetic::UnsignedShortInt => UnqualifiedType::UnsignedShortInt,
+        Arithmetic::UnsignedInt => UnqualifiedType::UnsignedInt,
+        Arithmetic::UnsignedLongInt => UnqualifiedType::UnsignedLongInt,
     };
-    expr_node.data = Expression::Literal(LiteralNode {
-        span: expr_node.span,
-        data: lit,
-    })
+    QualifiedTypeNode {
+        span,
+        is_const: None,
+        unqualified: UnqualifiedTypeNode { span, data },
+    }
 }
@@ -0,0 +1,694 @@
+//! Pretty-prints C source from the tokens of its CST.
+//!
+//! The formatter only looks at the tokens, including the comments and pragmas on the hidden
+//! channels, so it never changes what the source means. Newlines in the original source are only used to keep
+//! blank lines between statements and to tell comments on their own line apart from comments at
+//! the end of a line. Formatting already formatted source gives the same source again.
+
+use crate::{
+    cst, diagnostic::AggregateResult, generated::clexer as g, passes::parse::parse_to_cst,
+};
+use antlr_rust::{
+    int_stream::IntStream,
+    token::{Token, TOKEN_EOF},
+    token_stream::TokenStream,
+};
+
+/// Where the opening brace of a block is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BraceStyle {
+    /// On the same line as the statement or function it belongs to, `else` follows the closing
+    /// brace.
+    #[default]
+    Attach,
+    /// On its own line, for every block.
+    Allman,
+    /// On its own line for functions, like [`BraceStyle::Attach`] for everything else.
+    Linux,
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatOpts {
+    pub brace_style: BraceStyle,
+    /// The number of spaces per indentation level.
+    pub indent_width: usize,
+    /// Lines longer than this are broken where possible.
+    pub max_width: usize,
+}
+
+impl Default for FormatOpts {
+    fn default() -> Self {
+        Self {
+            brace_style: BraceStyle::default(),
+            indent_width: 4,
+            max_width: 100,
+        }
+    }
+}
+
+/// Parses and formats the source, fails if the source has syntax errors.
+pub fn format_source(source: &str, opts: &FormatOpts) -> AggregateResult<String> {
+    parse_to_cst(source).map(|cst| format(&cst, source, opts))
+}
+
+/// Formats the source `cst` was parsed from.
+pub fn format(cst: &cst::Cst, source: &str, opts: &FormatOpts) -> String {
+    let tokens = collect_tokens(&cst.token_stream, source);
+    let mut formatter = Formatter::new(opts, &tokens);
+    formatter.run();
+    formatter.render()
+}
+
+/// A token of the source, with the number of newlines between it and the previous token.
+struct Tok<'s> {
+    token_type: isize,
+    text: &'s str,
+    /// Comments and pragmas aren't part of the CST, pragmas are printed like a line comment.
+    is_comment: bool,
+    newlines_before: usize,
+}
+
+fn collect_tokens<'s>(token_stream: &cst::TokenStream, source: &'s str) -> Vec<Tok<'s>> {
+    let mut tokens = Vec::new();
+    let mut prev_end = 0;
+    for i in 0..token_stream.size() {
+        let token = token_stream.get(i);
+        if token.get_token_type() == TOKEN_EOF {
+            break;
+        }
+        let start: usize = token.get_start().try_into().unwrap();
+        let end: usize = (token.get_stop() + 1).try_into().unwrap();
+        // An include ends with the newline after it.
+        let text = source[start..end].trim_end();
+        tokens.push(Tok {
+            token_type: token.get_token_type(),
+            text,
+            is_comment: [g::COMMENTS, g::PRAGMAS].contains(&(token.get_channel() as usize)),
+            newlines_before: source[prev_end..start].matches('\n').count(),
+        });
+        prev_end = start + text.len();
+    }
+    tokens
+}
+
+/// How a token is printed, which decides the spaces around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Word,
+    TypeKeyword,
+    Keyword,
+    Include,
+    BinaryOp,
+    Assign,
+    UnaryOp,
+    PostfixOp,
+    /// The `*` of a pointer type.
+    PointerStar,
+    OpenParen,
+    /// The `(` of a call or a function declaration.
+    CallParen,
+    CloseParen,
+    /// The `)` of a cast.
+    CastClose,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Semicolon,
+    /// The `:` after a switch case.
+    LabelColon,
+    Brace,
+}
+
+impl Kind {
+    /// Whether the token ends an operand, so an operator after it is a binary or postfix operator.
+    fn ends_operand(self) -> bool {
+        matches!(
+            self,
+            Kind::Word | Kind::PostfixOp | Kind::CloseParen | Kind::CloseBracket
+        )
+    }
+}
+
+fn space_between(prev: (Kind, &str), next: (Kind, &str)) -> bool {
+    use Kind::*;
+    // Keeps `- -a` from becoming `--a`.
+    let last = prev.1.chars().last();
+    if matches!(last, Some('+' | '-')) && next.1.starts_with(last.unwrap()) {
+        return true;
+    }
+    match (prev.0, next.0) {
+        (
+            _,
+            Comma | Semicolon | CloseParen | CastClose | CloseBracket | OpenBracket | CallParen
+            | PostfixOp | LabelColon,
+        ) => false,
+        (OpenParen | CallParen | OpenBracket | UnaryOp | CastClose, _) => false,
+        (PointerStar, PointerStar | Word | TypeKeyword) => false,
+        _ => true,
+    }
+}
+
+fn is_type_keyword(token_type: isize) -> bool {
+    matches!(
+        token_type,
+        g::KW_CONST
+            | g::KW_SIGNED
+            | g::KW_UNSIGNED
+            | g::KW_LONG
+            | g::KW_SHORT
+            | g::KW_CHAR
+            | g::KW_INT
+            | g::KW_FLOAT
+            | g::KW_DOUBLE
+            | g::KW_VOID
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParenKind {
+    /// The parentheses after `if`, `while`, `for` or `switch`.
+    Control(isize),
+    Call,
+    Cast,
+    Group,
+}
+
+/// What a block or a single statement is the body of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Function,
+    If,
+    Else,
+    Loop,
+    Switch,
+}
+
+enum Scope {
+    Block {
+        body: Option<BodyKind>,
+    },
+    /// A single statement without braces after `if`, `else`, `while` or `for`.
+    Body(BodyKind),
+}
+
+struct Piece<'s> {
+    text: &'s str,
+    space_before: bool,
+    /// The number of open parentheses and brackets, lines are preferably broken at the lowest
+    /// depth.
+    depth: usize,
+    /// How bad it is to break the line before this piece, `None` if it can't be broken there.
+    break_cost: Option<u8>,
+}
+
+#[derive(Default)]
+struct Line<'s> {
+    indent: usize,
+    pieces: Vec<Piece<'s>>,
+}
+
+struct Formatter<'a, 's> {
+    opts: &'a FormatOpts,
+    tokens: &'a [Tok<'s>],
+    lines: Vec<Line<'s>>,
+    current: Option<Line<'s>>,
+
+    scopes: Vec<Scope>,
+    parens: Vec<ParenKind>,
+    brackets: usize,
+    /// Set after the header of an `if`, `else`, `while`, `for` or `switch`, until its body starts.
+    pending_body: Option<BodyKind>,
+    /// Whether the next code token starts a new statement on a new line.
+    at_statement_start: bool,
+    /// The indentation of the line the current statement started on.
+    statement_indent: usize,
+    first_in_block: bool,
+    after_function: bool,
+    /// Set between `case` or `default` and its `:`.
+    in_case_label: bool,
+    /// The number of `?` without a matching `:` yet.
+    open_questions: usize,
+    prev: Option<(Kind, isize, &'s str)>,
+}
+
+impl<'a, 's> Formatter<'a, 's> {
+    fn new(opts: &'a FormatOpts, tokens: &'a [Tok<'s>]) -> Self {
+        Self {
+            opts,
+            tokens,
+            lines: Vec::new(),
+            current: None,
+            scopes: Vec::new(),
+            parens: Vec::new(),
+            brackets: 0,
+            pending_body: None,
+            at_statement_start: true,
+            statement_indent: 0,
+            first_in_block: false,
+            after_function: false,
+            in_case_label: false,
+            open_questions: 0,
+            prev: None,
+        }
+    }
+
+    fn run(&mut self) {
+        let tokens = self.tokens;
+        for (i, tok) in tokens.iter().enumerate() {
+            if tok.is_comment {
+                self.comment(tok);
+                continue;
+            }
+
+            if let Some(body) = self.pending_body.take() {
+                match tok.token_type {
+                    g::BRACE_LEFT => {
+                        self.open_brace(tok, Some(body));
+                        continue;
+                    }
+                    // `else if` stays on one line, the `if` has its own body.
+                    g::KW_IF if body == BodyKind::Else => {
+                        self.emit(tok, Kind::Keyword);
+                        continue;
+                    }
+                    _ => {
+                        self.scopes.push(Scope::Body(body));
+                        self.at_statement_start = true;
+                    }
+                }
+            }
+
+            self.token(i, tok);
+        }
+        self.finish_line();
+    }
+
+    fn token(&mut self, i: usize, tok: &Tok<'s>) {
+        match tok.token_type {
+            g::INCLUDE => {
+                self.emit(tok, Kind::Include);
+                self.at_statement_start = true;
+            }
+            g::BRACE_LEFT => {
+                let body = self.scopes.is_empty().then_some(BodyKind::Function);
+                self.open_brace(tok, body);
+            }
+            g::BRACE_RIGHT => self.close_brace(i, tok),
+            g::SEMICOLON => {
+                self.emit(tok, Kind::Semicolon);
+                if self.parens.is_empty() {
+                    self.end_statement(i, None);
+                }
+            }
+            g::COMMA => self.emit(tok, Kind::Comma),
+            g::COLON if self.in_case_label && self.open_questions == 0 => {
+                self.emit(tok, Kind::LabelColon);
+                self.in_case_label = false;
+                self.at_statement_start = true;
+            }
+            g::COLON => {
+                self.open_questions = self.open_questions.saturating_sub(1);
+                self.emit(tok, Kind::BinaryOp);
+            }
+            g::QUESTION_MARK => {
+                self.open_questions += 1;
+                self.emit(tok, Kind::BinaryOp);
+            }
+            g::EQUALS => self.emit(tok, Kind::Assign),
+            g::KW_CASE | g::KW_DEFAULT => {
+                self.in_case_label = true;
+                self.open_questions = 0;
+                self.emit(tok, Kind::Keyword);
+            }
+            g::KW_ELSE => self.else_keyword(tok),
+            g::PAREN_LEFT => {
+                let kind = match self.prev() {
+                    Some((Kind::Word, g::IDENT, _)) => ParenKind::Call,
+                    Some((_, kw @ (g::KW_IF | g::KW_WHILE | g::KW_FOR | g::KW_SWITCH), _)) => {
+                        ParenKind::Control(kw)
+                    }
+                    _ if self.next_code_token(i).is_some_and(is_type_keyword) => ParenKind::Cast,
+                    _ => ParenKind::Group,
+                };
+                self.parens.push(kind);
+                let kind = match kind {
+                    ParenKind::Call => Kind::CallParen,
+                    _ => Kind::OpenParen,
+                };
+                self.emit(tok, kind);
+            }
+            g::PAREN_RIGHT => match self.parens.pop() {
+                Some(ParenKind::Cast) => self.emit(tok, Kind::CastClose),
+                Some(ParenKind::Control(kw)) => {
+                    self.emit(tok, Kind::CloseParen);
+                    self.pending_body = Some(match kw {
+                        g::KW_IF => BodyKind::If,
+                        g::KW_SWITCH => BodyKind::Switch,
+                        _ => BodyKind::Loop,
+                    });
+                }
+                _ => self.emit(tok, Kind::CloseParen),
+            },
+            g::BRACKET_LEFT => {
+                self.emit(tok, Kind::OpenBracket);
+                self.brackets += 1;
+            }
+            g::BRACKET_RIGHT => {
+                self.brackets = self.brackets.saturating_sub(1);
+                self.emit(tok, Kind::CloseBracket);
+            }
+            g::STAR | g::PLUS | g::MINUS | g::AMPERSAND | g::DOUBLE_PLUS | g::DOUBLE_MINUS => {
+                let kind = self.operator_kind(tok.token_type);
+                self.emit(tok, kind);
+            }
+            g::BANG | g::TILDE => self.emit(tok, Kind::UnaryOp),
+            g::DOUBLE_PIPE
+            | g::DOUBLE_AMPERSAND
+            | g::DOUBLE_EQUALS
+            | g::BANG_EQUALS
+            | g::ANGLE_LEFT_EQUALS
+            | g::ANGLE_RIGHT_EQUALS
+            | g::DOUBLE_ANGLE_LEFT
+            | g::DOUBLE_ANGLE_RIGHT
+            | g::PIPE
+            | g::CARET
+            | g::ANGLE_LEFT
+            | g::ANGLE_RIGHT
+            | g::SLASH
+            | g::PERCENT => self.emit(tok, Kind::BinaryOp),
+            token_type if is_type_keyword(token_type) => self.emit(tok, Kind::TypeKeyword),
+            g::KW_IF
+            | g::KW_WHILE
+            | g::KW_FOR
+            | g::KW_SWITCH
+            | g::KW_BREAK
+            | g::KW_CONTINUE
+            | g::KW_RETURN => self.emit(tok, Kind::Keyword),
+            _ => self.emit(tok, Kind::Word),
+        }
+    }
+
+    /// Tells unary, binary and postfix operators and pointer types apart by the token before.
+    fn operator_kind(&self, token_type: isize) -> Kind {
+        let prev = self.prev().map(|(kind, _, _)| kind);
+        match token_type {
+            g::STAR if matches!(prev, Some(Kind::TypeKeyword | Kind::PointerStar)) => {
+                Kind::PointerStar
+            }
+            g::DOUBLE_PLUS | g::DOUBLE_MINUS if prev.is_some_and(Kind::ends_operand) => {
+                Kind::PostfixOp
+            }
+            g::DOUBLE_PLUS | g::DOUBLE_MINUS => Kind::UnaryOp,
+            _ if prev.is_some_and(Kind::ends_operand) => Kind::BinaryOp,
+            _ => Kind::UnaryOp,
+        }
+    }
+
+    /// The previous token in the same statement.
+    fn prev(&self) -> Option<(Kind, isize, &'s str)> {
+        if self.at_statement_start {
+            None
+        } else {
+            self.prev
+        }
+    }
+
+    fn next_code_token(&self, i: usize) -> Option<isize> {
+        self.tokens[i + 1..]
+            .iter()
+            .find(|tok| !tok.is_comment)
+            .map(|tok| tok.token_type)
+    }
+
+    fn indent(&self) -> usize {
+        self.scopes.len() * self.opts.indent_width
+    }
+
+    fn in_switch_block(&self) -> bool {
+        matches!(
+            self.scopes.last(),
+            Some(Scope::Block {
+                body: Some(BodyKind::Switch)
+            })
+        )
+    }
+
+    /// Prints a code token, on a new line if it starts a statement.
+    fn emit(&mut self, tok: &Tok<'s>, kind: Kind) {
+        let space_before = if self.at_statement_start {
+            let blank_line = tok.newlines_before >= 2 && !self.first_in_block;
+            let mut indent = self.indent();
+            // Case labels line up with their switch, the statements after them are indented.
+            if matches!(tok.token_type, g::KW_CASE | g::KW_DEFAULT) && self.in_switch_block() {
+                indent -= self.opts.indent_width;
+            }
+            self.start_line(indent, blank_line);
+            self.statement_indent = self.indent();
+            self.at_statement_start = false;
+            self.prev = None;
+            false
+        } else {
+            self.prev.is_some_and(|(prev, _, prev_text)| {
+                space_between((prev, prev_text), (kind, tok.text))
+            })
+        };
+
+        let break_cost = match (self.prev, kind) {
+            (Some((Kind::Comma | Kind::Semicolon, _, _)), _) => Some(0),
+            (_, Kind::BinaryOp) => Some(match tok.token_type {
+                g::QUESTION_MARK | g::COLON => 1,
+                g::DOUBLE_PIPE => 2,
+                g::DOUBLE_AMPERSAND => 3,
+                g::PLUS | g::MINUS | g::STAR | g::SLASH | g::PERCENT => 6,
+                _ => 5,
+            }),
+            (Some((Kind::Assign, _, _)), _) => Some(4),
+            _ => None,
+        };
+        self.push(Piece {
+            text: tok.text,
+            space_before,
+            depth: self.parens.len() + self.brackets,
+            break_cost,
+        });
+        self.prev = Some((kind, tok.token_type, tok.text));
+    }
+
+    fn comment(&mut self, tok: &Tok<'s>) {
+        // A pragma is only recognized at the start of a line.
+        let is_pragma = tok.token_type == g::PRAGMA;
+        if tok.newlines_before == 0 && self.current.is_some() && !is_pragma {
+            // A comment after code on the same line stays there.
+            self.push(Piece {
+                text: tok.text,
+                space_before: true,
+                depth: self.parens.len() + self.brackets,
+                break_cost: None,
+            });
+        } else if self.at_statement_start {
+            let blank_line = tok.newlines_before >= 2 && !self.first_in_block;
+            self.start_line(self.indent(), blank_line);
+            self.push(Piece {
+                text: tok.text,
+                space_before: false,
+                depth: 0,
+                break_cost: None,
+            });
+        } else {
+            // A comment on its own line in the middle of a statement.
+            self.start_line(self.continuation_indent(), false);
+            self.push(Piece {
+                text: tok.text,
+                space_before: false,
+                depth: 0,
+                break_cost: None,
+            });
+        }
+
+        // Code after a block comment on its own line goes on the next line too, so the comment
+        // stays on its own line when formatting again.
+        if tok.token_type == g::SINGLELINE_COMMENT || is_pragma || tok.newlines_before != 0 {
+            self.finish_line();
+        }
+    }
+
+    fn else_keyword(&mut self, tok: &Tok<'s>) {
+        let after_brace = self
+            .current
+            .as_ref()
+            .and_then(|line| line.pieces.last())
+            .is_some_and(|piece| piece.text == "}");
+        // `else` on its own line never gets a blank line before it.
+        self.first_in_block = true;
+        self.at_statement_start = !(after_brace && self.opts.brace_style != BraceStyle::Allman);
+        self.emit(tok, Kind::Keyword);
+        self.first_in_block = false;
+        self.pending_body = Some(BodyKind::Else);
+    }
+
+    fn open_brace(&mut self, tok: &Tok<'s>, body: Option<BodyKind>) {
+        let attach = match (self.opts.brace_style, body) {
+            (_, None) | (BraceStyle::Allman, _) => false,
+            (BraceStyle::Linux, Some(BodyKind::Function)) => false,
+            _ => true,
+        };
+        // No blank line between a function or statement and its block.
+        self.first_in_block = true;
+        self.at_statement_start = !(attach && self.current.is_some());
+        self.emit(tok, Kind::Brace);
+
+        self.scopes.push(Scope::Block { body });
+        self.at_statement_start = true;
+        self.first_in_block = true;
+    }
+
+    fn close_brace(&mut self, i: usize, tok: &Tok<'s>) {
+        let body = loop {
+            match self.scopes.pop() {
+                Some(Scope::Block { body }) => break body,
+                Some(Scope::Body(_)) => {}
+                None => break None,
+            }
+        };
+        self.at_statement_start = true;
+        self.first_in_block = true;
+        self.emit(tok, Kind::Brace);
+        self.first_in_block = false;
+        self.end_statement(i, body);
+        if body == Some(BodyKind::Function) {
+            self.after_function = true;
+        }
+    }
+
+    /// Ends the statement and every statement without braces it was the body of. `ended` is what
+    /// the statement was the body of, if it was a block.
+    fn end_statement(&mut self, i: usize, mut ended: Option<BodyKind>) {
+        self.at_statement_start = true;
+        self.in_case_label = false;
+        self.open_questions = 0;
+        loop {
+            if ended == Some(BodyKind::If) && self.next_code_token(i) == Some(g::KW_ELSE) {
+                break;
+            }
+            match self.scopes.last() {
+                Some(Scope::Body(body)) => {
+                    ended = Some(*body);
+                    self.scopes.pop();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn continuation_indent(&self) -> usize {
+        self.statement_indent + 2 * self.opts.indent_width
+    }
+
+    fn start_line(&mut self, indent: usize, blank_line: bool) {
+        self.finish_line();
+        if (blank_line || self.after_function) && !self.lines.is_empty() {
+            self.lines.push(Line::default());
+        }
+        self.after_function = false;
+        self.first_in_block = false;
+        self.current = Some(Line {
+            indent,
+            pieces: Vec::new(),
+        });
+    }
+
+    fn push(&mut self, piece: Piece<'s>) {
+        if self.current.is_none() {
+            // The previous line ended with a comment in the middle of a statement.
+            self.current = Some(Line {
+                indent: self.continuation_indent(),
+                pieces: Vec::new(),
+            });
+        }
+        self.current.as_mut().unwrap().pieces.push(piece);
+    }
+
+    fn finish_line(&mut self) {
+        if let Some(line) = self.current.take() {
+            self.lines.push(line);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            for row in self.wrap(line) {
+                let mut text = String::new();
+                for (i, piece) in line.pieces[row.clone()].iter().enumerate() {
+                    if i != 0 && piece.space_before {
+                        text.push(' ');
+                    }
+                    text.push_str(piece.text);
+                }
+                if !text.is_empty() {
+                    let indent = if row.start == 0 {
+                        line.indent
+                    } else {
+                        line.indent + 2 * self.opts.indent_width
+                    };
+                    out.push_str(&" ".repeat(indent));
+                    out.push_str(&text);
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Splits a line into rows that fit in the maximum width where possible. A line is broken at
+    /// the lowest nesting depth, at the cheapest place there, and as late as possible.
+    fn wrap(&self, line: &Line) -> Vec<std::ops::Range<usize>> {
+        let pieces = &line.pieces;
+        let width = |piece: &Piece, first: bool| {
+            let text_width = piece.text.lines().next().unwrap_or("").chars().count();
+            text_width + usize::from(!first && piece.space_before)
+        };
+
+        let mut rows = Vec::new();
+        let mut start = 0;
+        let mut indent = line.indent;
+        while start < pieces.len() {
+            let mut column = indent;
+            let mut end = start;
+            while end < pieces.len() {
+                let piece_width = width(&pieces[end], end == start);
+                if end > start && column + piece_width > self.opts.max_width {
+                    break;
+                }
+                column += piece_width;
+                end += 1;
+            }
+            if end == pieces.len() {
+                rows.push(start..end);
+                break;
+            }
+
+            let fitting = (start + 1..=end).filter(|&i| pieces[i].break_cost.is_some());
+            let split = match fitting
+                .min_by_key(|&i| (pieces[i].depth, pieces[i].break_cost, usize::MAX - i))
+            {
+                Some(split) => split,
+                // Nothing fits, so the row is made as short as possible.
+                None => match (end + 1..pieces.len()).find(|&i| pieces[i].break_cost.is_some()) {
+                    Some(split) => split,
+                    None => {
+                        rows.push(start..pieces.len());
+                        break;
+                    }
+                },
+            };
+            rows.push(start..split);
+            start = split;
+            indent = line.indent + 2 * self.opts.indent_width;
+        }
+        if rows.is_empty() {
+            rows.push(0..0);
+        }
+        rows
+    }
+}
@@ -0,0 +1,439 @@
+//! Checks the literal format strings of `printf` and `scanf` calls against their arguments, like
+//! `-Wformat` of gcc.
+//!
+//! Besides the number and types of the arguments, the conversion specifications are checked
+//! against what the `printf` and `scanf` of the MIPS runtime (`mips_ir/src/linker`) support: the
+//! conversions `d`, `i`, `c`, `f` and `s`, without flags, precision or length modifiers, and only a
+//! field width for `scanf`.
+
+use std::collections::LinkedList;
+
+use crate::{
+    diagnostic::{Diagnostic, DiagnosticBuilder, Span},
+    ir::{
+        ctype::{Aggregate, Arithmetic, CType, Pointer, Scalar},
+        BlockNode, Constant, Expr, ExprNode, LvalueExpr, Root, Stmt, SwitchStmtCase,
+    },
+};
+
+pub fn check_format_strings(root: &Root, source: &str) -> LinkedList<Diagnostic> {
+    let mut checker = FormatChecker {
+        root,
+        source,
+        diagnostics: LinkedList::new(),
+    };
+    let mut bodies: Vec<_> = root
+        .functions
+        .values()
+        .filter_map(|function| function.body.as_ref())
+        .collect();
+    bodies.sort_by_key(|body| body.span.start());
+    for body in bodies {
+        checker.block(body);
+    }
+    checker.diagnostics
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormatFunction {
+    Printf,
+    Scanf,
+}
+
+impl FormatFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "printf" => Some(Self::Printf),
+            "scanf" => Some(Self::Scanf),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Printf => "printf",
+            Self::Scanf => "scanf",
+        }
+    }
+}
+
+/// The argument a supported conversion expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expected {
+    /// `%d`, `%i` and `%c` of `printf`, any integer type is promoted to a fitting one.
+    Integer,
+    /// `%f` of `printf`, a `float` is promoted to `double`.
+    Floating,
+    /// `%s` of `printf` and `%c` and `%s` of `scanf`.
+    CharPointer,
+    /// `%d` and `%i` of `scanf`.
+    IntPointer,
+    /// `%f` of `scanf`.
+    FloatPointer,
+}
+
+impl Expected {
+    fn type_name(self) -> &'static str {
+        match self {
+            Expected::Integer => "int",
+            Expected::Floating => "double",
+            Expected::CharPointer => "char*",
+            Expected::IntPointer => "int*",
+            Expected::FloatPointer => "float*",
+        }
+    }
+
+    fn matches(self, ty: &CType) -> bool {
+        let arithmetic = |ty: &CType| match ty {
+            CType::Scalar(Scalar::Arithmetic(a)) => Some(*a),
+            _ => None,
+        };
+        let pointee = match ty {
+            CType::Scalar(Scalar::Pointer(Pointer { inner, .. })) => Some(inner.as_ref()),
+            CType::Aggregate(Aggregate::Array(array)) => Some(array.inner.as_ref()),
+            _ => None,
+        };
+        let pointee = pointee.and_then(arithmetic);
+        match self {
+            Expected::Integer => arithmetic(ty).is_some_and(|a| a.is_integral()),
+            Expected::Floating => arithmetic(ty).is_some_and(|a| a.is_floating()),
+            Expected::CharPointer => matches!(
+                pointee,
+                Some(Arithmetic::Char | Arithmetic::SignedChar | Arithmetic::UnsignedChar)
+            ),
+            Expected::IntPointer => matches!(
+                pointee,
+                Some(Arithmetic::SignedInt | Arithmetic::UnsignedInt)
+            ),
+            Expected::FloatPointer => pointee == Some(Arithmetic::Float),
+        }
+    }
+}
+
+/// A conversion specification in a format string, e.g. `%d` or `%-5.2lf`.
+#[derive(Debug)]
+struct Conversion {
+    /// The range of the specification in the format string.
+    range: std::ops::Range<usize>,
+    /// `None` if the runtime doesn't support the specification.
+    expected: Option<Expected>,
+    /// Whether the specification uses an argument.
+    takes_arg: bool,
+}
+
+/// The conversions of C89 4.9.6.1 that take an argument.
+const PRINTF_CONVERSIONS: &[u8] = b"diouxXfeEgGcspn";
+/// The conversions of C89 4.9.6.2 that take an argument, `[` starts a scan set.
+const SCANF_CONVERSIONS: &[u8] = b"diouxefgscpn[";
+
+fn conversions(format: &[u8], function: FormatFunction) -> Vec<Conversion> {
+    let mut conversions = Vec::new();
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        if format.get(i) == Some(&b'%') {
+            i += 1;
+            continue;
+        }
+
+        let digits = |i: &mut usize| {
+            while format.get(*i).is_some_and(u8::is_ascii_digit) {
+                *i += 1;
+            }
+        };
+        let mut extended = false;
+        let mut suppressed = false;
+        match function {
+            FormatFunction::Printf => {
+                while format.get(i).is_some_and(|c| b"-+ #0".contains(c)) {
+                    i += 1;
+                    extended = true;
+                }
+                // The field width and precision
+                let flags_end = i;
+                let width = |i: &mut usize| {
+                    if format.get(*i) == Some(&b'*') {
+                        *i += 1;
+                    } else {
+                        digits(i);
+                    }
+                };
+                width(&mut i);
+                if format.get(i) == Some(&b'.') {
+                    i += 1;
+                    width(&mut i);
+                }
+                extended |= i != flags_end;
+            }
+            FormatFunction::Scanf => {
+                if format.get(i) == Some(&b'*') {
+                    i += 1;
+                    suppressed = true;
+                }
+                // The runtime supports a field width, e.g. `%10s`
+                digits(&mut i);
+            }
+        }
+        if format.get(i).is_some_and(|c| b"hlL".contains(c)) {
+            i += 1;
+            extended = true;
+        }
+
+        let Some(&conversion) = format.get(i) else {
+            // A `%` at the end of the format string
+            conversions.push(Conversion {
+                range: start..i,
+                expected: None,
+                takes_arg: false,
+            });
+            break;
+        };
+        i += 1;
+        if function == FormatFunction::Scanf && conversion == b'[' {
+            // The scan set can start with a `]` that is part of the set
+            i += (format.get(i) == Some(&b']')) as usize;
+            while format.get(i).is_some_and(|c| *c != b']') {
+                i += 1;
+            }
+            i = (i + 1).min(format.len());
+        }
+
+        let expected = match (function, conversion) {
+            _ if extended || suppressed => None,
+            (FormatFunction::Printf, b'd' | b'i' | b'c') => Some(Expected::Integer),
+            (FormatFunction::Printf, b'f') => Some(Expected::Floating),
+            (FormatFunction::Printf, b's') => Some(Expected::CharPointer),
+            (FormatFunction::Scanf, b'd' | b'i') => Some(Expected::IntPointer),
+            (FormatFunction::Scanf, b'f') => Some(Expected::FloatPointer),
+            (FormatFunction::Scanf, b'c' | b's') => Some(Expected::CharPointer),
+            _ => None,
+        };
+        let takes_arg = !suppressed
+            && match function {
+                FormatFunction::Printf => PRINTF_CONVERSIONS.contains(&conversion),
+                FormatFunction::Scanf => SCANF_CONVERSIONS.contains(&conversion),
+            };
+        conversions.push(Conversion {
+            range: start..i,
+            expected,
+            takes_arg,
+        });
+    }
+    conversions
+}
+
+/// The spans in the source of every byte of a string literal, an escape sequence is the span of
+/// every byte it gives.
+fn byte_spans(source: &str, literal: Span) -> Vec<Span> {
+    let text = &source[std::ops::Range::<usize>::from(literal)];
+    let mut chars = text
+        .char_indices()
+        .map(|(i, c)| (literal.start() + i, c))
+        .peekable();
+    let mut spans = Vec::new();
+    // A literal can consist of multiple string literals, e.g. `"foo" "bar"`
+    while let Some((_, c)) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => {
+                    match chars.next() {
+                        Some((_, 'x')) => {
+                            while chars.next_if(|(_, c)| c.is_ascii_hexdigit()).is_some() {}
+                        }
+                        Some((_, '0'..='7')) => {
+                            for _ in 0..2 {
+                                chars.next_if(|(_, c)| matches!(c, '0'..='7'));
+                            }
+                        }
+                        _ => {}
+                    }
+                    let end = chars.peek().map_or(literal.excl_end(), |(i, _)| *i);
+                    spans.push((start..end).into());
+                }
+                c => spans.extend(
+                    std::iter::repeat(Span::from(start..start + c.len_utf8())).take(c.len_utf8()),
+                ),
+            }
+        }
+    }
+    spans
+}
+
+/// Removes the implicit casts around an expression, an implicit cast has the same span as the
+/// expression it casts.
+fn without_implicit_casts(mut expr: &ExprNode) -> &ExprNode {
+    while let Expr::Cast(inner) = &expr.expr {
+        if inner.span != expr.span {
+            break;
+        }
+        expr = inner;
+    }
+    expr
+}
+
+struct FormatChecker<'a> {
+    root: &'a Root,
+    source: &'a str,
+    diagnostics: LinkedList<Diagnostic>,
+}
+
+impl FormatChecker<'_> {
+    fn block(&mut self, block: &BlockNode) {
+        for stmt in &block.stmts {
+            match &stmt.stmt {
+                Stmt::Expr(expr) => self.expr(expr),
+                Stmt::IfStmt(if_stmt) => {
+                    self.expr(&if_stmt.condition);
+                    self.block(&if_stmt.if_branch);
+                    if let Some(else_branch) = &if_stmt.else_branch {
+                        self.block(else_branch);
+                    }
+                }
+                Stmt::SwitchStmt(switch) => {
+                    self.expr(&switch.expr);
+                    for case in &switch.cases {
+                        match &case.data {
+                            SwitchStmtCase::Case { body, .. }
+                            | SwitchStmtCase::Default { body } => self.block(body),
+                        }
+                    }
+                }
+                Stmt::LoopStmt(loop_stmt) => {
+                    if let Some(condition) = &loop_stmt.condition {
+                        self.expr(condition);
+                    }
+                    self.block(&loop_stmt.body);
+                    if let Some(continuation) = &loop_stmt.continuation {
+                        self.expr(continuation);
+                    }
+                }
+                Stmt::Return(Some(value)) => self.expr(value),
+                Stmt::Break | Stmt::Continue | Stmt::Return(None) => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ExprNode) {
+        match &expr.expr {
+            Expr::LvalueDeref(lvalue)
+            | Expr::PostfixInc(lvalue)
+            | Expr::PostfixDec(lvalue)
+            | Expr::PrefixInc(lvalue)
+            | Expr::PrefixDec(lvalue)
+            | Expr::Reference(lvalue) => {
+                if let LvalueExpr::Dereference(pointer) = &lvalue.expr {
+                    self.expr(pointer);
+                }
+            }
+            Expr::Constant(_) => {}
+            Expr::FunctionCall(name, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                self.call(name, args);
+            }
+            Expr::UnaryArith(_, inner) | Expr::Cast(inner) => self.expr(inner),
+            Expr::Binary(left, _, right)
+            | Expr::Relation(left, _, right)
+            | Expr::LogicalAnd(left, right)
+            | Expr::LogicalOr(left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Assign(to, value) => {
+                if let LvalueExpr::Dereference(pointer) = &to.expr {
+                    self.expr(pointer);
+                }
+                self.expr(value);
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[ExprNode]) {
+        let Some(function) = FormatFunction::from_name(name) else {
+            return;
+        };
+        if !self
+            .root
+            .functions
+            .get(name)
+            .is_some_and(|f| f.is_declaration() && f.is_vararg)
+        {
+            return;
+        }
+        let Some((format, args)) = args.split_first() else {
+            return;
+        };
+        let format = without_implicit_casts(format);
+        let Expr::Constant(Constant::String(bytes)) = &format.expr else {
+            return;
+        };
+        // The string has a null byte at the end
+        let bytes = bytes.split(|b| *b == 0).next().unwrap_or_default();
+
+        let spans = byte_spans(self.source, format.span);
+        let span_of = |range: &std::ops::Range<usize>| match (
+            spans.get(range.start),
+            spans.get(range.end.saturating_sub(1)),
+        ) {
+            (Some(start), Some(end)) if spans.len() >= bytes.len() => {
+                Span::from(start.start()..end.excl_end())
+            }
+            _ => format.span,
+        };
+
+        let mut args = args.iter();
+        for conversion in conversions(bytes, function) {
+            let text = String::from_utf8_lossy(&bytes[conversion.range.clone()]);
+            let builder = DiagnosticBuilder::new(span_of(&conversion.range));
+            let Some(expected) = conversion.expected else {
+                self.diagnostics
+                    .push_back(builder.build_unsupported_format(&text, function.name()));
+                if conversion.takes_arg {
+                    args.next();
+                }
+                continue;
+            };
+            let Some(arg) = args.next() else {
+                self.diagnostics
+                    .push_back(builder.build_format_missing_arg(&text, function.name()));
+                continue;
+            };
+
+            let arg = without_implicit_casts(arg);
+            if expected.matches(&arg.ty) {
+                continue;
+            }
+            let diagnostic = match arg.ty {
+                CType::Scalar(Scalar::Arithmetic(_)) if function == FormatFunction::Scanf => {
+                    let address = CType::Scalar(Scalar::Pointer(Pointer {
+                        inner: Box::new(arg.ty.clone()),
+                        inner_const: false,
+                    }));
+                    let fixable =
+                        matches!(arg.expr, Expr::LvalueDeref(_)) && expected.matches(&address);
+                    builder.build_scanf_missing_address(&text, arg, fixable)
+                }
+                _ => builder.build_format_arg_type(&text, expected.type_name(), arg),
+            };
+            self.diagnostics.push_back(diagnostic);
+        }
+
+        let extra: Vec<_> = args.collect();
+        if let (Some(first), Some(last)) = (extra.first(), extra.last()) {
+            self.diagnostics.push_back(
+                DiagnosticBuilder::new(first.span.start()..last.span.excl_end())
+                    .build_format_extra_args(format.span, function.name()),
+            );
+        }
+    }
+}
@@ -15,8 +15,9 @@ use crate::{
     },
     passes::lower_ast::{
         type_checking::{
-            check_assign, AnyScaler, CheckBinErr, CheckBinOk, CheckUnErr, CheckUnOk, CompatPointer,
-            PointerInteger, PromoteArith, TypeRuleBin, TypeRuleUn, UsualArithConversions,
+            check_assign_expr, AnyScaler, CheckBinErr, CheckBinOk, CheckUnErr, CheckUnOk,
+            CompatPointer, PointerInteger, PromoteArith, TypeRuleBin, TypeRuleUn,
+            UsualArithConversions,
         },
         util::{find_first_fit, maybe_cast, FunctionScope},
     },
@@ -45,6 +46,14 @@ pub fn build_ir_expr(
                     scope,
                 ))
                 .and_then(|(rhs, lhs)| assign(lhs, rhs, span, op.span, settings))
+                .map(|assign| {
+                    if let Expr::Assign(to, _) = &assign.expr {
+                        if let LvalueExpr::Ident(id) = to.expr {
+                            scope.vars.get_mut(id).last_assign = Some(span);
+                        }
+                    }
+                    assign
+                })
         }
         ast::Expression::Binary(left, op, right) => {
             build_binary_op_ir_expr(op, left, right, span, settings, scope)
@@ -96,8 +105,8 @@ fn build_binary_op_ir_expr(
             Plus => builder.add(false),
             Minus => builder.sub(),
             Star => builder.bin_op(UsualArithConversions::new(), BinaryOp::Mul),
-            Slash => builder.bin_op(UsualArithConversions::new(), BinaryOp::Div),
-            Percent => builder.bin_op(UsualArithConversions::only_int(), BinaryOp::Rem),
+            Slash => builder.div_op(UsualArithConversions::new(), BinaryOp::Div),
+            Percent => builder.div_op(UsualArithConversions::only_int(), BinaryOp::Rem),
             Pipe => builder.bitwise_op(BitwiseOp::Or),
             Caret => builder.bitwise_op(BitwiseOp::Xor),
             Ampersand => builder.bitwise_op(BitwiseOp::And),
@@ -109,12 +118,8 @@ fn build_binary_op_ir_expr(
             AngleRightEquals => builder.relation(RelationOp::Ge),
             DoubleAmpersand => builder.logical_and(),
             DoublePipe => builder.logical_or(),
-            DoubleAngleLeft => {
-                builder.bin_op(UsualArithConversions::only_int(), BinaryOp::ShiftLeft)
-            }
-            DoubleAngleRight => {
-                builder.bin_op(UsualArithConversions::only_int(), BinaryOp::ShiftRight)
-            }
+            DoubleAngleLeft => builder.shift_op(BinaryOp::ShiftLeft),
+            DoubleAngleRight => builder.shift_op(BinaryOp::ShiftRight),
         }
     })
 }
@@ -185,9 +190,13 @@ pub fn build_ir_lvalue(
         _ => None,
     };
     lvalue.unwrap_or_else(|| {
-        AggregateResult::new_err(
-            DiagnosticBuilder::new(op_span).build_need_lvalue(needed_for, e.span),
-        )
+        // The operand is only lowered here, a pointer that isn't dereferenced gets a fix
+        build_ir_expr(e, settings, scope).and_then(|value| {
+            let is_pointer = matches!(value.ty, CType::Scalar(ctype::Scalar::Pointer(_)));
+            AggregateResult::new_err(
+                DiagnosticBuilder::new(op_span).build_need_lvalue(needed_for, e.span, is_pointer),
+            )
+        })
     })
 }
 
@@ -285,7 +294,7 @@ fn function_call(
 
                     let builder = DiagnosticBuilder::new(arg.span);
                     use super::type_checking::AssignCheckResult::*;
-                    match check_assign(&out_ty, &arg.ty, settings) {
+                    match check_assign_expr(&out_ty, &arg, settings) {
                         Ok => AggregateResult::new_ok(out_ty),
                         Lossy => AggregateResult::new_rec(
                             out_ty,
@@ -306,6 +315,11 @@ fn function_call(
                         PointerAndFloat => {
                             AggregateResult::new_err(builder.build_incompatible_arg(&arg, param))
                         }
+                        ConstantChanged(converted) => {
+                            let diagnostic =
+                                builder.build_constant_conversion(param.span, &out_ty, converted);
+                            AggregateResult::new_rec(out_ty, diagnostic)
+                        }
                         FromVoid => AggregateResult::new_err(builder.build_void_used(&arg)),
                         ToArray | FromArray => unreachable!(
                             "ICE: Array should have been converted to a pointer by now"
@@ -373,13 +387,19 @@ fn variable_ident(
 
     let (expr, ty, is_const) = if let Some((id, ty)) = scope.vars.reference_mut(&idt.data) {
         // init checks are disabled for arrays since we can't check element by element (yet)
+        // These are replaced by the control flow analysis when the whole IR could be built
         if !will_init
             && !ty.initialized
             && !matches!(ty.ty, CType::Aggregate(ctype::Aggregate::Array(_)))
         {
-            res.add_rec_diagnostic(DiagnosticBuilder::new(idt.span).build_usign_uninit(&idt.data));
-        } else if will_init {
+            res.add_rec_diagnostic(
+                DiagnosticBuilder::new(idt.span).build_usign_uninit(&idt.data, ty.ident_span),
+            );
+        }
+        if will_init {
             ty.initialized = true;
+        } else {
+            ty.is_used = true;
         }
         if needs_address {
             ty.needs_address = true;
@@ -392,8 +412,12 @@ fn variable_ident(
             global_var.is_const,
         )
     } else {
+        let in_scope = scope
+            .vars
+            .names()
+            .chain(scope.global.vars.keys().map(String::as_str));
         return AggregateResult::new_err(
-            DiagnosticBuilder::new(idt.span).build_undeclared_ident(&idt.data),
+            DiagnosticBuilder::new(idt.span).build_undeclared_ident(&idt.data, in_scope),
         );
     };
 
@@ -415,19 +439,20 @@ fn function_ident<'a, 'b, 'c, 'g: 'a>(
         .get_key_value(&ident_node.data)
         .map(|(k, v)| AggregateResult::new_ok((k.as_str(), v)))
         .unwrap_or_else(|| {
+            let in_scope = scope.global.functions.keys().map(String::as_str);
             AggregateResult::new_err(
-                DiagnosticBuilder::new(ident_node.span).build_undeclared_ident(&ident_node.data),
+                DiagnosticBuilder::new(ident_node.span)
+                    .build_undeclared_ident(&ident_node.data, in_scope),
             )
         })
 }
 
 pub fn literal(lit: &ast::LiteralNode, settings: &Settings) -> AggregateResult<ExprNode> {
     use ctype::Arithmetic::*;
-    // TODO these need to change if we ever support sufixes
     let (value, pos_types) = match &lit.data {
-        ast::Literal::Dec(i) => (*i, &[SignedInt, SignedLongInt, UnsignedLongInt]),
+        ast::Literal::Dec(i) => (*i, ctype::Arithmetic::int_constant_types(true)),
         ast::Literal::Hex(i) | ast::Literal::Octal(i) => {
-            (*i, &[SignedInt, UnsignedInt, SignedLongInt])
+            (*i, ctype::Arithmetic::int_constant_types(false))
         }
         ast::Literal::Char(i) => {
             return AggregateResult::new_ok(ExprNode {
@@ -518,15 +543,18 @@ pub fn assign(
 
     use super::type_checking::AssignCheckResult::*;
     let builder = DiagnosticBuilder::new(op_span);
-    match check_assign(&to.ty, &from.ty, settings) {
+    match check_assign_expr(&to.ty, &from, settings) {
         Ok => {}
         Lossy => res.add_rec_diagnostic(builder.build_implicit_lossy_assign(&from, &to, false)),
         SignChange => res.add_rec_diagnostic(builder.build_implicit_lossy_assign(&from, &to, true)),
         Incompatible => res.add_rec_diagnostic(builder.build_incompatible_assign(&from, &to)),
-        LossOfConst => res.add_rec_diagnostic(builder.build_assign_const_loss(from.span, to.span)),
+        LossOfConst => res.add_rec_diagnostic(builder.build_assign_const_loss(&from, &to)),
         PointerAndInt => res.add_rec_diagnostic(builder.build_incompatible_assign(&from, &to)),
         PointerAndFloat => res.add_err(builder.build_incompatible_assign(&from, &to)),
         ToArray => res.add_err(builder.build_assign_to_array(&to)),
+        ConstantChanged(converted) => res.add_rec_diagnostic(
+            DiagnosticBuilder::new(from.span).build_constant_conversion(to.span, &to.ty, converted),
+        ),
         FromVoid => res.add_err(builder.build_void_used(&from)),
         FromArray => unreachable!("ICE: Array should have been converted to a pointer by now"),
         ToVoid => unreachable!("ICE: Lvalue with void type should not exist"),
@@ -824,6 +852,51 @@ impl BinaryBuilder<'_> {
         self.build(rule, op.long_name(), |l, r| Expr::Binary(l, op, r))
     }
 
+    /// Like [`Self::bin_op`], but a division by a constant zero is reported.
+    fn div_op<R>(self, rule: R, op: BinaryOp) -> AggregateResult<ExprNode>
+    where
+        R: TypeRuleBin,
+    {
+        let is_zero = match self.right.expr {
+            Expr::Constant(ir::Constant::Integer(value)) => value == 0,
+            Expr::Constant(ir::Constant::Float(value)) => value == 0.0,
+            _ => false,
+        };
+        let builder = DiagnosticBuilder::new(self.right.span);
+        let is_modulo = op == BinaryOp::Rem;
+
+        let mut res = self.bin_op(rule, op);
+        if is_zero {
+            res.add_rec_diagnostic(builder.build_division_by_zero(is_modulo));
+        }
+        res
+    }
+
+    /// Like [`Self::bin_op`], but a constant shift count that is negative or not less than the
+    /// width of the promoted left operand (3.3.7) is reported.
+    fn shift_op(self, op: BinaryOp) -> AggregateResult<ExprNode> {
+        let diagnostic = match (&self.left.ty, &self.right.expr) {
+            (
+                CType::Scalar(ctype::Scalar::Arithmetic(left_ty)),
+                Expr::Constant(ir::Constant::Integer(count)),
+            ) if left_ty.is_integral() => {
+                let left_ty = left_ty.promote(self.settings);
+                let bits = left_ty.size_in_bits(self.settings);
+                (*count < 0 || *count >= bits as i128).then(|| {
+                    DiagnosticBuilder::new(self.right.span)
+                        .build_shift_count_overflow(*count, left_ty, bits)
+                })
+            }
+            _ => None,
+        };
+
+        let mut res = self.bin_op(UsualArithConversions::only_int(), op);
+        if let Some(diagnostic) = diagnostic {
+            res.add_rec_diagnostic(diagnostic);
+        }
+        res
+    }
+
     /// See [`BinaryOp::Add`]
     fn add(self, from_array_subscript: bool) -> AggregateResult<ExprNode> {
         let rule = UsualArithConversions::new().or(PointerInteger::new());
@@ -72,7 +72,8 @@ fn add_global_var(
     declaration_type(&decl.type_name, &decl.array_parts)
         .zip(AggregateResult::transpose_from(
             decl.initializer.as_ref().map(|(span, expr_node)| {
-                extract_global_var_initializer(expr_node, settings).map(|con| (*span, con))
+                extract_global_var_initializer(expr_node, settings)
+                    .map(|con| (*span, expr_node.span, con))
             }),
         ))
         .and_then(
@@ -81,11 +82,21 @@ fn add_global_var(
                     DiagnosticBuilder::new(decl.type_name.span).build_void_vars(),
                 ),
                 _ => {
-                    let res = AggregateResult::transpose_from(
-                        constant.map(|(span, constant)| check_constant_init(constant, &ty, span)),
-                    );
+                    let res = AggregateResult::transpose_from(constant.map(
+                        |(span, value_span, constant)| {
+                            convert_constant_init(
+                                constant,
+                                &ty,
+                                value_span,
+                                decl.type_name.span,
+                                settings,
+                            )
+                            .and_then(|constant| check_constant_init(constant, &ty, span))
+                        },
+                    ));
                     res.map(|constant| ir::GlobalVarNode {
                         original_span: ext_decl.span,
+                        ident_span: decl.ident.span,
                         comments: ext_decl.comments.map(String::from),
                         ty,
                         is_const,
@@ -103,6 +114,42 @@ fn add_global_var(
         })
 }
 
+/// Converts a constant that doesn't fit in the integer type of the global variable it
+/// initializes, the same way as an implicit conversion at runtime.
+fn convert_constant_init(
+    constant: ir::Constant,
+    to_ty: &CType,
+    value_span: Span,
+    type_span: Span,
+    settings: &Settings,
+) -> AggregateResult<ir::Constant> {
+    let to_int = match to_ty {
+        CType::Scalar(ir::ctype::Scalar::Arithmetic(a)) if a.is_integral() => a,
+        _ => return AggregateResult::new_ok(constant),
+    };
+    let range = to_int.int_range(settings);
+    let builder = DiagnosticBuilder::new(value_span);
+    match constant {
+        ir::Constant::Integer(value) if !range.contains(&value) => {
+            let converted = to_int.wrap_int(value, settings);
+            AggregateResult::new_rec(
+                ir::Constant::Integer(converted),
+                builder.build_constant_conversion(type_span, to_ty, Some(converted)),
+            )
+        }
+        ir::Constant::Float(value)
+            if value.trunc() < *range.start() as f64
+                || value.trunc() >= (*range.end() + 1) as f64 =>
+        {
+            AggregateResult::new_rec(
+                constant,
+                builder.build_constant_conversion(type_span, to_ty, None),
+            )
+        }
+        _ => AggregateResult::new_ok(constant),
+    }
+}
+
 fn check_constant_init(
     constant: ir::Constant,
     to_ty: &CType,
@@ -181,6 +228,23 @@ fn extract_global_var_initializer(
                 _ => unreachable!("ICE: ast literals can only be mapped to ir constants"),
             })
         }
+        // Folded constants keep their type with a cast
+        ast::Expression::Cast(type_name, inner)
+            if matches!(inner.data, ast::Expression::Literal(_)) =>
+        {
+            let ty = CType::from_ast_type(&type_name.unqualified.data);
+            extract_global_var_initializer(inner, settings).and_then(|constant| {
+                cast_constant(constant, &ty, settings).map_or_else(
+                    || {
+                        AggregateResult::new_err(
+                            DiagnosticBuilder::new(expr_node.span)
+                                .build_non_const_global_initializer(),
+                        )
+                    },
+                    AggregateResult::new_ok,
+                )
+            })
+        }
         // TODO: allow more constant expressions than only literals
         _ => AggregateResult::new_err(
             DiagnosticBuilder::new(expr_node.span).build_non_const_global_initializer(),
@@ -188,6 +252,28 @@ fn extract_global_var_initializer(
     }
 }
 
+/// Explicitly casts a constant to an arithmetic type, [`None`] if the cast can't be done at
+/// compile time.
+fn cast_constant(constant: ir::Constant, ty: &CType, settings: &Settings) -> Option<ir::Constant> {
+    let CType::Scalar(ir::ctype::Scalar::Arithmetic(a)) = ty else {
+        return None;
+    };
+    match constant {
+        ir::Constant::Integer(value) if a.is_integral() => {
+            Some(ir::Constant::Integer(a.wrap_int(value, settings)))
+        }
+        ir::Constant::Integer(value) => Some(ir::Constant::Float(value as f64)),
+        ir::Constant::Float(value) if a.is_floating() => Some(ir::Constant::Float(value)),
+        ir::Constant::Float(value) => {
+            let range = a.int_range(settings);
+            let truncated = value.trunc();
+            (truncated >= *range.start() as f64 && truncated < (*range.end() + 1) as f64)
+                .then_some(ir::Constant::Integer(truncated as i128))
+        }
+        ir::Constant::String(_) => None,
+    }
+}
+
 /// Merge of [`ast::FunctionDeclaration`] and [`ast::FunctionDefinition`].
 #[derive(Debug, Clone)]
 struct AstFunction<'a> {
@@ -240,6 +326,8 @@ fn add_function(
                         .get(ident)
                         .map(|f| f.original_span)
                         .unwrap_or(function.prototype_span),
+                    // A function is only redefined by its definition.
+                    ident_span: function.ident.span,
                     comments: function.comments.map(String::from),
                     return_type,
                     params,
@@ -279,11 +367,14 @@ pub fn function_params(
                         .map(|ident| {
                             let item = VariableItem {
                                 original_span: param.span,
+                                ident_span: ident.span,
                                 ty: ty.clone(),
                                 is_const,
                                 needs_address,
                                 // params will be initialized by the arguments passsed to a function call
                                 initialized: true,
+                                is_used: false,
+                                last_assign: None,
                             };
                             match scope.vars.declare(ident.data.clone(), item) {
                                 Ok(id) => AggregateResult::new_ok(Some(id)),
@@ -15,7 +15,7 @@ use crate::{
 
 use super::{
     expr,
-    type_checking::{check_assign, AnyScaler, CheckUnErr, PromoteArith, TypeRuleUn},
+    type_checking::{check_assign_expr, AnyScaler, CheckUnErr, PromoteArith, TypeRuleUn},
     util::{extract_literal_int, DeclarationType, FunctionScope, LiteralExtractErr},
 };
 
@@ -147,7 +147,7 @@ fn return_statement(
 
             let builder = DiagnosticBuilder::new(span);
             use super::type_checking::AssignCheckResult::*;
-            match check_assign(return_type, &expr.ty, settings) {
+            match check_assign_expr(return_type, &expr, settings) {
                 Ok => {}
                 Lossy => res.add_rec_diagnostic(builder.build_implicit_lossy_return(
                     &expr,
@@ -172,6 +172,13 @@ fn return_statement(
                     return_type_span,
                     return_type,
                 )),
+                ConstantChanged(converted) => res.add_rec_diagnostic(
+                    DiagnosticBuilder::new(expr.span).build_constant_conversion(
+                        return_type_span,
+                        return_type,
+                        converted,
+                    ),
+                ),
                 FromVoid => res.add_err(builder.build_void_used(&expr)),
                 ToVoid => {
                     res.add_err(
@@ -385,10 +392,13 @@ fn variable_declaration(
              }| {
                 let item = VariableItem {
                     original_span: span,
+                    ident_span: decl.ident.span,
                     ty: ty.clone(),
                     is_const,
                     needs_address,
                     initialized: decl.initializer.is_some(),
+                    is_used: false,
+                    last_assign: decl.initializer.as_ref().map(|_| span),
                 };
                 match scope.vars.declare(decl.ident.data.clone(), item) {
                     Ok(id) => AggregateResult::new_ok(LvalueExprNode {
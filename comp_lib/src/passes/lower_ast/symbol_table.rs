@@ -108,6 +108,16 @@ impl<I> ScopedHandle<'_, I> {
             })
     }
 
+    /// Same as [`Table::get_mut`] on the root table.
+    pub fn get_mut(&mut self, id: ItemId) -> &mut I {
+        self.root_table.table.get_mut(id)
+    }
+
+    /// The names of the items in this scope and all outer scopes.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.root_table.idents.iter().map(|(name, _)| name.as_str())
+    }
+
     /// Same rules as [`reference`] but gived a `&mut I`
     pub fn reference_mut(&mut self, name: &str) -> Option<(ItemId, &mut I)> {
         self.root_table
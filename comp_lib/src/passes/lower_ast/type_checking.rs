@@ -1,7 +1,10 @@
 use crate::{
     compile::Target,
     diagnostic::builder::TypeCat,
-    ir::ctype::{Aggregate, Arithmetic, CType, ConversionLossyness, Pointer, Scalar},
+    ir::{
+        ctype::{Aggregate, Arithmetic, CType, ConversionLossyness, Pointer, Scalar},
+        Constant, Expr, ExprNode,
+    },
     settings::Settings,
 };
 
@@ -254,7 +257,9 @@ impl TypeRuleBin for PointerInteger {
     ) -> Result<CheckBinOk, CheckBinErr> {
         let pointer_size = match _settings.target {
             Target::X86_64 => CType::Scalar(Scalar::Arithmetic(Arithmetic::UnsignedLongInt)),
-            Target::Mips => CType::Scalar(Scalar::Arithmetic(Arithmetic::UnsignedInt)),
+            Target::Mips | Target::RiscV32 | Target::Wasm32 => {
+                CType::Scalar(Scalar::Arithmetic(Arithmetic::UnsignedInt))
+            }
         };
 
         match (left, right) {
@@ -376,6 +381,47 @@ pub enum AssignCheckResult {
     FromArray,
     FromVoid,
     ToVoid,
+    /// A constant that doesn't fit in the integer type it is converted to, with the converted
+    /// value if the conversion is defined.
+    ConstantChanged(Option<i128>),
+}
+
+/// Like [`check_assign`], but also looks at the value of a constant. A constant that keeps its
+/// value is always fine, and one that doesn't fit in a smaller integer type gives
+/// [`AssignCheckResult::ConstantChanged`].
+pub fn check_assign_expr(to: &CType, from: &ExprNode, settings: &Settings) -> AssignCheckResult {
+    let res = check_assign(to, &from.ty, settings);
+    let CType::Scalar(Scalar::Arithmetic(to_ty)) = to else {
+        return res;
+    };
+    if !matches!(
+        res,
+        AssignCheckResult::Lossy | AssignCheckResult::SignChange
+    ) || !to_ty.is_integral()
+    {
+        return res;
+    }
+
+    let range = to_ty.int_range(settings);
+    match from.expr {
+        Expr::Constant(Constant::Integer(value)) => match res {
+            _ if range.contains(&value) => AssignCheckResult::Ok,
+            AssignCheckResult::SignChange => res,
+            _ => AssignCheckResult::ConstantChanged(Some(to_ty.wrap_int(value, settings))),
+        },
+        Expr::Constant(Constant::Float(value)) => {
+            // The ends of the range are powers of two, so they are exact as floats
+            let truncated = value.trunc();
+            if truncated < *range.start() as f64 || truncated >= (*range.end() + 1) as f64 {
+                AssignCheckResult::ConstantChanged(None)
+            } else if truncated == value {
+                AssignCheckResult::Ok
+            } else {
+                res
+            }
+        }
+        _ => res,
+    }
 }
 
 pub fn check_assign(to: &CType, from: &CType, settings: &Settings) -> AssignCheckResult {
@@ -115,6 +115,8 @@ mod tests {
 
         let settings = Settings {
             target: crate::settings::Target::X86_64,
+            o32_abi: false,
+            bounds_check: false,
         };
         let order = [SignedInt, SignedLongInt, UnsignedLongInt];
 
@@ -1014,32 +1014,29 @@ impl<'a, 'b> AstBuilder<'a, 'b> {
         ctx: &cst::IntegerLiteral,
     ) -> AggregateResult<ast::Literal> {
         use cst::IntegerLiteral;
-        // All the unwraps here should be fine since the grammar ensures that the invariant are met
-        // for the various parse functions
-        AggregateResult::new_ok(match ctx {
+        let (token, radix, literal): (_, _, fn(i128) -> ast::Literal) = match ctx {
             IntegerLiteral::IntegerLiteralOctalContext(literal) => {
-                let value = i128::from_str_radix(literal.value.as_deref().unwrap().get_text(), 8)
-                    .expect("ICE: grammar should have made sure this is a valid octal literal");
-
-                ast::Literal::Octal(value)
+                (literal.value.as_deref().unwrap(), 8, ast::Literal::Octal)
             }
             IntegerLiteral::IntegerLiteralDecimalContext(literal) => {
-                let value =
-                    literal.value.as_deref().unwrap().get_text().parse().expect(
-                        "ICE: grammar should have made sure this is a valid decimal literal",
-                    );
-                ast::Literal::Dec(value)
+                (literal.value.as_deref().unwrap(), 10, ast::Literal::Dec)
             }
             IntegerLiteral::IntegerLiteralHexadecimalContext(literal) => {
-                let literal = literal.value.as_deref().unwrap().get_text();
-                // This is safe since the grammar ensures we start with `0x` or `0X`
-                let literal = &literal[2..];
-                let value = i128::from_str_radix(literal, 16)
-                    .expect("ICE: grammar should have made sure this is a valid hex literal");
-                ast::Literal::Hex(value)
+                (literal.value.as_deref().unwrap(), 16, ast::Literal::Hex)
             }
             IntegerLiteral::Error(ectx) => tree_error(ectx),
-        })
+        };
+        let text = token.get_text();
+        // This is safe since the grammar ensures hexadecimal literals start with `0x` or `0X`
+        let digits = if radix == 16 { &text[2..] } else { text };
+        // The grammar ensures that the literal only contains valid digits, so this can only fail
+        // if the value doesn't even fit in an i128.
+        match i128::from_str_radix(digits, radix) {
+            Ok(value) => AggregateResult::new_ok(literal(value)),
+            Err(_) => AggregateResult::new_err(
+                DiagnosticBuilder::new(extract_span_from_token(token)).build_too_big_constant(text),
+            ),
+        }
     }
 
     fn build_from_identifier(&self, ctx: &cst::Identifier) -> AggregateResult<ast::IdentNode> {
@@ -1,5 +1,11 @@
+pub mod access;
 pub mod const_fold;
 pub mod dead_code_removal;
+pub mod format;
+pub mod format_string;
 pub mod lower_ast;
 pub mod lower_cst;
 pub mod parse;
+pub mod pragma;
+pub mod uninit;
+pub mod unused;
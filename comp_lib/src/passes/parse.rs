@@ -1,16 +1,19 @@
 use crate::{
     cst,
-    diagnostic::{AggregateResult, DiagnosticBuilder},
+    diagnostic::{AggregateResult, Code, Diagnostic, DiagnosticBuilder, DiagnosticKind, Span},
     generated::{
-        clexer::CLexer,
+        clexer::{self, CLexer},
         cparser::{CParser, CParserContextType},
     },
 };
 use antlr_rust::{
-    error_listener::ErrorListener, errors::ANTLRError, recognizer::Recognizer, token::Token,
+    error_listener::ErrorListener,
+    errors::ANTLRError,
+    recognizer::Recognizer,
+    token::{Token, TOKEN_DEFAULT_CHANNEL, TOKEN_EOF},
     token_factory::TokenFactory,
 };
-use std::{cell::RefCell, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, collections::HashSet, ops::Range, rc::Rc};
 
 type LexerInput<'a> = antlr_rust::InputStream<&'a str>;
 type Lexer<'a> = CLexer<'a, LexerInput<'a>>;
@@ -47,6 +50,311 @@ pub fn parse_to_cst(input: &str) -> AggregateResult<cst::Cst<'_>> {
         })
 }
 
+/// The `#pragma` lines of the input with their span, which the parser never sees.
+pub fn pragmas(input: &str) -> Vec<(Span, &str)> {
+    use antlr_rust::token_source::TokenSource;
+
+    let mut lexer = build_lexer(input);
+    let mut pragmas = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        match token.get_token_type() {
+            TOKEN_EOF => break,
+            clexer::PRAGMA => {
+                let start: usize = token.get_start().try_into().unwrap();
+                let end: usize = (token.get_stop() + 1).try_into().unwrap();
+                let text = input[start..end].trim_end();
+                pragmas.push((Span::from(start..start + text.len()), text));
+            }
+            _ => {}
+        }
+    }
+    pragmas
+}
+
+/// The source with every statement or external declaration that has a syntax error replaced by
+/// spaces, so the rest of the source can still be checked. See [`recover`].
+pub struct Recovery<'a> {
+    /// The recovered source, a part is removed without changing the spans of the rest.
+    pub source: Cow<'a, str>,
+    /// The first syntax error of every removed part.
+    errors: Vec<Diagnostic>,
+    /// The identifiers in the removed parts, which are probably declared or assigned there.
+    removed_names: HashSet<String>,
+    /// Where the functions with a removed statement end, the statement may have been the return.
+    removed_in_body: HashSet<usize>,
+}
+
+impl Recovery<'_> {
+    /// Adds the syntax errors to the result of the passes that checked the recovered source, and
+    /// drops the diagnostics that are probably caused by the removed parts. If there are syntax
+    /// errors the result is an error.
+    pub fn apply<T>(self, res: AggregateResult<T>) -> AggregateResult<T> {
+        let mut errors = self.errors.iter().cloned();
+        let Some(first) = errors.next() else {
+            return res;
+        };
+        let mut recovered = AggregateResult::new_err(first);
+        errors.for_each(|error| recovered.add_err(error));
+        for (kind, diagnostic) in res.into_diagnostics() {
+            if self.is_cascading(&diagnostic) {
+                continue;
+            }
+            match kind {
+                DiagnosticKind::Rec => recovered.add_rec_diagnostic(diagnostic),
+                DiagnosticKind::Err => recovered.add_err(diagnostic),
+            }
+        }
+        recovered
+    }
+
+    fn is_cascading(&self, diagnostic: &Diagnostic) -> bool {
+        let span = *diagnostic.main_span();
+        match diagnostic.code() {
+            Code::UndeclaredIdent
+            | Code::UndeclaredFunction
+            | Code::UsingUninit
+            | Code::MaybeUninit
+            | Code::UnusedVariable
+            | Code::UnusedParameter => self
+                .removed_names
+                .contains(&self.source[Range::<usize>::from(span)]),
+            Code::NotAlwaysReturn => self.removed_in_body.contains(&span.start()),
+            _ => false,
+        }
+    }
+}
+
+/// Removes the parts of the source with syntax errors, so the later passes can check the rest and
+/// report all problems in one run. The parser resynchronizes at the `;` or `}` ending a statement
+/// and at the start of a declaration: the innermost statement with an error is removed, or the
+/// external declaration if it isn't inside a function body. If removing a part leaves an error
+/// in the statement around it, that statement is removed too, without reporting the error.
+pub fn recover(input: &str) -> Recovery<'_> {
+    let mut recovery = Recovery {
+        source: Cow::Borrowed(input),
+        errors: Vec::new(),
+        removed_names: HashSet::new(),
+        removed_in_body: HashSet::new(),
+    };
+    let mut removed: Vec<Span> = Vec::new();
+
+    loop {
+        let errors = syntax_errors(&recovery.source);
+        if errors.is_empty() {
+            return recovery;
+        }
+        let tokens = tokens(&recovery.source);
+        let mut units: Vec<Range<usize>> = Vec::new();
+        for error in errors {
+            let (unit, body_end) = enclosing_unit(&tokens, error.main_span().start());
+            let overlaps = units
+                .iter()
+                .any(|other| other.start < unit.end && unit.start < other.end);
+            let span = Span::from(tokens[unit.start].1.start()..tokens[unit.end - 1].1.excl_end());
+            // Errors next to a removed part are caused by removing it
+            if !overlaps && !touches(&recovery.source, span, &removed) {
+                recovery.errors.push(error);
+            }
+            recovery.removed_in_body.extend(body_end);
+            units.push(unit);
+        }
+
+        let mut source = recovery.source.into_owned();
+        for unit in units {
+            let span = Span::from(tokens[unit.start].1.start()..tokens[unit.end - 1].1.excl_end());
+            let names = tokens[unit]
+                .iter()
+                .filter(|(token_type, _)| *token_type == clexer::IDENT)
+                .map(|(_, span)| source[Range::<usize>::from(*span)].to_owned());
+            recovery.removed_names.extend(names);
+            let blank: String = source[Range::<usize>::from(span)]
+                .chars()
+                .map(|c| match c {
+                    '\n' | '\r' => c.to_string(),
+                    c => " ".repeat(c.len_utf8()),
+                })
+                .collect();
+            source.replace_range(Range::<usize>::from(span), &blank);
+            removed.push(span);
+        }
+        recovery.source = Cow::Owned(source);
+    }
+}
+
+fn syntax_errors(input: &str) -> Vec<Diagnostic> {
+    let (_, parser, error_listener) = parse(input);
+    std::mem::drop(parser);
+
+    Rc::try_unwrap(error_listener.0)
+        .expect("ICE: All references to the error_listener should be dropped by now")
+        .into_inner()
+        .into_diagnostics()
+        .map(|(_, diagnostic)| diagnostic)
+        .collect()
+}
+
+/// The types and spans of the tokens the parser sees.
+fn tokens(input: &str) -> Vec<(isize, Span)> {
+    use antlr_rust::token_source::TokenSource;
+
+    let mut lexer = build_lexer(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.get_token_type() == TOKEN_EOF {
+            return tokens;
+        }
+        if token.get_channel() == TOKEN_DEFAULT_CHANNEL {
+            let start: usize = token.get_start().try_into().unwrap();
+            let end: usize = (token.get_stop() + 1).try_into().unwrap();
+            tokens.push((token.get_token_type(), Span::from(start..end)));
+        }
+    }
+}
+
+/// The tokens of the statement or external declaration with the error at `offset`, and the
+/// offset of the `}` ending the function body around it, if any.
+fn enclosing_unit(tokens: &[(isize, Span)], offset: usize) -> (Range<usize>, Option<usize>) {
+    let mut error = tokens.partition_point(|(_, span)| span.start() < offset);
+
+    // The braces that are still open at the error, the innermost last
+    let mut blocks = Vec::new();
+    for (i, (token_type, _)) in tokens[..error].iter().enumerate() {
+        match *token_type {
+            clexer::BRACE_LEFT => blocks.push(i),
+            clexer::BRACE_RIGHT => {
+                blocks.pop();
+            }
+            _ => {}
+        }
+    }
+    let body_end = blocks
+        .first()
+        .map(|open| matching_brace(tokens, *open))
+        .filter(|close| *close < tokens.len())
+        .map(|close| tokens[close].1.start());
+
+    // A block without statements left is removed as a whole in the block around it
+    while let Some(open) = blocks.pop() {
+        let close = matching_brace(tokens, open);
+        if let Some(unit) = unit_at(tokens, open + 1..close, error) {
+            return (unit, body_end);
+        }
+        error = open;
+    }
+    let unit = unit_at(tokens, 0..tokens.len(), error)
+        .expect("ICE: A syntax error should have a token to remove");
+    (unit, None)
+}
+
+fn matching_brace(tokens: &[(isize, Span)], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, (token_type, _)) in tokens.iter().enumerate().skip(open) {
+        match *token_type {
+            clexer::BRACE_LEFT => depth += 1,
+            clexer::BRACE_RIGHT => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// The unit in `range` containing `error`, or the last one before it if `error` ends the range.
+fn unit_at(tokens: &[(isize, Span)], range: Range<usize>, error: usize) -> Option<Range<usize>> {
+    let units = split_units(tokens, range);
+    let containing = units.iter().position(|unit| unit.contains(&error));
+    match containing {
+        Some(i) => Some(units[i].clone()),
+        None => units.into_iter().filter(|unit| unit.end <= error).last(),
+    }
+}
+
+/// Splits the tokens in statements or external declarations: a unit ends with a `;` or with the
+/// `}` of its block, and a type that isn't part of the current declaration starts a new one.
+fn split_units(tokens: &[(isize, Span)], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut units = Vec::new();
+    let mut start = range.start;
+    let (mut braces, mut parens) = (0usize, 0usize);
+
+    for i in range.clone() {
+        let token_type = tokens[i].0;
+        if braces == 0
+            && parens == 0
+            && i > start
+            && is_type_token(token_type)
+            && !is_type_token(tokens[i - 1].0)
+            && tokens[i - 1].0 != clexer::STAR
+        {
+            units.push(start..i);
+            start = i;
+        }
+        match token_type {
+            clexer::INCLUDE if braces == 0 => {
+                if start < i {
+                    units.push(start..i);
+                }
+                units.push(i..i + 1);
+                start = i + 1;
+            }
+            clexer::PAREN_LEFT => parens += 1,
+            clexer::PAREN_RIGHT => parens = parens.saturating_sub(1),
+            // A block can't be inside parentheses, so a `(` before it is missing its `)`
+            clexer::BRACE_LEFT => {
+                braces += 1;
+                parens = 0;
+            }
+            clexer::BRACE_RIGHT => {
+                braces = braces.saturating_sub(1);
+                if braces == 0 {
+                    units.push(start..i + 1);
+                    start = i + 1;
+                }
+            }
+            clexer::SEMICOLON if braces == 0 && parens == 0 => {
+                units.push(start..i + 1);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < range.end {
+        units.push(start..range.end);
+    }
+    units
+}
+
+fn is_type_token(token_type: isize) -> bool {
+    matches!(
+        token_type,
+        clexer::KW_CONST
+            | clexer::KW_SIGNED
+            | clexer::KW_UNSIGNED
+            | clexer::KW_LONG
+            | clexer::KW_SHORT
+            | clexer::KW_CHAR
+            | clexer::KW_INT
+            | clexer::KW_FLOAT
+            | clexer::KW_DOUBLE
+            | clexer::KW_VOID
+    )
+}
+
+/// Whether only whitespace separates the span from a removed part.
+fn touches(source: &str, span: Span, removed: &[Span]) -> bool {
+    let start = source[..span.start()].trim_end().len();
+    let after = &source[span.excl_end()..];
+    let end = span.excl_end() + after.len() - after.trim_start().len();
+    removed
+        .iter()
+        .any(|part| part.start() <= end && start <= part.excl_end())
+}
+
 fn build_lexer(input: &str) -> Lexer {
     let input = LexerInput::new(input);
     let mut lexer = Lexer::new(input);
@@ -0,0 +1,94 @@
+//! Handles the `#pragma comp diagnostic` lines, which change the levels of the diagnostics for the
+//! rest of the source, or until the levels are restored:
+//!
+//! ```c
+//! #pragma comp diagnostic push
+//! #pragma comp diagnostic ignored "-Wconversion"
+//! char c = 1000;
+//! #pragma comp diagnostic pop
+//! ```
+//!
+//! Besides `ignored` there is `warning` and `error`, which take the same names as the `-W` flags.
+//! Other pragmas are ignored, like a C compiler does with pragmas it doesn't know.
+
+use crate::{
+    diagnostic::{levels, AggregateResult, DiagnosticBuilder, DiagnosticLevels},
+    passes::parse,
+};
+
+/// The levels of the diagnostics in every region of the source.
+pub struct LevelRegions {
+    /// The levels from an offset on, sorted by offset and starting at offset 0.
+    regions: Vec<(usize, DiagnosticLevels)>,
+}
+
+impl LevelRegions {
+    /// The levels of a diagnostic with its main span starting at `offset`.
+    pub fn levels_at(&self, offset: usize) -> &DiagnosticLevels {
+        let index = self.regions.partition_point(|(start, _)| *start <= offset);
+        &self.regions[index - 1].1
+    }
+}
+
+/// Splits the source in regions with the levels in effect there, starting from `levels`.
+pub fn level_regions(source: &str, levels: &DiagnosticLevels) -> AggregateResult<LevelRegions> {
+    let mut res = AggregateResult::new_ok(());
+    let mut current = levels.clone();
+    let mut stack = Vec::new();
+    let mut regions = Vec::new();
+
+    for (span, pragma) in parse::pragmas(source) {
+        let mut words = pragma.trim_start_matches("#pragma").split_whitespace();
+        if words.next() != Some("comp") {
+            continue;
+        }
+        let invalid = |reason: &str| DiagnosticBuilder::new(span).build_invalid_pragma(reason);
+
+        if words.next() != Some("diagnostic") {
+            res.add_rec_diagnostic(invalid("expected `diagnostic` after `comp`"));
+            continue;
+        }
+        let kind = words.next();
+        match kind {
+            Some("push") => stack.push(current.clone()),
+            Some("pop") => match stack.pop() {
+                Some(levels) => current = levels,
+                None => {
+                    res.add_rec_diagnostic(invalid("`pop` without a matching `push`"));
+                    continue;
+                }
+            },
+            Some("ignored" | "warning" | "error") => {
+                let Some(flag) = words.next() else {
+                    res.add_rec_diagnostic(invalid("expected an option like \"-Wconversion\""));
+                    continue;
+                };
+                let name = flag.trim_matches('"').trim_start_matches("-W");
+                let Some(codes) = levels::codes_by_name(name) else {
+                    res.add_rec_diagnostic(invalid(&format!("unknown warning option {flag}")));
+                    continue;
+                };
+                for code in codes {
+                    match kind {
+                        Some("ignored") => current.enable(code, false),
+                        Some("warning") => {
+                            current.enable(code, true);
+                            current.set_error(code, false);
+                        }
+                        _ => current.set_error(code, true),
+                    }
+                }
+            }
+            _ => {
+                res.add_rec_diagnostic(invalid(
+                    "expected `push`, `pop`, `ignored`, `warning` or `error`",
+                ));
+                continue;
+            }
+        }
+        regions.push((span.excl_end(), current.clone()));
+    }
+
+    regions.insert(0, (0, levels.clone()));
+    res.map(|()| LevelRegions { regions })
+}
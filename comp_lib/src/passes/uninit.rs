@@ -0,0 +1,360 @@
+//! Finds the uses of local variables before they are initialized, following the control flow of
+//! every function: if/else, loops, switch fallthrough, `break`, `continue` and `return`.
+//!
+//! A use is reported as uninitialized if the variable isn't initialized on any path to it, or as
+//! maybe uninitialized if it's only initialized on some paths. Taking the address of a variable,
+//! e.g. to pass it to `scanf`, counts as initializing it, since it can be written through the
+//! pointer. Arrays aren't checked, since their elements are initialized one by one.
+
+use std::collections::{HashMap, HashSet, LinkedList};
+
+use crate::{
+    diagnostic::{Diagnostic, DiagnosticBuilder, Span},
+    ir::{
+        ctype::CType,
+        table::{ItemId, Table, VariableItem},
+        BlockNode, Expr, ExprNode, FunctionNode, IfStmtNode, LoopStmtNode, LvalueExpr,
+        LvalueExprNode, Root, Stmt, StmtNode, SwitchStmtCase, SwitchStmtNode,
+    },
+};
+
+pub fn find_uninit_uses(root: &Root, source: &str) -> LinkedList<Diagnostic> {
+    let mut diagnostics: Vec<_> = root
+        .functions
+        .values()
+        .flat_map(|function| function_uses(function, source))
+        .collect();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.main_span().start());
+    diagnostics.into_iter().collect()
+}
+
+fn function_uses(function: &FunctionNode, source: &str) -> Vec<Diagnostic> {
+    let Some(body) = &function.body else {
+        return Vec::new();
+    };
+    let params: HashSet<ItemId> = function.params.iter().filter_map(|p| p.ident).collect();
+    let locals = function
+        .table
+        .iter()
+        .filter(|(id, item)| !params.contains(id) && !matches!(item.ty, CType::Aggregate(_)))
+        .map(|(id, _)| (id, Uninit::default()));
+
+    let mut analyzer = Analyzer::default();
+    analyzer.block(body, Some(State(locals.collect())));
+
+    analyzer
+        .uses
+        .into_iter()
+        .map(|(span, (id, uninit))| uninit.build(span, &function.table, id, source))
+        .collect()
+}
+
+/// A branch taken on a path, e.g. the span of a condition with `if this condition is false`.
+type Branch = (Span, &'static str);
+
+/// A variable that isn't initialized on at least one path.
+#[derive(Debug, Clone, PartialEq)]
+struct Uninit {
+    /// Not initialized on every path, instead of only on some.
+    always: bool,
+    /// The branches taken on a path where the variable isn't initialized.
+    path: Vec<Branch>,
+}
+
+impl Default for Uninit {
+    fn default() -> Self {
+        Self {
+            always: true,
+            path: Vec::new(),
+        }
+    }
+}
+
+impl Uninit {
+    fn build(
+        mut self,
+        span: Span,
+        table: &Table<VariableItem>,
+        id: ItemId,
+        source: &str,
+    ) -> Diagnostic {
+        let declaration = table.get(id).ident_span;
+        let name = &source[std::ops::Range::<usize>::from(declaration)];
+        let builder = DiagnosticBuilder::new(span);
+        if self.always {
+            builder.build_usign_uninit(name, declaration)
+        } else {
+            self.path.sort_by_key(|(span, _)| span.start());
+            builder.build_maybe_uninit(name, declaration, &self.path)
+        }
+    }
+}
+
+/// The variables that aren't initialized on every path to a point in a function.
+#[derive(Debug, Clone, Default)]
+struct State(HashMap<ItemId, Uninit>);
+
+impl State {
+    /// Whether both states have the same variables that are (always) uninitialized, regardless of
+    /// the paths.
+    fn same_vars(&self, other: &State) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|(id, uninit)| {
+                other
+                    .0
+                    .get(id)
+                    .is_some_and(|other| other.always == uninit.always)
+            })
+    }
+}
+
+/// The state at a point, `None` if the point can't be reached.
+type Flow = Option<State>;
+
+/// Joins the flows of paths that come together, a variable that is only uninitialized on some of
+/// them gets the branch of the first such path.
+fn join(paths: impl IntoIterator<Item = (Flow, Option<Branch>)>) -> Flow {
+    let reachable: Vec<_> = paths
+        .into_iter()
+        .filter_map(|(flow, branch)| flow.map(|state| (state, branch)))
+        .collect();
+    if reachable.is_empty() {
+        return None;
+    }
+
+    let mut joined = State::default();
+    for (state, branch) in &reachable {
+        for (id, uninit) in &state.0 {
+            if joined.0.contains_key(id) {
+                continue;
+            }
+            let uninits = reachable.iter().map(|(other, _)| other.0.get(id));
+            let on_every_path = uninits.clone().all(|other| other.is_some());
+            let mut uninit = uninit.clone();
+            uninit.always = uninits
+                .into_iter()
+                .all(|other| other.is_some_and(|u| u.always));
+            if !on_every_path {
+                uninit.path.extend(*branch);
+            }
+            joined.0.insert(*id, uninit);
+        }
+    }
+    Some(joined)
+}
+
+#[derive(Default)]
+struct Analyzer {
+    /// The uses of variables that may be uninitialized, by the span of the use.
+    uses: HashMap<Span, (ItemId, Uninit)>,
+    /// The flows at the `break`s of every loop or switch around the current statement.
+    breaks: Vec<Vec<(Flow, Option<Branch>)>>,
+    /// The flows at the `continue`s of every loop around the current statement.
+    continues: Vec<Vec<(Flow, Option<Branch>)>>,
+}
+
+impl Analyzer {
+    fn block(&mut self, block: &BlockNode, mut flow: Flow) -> Flow {
+        for stmt in &block.stmts {
+            flow = self.stmt(stmt, flow);
+        }
+        flow
+    }
+
+    fn stmt(&mut self, stmt: &StmtNode, flow: Flow) -> Flow {
+        let mut state = flow?;
+        match &stmt.stmt {
+            Stmt::Expr(expr) => {
+                self.expr(expr, &mut state);
+                Some(state)
+            }
+            Stmt::IfStmt(if_stmt) => self.if_stmt(if_stmt, state),
+            Stmt::SwitchStmt(switch) => self.switch_stmt(switch, state),
+            Stmt::LoopStmt(loop_stmt) => self.loop_stmt(loop_stmt, state),
+            Stmt::Break => {
+                self.breaks
+                    .last_mut()
+                    .expect("ICE: break outside a loop or switch")
+                    .push((Some(state), Some((stmt.span, "after this `break`"))));
+                None
+            }
+            Stmt::Continue => {
+                self.continues
+                    .last_mut()
+                    .expect("ICE: continue outside a loop")
+                    .push((Some(state), Some((stmt.span, "after this `continue`"))));
+                None
+            }
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value, &mut state);
+                }
+                None
+            }
+        }
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmtNode, mut state: State) -> Flow {
+        self.expr(&stmt.condition, &mut state);
+        let if_end = self.block(&stmt.if_branch, Some(state.clone()));
+        let else_end = match &stmt.else_branch {
+            Some(else_branch) => self.block(else_branch, Some(state)),
+            None => Some(state),
+        };
+        let span = stmt.condition.span;
+        join([
+            (if_end, Some((span, "if this condition is true"))),
+            (else_end, Some((span, "if this condition is false"))),
+        ])
+    }
+
+    fn switch_stmt(&mut self, stmt: &SwitchStmtNode, mut state: State) -> Flow {
+        self.expr(&stmt.expr, &mut state);
+
+        self.breaks.push(Vec::new());
+        let mut fallthrough = None;
+        for case in &stmt.cases {
+            let body = match &case.data {
+                SwitchStmtCase::Case { body, .. } | SwitchStmtCase::Default { body } => body,
+            };
+            let entry = join([
+                (
+                    Some(state.clone()),
+                    Some((case.span, "if this case is taken")),
+                ),
+                (
+                    fallthrough,
+                    Some((case.span, "if this case is fallen into")),
+                ),
+            ]);
+            fallthrough = self.block(body, entry);
+        }
+        let breaks = self.breaks.pop().expect("ICE: the breaks were pushed");
+
+        let no_match = (!stmt.has_default).then_some(state);
+        join(
+            [
+                (fallthrough, None),
+                (no_match, Some((stmt.expr.span, "if no case matches"))),
+            ]
+            .into_iter()
+            .chain(breaks),
+        )
+    }
+
+    fn loop_stmt(&mut self, stmt: &LoopStmtNode, entry: State) -> Flow {
+        let condition_span = stmt.condition.as_ref().map_or(stmt.span, |c| c.span);
+
+        // Runs the body until the state at the start of an iteration doesn't change anymore, the
+        // uses are overwritten by every run so they end up with the final state.
+        let mut start = entry.clone();
+        loop {
+            let mut state = start.clone();
+            if let Some(condition) = &stmt.condition {
+                self.expr(condition, &mut state);
+            }
+
+            self.breaks.push(Vec::new());
+            self.continues.push(Vec::new());
+            let end = self.block(&stmt.body, Some(state.clone()));
+            let breaks = self.breaks.pop().expect("ICE: the breaks were pushed");
+            let continues = self
+                .continues
+                .pop()
+                .expect("ICE: the continues were pushed");
+
+            let mut next = join([(end, None)].into_iter().chain(continues));
+            if let (Some(continuation), Some(next)) = (&stmt.continuation, &mut next) {
+                self.expr(continuation, next);
+            }
+            let next = join([
+                (
+                    Some(entry.clone()),
+                    Some((condition_span, "on the first iteration of this loop")),
+                ),
+                (next, None),
+            ])
+            .expect("ICE: the entry of a loop is reachable");
+
+            if next.same_vars(&start) {
+                let exit = stmt.condition.as_ref().map(|_| state);
+                return join(
+                    [(
+                        exit,
+                        Some((condition_span, "if this loop condition is false")),
+                    )]
+                    .into_iter()
+                    .chain(breaks),
+                );
+            }
+            start = next;
+        }
+    }
+
+    fn expr(&mut self, expr: &ExprNode, state: &mut State) {
+        match &expr.expr {
+            Expr::LvalueDeref(lvalue) => self.read(lvalue, state),
+            Expr::Constant(_) => {}
+            Expr::FunctionCall(_, args) => {
+                for arg in args {
+                    self.expr(arg, state);
+                }
+            }
+            Expr::PostfixInc(lvalue)
+            | Expr::PostfixDec(lvalue)
+            | Expr::PrefixInc(lvalue)
+            | Expr::PrefixDec(lvalue) => {
+                self.read(lvalue, state);
+                self.write(lvalue, state);
+            }
+            Expr::Reference(lvalue) => self.write(lvalue, state),
+            Expr::UnaryArith(_, inner) | Expr::Cast(inner) => self.expr(inner, state),
+            Expr::Binary(left, _, right) | Expr::Relation(left, _, right) => {
+                self.expr(left, state);
+                self.expr(right, state);
+            }
+            Expr::LogicalAnd(left, right) | Expr::LogicalOr(left, right) => {
+                self.expr(left, state);
+                let mut evaluated = state.clone();
+                self.expr(right, &mut evaluated);
+                *state = join([
+                    (
+                        Some(std::mem::take(state)),
+                        Some((right.span, "if this isn't evaluated")),
+                    ),
+                    (Some(evaluated), None),
+                ])
+                .expect("ICE: both operands are reachable");
+            }
+            Expr::Assign(to, value) => {
+                self.expr(value, state);
+                self.write(to, state);
+            }
+        }
+    }
+
+    fn read(&mut self, lvalue: &LvalueExprNode, state: &mut State) {
+        match &lvalue.expr {
+            LvalueExpr::Ident(id) => match state.0.get(id) {
+                Some(uninit) => {
+                    self.uses.insert(lvalue.span, (*id, uninit.clone()));
+                }
+                None => {
+                    self.uses.remove(&lvalue.span);
+                }
+            },
+            LvalueExpr::GlobalIdent(_) => {}
+            LvalueExpr::Dereference(pointer) => self.expr(pointer, state),
+        }
+    }
+
+    fn write(&mut self, lvalue: &LvalueExprNode, state: &mut State) {
+        match &lvalue.expr {
+            LvalueExpr::Ident(id) => {
+                state.0.remove(id);
+            }
+            LvalueExpr::GlobalIdent(_) => {}
+            LvalueExpr::Dereference(pointer) => self.expr(pointer, state),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Finds local variables and parameters whose value is never used, using the uses collected in the
+//! [`VariableItem`](crate::ir::table::VariableItem)s while lowering the AST, and expression
+//! statements without any effect.
+
+use std::collections::{HashSet, LinkedList};
+
+use crate::{
+    diagnostic::{Diagnostic, DiagnosticBuilder},
+    ir::{
+        ctype::CType, BlockNode, Expr, ExprNode, FunctionNode, LvalueExpr, RelationOp, Root, Stmt,
+        SwitchStmtCase,
+    },
+};
+
+pub fn find_unused(root: &Root, source: &str) -> LinkedList<Diagnostic> {
+    let mut functions: Vec<_> = root
+        .functions
+        .values()
+        .filter(|function| !function.is_declaration())
+        .collect();
+    functions.sort_by_key(|function| function.original_span.start());
+
+    let mut diagnostics = LinkedList::new();
+    for function in functions {
+        diagnostics.extend(unused_variables(function, source));
+        if let Some(body) = &function.body {
+            no_effect_stmts(body, source, &mut diagnostics);
+        }
+    }
+    diagnostics
+}
+
+fn unused_variables(function: &FunctionNode, source: &str) -> Vec<Diagnostic> {
+    let params: HashSet<_> = function.params.iter().filter_map(|p| p.ident).collect();
+    let mut unused: Vec<_> = function
+        .table
+        .iter()
+        .filter(|(_, item)| !item.is_used)
+        .collect();
+    unused.sort_by_key(|(_, item)| item.ident_span.start());
+    unused
+        .into_iter()
+        .map(|(id, item)| {
+            let name = &source[std::ops::Range::<usize>::from(item.ident_span)];
+            let builder = DiagnosticBuilder::new(item.ident_span);
+            if params.contains(&id) {
+                builder.build_unused_param(name, item.last_assign)
+            } else {
+                builder.build_unused_var(name, item.last_assign)
+            }
+        })
+        .collect()
+}
+
+fn no_effect_stmts(block: &BlockNode, source: &str, diagnostics: &mut LinkedList<Diagnostic>) {
+    for stmt in &block.stmts {
+        match &stmt.stmt {
+            Stmt::Expr(expr) => {
+                // A cast to void is the usual way to ignore a value on purpose
+                if !has_effect(expr) && expr.ty != CType::Void {
+                    diagnostics.push_back(no_effect(expr, source));
+                }
+            }
+            Stmt::IfStmt(if_stmt) => {
+                no_effect_stmts(&if_stmt.if_branch, source, diagnostics);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    no_effect_stmts(else_branch, source, diagnostics);
+                }
+            }
+            Stmt::SwitchStmt(switch) => {
+                for case in &switch.cases {
+                    match &case.data {
+                        SwitchStmtCase::Case { body, .. } | SwitchStmtCase::Default { body } => {
+                            no_effect_stmts(body, source, diagnostics)
+                        }
+                    }
+                }
+            }
+            Stmt::LoopStmt(loop_stmt) => no_effect_stmts(&loop_stmt.body, source, diagnostics),
+            Stmt::Break | Stmt::Continue | Stmt::Return(_) => {}
+        }
+    }
+}
+
+/// A comparison of a variable with `==` was probably meant to be an assignment, so it gets a fix
+/// that replaces the `==` with `=`.
+fn no_effect(expr: &ExprNode, source: &str) -> Diagnostic {
+    let builder = DiagnosticBuilder::new(expr.span);
+    let Expr::Relation(left, RelationOp::Eq, right) = &expr.expr else {
+        return builder.build_no_effect(None);
+    };
+    let is_lvalue = match &left.expr {
+        Expr::LvalueDeref(_) => true,
+        Expr::Cast(inner) => matches!(inner.expr, Expr::LvalueDeref(_)),
+        _ => false,
+    };
+    let between = left.span.excl_end()..right.span.start();
+    let operator = source
+        .get(between.clone())
+        .and_then(|between| between.find("=="))
+        .map(|offset| between.start + offset);
+    match operator {
+        Some(operator) if is_lvalue => {
+            builder.build_no_effect(Some((operator..operator + 2).into()))
+        }
+        _ => builder.build_no_effect(None),
+    }
+}
+
+/// Whether evaluating the expression changes anything, besides giving a value.
+fn has_effect(expr: &ExprNode) -> bool {
+    match &expr.expr {
+        Expr::FunctionCall(..)
+        | Expr::PostfixInc(_)
+        | Expr::PostfixDec(_)
+        | Expr::PrefixInc(_)
+        | Expr::PrefixDec(_)
+        | Expr::Assign(..) => true,
+        Expr::LvalueDeref(lvalue) | Expr::Reference(lvalue) => match &lvalue.expr {
+            LvalueExpr::Dereference(pointer) => has_effect(pointer),
+            LvalueExpr::Ident(_) | LvalueExpr::GlobalIdent(_) => false,
+        },
+        Expr::Constant(_) => false,
+        Expr::UnaryArith(_, inner) | Expr::Cast(inner) => has_effect(inner),
+        Expr::Binary(left, _, right)
+        | Expr::Relation(left, _, right)
+        | Expr::LogicalAnd(left, right)
+        | Expr::LogicalOr(left, right) => has_effect(left) || has_effect(right),
+    }
+}
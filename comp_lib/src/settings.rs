@@ -2,6 +2,8 @@
 pub enum Target {
     X86_64,
     Mips,
+    RiscV32,
+    Wasm32,
 }
 
 impl std::fmt::Display for Target {
@@ -9,6 +11,8 @@ impl std::fmt::Display for Target {
         let name = match self {
             Target::X86_64 => "X86_64",
             Target::Mips => "MIPS",
+            Target::RiscV32 => "RISC-V 32",
+            Target::Wasm32 => "WebAssembly 32",
         };
         write!(f, "{name}")
     }
@@ -17,4 +21,10 @@ impl std::fmt::Display for Target {
 #[derive(Debug, Clone)]
 pub struct Settings {
     pub target: Target,
+    /// Use the standard O32 calling convention instead of passing all arguments on the stack.
+    /// Only used by the MIPS target, the RISC-V and WebAssembly targets don't support it.
+    pub o32_abi: bool,
+    /// Trap at runtime when an array is indexed outside of its bounds. Only used by the MIPS and
+    /// RISC-V targets.
+    pub bounds_check: bool,
 }
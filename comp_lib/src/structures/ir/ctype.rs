@@ -262,7 +262,8 @@ impl Arithmetic {
                 Arithmetic::UnsignedInt => 32,
                 Arithmetic::UnsignedLongInt => 64,
             },
-            Target::Mips => match self {
+            // All are ILP32.
+            Target::Mips | Target::RiscV32 | Target::Wasm32 => match self {
                 Arithmetic::Float => 32,
                 Arithmetic::Double => 64,
                 Arithmetic::LongDouble => 64,
@@ -279,6 +280,38 @@ impl Arithmetic {
         }
     }
 
+    /// The types an integer constant can have, it has the first one that can represent its value
+    /// (3.1.3.2).
+    pub fn int_constant_types(is_decimal: bool) -> &'static [Self] {
+        use Arithmetic::*;
+        // TODO these need to change if we ever support sufixes
+        match is_decimal {
+            true => &[SignedInt, SignedLongInt, UnsignedLongInt],
+            false => &[SignedInt, UnsignedInt, SignedLongInt],
+        }
+    }
+
+    /// The smallest and largest value of an integral type.
+    pub fn int_range(&self, settings: &Settings) -> std::ops::RangeInclusive<i128> {
+        let bits = self.size_in_bits(settings);
+        match self.is_signed() {
+            true => -(1 << (bits - 1))..=(1 << (bits - 1)) - 1,
+            false => 0..=(1 << bits) - 1,
+        }
+    }
+
+    /// Converts an integer to this integral type. A value that doesn't fit wraps around, which is
+    /// defined for unsigned types (3.2.1.2) and what every target does for signed types.
+    pub fn wrap_int(&self, value: i128, settings: &Settings) -> i128 {
+        let bits = self.size_in_bits(settings);
+        let value = value & ((1 << bits) - 1);
+        if self.is_signed() && value >= 1 << (bits - 1) {
+            value - (1 << bits)
+        } else {
+            value
+        }
+    }
+
     pub fn conversion_lossynes_into(
         &self,
         to_ty: &Arithmetic,
@@ -471,6 +504,8 @@ mod test {
         use Arithmetic::*;
         let settings = Settings {
             target: crate::settings::Target::X86_64,
+            o32_abi: false,
+            bounds_check: false,
         };
 
         let test = [
@@ -489,4 +524,24 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn wrap_int_depends_on_target() {
+        use Arithmetic::*;
+        let settings = |target| Settings {
+            target,
+            o32_abi: false,
+            bounds_check: false,
+        };
+        let x86 = settings(crate::settings::Target::X86_64);
+        let mips = settings(crate::settings::Target::Mips);
+
+        assert_eq!(SignedInt.int_range(&x86), -2147483648..=2147483647);
+        assert_eq!(UnsignedChar.int_range(&mips), 0..=255);
+        assert_eq!(SignedInt.wrap_int(2147483648, &mips), -2147483648);
+        assert_eq!(Char.wrap_int(300, &mips), 44);
+        assert_eq!(UnsignedInt.wrap_int(-1, &mips), 4294967295);
+        assert_eq!(SignedLongInt.wrap_int(2147483648, &x86), 2147483648);
+        assert_eq!(SignedLongInt.wrap_int(2147483648, &mips), -2147483648);
+    }
 }
@@ -16,6 +16,8 @@ pub struct Root {
 pub struct GlobalVarNode {
     /// The span where this global variable was declared/defined.
     pub original_span: Span,
+    /// The span of the identifier in the declaration/definition.
+    pub ident_span: Span,
     pub comments: Option<String>,
     /// The type.
     pub ty: CType,
@@ -35,6 +37,9 @@ impl GlobalVarNode {
 pub struct FunctionNode {
     /// The span where this function was first declared or defined.
     pub original_span: Span,
+    /// The span of the identifier in the definition, or in the first declaration if the function
+    /// isn't defined.
+    pub ident_span: Span,
     pub comments: Option<String>,
     pub return_type: CType,
     pub params: Vec<FunctionParamNode>,
@@ -7,6 +7,8 @@ use std::ops::Deref;
 pub struct VariableItem {
     /// The span where this item was defined
     pub original_span: Span,
+    /// The span of the identifier in the definition
+    pub ident_span: Span,
     /// The type
     pub ty: CType,
     /// Defined as const
@@ -14,6 +16,10 @@ pub struct VariableItem {
     /// Whether or not the address of the variable will ever be used
     pub needs_address: bool,
     pub initialized: bool,
+    /// Whether the value of the variable is ever used, taking its address counts as a use
+    pub is_used: bool,
+    /// The span of the last assignment to the variable in the source, including an initializer
+    pub last_assign: Option<Span>,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,68 @@
+use comp_lib::{
+    compile::{compile, CompileOptsBuilder, Target},
+    diagnostic::Code,
+};
+
+/// The contents of the `c` code blocks of a Markdown explanation.
+fn examples(explanation: &str) -> Vec<String> {
+    let mut examples = Vec::new();
+    let mut current: Option<String> = None;
+    for line in explanation.lines() {
+        match current.as_mut() {
+            None if line == "```c" => current = Some(String::new()),
+            None => {}
+            Some(_) if line == "```" => examples.extend(current.take()),
+            Some(example) => {
+                example.push_str(line);
+                example.push('\n');
+            }
+        }
+    }
+    examples
+}
+
+fn codes(source: &str) -> Vec<Code> {
+    let opts = CompileOptsBuilder::new()
+        .target(Target::Mips)
+        .build()
+        .unwrap();
+    let res = compile(source, "example.c", &opts);
+    res.diagnostics()
+        .map(|(_, diagnostic)| *diagnostic.code())
+        .collect()
+}
+
+#[test]
+fn examples_show_their_code() {
+    for code in Code::ALL {
+        let examples = examples(code.explanation());
+        if code == Code::Unspecified {
+            assert!(examples.is_empty());
+            continue;
+        }
+        let [erroneous, corrected] = examples.as_slice() else {
+            panic!("{code:?} should have an erroneous and a corrected example");
+        };
+        assert!(
+            codes(erroneous).contains(&code),
+            "the erroneous example of {code:?} doesn't report it"
+        );
+        assert_eq!(
+            codes(corrected),
+            vec![],
+            "the corrected example of {code:?} isn't clean"
+        );
+    }
+}
+
+#[test]
+fn parse_codes() {
+    assert_eq!("#0011".parse::<Code>().unwrap(), Code::UndeclaredIdent);
+    assert_eq!("0011".parse::<Code>().unwrap(), Code::UndeclaredIdent);
+    assert_eq!(
+        "undeclared-ident".parse::<Code>().unwrap(),
+        Code::UndeclaredIdent
+    );
+    assert!("#ffff".parse::<Code>().is_err());
+    assert!("nope".parse::<Code>().is_err());
+}
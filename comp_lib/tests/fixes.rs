@@ -0,0 +1,160 @@
+use comp_lib::{
+    compile::{compile_to_ir, CompileOptsBuilder},
+    diagnostic::{apply_fixes, Code},
+};
+
+/// Applies the fixes of the only diagnostic with a fix, which must have `code`.
+fn fixed(source: &str, code: Code, is_safe: bool) -> String {
+    let res = compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+    let fixed: Vec<_> = res
+        .diagnostics()
+        .filter(|(_, diagnostic)| !diagnostic.fixes().is_empty())
+        .collect();
+    assert_eq!(fixed.len(), 1, "{fixed:?}");
+    let diagnostic = fixed[0].1;
+    assert_eq!(*diagnostic.code(), code);
+    assert!(diagnostic
+        .fixes()
+        .iter()
+        .all(|fix| fix.is_safe() == is_safe));
+    let (fixed, applied) = apply_fixes(source, diagnostic.fixes());
+    assert_eq!(applied, diagnostic.fixes().len());
+    fixed
+}
+
+#[test]
+fn casts_incompatible_assign() {
+    assert_eq!(
+        fixed(
+            "int main() { int *p = 5 + 1; return 0; }",
+            Code::IncompatibleAssign,
+            false
+        ),
+        "int main() { int *p = (int *)(5 + 1); return 0; }"
+    );
+}
+
+#[test]
+fn casts_away_const() {
+    assert_eq!(
+        fixed(
+            "int main() { const int x = 1; int *p = &x; return 0; }",
+            Code::AssignConstLoss,
+            false
+        ),
+        "int main() { const int x = 1; int *p = (int *)&x; return 0; }"
+    );
+}
+
+#[test]
+fn returns_a_value() {
+    assert_eq!(
+        fixed("int main() { return; }", Code::NoReturnValue, false),
+        "int main() { return 0; }"
+    );
+}
+
+#[test]
+fn suggests_similar_names() {
+    assert_eq!(
+        fixed(
+            "int counter; int main() { int total = 1; return total + countr; }",
+            Code::UndeclaredIdent,
+            false
+        ),
+        "int counter; int main() { int total = 1; return total + counter; }"
+    );
+    let res = compile_to_ir(
+        "int main() { int total = 1; return x; }",
+        &CompileOptsBuilder::new().build().unwrap(),
+    );
+    assert!(res.diagnostics().all(|(_, d)| d.fixes().is_empty()));
+}
+
+#[test]
+fn dereferences_pointers() {
+    assert_eq!(
+        fixed(
+            "int main() { int a[2]; int *p = a; (p + 1) = 2; return 0; }",
+            Code::NeedLvalue,
+            false
+        ),
+        "int main() { int a[2]; int *p = a; *(p + 1) = 2; return 0; }"
+    );
+    assert_eq!(
+        fixed(
+            "int main() { int a[2]; int *p = a; (p + 1)++; return 0; }",
+            Code::NeedLvalue,
+            false
+        ),
+        "int main() { int a[2]; int *p = a; (*(p + 1))++; return 0; }"
+    );
+}
+
+#[test]
+fn skips_overlapping_fixes() {
+    let source = "int main() { int *p = 5; int *q = 6; return 0; }";
+    let res = compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+    let fixes: Vec<_> = res.diagnostics().flat_map(|(_, d)| d.fixes()).collect();
+    assert_eq!(fixes.len(), 2);
+    let (fixed, applied) = apply_fixes(source, fixes.iter().copied().chain([fixes[0]]));
+    assert_eq!(applied, 2);
+    assert_eq!(
+        fixed,
+        "int main() { int *p = (int *)(5); int *q = (int *)(6); return 0; }"
+    );
+}
+
+#[test]
+fn passes_the_address_to_scanf() {
+    assert_eq!(
+        fixed(
+            "#include <stdio.h>\nint main() { int n = 0; scanf(\"%d\", n); return n; }",
+            Code::ScanfMissingAddress,
+            false
+        ),
+        "#include <stdio.h>\nint main() { int n = 0; scanf(\"%d\", &n); return n; }"
+    );
+}
+
+#[test]
+fn assigns_instead_of_comparing() {
+    assert_eq!(
+        fixed(
+            "#pragma comp diagnostic warning \"-Wunused-value\"
+int main() { int x = 0; x == 1; return x; }",
+            Code::NoEffect,
+            false
+        ),
+        "#pragma comp diagnostic warning \"-Wunused-value\"
+int main() { int x = 0; x = 1; return x; }"
+    );
+}
+
+#[test]
+fn casts_lossy_conversions() {
+    assert_eq!(
+        fixed(
+            "int f(long l) { char c = l + 1; return c; }\nint main() { return 0; }",
+            Code::LossyImplicitAssign,
+            true
+        ),
+        "int f(long l) { char c = (char)(l + 1); return c; }\nint main() { return 0; }"
+    );
+    assert_eq!(
+        fixed(
+            "char f(long l) { return l; }\nint main() { return 0; }",
+            Code::LossyImplicitReturn,
+            true
+        ),
+        "char f(long l) { return (char)l; }\nint main() { return 0; }"
+    );
+    assert_eq!(
+        fixed(
+            "int g(char c) { return c; }\nint main() { long l = 1; return g(l); }",
+            Code::LossyImplicitArg,
+            true
+        ),
+        "int g(char c) { return c; }\nint main() { long l = 1; return g((char)l); }"
+    );
+}
@@ -0,0 +1,213 @@
+use comp_lib::passes::format::{format_source, BraceStyle, FormatOpts};
+
+fn format(source: &str, opts: &FormatOpts) -> String {
+    let res = format_source(source, opts);
+    assert!(res.is_ok(), "{:?}", res.diagnostics().collect::<Vec<_>>());
+    res.into_value().unwrap()
+}
+
+fn opts(brace_style: BraceStyle) -> FormatOpts {
+    FormatOpts {
+        brace_style,
+        ..Default::default()
+    }
+}
+
+const MESSY: &str = "#include <stdio.h>
+// Adds two numbers.
+int add(int a,int b){return a+b;} // the end
+
+
+int main(void)
+{
+  int* p=(int*)0;int x=add(1,-2);
+  if(x>0)x++;else if(x) { x=-x; } else {
+  /* nothing */
+  }
+  switch(x){case 1:x=2;break;default:x= x?1:2;}
+  for(;;)break;
+  return x;
+}";
+
+#[test]
+fn formats_source() {
+    let expected = "#include <stdio.h>
+// Adds two numbers.
+int add(int a, int b) {
+    return a + b;
+} // the end
+
+int main(void) {
+    int *p = (int *)0;
+    int x = add(1, -2);
+    if (x > 0)
+        x++;
+    else if (x) {
+        x = -x;
+    } else {
+        /* nothing */
+    }
+    switch (x) {
+    case 1:
+        x = 2;
+        break;
+    default:
+        x = x ? 1 : 2;
+    }
+    for (;;)
+        break;
+    return x;
+}
+";
+    assert_eq!(format(MESSY, &FormatOpts::default()), expected);
+}
+
+#[test]
+fn formats_brace_styles() {
+    let source = "int f(int a) { if (a) { return 1; } else { return 2; } }";
+    assert_eq!(
+        format(source, &opts(BraceStyle::Allman)),
+        "int f(int a)
+{
+    if (a)
+    {
+        return 1;
+    }
+    else
+    {
+        return 2;
+    }
+}
+"
+    );
+    assert_eq!(
+        format(source, &opts(BraceStyle::Linux)),
+        "int f(int a)
+{
+    if (a) {
+        return 1;
+    } else {
+        return 2;
+    }
+}
+"
+    );
+
+    let opts = FormatOpts {
+        indent_width: 2,
+        ..Default::default()
+    };
+    assert_eq!(
+        format(source, &opts),
+        "int f(int a) {
+  if (a) {
+    return 1;
+  } else {
+    return 2;
+  }
+}
+"
+    );
+}
+
+#[test]
+fn preserves_comments() {
+    let source = "/* header */
+int main() { // opening
+    int x = f(1, // first
+        2 /* second */);
+    // before return
+
+    return /* zero */ 0;
+    /* multi
+       line */
+}
+";
+    let formatted = format(source, &FormatOpts::default());
+    let comments = [
+        "/* header */",
+        "// opening",
+        "// first",
+        "/* second */",
+        "// before return",
+        "/* zero */",
+        "/* multi\n       line */",
+    ];
+    let mut rest = formatted.as_str();
+    for comment in comments {
+        let index = rest
+            .find(comment)
+            .unwrap_or_else(|| panic!("`{comment}` is missing or out of order in:\n{formatted}"));
+        rest = &rest[index + comment.len()..];
+    }
+}
+
+#[test]
+fn keeps_pragmas_on_their_own_line() {
+    let source = "#pragma comp diagnostic push
+int main() { int x; #pragma comp diagnostic ignored \"-Wuninitialized\"
+return x; }
+#pragma comp diagnostic pop
+";
+    assert_eq!(
+        format(source, &FormatOpts::default()),
+        "#pragma comp diagnostic push
+int main() {
+    int x;
+    #pragma comp diagnostic ignored \"-Wuninitialized\"
+    return x;
+}
+#pragma comp diagnostic pop
+"
+    );
+}
+
+#[test]
+fn breaks_long_lines() {
+    let source = "int main() { return function_with_a_long_name(first_argument, second_argument) \
+                  || other_function(third_argument); }";
+    let opts = FormatOpts {
+        max_width: 60,
+        ..Default::default()
+    };
+    assert_eq!(
+        format(source, &opts),
+        "int main() {
+    return function_with_a_long_name(first_argument,
+            second_argument)
+            || other_function(third_argument);
+}
+"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let test_files = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/test_files");
+    let mut sources: Vec<_> = std::fs::read_dir(test_files)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "c"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    sources.push(MESSY.to_owned());
+
+    for source in sources {
+        for brace_style in [BraceStyle::Attach, BraceStyle::Allman, BraceStyle::Linux] {
+            for max_width in [100, 30] {
+                let opts = FormatOpts {
+                    brace_style,
+                    indent_width: 4,
+                    max_width,
+                };
+                let once = format(&source, &opts);
+                assert_eq!(format(&once, &opts), once);
+            }
+        }
+    }
+}
+
+#[test]
+fn rejects_syntax_errors() {
+    assert!(format_source("int main( {", &FormatOpts::default()).is_err());
+}
@@ -0,0 +1,62 @@
+//! Inputs found by the fuzz targets in `fuzz/` that used to make the compiler panic.
+
+use comp_lib::{
+    compile::{compile, CompileOptsBuilder, OutputFormat, Target},
+    diagnostic::{AggregateResult, Code},
+};
+
+fn compile_for(
+    source: &str,
+    target: Target,
+    output_format: OutputFormat,
+) -> AggregateResult<Vec<u8>> {
+    let opts = CompileOptsBuilder::new()
+        .target(target)
+        .output_format(output_format)
+        .build()
+        .unwrap();
+    compile(source, "fuzz.c", &opts)
+}
+
+fn compile_mips(source: &str) -> AggregateResult<Vec<u8>> {
+    compile_for(source, Target::Mips, OutputFormat::MipsAsm)
+}
+
+fn error_codes<T>(res: &AggregateResult<T>) -> Vec<Code> {
+    res.diagnostics().map(|(_, diag)| *diag.code()).collect()
+}
+
+#[test]
+fn integer_literal_too_big_for_i128() {
+    for target in [Target::X86_64, Target::Mips] {
+        let output_format = match target {
+            Target::Mips => OutputFormat::MipsAsm,
+            _ => OutputFormat::LlvmIr,
+        };
+        let res = compile_for(
+            "int main() { return 0x1000000000000000000000000000000000; }",
+            target,
+            output_format,
+        );
+        assert!(res.is_err());
+        assert_eq!(error_codes(&res), vec![Code::TooBigConstant]);
+    }
+}
+
+#[test]
+fn mips_program_without_main() {
+    let res = compile_mips("int f() { return 0; }");
+    assert!(res.is_err());
+    assert_eq!(error_codes(&res), vec![Code::LinkError]);
+}
+
+#[test]
+fn mips_pointer_arithmetic_on_big_elements() {
+    for size in ["70000", "5000000000"] {
+        let source = format!(
+            "char f(char a[][{size}], int i) {{ return (a + i - 1)[1][0]; }}
+            int main() {{ return 0; }}"
+        );
+        assert!(!compile_mips(&source).is_err(), "size {size}");
+    }
+}
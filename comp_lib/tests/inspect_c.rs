@@ -0,0 +1,64 @@
+use comp_lib::compile::{compile, CompileOptsBuilder, OutputFormat};
+
+fn print_c(source: &str, output_format: OutputFormat) -> String {
+    let opts = CompileOptsBuilder::new()
+        .output_format(output_format)
+        .build()
+        .unwrap();
+    let res = compile(source, "test.c", &opts);
+    assert!(res.is_ok(), "{:?}", res.diagnostics().collect::<Vec<_>>());
+    String::from_utf8(res.into_value().unwrap()).unwrap()
+}
+
+#[test]
+fn prints_folded_ast() {
+    let source = "int f(const char *s, int a[]);
+int main() {
+    int x = 1 + 2 * 3;
+    for (x = 0; x < 3; x++)
+        x = -(-x) - -1;
+    return x;
+}";
+    assert_eq!(
+        print_c(source, OutputFormat::AstC),
+        "int f(const char *s, int a[]);
+
+int main(void) {
+    int x = 7;
+    for (x = 0; x < 3; x++) {
+        x = -(-x) - (-1);
+    }
+    return x;
+}
+"
+    );
+}
+
+#[test]
+fn prints_lowered_ir() {
+    let source = "int g = 2;
+int main() {
+    int a[2];
+    char c = 'x';
+    {
+        int a = 1;
+        a++;
+    }
+    a[1] = c + g;
+    return a[1];
+}";
+    let c = print_c(source, OutputFormat::IrC);
+    for expected in [
+        "int main(void);",
+        "int g = 2;",
+        "int a_0[2];",
+        "char c_1;",
+        "int a_2;",
+        "c_1 = 'x';",
+        "a_2++;",
+        "*(a_0 + ",
+        "(int)c_1 + g",
+    ] {
+        assert!(c.contains(expected), "`{expected}` is missing in:\n{c}");
+    }
+}
@@ -0,0 +1,163 @@
+use comp_lib::{
+    compile::{self, CompileOptsBuilder, Target},
+    interpreter::{ErrorKind, Interpreter, RuntimeError},
+};
+
+fn interpret(source: &str, input: &str) -> (Result<i32, RuntimeError>, String) {
+    let opts = CompileOptsBuilder::new()
+        .target(Target::X86_64)
+        .const_fold(false)
+        .build()
+        .unwrap();
+    let root = compile::compile_to_ir(source, &opts).into_value().unwrap();
+
+    let mut output = Vec::new();
+    let res = Interpreter::new(&root, Target::X86_64, input.as_bytes(), &mut output)
+        .with_step_limit(1_000_000)
+        .run();
+    (res, String::from_utf8(output).unwrap())
+}
+
+/// Returns the kind of the error, and the source text of its span.
+fn interpret_err(source: &str) -> (ErrorKind, &str) {
+    let err = interpret(source, "").0.unwrap_err();
+    let range: std::ops::Range<usize> = err.span.into();
+    (err.kind, &source[range])
+}
+
+#[test]
+fn runs_programs() {
+    let (res, output) = interpret(
+        r#"
+        #include <stdio.h>
+
+        int fib(int n) {
+            if (n < 2) return n;
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        int main() {
+            int a[5];
+            int *p = a;
+            int i;
+            for (i = 0; i < 5; i++) {
+                *p++ = fib(i + 5);
+            }
+            printf("%d %d %ld %5.2f|%-3s|\n", a[0], a[4], p - a, 1.0 / 3, "x");
+            return a[1];
+        }
+        "#,
+        "",
+    );
+    assert_eq!(res, Ok(8));
+    assert_eq!(output, "5 34 5  0.33|x  |\n");
+}
+
+#[test]
+fn reads_input() {
+    let (res, output) = interpret(
+        r#"
+        #include <stdio.h>
+
+        int main() {
+            int a;
+            float f;
+            char s[8];
+            int n = scanf("%d %f %7s", &a, &f, s);
+            printf("%d %d %.2f %s\n", n, a, f, s);
+            printf("%d\n", scanf("%d", &a));
+        }
+        "#,
+        "12 0.5 abcdefghij",
+    );
+    assert_eq!(res, Ok(0));
+    assert_eq!(output, "3 12 0.50 abcdefg\n0\n");
+}
+
+#[test]
+fn reports_undefined_behavior() {
+    let (kind, span) = interpret_err(
+        r#"
+        int main() {
+            int a[4];
+            int i;
+            for (i = 0; i <= 4; i++) {
+                a[i] = i;
+            }
+        }
+        "#,
+    );
+    assert_eq!(
+        kind,
+        ErrorKind::OutOfBounds {
+            offset: 16,
+            size: 4,
+            len: 16
+        }
+    );
+    assert_eq!(span, "a[i] = i");
+
+    let (kind, span) = interpret_err(
+        r#"
+        #include <stdio.h>
+
+        int main() {
+            int x;
+            printf("%d\n", x + 1);
+        }
+        "#,
+    );
+    assert_eq!(kind, ErrorKind::UninitializedRead);
+    assert_eq!(span, "x");
+
+    let (kind, span) = interpret_err(
+        r#"
+        int main() {
+            int x = 2147483647;
+            int y = 1;
+            return x + y;
+        }
+        "#,
+    );
+    assert_eq!(kind, ErrorKind::SignedOverflow);
+    assert_eq!(span, "x + y");
+
+    let (kind, span) = interpret_err(
+        r#"
+        int f(int d) {
+            return 10 / d;
+        }
+
+        int main() {
+            return f(0);
+        }
+        "#,
+    );
+    assert_eq!(kind, ErrorKind::DivisionByZero);
+    assert_eq!(span, "10 / d");
+
+    let (kind, _) = interpret_err(
+        r#"
+        int *f() {
+            int x = 1;
+            return &x;
+        }
+
+        int main() {
+            return *f();
+        }
+        "#,
+    );
+    assert_eq!(kind, ErrorKind::DanglingPointer);
+
+    let (kind, _) = interpret_err(
+        r#"
+        int main() {
+            unsigned int x = 0;
+            x = x - 1;
+            while (x) {}
+        }
+        "#,
+    );
+    assert!(!kind.is_undefined_behavior());
+}
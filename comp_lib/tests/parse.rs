@@ -99,6 +99,12 @@ fn parses_integer_literals() {
     // erroneous number literals
     // numbers with digits oustide base range
     check_literals_err!["12ab", "30f", "0190", "0baf", "08", "0xGHB", "0Xfoobar"];
+    // numbers that don't fit in an i128
+    check_literals_err![
+        "999999999999999999999999999999999999999999",
+        "0xfffffffffffffffffffffffffffffffff",
+        "07777777777777777777777777777777777777777777"
+    ];
 }
 
 #[test]
@@ -430,3 +436,20 @@ fn assert_expr_eq(raw_expr: String, expected_expr: ast::Expression) {
     );
     assert!(is_ok);
 }
+
+#[test]
+fn recovers_from_syntax_errors() {
+    let source =
+        "int f() { return 1 }\nint g(int a b) { return a; }\nint main() { int x = ; return f(); }";
+    let recovery = passes::parse::recover(source);
+    assert_eq!(recovery.source.len(), source.len());
+    assert!(recovery.source.contains("int f() {"));
+    assert!(recovery.source.contains("int main() {"));
+    assert!(!recovery.source.contains("int g"));
+    assert!(!recovery.source.contains("int x"));
+    assert!(parse(&recovery.source).is_ok());
+
+    let res = recovery.apply(diagnostic::AggregateResult::new_ok(()));
+    assert!(res.is_err());
+    assert_eq!(res.diagnostics().count(), 3);
+}
@@ -0,0 +1,267 @@
+use comp_lib::{
+    compile::{compile_to_ir, CompileOptsBuilder, CompileOptsErr, Target},
+    diagnostic::{Code, DiagnosticKind, Span},
+};
+
+const UNINIT: &str = "int main() {
+    int x;
+    int y;
+    return x + y;
+}";
+
+fn diagnostics(source: &str, opts: CompileOptsBuilder) -> Vec<(DiagnosticKind, Code)> {
+    let res = compile_to_ir(source, &opts.build().unwrap());
+    res.diagnostics()
+        .map(|(kind, diagnostic)| (kind, *diagnostic.code()))
+        .collect()
+}
+
+#[test]
+fn codes_have_unique_names() {
+    for (i, code) in Code::ALL.into_iter().enumerate() {
+        assert_eq!(code as usize, i, "{code:?} is out of order in `Code::ALL`");
+        if code != Code::Unspecified {
+            assert_eq!(Code::from_name(code.name()), Some(code));
+        }
+    }
+}
+
+#[test]
+fn flags_change_levels() {
+    let warning = (DiagnosticKind::Rec, Code::UsingUninit);
+    let error = (DiagnosticKind::Err, Code::UsingUninit);
+    let tests = [
+        (vec![], vec![warning, warning]),
+        (vec!["no-uninitialized"], vec![]),
+        (vec!["no-all"], vec![]),
+        (vec!["no-all", "uninitialized"], vec![warning, warning]),
+        (vec!["error=uninitialized"], vec![error, error]),
+        (vec!["error"], vec![error, error]),
+        (
+            vec!["error", "no-error=uninitialized"],
+            vec![warning, warning],
+        ),
+        (vec!["error=all", "no-error"], vec![error, error]),
+    ];
+    for (flags, expected) in tests {
+        let opts = flags.iter().fold(CompileOptsBuilder::new(), |opts, flag| {
+            opts.warning_flag(*flag)
+        });
+        assert_eq!(diagnostics(UNINIT, opts), expected, "with flags {flags:?}");
+    }
+}
+
+#[test]
+fn inhibit_keeps_errors() {
+    let opts = CompileOptsBuilder::new()
+        .with_code_to_upgrade(Code::UsingUninit)
+        .inhibit_warnings(true);
+    assert_eq!(diagnostics(UNINIT, opts).len(), 2);
+    let opts = CompileOptsBuilder::new().inhibit_warnings(true);
+    assert_eq!(diagnostics(UNINIT, opts), vec![]);
+}
+
+#[test]
+fn max_errors() {
+    let opts = CompileOptsBuilder::new()
+        .warning_flag("error")
+        .max_errors(1);
+    assert_eq!(
+        diagnostics(UNINIT, opts),
+        vec![(DiagnosticKind::Err, Code::UsingUninit)]
+    );
+}
+
+#[test]
+fn unknown_flag() {
+    let opts = CompileOptsBuilder::new()
+        .warning_flag("no-such-warning")
+        .build();
+    assert!(matches!(opts, Err(CompileOptsErr::UnknownWarningFlag(_))));
+}
+
+#[test]
+fn pragmas_change_levels_for_a_region() {
+    let source = "int main() {
+    int x;
+    int y;
+    int z;
+#pragma comp diagnostic push
+#pragma comp diagnostic ignored \"-Wuninitialized\"
+    x;
+#pragma comp diagnostic error \"-Wall\"
+    y;
+#pragma comp diagnostic pop
+    return z;
+}";
+    assert_eq!(
+        diagnostics(source, CompileOptsBuilder::new()),
+        vec![
+            (DiagnosticKind::Err, Code::UsingUninit),
+            (DiagnosticKind::Rec, Code::UsingUninit),
+        ]
+    );
+}
+
+#[test]
+fn invalid_pragmas() {
+    let source = "#pragma once
+#pragma comp diagnostic pop
+#pragma comp diagnostic ignored \"-Wnothing\"
+#pragma comp warning
+int main() {
+    return 0;
+}";
+    assert_eq!(
+        diagnostics(source, CompileOptsBuilder::new()),
+        vec![(DiagnosticKind::Rec, Code::InvalidPragma); 3]
+    );
+}
+
+#[test]
+fn format_diagnostics_point_at_the_conversion() {
+    let source = "#include <stdio.h>
+int main() {
+    printf(\"\\t%d\\x41 %5.1f\\n\" \"%s\", 1, 2.0);
+    return 0;
+}";
+    let res = compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+    let spans: Vec<_> = res
+        .diagnostics()
+        .map(|(_, diagnostic)| {
+            let span = diagnostic.main_span();
+            (*diagnostic.code(), &source[span.start()..span.excl_end()])
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Code::UnsupportedFormat, "%5.1f"),
+            (Code::FormatArgCount, "%s"),
+        ]
+    );
+}
+
+#[test]
+fn extra_enables_unused_warnings() {
+    let source = "int first(int a, int b) {
+    int unused = a;
+    return a;
+}
+int main() {
+    return first(1, 2);
+}";
+    let unused_var = (DiagnosticKind::Rec, Code::UnusedVariable);
+    let unused_param = (DiagnosticKind::Rec, Code::UnusedParameter);
+    let tests = [
+        (vec![], vec![]),
+        (vec!["all"], vec![]),
+        (vec!["unused-variable"], vec![unused_var]),
+        (vec!["extra"], vec![unused_param, unused_var]),
+        (vec!["unused"], vec![unused_param, unused_var]),
+        (vec!["extra", "no-unused"], vec![]),
+    ];
+    for (flags, expected) in tests {
+        let opts = flags.iter().fold(CompileOptsBuilder::new(), |opts, flag| {
+            opts.warning_flag(*flag)
+        });
+        assert_eq!(diagnostics(source, opts), expected, "with flags {flags:?}");
+    }
+}
+
+#[test]
+fn access_diagnostics_point_at_the_declaration() {
+    let source = "int main() {
+    int values[2];
+    int *p = 0;
+    values[0] = 1;
+    values[2] = *p;
+    return values[0];
+}";
+    let res = compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+    let text = |span: &Span| &source[span.start()..span.excl_end()];
+    let spans: Vec<_> = res
+        .diagnostics()
+        .map(|(_, diagnostic)| {
+            let notes: Vec<_> = diagnostic
+                .additional_spans()
+                .map(|(span, _)| text(span))
+                .collect();
+            (*diagnostic.code(), text(diagnostic.main_span()), notes)
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Code::ArrayOutOfBounds, "2", vec!["values"]),
+            (Code::NullDereference, "*p", vec!["int *p = 0;"]),
+        ]
+    );
+}
+
+#[test]
+fn constant_diagnostics_point_at_the_constant() {
+    let source = "char letter = 300;
+int main() {
+    int big = 2000000000 + 2000000000;
+    int total = 10;
+    return big + total / 0 + (total << 32) + letter;
+}";
+    let res = compile_to_ir(source, &CompileOptsBuilder::new().build().unwrap());
+    let text = |span: &Span| &source[span.start()..span.excl_end()];
+    let spans: Vec<_> = res
+        .diagnostics()
+        .map(|(_, diagnostic)| {
+            let notes: Vec<_> = diagnostic
+                .additional_spans()
+                .map(|(span, _)| text(span))
+                .collect();
+            (*diagnostic.code(), text(diagnostic.main_span()), notes)
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (Code::IntegerOverflow, "2000000000 + 2000000000", vec![]),
+            (Code::ConstantConversion, "300", vec!["char"]),
+            (Code::DivisionByZero, "0", vec![]),
+            (Code::ShiftCountOverflow, "32", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn conversions_dont_depend_on_folding() {
+    let source = "int main() {
+    int small = 4;
+    char c = small;
+    float f = 4.5;
+    int i = f;
+    return c + i;
+}";
+    let lossy = (DiagnosticKind::Rec, Code::LossyImplicitAssign);
+    for const_fold in [true, false] {
+        let opts = CompileOptsBuilder::new().const_fold(const_fold);
+        assert_eq!(
+            diagnostics(source, opts),
+            vec![lossy, lossy],
+            "with const_fold {const_fold}"
+        );
+    }
+}
+
+#[test]
+fn bounds_check_needs_mips() {
+    for (target, is_ok) in [
+        (Target::Mips, true),
+        (Target::RiscV32, true),
+        (Target::X86_64, false),
+        (Target::Wasm32, false),
+    ] {
+        let opts = CompileOptsBuilder::new()
+            .target(target)
+            .bounds_check(true)
+            .build();
+        assert_eq!(opts.is_ok(), is_ok, "with target {target:?}");
+    }
+}
@@ -0,0 +1,19 @@
+#![no_main]
+
+use comp_lib::compile::{compile, CompileOptsBuilder, OutputFormat, Target};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+    for (target, output_format) in [
+        (Target::X86_64, OutputFormat::LlvmIr),
+        (Target::Mips, OutputFormat::MipsAsm),
+    ] {
+        let opts = CompileOptsBuilder::new()
+            .target(target)
+            .output_format(output_format)
+            .build()
+            .unwrap();
+        let _ = compile(&source, "fuzz.c", &opts);
+    }
+});
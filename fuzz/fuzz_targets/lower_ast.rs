@@ -0,0 +1,36 @@
+#![no_main]
+
+use arbitrary::Unstructured;
+use comp_lib::{
+    codegen,
+    compile::{CompileOptsBuilder, Target},
+    passes::{const_fold::const_fold, dead_code_removal::remove_dead_code, lower_ast},
+};
+use fuzz::ast::arbitrary_ast;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(ast) = arbitrary_ast(&mut Unstructured::new(data)) else {
+        return;
+    };
+    let fold = data.first().is_some_and(|byte| byte % 2 == 0);
+    for target in [Target::X86_64, Target::Mips] {
+        let opts = CompileOptsBuilder::new().target(target).build().unwrap();
+        let mut ast = ast.clone();
+        if fold {
+            const_fold(&mut ast, opts.settings());
+        }
+        let Some(mut ir) = lower_ast::build_ir_from_ast(&ast, opts.settings()).into_value() else {
+            continue;
+        };
+        remove_dead_code(&mut ir);
+        match target {
+            Target::Mips => {
+                let _ = codegen::mips::build_from_ir(&ir, opts.settings(), "fuzz.c", "");
+            }
+            _ => {
+                let _ = codegen::llvm::build_from_ir(&ir, opts.settings(), "fuzz.c", "");
+            }
+        }
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use arbitrary::Unstructured;
+use fuzz::cfg::arbitrary_root;
+use libfuzzer_sys::fuzz_target;
+use mips_ir::validator::validate_root;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut root) = arbitrary_root(&mut Unstructured::new(data)) else {
+        return;
+    };
+    assert_eq!(validate_root(&root), Ok(()), "generated an invalid root");
+    mips_ir::compile_and_link(&mut root).unwrap();
+});
@@ -0,0 +1,314 @@
+//! Generates arbitrary (but structurally valid) ASTs, i.e. anything the parser could produce.
+
+use arbitrary::{Result, Unstructured};
+use comp_lib::{ast::*, diagnostic::Span};
+
+/// The names used for all identifiers, so that most uses refer to something that is declared.
+const IDENTS: [&str; 7] = ["main", "f", "g", "a", "b", "c", "printf"];
+
+/// The maximum nesting depth of statements and expressions.
+const MAX_DEPTH: u32 = 4;
+
+/// Interesting array lengths, including ones that overflow the address space of the targets.
+const ARRAY_LENGTHS: [i128; 7] = [1, 2, 3, 16, 65537, 1 << 31, 1 << 40];
+
+pub fn arbitrary_ast(u: &mut Unstructured) -> Result<Ast> {
+    Ok(Ast {
+        global_declarations: list(u, 5, |u| {
+            let data = match u.ratio(3, 4)? {
+                true => ExternalDeclaration::FunctionDefinition(function_definition(u)?),
+                false => ExternalDeclaration::Declaration(declaration(u, 0)?),
+            };
+            Ok(ExternalDeclarationNode {
+                span: span(),
+                data,
+                comments: None,
+            })
+        })?,
+    })
+}
+
+fn span() -> Span {
+    (0..0).into()
+}
+
+fn list<T>(
+    u: &mut Unstructured,
+    max_len: usize,
+    mut f: impl FnMut(&mut Unstructured) -> Result<T>,
+) -> Result<Vec<T>> {
+    let len = u.int_in_range(0..=max_len)?;
+    (0..len).map(|_| f(u)).collect()
+}
+
+fn optional<T>(
+    u: &mut Unstructured,
+    f: impl FnOnce(&mut Unstructured) -> Result<T>,
+) -> Result<Option<T>> {
+    match u.arbitrary()? {
+        true => f(u).map(Some),
+        false => Ok(None),
+    }
+}
+
+fn ident(u: &mut Unstructured) -> Result<IdentNode> {
+    Ok(IdentNode {
+        span: span(),
+        data: (*u.choose(&IDENTS)?).to_owned(),
+    })
+}
+
+fn function_definition(u: &mut Unstructured) -> Result<FunctionDefinition> {
+    Ok(FunctionDefinition {
+        prototype_span: span(),
+        return_type: qualified_type(u, 0)?,
+        ident: ident(u)?,
+        params: list(u, 3, function_param)?,
+        is_vararg: u.ratio(1, 8)?,
+        body: block(u, 0)?,
+    })
+}
+
+fn function_declaration(u: &mut Unstructured) -> Result<FunctionDeclaration> {
+    Ok(FunctionDeclaration {
+        return_type: qualified_type(u, 0)?,
+        ident: ident(u)?,
+        params: list(u, 3, function_param)?,
+        is_vararg: u.ratio(1, 8)?,
+    })
+}
+
+fn function_param(u: &mut Unstructured) -> Result<FunctionParamNode> {
+    Ok(FunctionParamNode {
+        span: span(),
+        type_name: qualified_type(u, 0)?,
+        ident: optional(u, ident)?,
+        array_parts: array_parts(u, MAX_DEPTH)?,
+    })
+}
+
+fn declaration(u: &mut Unstructured, depth: u32) -> Result<Declaration> {
+    Ok(match u.ratio(1, 5)? {
+        true => Declaration::FunctionDeclaration(function_declaration(u)?),
+        false => Declaration::Variable(VariableDeclaration {
+            type_name: qualified_type(u, 0)?,
+            ident: ident(u)?,
+            array_parts: array_parts(u, depth)?,
+            initializer: optional(u, |u| Ok((span(), expression(u, depth + 1)?)))?,
+        }),
+    })
+}
+
+fn array_parts(u: &mut Unstructured, depth: u32) -> Result<Vec<ArrayDeclarationNode>> {
+    list(u, 2, |u| {
+        let data = match u.int_in_range(0..=9)? {
+            0 => ArrayDeclaration::Unknown,
+            1 => ArrayDeclaration::Known(expression(u, depth + 1)?),
+            _ => ArrayDeclaration::Known(ExpressionNode {
+                span: span(),
+                data: Expression::Literal(LiteralNode {
+                    span: span(),
+                    data: Literal::Dec(*u.choose(&ARRAY_LENGTHS)?),
+                }),
+            }),
+        };
+        Ok(ArrayDeclarationNode { span: span(), data })
+    })
+}
+
+fn qualified_type(u: &mut Unstructured, pointer_depth: u32) -> Result<QualifiedTypeNode> {
+    use UnqualifiedType as UT;
+    let data = match pointer_depth < 3 && u.ratio(1, 4)? {
+        true => UT::PointerType(Box::new(qualified_type(u, pointer_depth + 1)?)),
+        false => u
+            .choose(&[
+                UT::Void,
+                UT::Float,
+                UT::Double,
+                UT::LongDouble,
+                UT::Char,
+                UT::SignedChar,
+                UT::SignedShortInt,
+                UT::SignedInt,
+                UT::SignedLongInt,
+                UT::UnsignedChar,
+                UT::UnsignedShortInt,
+                UT::UnsignedInt,
+                UT::UnsignedLongInt,
+            ])?
+            .clone(),
+    };
+    Ok(QualifiedTypeNode {
+        span: span(),
+        is_const: optional(u, |_| Ok(span()))?,
+        unqualified: UnqualifiedTypeNode { span: span(), data },
+    })
+}
+
+fn block(u: &mut Unstructured, depth: u32) -> Result<BlockStatementNode> {
+    Ok(BlockStatementNode {
+        span: span(),
+        stmts: list(u, 4, |u| statement(u, depth + 1))?,
+    })
+}
+
+fn statement(u: &mut Unstructured, depth: u32) -> Result<StatementNode> {
+    // Nested statements can only be generated below the maximum depth.
+    let max_kind = if depth < MAX_DEPTH { 9 } else { 4 };
+    let data = match u.int_in_range(0..=max_kind)? {
+        0 => Statement::Declaration(declaration(u, depth)?),
+        1 => Statement::Expression(expression(u, depth)?),
+        2 => Statement::Break,
+        3 => Statement::Continue,
+        4 => Statement::Return(span(), optional(u, |u| expression(u, depth))?),
+        5 => Statement::If(IfStatement {
+            condition: expression(u, depth)?,
+            if_body: block(u, depth)?,
+            else_body: optional(u, |u| block(u, depth))?,
+        }),
+        6 => {
+            let expr = expression(u, depth)?;
+            let mut cases = list(u, 3, |u| {
+                Ok(SwitchCase::Expr(SwithCaseExprNode {
+                    label_span: span(),
+                    expr: expression(u, depth)?,
+                    body: block(u, depth)?,
+                }))
+            })?;
+            if u.arbitrary()? {
+                // The parser guarantees there is at most one default case.
+                let default = SwitchCase::Default(SwitchCaseDefaultNode {
+                    label_span: span(),
+                    body: block(u, depth)?,
+                });
+                cases.insert(u.int_in_range(0..=cases.len())?, default);
+            }
+            Statement::Switch(SwitchStatement { expr, cases })
+        }
+        7 => Statement::While(WhileStatement {
+            condition: expression(u, depth)?,
+            body: block(u, depth)?,
+        }),
+        8 => Statement::For(ForStatement {
+            init: optional(u, |u| {
+                let data = match u.arbitrary()? {
+                    true => Statement::Declaration(declaration(u, depth)?),
+                    false => Statement::Expression(expression(u, depth)?),
+                };
+                Ok(Box::new(StatementNode {
+                    span: span(),
+                    data,
+                    comments: None,
+                }))
+            })?,
+            condition: optional(u, |u| expression(u, depth))?,
+            iter: optional(u, |u| expression(u, depth))?,
+            body: block(u, depth)?,
+        }),
+        _ => Statement::BlockStatement(block(u, depth)?),
+    };
+    Ok(StatementNode {
+        span: span(),
+        data,
+        comments: None,
+    })
+}
+
+fn expression(u: &mut Unstructured, depth: u32) -> Result<ExpressionNode> {
+    let boxed = |u: &mut Unstructured| expression(u, depth + 1).map(Box::new);
+    // Nested expressions can only be generated below the maximum depth.
+    let max_kind = if depth < MAX_DEPTH { 7 } else { 1 };
+    let data = match u.int_in_range(0..=max_kind)? {
+        0 => Expression::Literal(literal(u)?),
+        1 => Expression::Ident(ident(u)?),
+        2 => Expression::Assignment(
+            boxed(u)?,
+            AssignmentOperatorNode { span: span() },
+            boxed(u)?,
+        ),
+        3 => Expression::Binary(
+            boxed(u)?,
+            BinaryOperatorNode {
+                span: span(),
+                data: binary_operator(u)?,
+            },
+            boxed(u)?,
+        ),
+        4 => Expression::ArraySubscript(boxed(u)?, boxed(u)?),
+        5 => Expression::Unary(
+            UnaryOperatorNode {
+                span: span(),
+                data: unary_operator(u)?,
+            },
+            boxed(u)?,
+        ),
+        6 => Expression::Cast(qualified_type(u, 0)?, boxed(u)?),
+        _ => Expression::FunctionCall(FunctionCall {
+            ident: ident(u)?,
+            args: list(u, 3, |u| expression(u, depth + 1))?,
+        }),
+    };
+    Ok(ExpressionNode { span: span(), data })
+}
+
+/// Generates the literals the parser can produce, e.g. integer literals are never negative and
+/// strings never contain a null byte.
+fn literal(u: &mut Unstructured) -> Result<LiteralNode> {
+    let int = |u: &mut Unstructured| -> Result<i128> {
+        Ok(match u.arbitrary()? {
+            true => u.int_in_range(0..=300)?,
+            false => u.int_in_range(0..=i128::MAX)?,
+        })
+    };
+    let data = match u.int_in_range(0..=5)? {
+        0 => Literal::Dec(int(u)?),
+        1 => Literal::Hex(int(u)?),
+        2 => Literal::Octal(int(u)?),
+        3 => Literal::Char(u.arbitrary()?),
+        4 => Literal::Float(f64::from(u.int_in_range(0..=u32::MAX)?) / 256.0),
+        _ => Literal::String(list(u, 8, |u| u.int_in_range(1..=u8::MAX))?),
+    };
+    Ok(LiteralNode { span: span(), data })
+}
+
+fn binary_operator(u: &mut Unstructured) -> Result<BinaryOperator> {
+    use BinaryOperator as BO;
+    Ok(u.choose(&[
+        BO::Plus,
+        BO::Minus,
+        BO::Star,
+        BO::Slash,
+        BO::Pipe,
+        BO::Caret,
+        BO::Ampersand,
+        BO::AngleLeft,
+        BO::AngleRight,
+        BO::DoubleEquals,
+        BO::DoubleAmpersand,
+        BO::DoublePipe,
+        BO::BangEquals,
+        BO::Percent,
+        BO::AngleLeftEquals,
+        BO::AngleRightEquals,
+        BO::DoubleAngleLeft,
+        BO::DoubleAngleRight,
+    ])?
+    .clone())
+}
+
+fn unary_operator(u: &mut Unstructured) -> Result<UnaryOperator> {
+    use UnaryOperator as UO;
+    Ok(u.choose(&[
+        UO::Bang,
+        UO::Plus,
+        UO::Minus,
+        UO::DoublePlusPrefix,
+        UO::DoubleMinusPrefix,
+        UO::DoublePlusPostfix,
+        UO::DoubleMinusPostfix,
+        UO::Tilde,
+        UO::Ampersand,
+        UO::Star,
+    ])?
+    .clone())
+}
@@ -0,0 +1,473 @@
+//! Generates arbitrary roots of the MIPS IR that are valid according to
+//! [`validate_root`](mips_ir::validator::validate_root), in the same shape as the ones generated
+//! by the compiler: all values are virtual registers, and values are only passed between blocks
+//! through block arguments or by defining them in the entry block.
+
+use arbitrary::{Result, Unstructured};
+use mips_ir::{
+    instr, term, AnyReg, BCond, BZCond, BlockId, BlockRef, CallingConvention, DataDirective, FCmp,
+    FFmt, FImmOp, FReg, FRegOp2, FRegOp3, Function, GlobalData, ImmOp2, Instruction, Label, MemOp,
+    Reg, RegOp1, RegOp2, RegOp3, Root, Terminator,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Single,
+    Double,
+}
+
+const KINDS: [Kind; 3] = [Kind::Int, Kind::Single, Kind::Double];
+
+#[derive(Debug, Clone)]
+struct Signature {
+    label: Label,
+    params: Vec<Kind>,
+    return_kind: Option<Kind>,
+}
+
+/// The memory operations that are generated by the compiler.
+const MEM_OPS: [MemOp; 8] = [
+    MemOp::LoadByteS,
+    MemOp::LoadByteU,
+    MemOp::LoadHalfS,
+    MemOp::LoadHalfU,
+    MemOp::LoadWord,
+    MemOp::StoreByte,
+    MemOp::StoreHalf,
+    MemOp::StoreWord,
+];
+
+pub fn arbitrary_root(u: &mut Unstructured) -> Result<Root> {
+    let mut root = Root::new();
+    root.set_calling_convention(*u.choose(&[CallingConvention::Stack, CallingConvention::O32])?);
+
+    let mut data_labels = Vec::new();
+    for i in 0..u.int_in_range(0..=2)? {
+        let label = Label::from(format!("data_{i}"));
+        root.add_data(GlobalData::new(
+            label.clone(),
+            DataDirective::Word(u.arbitrary()?),
+        ));
+        data_labels.push(label);
+    }
+
+    let mut signatures = vec![Signature {
+        label: "main".into(),
+        params: Vec::new(),
+        return_kind: Some(Kind::Int),
+    }];
+    for i in 0..u.int_in_range(0..=3)? {
+        signatures.push(Signature {
+            label: format!("function_{i}").into(),
+            params: list(u, 4, |u| u.choose(&KINDS).copied())?,
+            return_kind: optional(u, |u| u.choose(&KINDS).copied())?,
+        });
+    }
+    if u.arbitrary()? {
+        signatures.push(Signature {
+            label: root.create_external_label("printf"),
+            params: vec![Kind::Int, Kind::Int],
+            return_kind: Some(Kind::Int),
+        });
+    }
+
+    let defined: Vec<_> = (signatures.iter())
+        .filter(|s| !root.is_external(&s.label))
+        .collect();
+    for signature in defined {
+        let function = FunctionGenerator {
+            u: &mut *u,
+            signature,
+            callees: &signatures[1..],
+            data_labels: &data_labels,
+            next_reg: 1,
+        }
+        .generate()?;
+        root.add_function(function);
+    }
+    root.export_label("main".into());
+    Ok(root)
+}
+
+fn list<T>(
+    u: &mut Unstructured,
+    max_len: usize,
+    mut f: impl FnMut(&mut Unstructured) -> Result<T>,
+) -> Result<Vec<T>> {
+    let len = u.int_in_range(0..=max_len)?;
+    (0..len).map(|_| f(u)).collect()
+}
+
+fn optional<T>(
+    u: &mut Unstructured,
+    f: impl FnOnce(&mut Unstructured) -> Result<T>,
+) -> Result<Option<T>> {
+    match u.arbitrary()? {
+        true => f(u).map(Some),
+        false => Ok(None),
+    }
+}
+
+/// The registers that can be used at some point in a block.
+#[derive(Debug, Clone, Default)]
+struct Available {
+    int: Vec<Reg>,
+    single: Vec<FReg>,
+    double: Vec<FReg>,
+}
+
+impl Available {
+    fn add(&mut self, reg: AnyReg) {
+        match reg {
+            AnyReg::R(reg) => self.int.push(reg),
+            AnyReg::F(reg) if reg.is_double() => self.double.push(reg),
+            AnyReg::F(reg) => self.single.push(reg),
+        }
+    }
+}
+
+struct FunctionGenerator<'a, 'b> {
+    u: &'a mut Unstructured<'b>,
+    signature: &'a Signature,
+    callees: &'a [Signature],
+    data_labels: &'a [Label],
+    next_reg: u32,
+}
+
+impl FunctionGenerator<'_, '_> {
+    fn generate(mut self) -> Result<Function> {
+        let params: Vec<_> = (self.signature.params.clone().into_iter())
+            .map(|kind| self.new_reg(kind))
+            .collect();
+        let mut function = Function::new(
+            self.signature.label.clone(),
+            params.iter().map(AnyReg::stack_info).collect(),
+        );
+
+        let block_ids: Vec<BlockId> = (0..self.u.int_in_range(0..=5)?)
+            .map(|_| function.create_block_label())
+            .collect();
+        let mut block_args = Vec::new();
+        for _ in &block_ids {
+            let kinds = list(self.u, 3, |u| u.choose(&KINDS).copied())?;
+            block_args.push(kinds.into_iter().map(|k| self.new_reg(k)).collect());
+        }
+
+        // The values defined in the entry block can be used in every other block, since it
+        // dominates all of them.
+        let mut entry = Available::default();
+        params.iter().for_each(|&reg| entry.add(reg));
+        let mut builder = function.start_entry_block(params);
+        let terminator = self.block(
+            &mut entry,
+            &mut |instruction| builder.add_instruction(instruction),
+            &block_ids,
+            &block_args,
+        )?;
+        function.add_block(builder.terminate(terminator));
+
+        for (&id, args) in block_ids.iter().zip(&block_args) {
+            let mut available = entry.clone();
+            args.iter().for_each(|&reg: &AnyReg| available.add(reg));
+            let mut builder = function.start_block(id, args.clone());
+            let terminator = self.block(
+                &mut available,
+                &mut |instruction| builder.add_instruction(instruction),
+                &block_ids,
+                &block_args,
+            )?;
+            function.add_block(builder.terminate(terminator));
+        }
+        Ok(function)
+    }
+
+    fn new_reg(&mut self, kind: Kind) -> AnyReg {
+        self.next_reg += 1;
+        match kind {
+            Kind::Int => Reg::Virtual(self.next_reg).into(),
+            Kind::Single => FReg::VirtualSingle(self.next_reg).into(),
+            Kind::Double => FReg::VirtualDouble(self.next_reg).into(),
+        }
+    }
+
+    fn new_int(&mut self) -> Reg {
+        self.new_reg(Kind::Int).try_into().unwrap()
+    }
+
+    fn new_freg(&mut self, fmt: FFmt) -> FReg {
+        let kind = match fmt {
+            FFmt::S => Kind::Single,
+            FFmt::D => Kind::Double,
+        };
+        self.new_reg(kind).try_into().unwrap()
+    }
+
+    /// Generates the instructions and the terminator of a block.
+    fn block(
+        &mut self,
+        available: &mut Available,
+        add: &mut impl FnMut(Instruction),
+        block_ids: &[BlockId],
+        block_args: &[Vec<AnyReg>],
+    ) -> Result<Terminator> {
+        for _ in 0..self.u.int_in_range(0..=8)? {
+            self.instruction(available, add)?;
+        }
+        self.terminator(available, add, block_ids, block_args)
+    }
+
+    /// Returns an available register of the given kind, or defines a new one if there are none.
+    fn operand(
+        &mut self,
+        kind: Kind,
+        available: &mut Available,
+        add: &mut impl FnMut(Instruction),
+    ) -> Result<AnyReg> {
+        let regs: Vec<AnyReg> = match kind {
+            Kind::Int => available.int.iter().map(|&r| r.into()).collect(),
+            Kind::Single => available.single.iter().map(|&r| r.into()).collect(),
+            Kind::Double => available.double.iter().map(|&r| r.into()).collect(),
+        };
+        if !regs.is_empty() && self.u.ratio(7, 8)? {
+            return self.u.choose(&regs).copied();
+        }
+        let reg = match kind {
+            Kind::Int => {
+                let reg = self.new_int();
+                add(instr::or_imm(reg, Reg::ZERO, self.u.arbitrary()?));
+                reg.into()
+            }
+            Kind::Single => {
+                let int = self.int(available, add)?;
+                let reg = self.new_freg(FFmt::S);
+                add(instr::move_to_fpu(int, reg));
+                reg.into()
+            }
+            Kind::Double => {
+                let word = self.freg(FFmt::S, available, add)?;
+                let reg = self.new_freg(FFmt::D);
+                add(instr::convert_from_word(FFmt::D, reg, word));
+                reg.into()
+            }
+        };
+        available.add(reg);
+        Ok(reg)
+    }
+
+    fn int(&mut self, available: &mut Available, add: &mut impl FnMut(Instruction)) -> Result<Reg> {
+        match self.u.ratio(1, 16)? {
+            true => Ok(Reg::ZERO),
+            false => Ok(self.operand(Kind::Int, available, add)?.try_into().unwrap()),
+        }
+    }
+
+    fn freg(
+        &mut self,
+        fmt: FFmt,
+        available: &mut Available,
+        add: &mut impl FnMut(Instruction),
+    ) -> Result<FReg> {
+        let kind = match fmt {
+            FFmt::S => Kind::Single,
+            FFmt::D => Kind::Double,
+        };
+        Ok(self.operand(kind, available, add)?.try_into().unwrap())
+    }
+
+    fn instruction(
+        &mut self,
+        available: &mut Available,
+        add: &mut impl FnMut(Instruction),
+    ) -> Result<()> {
+        let fmt = *self.u.choose(&[FFmt::S, FFmt::D])?;
+        let instruction = match self.u.int_in_range(0..=11)? {
+            0 => {
+                let (rs, rt) = (self.int(available, add)?, self.int(available, add)?);
+                Instruction::Reg3(*self.u.choose(&RegOp3::ALL)?, self.new_int(), rs, rt)
+            }
+            1 => {
+                let (rs, rt) = (self.int(available, add)?, self.int(available, add)?);
+                add(Instruction::Reg2(*self.u.choose(&RegOp2::ALL)?, rs, rt));
+                let op = *self.u.choose(&[RegOp1::MoveFromHi, RegOp1::MoveFromLo])?;
+                Instruction::Reg1(op, self.new_int())
+            }
+            2 => {
+                let rs = self.int(available, add)?;
+                let imm = self.u.arbitrary()?;
+                Instruction::Imm2(*self.u.choose(&ImmOp2::ALL)?, self.new_int(), rs, imm)
+            }
+            3 => {
+                let op = *self.u.choose(&MEM_OPS)?;
+                let base = self.int(available, add)?;
+                let offset = self.u.arbitrary()?;
+                let rt = match instruction_defines(op) {
+                    true => self.new_int(),
+                    false => self.int(available, add)?,
+                };
+                Instruction::Mem(op, rt, base, offset)
+            }
+            4 => instr::load_upper(self.new_int(), self.u.arbitrary()?),
+            5 => {
+                let fs = self.freg(fmt, available, add)?;
+                let ft = self.freg(fmt, available, add)?;
+                let op = *self.u.choose(&[
+                    FRegOp3::Add(fmt),
+                    FRegOp3::Sub(fmt),
+                    FRegOp3::Div(fmt),
+                    FRegOp3::Mul(fmt),
+                ])?;
+                Instruction::FReg3(op, self.new_freg(fmt), fs, ft)
+            }
+            6 => {
+                let other = match fmt {
+                    FFmt::S => FFmt::D,
+                    FFmt::D => FFmt::S,
+                };
+                let fs = self.freg(fmt, available, add)?;
+                let (op, fd) = match self.u.int_in_range(0..=3)? {
+                    0 => {
+                        let op = *self.u.choose(&[
+                            FRegOp2::Abs(fmt),
+                            FRegOp2::Neg(fmt),
+                            FRegOp2::Sqrt(fmt),
+                            FRegOp2::Move(fmt),
+                        ])?;
+                        (op, self.new_freg(fmt))
+                    }
+                    1 => (FRegOp2::Convert(other, fmt), self.new_freg(other)),
+                    2 => (FRegOp2::ConvertToWord(fmt), self.new_freg(FFmt::S)),
+                    _ => {
+                        let ft = self.freg(fmt, available, add)?;
+                        let op = *self
+                            .u
+                            .choose(&[FCmp::Eq(fmt), FCmp::Le(fmt), FCmp::Lt(fmt)])?;
+                        // Comparisons don't define a register.
+                        add(Instruction::FReg2(FRegOp2::Cmp(op), fs, ft));
+                        return Ok(());
+                    }
+                };
+                Instruction::FReg2(op, fd, fs)
+            }
+            7 => {
+                let base = self.int(available, add)?;
+                let offset = self.u.arbitrary()?;
+                let (load, store) = match fmt {
+                    FFmt::S => (FImmOp::LoadWordToFpu, FImmOp::StoreWordFromFpu),
+                    FFmt::D => (FImmOp::LoadDoublewordToFpu, FImmOp::StoreDoublewordFromFpu),
+                };
+                match self.u.arbitrary()? {
+                    true => Instruction::FImm(load, self.new_freg(fmt), base, offset),
+                    false => {
+                        Instruction::FImm(store, self.freg(fmt, available, add)?, base, offset)
+                    }
+                }
+            }
+            8 => {
+                let fs = self.freg(FFmt::S, available, add)?;
+                instr::move_from_fpu(self.new_int(), fs)
+            }
+            9 => match self.data_labels.is_empty() {
+                true => return Ok(()),
+                false => {
+                    let label = self.u.choose(self.data_labels)?.clone();
+                    instr::pseudo::load_address(self.new_int(), label)
+                }
+            },
+            10 => {
+                let kind = *self.u.choose(&KINDS)?;
+                let src = self.operand(kind, available, add)?;
+                let dst = match src {
+                    AnyReg::R(_) => self.new_reg(Kind::Int),
+                    AnyReg::F(reg) => self.new_freg(reg.ffmt()).into(),
+                };
+                instr::virt::move_(dst, src)
+            }
+            _ => match self.callees.is_empty() {
+                true => return Ok(()),
+                false => {
+                    let callee = self.u.choose(self.callees)?;
+                    let mut arguments = Vec::new();
+                    for &kind in &callee.params {
+                        let reg = self.operand(kind, available, add)?;
+                        arguments.push((reg, reg.stack_info()));
+                    }
+                    let return_reg = callee.return_kind.map(|kind| self.new_reg(kind));
+                    instr::virt::function_call(callee.label.clone(), return_reg, arguments)
+                }
+            },
+        };
+        if let Some(def) = instruction.defs().next() {
+            available.add(def);
+        }
+        add(instruction);
+        Ok(())
+    }
+
+    fn terminator(
+        &mut self,
+        available: &mut Available,
+        add: &mut impl FnMut(Instruction),
+        block_ids: &[BlockId],
+        block_args: &[Vec<AnyReg>],
+    ) -> Result<Terminator> {
+        // Returns the reference to a block, with the arguments it needs.
+        let mut target = |this: &mut Self, i: usize| -> Result<BlockRef> {
+            let mut arguments = Vec::new();
+            for &arg in &block_args[i] {
+                let kind = match arg {
+                    AnyReg::R(_) => Kind::Int,
+                    AnyReg::F(reg) if reg.is_double() => Kind::Double,
+                    AnyReg::F(_) => Kind::Single,
+                };
+                arguments.push(this.operand(kind, available, add)?);
+            }
+            Ok(BlockRef::new(block_ids[i], arguments))
+        };
+
+        let n_targets = match block_ids.len() {
+            0 => 0,
+            1 => self.u.int_in_range(0..=1)?,
+            _ => self.u.int_in_range(0..=2)?,
+        };
+        match n_targets {
+            0 => {
+                let value = match self.signature.return_kind {
+                    Some(kind) => Some(self.operand(kind, available, add)?),
+                    None => None,
+                };
+                Ok(term::virt::return_(value))
+            }
+            1 => {
+                let i = self.u.choose_index(block_ids.len())?;
+                Ok(term::jump(target(self, i)?))
+            }
+            _ => {
+                // The successors of a block have to be distinct.
+                let i = self.u.choose_index(block_ids.len())?;
+                let j = (i + 1 + self.u.choose_index(block_ids.len() - 1)?) % block_ids.len();
+                let (true_target, false_target) = (target(self, i)?, target(self, j)?);
+                Ok(match self.u.int_in_range(0..=2)? {
+                    0 => {
+                        let (rs, rt) = (self.int(available, add)?, self.int(available, add)?);
+                        let cond = *self.u.choose(&BCond::ALL)?;
+                        term::branch_if(cond, rs, rt, true_target, false_target)
+                    }
+                    1 => {
+                        let rs = self.int(available, add)?;
+                        let cond = *self.u.choose(&BZCond::ALL)?;
+                        term::branch_if_z(cond, rs, true_target, false_target)
+                    }
+                    _ => term::branch_if_f_cond(self.u.arbitrary()?, true_target, false_target),
+                })
+            }
+        }
+    }
+}
+
+fn instruction_defines(op: MemOp) -> bool {
+    matches!(
+        op,
+        MemOp::LoadByteS | MemOp::LoadByteU | MemOp::LoadHalfS | MemOp::LoadHalfU | MemOp::LoadWord
+    )
+}
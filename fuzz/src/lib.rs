@@ -0,0 +1,4 @@
+//! Generators for the fuzz targets that need structured input instead of arbitrary bytes.
+
+pub mod ast;
+pub mod cfg;
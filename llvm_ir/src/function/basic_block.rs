@@ -52,6 +52,11 @@ impl crate::FmtAsLlvmAsmFC for BasicBlock {
             instruction.fmt_as_llvm_asm(f, opts, module, function)?;
             f.write_char('\n')?
         }
+        for (_, comment) in comments {
+            for line in comment.lines() {
+                writeln!(f, "    ;{line}")?;
+            }
+        }
         f.write_str("    ")?;
         self.terminator.fmt_as_llvm_asm(f, opts, module, function)?;
         f.write_char('\n')
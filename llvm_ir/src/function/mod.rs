@@ -135,18 +135,22 @@ impl FunctionDeclaration {
         self.symbols.id(&handle.0)
     }
 
-    fn create_unnamed_id_handle(&mut self) -> LocalIdHandle {
+    pub(crate) fn has_id(&self, handle: &LocalIdHandle) -> bool {
+        self.symbols.contains(&handle.0)
+    }
+
+    pub(crate) fn create_unnamed_id_handle(&mut self) -> LocalIdHandle {
         LocalIdHandle(self.symbols.create_unnamed_id_handle())
     }
 
-    fn create_named_id_handle(&mut self, name: Name) -> Result<LocalIdHandle, String> {
+    pub(crate) fn create_named_id_handle(&mut self, name: Name) -> Result<LocalIdHandle, String> {
         self.symbols
             .create_named_id_handle(name)
             .map(LocalIdHandle)
             .map_err(|name| format!("function local name {name:?} shouldn't already exist"))
     }
 
-    fn update_unnamed_id(&mut self, handle: &LocalIdHandle) -> bool {
+    pub(crate) fn update_unnamed_id(&mut self, handle: &LocalIdHandle) -> bool {
         self.symbols.update_unnamed_id(&handle.0)
     }
 
@@ -208,6 +212,12 @@ impl FunctionDefinition {
         }
     }
 
+    /// Creates a definition from blocks whose labels and results were created in `declaration`,
+    /// without renumbering its unnamed ids.
+    pub(crate) fn from_body(declaration: FunctionDeclaration, body: Vec<BasicBlock>) -> Self {
+        Self { declaration, body }
+    }
+
     pub fn body(&self) -> &[BasicBlock] {
         &self.body
     }
@@ -334,8 +344,8 @@ fn fmt_function_as_llvm_asm_with_id(
     }
 
     if function.address_space != AddressSpace::default() {
-        function.address_space.fmt_as_llvm_asm(f, opts, module)?;
         f.write_char(' ')?;
+        function.address_space.fmt_as_llvm_asm(f, opts, module)?;
     }
 
     if function.alignment != Alignment::default() {
@@ -387,6 +397,12 @@ impl value::Value for Param {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct LocalIdHandle(IdHandle);
 
+impl LocalIdHandle {
+    pub(crate) fn index(&self) -> usize {
+        self.0.index()
+    }
+}
+
 // Note that `ReturnType` is not a `ty::Type`! (because void is not a type)
 #[derive(Debug, Clone, Default)]
 pub enum ReturnType {
@@ -59,6 +59,12 @@ impl IdHandle {
     pub fn is_valid(&self) -> bool {
         self.0.get() != usize::MAX
     }
+
+    /// The index of the id this handle points to. Handles that point to the same id have the same
+    /// index, so unlike the handle itself, this can be used as a key in maps.
+    pub fn index(&self) -> usize {
+        self.0.get()
+    }
 }
 
 impl PartialEq for IdHandle {
@@ -81,6 +87,12 @@ where
         &self.ids[handle.0.get()].1
     }
 
+    /// Returns `true` if `handle` can point to an id of this store, other stores give out the same
+    /// indices though.
+    pub fn contains(&self, handle: &IdHandle) -> bool {
+        handle.0.get() < self.ids.len()
+    }
+
     fn id_mut(&mut self, handle: &IdHandle) -> &mut I {
         &mut self.ids[handle.0.get()].1
     }
@@ -1,4 +1,4 @@
-use super::{IntoValidated, ValidationError};
+use super::{IntoValidated, Operands, ValidationError};
 use crate::ty::{self, Type};
 use crate::value::{self, Value};
 use crate::{AddressSpace, CallingConv};
@@ -138,3 +138,11 @@ impl crate::FmtAsLlvmAsmFC for Call {
         f.write_char(')')
     }
 }
+
+impl Operands for Call {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        std::iter::once(self.fn_pointer.clone().into())
+            .chain(self.fn_args.iter().cloned())
+            .collect()
+    }
+}
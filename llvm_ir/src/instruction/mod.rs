@@ -37,6 +37,14 @@ macro_rules! declare_type_union_validated {
             }
         }}
 
+        impl crate::instruction::Operands for $enum_name {
+            fn operands(&self) -> Vec<crate::value::FirstClass> {
+                match self {
+                    $(Self::$variant(instr) => instr.operands(),)*
+                }
+            }
+        }
+
         impl crate::FmtAsLlvmAsmFC for $enum_name {
             fn fmt_as_llvm_asm(
                 &self,
@@ -62,6 +70,12 @@ macro_rules! declare_type_union_validated {
                 }
             }
 
+            impl crate::instruction::Operands for $variant {
+                fn operands(&self) -> Vec<crate::value::FirstClass> {
+                    self.0.operands()
+                }
+            }
+
             impl crate::FmtAsLlvmAsmFC for $variant {
                 fn fmt_as_llvm_asm(
                     &self,
@@ -180,6 +194,13 @@ impl<U: IntoValidated<Terminator>> TryFrom_<U> for Terminator {
     }
 }
 
+/// The values used by an instruction, e.g. to verify a [`crate::Module`].
+pub(crate) trait Operands {
+    /// For terminators this doesn't include the labels of the successors, see
+    /// [`Terminator::successors`].
+    fn operands(&self) -> Vec<value::FirstClass>;
+}
+
 #[derive(Debug, Clone)]
 pub enum Instruction {
     Yielding(value::Register<ty::FirstClass>, YieldingInstruction),
@@ -222,3 +243,13 @@ impl crate::FmtAsLlvmAsmFC for Instruction {
         }
     }
 }
+
+impl Operands for Instruction {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        match self {
+            Instruction::Yielding(_, instruction) => instruction.operands(),
+            Instruction::MaybeYielding(_, instruction) => instruction.operands(),
+            Instruction::Void(instruction) => instruction.operands(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{IntoValidated, ValidationError};
+use super::{IntoValidated, Operands, ValidationError};
 use crate::value::{self, Value};
 use std::fmt::{self, Write};
 
@@ -284,6 +284,70 @@ where
     }
 }
 
+impl Terminator {
+    /// The labels of the blocks this terminator can jump to.
+    pub(crate) fn successors(&self) -> Vec<&value::Label> {
+        match self {
+            Self::ValidatedReturnVoid(_) | Self::ValidatedReturn(_) => Vec::new(),
+            Self::ValidatedBranch(branch) => vec![&branch.dest],
+            Self::ValidatedBranchConditional(branch) => vec![&branch.dest_true, &branch.dest_false],
+            Self::ValidatedIndirectBranch(branch) => branch.targets.iter().collect(),
+            Self::ValidatedSwitch(switch) => std::iter::once(&switch.default_dest)
+                .chain(switch.branches.iter().map(|(_, label)| label))
+                .collect(),
+        }
+    }
+}
+
+impl Operands for ReturnVoid {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        Vec::new()
+    }
+}
+
+impl<V: value::ElementValue> Operands for Return<V> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.0.clone().into()]
+    }
+}
+
+impl<V: value::LabelValue> Operands for Branch<V> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        Vec::new()
+    }
+}
+
+impl<C, T, F> Operands for BranchConditional<C, T, F>
+where
+    C: value::BooleanValue,
+    T: value::LabelValue,
+    F: value::LabelValue,
+{
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.cond.clone().into()]
+    }
+}
+
+impl<A, L> Operands for IndirectBranch<A, L>
+where
+    A: value::PointerValue,
+    L: value::LabelValue,
+{
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.address.clone().into()]
+    }
+}
+
+impl<V, L> Operands for Switch<V, L>
+where
+    V: value::IntegerValue,
+    L: value::LabelValue,
+{
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.value.clone().into()]
+    }
+}
+
 // #[derive(Debug, Clone)]
 // pub struct Invoke {}
 
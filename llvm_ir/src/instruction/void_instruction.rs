@@ -1,4 +1,4 @@
-use super::{IntoValidated, ValidationError};
+use super::{IntoValidated, Operands, ValidationError};
 use crate::ty::{self, Type};
 use crate::value::{self, Value};
 use std::fmt::{self, Write};
@@ -79,3 +79,9 @@ impl<V: value::ElementValue, P: value::PointerValue> crate::FmtAsLlvmAsmFC for S
         self.pointer.fmt_as_llvm_asm(f, opts, module, function)
     }
 }
+
+impl<V: value::ElementValue, P: value::PointerValue> Operands for Store<V, P> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.value.clone().into(), self.pointer.clone().into()]
+    }
+}
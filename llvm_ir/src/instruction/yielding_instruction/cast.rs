@@ -72,6 +72,12 @@ macro_rules! declare_cast_ops {
                     self.to_ty.fmt_as_llvm_asm(f, opts, module)
                 }
             }
+
+            impl<V: value::$value, T: ty::$ty> crate::instruction::Operands for $name<V, T> {
+                fn operands(&self) -> Vec<value::FirstClass> {
+                    vec![self.value.clone().into()]
+                }
+            }
         )+
     };
 }
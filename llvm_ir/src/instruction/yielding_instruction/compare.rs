@@ -163,6 +163,12 @@ macro_rules! fmt_compare_op {
                 self.operand2.fmt_as_llvm_asm(f, opts, module, function)
             }
         }
+
+        impl<V: value::$gen> crate::instruction::Operands for $operation<V> {
+            fn operands(&self) -> Vec<value::FirstClass> {
+                vec![self.operand1.clone().into(), self.operand2.clone().into()]
+            }
+        }
     };
 }
 
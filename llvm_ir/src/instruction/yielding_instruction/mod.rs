@@ -8,7 +8,7 @@ pub use cast::ValidatedCastOp;
 pub use compare::{FcmpCond, IcmpCond, ValidatedCompareOp};
 pub use unary_op::{UnaryFpOp, ValidatedUnaryOp};
 
-use super::{IntoValidated, ValidationError};
+use super::{IntoValidated, Operands, ValidationError};
 use crate::ty::{self, Type};
 use crate::value::{self, Value};
 use std::fmt::{self, Write};
@@ -139,6 +139,12 @@ impl<V: value::AggregateValue> crate::FmtAsLlvmAsmFC for ExtractValue<V> {
     }
 }
 
+impl<V: value::AggregateValue> Operands for ExtractValue<V> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.value.clone().into()]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GetElementPtr<T: ty::ElementType> {
     pub ty: T,
@@ -234,6 +240,14 @@ impl<T: ty::ElementType> crate::FmtAsLlvmAsmFC for GetElementPtr<T> {
     }
 }
 
+impl<T: ty::ElementType> Operands for GetElementPtr<T> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        std::iter::once(self.pointer.clone().into())
+            .chain(self.indices.iter().map(|index| index.clone().into()))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Phi<V: value::FirstClassValue> {
     pub head: (V, value::constant::Label),
@@ -297,6 +311,21 @@ impl<V: value::FirstClassValue> crate::FmtAsLlvmAsmFC for Phi<V> {
     }
 }
 
+impl<V: value::FirstClassValue> Phi<V> {
+    /// The incoming values, together with the label of the block they come from.
+    pub fn incoming(&self) -> impl Iterator<Item = &(V, value::constant::Label)> {
+        std::iter::once(&self.head).chain(&self.tail)
+    }
+}
+
+impl<V: value::FirstClassValue> Operands for Phi<V> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        self.incoming()
+            .map(|(value, _)| value.clone().into())
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Alloca<T: ty::ElementType> {
     pub ty: T,
@@ -346,6 +375,15 @@ impl<T: ty::ElementType> crate::FmtAsLlvmAsmFC for Alloca<T> {
     }
 }
 
+impl<T: ty::ElementType> Operands for Alloca<T> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        self.amount
+            .iter()
+            .map(|amount| amount.clone().into())
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Load<T: ty::ElementType> {
     pub ty: T,
@@ -404,3 +442,9 @@ impl<T: ty::ElementType> crate::FmtAsLlvmAsmFC for Load<T> {
         self.pointer.fmt_as_llvm_asm(f, opts, module, function)
     }
 }
+
+impl<T: ty::ElementType> Operands for Load<T> {
+    fn operands(&self) -> Vec<value::FirstClass> {
+        vec![self.pointer.clone().into()]
+    }
+}
@@ -58,6 +58,12 @@ macro_rules! declare_unary_op {
                 self.operand.fmt_as_llvm_asm(f, opts, module, function)
             }
         }
+
+        impl<V: value::$gen> crate::instruction::Operands for $operation<V> {
+            fn operands(&self) -> Vec<value::FirstClass> {
+                vec![self.operand.clone().into()]
+            }
+        }
     };
 }
 
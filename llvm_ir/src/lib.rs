@@ -8,6 +8,8 @@ mod global_var;
 mod id;
 mod id_store;
 mod module;
+mod parser;
+mod verify;
 
 pub mod instruction;
 pub mod ty;
@@ -18,7 +20,9 @@ pub use fmt_as_llvm_asm::FmtOpts;
 pub use function::*;
 pub use global_var::*;
 pub use module::*;
+pub use parser::{parse, ParseError};
 pub use value::constant;
+pub use verify::VerifyError;
 
 use fmt_as_llvm_asm::*;
 use std::fmt;
@@ -421,6 +421,69 @@ impl Module {
             .find_map(|(handle, fd)| std::ptr::eq(fd, function_definition).then_some(handle))
     }
 
+    pub(crate) fn function_definitions(
+        &self,
+    ) -> impl Iterator<Item = (&GlobalIdHandle, &FunctionDefinition)> {
+        self.function_definitions
+            .iter()
+            .map(|(handle, definition)| (handle, definition))
+    }
+
+    /// Returns the type of the function declared or defined with the given handle.
+    pub(crate) fn function_type(&self, handle: &GlobalIdHandle) -> Option<ty::Function> {
+        let declarations = self.function_declarations.iter().flatten();
+        let definitions = self
+            .function_definitions
+            .iter()
+            .map(|(handle, definition)| (handle, &**definition));
+        declarations
+            .map(|(handle, declaration)| (handle, declaration))
+            .chain(definitions)
+            .find(|(h, _)| *h == handle)
+            .map(|(_, declaration)| declaration.ty())
+    }
+
+    /// Adds a global variable declaration with an id that was created in advance, e.g. by the
+    /// parser, which creates the ids of globals when they're first used.
+    pub(crate) fn push_global_var_declaration(
+        &mut self,
+        handle: GlobalIdHandle,
+        global_var_declaration: GlobalVarDeclaration,
+    ) {
+        self.global_var_declarations
+            .push((handle, global_var_declaration));
+    }
+
+    /// See [`Self::push_global_var_declaration`].
+    pub(crate) fn push_global_var_definition(
+        &mut self,
+        handle: GlobalIdHandle,
+        global_var_definition: GlobalVarDefinition,
+    ) {
+        self.global_var_definitions
+            .push((handle, global_var_definition));
+    }
+
+    /// See [`Self::push_global_var_declaration`].
+    pub(crate) fn push_function_declaration(
+        &mut self,
+        handle: GlobalIdHandle,
+        function_declaration: FunctionDeclaration,
+    ) {
+        self.function_declarations
+            .push(Some((handle, function_declaration)));
+    }
+
+    /// See [`Self::push_global_var_declaration`].
+    pub(crate) fn push_function_definition(
+        &mut self,
+        handle: GlobalIdHandle,
+        function_definition: FunctionDefinition,
+    ) {
+        self.function_definitions
+            .push((handle, function_definition));
+    }
+
     pub(crate) fn global_id(&self, handle: &GlobalIdHandle) -> &id::Global {
         self.global_symbols.id(&handle.0)
     }
@@ -429,11 +492,14 @@ impl Module {
         self.local_symbols.id(&handle.0)
     }
 
-    fn create_global_unnamed_id_handle(&mut self) -> GlobalIdHandle {
+    pub(crate) fn create_global_unnamed_id_handle(&mut self) -> GlobalIdHandle {
         GlobalIdHandle(self.global_symbols.create_unnamed_id_handle())
     }
 
-    fn create_global_named_id_handle(&mut self, name: Name) -> Result<GlobalIdHandle, String> {
+    pub(crate) fn create_global_named_id_handle(
+        &mut self,
+        name: Name,
+    ) -> Result<GlobalIdHandle, String> {
         self.global_symbols
             .create_named_id_handle(name)
             .map(GlobalIdHandle)
@@ -451,7 +517,7 @@ impl Module {
             .map_err(|name| format!("module local name {name:?} shouldn't already exist"))
     }
 
-    fn update_global_unnamed_id(&mut self, handle: &GlobalIdHandle) -> bool {
+    pub(crate) fn update_global_unnamed_id(&mut self, handle: &GlobalIdHandle) -> bool {
         self.global_symbols.update_unnamed_id(&handle.0)
     }
 
@@ -0,0 +1,303 @@
+use super::ParseError;
+use std::collections::HashMap;
+use std::fmt;
+
+/// An identifier as written in the assembly, without its `@` or `%` prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum Id {
+    Named(String),
+    Unnamed(usize),
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::Named(name) if is_plain_name(name) => f.write_str(name),
+            Id::Named(name) => crate::fmt_as_llvm_quoted_string(f, name.bytes()),
+            Id::Unnamed(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    /// A keyword or a primitive type, e.g. `define` or `i32`.
+    Word(String),
+    GlobalId(Id),
+    LocalId(Id),
+    /// The definition of a block label, e.g. `entry:` or `3:`.
+    Label(Id),
+    Integer(i128),
+    /// A hexadecimal constant, i.e. the bit pattern of a floating point constant.
+    Hex(u128),
+    Float(f64),
+    String(Vec<u8>),
+    /// A character array constant, e.g. `c"foo\00"`.
+    CharArray(Vec<u8>),
+    Ellipsis,
+    Punct(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{word}`"),
+            Token::GlobalId(id) => write!(f, "`@{id}`"),
+            Token::LocalId(id) => write!(f, "`%{id}`"),
+            Token::Label(id) => write!(f, "label `{id}:`"),
+            Token::Integer(n) => write!(f, "`{n}`"),
+            Token::Hex(n) => write!(f, "`{n:#x}`"),
+            Token::Float(n) => write!(f, "`{n:?}`"),
+            Token::String(_) => f.write_str("a string"),
+            Token::CharArray(_) => f.write_str("a character array"),
+            Token::Ellipsis => f.write_str("`...`"),
+            Token::Punct(c) => write!(f, "`{c}`"),
+        }
+    }
+}
+
+/// The tokens of the assembly, together with the comments that are on a line of their own.
+pub(super) struct Lexed {
+    /// Every token with its (1-based) line.
+    pub tokens: Vec<(Token, usize)>,
+    /// The text after the `;` of every comment that is the only thing on its line.
+    pub comments: HashMap<usize, String>,
+}
+
+pub(super) fn lex(source: &str) -> Result<Lexed, ParseError> {
+    let mut lexer = Lexer {
+        source: source.as_bytes(),
+        pos: 0,
+        line: 1,
+        lexed: Lexed {
+            tokens: Vec::new(),
+            comments: HashMap::new(),
+        },
+    };
+    while lexer.skip_whitespace() {
+        let token = lexer.lex_token().map_err(|message| ParseError {
+            line: lexer.line,
+            message,
+        })?;
+        if let Some(token) = token {
+            lexer.lexed.tokens.push((token, lexer.line));
+        }
+    }
+    Ok(lexer.lexed)
+}
+
+struct Lexer<'a> {
+    source: &'a [u8],
+    pos: usize,
+    line: usize,
+    lexed: Lexed,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source.get(self.pos + offset).copied()
+    }
+
+    /// Returns `false` at the end of the source.
+    fn skip_whitespace(&mut self) -> bool {
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' => self.line += 1,
+                c if c.is_ascii_whitespace() => {}
+                _ => return true,
+            }
+            self.pos += 1;
+        }
+        false
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += 1;
+        }
+        eaten
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        // Only ascii characters are taken.
+        std::str::from_utf8(&self.source[start..self.pos]).unwrap()
+    }
+
+    /// Returns `None` for comments.
+    fn lex_token(&mut self) -> Result<Option<Token>, String> {
+        let c = self.peek().unwrap();
+        let token = match c {
+            b';' => {
+                self.pos += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != b'\n') {
+                    self.pos += 1;
+                }
+                let text = String::from_utf8_lossy(&self.source[start..self.pos]);
+                let own_line = self.lexed.tokens.last().is_none_or(|t| t.1 != self.line);
+                if own_line {
+                    let text = text.strip_suffix('\r').unwrap_or(&text);
+                    self.lexed.comments.insert(self.line, text.to_owned());
+                }
+                return Ok(None);
+            }
+            b'@' | b'%' => {
+                self.pos += 1;
+                let id = self.lex_id()?;
+                if c == b'@' {
+                    Token::GlobalId(id)
+                } else {
+                    Token::LocalId(id)
+                }
+            }
+            b'"' => {
+                self.pos += 1;
+                let string = self.lex_string()?;
+                if self.eat(b':') {
+                    Token::Label(Id::Named(into_name(string)?))
+                } else {
+                    Token::String(string)
+                }
+            }
+            b'.' if self.source[self.pos..].starts_with(b"...") => {
+                self.pos += 3;
+                Token::Ellipsis
+            }
+            b'-' | b'0'..=b'9' => self.lex_number()?,
+            c if is_name_char(c) => {
+                let word = self.take_while(is_name_char).to_owned();
+                if word == "c" && self.eat(b'"') {
+                    Token::CharArray(self.lex_string()?)
+                } else if self.eat(b':') {
+                    Token::Label(Id::Named(word))
+                } else {
+                    Token::Word(word)
+                }
+            }
+            b'=' | b',' | b'(' | b')' | b'[' | b']' | b'{' | b'}' | b'<' | b'>' => {
+                self.pos += 1;
+                Token::Punct(c.into())
+            }
+            _ => {
+                let c = String::from_utf8_lossy(&self.source[self.pos..])
+                    .chars()
+                    .next()
+                    .unwrap();
+                return Err(format!("unexpected character `{c}`"));
+            }
+        };
+        Ok(Some(token))
+    }
+
+    fn lex_id(&mut self) -> Result<Id, String> {
+        match self.peek() {
+            Some(b'"') => {
+                self.pos += 1;
+                Ok(Id::Named(into_name(self.lex_string()?)?))
+            }
+            Some(b'0'..=b'9') => self
+                .take_while(|c| c.is_ascii_digit())
+                .parse()
+                .map(Id::Unnamed)
+                .map_err(|_| "id number is too large".to_owned()),
+            Some(c) if is_name_char(c) => Ok(Id::Named(self.take_while(is_name_char).to_owned())),
+            _ => Err("expected a name or number after `@` or `%`".to_owned()),
+        }
+    }
+
+    /// Lexes the rest of a string, after the opening `"`.
+    fn lex_string(&mut self) -> Result<Vec<u8>, String> {
+        let mut string = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err("unterminated string".to_owned()),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') if self.peek_at(1) == Some(b'\\') => {
+                    self.pos += 2;
+                    string.push(b'\\');
+                }
+                Some(b'\\') => {
+                    let digits = self
+                        .source
+                        .get(self.pos + 1..self.pos + 3)
+                        .and_then(|digits| std::str::from_utf8(digits).ok())
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                        .ok_or("expected two hexadecimal digits after `\\`")?;
+                    self.pos += 3;
+                    string.push(digits);
+                }
+                Some(c) => {
+                    if c == b'\n' {
+                        self.line += 1;
+                    }
+                    self.pos += 1;
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    fn lex_number(&mut self) -> Result<Token, String> {
+        if self.source[self.pos..].starts_with(b"0x") {
+            self.pos += 2;
+            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+            return u128::from_str_radix(digits, 16)
+                .map(Token::Hex)
+                .map_err(|_| "invalid hexadecimal constant".to_owned());
+        }
+        let start = self.pos;
+        self.eat(b'-');
+        self.take_while(|c| c.is_ascii_digit());
+        let mut is_float = false;
+        if self.eat(b'.') {
+            is_float = true;
+            self.take_while(|c| c.is_ascii_digit());
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            is_float = true;
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            self.take_while(|c| c.is_ascii_digit());
+        }
+        let text = std::str::from_utf8(&self.source[start..self.pos]).unwrap();
+        if is_float {
+            text.parse()
+                .map(Token::Float)
+                .map_err(|_| format!("invalid floating point constant `{text}`"))
+        } else if !text.starts_with('-') && self.eat(b':') {
+            text.parse()
+                .map(|n| Token::Label(Id::Unnamed(n)))
+                .map_err(|_| "label number is too large".to_owned())
+        } else {
+            text.parse()
+                .map(Token::Integer)
+                .map_err(|_| format!("invalid integer constant `{text}`"))
+        }
+    }
+}
+
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'-' | b'$' | b'.' | b'_')
+}
+
+/// Whether the name can be written without quotes.
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.as_bytes()[0].is_ascii_digit() && name.bytes().all(is_name_char)
+}
+
+fn into_name(bytes: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(bytes).map_err(|_| "names must be valid UTF-8".to_owned())
+}
@@ -0,0 +1,1551 @@
+mod lexer;
+
+use crate::function::LocalIdHandle;
+use crate::instruction::{
+    binary_op, cast, compare, unary_op, Alloca, BinaryFpOp, BinaryIntOp, Branch, BranchConditional,
+    Call, ExtractValue, FcmpCond, GetElementPtr, IcmpCond, IndirectBranch, Instruction,
+    IntoValidated, Load, MaybeYielding, MaybeYieldingInstruction, Phi, Return, ReturnVoid, Store,
+    Switch, Terminator, UnaryFpOp, VoidInstruction, Yielding, YieldingInstruction,
+};
+use crate::module::GlobalIdHandle;
+use crate::ty::{self, Type};
+use crate::value::{self, constant, Value};
+use crate::{
+    AddressSignificance, AddressSpace, Alignment, BasicBlock, CallingConv, FunctionDeclaration,
+    FunctionDefinition, GlobalVarDeclaration, GlobalVarDefinition, Linkage, Module, ReturnType,
+    TargetTriple, Visibility,
+};
+use lexer::{Id, Token};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The (1-based) line of the assembly text where the error occurred.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the LLVM assembly that [`Module::display`] prints back into a [`Module`], so that
+/// printing the result gives the same text (with `display_unnamed_ids` enabled).
+///
+/// The module name is taken from the `; module = NAME` comment on the first line. Comments on a
+/// line of their own are kept when they are directly above a global, a function, a block label
+/// or an instruction; other comments are dropped.
+///
+/// Globals and the locals of a function can be used before they're defined, but types have to be
+/// defined before they're used. Unnamed ids must be numbered in the order of their definitions,
+/// just like LLVM requires, and the result of an instruction may be left out, in which case it
+/// gets the next unnamed id. The instructions are validated like the
+/// [`FunctionDefinitionBuilder`](crate::FunctionDefinitionBuilder) does, but the module as a
+/// whole isn't, see [`Module::verify`] for that.
+pub fn parse(source: &str) -> Result<Module, ParseError> {
+    let lexer::Lexed {
+        tokens,
+        mut comments,
+    } = lexer::lex(source)?;
+    let name = match comments.get(&1).and_then(|c| c.strip_prefix(" module = ")) {
+        Some(name) => {
+            let name = name.to_owned();
+            comments.remove(&1);
+            name
+        }
+        None => String::new(),
+    };
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+        line: 1,
+        comments,
+        module: Module::new(name),
+        types: HashMap::new(),
+        unnamed_type_count: 0,
+        globals: HashMap::new(),
+        unnamed_globals: Vec::new(),
+    };
+    parser.parse_module().map_err(|message| ParseError {
+        line: parser.line,
+        message,
+    })?;
+    Ok(parser.module)
+}
+
+struct Global {
+    handle: GlobalIdHandle,
+    is_defined: bool,
+    /// The line where the global is first used or defined.
+    line: usize,
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<(Token, usize)>>,
+    /// The line of the last token that was taken, which is where errors are reported.
+    line: usize,
+    comments: HashMap<usize, String>,
+    module: Module,
+    types: HashMap<Id, ty::FirstClass>,
+    unnamed_type_count: usize,
+    globals: HashMap<Id, Global>,
+    /// The unnamed globals, in the order of their definitions.
+    unnamed_globals: Vec<GlobalIdHandle>,
+}
+
+impl Parser {
+    fn parse_module(&mut self) -> Result<(), String> {
+        while let Some(line) = self.peek_line() {
+            let comment = self.comment_above(line);
+            match self.next()? {
+                Token::Word(word) if word == "source_filename" => {
+                    self.expect_punct('=')?;
+                    let source_filename = self.string()?;
+                    self.module.set_source_filename(source_filename);
+                }
+                Token::Word(word) if word == "target" => {
+                    self.expect_word("triple")?;
+                    self.expect_punct('=')?;
+                    let target_triple = self.target_triple()?;
+                    self.module.set_target_triple(target_triple);
+                }
+                Token::Word(word) if word == "declare" || word == "define" => {
+                    self.parse_function(word == "define", comment)?;
+                }
+                Token::LocalId(id) => {
+                    self.expect_punct('=')?;
+                    self.expect_word("type")?;
+                    self.parse_type_definition(id)?;
+                }
+                Token::GlobalId(id) => {
+                    self.expect_punct('=')?;
+                    self.parse_global_var(id, comment)?;
+                }
+                token => {
+                    return Err(format!(
+                        "expected a global, a function or a type definition, found {token}"
+                    ))
+                }
+            }
+        }
+
+        if let Some((id, global)) = self
+            .globals
+            .iter()
+            .filter(|(_, global)| !global.is_defined)
+            .min_by_key(|(_, global)| global.line)
+        {
+            self.line = global.line;
+            return Err(format!("@{id} is used, but never defined"));
+        }
+        for handle in &self.unnamed_globals {
+            self.module.update_global_unnamed_id(handle);
+        }
+        Ok(())
+    }
+
+    fn target_triple(&mut self) -> Result<TargetTriple, String> {
+        let triple = self.string()?;
+        let mut parts = triple.split('-').map(str::to_owned);
+        match (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) {
+            (Some(architecture), Some(vendor), Some(operating_system), environment, None) => {
+                Ok(TargetTriple {
+                    architecture,
+                    vendor,
+                    operating_system,
+                    environment,
+                })
+            }
+            _ => Err(format!("invalid target triple \"{triple}\"")),
+        }
+    }
+
+    fn parse_type_definition(&mut self, id: Id) -> Result<(), String> {
+        if self.types.contains_key(&id) {
+            return Err(format!("type %{id} is defined twice"));
+        }
+        check_numbering('%', &id, self.unnamed_type_count)?;
+        let ty: ty::FirstClass = if self.eat_word("opaque") {
+            match &id {
+                Id::Named(name) => self.module.declare_type_named(name.into())?.1.into(),
+                Id::Unnamed(_) => self.module.declare_type().1.into(),
+            }
+        } else {
+            let ty = self.parse_type()?;
+            match &id {
+                Id::Named(name) => self.module.define_type_named(name.into(), ty)?.into(),
+                Id::Unnamed(_) => self.module.define_type(ty).into(),
+            }
+        };
+        if let Id::Unnamed(_) = id {
+            self.unnamed_type_count += 1;
+        }
+        self.types.insert(id, ty);
+        Ok(())
+    }
+
+    fn parse_global_var(&mut self, id: Id, comment: Option<String>) -> Result<(), String> {
+        let handle = self.define_global(id)?;
+        let linkage = self.parse_linkage();
+        let visibility = self.parse_visibility();
+        let address_significance = self.parse_address_significance();
+        let address_space = self.parse_address_space()?;
+        let is_constant = match self.next()? {
+            Token::Word(word) if word == "global" => false,
+            Token::Word(word) if word == "constant" => true,
+            token => return Err(format!("expected `global` or `constant`, found {token}")),
+        };
+        let ty = self.parse_element_type()?;
+
+        // Like in LLVM, a global without initializer is declared by giving it external linkage.
+        if let Some(linkage @ (Linkage::External | Linkage::ExternWeak)) = linkage {
+            let mut declaration = GlobalVarDeclaration::new(ty, is_constant)
+                .with_comment(comment)
+                .with_linkage(linkage)
+                .with_visibility(visibility)
+                .with_address_space(address_space)
+                .with_alignment(self.parse_global_alignment()?);
+            declaration.address_significance = address_significance;
+            self.module.push_global_var_declaration(handle, declaration);
+        } else {
+            let value = self.parse_constant(&ty)?;
+            let mut definition = GlobalVarDefinition::new(value, is_constant)
+                .with_comment(comment)
+                .with_linkage(linkage.unwrap_or_default())
+                .with_visibility(visibility)
+                .with_address_space(address_space)
+                .with_alignment(self.parse_global_alignment()?);
+            definition.address_significance = address_significance;
+            self.module.push_global_var_definition(handle, definition);
+        }
+        Ok(())
+    }
+
+    fn parse_global_alignment(&mut self) -> Result<Alignment, String> {
+        if self.eat_punct(',') {
+            self.expect_word("align")?;
+            Ok(Alignment(self.unsigned()?))
+        } else {
+            Ok(Alignment::default())
+        }
+    }
+
+    fn parse_function(
+        &mut self,
+        is_definition: bool,
+        comment: Option<String>,
+    ) -> Result<(), String> {
+        let linkage = self.parse_linkage().unwrap_or_default();
+        let visibility = self.parse_visibility();
+        let calling_conv = self.parse_calling_conv()?;
+        let return_type = self.parse_return_type()?;
+        let handle = match self.next()? {
+            Token::GlobalId(id) => self.define_global(id)?,
+            token => return Err(format!("expected the name of the function, found {token}")),
+        };
+        let declaration = FunctionDeclaration::new(return_type)
+            .with_comment(comment)
+            .with_linkage(linkage)
+            .with_visibility(visibility)
+            .with_calling_conv(calling_conv);
+
+        let mut function = FunctionParser {
+            parser: self,
+            declaration,
+            locals: HashMap::new(),
+            unnamed_locals: Vec::new(),
+        };
+        function.parse_params()?;
+        let declaration = &mut function.declaration;
+        declaration.address_significance = function.parser.parse_address_significance();
+        declaration.address_space = function.parser.parse_address_space()?;
+        if function.parser.eat_word("align") {
+            declaration.alignment = Alignment(function.parser.unsigned()?);
+        }
+
+        if is_definition {
+            function.parser.expect_punct('{')?;
+            let (declaration, body) = function.parse_body()?;
+            let definition = FunctionDefinition::from_body(declaration, body);
+            self.module.push_function_definition(handle, definition);
+        } else {
+            let declaration = function.declaration;
+            self.module.push_function_declaration(handle, declaration);
+        }
+        Ok(())
+    }
+
+    fn parse_linkage(&mut self) -> Option<Linkage> {
+        let linkage = match self.peek_word()? {
+            "private" => Linkage::Private,
+            "internal" => Linkage::Internal,
+            "available_externally" => Linkage::AvailableExternally,
+            "linkonce" => Linkage::Linkonce,
+            "weak" => Linkage::Weak,
+            "common" => Linkage::Common,
+            "appending" => Linkage::Appending,
+            "extern_weak" => Linkage::ExternWeak,
+            "linkonce_odr" => Linkage::LinkonceOdr,
+            "weak_odr" => Linkage::WeakOdr,
+            "external" => Linkage::External,
+            _ => return None,
+        };
+        self.advance();
+        Some(linkage)
+    }
+
+    fn parse_visibility(&mut self) -> Visibility {
+        let visibility = match self.peek_word() {
+            Some("default") => Visibility::Default,
+            Some("hidden") => Visibility::Hidden,
+            Some("protected") => Visibility::Protected,
+            _ => return Visibility::default(),
+        };
+        self.advance();
+        visibility
+    }
+
+    fn parse_address_significance(&mut self) -> Option<AddressSignificance> {
+        let address_significance = match self.peek_word()? {
+            "unnamed_addr" => AddressSignificance::Unnamed,
+            "local_unnamed_addr" => AddressSignificance::LocalUnnamed,
+            _ => return None,
+        };
+        self.advance();
+        Some(address_significance)
+    }
+
+    fn parse_address_space(&mut self) -> Result<AddressSpace, String> {
+        if !self.eat_word("addrspace") {
+            return Ok(AddressSpace::default());
+        }
+        self.expect_punct('(')?;
+        let address_space = self.unsigned()?;
+        self.expect_punct(')')?;
+        Ok(AddressSpace(address_space))
+    }
+
+    fn parse_calling_conv(&mut self) -> Result<CallingConv, String> {
+        use crate::cc;
+        let calling_conv = match self.peek_word() {
+            Some("ccc") => cc::C,
+            Some("fastcc") => cc::FAST,
+            Some("coldcc") => cc::COLD,
+            Some("webkit_jscc") => cc::WEBKIT_JS,
+            Some("anyregcc") => cc::ANY_REG,
+            Some("preserve_mostcc") => cc::PRESERVE_MOST,
+            Some("preserve_allcc") => cc::PRESERVE_ALL,
+            Some("swiftcc") => cc::SWIFT,
+            Some("cxx_fast_tlscc") => cc::CXX_FAST_TLS,
+            Some("tailcc") => cc::TAIL,
+            Some("cfguard_checkcc") => cc::CFGUARD_CHECK,
+            Some("swifttailcc") => cc::SWIFT_TAIL,
+            Some("cc") => {
+                self.advance();
+                return Ok(CallingConv(self.unsigned()?));
+            }
+            _ => return Ok(CallingConv::default()),
+        };
+        self.advance();
+        Ok(CallingConv(calling_conv))
+    }
+
+    fn parse_return_type(&mut self) -> Result<ReturnType, String> {
+        if self.eat_word("void") {
+            Ok(ReturnType::Void)
+        } else {
+            Ok(ReturnType::Element(self.parse_element_type()?))
+        }
+    }
+
+    fn parse_element_type(&mut self) -> Result<ty::Element, String> {
+        ty::Element::try_from(self.parse_type()?)
+            .map_err(|_| "`label` can't be used as a type here".to_owned())
+    }
+
+    fn parse_type(&mut self) -> Result<ty::FirstClass, String> {
+        Ok(match self.next()? {
+            Token::Word(word) => match word.as_str() {
+                "half" => ty::Half::new_literal().into(),
+                "bfloat" => ty::BFloat::new_literal().into(),
+                "float" => ty::Float::new_literal().into(),
+                "double" => ty::Double::new_literal().into(),
+                "fp128" => ty::Fp128::new_literal().into(),
+                "x86fp80" | "x86_fp80" => ty::X86Fp80::new_literal().into(),
+                "ppcfp128" | "ppc_fp128" => ty::PpcFp128::new_literal().into(),
+                "label" => ty::Label::new_literal().into(),
+                "ptr" => ty::Pointer::new_literal()
+                    .with_address_space(self.parse_address_space()?)
+                    .build()
+                    .into(),
+                _ => match word.strip_prefix('i').and_then(|n| n.parse().ok()) {
+                    Some(bit_size) => ty::Integer::new_literal(bit_size)?.into(),
+                    None => return Err(format!("expected a type, found `{word}`")),
+                },
+            },
+            Token::LocalId(id) => self
+                .types
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| format!("type %{id} is not defined"))?,
+            Token::Punct('[') => {
+                let size = self.unsigned()?;
+                self.expect_word("x")?;
+                let element_type = self.parse_element_type()?;
+                self.expect_punct(']')?;
+                ty::Array::new_literal(element_type)?
+                    .with_size(size)
+                    .build()
+                    .into()
+            }
+            Token::Punct('<') if self.eat_punct('{') => {
+                let body_types = self.parse_structure_body()?;
+                self.expect_punct('>')?;
+                ty::Structure::new_literal(body_types)
+                    .with_packed(true)
+                    .build()
+                    .into()
+            }
+            Token::Punct('<') => {
+                let is_scalable = self.eat_word("vscale");
+                if is_scalable {
+                    self.expect_word("x")?;
+                }
+                let size = self.unsigned()?;
+                self.expect_word("x")?;
+                let element_type = ty::Primitive::try_from(self.parse_type()?)
+                    .map_err(|_| "the elements of a vector must have a primitive type")?;
+                self.expect_punct('>')?;
+                ty::Vector::new_literal(element_type)
+                    .with_size(size)?
+                    .with_scalable(is_scalable)
+                    .build()
+                    .into()
+            }
+            Token::Punct('{') => ty::Structure::new_literal(self.parse_structure_body()?)
+                .build()
+                .into(),
+            token => return Err(format!("expected a type, found {token}")),
+        })
+    }
+
+    /// Parses the types of a structure, after the opening `{`.
+    fn parse_structure_body(&mut self) -> Result<Vec<ty::Element>, String> {
+        let mut body_types = Vec::new();
+        if !self.eat_punct('}') {
+            loop {
+                body_types.push(self.parse_element_type()?);
+                if !self.eat_punct(',') {
+                    break;
+                }
+            }
+            self.expect_punct('}')?;
+        }
+        Ok(body_types)
+    }
+
+    fn parse_constant(&mut self, ty: &ty::Element) -> Result<constant::Element, String> {
+        use ty::{Aggregate, Element, Primitive, Single};
+
+        let token = self.next()?;
+        match &token {
+            Token::Word(word) if word == "undef" => return Ok(constant::Undef(ty.clone()).into()),
+            Token::Word(word) if word == "poison" => return Ok(constant::Poison(ty.clone()).into()),
+            Token::Word(word) if word == "zeroinitializer" => {
+                return Ok(constant::ZeroInitializer(ty.clone()).into())
+            }
+            _ => {}
+        }
+        let invalid = |token: &Token| format!("{token} is not a valid constant of this type");
+
+        Ok(match ty {
+            Element::Single(Single::Primitive(Primitive::Integer(ty))) => {
+                let value = match token {
+                    Token::Integer(n) => n,
+                    Token::Word(word) if word == "true" => 1,
+                    Token::Word(word) if word == "false" => 0,
+                    token => return Err(invalid(&token)),
+                };
+                constant::Integer::new(ty.clone(), value).into()
+            }
+            Element::Single(Single::Primitive(Primitive::FloatingPoint(ty))) => {
+                floating_point_constant(ty, &token).ok_or_else(|| invalid(&token))?
+            }
+            Element::Single(Single::Primitive(Primitive::Pointer(ty))) => match token {
+                Token::Word(word) if word == "null" => constant::Pointer::null_typed(ty.clone()),
+                Token::GlobalId(id) => {
+                    let handle = self.use_global(id);
+                    constant::Pointer::from_global_address(ty.clone(), handle)
+                }
+                token => return Err(invalid(&token)),
+            }
+            .into(),
+            Element::Single(Single::Vector(ty)) => {
+                if token != Token::Punct('<') {
+                    return Err(invalid(&token));
+                }
+                let elements = self
+                    .parse_constant_elements('>')?
+                    .into_iter()
+                    .map(constant::Primitive::try_from)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "the elements of a vector must be primitive constants")?;
+                constant::Vector::new_typed(ty.clone(), elements)?.into()
+            }
+            Element::Aggregate(Aggregate::Array(ty)) => match token {
+                Token::CharArray(bytes) => {
+                    constant::Array::new_char_array_typed(ty.clone(), bytes)?
+                }
+                Token::Punct('[') => {
+                    let elements = self.parse_constant_elements(']')?;
+                    constant::Array::new_typed(ty.clone(), elements)?
+                }
+                token => return Err(invalid(&token)),
+            }
+            .into(),
+            Element::Aggregate(Aggregate::Structure(ty)) => {
+                let is_packed = match token {
+                    Token::Punct('{') => false,
+                    Token::Punct('<') if self.eat_punct('{') => true,
+                    token => return Err(invalid(&token)),
+                };
+                let body = self.parse_constant_elements('}')?;
+                if is_packed {
+                    self.expect_punct('>')?;
+                }
+                if ty.is_packed() != Some(is_packed)
+                    || ty.body_types().map(|body_types| body_types.len()) != Some(body.len())
+                {
+                    return Err("the structure constant doesn't match its type".to_owned());
+                }
+                constant::Structure::new_typed(ty.clone(), body)?.into()
+            }
+        })
+    }
+
+    /// Parses typed constants separated by commas, up to and including `close`.
+    fn parse_constant_elements(&mut self, close: char) -> Result<Vec<constant::Element>, String> {
+        let mut elements = Vec::new();
+        if !self.eat_punct(close) {
+            loop {
+                let ty = self.parse_element_type()?;
+                elements.push(self.parse_constant(&ty)?);
+                if !self.eat_punct(',') {
+                    break;
+                }
+            }
+            self.expect_punct(close)?;
+        }
+        Ok(elements)
+    }
+
+    fn use_global(&mut self, id: Id) -> GlobalIdHandle {
+        if let Some(global) = self.globals.get(&id) {
+            return global.handle.clone();
+        }
+        let handle = match &id {
+            Id::Named(name) => self
+                .module
+                .create_global_named_id_handle(name.into())
+                .expect("every global name is only created once"),
+            Id::Unnamed(_) => self.module.create_global_unnamed_id_handle(),
+        };
+        let global = Global {
+            handle: handle.clone(),
+            is_defined: false,
+            line: self.line,
+        };
+        self.globals.insert(id, global);
+        handle
+    }
+
+    fn define_global(&mut self, id: Id) -> Result<GlobalIdHandle, String> {
+        if self
+            .globals
+            .get(&id)
+            .is_some_and(|global| global.is_defined)
+        {
+            return Err(format!("@{id} is defined twice"));
+        }
+        check_numbering('@', &id, self.unnamed_globals.len())?;
+        let is_unnamed = matches!(id, Id::Unnamed(_));
+        let handle = self.use_global(id.clone());
+        self.globals.get_mut(&id).unwrap().is_defined = true;
+        if is_unnamed {
+            self.unnamed_globals.push(handle.clone());
+        }
+        Ok(handle)
+    }
+
+    /// Takes the comments on the lines directly above `line`.
+    fn comment_above(&mut self, line: usize) -> Option<String> {
+        let mut first_line = line;
+        while first_line > 1 && self.comments.contains_key(&(first_line - 1)) {
+            first_line -= 1;
+        }
+        if first_line == line {
+            return None;
+        }
+        let lines: Vec<_> = (first_line..line)
+            .map(|line| self.comments.remove(&line).unwrap())
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(|(token, _)| token)
+    }
+
+    fn peek_word(&mut self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn peek_line(&mut self) -> Option<usize> {
+        self.tokens.peek().map(|(_, line)| *line)
+    }
+
+    /// Takes the next token, which must have been peeked.
+    fn advance(&mut self) {
+        self.next().unwrap();
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let (token, line) = self.tokens.next().ok_or("unexpected end of input")?;
+        self.line = line;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let eaten = self.peek() == Some(token);
+        if eaten {
+            self.advance();
+        }
+        eaten
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        self.eat(&Token::Punct(c))
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let eaten = self.peek_word() == Some(word);
+        if eaten {
+            self.advance();
+        }
+        eaten
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Punct(p) if p == c => Ok(()),
+            token => Err(format!("expected `{c}`, found {token}")),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), String> {
+        match self.next()? {
+            Token::Word(w) if w == word => Ok(()),
+            token => Err(format!("expected `{word}`, found {token}")),
+        }
+    }
+
+    fn unsigned<T: TryFrom<i128>>(&mut self) -> Result<T, String> {
+        match self.next()? {
+            Token::Integer(n) => n.try_into().map_err(|_| format!("`{n}` is out of range")),
+            token => Err(format!("expected a number, found {token}")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::String(bytes) => {
+                String::from_utf8(bytes).map_err(|_| "strings must be valid UTF-8".to_owned())
+            }
+            token => Err(format!("expected a string, found {token}")),
+        }
+    }
+}
+
+/// Checks that an unnamed id is the next one of the `count` unnamed ids that are already defined.
+fn check_numbering(sigil: char, id: &Id, count: usize) -> Result<(), String> {
+    match id {
+        Id::Unnamed(n) if *n != count => Err(format!(
+            "unnamed ids must be numbered in order: expected {sigil}{count}, found {sigil}{n}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn floating_point_constant(ty: &ty::FloatingPoint, token: &Token) -> Option<constant::Element> {
+    use constant::FloatingPoint as Fp;
+
+    let constant = match (ty, token) {
+        (ty::FloatingPoint::Half(ty), Token::Hex(bits)) => {
+            Fp::new_half_typed(ty.clone(), (*bits).try_into().ok()?)
+        }
+        (ty::FloatingPoint::BFloat(ty), Token::Hex(bits)) => {
+            Fp::new_bfloat_typed(ty.clone(), (*bits).try_into().ok()?)
+        }
+        (ty::FloatingPoint::Float(ty), Token::Hex(bits)) => {
+            Fp::new_float_typed(ty.clone(), f32::from_bits((*bits).try_into().ok()?))
+        }
+        (ty::FloatingPoint::Float(ty), Token::Float(value)) => {
+            Fp::new_float_typed(ty.clone(), *value as f32)
+        }
+        (ty::FloatingPoint::Double(ty), Token::Hex(bits)) => {
+            Fp::new_double_typed(ty.clone(), f64::from_bits((*bits).try_into().ok()?))
+        }
+        (ty::FloatingPoint::Double(ty), Token::Float(value)) => {
+            Fp::new_double_typed(ty.clone(), *value)
+        }
+        (ty::FloatingPoint::Fp128(ty), Token::Hex(bits)) => Fp::new_fp128_typed(ty.clone(), *bits),
+        (ty::FloatingPoint::X86Fp80(ty), Token::Hex(bits)) => {
+            Fp::new_x86fp80_typed(ty.clone(), *bits)
+        }
+        (ty::FloatingPoint::PpcFp128(ty), Token::Hex(bits)) => {
+            Fp::new_ppcfp128_typed(ty.clone(), *bits)
+        }
+        _ => return None,
+    };
+    Some(constant.into())
+}
+
+/// Converts an operand of `opcode` into the kind of value or type the instruction expects.
+fn operand<T: TryFrom<U>, U>(operand: U, opcode: &str) -> Result<T, String> {
+    T::try_from(operand)
+        .map_err(|_| format!("invalid {opcode} instruction: operand has the wrong type"))
+}
+
+fn is_vector(ty: &ty::FirstClass) -> bool {
+    matches!(
+        ty,
+        ty::FirstClass::Element(ty::Element::Single(ty::Single::Vector(_)))
+    )
+}
+
+struct Local {
+    handle: LocalIdHandle,
+    /// The type of the register, or `label` for a block, once its definition is parsed.
+    definition: Option<ty::FirstClass>,
+    /// The types and lines of the uses that come before the definition.
+    uses: Vec<(ty::FirstClass, usize)>,
+}
+
+/// A block whose terminator isn't parsed yet.
+struct PendingBlock {
+    id: Id,
+    label: LocalIdHandle,
+    comment: Option<String>,
+    instructions: Vec<Instruction>,
+    comments: Vec<(usize, String)>,
+}
+
+enum Statement {
+    Instruction(Instruction),
+    Terminator(Terminator),
+}
+
+struct FunctionParser<'p> {
+    parser: &'p mut Parser,
+    declaration: FunctionDeclaration,
+    locals: HashMap<Id, Local>,
+    /// The unnamed locals, in the order of their definitions.
+    unnamed_locals: Vec<LocalIdHandle>,
+}
+
+impl FunctionParser<'_> {
+    /// Parses the parameters, including the parentheses.
+    fn parse_params(&mut self) -> Result<(), String> {
+        self.parser.expect_punct('(')?;
+        if self.parser.eat_punct(')') {
+            return Ok(());
+        }
+        loop {
+            if self.parser.eat(&Token::Ellipsis) {
+                self.declaration.is_vararg = true;
+                break;
+            }
+            let ty = self.parser.parse_type()?;
+            let id = match self.parser.peek() {
+                Some(Token::LocalId(_)) => match self.parser.next()? {
+                    Token::LocalId(id) => id,
+                    _ => unreachable!(),
+                },
+                _ => Id::Unnamed(self.unnamed_locals.len()),
+            };
+            if self.locals.contains_key(&id) {
+                return Err(format!("%{id} is defined twice"));
+            }
+            check_numbering('%', &id, self.unnamed_locals.len())?;
+            let handle = match &id {
+                Id::Named(name) => self.declaration.add_param_named(name.into(), ty.clone())?,
+                Id::Unnamed(_) => self.declaration.add_param(ty.clone()),
+            }
+            .handle;
+            if let Id::Unnamed(_) = id {
+                self.unnamed_locals.push(handle.clone());
+            }
+            let local = Local {
+                handle,
+                definition: Some(ty),
+                uses: Vec::new(),
+            };
+            self.locals.insert(id, local);
+            if !self.parser.eat_punct(',') {
+                break;
+            }
+        }
+        self.parser.expect_punct(')')
+    }
+
+    /// Parses the blocks of the function, after the opening `{`.
+    fn parse_body(mut self) -> Result<(FunctionDeclaration, Vec<BasicBlock>), String> {
+        let mut body = Vec::new();
+        let mut pending: Option<PendingBlock> = None;
+        loop {
+            let line = self.parser.peek_line();
+            match self.parser.peek() {
+                Some(Token::Punct('}')) => {
+                    self.parser.advance();
+                    if let Some(block) = pending {
+                        return Err(format!("block %{} has no terminator", block.id));
+                    }
+                    break;
+                }
+                Some(Token::Label(_)) => {
+                    let Token::Label(id) = self.parser.next()? else {
+                        unreachable!()
+                    };
+                    if let Some(block) = pending {
+                        return Err(format!("block %{} has no terminator", block.id));
+                    }
+                    let comment = self.parser.comment_above(self.parser.line);
+                    pending = Some(self.start_block(Some(id), comment)?);
+                }
+                _ => {
+                    let block = match &mut pending {
+                        Some(block) => block,
+                        None => pending.insert(self.start_block(None, None)?),
+                    };
+                    if let Some(comment) = line.and_then(|line| self.parser.comment_above(line)) {
+                        block.comments.push((block.instructions.len(), comment));
+                    }
+                    match self.parse_statement()? {
+                        Statement::Instruction(instruction) => {
+                            if matches!(
+                                instruction,
+                                Instruction::Yielding(_, YieldingInstruction::ValidatedPhi(_))
+                            ) && block.instructions.iter().any(|instruction| {
+                                !matches!(
+                                    instruction,
+                                    Instruction::Yielding(_, YieldingInstruction::ValidatedPhi(_))
+                                )
+                            }) {
+                                return Err("phi instructions can only appear at the beginning of a basic block".to_owned());
+                            }
+                            block.instructions.push(instruction);
+                        }
+                        Statement::Terminator(terminator) => {
+                            let block = pending.take().unwrap();
+                            body.push(BasicBlock {
+                                label: constant::Label::with_literal_type(block.label),
+                                comment: block.comment,
+                                instructions: block.instructions,
+                                comments: block.comments,
+                                terminator,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        if body.is_empty() {
+            return Err("a function definition must have at least one block".to_owned());
+        }
+
+        if let Some((id, line)) = self
+            .locals
+            .iter()
+            .filter(|(_, local)| local.definition.is_none())
+            .map(|(id, local)| (id, local.uses[0].1))
+            .min_by_key(|(_, line)| *line)
+        {
+            self.parser.line = line;
+            return Err(format!("%{id} is used, but never defined"));
+        }
+        for handle in &self.unnamed_locals {
+            self.declaration.update_unnamed_id(handle);
+        }
+        Ok((self.declaration, body))
+    }
+
+    fn start_block(
+        &mut self,
+        id: Option<Id>,
+        comment: Option<String>,
+    ) -> Result<PendingBlock, String> {
+        let id = id.unwrap_or(Id::Unnamed(self.unnamed_locals.len()));
+        let label = self.define_local(id.clone(), ty::Label::new_literal().into())?;
+        Ok(PendingBlock {
+            id,
+            label,
+            comment,
+            instructions: Vec::new(),
+            comments: Vec::new(),
+        })
+    }
+
+    fn use_local(&mut self, id: Id, ty: ty::FirstClass) -> Result<LocalIdHandle, String> {
+        if let Some(local) = self.locals.get_mut(&id) {
+            match &local.definition {
+                Some(definition) if !definition.equiv_to(&ty) => {
+                    return Err(format!(
+                        "%{id} is used with another type than its definition"
+                    ))
+                }
+                Some(_) => {}
+                None => local.uses.push((ty, self.parser.line)),
+            }
+            return Ok(local.handle.clone());
+        }
+        let handle = match &id {
+            Id::Named(name) => self
+                .declaration
+                .create_named_id_handle(name.into())
+                .expect("every local name is only created once"),
+            Id::Unnamed(_) => self.declaration.create_unnamed_id_handle(),
+        };
+        let local = Local {
+            handle: handle.clone(),
+            definition: None,
+            uses: vec![(ty, self.parser.line)],
+        };
+        self.locals.insert(id, local);
+        Ok(handle)
+    }
+
+    fn define_local(&mut self, id: Id, ty: ty::FirstClass) -> Result<LocalIdHandle, String> {
+        if self
+            .locals
+            .get(&id)
+            .is_some_and(|local| local.definition.is_some())
+        {
+            return Err(format!("%{id} is defined twice"));
+        }
+        check_numbering('%', &id, self.unnamed_locals.len())?;
+        let line = self.parser.line;
+        let handle = match self.locals.get_mut(&id) {
+            Some(local) => {
+                if let Some((_, line)) = local.uses.iter().find(|(use_ty, _)| !use_ty.equiv_to(&ty))
+                {
+                    self.parser.line = *line;
+                    return Err(format!(
+                        "%{id} is used with another type than its definition"
+                    ));
+                }
+                local.definition = Some(ty);
+                local.handle.clone()
+            }
+            None => {
+                let handle = self.use_local(id.clone(), ty.clone())?;
+                let local = self.locals.get_mut(&id).unwrap();
+                local.definition = Some(ty);
+                local.uses.clear();
+                handle
+            }
+        };
+        self.parser.line = line;
+        if let Id::Unnamed(_) = id {
+            self.unnamed_locals.push(handle.clone());
+        }
+        Ok(handle)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, String> {
+        let result = match self.parser.peek() {
+            Some(Token::LocalId(_)) => {
+                let Token::LocalId(id) = self.parser.next()? else {
+                    unreachable!()
+                };
+                self.parser.expect_punct('=')?;
+                Some(id)
+            }
+            _ => None,
+        };
+        let opcode = match self.parser.next()? {
+            Token::Word(word) => word,
+            token => return Err(format!("expected an instruction, found {token}")),
+        };
+        let no_result = |opcode: &str| match &result {
+            Some(id) => Err(format!("the result of {opcode} can't be assigned to %{id}")),
+            None => Ok(()),
+        };
+
+        Ok(match opcode.as_str() {
+            "ret" | "br" | "switch" | "indirectbr" => {
+                no_result(&opcode)?;
+                Statement::Terminator(self.parse_terminator(&opcode)?)
+            }
+            "store" => {
+                no_result(&opcode)?;
+                let (_, value) = self.parse_typed_value()?;
+                self.parser.expect_punct(',')?;
+                let (_, pointer) = self.parse_typed_value()?;
+                let store: Store = Store {
+                    value: operand(value, &opcode)?,
+                    pointer: operand(pointer, &opcode)?,
+                };
+                Statement::Instruction(Instruction::Void(VoidInstruction::from(
+                    store.into_validated()?,
+                )))
+            }
+            "call" => {
+                let call = self.parse_call()?;
+                let result = match (call.yield_ty(), result) {
+                    (None, Some(id)) => {
+                        return Err(format!(
+                            "the result of a call to a void function can't be assigned to %{id}"
+                        ))
+                    }
+                    (None, None) => None,
+                    (Some(ty), id) => {
+                        let ty = ty::FirstClass::from(ty);
+                        let id = id.unwrap_or(Id::Unnamed(self.unnamed_locals.len()));
+                        let handle = self.define_local(id, ty.clone())?;
+                        Some(value::Register { ty, handle })
+                    }
+                };
+                let call = MaybeYieldingInstruction::from(call.into_validated()?);
+                Statement::Instruction(Instruction::MaybeYielding(result, call))
+            }
+            _ => {
+                let instruction = self.parse_yielding(&opcode)?;
+                let ty = instruction.yield_ty();
+                let id = result.unwrap_or(Id::Unnamed(self.unnamed_locals.len()));
+                let handle = self.define_local(id, ty.clone())?;
+                Statement::Instruction(Instruction::Yielding(
+                    value::Register { ty, handle },
+                    instruction,
+                ))
+            }
+        })
+    }
+
+    fn parse_yielding(&mut self, opcode: &str) -> Result<YieldingInstruction, String> {
+        const INT_OPS: [BinaryIntOp; 13] = [
+            BinaryIntOp::Add,
+            BinaryIntOp::Sub,
+            BinaryIntOp::Mul,
+            BinaryIntOp::Udiv,
+            BinaryIntOp::Sdiv,
+            BinaryIntOp::Urem,
+            BinaryIntOp::Srem,
+            BinaryIntOp::And,
+            BinaryIntOp::Or,
+            BinaryIntOp::Xor,
+            BinaryIntOp::Shl,
+            BinaryIntOp::Lshr,
+            BinaryIntOp::Ashr,
+        ];
+        const FP_OPS: [BinaryFpOp; 5] = [
+            BinaryFpOp::Fadd,
+            BinaryFpOp::Fsub,
+            BinaryFpOp::Fmul,
+            BinaryFpOp::Fdiv,
+            BinaryFpOp::Frem,
+        ];
+
+        if let Some(operator) = INT_OPS.into_iter().find(|op| op.to_string() == opcode) {
+            let (ty, operand1, operand2) = self.parse_binary_operands()?;
+            return Ok(if is_vector(&ty) {
+                binary_op::IntVec::<value::Vector> {
+                    operator,
+                    operand1: operand(operand1, opcode)?,
+                    operand2: operand(operand2, opcode)?,
+                }
+                .into_validated()?
+                .into()
+            } else {
+                binary_op::Int::<value::Integer> {
+                    operator,
+                    operand1: operand(operand1, opcode)?,
+                    operand2: operand(operand2, opcode)?,
+                }
+                .into_validated()?
+                .into()
+            });
+        }
+        if let Some(operator) = FP_OPS.into_iter().find(|op| op.to_string() == opcode) {
+            let (ty, operand1, operand2) = self.parse_binary_operands()?;
+            return Ok(if is_vector(&ty) {
+                binary_op::FpVec::<value::Vector> {
+                    operator,
+                    operand1: operand(operand1, opcode)?,
+                    operand2: operand(operand2, opcode)?,
+                }
+                .into_validated()?
+                .into()
+            } else {
+                binary_op::Fp::<value::FloatingPoint> {
+                    operator,
+                    operand1: operand(operand1, opcode)?,
+                    operand2: operand(operand2, opcode)?,
+                }
+                .into_validated()?
+                .into()
+            });
+        }
+
+        Ok(match opcode {
+            "fneg" => {
+                let (ty, value) = self.parse_typed_value()?;
+                let operator = UnaryFpOp::Fneg;
+                if is_vector(&ty) {
+                    let operand = operand(value, opcode)?;
+                    unary_op::FpVec::<value::Vector> { operator, operand }
+                        .into_validated()?
+                        .into()
+                } else {
+                    let operand = operand(value, opcode)?;
+                    unary_op::Fp::<value::FloatingPoint> { operator, operand }
+                        .into_validated()?
+                        .into()
+                }
+            }
+            "trunc" | "zext" | "sext" | "fptrunc" | "fpext" | "fptoui" | "fptosi" | "uitofp"
+            | "sitofp" | "ptrtoint" | "inttoptr" | "bitcast" => self.parse_cast(opcode)?,
+            "icmp" => {
+                let cond = self.parser.next()?;
+                let operator = [
+                    IcmpCond::Eq,
+                    IcmpCond::Ne,
+                    IcmpCond::Ugt,
+                    IcmpCond::Uge,
+                    IcmpCond::Ult,
+                    IcmpCond::Ule,
+                    IcmpCond::Sgt,
+                    IcmpCond::Sge,
+                    IcmpCond::Slt,
+                    IcmpCond::Sle,
+                ]
+                .into_iter()
+                .find(|op| Token::Word(op.to_string()) == cond)
+                .ok_or_else(|| format!("expected an icmp condition, found {cond}"))?;
+                let (ty, operand1, operand2) = self.parse_binary_operands()?;
+                let is_pointer = |ty: &ty::FirstClass| {
+                    matches!(
+                        ty,
+                        ty::FirstClass::Element(ty::Element::Single(ty::Single::Primitive(
+                            ty::Primitive::Pointer(_)
+                        )))
+                    )
+                };
+                match &ty {
+                    ty::FirstClass::Element(ty::Element::Single(ty::Single::Vector(vector)))
+                        if is_pointer(&vector.element_type().clone().into()) =>
+                    {
+                        compare::PtrVec::<value::Vector> {
+                            operator,
+                            operand1: operand(operand1, opcode)?,
+                            operand2: operand(operand2, opcode)?,
+                        }
+                        .into_validated()?
+                        .into()
+                    }
+                    ty if is_vector(ty) => compare::IntVec::<value::Vector> {
+                        operator,
+                        operand1: operand(operand1, opcode)?,
+                        operand2: operand(operand2, opcode)?,
+                    }
+                    .into_validated()?
+                    .into(),
+                    ty if is_pointer(ty) => compare::Ptr::<value::Pointer> {
+                        operator,
+                        operand1: operand(operand1, opcode)?,
+                        operand2: operand(operand2, opcode)?,
+                    }
+                    .into_validated()?
+                    .into(),
+                    _ => compare::Int::<value::Integer> {
+                        operator,
+                        operand1: operand(operand1, opcode)?,
+                        operand2: operand(operand2, opcode)?,
+                    }
+                    .into_validated()?
+                    .into(),
+                }
+            }
+            "fcmp" => {
+                let cond = self.parser.next()?;
+                let operator = [
+                    FcmpCond::False,
+                    FcmpCond::Oeq,
+                    FcmpCond::Ogt,
+                    FcmpCond::Oge,
+                    FcmpCond::Olt,
+                    FcmpCond::Ole,
+                    FcmpCond::One,
+                    FcmpCond::Ord,
+                    FcmpCond::Ueq,
+                    FcmpCond::Ugt,
+                    FcmpCond::Uge,
+                    FcmpCond::Ult,
+                    FcmpCond::Ule,
+                    FcmpCond::Une,
+                    FcmpCond::Uno,
+                    FcmpCond::True,
+                ]
+                .into_iter()
+                .find(|op| Token::Word(op.to_string()) == cond)
+                .ok_or_else(|| format!("expected an fcmp condition, found {cond}"))?;
+                let (ty, operand1, operand2) = self.parse_binary_operands()?;
+                if is_vector(&ty) {
+                    compare::FpVec::<value::Vector> {
+                        operator,
+                        operand1: operand(operand1, opcode)?,
+                        operand2: operand(operand2, opcode)?,
+                    }
+                    .into_validated()?
+                    .into()
+                } else {
+                    compare::Fp::<value::FloatingPoint> {
+                        operator,
+                        operand1: operand(operand1, opcode)?,
+                        operand2: operand(operand2, opcode)?,
+                    }
+                    .into_validated()?
+                    .into()
+                }
+            }
+            "extractvalue" => {
+                let (_, value) = self.parse_typed_value()?;
+                let mut indices = Vec::new();
+                while self.parser.eat_punct(',') {
+                    indices.push(constant::Integer::new(ty::I32, self.parser.unsigned()?));
+                }
+                if indices.is_empty() {
+                    return Err("extractvalue needs at least one index".to_owned());
+                }
+                let index = indices.remove(0);
+                ExtractValue::<value::Aggregate> {
+                    value: operand(value, opcode)?,
+                    index,
+                    indices,
+                }
+                .into_validated()?
+                .into()
+            }
+            "getelementptr" => {
+                let ty = self.parser.parse_element_type()?;
+                self.parser.expect_punct(',')?;
+                let (_, pointer) = self.parse_typed_value()?;
+                let mut indices = Vec::new();
+                while self.parser.eat_punct(',') {
+                    let (_, index) = self.parse_typed_value()?;
+                    indices.push(operand(index, opcode)?);
+                }
+                GetElementPtr {
+                    ty,
+                    pointer: operand(pointer, opcode)?,
+                    indices,
+                }
+                .into_validated()?
+                .into()
+            }
+            "phi" => {
+                let ty = self.parser.parse_type()?;
+                let mut incoming = Vec::new();
+                loop {
+                    self.parser.expect_punct('[')?;
+                    let value = self.parse_value(&ty)?;
+                    self.parser.expect_punct(',')?;
+                    let label = self.parse_label()?;
+                    self.parser.expect_punct(']')?;
+                    incoming.push((value, label));
+                    if !self.parser.eat_punct(',') {
+                        break;
+                    }
+                }
+                let head = incoming.remove(0);
+                Phi {
+                    head,
+                    tail: incoming,
+                }
+                .into_validated()?
+                .into()
+            }
+            "alloca" => {
+                let ty = self.parser.parse_element_type()?;
+                let amount = if self.parser.eat_punct(',') {
+                    let (_, amount) = self.parse_typed_value()?;
+                    Some(operand(amount, opcode)?)
+                } else {
+                    None
+                };
+                Alloca { ty, amount }.into_validated()?.into()
+            }
+            "load" => {
+                let ty = self.parser.parse_element_type()?;
+                self.parser.expect_punct(',')?;
+                let (_, pointer) = self.parse_typed_value()?;
+                Load {
+                    ty,
+                    pointer: operand(pointer, opcode)?,
+                }
+                .into_validated()?
+                .into()
+            }
+            _ => return Err(format!("unknown instruction `{opcode}`")),
+        })
+    }
+
+    fn parse_cast(&mut self, opcode: &str) -> Result<YieldingInstruction, String> {
+        let (from_ty, value) = self.parse_typed_value()?;
+        self.parser.expect_word("to")?;
+        let to_ty = self.parser.parse_type()?;
+
+        macro_rules! cast {
+            ($Cast:ident) => {{
+                let cast: cast::$Cast = cast::$Cast {
+                    value: operand(value, opcode)?,
+                    to_ty: operand(to_ty, opcode)?,
+                };
+                cast.into_validated()?.into()
+            }};
+        }
+
+        Ok(match (opcode, is_vector(&from_ty)) {
+            ("trunc", false) => cast!(TruncInt),
+            ("trunc", true) => cast!(TruncIntVec),
+            ("zext", false) => cast!(ZextInt),
+            ("zext", true) => cast!(ZextIntVec),
+            ("sext", false) => cast!(SextInt),
+            ("sext", true) => cast!(SextIntVec),
+            ("fptrunc", false) => cast!(FpTrunc),
+            ("fptrunc", true) => cast!(FpVecTrunc),
+            ("fpext", false) => cast!(FpExt),
+            ("fpext", true) => cast!(FpVecExt),
+            ("fptoui", false) => cast!(FpToUi),
+            ("fptoui", true) => cast!(FpVecToUiVec),
+            ("fptosi", false) => cast!(FpToSi),
+            ("fptosi", true) => cast!(FpVecToSiVec),
+            ("uitofp", false) => cast!(UiToFp),
+            ("uitofp", true) => cast!(UiVecToFpVec),
+            ("sitofp", false) => cast!(SiToFp),
+            ("sitofp", true) => cast!(SiVecToFpVec),
+            ("ptrtoint", false) => cast!(PtrToInt),
+            ("ptrtoint", true) => cast!(PtrVecToIntVec),
+            ("inttoptr", false) => cast!(IntToPtr),
+            ("inttoptr", true) => cast!(IntVecToPtrVec),
+            _ => cast!(BitCast),
+        })
+    }
+
+    fn parse_call(&mut self) -> Result<Call, String> {
+        let calling_conv = self.parser.parse_calling_conv()?;
+        let address_space = self.parser.parse_address_space()?;
+        let return_type = self.parser.parse_return_type()?;
+
+        // The parameter types are only written for vararg functions.
+        let fn_ty = if self.parser.eat_punct('(') {
+            let mut param_types = Vec::new();
+            let mut is_vararg = false;
+            if !self.parser.eat_punct(')') {
+                loop {
+                    if self.parser.eat(&Token::Ellipsis) {
+                        is_vararg = true;
+                        break;
+                    }
+                    param_types.push(self.parser.parse_type()?);
+                    if !self.parser.eat_punct(',') {
+                        break;
+                    }
+                }
+                self.parser.expect_punct(')')?;
+            }
+            Some((param_types, is_vararg))
+        } else {
+            None
+        };
+
+        let pointer_ty = ty::Pointer::new_literal()
+            .with_address_space(address_space)
+            .build();
+        let fn_pointer = self.parse_value(&pointer_ty.into())?;
+        self.parser.expect_punct('(')?;
+        let mut fn_args = Vec::new();
+        if !self.parser.eat_punct(')') {
+            loop {
+                fn_args.push(self.parse_typed_value()?.1);
+                if !self.parser.eat_punct(',') {
+                    break;
+                }
+            }
+            self.parser.expect_punct(')')?;
+        }
+
+        let (param_types, is_vararg) =
+            fn_ty.unwrap_or_else(|| (fn_args.iter().map(Value::ty).collect(), false));
+        Ok(Call {
+            calling_conv,
+            fn_ty: ty::Function::new_literal(return_type)
+                .with_params(param_types)
+                .with_vararg(is_vararg)
+                .build(),
+            fn_pointer: operand(fn_pointer, "call")?,
+            fn_args,
+        })
+    }
+
+    fn parse_terminator(&mut self, opcode: &str) -> Result<Terminator, String> {
+        Ok(match opcode {
+            "ret" => {
+                if self.parser.eat_word("void") {
+                    if let ReturnType::Element(_) = &self.declaration.return_type {
+                        return Err("void return in function returning non-void type".to_owned());
+                    }
+                    ReturnVoid.into_validated()?.into()
+                } else {
+                    let (ty, value) = self.parse_typed_value()?;
+                    match &self.declaration.return_type {
+                        ReturnType::Void => {
+                            return Err("non-void return in function returning void".to_owned())
+                        }
+                        ReturnType::Element(return_type) => {
+                            if !ty::FirstClass::from(return_type.clone()).equiv_to(&ty) {
+                                return Err("return value type doesn't match function return type"
+                                    .to_owned());
+                            }
+                        }
+                    }
+                    let ret: Return = Return(operand(value, opcode)?);
+                    ret.into_validated()?.into()
+                }
+            }
+            "br" => {
+                if self.parser.eat_word("label") {
+                    let dest = value::Label::from(self.parse_label()?);
+                    Branch { dest }.into_validated()?.into()
+                } else {
+                    let (_, cond) = self.parse_typed_value()?;
+                    self.parser.expect_punct(',')?;
+                    let dest_true = self.parse_typed_label()?;
+                    self.parser.expect_punct(',')?;
+                    let dest_false = self.parse_typed_label()?;
+                    let branch: BranchConditional = BranchConditional {
+                        cond: operand(cond, opcode)?,
+                        dest_true,
+                        dest_false,
+                    };
+                    branch.into_validated()?.into()
+                }
+            }
+            "switch" => {
+                let (_, value) = self.parse_typed_value()?;
+                self.parser.expect_punct(',')?;
+                let default_dest = self.parse_typed_label()?;
+                self.parser.expect_punct('[')?;
+                let mut branches = Vec::new();
+                while !self.parser.eat_punct(']') {
+                    let ty = self.parser.parse_element_type()?;
+                    let case = operand(self.parser.parse_constant(&ty)?, opcode)?;
+                    self.parser.expect_punct(',')?;
+                    branches.push((case, self.parse_typed_label()?));
+                }
+                let switch: Switch = Switch {
+                    value: operand(value, opcode)?,
+                    default_dest,
+                    branches,
+                };
+                switch.into_validated()?.into()
+            }
+            _ => {
+                let (_, address) = self.parse_typed_value()?;
+                self.parser.expect_punct(',')?;
+                self.parser.expect_punct('[')?;
+                let mut targets = Vec::new();
+                if !self.parser.eat_punct(']') {
+                    loop {
+                        targets.push(self.parse_typed_label()?);
+                        if !self.parser.eat_punct(',') {
+                            break;
+                        }
+                    }
+                    self.parser.expect_punct(']')?;
+                }
+                let branch: IndirectBranch = IndirectBranch {
+                    address: operand(address, opcode)?,
+                    targets,
+                };
+                branch.into_validated()?.into()
+            }
+        })
+    }
+
+    /// Parses the two operands of a binary operator, which have the same type.
+    fn parse_binary_operands(
+        &mut self,
+    ) -> Result<(ty::FirstClass, value::FirstClass, value::FirstClass), String> {
+        let (ty, operand1) = self.parse_typed_value()?;
+        self.parser.expect_punct(',')?;
+        let operand2 = self.parse_value(&ty)?;
+        Ok((ty, operand1, operand2))
+    }
+
+    fn parse_typed_value(&mut self) -> Result<(ty::FirstClass, value::FirstClass), String> {
+        let ty = self.parser.parse_type()?;
+        let value = self.parse_value(&ty)?;
+        Ok((ty, value))
+    }
+
+    fn parse_value(&mut self, ty: &ty::FirstClass) -> Result<value::FirstClass, String> {
+        if let Some(Token::LocalId(_)) = self.parser.peek() {
+            let Token::LocalId(id) = self.parser.next()? else {
+                unreachable!()
+            };
+            let handle = self.use_local(id, ty.clone())?;
+            return Ok(match ty {
+                ty::FirstClass::Label(_) => {
+                    value::Label::from(constant::Label::with_literal_type(handle)).into()
+                }
+                ty => value::Register {
+                    ty: ty.clone(),
+                    handle,
+                }
+                .into(),
+            });
+        }
+        let ty = ty::Element::try_from(ty.clone())
+            .map_err(|_| "labels must be written as local ids".to_owned())?;
+        Ok(value::Element::from(self.parser.parse_constant(&ty)?).into())
+    }
+
+    /// Parses a label without its type, e.g. `%2`.
+    fn parse_label(&mut self) -> Result<constant::Label, String> {
+        match self.parser.next()? {
+            Token::LocalId(id) => {
+                let handle = self.use_local(id, ty::Label::new_literal().into())?;
+                Ok(constant::Label::with_literal_type(handle))
+            }
+            token => Err(format!("expected a label, found {token}")),
+        }
+    }
+
+    /// Parses a label with its type, e.g. `label %2`.
+    fn parse_typed_label(&mut self) -> Result<value::Label, String> {
+        self.parser.expect_word("label")?;
+        Ok(self.parse_label()?.into())
+    }
+}
@@ -28,6 +28,21 @@ impl Pointer {
         value: ConstantPointer::Null,
     };
 
+    /// A null pointer in the address space of `ty`.
+    pub fn null_typed(ty: ty::Pointer) -> Self {
+        Self {
+            ty,
+            value: ConstantPointer::Null,
+        }
+    }
+
+    pub(crate) fn global_address(&self) -> Option<&GlobalIdHandle> {
+        match &self.value {
+            ConstantPointer::GlobalAddress(handle) => Some(handle),
+            _ => None,
+        }
+    }
+
     pub(crate) fn from_global_address(ty: ty::Pointer, handle: GlobalIdHandle) -> Self {
         Self {
             ty,
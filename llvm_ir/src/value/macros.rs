@@ -134,6 +134,16 @@ macro_rules! declare_value_cat {
             }
         }
 
+        impl $Value {
+            /// Returns the handle of the register, or `None` if the value is a constant.
+            #[allow(unused)] // Not every value category needs this
+            pub(crate) fn register_handle(&self) -> Option<&crate::function::LocalIdHandle> {
+                match self {
+                    $(Self::$ChildValue(child) => child.register_handle(),)+
+                }
+            }
+        }
+
 
         $(
             impl TryFrom<$Value> for crate::value::Register<crate::ty::$ParentValue> {
@@ -353,6 +363,17 @@ macro_rules! declare_base_value {
             }
         }
 
+        impl $Value {
+            /// Returns the handle of the register, or `None` if the value is a constant.
+            #[allow(unused)] // Not every value category needs this
+            pub(crate) fn register_handle(&self) -> Option<&crate::function::LocalIdHandle> {
+                match self {
+                    Self::Constant(_) => None,
+                    Self::Register(register) => Some(&register.handle),
+                }
+            }
+        }
+
         impl TryFrom<$Value> for crate::constant::$Value {
             type Error = crate::value::ValueConversionError;
 
@@ -0,0 +1,352 @@
+use crate::instruction::{Instruction, Operands, YieldingInstruction};
+use crate::ty::Type;
+use crate::value::{self, Value};
+use crate::{FunctionDeclaration, FunctionDefinition, Module};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug)]
+pub struct VerifyError {
+    function: String,
+    reason: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid function {}: {}", self.function, self.reason)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<VerifyError> for String {
+    fn from(value: VerifyError) -> Self {
+        value.to_string()
+    }
+}
+
+impl Module {
+    /// Checks the invariants of the whole module that aren't checked when building single
+    /// instructions:
+    /// - every function definition has a body, and every branch jumps to a block of the same
+    ///   function, other than the entry block,
+    /// - every register is defined exactly once, and every use of a register is dominated by its
+    ///   definition (uses in unreachable blocks are not checked),
+    /// - the phi instructions are at the start of their block, and have exactly one incoming value
+    ///   for every predecessor of the block,
+    /// - calls to a function of the module use the type of that function.
+    ///
+    /// Every block has a terminator by construction.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        for (handle, function) in self.function_definitions() {
+            FunctionVerifier::new(self, function)
+                .verify()
+                .map_err(|reason| VerifyError {
+                    function: Fmt(self.global_id(handle), self, function).to_string(),
+                    reason,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a register is defined, the position of the terminator of a block is the number of
+/// instructions in the block.
+#[derive(Debug, Clone, Copy)]
+enum Definition {
+    Param,
+    Instruction { block: usize, position: usize },
+}
+
+struct FunctionVerifier<'a> {
+    module: &'a Module,
+    function: &'a FunctionDefinition,
+    /// The index of the block of every label.
+    blocks: HashMap<usize, usize>,
+    /// The definition of every register.
+    definitions: HashMap<usize, Definition>,
+    predecessors: Vec<HashSet<usize>>,
+    /// The blocks that dominate each block, `None` if the block is unreachable.
+    dominators: Vec<Option<HashSet<usize>>>,
+}
+
+impl<'a> FunctionVerifier<'a> {
+    fn new(module: &'a Module, function: &'a FunctionDefinition) -> Self {
+        Self {
+            module,
+            function,
+            blocks: HashMap::new(),
+            definitions: HashMap::new(),
+            predecessors: Vec::new(),
+            dominators: Vec::new(),
+        }
+    }
+
+    fn verify(mut self) -> Result<(), String> {
+        let body = self.function.body();
+        if body.is_empty() {
+            return Err("the function has no blocks".to_owned());
+        }
+
+        for (i, block) in body.iter().enumerate() {
+            if self.blocks.insert(block.label.handle.index(), i).is_some() {
+                return Err(format!(
+                    "the label {} is used twice",
+                    self.fmt(&block.label)
+                ));
+            }
+        }
+        for param in self.function.params() {
+            self.define(param.value(), Definition::Param)?;
+        }
+        for (i, block) in body.iter().enumerate() {
+            for (position, instruction) in block.instructions.iter().enumerate() {
+                if let Some(result) = instruction_result(instruction) {
+                    self.define(result, Definition::Instruction { block: i, position })?;
+                }
+            }
+        }
+
+        self.predecessors = vec![HashSet::new(); body.len()];
+        for (i, block) in body.iter().enumerate() {
+            for label in block.terminator.successors() {
+                let successor = self.block_of(label)?;
+                if successor == 0 {
+                    return Err("the entry block can't be the target of a branch".to_owned());
+                }
+                self.predecessors[successor].insert(i);
+            }
+        }
+        self.compute_dominators();
+
+        for (i, block) in body.iter().enumerate() {
+            let mut phis_allowed = true;
+            for (position, instruction) in block.instructions.iter().enumerate() {
+                if let Instruction::Yielding(result, YieldingInstruction::ValidatedPhi(phi)) =
+                    instruction
+                {
+                    if !phis_allowed {
+                        return Err(format!(
+                            "the phi instruction of {} isn't at the start of its block",
+                            self.fmt(result)
+                        ));
+                    }
+                    self.verify_phi(i, result, phi.incoming())?;
+                    continue;
+                }
+                phis_allowed = false;
+                for operand in instruction.operands() {
+                    self.verify_use(&operand, i, position)?;
+                }
+                if let Instruction::MaybeYielding(_, call) = instruction {
+                    self.verify_call(call)?;
+                }
+            }
+            for operand in block.terminator.operands() {
+                self.verify_use(&operand, i, block.instructions.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn define<T: crate::ty::FirstClassType>(
+        &mut self,
+        register: &value::Register<T>,
+        definition: Definition,
+    ) -> Result<(), String> {
+        match self.definitions.insert(register.handle.index(), definition) {
+            Some(_) => Err(format!("{} is defined twice", self.fmt(register))),
+            None => Ok(()),
+        }
+    }
+
+    fn block_of(&self, label: &value::Label) -> Result<usize, String> {
+        let block = match label {
+            value::Label::Constant(label) => self.blocks.get(&label.handle.index()),
+            value::Label::Register(_) => None,
+        };
+        block
+            .copied()
+            .ok_or_else(|| format!("{} isn't a block of the function", self.fmt(label)))
+    }
+
+    /// Computes the dominators of every reachable block with the iterative data flow algorithm.
+    fn compute_dominators(&mut self) {
+        let n = self.function.body().len();
+        let mut reachable = vec![false; n];
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            if !std::mem::replace(&mut reachable[i], true) {
+                stack.extend(
+                    self.function.body()[i]
+                        .terminator
+                        .successors()
+                        .into_iter()
+                        .filter_map(|label| self.block_of(label).ok()),
+                );
+            }
+        }
+
+        let all = (0..n).filter(|&i| reachable[i]).collect::<HashSet<_>>();
+        self.dominators = (0..n)
+            .map(|i| match i {
+                0 => Some(HashSet::from([0])),
+                _ if reachable[i] => Some(all.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in (1..n).filter(|&i| reachable[i]) {
+                let mut dominators = self.predecessors[i]
+                    .iter()
+                    .filter_map(|&p| self.dominators[p].as_ref())
+                    .fold(None, |acc: Option<HashSet<usize>>, doms| match acc {
+                        None => Some(doms.clone()),
+                        Some(acc) => Some(acc.intersection(doms).copied().collect()),
+                    })
+                    .unwrap_or_default();
+                dominators.insert(i);
+                if self.dominators[i].as_ref() != Some(&dominators) {
+                    self.dominators[i] = Some(dominators);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Checks that the definition of `operand` dominates the instruction at `position` in `block`.
+    fn verify_use(
+        &self,
+        operand: &value::FirstClass,
+        block: usize,
+        position: usize,
+    ) -> Result<(), String> {
+        let Some(handle) = operand.register_handle() else {
+            return Ok(());
+        };
+        let dominated = match self.definitions.get(&handle.index()) {
+            // The register can't be displayed without an id in this function.
+            None if !self.function.has_id(handle) => {
+                return Err("a register of another function is used".to_owned())
+            }
+            None => return Err(format!("{} is used, but never defined", self.fmt(operand))),
+            Some(Definition::Param) => true,
+            Some(&Definition::Instruction {
+                block: def_block,
+                position: def_position,
+            }) => match &self.dominators[block] {
+                None => true,
+                Some(_) if def_block == block => def_position < position,
+                Some(dominators) => dominators.contains(&def_block),
+            },
+        };
+        if dominated {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is used in block {}, where its definition doesn't dominate the use",
+                self.fmt(operand),
+                self.fmt(&self.function.body()[block].label),
+            ))
+        }
+    }
+
+    fn verify_phi<'b>(
+        &self,
+        block: usize,
+        result: &value::Register<crate::ty::FirstClass>,
+        incoming: impl Iterator<Item = &'b (value::FirstClass, value::constant::Label)>,
+    ) -> Result<(), String> {
+        let mut incoming_blocks = HashSet::new();
+        for (value, label) in incoming {
+            let predecessor = self.block_of(&label.clone().into())?;
+            if !self.predecessors[block].contains(&predecessor) {
+                return Err(format!(
+                    "the phi instruction of {} has an incoming value for {}, which isn't a predecessor",
+                    self.fmt(result),
+                    self.fmt(label),
+                ));
+            }
+            if !incoming_blocks.insert(predecessor) {
+                return Err(format!(
+                    "the phi instruction of {} has two incoming values for {}",
+                    self.fmt(result),
+                    self.fmt(label),
+                ));
+            }
+            if !value.ty().equiv_to(&result.ty()) {
+                return Err(format!(
+                    "the phi instruction of {} has an incoming value of another type",
+                    self.fmt(result),
+                ));
+            }
+            // The value is used at the end of the predecessor.
+            let end = self.function.body()[predecessor].instructions.len();
+            self.verify_use(value, predecessor, end)?;
+        }
+        if let Some(&missing) = self.predecessors[block].difference(&incoming_blocks).next() {
+            return Err(format!(
+                "the phi instruction of {} has no incoming value for the predecessor {}",
+                self.fmt(result),
+                self.fmt(&self.function.body()[missing].label),
+            ));
+        }
+        Ok(())
+    }
+
+    fn verify_call(
+        &self,
+        call: &crate::instruction::MaybeYieldingInstruction,
+    ) -> Result<(), String> {
+        let crate::instruction::MaybeYieldingInstruction::ValidatedCall(call) = call;
+        let value::Pointer::Constant(pointer) = &call.fn_pointer else {
+            return Ok(());
+        };
+        let Some(fn_ty) = pointer
+            .global_address()
+            .and_then(|handle| self.module.function_type(handle))
+        else {
+            return Ok(());
+        };
+        // `equiv_to` doesn't compare the number of parameters.
+        if fn_ty.param_types().len() != call.fn_ty.param_types().len()
+            || !fn_ty.equiv_to(&call.fn_ty)
+        {
+            return Err(format!(
+                "{} is called with the type {}, but has the type {}",
+                self.fmt(&call.fn_pointer),
+                self.fmt(&call.fn_ty),
+                self.fmt(&fn_ty),
+            ));
+        }
+        Ok(())
+    }
+
+    fn fmt<'b, T>(&'b self, value: &'b T) -> Fmt<'b, T> {
+        Fmt(value, self.module, self.function)
+    }
+}
+
+fn instruction_result(
+    instruction: &Instruction,
+) -> Option<&value::Register<crate::ty::FirstClass>> {
+    match instruction {
+        Instruction::Yielding(result, _) | Instruction::MaybeYielding(Some(result), _) => {
+            Some(result)
+        }
+        Instruction::MaybeYielding(None, _) | Instruction::Void(_) => None,
+    }
+}
+
+/// Displays a value as LLVM assembly, for error messages.
+struct Fmt<'a, T>(&'a T, &'a Module, &'a FunctionDeclaration);
+
+impl<T: crate::FmtAsLlvmAsmFC> fmt::Display for Fmt<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0
+            .fmt_as_llvm_asm(f, &crate::FmtOpts::default(), self.1, self.2)
+    }
+}
@@ -83,3 +83,277 @@ define %0 @main (%0, %0) {
 "#
     );
 }
+
+/// Builds `i32 @f(i32 %0)`, which adds `%0` to itself in the `then` block, and then returns the
+/// value built by `ret_value` in the `end` block.
+fn build_diamond<F>(ret_value: F) -> Module
+where
+    F: FnOnce(
+        &mut FunctionDefinitionBuilder,
+        (value::Integer, constant::Label),
+        (value::Integer, constant::Label),
+    ) -> value::Integer,
+{
+    let mut module = Module::new("main".to_owned());
+    let mut function = FunctionDeclaration::new(ty::I32.into());
+    let param = function.add_param(ty::I32);
+    let mut function = function.to_definition_builder();
+
+    let entry = function.get_or_set_block_label();
+    let then = function.declare_block();
+    let end = function.declare_block();
+
+    let cond = function
+        .add_instruction(instruction::compare::Int {
+            operator: instruction::IcmpCond::Eq,
+            operand1: param.clone(),
+            operand2: param.clone(),
+        })
+        .unwrap();
+    function
+        .terminate_and_start_declared_block(
+            instruction::BranchConditional {
+                cond,
+                dest_true: then.clone(),
+                dest_false: end.clone(),
+            },
+            then.clone(),
+        )
+        .unwrap();
+    let sum = function
+        .add_instruction(instruction::binary_op::Int {
+            operator: instruction::BinaryIntOp::Add,
+            operand1: param.clone(),
+            operand2: param.clone(),
+        })
+        .unwrap();
+    function
+        .terminate_and_start_declared_block(instruction::Branch { dest: end.clone() }, end)
+        .unwrap();
+    let value = ret_value(&mut function, (param.into(), entry), (sum.into(), then));
+    function
+        .terminate_block(instruction::Return(value))
+        .unwrap();
+
+    module
+        .define_function_named("f".into(), function.build())
+        .unwrap();
+    module
+}
+
+#[test]
+fn verify_accepts_valid_modules() {
+    let module = build_diamond(|function, from_entry, from_then| {
+        function
+            .add_instruction(instruction::Phi {
+                head: from_entry,
+                tail: vec![from_then],
+            })
+            .unwrap()
+            .into()
+    });
+    module.verify().unwrap();
+}
+
+#[test]
+fn verify_reports_invalid_modules() {
+    let module = build_diamond(|_, _, (sum, _)| sum);
+    assert_eq!(
+        module.verify().unwrap_err().to_string(),
+        "invalid function @f: %4 is used in block %5, where its definition doesn't dominate the use"
+    );
+
+    let module = build_diamond(|function, _, from_then| {
+        function
+            .add_instruction(instruction::Phi {
+                head: from_then,
+                tail: vec![],
+            })
+            .unwrap()
+            .into()
+    });
+    assert_eq!(
+        module.verify().unwrap_err().to_string(),
+        "invalid function @f: the phi instruction of %6 has no incoming value for the predecessor %1"
+    );
+}
+
+#[test]
+fn verify_reports_blocks_without_a_terminator() {
+    // A `BasicBlock` always has a terminator, so the blocks that don't are rejected when the
+    // function is built or parsed, before the module can be verified.
+    let mut function = FunctionDeclaration::new(ty::I32.into()).to_definition_builder();
+    function.get_or_set_block_label();
+    assert_eq!(
+        function.try_build().unwrap_err(),
+        "cannot build function definition: function body contains unfinished basic blocks"
+    );
+
+    let source = "define i32 @f (i32 %x) {\nentry:\n    %y = add i32 %x, %x\n}\n";
+    assert_eq!(
+        parse(source).unwrap_err().to_string(),
+        "line 4: block %entry has no terminator"
+    );
+}
+
+#[test]
+fn verify_reports_undefined_registers() {
+    // Returns the last of `n` params of another function, which aren't defined in `f`.
+    let verify = |n| {
+        let mut other = FunctionDeclaration::new(ty::I32.into());
+        let params: Vec<_> = (0..n).map(|_| other.add_param(ty::I32)).collect();
+
+        let mut module = Module::new("main".to_owned());
+        let mut function = FunctionDeclaration::new(ty::I32.into()).to_definition_builder();
+        function.get_or_set_block_label();
+        function
+            .terminate_block(instruction::Return(params[n - 1].clone()))
+            .unwrap();
+        module
+            .define_function_named("f".into(), function.build())
+            .unwrap();
+        module.verify().unwrap_err().to_string()
+    };
+    // The register has the id of the entry block of `f`.
+    assert_eq!(
+        verify(1),
+        "invalid function @f: %0 is used, but never defined"
+    );
+    assert_eq!(
+        verify(4),
+        "invalid function @f: a register of another function is used"
+    );
+}
+
+#[test]
+fn verify_reports_calls_with_another_type() {
+    let verify = |call| {
+        let source = format!(
+            "declare i32 @g (i32)\n\ndefine void @f (i32 %x) {{\n    {call}\n    ret void\n}}\n"
+        );
+        parse(&source).unwrap().verify().unwrap_err().to_string()
+    };
+    assert_eq!(
+        verify("call i32 (i32, i32) @g(i32 %x, i32 %x)"),
+        "invalid function @f: @g is called with the type i32 (i32, i32), but has the type i32 (i32)"
+    );
+    assert_eq!(
+        verify("call i32 (ptr) @g(ptr null)"),
+        "invalid function @f: @g is called with the type i32 (ptr), but has the type i32 (i32)"
+    );
+    assert_eq!(
+        verify("call void (i32) @g(i32 %x)"),
+        "invalid function @f: @g is called with the type void (i32), but has the type i32 (i32)"
+    );
+}
+
+fn print(module: &Module) -> String {
+    module
+        .display(FmtOpts {
+            display_unnamed_ids: true,
+        })
+        .to_string()
+}
+
+#[test]
+fn parse_round_trips_printed_modules() {
+    let module = build_diamond(|function, from_entry, from_then| {
+        function
+            .add_instruction(instruction::Phi {
+                head: from_entry,
+                tail: vec![from_then],
+            })
+            .unwrap()
+            .into()
+    });
+    let printed = print(&module);
+    let parsed = parse(&printed).unwrap();
+    assert_eq!(print(&parsed), printed);
+    parsed.verify().unwrap();
+}
+
+#[test]
+fn parse_hand_written_module() {
+    let source = r#"; module = fixture
+source_filename = "fixture.c"
+target triple = "mipsel-unknown-linux-gnu"
+
+%pair = type {i32, ptr}
+
+@.str = private unnamed_addr constant [4 x i8] c"%d\0A\00"
+
+; the answer
+@answer = global %pair {i32 42, ptr @.str}, align 4
+
+@extern = external global i32
+
+declare i32 @printf (ptr, ...)
+
+define i32 @main (i32 %argc) {
+entry:
+    %cond = icmp slt i32 %argc, 2
+    br i1 %cond, label %small, label %big
+
+small:
+    ; print the answer
+    call i32 (ptr, ...) @printf(ptr @.str, i32 42)
+    ret i32 0
+
+big:
+    switch i32 %argc, label %small [ i32 3, label %small i32 4, label %small ]
+}
+"#;
+    let module = parse(source).unwrap();
+    module.verify().unwrap();
+    assert_eq!(
+        print(&module),
+        r#"; module = fixture
+source_filename = "fixture.c"
+target triple = "mipsel-unknown-linux-gnu"
+
+%pair = type {i32, ptr}
+
+@extern = external global i32
+
+@.str = private unnamed_addr constant [4 x i8] c"%d\0A\00"
+
+; the answer
+@answer = global %pair {i32 42, ptr @.str}, align 4
+
+declare i32 @printf (ptr %0, ...)
+
+define i32 @main (i32 %argc) {
+entry:
+    %cond = icmp slt i32 %argc, 2
+    br i1 %cond, label %small, label %big
+small:
+    ; print the answer
+    %0 = call i32 (ptr, ...) @printf(ptr @.str, i32 42)
+    ret i32 0
+big:
+    switch i32 %argc, label %small [ i32 3, label %small i32 4, label %small ]
+}
+"#
+    );
+}
+
+#[test]
+fn parse_reports_errors() {
+    let error = |source| parse(source).unwrap_err().to_string();
+    assert_eq!(
+        error("define void @f () {\n    ret void\n}\n\ndefine void @f () {\n    ret void\n}\n"),
+        "line 5: @f is defined twice"
+    );
+    assert_eq!(
+        error("define i32 @f () {\n    br label %1\n\n1:\n    ret i32 %2\n}\n"),
+        "line 5: %2 is used, but never defined"
+    );
+    assert_eq!(
+        error("define void @f () {\n    %2 = add i32 1, 2\n    ret void\n}\n"),
+        "line 2: unnamed ids must be numbered in order: expected %1, found %2"
+    );
+    assert_eq!(
+        error("define void @f () {\n    ret i32 0\n}\n"),
+        "line 2: non-void return in function returning void"
+    );
+}
@@ -0,0 +1,254 @@
+//! The instruction sets that the target-independent parts of the IR can work with.
+//!
+//! A [`Function`](crate::Function) is a CFG of blocks with instructions and terminators of an
+//! [`Arch`]. The use-def analysis, liveness analysis, dead-code elimination and register
+//! allocation only need to know which registers an instruction defines and uses, and which
+//! registers the allocator may hand out, so they work for every [`Arch`]. The other passes (stack
+//! frame building, devirtualization, fixing, ...) only exist for [`Mips`].
+
+use crate::{
+    dfa::uda::{Defs, Uses},
+    function::StackAddress,
+    AnyReg, BlockId, BlockRef, FReg, Instruction, Reg, StackInfo, Terminator,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub trait Arch: std::fmt::Debug + Clone + 'static {
+    type Instruction: ArchInstruction;
+    type Terminator: ArchTerminator;
+
+    /// CPU registers the register allocator may use that are preserved across calls.
+    const SAVED_CPU_REGS: &'static [Reg];
+    /// CPU registers the register allocator may use that are clobbered by calls.
+    const TEMP_CPU_REGS: &'static [Reg];
+    /// FPU registers the register allocator may use that are preserved across calls.
+    const SAVED_FPU_REGS: &'static [FReg];
+    /// FPU registers the register allocator may use that are clobbered by calls.
+    const TEMP_FPU_REGS: &'static [FReg];
+
+    /// Returns the size and alignment of the stack slot `reg` is spilled to.
+    fn spill_info(reg: AnyReg) -> StackInfo;
+
+    /// Returns the physical register that an unused def of an instruction with side effects can
+    /// be written to, or `None` if there's no such register for the kind of `reg`.
+    fn discard_reg(reg: AnyReg) -> Option<AnyReg>;
+
+    /// Returns `true` if `reg` is one of the saved registers the allocator may use.
+    fn is_saved(reg: AnyReg) -> bool {
+        match reg {
+            AnyReg::R(reg) => Self::SAVED_CPU_REGS.contains(&reg),
+            AnyReg::F(freg) => Self::SAVED_FPU_REGS.contains(&freg),
+        }
+    }
+}
+
+pub trait ArchInstruction: std::fmt::Debug + Clone + Serialize + DeserializeOwned {
+    fn defs(&self) -> Defs;
+
+    fn uses(&self) -> Uses;
+
+    fn map_defs(&mut self, f: impl FnMut(AnyReg) -> AnyReg);
+
+    fn map_uses(&mut self, f: impl FnMut(AnyReg) -> AnyReg);
+
+    fn has_side_effects(&self) -> bool;
+
+    /// Returns `true` if the instruction calls a function, i.e. clobbers all temporary registers.
+    fn is_call(&self) -> bool;
+
+    fn nop() -> Self;
+
+    fn load_from_stack(reg: AnyReg, stack_address: StackAddress) -> Self;
+
+    fn store_to_stack(reg: AnyReg, stack_address: StackAddress) -> Self;
+
+    /// Copies physical register `src` to physical register `dst`. Used to destruct SSA, so it
+    /// must be ignored by the use-def analysis.
+    fn move_(dst: AnyReg, src: AnyReg) -> Self;
+
+    /// Swaps the physical registers `a` and `b`, which are of the same kind. Used to destruct SSA.
+    fn swap(a: AnyReg, b: AnyReg) -> Vec<Self>;
+}
+
+pub trait ArchTerminator: std::fmt::Debug + Clone + Serialize + DeserializeOwned {
+    /// Jump to `target`.
+    fn jump(target: BlockRef) -> Self;
+
+    fn targets(&self) -> impl Iterator<Item = &BlockRef>;
+
+    fn targets_mut(&mut self) -> impl Iterator<Item = &mut BlockRef>;
+
+    /// Returns the target that's jumped to if the terminator falls through.
+    fn default_target(&self) -> Option<&BlockRef>;
+
+    /// The registers used by the terminator itself, i.e. not including block arguments.
+    fn uses(&self) -> Uses;
+
+    fn map_uses(&mut self, f: impl FnMut(AnyReg) -> AnyReg);
+
+    fn target(&self, id: BlockId) -> Option<&BlockRef> {
+        self.targets().find(|t| t.id == id)
+    }
+
+    fn target_mut(&mut self, id: BlockId) -> Option<&mut BlockRef> {
+        self.targets_mut().find(|t| t.id == id)
+    }
+
+    /// Maps the uses of the terminator and the block arguments it passes to its targets.
+    fn map_all_uses(&mut self, mut f: impl FnMut(AnyReg) -> AnyReg) {
+        self.map_uses(&mut f);
+        for bref in self.targets_mut() {
+            for arg in &mut bref.arguments {
+                *arg = f(*arg);
+            }
+        }
+    }
+}
+
+/// The MIPS32 instruction set, which all passes support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mips;
+
+impl Arch for Mips {
+    type Instruction = Instruction;
+    type Terminator = Terminator;
+
+    const SAVED_CPU_REGS: &'static [Reg] = &[
+        Reg::S0,
+        Reg::S1,
+        Reg::S2,
+        Reg::S3,
+        Reg::S4,
+        Reg::S5,
+        Reg::S6,
+        Reg::S7,
+    ];
+    const TEMP_CPU_REGS: &'static [Reg] = &[
+        Reg::T0,
+        Reg::T1,
+        Reg::T2,
+        Reg::T3,
+        Reg::T4,
+        Reg::T5,
+        Reg::T6,
+        Reg::T7,
+        Reg::T8,
+        Reg::T9,
+    ];
+    // Only the even registers can hold doubles, so only those are allocated.
+    const SAVED_FPU_REGS: &'static [FReg] = &[
+        FReg::F(20),
+        FReg::F(22),
+        FReg::F(24),
+        FReg::F(26),
+        FReg::F(28),
+        FReg::F(30),
+    ];
+    const TEMP_FPU_REGS: &'static [FReg] = &[
+        FReg::F(4),
+        FReg::F(6),
+        FReg::F(8),
+        FReg::F(10),
+        FReg::F(16),
+        FReg::F(18),
+    ];
+
+    fn spill_info(reg: AnyReg) -> StackInfo {
+        reg.stack_info()
+    }
+
+    fn discard_reg(reg: AnyReg) -> Option<AnyReg> {
+        match reg {
+            AnyReg::R(_) => Some(Reg::ZERO.into()),
+            AnyReg::F(_) => None,
+        }
+    }
+}
+
+impl ArchInstruction for Instruction {
+    fn defs(&self) -> Defs {
+        Instruction::defs(self)
+    }
+
+    fn uses(&self) -> Uses {
+        Instruction::uses(self)
+    }
+
+    fn map_defs(&mut self, f: impl FnMut(AnyReg) -> AnyReg) {
+        Instruction::map_defs(self, f)
+    }
+
+    fn map_uses(&mut self, f: impl FnMut(AnyReg) -> AnyReg) {
+        Instruction::map_uses(self, f)
+    }
+
+    fn has_side_effects(&self) -> bool {
+        Instruction::has_side_effects(self)
+    }
+
+    fn is_call(&self) -> bool {
+        // TODO: what about syscalls?
+        matches!(
+            self,
+            Instruction::Call(_) | Instruction::Virtual(crate::VirtualInstruction::FunctionCall(_))
+        )
+    }
+
+    fn nop() -> Self {
+        Instruction::Nop
+    }
+
+    fn load_from_stack(reg: AnyReg, stack_address: StackAddress) -> Self {
+        crate::instr::virt::load_from_stack(reg, stack_address)
+    }
+
+    fn store_to_stack(reg: AnyReg, stack_address: StackAddress) -> Self {
+        crate::instr::virt::store_to_stack(reg, stack_address)
+    }
+
+    fn move_(dst: AnyReg, src: AnyReg) -> Self {
+        crate::instr::virt::move_(dst, src).to_hidden()
+    }
+
+    fn swap(a: AnyReg, b: AnyReg) -> Vec<Self> {
+        match (a, b) {
+            (AnyReg::R(a), AnyReg::R(b)) => vec![
+                crate::instr::xor(a, a, b),
+                crate::instr::xor(b, a, b),
+                crate::instr::xor(a, a, b),
+            ],
+            (AnyReg::F(a), AnyReg::F(b)) => vec![
+                crate::instr::move_(b.ffmt(), FReg::F(2), b),
+                crate::instr::move_(a.ffmt(), b, a),
+                crate::instr::move_(a.ffmt(), a, FReg::F(2)),
+            ],
+            (AnyReg::R(_), AnyReg::F(_)) | (AnyReg::F(_), AnyReg::R(_)) => unreachable!(),
+        }
+    }
+}
+
+impl ArchTerminator for Terminator {
+    fn jump(target: BlockRef) -> Self {
+        crate::term::jump(target)
+    }
+
+    fn targets(&self) -> impl Iterator<Item = &BlockRef> {
+        Terminator::targets(self)
+    }
+
+    fn targets_mut(&mut self) -> impl Iterator<Item = &mut BlockRef> {
+        Terminator::targets_mut(self)
+    }
+
+    fn default_target(&self) -> Option<&BlockRef> {
+        Terminator::default_target(self)
+    }
+
+    fn uses(&self) -> Uses {
+        Terminator::uses(self)
+    }
+
+    fn map_uses(&mut self, f: impl FnMut(AnyReg) -> AnyReg) {
+        Terminator::map_uses(self, f)
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod test;
+
+use crate::{size, AlignBoundary, FReg, Reg, StackInfo};
+use serde::{Deserialize, Serialize};
+
+/// The convention used to pass arguments to and return values from functions.
+///
+/// Return values are always passed in `$v0` (or `$f0` for floating point values), and the
+/// callee-saved registers (`$s0-$s7`, `$f20-$f31`, `$fp` and `$ra`) are always preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CallingConvention {
+    /// All arguments are passed on the stack, starting at `0($sp)` of the caller. Every argument
+    /// only takes up its own size.
+    #[default]
+    Stack,
+    /// The standard MIPS O32 ABI. Every argument takes up at least a word on the stack, and the
+    /// first 16 bytes (the home space) are always reserved but passed in `$a0-$a3` instead. If the
+    /// first arguments are floating point, they are passed in `$f12` and `$f14`.
+    O32,
+}
+
+/// The registers in which an argument is passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArgRegs {
+    Cpu(Reg),
+    /// A double passed in two cpu registers, the low word in the first one.
+    CpuPair(Reg, Reg),
+    Fpu(FReg),
+}
+
+const ARG_REGS: [Reg; 4] = [Reg::A0, Reg::A1, Reg::A2, Reg::A3];
+const HOME_SPACE: u32 = 4 * size::WORD;
+
+impl CallingConvention {
+    /// Returns the offset of each argument, relative to the start of the argument area (i.e.
+    /// `$sp` of the caller), together with the total size of the area.
+    pub(crate) fn arg_layout<'a>(
+        self,
+        args: impl IntoIterator<Item = &'a StackInfo>,
+    ) -> (Vec<u32>, u32) {
+        let mut offsets = Vec::new();
+        let mut size = 0;
+        for stack_info in args {
+            let (alignment, arg_size) = self.slot_of(stack_info);
+            size = alignment.next_multiple_from(size);
+            offsets.push(size);
+            size += arg_size;
+        }
+        if self == Self::O32 {
+            size = size.max(HOME_SPACE);
+        }
+        (offsets, size)
+    }
+
+    /// Returns the registers in which each argument is passed, if any. `args` gives for every
+    /// argument whether it is a floating point value, together with its stack info.
+    pub(crate) fn arg_regs<'a>(
+        self,
+        args: impl IntoIterator<Item = (bool, &'a StackInfo)> + Clone,
+    ) -> Vec<Option<ArgRegs>> {
+        let (offsets, _) = self.arg_layout(args.clone().into_iter().map(|(_, info)| info));
+        if self == Self::Stack {
+            return vec![None; offsets.len()];
+        }
+
+        let mut only_floats_so_far = true;
+        let mut regs = Vec::with_capacity(offsets.len());
+        for (i, ((is_float, stack_info), offset)) in args.into_iter().zip(offsets).enumerate() {
+            only_floats_so_far &= is_float;
+            let word = (offset / size::WORD) as usize;
+            let arg_regs = if only_floats_so_far && i < 2 {
+                Some(ArgRegs::Fpu(FReg::F(12 + 2 * i as u8)))
+            } else if offset >= HOME_SPACE {
+                None
+            } else if stack_info.size as u32 == size::DOUBLE {
+                Some(ArgRegs::CpuPair(ARG_REGS[word], ARG_REGS[word + 1]))
+            } else {
+                Some(ArgRegs::Cpu(ARG_REGS[word]))
+            };
+            regs.push(arg_regs);
+        }
+        regs
+    }
+
+    fn slot_of(self, stack_info: &StackInfo) -> (AlignBoundary, u32) {
+        let size = stack_info.size as u32;
+        match self {
+            Self::Stack => (stack_info.alignment, size),
+            Self::O32 if size <= size::WORD => (AlignBoundary::WORD, size::WORD),
+            Self::O32 => (stack_info.alignment.max(AlignBoundary::WORD), size),
+        }
+    }
+}
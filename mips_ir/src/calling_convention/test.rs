@@ -0,0 +1,78 @@
+use super::*;
+
+fn info(size: u32, alignment: AlignBoundary) -> StackInfo {
+    StackInfo {
+        size: size as u128,
+        alignment,
+        signed: true,
+    }
+}
+
+#[test]
+fn stack_layout_packs_arguments() {
+    let args = [
+        info(1, AlignBoundary::BYTE),
+        info(1, AlignBoundary::BYTE),
+        info(4, AlignBoundary::WORD),
+        info(8, AlignBoundary::DOUBLE),
+    ];
+    let (offsets, size) = CallingConvention::Stack.arg_layout(&args);
+    assert_eq!(offsets, [0, 1, 4, 8]);
+    assert_eq!(size, 16);
+    assert_eq!(
+        CallingConvention::Stack.arg_regs(args.iter().map(|info| (false, info))),
+        [None; 4]
+    );
+    assert_eq!(CallingConvention::Stack.arg_layout(&[]).1, 0);
+}
+
+#[test]
+fn o32_layout_uses_word_slots_and_home_space() {
+    let args = [
+        info(1, AlignBoundary::BYTE),
+        info(8, AlignBoundary::DOUBLE),
+        info(2, AlignBoundary::HALF),
+    ];
+    let (offsets, size) = CallingConvention::O32.arg_layout(&args);
+    assert_eq!(offsets, [0, 8, 16]);
+    assert_eq!(size, 20);
+    assert_eq!(CallingConvention::O32.arg_layout(&[]).1, 16);
+
+    let regs =
+        CallingConvention::O32.arg_regs([(false, &args[0]), (true, &args[1]), (false, &args[2])]);
+    assert_eq!(
+        regs,
+        [
+            Some(ArgRegs::Cpu(Reg::A0)),
+            Some(ArgRegs::CpuPair(Reg::A2, Reg::A3)),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn o32_leading_floats_use_fpu_registers() {
+    let double = info(8, AlignBoundary::DOUBLE);
+    let float = info(4, AlignBoundary::WORD);
+    let int = info(4, AlignBoundary::WORD);
+
+    let regs = CallingConvention::O32.arg_regs([(true, &float), (true, &double), (true, &float)]);
+    assert_eq!(
+        regs,
+        [
+            Some(ArgRegs::Fpu(FReg::F(12))),
+            Some(ArgRegs::Fpu(FReg::F(14))),
+            None,
+        ]
+    );
+
+    let regs = CallingConvention::O32.arg_regs([(true, &float), (false, &int), (true, &float)]);
+    assert_eq!(
+        regs,
+        [
+            Some(ArgRegs::Fpu(FReg::F(12))),
+            Some(ArgRegs::Cpu(Reg::A1)),
+            Some(ArgRegs::Cpu(Reg::A2)),
+        ]
+    );
+}
@@ -4,25 +4,28 @@
 mod terminator;
 
 use crate::{
+    arch::{Arch, ArchTerminator, Mips},
     dfa::{
         liveness,
         uda::{self, GlobalLocation},
     },
-    AnyReg, Instruction, Terminator, VARGenerator,
+    AnyReg, VARGenerator,
 };
 use generational_arena::{Arena, Index as ArenaIndex};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::{cell::RefCell, collections::BTreeSet};
 
-#[derive(Debug, Clone)]
-pub struct BasicBlock {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BasicBlock<A: Arch = Mips> {
     pub arguments: Vec<AnyReg>,
-    pub instructions: Vec<Instruction>,
-    pub(crate) terminator: Option<Terminator>,
+    pub instructions: Vec<A::Instruction>,
+    pub(crate) terminator: Option<A::Terminator>,
     pub(crate) is_call_block: bool,
 }
 
-impl BasicBlock {
+impl<A: Arch> BasicBlock<A> {
     pub(crate) fn new_incomplete() -> Self {
         Self {
             arguments: Vec::new(),
@@ -36,13 +39,13 @@ impl BasicBlock {
         self.terminator.is_some()
     }
 
-    pub fn terminator(&self) -> &Terminator {
+    pub fn terminator(&self) -> &A::Terminator {
         self.terminator
             .as_ref()
             .expect("invalid basic block terminator")
     }
 
-    pub fn terminator_mut(&mut self) -> &mut Terminator {
+    pub fn terminator_mut(&mut self) -> &mut A::Terminator {
         self.terminator
             .as_mut()
             .expect("invalid basic block terminator")
@@ -86,7 +89,7 @@ impl Cache {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct BlockId(ArenaIndex);
 
 impl BlockId {
@@ -101,7 +104,7 @@ impl BlockId {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockRef {
     pub id: BlockId,
     pub arguments: Vec<AnyReg>,
@@ -113,15 +116,17 @@ impl BlockRef {
     }
 }
 
-#[derive(Debug)]
-pub struct Cfg {
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Cfg<A: Arch = Mips> {
     entry_block: BlockId,
-    blocks: Arena<BasicBlock>,
+    blocks: Arena<BasicBlock<A>>,
+    #[serde(skip)]
     cache: RefCell<Cache>,
 }
 
-impl Cfg {
-    pub fn new(entry_block: BasicBlock) -> Self {
+impl<A: Arch> Cfg<A> {
+    pub fn new(entry_block: BasicBlock<A>) -> Self {
         let mut blocks = Arena::new();
         Self {
             entry_block: BlockId(blocks.insert(entry_block)),
@@ -150,28 +155,28 @@ impl Cfg {
         self.entry_block = new_entry_block;
     }
 
-    pub fn entry_block(&self) -> &BasicBlock {
+    pub fn entry_block(&self) -> &BasicBlock<A> {
         &self[self.entry_block]
     }
 
-    pub fn entry_block_mut(&mut self) -> &mut BasicBlock {
+    pub fn entry_block_mut(&mut self) -> &mut BasicBlock<A> {
         let id = self.entry_block;
         &mut self[id]
     }
 
     /// Retrieves a reference to an existing block in the CFG by its id.
-    pub fn get(&self, block: BlockId) -> Option<&BasicBlock> {
+    pub fn get(&self, block: BlockId) -> Option<&BasicBlock<A>> {
         self.blocks.get(block.0)
     }
 
     /// Retrieves a mutable reference to an existing block in the CFG by its id.
-    pub fn get_mut(&mut self, block: BlockId) -> Option<&mut BasicBlock> {
+    pub fn get_mut(&mut self, block: BlockId) -> Option<&mut BasicBlock<A>> {
         self.invalidate_cache();
         self.blocks.get_mut(block.0)
     }
 
     /// Returns a reference to the instruction at `location`.
-    pub fn instruction(&self, location: GlobalLocation) -> Option<&Instruction> {
+    pub fn instruction(&self, location: GlobalLocation) -> Option<&A::Instruction> {
         if location.local.0 < 0 {
             return None;
         }
@@ -180,7 +185,7 @@ impl Cfg {
             .get(location.local.0 as usize)
     }
 
-    pub fn instruction_mut(&mut self, location: GlobalLocation) -> Option<&mut Instruction> {
+    pub fn instruction_mut(&mut self, location: GlobalLocation) -> Option<&mut A::Instruction> {
         if location.local.0 < 0 {
             return None;
         }
@@ -190,25 +195,25 @@ impl Cfg {
     }
 
     /// Inserts a new block in the CFG and returns it id.
-    pub fn insert(&mut self, block: BasicBlock) -> BlockId {
+    pub fn insert(&mut self, block: BasicBlock<A>) -> BlockId {
         self.invalidate_cache();
         BlockId(self.blocks.insert(block))
     }
 
     /// Removes a block from the CFG and returns it. Any predecessors or successor reference will
     /// **NOT** be updated. This can be relied upon.
-    pub fn remove(&mut self, block: BlockId) -> BasicBlock {
+    pub fn remove(&mut self, block: BlockId) -> BasicBlock<A> {
         self.invalidate_cache();
         self.blocks.remove(block.0).unwrap()
     }
 
     /// Returns an iterator over all blocks in the CFG.
-    pub fn blocks(&self) -> impl Iterator<Item = (BlockId, &BasicBlock)> {
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockId, &BasicBlock<A>)> {
         self.blocks.iter().map(|(idx, bb)| (BlockId(idx), bb))
     }
 
     /// Returns an iterator over all blocks in the CFG.
-    pub fn blocks_mut(&mut self) -> impl Iterator<Item = (BlockId, &mut BasicBlock)> {
+    pub fn blocks_mut(&mut self) -> impl Iterator<Item = (BlockId, &mut BasicBlock<A>)> {
         self.invalidate_cache();
         self.blocks.iter_mut().map(|(idx, bb)| (BlockId(idx), bb))
     }
@@ -221,7 +226,7 @@ impl Cfg {
         self[block].successors().map(|bref| bref.id)
     }
 
-    pub fn successors(&self, block: BlockId) -> impl Iterator<Item = (BlockId, &BasicBlock)> {
+    pub fn successors(&self, block: BlockId) -> impl Iterator<Item = (BlockId, &BasicBlock<A>)> {
         self.successor_ids(block).map(|id| (id, &self[id]))
     }
 
@@ -251,7 +256,7 @@ impl Cfg {
             .into_iter()
     }
 
-    pub fn predecessors(&self, block: BlockId) -> impl Iterator<Item = (BlockId, &BasicBlock)> {
+    pub fn predecessors(&self, block: BlockId) -> impl Iterator<Item = (BlockId, &BasicBlock<A>)> {
         self.predecessor_ids(block).map(|id| (id, &self[id]))
     }
 
@@ -364,7 +369,7 @@ impl Cfg {
         &mut self,
         pred: BlockId,
         succ: BlockId,
-        instructions: &[Instruction],
+        instructions: &[A::Instruction],
     ) -> BlockId {
         let mut var_generator = self.var_generator();
         let arguments: Vec<_> = self[succ]
@@ -375,7 +380,7 @@ impl Cfg {
         let connector_block = BasicBlock {
             arguments: arguments.clone(),
             instructions: instructions.to_vec(),
-            terminator: Some(crate::term::jump(BlockRef::new(succ, arguments))),
+            terminator: Some(A::Terminator::jump(BlockRef::new(succ, arguments))),
             is_call_block: false,
         };
         let connector_id = self.insert(connector_block);
@@ -405,15 +410,15 @@ impl Cfg {
     }
 }
 
-impl std::ops::Index<BlockId> for Cfg {
-    type Output = BasicBlock;
+impl<A: Arch> std::ops::Index<BlockId> for Cfg<A> {
+    type Output = BasicBlock<A>;
 
     fn index(&self, index: BlockId) -> &Self::Output {
         &self.blocks[index.0]
     }
 }
 
-impl std::ops::IndexMut<BlockId> for Cfg {
+impl<A: Arch> std::ops::IndexMut<BlockId> for Cfg<A> {
     fn index_mut(&mut self, index: BlockId) -> &mut Self::Output {
         self.invalidate_cache();
         &mut self.blocks[index.0]
@@ -430,7 +435,7 @@ pub struct DominatorTree {
 }
 
 impl DominatorTree {
-    fn build_from(cfg: &Cfg) -> Self {
+    fn build_from<A: Arch>(cfg: &Cfg<A>) -> Self {
         // TODO: use a more efficent algorith for this, s.a. the Lengauer-Tarjan one.
 
         // Slow quadratic algorithm from Wikipedia
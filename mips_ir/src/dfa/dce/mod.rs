@@ -5,7 +5,10 @@
 //! position, size, or alignment.
 //!
 //! Note that purging a function is required before doing register allocation!
-use crate::{AnyReg, BlockId, Function, Reg, Root};
+use crate::{
+    arch::{Arch, ArchInstruction, ArchTerminator},
+    AnyReg, BlockId, Function, Root,
+};
 use std::collections::HashSet;
 
 /// Assumes the root is validated.
@@ -14,13 +17,13 @@ pub fn purge_root(root: &mut Root) {
 }
 
 /// Assumes the function is validated.
-pub fn purge_function(function: &mut Function) {
+pub fn purge_function<A: Arch>(function: &mut Function<A>) {
     remove_unreachable_blocks(function);
     eliminate_unused_regs(function);
     remove_redundant_phi_args(function);
 }
 
-fn eliminate_unused_regs(function: &mut Function) {
+fn eliminate_unused_regs<A: Arch>(function: &mut Function<A>) {
     let mut to_eliminate = HashSet::new();
 
     let du_chains = function.cfg.du_chains();
@@ -76,7 +79,7 @@ fn eliminate_unused_regs(function: &mut Function) {
     }
 }
 
-fn remove_unreachable_blocks(function: &mut Function) {
+fn remove_unreachable_blocks<A: Arch>(function: &mut Function<A>) {
     let reachables = HashSet::<_>::from_iter(function.traverse().map(|(id, _)| id));
     for &unreachable in
         HashSet::from_iter(function.cfg.blocks().map(|(id, _)| id)).difference(&reachables)
@@ -87,9 +90,13 @@ fn remove_unreachable_blocks(function: &mut Function) {
 
 /// Eliminates all uses and defs of `reg` within `block`. Panics if a usage of `reg` couldn't be
 /// removed (can happen when the usage is in an effectful instruction) or if `reg` is virtual.
-/// Does not panic if the definition of `reg` could not be removed. Tries to replace it with $zero
-/// if possible, or leaves it as is.
-fn eliminate_reg(function: &mut Function, block_id: BlockId, to_eliminate: &HashSet<AnyReg>) {
+/// Does not panic if the definition of `reg` could not be removed. Tries to replace it with
+/// [`Arch::discard_reg`] (e.g. $zero) if possible, or leaves it as is.
+fn eliminate_reg<A: Arch>(
+    function: &mut Function<A>,
+    block_id: BlockId,
+    to_eliminate: &HashSet<AnyReg>,
+) {
     for i in (0..function.cfg[block_id].arguments.len()).rev() {
         if to_eliminate.contains(&function.cfg[block_id].arguments[i]) {
             function.cfg.remove_param(block_id, i);
@@ -112,8 +119,8 @@ fn eliminate_reg(function: &mut Function, block_id: BlockId, to_eliminate: &Hash
         }
         if instr.defs().any(|r| to_eliminate.contains(&r)) {
             if instr.has_side_effects() {
-                instr.map_defs(|r| match r {
-                    AnyReg::R(_) if to_eliminate.contains(&r) => Reg::ZERO.into(),
+                instr.map_defs(|r| match A::discard_reg(r) {
+                    Some(discarded) if to_eliminate.contains(&r) => discarded,
                     _ => r,
                 });
             } else {
@@ -133,7 +140,7 @@ fn eliminate_reg(function: &mut Function, block_id: BlockId, to_eliminate: &Hash
     }
 }
 
-fn remove_redundant_phi_args(function: &mut Function) {
+fn remove_redundant_phi_args<A: Arch>(function: &mut Function<A>) {
     let mut changing = true;
     while changing {
         changing = false;
@@ -163,7 +170,7 @@ fn remove_redundant_phi_args(function: &mut Function) {
     }
 }
 
-fn map_reg(function: &mut Function, from: AnyReg, to: AnyReg) {
+fn map_reg<A: Arch>(function: &mut Function<A>, from: AnyReg, to: AnyReg) {
     for (_, block) in function.cfg.blocks_mut() {
         for arg in &mut block.arguments {
             if *arg == from {
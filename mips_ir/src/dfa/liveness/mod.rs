@@ -16,6 +16,7 @@
 //! Non-virtual regs are completely ignored!
 
 use crate::{
+    arch::Arch,
     cfg::{BlockId, Cfg},
     AnyReg,
 };
@@ -47,19 +48,19 @@ pub struct LiveSet {
 }
 
 impl LiveSets {
-    pub fn build_from(cfg: &Cfg) -> Self {
+    pub fn build_from<A: Arch>(cfg: &Cfg<A>) -> Self {
         LiveSetsBuilder::new(cfg).build()
     }
 }
 
-struct LiveSetsBuilder<'a> {
-    cfg: &'a Cfg,
+struct LiveSetsBuilder<'a, A: Arch> {
+    cfg: &'a Cfg<A>,
     du_chains: uda::DuChains,
     live_sets: LiveSets,
 }
 
-impl<'a> LiveSetsBuilder<'a> {
-    fn new(cfg: &'a Cfg) -> Self {
+impl<'a, A: Arch> LiveSetsBuilder<'a, A> {
+    fn new(cfg: &'a Cfg<A>) -> Self {
         Self {
             cfg,
             du_chains: cfg.du_chains(),
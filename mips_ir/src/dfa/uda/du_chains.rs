@@ -1,4 +1,5 @@
 use crate::{
+    arch::{Arch, ArchInstruction, ArchTerminator},
     cfg::{BlockId, Cfg},
     AnyReg, FReg, Reg, VARGenerator,
 };
@@ -214,7 +215,7 @@ impl DuChains {
     /// defs of the same reg. Does *not* check for use-before def. Multiple arguments are considered
     /// multiple defs and as such result in an error. Non-virtual registers are completely ignored.
     /// Undef phi uses are considered undef uses.
-    pub fn try_build_from(cfg: &Cfg) -> Result<Self, DefUseError> {
+    pub fn try_build_from<A: Arch>(cfg: &Cfg<A>) -> Result<Self, DefUseError> {
         // Allow multiple def locations to be set, so we can collect error info later.
         let mut defs: HashMap<AnyReg, HashSet<GlobalDefLocation>> = HashMap::new();
         let mut uses: HashMap<AnyReg, BTreeSet<GlobalLocation>> = HashMap::new();
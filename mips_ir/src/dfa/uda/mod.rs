@@ -9,7 +9,7 @@ pub use du_chains::{DefLocation, DefUseError, DuChain, DuChains, GlobalLocation,
 use crate::AnyReg;
 
 #[derive(Debug, Clone)]
-pub struct Defs(Option<AnyReg>);
+pub struct Defs(pub(crate) Option<AnyReg>);
 
 #[derive(Debug, Clone)]
-pub struct Uses(<Vec<AnyReg> as IntoIterator>::IntoIter);
+pub struct Uses(pub(crate) <Vec<AnyReg> as IntoIterator>::IntoIter);
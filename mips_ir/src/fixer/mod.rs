@@ -8,7 +8,7 @@
 // #[cfg(test)]
 // mod test;
 
-use crate::{Function, Root};
+use crate::{BlockId, Function, Root};
 
 /// Checks if the root matches all the constraints required to output valid MIPS, and applies fixes
 /// to make it valid if not. See [`crate::MipsOutputter`] for the list of constraints.
@@ -44,36 +44,55 @@ pub fn fix_function(function: &mut Function) {
     // if !check::is_graph_complete(function) {
     //     panic!("cannot fix incomplete graph");
     // }
-    // Break any possible cycles.
-    'outer: loop {
-        // Traverse the function starting from the entry point and following default successors
-        // whenever possible. If we encounter a block while traversing of which the default
-        // has already been traversed, we know that that block is part of a cycle in the reduced
-        // graph.
-        let mut traverser = function.traverse_all();
+    // Break any possible cycles. The outputter only writes the blocks that are reachable, in the
+    // order of `traverse`, which can differ from the order of `traverse_all`. So the blocks are
+    // checked in both orders.
+    loop {
+        if let Some((base_id, dsucc_id, _)) = find_misplaced_block(function, false) {
+            // Only introduce indirection here, so this can't undo the swaps made below.
+            fix::antichain_successor(function, base_id, dsucc_id, Vec::new());
+        } else if let Some((base_id, dsucc_id, candidates)) = find_misplaced_block(function, true) {
+            fix::antichain_successor(function, base_id, dsucc_id, candidates);
+        } else {
+            break;
+        }
+    }
+}
+
+/// Returns a block of which the default successor is traversed before the block itself, together
+/// with that default successor and the other successors that aren't traversed yet.
+fn find_misplaced_block(
+    function: &Function,
+    traverse_all: bool,
+) -> Option<(BlockId, BlockId, Vec<BlockId>)> {
+    // Traverse the function starting from the entry point and following default successors
+    // whenever possible. If we encounter a block while traversing of which the default
+    // has already been traversed, we know that that block is part of a cycle in the reduced
+    // graph, or that it can't fall through to its default successor.
+    let mut traverser = match traverse_all {
+        true => function.traverse_all(),
+        false => function.traverse(),
+    };
 
-        // `cycle_base` will be the block of which we'll antichain the default successor to break
-        // the cycle. If no cycle is found, we can successfully break from 'outer.
-        let (cycle_base_id, dsucc_id) = loop {
-            let Some((block_id, block)) = traverser.next() else {
-                break 'outer;
-            };
-            if let Some(bref) = block.terminator().default_target() {
-                if traverser.has_traversed(bref.id) {
-                    break (block_id, bref.id);
-                }
+    // `cycle_base` will be the block of which we'll antichain the default successor to break
+    // the cycle.
+    let (cycle_base_id, dsucc_id) = loop {
+        let (block_id, block) = traverser.next()?;
+        if let Some(bref) = block.terminator().default_target() {
+            if traverser.has_traversed(bref.id) {
+                break (block_id, bref.id);
             }
-        };
-        // Found a cycle, so fix it by either swapping the default successor with a
-        // non-default successor, or by introducing indirection.
-        let swap_candidates = function.cfg[cycle_base_id]
-            .terminator()
-            .targets()
-            .filter(|bref| bref.id != dsucc_id && !traverser.has_traversed(bref.id))
-            .map(|bref| bref.id)
-            .collect::<Vec<_>>(); // TODO: find optimal order of candidates
-        fix::antichain_successor(function, cycle_base_id, dsucc_id, swap_candidates);
-    }
+        }
+    };
+    // Found a cycle, so it can be fixed by either swapping the default successor with a
+    // non-default successor, or by introducing indirection.
+    let swap_candidates = function.cfg[cycle_base_id]
+        .terminator()
+        .targets()
+        .filter(|bref| bref.id != dsucc_id && !traverser.has_traversed(bref.id))
+        .map(|bref| bref.id)
+        .collect::<Vec<_>>(); // TODO: find optimal order of candidates
+    Some((cycle_base_id, dsucc_id, swap_candidates))
 }
 
 mod fix {
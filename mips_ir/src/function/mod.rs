@@ -1,22 +1,25 @@
 mod stack_frame;
 
+use crate::arch::{Arch, ArchInstruction, ArchTerminator, Mips};
 use crate::cfg::{self, BlockId, BlockRef, Cfg};
-use crate::{scanner, AnyReg, Instruction, Label, Reg, Terminator, VirtualInstruction};
+use crate::{scanner, AnyReg, Instruction, Label, Reg, VirtualInstruction};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 pub use stack_frame::*;
 
 /// Used to specify which registers need to start with pointers to stack allocated space.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReferenceRegister {
     pub register: Reg,
     pub stack_info: StackInfo,
 }
 
-#[derive(Debug)]
-pub struct Function {
-    label: Label,
-    pub(crate) cfg: Cfg,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Function<A: Arch = Mips> {
+    pub(crate) label: Label,
+    pub(crate) cfg: Cfg<A>,
     pub(crate) exit_block_id: Option<BlockId>,
     /// The registers here are expected to have references to places on the stack before they're
     /// first used.
@@ -27,12 +30,57 @@ pub struct Function {
     /// If registers have been spilled to memory, their stack_addresses will be given here.
     pub(crate) reg_to_stack_address: HashMap<AnyReg, StackAddress>,
     pub(crate) params: Vec<StackAddress>,
+    /// The params that are floating point values. Only known after [`finish`].
+    pub(crate) fpu_params: BTreeSet<StackAddress>,
     pub(crate) stack_frame: StackFrame,
     stack_info: BTreeMap<StackAddress, StackInfo>,
 }
 
 impl Function {
     pub fn new(label: Label, params_info: Vec<StackInfo>) -> Self {
+        Self::for_arch(label, params_info)
+    }
+
+    pub fn finish(&mut self) {
+        fn insert_loads_for_reference_registers(function: &mut Function) {
+            for decl_location in scanner::function::declares(&function.cfg).collect::<Vec<_>>() {
+                let reg = match function.cfg.instruction(decl_location) {
+                    Some(&Instruction::Virtual(VirtualInstruction::Declare(reg))) => reg,
+                    _ => unreachable!(),
+                };
+                let AnyReg::R(reg) = reg else { continue };
+                if let Some(ref_reg) = function
+                    .reference_registers
+                    .iter()
+                    .find(|r| r.register == reg)
+                    .cloned()
+                {
+                    let stack_addr = match function
+                        .reference_register_to_stack_address
+                        .get(&ref_reg.register)
+                    {
+                        Some(stack_addr) => *stack_addr,
+                        None => {
+                            let stack_addr = function.create_stack_address(ref_reg.stack_info);
+                            function
+                                .reference_register_to_stack_address
+                                .insert(ref_reg.register, stack_addr);
+                            stack_addr
+                        }
+                    };
+                    *function.cfg.instruction_mut(decl_location).unwrap() =
+                        crate::instr::virt::load_stack_address(ref_reg.register, stack_addr);
+                }
+            }
+        }
+        self.declare_params_in_entry_block();
+        insert_loads_for_reference_registers(self);
+    }
+}
+
+impl<A: Arch> Function<A> {
+    /// Same as [`Function::new`], but for a function with instructions of `A`.
+    pub fn for_arch(label: Label, params_info: Vec<StackInfo>) -> Self {
         let mut function = Self {
             label,
             cfg: Cfg::new(cfg::BasicBlock::new_incomplete()),
@@ -41,12 +89,13 @@ impl Function {
             reference_register_to_stack_address: HashMap::new(),
             reg_to_stack_address: HashMap::new(),
             params: Vec::new(),
+            fpu_params: BTreeSet::new(),
             stack_info: BTreeMap::new(),
             stack_frame: StackFrame::new(),
         };
 
         let function_entry_block_id = function.cfg.insert(cfg::BasicBlock::new_incomplete());
-        function.cfg.entry_block_mut().terminator = Some(crate::term::jump(BlockRef::new(
+        function.cfg.entry_block_mut().terminator = Some(A::Terminator::jump(BlockRef::new(
             function_entry_block_id,
             // Note that this may be incorrect, because a param could be a FReg. It's up to
             // `finish` to fix this.
@@ -85,7 +134,7 @@ impl Function {
 
     /// Returns a mutable reference to the entry block of the function, or `None` if the entry
     /// block hasn't been started yet.
-    pub(crate) fn entry_block(&self) -> Option<&cfg::BasicBlock> {
+    pub(crate) fn entry_block(&self) -> Option<&cfg::BasicBlock<A>> {
         self.entry_block_id().map(|id| &self.cfg[id])
     }
 
@@ -100,6 +149,14 @@ impl Function {
         self.reference_registers.push(ref_reg);
     }
 
+    /// Returns for every param whether it is a floating point value, together with its stack
+    /// info.
+    pub(crate) fn params_info(&self) -> impl Iterator<Item = (bool, &StackInfo)> + Clone {
+        self.params
+            .iter()
+            .map(|sa| (self.fpu_params.contains(sa), self.stack_info(*sa)))
+    }
+
     pub(crate) fn non_param_stack_addresses(&self) -> impl Iterator<Item = &StackAddress> {
         self.stack_info
             .keys()
@@ -124,13 +181,13 @@ impl Function {
     /// Returns an iterator that traverses the graph starting from the entry point, visiting every
     /// reachable node exactly once. The default successor of a node is always traversed first (if
     /// it hasn't been traversed yet).
-    pub fn traverse(&self) -> Traverser {
+    pub fn traverse(&self) -> Traverser<'_, A> {
         Traverser::new(self, std::iter::once(self.cfg.entry_block_id()))
     }
 
     /// Same as [`traverse`], but this will also traverse unreachable blocks (i.e. blocks without
     /// predecessors).
-    pub fn traverse_all(&self) -> Traverser {
+    pub fn traverse_all(&self) -> Traverser<'_, A> {
         Traverser::new(
             self,
             std::iter::once(self.cfg.entry_block_id()).chain(self.block_ids()),
@@ -146,7 +203,7 @@ impl Function {
     }
 
     /// Start a basic block with a previously created [`BlockId`].
-    pub fn start_block(&mut self, label: BlockId, arguments: Vec<AnyReg>) -> BBBuilder {
+    pub fn start_block(&mut self, label: BlockId, arguments: Vec<AnyReg>) -> BBBuilder<A> {
         // TODO: guarantee that arguments are unique?
         BBBuilder {
             id: label,
@@ -155,7 +212,7 @@ impl Function {
         }
     }
 
-    pub fn start_entry_block(&mut self, arguments: Vec<AnyReg>) -> BBBuilder {
+    pub fn start_entry_block(&mut self, arguments: Vec<AnyReg>) -> BBBuilder<A> {
         // TODO: guarantee that arguments are unique?
         let succ_id = self.cfg.entry_block().successors().next().unwrap().id;
         BBBuilder {
@@ -167,14 +224,14 @@ impl Function {
 
     /// Starts a block with a new [`BlockId`]. The id can be retrieved from the returned
     /// [`BBBuilder`].
-    pub fn start_new_block(&mut self, arguments: Vec<AnyReg>) -> BBBuilder {
+    pub fn start_new_block(&mut self, arguments: Vec<AnyReg>) -> BBBuilder<A> {
         // TODO: guarantee that arguments are unique?
         let id = self.create_block_label();
         self.start_block(id, arguments)
     }
 
     /// Adds the provided block to the graph. Returns a reference to it for convenience.
-    pub fn add_block(&mut self, block: BasicBlock) -> &cfg::BasicBlock {
+    pub fn add_block(&mut self, block: BasicBlock<A>) -> &cfg::BasicBlock<A> {
         let BasicBlock(id, block) = block;
         if self.cfg[id].is_complete() {
             panic!("attempt to add block with already used label");
@@ -183,81 +240,47 @@ impl Function {
         &self.cfg[id]
     }
 
-    pub fn finish(&mut self) {
-        fn declare_params_in_entry_block(function: &mut Function) {
-            let mut var_generator = function.cfg.var_generator();
-            let arguments = function.entry_block().unwrap().arguments.clone();
-            let entry_block = function.cfg.entry_block_mut();
-
-            for ((i, &stack_addr), arg_reg) in function.params.iter().enumerate().zip(arguments) {
-                let reg = var_generator.next_of_type(arg_reg);
-                match arg_reg {
-                    AnyReg::R(arg_reg) => {
-                        match function
-                            .reference_registers
-                            .iter_mut()
-                            .find(|r| r.register == arg_reg)
-                        {
-                            Some(ref_reg) => {
-                                let reg = reg.try_into().unwrap();
-                                ref_reg.register = reg;
-                                function
-                                    .reference_register_to_stack_address
-                                    .insert(reg, stack_addr);
-                            }
-                            None => {
-                                function.reg_to_stack_address.insert(reg, stack_addr);
-                            }
-                        }
-                    }
-                    AnyReg::F(_) => {
-                        function.reg_to_stack_address.insert(reg, stack_addr);
-                    }
-                }
-                entry_block
-                    .instructions
-                    .push(crate::instr::virt::load_from_stack(reg, stack_addr));
-                entry_block.successors_mut().next().unwrap().arguments[i] = reg;
-            }
-        }
-        fn insert_loads_for_reference_registers(function: &mut Function) {
-            for decl_location in scanner::function::declares(&function.cfg).collect::<Vec<_>>() {
-                let reg = match function.cfg.instruction(decl_location) {
-                    Some(&Instruction::Virtual(VirtualInstruction::Declare(reg))) => reg,
-                    _ => unreachable!(),
-                };
-                let AnyReg::R(reg) = reg else { continue };
-                if let Some(ref_reg) = function
-                    .reference_registers
-                    .iter()
-                    .find(|r| r.register == reg)
-                    .cloned()
-                {
-                    let stack_addr = match function
-                        .reference_register_to_stack_address
-                        .get(&ref_reg.register)
+    /// Loads the params from their stack slots into the arguments of the function's entry block.
+    pub(crate) fn declare_params_in_entry_block(&mut self) {
+        let mut var_generator = self.cfg.var_generator();
+        let arguments = self.entry_block().unwrap().arguments.clone();
+        let entry_block = self.cfg.entry_block_mut();
+
+        for ((i, &stack_addr), arg_reg) in self.params.iter().enumerate().zip(arguments) {
+            let reg = var_generator.next_of_type(arg_reg);
+            match arg_reg {
+                AnyReg::R(arg_reg) => {
+                    match self
+                        .reference_registers
+                        .iter_mut()
+                        .find(|r| r.register == arg_reg)
                     {
-                        Some(stack_addr) => *stack_addr,
+                        Some(ref_reg) => {
+                            let reg = reg.try_into().unwrap();
+                            ref_reg.register = reg;
+                            self.reference_register_to_stack_address
+                                .insert(reg, stack_addr);
+                        }
                         None => {
-                            let stack_addr = function.create_stack_address(ref_reg.stack_info);
-                            function
-                                .reference_register_to_stack_address
-                                .insert(ref_reg.register, stack_addr);
-                            stack_addr
+                            self.reg_to_stack_address.insert(reg, stack_addr);
                         }
-                    };
-                    *function.cfg.instruction_mut(decl_location).unwrap() =
-                        crate::instr::virt::load_stack_address(ref_reg.register, stack_addr);
+                    }
+                }
+                AnyReg::F(_) => {
+                    self.reg_to_stack_address.insert(reg, stack_addr);
+                    self.fpu_params.insert(stack_addr);
                 }
             }
+            entry_block
+                .instructions
+                .push(A::Instruction::load_from_stack(reg, stack_addr));
+            entry_block.successors_mut().next().unwrap().arguments[i] = reg;
         }
-        declare_params_in_entry_block(self);
-        insert_loads_for_reference_registers(self);
     }
 }
 
-pub struct Traverser<'a> {
-    function: &'a Function,
+pub struct Traverser<'a, A: Arch = Mips> {
+    function: &'a Function<A>,
     /// If `Some(_)`, this will be traversed next. Otherwise a random block from `to_traverse` is
     /// chosen.
     next: Option<BlockId>,
@@ -265,13 +288,13 @@ pub struct Traverser<'a> {
     to_traverse: BTreeSet<BlockId>,
 }
 
-impl<'a> Traverser<'a> {
+impl<'a, A: Arch> Traverser<'a, A> {
     /// Creates a new traverser over the blocks of `function`, starting from the first block in
     /// `must_traverse`, and always following the default successor if the previously traversed
     /// block has one, and if it's not already traversed. Guaranteed to (also) visit all other
     /// blocks in `must_traverse`. Blocks without predecessors that are not included in
     /// `must_traverse` will not be traversed.
-    fn new(function: &'a Function, mut must_traverse: impl Iterator<Item = BlockId>) -> Self {
+    fn new(function: &'a Function<A>, mut must_traverse: impl Iterator<Item = BlockId>) -> Self {
         let next = must_traverse.next();
         Self {
             function,
@@ -287,8 +310,8 @@ impl<'a> Traverser<'a> {
     }
 }
 
-impl<'a> Iterator for Traverser<'a> {
-    type Item = (BlockId, &'a cfg::BasicBlock);
+impl<'a, A: Arch> Iterator for Traverser<'a, A> {
+    type Item = (BlockId, &'a cfg::BasicBlock<A>);
 
     fn next(&mut self) -> Option<Self::Item> {
         let current_id = self.next.take().or_else(|| {
@@ -316,21 +339,21 @@ impl<'a> Iterator for Traverser<'a> {
 }
 
 #[derive(Debug)]
-pub struct BasicBlock(BlockId, cfg::BasicBlock);
+pub struct BasicBlock<A: Arch = Mips>(BlockId, cfg::BasicBlock<A>);
 
 #[derive(Debug)]
-pub struct BBBuilder {
+pub struct BBBuilder<A: Arch = Mips> {
     id: BlockId,
     arguments: Vec<AnyReg>,
-    instructions: Vec<Instruction>,
+    instructions: Vec<A::Instruction>,
 }
 
-impl BBBuilder {
+impl<A: Arch> BBBuilder<A> {
     pub fn id(&self) -> BlockId {
         self.id
     }
 
-    pub fn add_instruction(&mut self, instruction: Instruction) {
+    pub fn add_instruction(&mut self, instruction: A::Instruction) {
         self.instructions.push(instruction);
     }
 
@@ -339,7 +362,7 @@ impl BBBuilder {
     }
 
     #[must_use = "A BasicBlock nees to be added to a function to do something"]
-    pub fn terminate(self, terminator: Terminator) -> BasicBlock {
+    pub fn terminate(self, terminator: A::Terminator) -> BasicBlock<A> {
         BasicBlock(
             self.id,
             cfg::BasicBlock {
@@ -1,8 +1,9 @@
-use crate::{AlignBoundary, FReg, Reg};
+use crate::{AlignBoundary, CallingConvention, FReg, Reg};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::ops::Range;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StackInfo {
     pub size: u128,
     pub alignment: AlignBoundary,
@@ -10,12 +11,12 @@ pub struct StackInfo {
 }
 
 /// Identifier for stack space.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct StackAddress(pub u32);
 
 /// Assumes the stack is 8-byte aligned. As such, cannot handle alignments bigger than 8. It will
 /// treat them incorrectly.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackFrame {
     /// `true` when $fp has been set correctly and can be used to refer to the static part of the
     /// stack frame.
@@ -38,6 +39,8 @@ pub struct StackFrame {
     saved_fpu_regs: Vec<FReg>,
     /// Does not include outer padding; space in bytes
     max_call_arguments_space: u32,
+    /// Determines the layout of the params and call arguments.
+    calling_convention: CallingConvention,
 }
 
 impl StackFrame {
@@ -51,6 +54,7 @@ impl StackFrame {
             saved_cpu_regs: Vec::new(),
             saved_fpu_regs: Vec::new(),
             max_call_arguments_space: 0,
+            calling_convention: CallingConvention::default(),
         }
     }
 
@@ -109,14 +113,9 @@ impl StackFrame {
 
     fn addr_of_param(&self, stack_address: StackAddress) -> Option<(Reg, u16)> {
         let index = *self.stack_address_to_param_idx.get(&stack_address)?;
-        let mut offset = self.byte_size() as u32;
-        // This is not necessary, since `offset` should already be 8-byte aligned at this point.
-        offset = self.params[0].alignment.next_multiple_from(offset);
-        for i in 1..=index {
-            offset += self.params[i - 1].size as u32;
-            offset = self.params[i].alignment.next_multiple_from(offset);
-        }
-        Some((self.static_base(), offset as u16))
+        // The params start right after this stack frame, which is always 8-byte aligned.
+        let (offsets, _) = self.calling_convention.arg_layout(&self.params);
+        Some((self.static_base(), self.byte_size() + offsets[index] as u16))
     }
 
     fn addr_of_spilled(&self, stack_address: StackAddress) -> Option<(Reg, u16)> {
@@ -140,31 +139,15 @@ impl StackFrame {
             })
     }
 
-    pub fn call_arg_addrs<'a, I>(&self, call_args: I) -> impl Iterator<Item = (Reg, u16)> + 'a
-    where
-        I: Iterator<Item = &'a StackInfo> + Clone + 'a,
-    {
-        struct Tmp<'a, I: Iterator<Item = &'a StackInfo>> {
-            offset: u16,
-            call_args: I,
-        }
-
-        impl<'a, I: Iterator<Item = &'a StackInfo>> Iterator for Tmp<'a, I> {
-            type Item = (Reg, u16);
-
-            fn next(&mut self) -> Option<Self::Item> {
-                let stack_info = self.call_args.next()?;
-                self.offset = stack_info.alignment.next_multiple_from(self.offset as u32) as u16;
-                let offset = self.offset;
-                self.offset += stack_info.size as u16;
-                Some((Reg::SP, offset))
-            }
-        }
-
-        Tmp {
-            offset: self.call_arguments_area_static_offset_range().start,
-            call_args,
-        }
+    pub fn call_arg_addrs<'a>(
+        &self,
+        call_args: impl IntoIterator<Item = &'a StackInfo>,
+    ) -> impl Iterator<Item = (Reg, u16)> {
+        let (offsets, _) = self.calling_convention.arg_layout(call_args);
+        let start = self.call_arguments_area_static_offset_range().start;
+        offsets
+            .into_iter()
+            .map(move |offset| (Reg::SP, start + offset as u16))
     }
 
     /// Returns the total size in bytes of this stack frame, including padding
@@ -285,6 +268,14 @@ impl StackFrame {
         self.stack_address_to_spilled_idx.remove(&stack_address);
     }
 
+    pub fn calling_convention(&self) -> CallingConvention {
+        self.calling_convention
+    }
+
+    pub fn set_calling_convention(&mut self, calling_convention: CallingConvention) {
+        self.calling_convention = calling_convention;
+    }
+
     pub fn set_max_call_argument_space(&mut self, space: u32) {
         self.max_call_arguments_space = space;
     }
@@ -1,8 +1,9 @@
 use crate::Label;
+use serde::{Deserialize, Serialize};
 use vec1::Vec1;
 
 /// Exponent `n` indicating data should be aligned at a `2.pow(n)`-byte boundary.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct AlignBoundary(pub u32);
 
 impl AlignBoundary {
@@ -49,7 +50,7 @@ pub mod size {
     pub const DOUBLE: u32 = 8;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataDirective {
     Space(u128),
     Ascii(Vec<u8>),
@@ -88,9 +89,9 @@ impl DataDirective {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalData {
-    label: Label,
+    pub(crate) label: Label,
     align: Option<AlignBoundary>,
     data: DataDirective,
 }
@@ -1,4 +1,5 @@
 use crate::{cfg::BlockRef, function::StackAddress, AnyReg, FReg, Label, Reg, StackInfo};
+use serde::{Deserialize, Serialize};
 
 pub mod instr {
     use super::*;
@@ -503,7 +504,7 @@ pub mod term {
 }
 
 /// A regular instructions that can appear inside basic blocks (i.e. doesn't branch).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Instruction {
     Nop,
     Reg3(RegOp3, Reg, Reg, Reg),
@@ -582,19 +583,19 @@ impl Instruction {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PseudoInstruction {
     LoadAddress(Reg, Label),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub label: Label,
     pub return_reg: Option<AnyReg>,
     pub arguments: Vec<(AnyReg, StackInfo)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VirtualInstruction {
     FunctionCall(FunctionCall),
     Declare(AnyReg),
@@ -617,7 +618,7 @@ pub enum VirtualInstruction {
 }
 
 /// An instruction that branches and is used to terminate basic blocks.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Terminator {
     BranchIf(BCond, Reg, Reg, BlockRef, BlockRef),
     BranchIfZ(BZCond, Reg, BlockRef, BlockRef),
@@ -633,12 +634,12 @@ pub enum Terminator {
     Virtual(VirtualTerminator),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VirtualTerminator {
     Return(Option<AnyReg>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RegOp3 {
     /// Add the signed values in the second and third register with overflow, and store the result
     /// in the first register.
@@ -697,7 +698,26 @@ impl std::fmt::Display for RegOp3 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl RegOp3 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 13] = [
+        Self::AddS,
+        Self::AddU,
+        Self::SubS,
+        Self::SubU,
+        Self::And,
+        Self::Or,
+        Self::Nor,
+        Self::Xor,
+        Self::ShiftLeftLogical,
+        Self::ShiftRightLogical,
+        Self::ShiftRightArithmetic,
+        Self::SetLtS,
+        Self::SetLtU,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RegOp2 {
     // Not yet supported in MIPS I.
     // /// Count the number of leading ones in the second register and store the count in the first
@@ -756,7 +776,23 @@ impl std::fmt::Display for RegOp2 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl RegOp2 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 10] = [
+        Self::DivS,
+        Self::DivU,
+        Self::MultS,
+        Self::MultU,
+        Self::TrapIf(TrapCond::Eq),
+        Self::TrapIf(TrapCond::Ne),
+        Self::TrapIf(TrapCond::GeS),
+        Self::TrapIf(TrapCond::GeU),
+        Self::TrapIf(TrapCond::LtS),
+        Self::TrapIf(TrapCond::LtU),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RegOp1 {
     /// Copy the word from the HI register to the specified register.
     MoveFromHi,
@@ -779,7 +815,17 @@ impl std::fmt::Display for RegOp1 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl RegOp1 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 4] = [
+        Self::MoveFromHi,
+        Self::MoveFromLo,
+        Self::MoveToHi,
+        Self::MoveToLo,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ImmOp2 {
     AddS,
     AddU,
@@ -797,7 +843,7 @@ pub enum ImmOp2 {
     SetLtU,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MemOp {
     /// Load byte from memory and store sign-extended in the first register.
     /// Memory address computed by adding the value in the second register to the immediate value.
@@ -854,6 +900,22 @@ impl std::fmt::Display for ImmOp2 {
     }
 }
 
+impl ImmOp2 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 10] = [
+        Self::AddS,
+        Self::AddU,
+        Self::And,
+        Self::Or,
+        Self::Xor,
+        Self::ShiftLeftLogical,
+        Self::ShiftRightLogical,
+        Self::ShiftRightArithmetic,
+        Self::SetLtS,
+        Self::SetLtU,
+    ];
+}
+
 impl std::fmt::Display for MemOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -875,7 +937,27 @@ impl std::fmt::Display for MemOp {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl MemOp {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 14] = [
+        Self::LoadByteS,
+        Self::LoadByteU,
+        Self::LoadHalfS,
+        Self::LoadHalfU,
+        Self::LoadWord,
+        Self::LoadWordLeft,
+        Self::LoadWordRight,
+        Self::StoreByte,
+        Self::StoreHalf,
+        Self::StoreWord,
+        Self::StoreWordLeft,
+        Self::StoreWordRight,
+        Self::LoadLinkedWord,
+        Self::StoreConditionalWord,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ImmOp1 {
     /// Set high-order 16 bits of the first register to the 16-bit immediate and the low-order
     /// 16 bits to 0.
@@ -894,7 +976,20 @@ impl std::fmt::Display for ImmOp1 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl ImmOp1 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 7] = [
+        Self::LoadUpper,
+        Self::TrapIf(TrapCondImm::Eq),
+        Self::TrapIf(TrapCondImm::Ne),
+        Self::TrapIf(TrapCondImm::GeS),
+        Self::TrapIf(TrapCondImm::GeU),
+        Self::TrapIf(TrapCondImm::LtS),
+        Self::TrapIf(TrapCondImm::LtU),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FRegOp3 {
     /// Add the floating-point values in the second and third register and store the result in the
     /// first register.
@@ -928,7 +1023,21 @@ impl std::fmt::Display for FRegOp3 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl FRegOp3 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 8] = [
+        Self::Add(FFmt::S),
+        Self::Add(FFmt::D),
+        Self::Sub(FFmt::S),
+        Self::Sub(FFmt::D),
+        Self::Div(FFmt::S),
+        Self::Div(FFmt::D),
+        Self::Mul(FFmt::S),
+        Self::Mul(FFmt::D),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FRegOp2 {
     /// Store the absolute value of the second register in the first register.
     Abs(FFmt),
@@ -990,7 +1099,33 @@ impl std::fmt::Display for FRegOp2 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl FRegOp2 {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 20] = [
+        Self::Abs(FFmt::S),
+        Self::Abs(FFmt::D),
+        Self::Neg(FFmt::S),
+        Self::Neg(FFmt::D),
+        Self::Sqrt(FFmt::S),
+        Self::Sqrt(FFmt::D),
+        Self::Cmp(FCmp::Eq(FFmt::S)),
+        Self::Cmp(FCmp::Eq(FFmt::D)),
+        Self::Cmp(FCmp::Le(FFmt::S)),
+        Self::Cmp(FCmp::Le(FFmt::D)),
+        Self::Cmp(FCmp::Lt(FFmt::S)),
+        Self::Cmp(FCmp::Lt(FFmt::D)),
+        Self::Convert(FFmt::S, FFmt::D),
+        Self::Convert(FFmt::D, FFmt::S),
+        Self::ConvertToWord(FFmt::S),
+        Self::ConvertToWord(FFmt::D),
+        Self::ConvertFromWord(FFmt::S),
+        Self::ConvertFromWord(FFmt::D),
+        Self::Move(FFmt::S),
+        Self::Move(FFmt::D),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FImmOp {
     /// Load a word from memory and store it in the first (FPU) register.
     /// Memory address is computed by adding the value in the second (integer) register to the
@@ -1023,8 +1158,18 @@ impl std::fmt::Display for FImmOp {
     }
 }
 
+impl FImmOp {
+    /// Every operation, e.g. to look up the operation of a mnemonic.
+    pub const ALL: [Self; 4] = [
+        Self::LoadWordToFpu,
+        Self::StoreWordFromFpu,
+        Self::LoadDoublewordToFpu,
+        Self::StoreDoublewordFromFpu,
+    ];
+}
+
 /// Floating-point format. Either _single_ ([`FFmt::S`]) or _double_ ([`FFmt::D`]).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FFmt {
     /// Single precision (32 bits)
     S,
@@ -1043,7 +1188,7 @@ impl std::fmt::Display for FFmt {
 
 // NOTE: MIPS I already supports more comparisons that account for NaN's: ueq, olt, ngt, etc.
 // MARS only supports the ones listed here.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FCmp {
     Eq(FFmt),
     Le(FFmt),
@@ -1060,7 +1205,7 @@ impl std::fmt::Display for FCmp {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrapCond {
     Eq,
     Ne,
@@ -1083,7 +1228,7 @@ impl std::fmt::Display for TrapCond {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrapCondImm {
     Eq,
     Ne,
@@ -1106,7 +1251,7 @@ impl std::fmt::Display for TrapCondImm {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BCond {
     Eq,
     Ne,
@@ -1121,7 +1266,12 @@ impl std::fmt::Display for BCond {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl BCond {
+    /// Every condition, e.g. to look up the condition of a mnemonic.
+    pub const ALL: [Self; 2] = [Self::Eq, Self::Ne];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BZCond {
     GeZ,
     GtZ,
@@ -1140,7 +1290,12 @@ impl std::fmt::Display for BZCond {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl BZCond {
+    /// Every condition, e.g. to look up the condition of a mnemonic.
+    pub const ALL: [Self; 4] = [Self::GeZ, Self::GtZ, Self::LeZ, Self::LtZ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BZalCond {
     GtZ,
     LtZ,
@@ -1154,3 +1309,8 @@ impl std::fmt::Display for BZalCond {
         })
     }
 }
+
+impl BZalCond {
+    /// Every condition, e.g. to look up the condition of a mnemonic.
+    pub const ALL: [Self; 2] = [Self::GtZ, Self::LtZ];
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// The instruction set a [`crate::Root`] is compiled for.
+///
+/// All passes work on MIPS instructions. RV32 is close enough to MIPS that a compiled root can be
+/// translated instruction by instruction by the [`crate::RiscVOutputter`], so the ISA only changes
+/// which runtime routines the linker adds and how the root is output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Isa {
+    #[default]
+    Mips32,
+    RiscV32,
+}
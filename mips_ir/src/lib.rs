@@ -1,32 +1,48 @@
+mod arch;
+mod calling_convention;
 mod cfg;
 mod dfa;
 mod fixer;
 mod function;
 mod global_data;
 mod instruction;
+mod isa;
 mod label;
 mod linker;
+pub mod object;
 mod optimizer;
 mod outputter;
+mod parser;
 mod passes;
 mod reg;
+mod riscv;
 mod root;
 mod scanner;
+pub mod validator;
+pub mod x86_64;
 
+pub use arch::{Arch, ArchInstruction, ArchTerminator, Mips};
+pub use calling_convention::CallingConvention;
 pub use cfg::{BlockId, BlockRef};
-pub use function::{BBBuilder, BasicBlock, Function, ReferenceRegister, StackInfo};
+pub use function::{BBBuilder, BasicBlock, Function, ReferenceRegister, StackAddress, StackInfo};
 pub use global_data::{size, AlignBoundary, DataDirective, GlobalData};
 pub use instruction::{
     instr, term, BCond, BZCond, BZalCond, FCmp, FFmt, FImmOp, FRegOp2, FRegOp3, FunctionCall,
-    ImmOp1, ImmOp2, Instruction, PseudoInstruction, RegOp1, RegOp2, RegOp3, Terminator, TrapCond,
-    TrapCondImm, VirtualInstruction, VirtualTerminator,
+    ImmOp1, ImmOp2, Instruction, MemOp, PseudoInstruction, RegOp1, RegOp2, RegOp3, Terminator,
+    TrapCond, TrapCondImm, VirtualInstruction, VirtualTerminator,
 };
+pub use isa::Isa;
 pub use label::Label;
+pub use linker::merge;
 pub use outputter::{MipsOutputConfig, MipsOutputter};
+pub use parser::{parse, ParseError};
 pub use reg::{AnyReg, FReg, Reg, VARGenerator};
+pub use riscv::RiscVOutputter;
 pub use root::Root;
 
-pub fn compile_and_link(root: &mut Root) {
+/// Runs all passes on `root`, except for linking. The result is an object that can be stored with
+/// [`object::write_object`] and later be linked with other objects using [`link`].
+pub fn compile(root: &mut Root) {
     passes::patcher::patch_root(root);
     dfa::dce::purge_root(root);
     passes::register_allocation::run(root);
@@ -34,5 +50,18 @@ pub fn compile_and_link(root: &mut Root) {
     passes::devirtualizer::run(root);
     optimizer::simplifier::simplify_root(root);
     fixer::fix_root(root);
-    linker::link(root).expect("linking failed");
+}
+
+/// Merges the compiled objects (see [`merge`]) and links them into a program.
+pub fn link(objects: impl IntoIterator<Item = Root>) -> Result<Root, String> {
+    let mut root = merge(objects)?;
+    linker::link(&mut root)?;
+    Ok(root)
+}
+
+/// Runs all passes on `root` and links it into a program, see [`compile`] and [`link`]. Returns
+/// an error if `root` can't be linked, e.g. because it doesn't export a `main` function.
+pub fn compile_and_link(root: &mut Root) -> Result<(), String> {
+    compile(root);
+    linker::link(root)
 }
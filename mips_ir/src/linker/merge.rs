@@ -0,0 +1,140 @@
+use crate::{
+    DataDirective, Function, Instruction, Label, PseudoInstruction, Root, Terminator,
+    VirtualInstruction,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Merges the objects into a single root, without linking it into a program.
+///
+/// Labels that aren't exported are local to their object, and are renamed if they clash with a
+/// label of another object. External labels are resolved by the labels exported from the other
+/// objects. Fails if multiple objects export the same label, or if the objects use different
+/// calling conventions.
+pub fn merge(objects: impl IntoIterator<Item = Root>) -> Result<Root, String> {
+    let objects: Vec<Root> = objects.into_iter().collect();
+
+    let calling_convention = objects
+        .first()
+        .map(|object| object.calling_convention())
+        .unwrap_or_default();
+    if objects
+        .iter()
+        .any(|object| object.calling_convention() != calling_convention)
+    {
+        return Err("objects use different calling conventions".to_owned());
+    }
+
+    let isa = objects
+        .first()
+        .map(|object| object.isa())
+        .unwrap_or_default();
+    if objects.iter().any(|object| object.isa() != isa) {
+        return Err("objects target different instruction sets".to_owned());
+    }
+
+    // All labels that are shared between objects. Local labels should never clash with these.
+    let mut global_labels = HashSet::new();
+    for object in &objects {
+        for label in object.exported_labels() {
+            if !global_labels.insert(label.clone()) {
+                return Err(format!("duplicate symbol: {label}"));
+            }
+        }
+    }
+    for object in &objects {
+        global_labels.extend(object.external_labels().cloned());
+    }
+
+    let mut merged = Root::new();
+    merged.set_calling_convention(calling_convention);
+    merged.set_isa(isa);
+    for (i, mut object) in objects.into_iter().enumerate() {
+        let local_labels = object
+            .data()
+            .iter()
+            .map(|d| d.label())
+            .chain(object.function_labels())
+            .filter(|label| !object.exports_label(label));
+        let mut renames = HashMap::new();
+        for label in local_labels {
+            if !global_labels.contains(label) && !merged.has_label(label) {
+                continue;
+            }
+            let mut n = i;
+            let new_label = loop {
+                let new_label = Label::from(format!("{label}.{n}"));
+                if !global_labels.contains(&new_label) && !merged.has_label(&new_label) {
+                    break new_label;
+                }
+                n += 1;
+            };
+            renames.insert(label.clone(), new_label);
+        }
+        let rename = |label: &Label| renames.get(label).cloned().unwrap_or_else(|| label.clone());
+
+        let data_labels: Vec<_> = object.data().iter().map(|d| d.label().clone()).collect();
+        for label in data_labels {
+            let mut data = object.remove_data(&label).unwrap();
+            data.label = rename(&data.label);
+            if let DataDirective::LabelWord(label) = data.data() {
+                let label = rename(label);
+                data.set_data(DataDirective::LabelWord(label));
+            }
+            if merged.is_external(&data.label) {
+                merged.remove_external_label(&data.label);
+            }
+            merged.add_data(data);
+        }
+
+        let function_labels: Vec<_> = object.function_labels().cloned().collect();
+        for label in function_labels {
+            let mut function = object.remove_function(&label).unwrap();
+            function.label = rename(&function.label);
+            rename_in_function(&mut function, &rename);
+            if merged.is_external(&function.label) {
+                merged.remove_external_label(&function.label);
+            }
+            merged.add_function(function);
+        }
+
+        for label in object.exported_labels() {
+            merged.export_label(rename(label));
+        }
+
+        for label in object.external_labels() {
+            if !merged.has_label(label) {
+                merged.create_external_label(label.as_ref());
+            }
+        }
+
+        for raw_text in object.raw_text() {
+            merged.add_raw_text(raw_text.clone());
+        }
+    }
+
+    Ok(merged)
+}
+
+fn rename_in_function(function: &mut Function, rename: &impl Fn(&Label) -> Label) {
+    for (_, block) in function.cfg.blocks_mut() {
+        for instruction in &mut block.instructions {
+            rename_in_instruction(instruction, rename);
+        }
+        if let Some(Terminator::BranchIfZAndLink(_, _, label, _)) = &mut block.terminator {
+            *label = rename(label);
+        }
+    }
+}
+
+fn rename_in_instruction(instruction: &mut Instruction, rename: &impl Fn(&Label) -> Label) {
+    match instruction {
+        Instruction::Call(label)
+        | Instruction::Pseudo(PseudoInstruction::LoadAddress(_, label))
+        | Instruction::Virtual(VirtualInstruction::FunctionCall(crate::FunctionCall {
+            label,
+            ..
+        })) => *label = rename(label),
+        Instruction::Hidden(inner) => rename_in_instruction(inner, rename),
+        _ => {}
+    }
+}
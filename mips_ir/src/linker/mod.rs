@@ -1,12 +1,23 @@
-use crate::{Function, Label, Reg, Root};
+#[cfg(test)]
+mod test;
+
+mod merge;
+
+use crate::{CallingConvention, Function, Isa, Label, Reg, Root};
+
+pub use merge::merge;
 
 pub fn link(root: &mut Root) -> Result<(), String> {
+    if root.isa() == Isa::RiscV32 && root.calling_convention() == CallingConvention::O32 {
+        return Err("the O32 calling convention can only be used for MIPS".to_owned());
+    }
+    let isa = root.isa();
     let label_main = Label::from("main");
     if !root.exports_label(&label_main) {
         return Err("no main symbol exported".to_owned());
     }
     match root.function_mut(&label_main) {
-        Some(func) => link_main(func),
+        Some(func) => link_main(func, isa),
         None => return Err("main is not a function".to_owned()),
     }
     if root.is_external(&"printf".into()) {
@@ -18,7 +29,7 @@ pub fn link(root: &mut Root) -> Result<(), String> {
     Ok(())
 }
 
-fn link_main(main: &mut Function) {
+fn link_main(main: &mut Function, isa: Isa) {
     // Make sure the stack is 8-byte aligned before entering main.
     let entry_block = main.cfg.entry_block_mut();
     let mut new_entry_instructions = Vec::with_capacity(entry_block.instructions.len() + 4);
@@ -46,15 +57,49 @@ fn link_main(main: &mut Function) {
             .push(crate::instr::or(Reg::A0, Reg::V0, Reg::ZERO));
         exit_block
             .instructions
-            .push(crate::instr::or_imm(Reg::V0, Reg::ZERO, 17));
+            .push(crate::instr::or_imm(Reg::V0, Reg::ZERO, exit2_syscall(isa)));
         *exit_block.terminator_mut() = crate::term::syscall(None);
     }
 }
 
+/// The number of the syscall that exits with the value in `$a0` as exit code.
+fn exit2_syscall(isa: Isa) -> u16 {
+    match isa {
+        Isa::Mips32 => 17,
+        Isa::RiscV32 => 93,
+    }
+}
+
 fn link_printf(root: &mut Root) {
-    root.add_raw_text(include_str!("printf.asm").to_owned());
+    let printf = match root.isa() {
+        Isa::Mips32 => {
+            with_calling_convention(include_str!("printf.asm"), root.calling_convention())
+        }
+        Isa::RiscV32 => include_str!("printf_rars.s").to_owned(),
+    };
+    root.add_raw_text(printf);
 }
 
 fn link_scanf(root: &mut Root) {
-    root.add_raw_text(include_str!("scanf.asm").to_owned());
+    let scanf = match root.isa() {
+        Isa::Mips32 => {
+            with_calling_convention(include_str!("scanf.asm"), root.calling_convention())
+        }
+        Isa::RiscV32 => include_str!("scanf_rars.s").to_owned(),
+    };
+    root.add_raw_text(scanf);
+}
+
+/// The premade functions expect all their arguments on the stack. For O32, the arguments passed in
+/// `$a0-$a3` are first stored in the home space, like any variadic function would do.
+fn with_calling_convention(function: &str, calling_convention: CallingConvention) -> String {
+    match calling_convention {
+        CallingConvention::Stack => function.to_owned(),
+        CallingConvention::O32 => {
+            let (label, body) = function.split_once('\n').unwrap();
+            format!(
+                "{label}\n\tsw\t$a0, 0($sp)\n\tsw\t$a1, 4($sp)\n\tsw\t$a2, 8($sp)\n\tsw\t$a3, 12($sp)\n{body}"
+            )
+        }
+    }
 }
@@ -0,0 +1,74 @@
+printf:
+	li	t6, 37			# '%'
+	addi	t5, sp, 4
+	lw	t1, 0(sp)
+	mv	t0, t1
+L_printf.main_loop:
+	lb	t2, 0(t1)
+	beqz	t2, L_printf.end
+	beq	t2, t6, L_printf.handle_code_start
+	addi	t1, t1, 1
+	j	L_printf.main_loop
+L_printf.handle_code_start:
+	beq	t0, t1, L_printf.handle_code
+	sb	zero, 0(t1)
+	li	a7, 4
+	mv	a0, t0
+	ecall
+	sb	t6, 0(t1)
+L_printf.handle_code:
+	lb	t3, 1(t1)
+	bne	t3, t6, L_printf.handle_not_percent
+	li	a7, 11
+	mv	a0, t6
+	ecall
+	j	L_printf.code_handle_end
+L_printf.handle_not_percent:
+	li	t4, 102			# 'f'
+	bne	t3, t4, L_printf.handle_not_float
+	addi	t5, t5, 7
+	andi	t5, t5, -8
+	fld	fa0, 0(t5)
+	li	a7, 3
+	ecall
+	addi	t5, t5, 8
+	j	L_printf.code_handle_end
+L_printf.handle_not_float:
+	lw	a0, 0(t5)
+	addi	t5, t5, 4
+	li	t4, 100			# 'd'
+	beq	t3, t4, L_printf.handle_int
+	li	t4, 105			# 'i'
+	bne	t3, t4, L_printf.handle_not_int
+L_printf.handle_int:
+	li	a7, 1
+	ecall
+	j	L_printf.code_handle_end
+L_printf.handle_not_int:
+	li	t4, 99			# 'c'
+	bne	t3, t4, L_printf.handle_not_char
+	li	a7, 11
+	ecall
+	j	L_printf.code_handle_end
+L_printf.handle_not_char:
+	li	t4, 115			# 's'
+	bne	t3, t4, L_printf.handle_not_string
+	li	a7, 4
+	ecall
+	j	L_printf.code_handle_end
+L_printf.handle_not_string:
+	mv	a0, t3
+	li	a7, 11
+	ecall
+L_printf.code_handle_end:
+	addi	t1, t1, 2
+	mv	t0, t1
+	j	L_printf.main_loop
+L_printf.end:
+	beq	t0, t1, L_printf.return
+	li	a7, 4
+	mv	a0, t0
+	ecall
+L_printf.return:
+	li	a0, 1
+	ret
@@ -0,0 +1,129 @@
+scanf:
+	addi	t5, sp, 4
+	li	t4, 0
+	li	a3, 0
+	lw	t1, 0(sp)
+L_scanf.main_loop_with_char_read:
+	lb	t2, 0(t1)
+	beqz	t2, L_scanf.end
+	li	a7, 12
+	ecall
+L_scanf.main_loop:
+	lb	t2, 0(t1)
+	beqz	t2, L_scanf.end
+	li	t6, 37			# '%'
+	beq	t2, t6, L_scanf.handle_code
+	li	a3, 0
+	bne	a0, t2, L_scanf.end
+	addi	t1, t1, 1
+	j	L_scanf.main_loop_with_char_read
+L_scanf.handle_code:
+	lb	t3, 1(t1)
+	addi	a2, t3, -48
+	sltiu	a4, a2, 10
+	beqz	a4, L_scanf.handle_not_length
+	li	t6, 10
+	mul	a3, a3, t6
+	add	a3, a3, a2
+	addi	t1, t1, 1
+	j	L_scanf.handle_code
+L_scanf.handle_not_length:
+	li	t6, 37			# '%'
+	bne	t3, t6, L_scanf.handle_not_percent
+	bne	a0, t6, L_scanf.end
+	j	L_scanf.code_handle_end
+L_scanf.handle_not_percent:
+	lw	a1, 0(t5)
+	addi	t5, t5, 4
+	addi	t4, t4, 1
+	li	t6, 102			# 'f'
+	bne	t3, t6, L_scanf.handle_not_float
+	li	a7, 6
+	ecall
+	fsw	fa0, 0(a1)
+	j	L_scanf.code_handle_end
+L_scanf.handle_not_float:
+	li	t6, 99			# 'c'
+	bne	t3, t6, L_scanf.handle_not_char
+	bnez	a3, L_scanf.handle_char_loop
+	li	a3, 1
+L_scanf.handle_char_loop:
+	sb	a0, 0(a1)
+	addi	a1, a1, 1
+	addi	a3, a3, -1
+	beqz	a3, L_scanf.code_handle_end
+	li	a7, 12
+	ecall
+	j	L_scanf.handle_char_loop
+L_scanf.handle_not_char:
+	j	L_scanf.trim_whitespace
+L_scanf.trim_whitespace_cont:
+	li	a7, 12
+	ecall
+L_scanf.trim_whitespace:
+	li	t6, 32			# ' '
+	beq	a0, t6, L_scanf.trim_whitespace_cont
+	li	t6, 10			# '\n'
+	beq	a0, t6, L_scanf.trim_whitespace_cont
+	li	t6, 9			# '\t'
+	beq	a0, t6, L_scanf.trim_whitespace_cont
+	li	t6, 100			# 'd'
+	beq	t3, t6, L_scanf.handle_int
+	li	t6, 105			# 'i'
+	bne	t3, t6, L_scanf.handle_not_int
+L_scanf.handle_int:
+	li	t0, 0
+L_scanf.handle_int_loop:
+	addi	a5, a0, -48
+	sltiu	a4, a5, 10
+	beqz	a4, L_scanf.handle_int_end_no_char
+	li	t6, 10
+	mul	t0, t0, t6
+	add	t0, t0, a5
+	beqz	a3, L_scanf.handle_int_loop_cont
+	addi	a3, a3, -1
+	bnez	a3, L_scanf.handle_int_loop_cont
+	sw	t0, 0(a1)
+	j	L_scanf.code_handle_end
+L_scanf.handle_int_loop_cont:
+	li	a7, 12
+	ecall
+	j	L_scanf.handle_int_loop
+L_scanf.handle_int_end_no_char:
+	sw	t0, 0(a1)
+	j	L_scanf.code_handle_end_no_char
+L_scanf.handle_not_int:
+	li	t6, 115			# 's'
+	bne	t3, t6, L_scanf.handle_not_string
+L_scanf.handle_string_loop:
+	beqz	a0, L_scanf.handle_string_end_no_char
+	li	t6, 32			# ' '
+	beq	a0, t6, L_scanf.handle_string_end_no_char
+	li	t6, 10			# '\n'
+	beq	a0, t6, L_scanf.handle_string_end_no_char
+	li	t6, 9			# '\t'
+	beq	a0, t6, L_scanf.handle_string_end_no_char
+	sb	a0, 0(a1)
+	addi	a1, a1, 1
+	beqz	a3, L_scanf.handle_string_loop_cont
+	addi	a3, a3, -1
+	bnez	a3, L_scanf.handle_string_loop_cont
+	sb	zero, 0(a1)
+	j	L_scanf.code_handle_end
+L_scanf.handle_string_loop_cont:
+	li	a7, 12
+	ecall
+	j	L_scanf.handle_string_loop
+L_scanf.handle_string_end_no_char:
+	sb	zero, 0(a1)
+	j	L_scanf.code_handle_end_no_char
+L_scanf.handle_not_string:
+L_scanf.code_handle_end:
+	addi	t1, t1, 2
+	j	L_scanf.main_loop_with_char_read
+L_scanf.code_handle_end_no_char:
+	addi	t1, t1, 2
+	j	L_scanf.main_loop
+L_scanf.end:
+	mv	a0, t4
+	ret
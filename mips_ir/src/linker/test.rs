@@ -0,0 +1,114 @@
+use super::*;
+use crate::{DataDirective, GlobalData, Instruction, PseudoInstruction};
+
+/// Creates a root with a function `name` that loads the address of the local data `data`, and
+/// calls `callee` if it is `Some`.
+fn build_object(name: &str, data: &str, callee: Option<&str>) -> Root {
+    let mut root = Root::new();
+    root.add_data(GlobalData::new(data.into(), DataDirective::Word(0)));
+    let mut function = Function::new(name.into(), Vec::new());
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(crate::instr::pseudo::load_address(Reg::T0, data.into()));
+    if let Some(callee) = callee {
+        let callee = root.create_external_label(callee);
+        builder.add_instruction(crate::instr::call(callee));
+    }
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+    root.add_function(function);
+    root.export_label(name.into());
+    root
+}
+
+fn loaded_label(function: &Function) -> Label {
+    function
+        .cfg
+        .blocks()
+        .flat_map(|(_, block)| &block.instructions)
+        .find_map(|instruction| match instruction {
+            Instruction::Pseudo(PseudoInstruction::LoadAddress(_, label)) => Some(label.clone()),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn merge_resolves_external_labels() {
+    let main = build_object("main", "x", Some("foo"));
+    let foo = build_object("foo", "y", None);
+    let merged = merge([main, foo]).unwrap();
+
+    assert!(!merged.is_external(&"foo".into()));
+    assert!(merged.function(&"foo".into()).is_some());
+    assert!(merged.exports_label(&"main".into()));
+    assert!(merged.exports_label(&"foo".into()));
+}
+
+#[test]
+fn merge_renames_clashing_local_labels() {
+    let main = build_object("main", "x", Some("foo"));
+    let foo = build_object("foo", "x", None);
+    let merged = merge([main, foo]).unwrap();
+
+    let main_data = loaded_label(merged.function(&"main".into()).unwrap());
+    let foo_data = loaded_label(merged.function(&"foo".into()).unwrap());
+    assert_ne!(main_data, foo_data);
+    assert!(merged.data().iter().any(|d| d.label() == &main_data));
+    assert!(merged.data().iter().any(|d| d.label() == &foo_data));
+}
+
+#[test]
+fn merge_rejects_duplicate_symbols() {
+    let first = build_object("main", "x", None);
+    let second = build_object("main", "y", None);
+    assert!(merge([first, second]).is_err());
+}
+
+#[test]
+fn merge_rejects_mixed_calling_conventions() {
+    let main = build_object("main", "x", Some("foo"));
+    let mut foo = build_object("foo", "y", None);
+    foo.set_calling_convention(CallingConvention::O32);
+    assert!(merge([main, foo]).is_err());
+}
+
+#[test]
+fn o32_printf_stores_argument_registers() {
+    let mut root = build_object("main", "x", Some("printf"));
+    root.set_calling_convention(CallingConvention::O32);
+    link(&mut root).unwrap();
+    let printf = root
+        .raw_text()
+        .iter()
+        .find(|t| t.starts_with("printf:"))
+        .unwrap();
+    assert!(printf.starts_with("printf:\n\tsw\t$a0, 0($sp)\n"));
+}
+
+#[test]
+fn merge_rejects_mixed_isas() {
+    let main = build_object("main", "x", Some("foo"));
+    let mut foo = build_object("foo", "y", None);
+    foo.set_isa(Isa::RiscV32);
+    assert!(merge([main, foo]).is_err());
+}
+
+#[test]
+fn riscv_links_rars_runtime() {
+    let mut root = build_object("main", "x", Some("printf"));
+    root.set_isa(Isa::RiscV32);
+    link(&mut root).unwrap();
+    let printf = root
+        .raw_text()
+        .iter()
+        .find(|t| t.starts_with("printf:"))
+        .unwrap();
+    assert!(printf.contains("ecall"));
+}
+
+#[test]
+fn riscv_rejects_o32() {
+    let mut root = build_object("main", "x", None);
+    root.set_isa(Isa::RiscV32);
+    root.set_calling_convention(CallingConvention::O32);
+    assert!(link(&mut root).is_err());
+}
@@ -0,0 +1,79 @@
+//! Serialization of (unlinked) [`Root`]s to object files.
+//!
+//! An object file starts with the 4 bytes of [`MAGIC`], followed by the format version as a
+//! little-endian `u32` and the bincode encoding of the [`Root`]. Objects are produced by
+//! [`crate::compile`] and can be combined with [`crate::merge`] (e.g. to create a library archive,
+//! which is just another object) or [`crate::link`].
+
+#[cfg(test)]
+mod test;
+
+use crate::Root;
+use std::io::{Read, Write};
+
+/// Identifies a MIPS object file.
+pub const MAGIC: [u8; 4] = *b"\0mo\x1a";
+
+/// The version of the object format. Must be incremented on every change to the serialized types
+/// (e.g. adding an instruction), since objects of different versions are incompatible.
+pub const VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub enum ObjectError {
+    Io(std::io::Error),
+    /// The input doesn't start with [`MAGIC`].
+    NotAnObject,
+    /// The object was written with another version of the format.
+    UnsupportedVersion(u32),
+    Malformed(bincode::Error),
+}
+
+impl std::fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectError::Io(err) => write!(f, "{err}"),
+            ObjectError::NotAnObject => write!(f, "not a MIPS object file"),
+            ObjectError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported object format version {version} (expected version {VERSION})"
+            ),
+            ObjectError::Malformed(err) => write!(f, "malformed object file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjectError {}
+
+impl From<std::io::Error> for ObjectError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Returns `true` if `bytes` starts with the object file [`MAGIC`].
+pub fn is_object(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Writes `root` as an object file.
+pub fn write_object<W: Write>(root: &Root, mut writer: W) -> Result<(), ObjectError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(writer, root).map_err(ObjectError::Malformed)
+}
+
+/// Reads an object file previously written by [`write_object`].
+pub fn read_object<R: Read>(mut reader: R) -> Result<Root, ObjectError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(ObjectError::NotAnObject);
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(ObjectError::UnsupportedVersion(version));
+    }
+    bincode::deserialize_from(reader).map_err(ObjectError::Malformed)
+}
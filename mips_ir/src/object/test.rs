@@ -0,0 +1,92 @@
+use super::*;
+use crate::{DataDirective, Function, GlobalData, Label, MipsOutputConfig, MipsOutputter, Reg};
+
+fn output(root: &Root) -> String {
+    let mut output = String::new();
+    MipsOutputter::new(&mut output)
+        .with_config(MipsOutputConfig {
+            allow_virtuals: true,
+            show_block_arguments: true,
+            show_all_blocks: true,
+            show_comments: true,
+            ..Default::default()
+        })
+        .write_root(root)
+        .unwrap();
+    output
+}
+
+fn build_root() -> Root {
+    let mut root = Root::new();
+    let string = Label::from("$.const.str.0");
+    root.add_data(GlobalData::new(
+        string.clone(),
+        DataDirective::Bytes(vec1::vec1![b'h', b'i', 0]),
+    ));
+    let printf = root.create_external_label("printf");
+
+    let mut function = Function::new("main".into(), Vec::new());
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(crate::instr::comment(" say hi".to_owned()));
+    builder.add_instruction(crate::instr::pseudo::load_address(Reg::Virtual(1), string));
+    builder.add_instruction(crate::instr::virt::function_call(
+        printf,
+        None,
+        vec![(Reg::Virtual(1).into(), Reg::Virtual(1).stack_info())],
+    ));
+    function.add_block(builder.terminate(crate::term::virt::return_(None)));
+    function.finish();
+    root.add_function(function);
+    root.export_label("main".into());
+    root.add_raw_text("raw:\n\tnop\n".to_owned());
+    root
+}
+
+#[test]
+fn round_trips_root() {
+    let root = build_root();
+    let mut bytes = Vec::new();
+    write_object(&root, &mut bytes).unwrap();
+    assert!(is_object(&bytes));
+
+    let read = read_object(bytes.as_slice()).unwrap();
+    assert_eq!(output(&root), output(&read));
+    assert!(read.exports_label(&"main".into()));
+    assert!(read.is_external(&"printf".into()));
+    assert_eq!(read.raw_text(), root.raw_text());
+}
+
+#[test]
+fn round_trips_compiled_root() {
+    let mut root = build_root();
+    crate::compile(&mut root);
+    let mut bytes = Vec::new();
+    write_object(&root, &mut bytes).unwrap();
+
+    let mut read = read_object(bytes.as_slice()).unwrap();
+    assert_eq!(output(&root), output(&read));
+
+    // The deserialized root should be ready to be linked.
+    crate::linker::link(&mut read).unwrap();
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = Vec::new();
+    write_object(&Root::new(), &mut bytes).unwrap();
+    bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        read_object(bytes.as_slice()),
+        Err(ObjectError::UnsupportedVersion(v)) if v == VERSION + 1
+    ));
+}
+
+#[test]
+fn rejects_non_objects() {
+    let source = "int main() { return 0; }";
+    assert!(!is_object(source.as_bytes()));
+    assert!(matches!(
+        read_object(source.as_bytes()),
+        Err(ObjectError::NotAnObject)
+    ));
+}
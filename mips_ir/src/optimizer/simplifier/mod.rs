@@ -28,7 +28,8 @@ fn remove_nops(function: &mut Function) {
     }
 }
 
-fn merge_chains(function: &mut Function) {
+/// Merges blocks into their only predecessor, if that predecessor unconditionally jumps to them.
+pub(crate) fn merge_chains(function: &mut Function) {
     let mut visited: BTreeSet<BlockId> = BTreeSet::new();
     let mut to_visit: BTreeSet<BlockId> = BTreeSet::new();
     let mut next_block_id = Some(function.cfg.entry_block_id());
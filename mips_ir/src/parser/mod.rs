@@ -0,0 +1,850 @@
+#[cfg(test)]
+mod test;
+
+use crate::{
+    cfg::BlockId, instr, term, AlignBoundary, BCond, BZCond, BZalCond, DataDirective, FImmOp, FReg,
+    FRegOp2, FRegOp3, Function, GlobalData, ImmOp1, ImmOp2, Instruction, Label, MemOp,
+    PseudoInstruction, Reg, RegOp1, RegOp2, RegOp3, Root, Terminator,
+};
+use std::collections::{HashMap, HashSet};
+use vec1::Vec1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The (1-based) line of the assembly text where the error occurred.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses MARS-style MIPS assembly, as written by [`MipsOutputter`](crate::MipsOutputter), into a
+/// [`Root`]. Registers can be written by number or by name, and virtual registers (e.g. `@3` or
+/// `@fd1`) are accepted as well, so tests can be written as assembly text.
+///
+/// A label in the `.text` segment starts a new function if it is the first label, if it is
+/// exported with `.globl`, or if its address is used (e.g. by `jal` or `la`). Other labels start a
+/// basic block of the current function, just like the instruction after a branch does. Blocks
+/// that don't end with a branch fall through to the next block. Branches must stay within the
+/// function, and a function can't fall through into the next one.
+///
+/// Every label in the `.data` segment must be followed by exactly one data directive, optionally
+/// preceded by an `.align`. Labels that are used but not defined become external labels.
+///
+/// Besides the instructions of `mips_ir`, the pseudo-instructions `la`, `li`, `move`, `b`, `beqz`
+/// and `bnez` are supported. Comments are dropped.
+pub fn parse(source: &str) -> Result<Root, ParseError> {
+    Parser::default().parse(source)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Segment {
+    #[default]
+    Text,
+    Data,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Statement<'a> {
+    Label(&'a str),
+    Instruction(&'a str),
+}
+
+/// A statement in the `.text` segment, with its line number and operands.
+struct Line<'a> {
+    nr: usize,
+    statement: Statement<'a>,
+    operands: Vec<&'a str>,
+}
+
+#[derive(Default)]
+struct Parser<'a> {
+    segment: Segment,
+    text: Vec<Line<'a>>,
+    data: Vec<GlobalData>,
+    /// The label of the next data directive, if it was already defined.
+    data_label: Option<(usize, &'a str)>,
+    /// The alignment of the next data directive, if it was set with `.align`.
+    data_align: Option<AlignBoundary>,
+    /// Every label defined in either segment.
+    defined: HashSet<&'a str>,
+    exported: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self, source: &'a str) -> Result<Root, ParseError> {
+        for (i, line) in source.lines().enumerate() {
+            let nr = i + 1;
+            self.parse_line(nr, line)
+                .map_err(|message| ParseError { line: nr, message })?;
+        }
+        if let Some((line, label)) = self.data_label {
+            return Err(ParseError {
+                line,
+                message: format!("label `{label}` has no data"),
+            });
+        }
+
+        let mut root = Root::new();
+        let mut used = HashSet::new();
+        for data in self.data {
+            if let DataDirective::LabelWord(label) = data.data() {
+                used.insert(label.clone());
+            }
+            root.add_data(data);
+        }
+
+        for line in &self.text {
+            if let Some(label) = used_address(line) {
+                used.insert(Label::from(label));
+            }
+        }
+        let functions = function_labels(&self.text, &self.exported, &used);
+        let mut lines = self.text.as_slice();
+        while let Some((first, rest)) = lines.split_first() {
+            let Statement::Label(label) = first.statement else {
+                return Err(ParseError {
+                    line: first.nr,
+                    message: "instruction outside of a function".to_owned(),
+                });
+            };
+            let end = rest
+                .iter()
+                .position(
+                    |line| matches!(line.statement, Statement::Label(l) if functions.contains(l)),
+                )
+                .unwrap_or(rest.len());
+            let function = FunctionParser::new(label, &functions).parse(first.nr, &rest[..end])?;
+            root.add_function(function);
+            lines = &rest[end..];
+        }
+
+        for (line, label) in self.exported {
+            let label = Label::from(label);
+            if !root.has_label(&label) {
+                return Err(ParseError {
+                    line,
+                    message: format!("exported label `{label}` is not defined"),
+                });
+            }
+            root.export_label(label);
+        }
+        for label in used {
+            if !root.has_label(&label) {
+                root.create_external_label(label.as_ref());
+            }
+        }
+        Ok(root)
+    }
+
+    fn parse_line(&mut self, nr: usize, line: &'a str) -> Result<(), String> {
+        let mut rest = strip_comment(line).trim();
+
+        // A line can start with any number of labels.
+        while let Some((label, after)) = rest.split_once(':') {
+            if !is_label(label) {
+                break;
+            }
+            self.define_label(nr, label)?;
+            rest = after.trim_start();
+        }
+
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let (head, operands) = match rest.split_once(char::is_whitespace) {
+            Some((head, operands)) => (head, split_operands(operands.trim())),
+            None => (rest, Vec::new()),
+        };
+
+        if head.starts_with('.') {
+            self.directive(nr, head, operands)
+        } else if self.segment == Segment::Text {
+            self.text.push(Line {
+                nr,
+                statement: Statement::Instruction(head),
+                operands,
+            });
+            Ok(())
+        } else {
+            Err(format!("instruction `{head}` in the data segment"))
+        }
+    }
+
+    fn define_label(&mut self, nr: usize, label: &'a str) -> Result<(), String> {
+        if !self.defined.insert(label) {
+            return Err(format!("label `{label}` is defined more than once"));
+        }
+        match self.segment {
+            Segment::Text => self.text.push(Line {
+                nr,
+                statement: Statement::Label(label),
+                operands: Vec::new(),
+            }),
+            Segment::Data => {
+                if let Some((_, previous)) = self.data_label.replace((nr, label)) {
+                    return Err(format!("label `{previous}` has no data"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, nr: usize, name: &str, operands: Vec<&'a str>) -> Result<(), String> {
+        match name {
+            ".globl" | ".global" => {
+                self.exported.extend(operands.into_iter().map(|l| (nr, l)));
+                return Ok(());
+            }
+            ".text" => {
+                self.segment = Segment::Text;
+                return Ok(());
+            }
+            ".data" => {
+                self.segment = Segment::Data;
+                return Ok(());
+            }
+            ".align" if self.segment == Segment::Text => return Ok(()),
+            _ => {}
+        }
+        if self.segment != Segment::Data {
+            return Err(format!(
+                "directive `{name}` can only be used in the data segment"
+            ));
+        }
+
+        let data = match (name, operands.as_slice()) {
+            (".align", [n]) => {
+                match parse_int(n) {
+                    Some(n @ 0..=15) => self.data_align = Some(AlignBoundary(n as u32)),
+                    _ => return Err(format!("invalid alignment `{n}`")),
+                }
+                return Ok(());
+            }
+            (".space", [n]) => DataDirective::Space(
+                parse_int(n)
+                    .and_then(|n| u128::try_from(n).ok())
+                    .ok_or_else(|| format!("invalid size `{n}`"))?,
+            ),
+            (".ascii", [string]) => DataDirective::Ascii(
+                parse_string(string).ok_or_else(|| format!("invalid string literal {string}"))?,
+            ),
+            (".asciiz", [string]) => DataDirective::AsciiZ(
+                parse_string(string).ok_or_else(|| format!("invalid string literal {string}"))?,
+            ),
+            (".word", [label]) if is_label(label) => DataDirective::LabelWord(Label::from(*label)),
+            (".byte", _) => match parse_list(&operands, |o| parse_int_of(o, 8).map(|v| v as u8))? {
+                Ok(x) => DataDirective::Byte(x),
+                Err(xs) => DataDirective::Bytes(xs),
+            },
+            (".half", _) => match parse_list(&operands, |o| parse_int_of(o, 16).map(|v| v as u16))?
+            {
+                Ok(x) => DataDirective::Half(x),
+                Err(xs) => DataDirective::Halfs(xs),
+            },
+            (".word", _) => match parse_list(&operands, |o| parse_int_of(o, 32).map(|v| v as u32))?
+            {
+                Ok(x) => DataDirective::Word(x),
+                Err(xs) => DataDirective::Words(xs),
+            },
+            (".float", _) => match parse_list(&operands, |o| parse_float(o).map(|v| v as f32))? {
+                Ok(x) => DataDirective::Float(x),
+                Err(xs) => DataDirective::Floats(xs),
+            },
+            (".double", _) => match parse_list(&operands, parse_float)? {
+                Ok(x) => DataDirective::Double(x),
+                Err(xs) => DataDirective::Doubles(xs),
+            },
+            (".align" | ".space" | ".ascii" | ".asciiz", _) => {
+                return Err("expected one operand".to_owned())
+            }
+            _ => return Err(format!("unknown directive `{name}`")),
+        };
+
+        let Some((_, label)) = self.data_label.take() else {
+            return Err(format!("`{name}` without a label"));
+        };
+        self.data
+            .push(GlobalData::new(Label::from(label), data).with_align(self.data_align.take()));
+        Ok(())
+    }
+}
+
+/// Returns the label of which the instruction uses the address, if any.
+fn used_address<'a>(line: &Line<'a>) -> Option<&'a str> {
+    let Statement::Instruction(mnemonic) = line.statement else {
+        return None;
+    };
+    match (mnemonic, line.operands.as_slice()) {
+        ("jal", [label]) | ("la", [_, label]) => Some(*label),
+        (mnemonic, [_, label]) if branch_and_link_cond(mnemonic).is_some() => Some(*label),
+        _ => None,
+    }
+    .filter(|label| is_label(label))
+}
+
+/// Returns the labels in the text segment that start a function.
+fn function_labels<'a>(
+    text: &[Line<'a>],
+    exported: &[(usize, &str)],
+    used: &HashSet<Label>,
+) -> HashSet<&'a str> {
+    text.iter()
+        .filter_map(|line| match line.statement {
+            Statement::Label(label) => Some(label),
+            Statement::Instruction(_) => None,
+        })
+        .enumerate()
+        .filter(|&(i, label)| {
+            i == 0
+                || exported.iter().any(|&(_, l)| l == label)
+                || used.contains(&Label::from(label))
+        })
+        .map(|(_, label)| label)
+        .collect()
+}
+
+struct FunctionParser<'f, 'a> {
+    label: &'a str,
+    function: Function,
+    /// The labels of all functions in the text segment.
+    functions: &'f HashSet<&'a str>,
+    /// The block of every label in this function.
+    blocks: HashMap<&'a str, BlockId>,
+}
+
+impl<'f, 'a> FunctionParser<'f, 'a> {
+    fn new(label: &'a str, functions: &'f HashSet<&'a str>) -> Self {
+        Self {
+            label,
+            function: Function::new(Label::from(label), Vec::new()),
+            functions,
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Parses the body of the function, i.e. the lines after its label on line `nr`.
+    fn parse(mut self, nr: usize, lines: &[Line<'a>]) -> Result<Function, ParseError> {
+        let entry_id = self.function.start_entry_block(Vec::new()).id();
+        // A function can also branch to its own label, e.g. to restart it.
+        self.blocks.insert(self.label, entry_id);
+
+        // First split the lines into blocks, so labels can be referenced before they are defined.
+        let mut blocks = vec![(entry_id, nr, Vec::new())];
+        let mut open = true;
+        for line in lines {
+            match line.statement {
+                Statement::Label(label) => {
+                    let current = blocks.last().unwrap();
+                    let id = match open && current.2.is_empty() {
+                        true => current.0,
+                        false => {
+                            let id = self.function.create_block_label();
+                            blocks.push((id, line.nr, Vec::new()));
+                            id
+                        }
+                    };
+                    self.blocks.insert(label, id);
+                    open = true;
+                }
+                Statement::Instruction(mnemonic) => {
+                    if !open {
+                        let id = self.function.create_block_label();
+                        blocks.push((id, line.nr, Vec::new()));
+                    }
+                    blocks.last_mut().unwrap().2.push(line);
+                    open = !is_terminator(mnemonic);
+                }
+            }
+        }
+
+        for i in 0..blocks.len() {
+            let (id, label_nr, ref lines) = blocks[i];
+            let next = blocks.get(i + 1).map(|block| block.0);
+            let mut builder = self.function.start_block(id, Vec::new());
+            let mut terminator = None;
+            for line in lines {
+                let Statement::Instruction(mnemonic) = line.statement else {
+                    unreachable!()
+                };
+                let operands = Operands(&line.operands);
+                terminator = self
+                    .instruction(mnemonic, operands, next, &mut |instruction| {
+                        builder.add_instruction(instruction)
+                    })
+                    .map_err(|message| ParseError {
+                        line: line.nr,
+                        message,
+                    })?;
+            }
+            let terminator = match (terminator, next) {
+                (Some(terminator), _) => terminator,
+                (None, Some(next)) => term::jump(crate::BlockRef::new(next, Vec::new())),
+                (None, None) => {
+                    return Err(ParseError {
+                        line: lines.last().map_or(label_nr, |line| line.nr),
+                        message: format!("`{}` falls through past its end", self.function.label()),
+                    })
+                }
+            };
+            self.function.add_block(builder.terminate(terminator));
+        }
+
+        // Like compiled functions, merge the entry block of the CFG with the first block, and
+        // make sure the blocks can be written in an order where every block falls through to its
+        // default successor.
+        crate::optimizer::simplifier::merge_chains(&mut self.function);
+        crate::fixer::fix_function(&mut self.function);
+        Ok(self.function)
+    }
+
+    /// Parses an instruction, adds the instructions it consists of with `add`, and returns the
+    /// terminator if it is a branch. `next` is the block after the instruction, if any.
+    fn instruction(
+        &self,
+        mnemonic: &str,
+        operands: Operands,
+        next: Option<BlockId>,
+        add: &mut dyn FnMut(Instruction),
+    ) -> Result<Option<Terminator>, String> {
+        let next_ref = || {
+            next.map(|id| crate::BlockRef::new(id, Vec::new()))
+                .ok_or_else(|| format!("`{}` falls through past its end", self.function.label()))
+        };
+        // A branch to the next block is the same as falling through.
+        let branch = |target: crate::BlockRef, terminator: Terminator| -> Result<_, String> {
+            let next = next_ref()?;
+            Ok(Some(match target.id == next.id {
+                true => term::jump(next),
+                false => terminator,
+            }))
+        };
+
+        let terminator = match mnemonic {
+            "syscall" => {
+                operands.get::<0>()?;
+                term::syscall(next.map(|id| crate::BlockRef::new(id, Vec::new())))
+            }
+            "j" | "b" => {
+                let [label] = operands.get()?;
+                term::jump(self.target(label)?)
+            }
+            "jr" => match operands.get()? {
+                [rs] if parse_reg(rs)? == Reg::RA => term::return_to_ra(),
+                [rs] => return Err(format!("only `jr $ra` is supported, found `jr {rs}`")),
+            },
+            "jalr" => match operands.0 {
+                [rs] => term::jump_and_link_ra(parse_reg(rs)?, next_ref()?),
+                [rd, rs] => term::jump_and_link_reg(parse_reg(rd)?, parse_reg(rs)?, next_ref()?),
+                _ => return Err("expected one or two operands".to_owned()),
+            },
+            "beqz" | "bnez" => {
+                let [rs, label] = operands.get()?;
+                let cond = if mnemonic == "beqz" {
+                    BCond::Eq
+                } else {
+                    BCond::Ne
+                };
+                let target = self.target(label)?;
+                let terminator =
+                    term::branch_if(cond, parse_reg(rs)?, Reg::ZERO, target.clone(), next_ref()?);
+                return branch(target, terminator);
+            }
+            "bc1t" | "bc1f" => {
+                let [label] = operands.get()?;
+                let target = self.target(label)?;
+                let terminator =
+                    term::branch_if_f_cond(mnemonic == "bc1t", target.clone(), next_ref()?);
+                return branch(target, terminator);
+            }
+            _ => {
+                if let Some(cond) = branch_and_link_cond(mnemonic) {
+                    let [rs, label] = operands.get()?;
+                    term::branch_if_z_and_link(
+                        cond,
+                        parse_reg(rs)?,
+                        Label::from(label),
+                        next_ref()?,
+                    )
+                } else if let Some(cond) = find(&BCond::ALL, mnemonic, "b", "") {
+                    let [rs, rt, label] = operands.get()?;
+                    let target = self.target(label)?;
+                    let terminator = term::branch_if(
+                        cond,
+                        parse_reg(rs)?,
+                        parse_reg(rt)?,
+                        target.clone(),
+                        next_ref()?,
+                    );
+                    return branch(target, terminator);
+                } else if let Some(cond) = find(&BZCond::ALL, mnemonic, "b", "") {
+                    let [rs, label] = operands.get()?;
+                    let target = self.target(label)?;
+                    let terminator =
+                        term::branch_if_z(cond, parse_reg(rs)?, target.clone(), next_ref()?);
+                    return branch(target, terminator);
+                } else {
+                    instructions(mnemonic, operands, add)?;
+                    return Ok(None);
+                }
+            }
+        };
+        Ok(Some(terminator))
+    }
+
+    /// Returns a reference to the block of the label, which must be in this function.
+    fn target(&self, label: &str) -> Result<crate::BlockRef, String> {
+        match self.blocks.get(label) {
+            Some(&id) => Ok(crate::BlockRef::new(id, Vec::new())),
+            None if self.functions.contains(label) => Err(format!(
+                "branch from `{}` to the function `{label}`",
+                self.function.label()
+            )),
+            None => Err(format!("undefined label `{label}`")),
+        }
+    }
+}
+
+/// Parses an instruction that doesn't branch, and adds the instructions it consists of with
+/// `add`.
+fn instructions(
+    mnemonic: &str,
+    operands: Operands,
+    add: &mut dyn FnMut(Instruction),
+) -> Result<(), String> {
+    let instruction = match mnemonic {
+        "nop" => operands.get::<0>().map(|_| instr::nop())?,
+        "break" => operands.get::<0>().map(|_| instr::break_())?,
+        "jal" => {
+            let [label] = operands.get()?;
+            instr::call(Label::from(label))
+        }
+        "la" => {
+            let [rt, label] = operands.get()?;
+            if !is_label(label) {
+                return Err(format!("expected a label, found `{label}`"));
+            }
+            Instruction::Pseudo(PseudoInstruction::LoadAddress(
+                parse_reg(rt)?,
+                Label::from(label),
+            ))
+        }
+        "li" => {
+            let [rt, imm] = operands.get()?;
+            let rt = parse_reg(rt)?;
+            let value =
+                parse_int_of(imm, 32).ok_or_else(|| format!("invalid 32-bit immediate `{imm}`"))?;
+            // Expand it like MARS does, but without using `$at`.
+            if i16::try_from(value).is_ok() {
+                instr::add_u_imm(rt, Reg::ZERO, value as u16)
+            } else if u16::try_from(value).is_ok() {
+                instr::or_imm(rt, Reg::ZERO, value as u16)
+            } else {
+                let value = value as u32;
+                add(instr::load_upper(rt, (value >> 16) as u16));
+                match value as u16 {
+                    0 => return Ok(()),
+                    low => instr::or_imm(rt, rt, low),
+                }
+            }
+        }
+        "move" => {
+            let [rd, rs] = operands.get()?;
+            instr::add_u(parse_reg(rd)?, Reg::ZERO, parse_reg(rs)?)
+        }
+        "mfc1" | "mtc1" => {
+            let [rt, fs] = operands.get()?;
+            let (rt, fs) = (parse_reg(rt)?, parse_freg(fs)?);
+            match mnemonic {
+                "mfc1" => instr::move_from_fpu(rt, fs),
+                _ => instr::move_to_fpu(rt, fs),
+            }
+        }
+        _ => op_instruction(mnemonic, operands)?,
+    };
+    add(instruction);
+    Ok(())
+}
+
+/// Parses the instructions that are named after one of the operation enums.
+fn op_instruction(mnemonic: &str, operands: Operands) -> Result<Instruction, String> {
+    if let Some(op) = find(&RegOp3::ALL, mnemonic, "", "") {
+        let [rd, rs, rt] = operands.get()?;
+        return Ok(Instruction::Reg3(
+            op,
+            parse_reg(rd)?,
+            parse_reg(rs)?,
+            parse_reg(rt)?,
+        ));
+    }
+    if let Some(op) = find(&RegOp2::ALL, mnemonic, "", "") {
+        let [rs, rt] = operands.get()?;
+        return Ok(Instruction::Reg2(op, parse_reg(rs)?, parse_reg(rt)?));
+    }
+    if let Some(op) = find(&RegOp1::ALL, mnemonic, "", "") {
+        let [rd] = operands.get()?;
+        return Ok(Instruction::Reg1(op, parse_reg(rd)?));
+    }
+    if let Some(op) = find(&ImmOp2::ALL, mnemonic, "", "") {
+        let [rt, rs, imm] = operands.get()?;
+        return Ok(Instruction::Imm2(
+            op,
+            parse_reg(rt)?,
+            parse_reg(rs)?,
+            parse_imm(imm)?,
+        ));
+    }
+    if let Some(op) = find(&MemOp::ALL, mnemonic, "", "") {
+        let [rt, address] = operands.get()?;
+        let (offset, base) = parse_mem(address)?;
+        return Ok(Instruction::Mem(op, parse_reg(rt)?, base, offset));
+    }
+    if let Some(op) = find(&ImmOp1::ALL, mnemonic, "", "") {
+        let [rt, imm] = operands.get()?;
+        return Ok(Instruction::Imm1(op, parse_reg(rt)?, parse_imm(imm)?));
+    }
+    if let Some(op) = find(&FRegOp3::ALL, mnemonic, "", "") {
+        let [fd, fs, ft] = operands.get()?;
+        return Ok(Instruction::FReg3(
+            op,
+            parse_freg(fd)?,
+            parse_freg(fs)?,
+            parse_freg(ft)?,
+        ));
+    }
+    if let Some(op) = find(&FRegOp2::ALL, mnemonic, "", "") {
+        let [fd, fs] = operands.get()?;
+        return Ok(Instruction::FReg2(op, parse_freg(fd)?, parse_freg(fs)?));
+    }
+    if let Some(op) = find(&FImmOp::ALL, mnemonic, "", "") {
+        let [ft, address] = operands.get()?;
+        let (offset, base) = parse_mem(address)?;
+        return Ok(Instruction::FImm(op, parse_freg(ft)?, base, offset));
+    }
+    Err(format!("unknown instruction `{mnemonic}`"))
+}
+
+/// Returns the operation or condition of which the mnemonic is `{prefix}{op}{suffix}`.
+fn find<T: Copy + std::fmt::Display>(
+    all: &[T],
+    mnemonic: &str,
+    prefix: &str,
+    suffix: &str,
+) -> Option<T> {
+    let name = mnemonic.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    all.iter().copied().find(|op| op.to_string() == name)
+}
+
+fn branch_and_link_cond(mnemonic: &str) -> Option<BZalCond> {
+    find(&BZalCond::ALL, mnemonic, "b", "al")
+}
+
+/// Returns `true` if the instruction ends a basic block.
+fn is_terminator(mnemonic: &str) -> bool {
+    matches!(
+        mnemonic,
+        "syscall" | "j" | "b" | "jr" | "jalr" | "beqz" | "bnez" | "bc1t" | "bc1f"
+    ) || branch_and_link_cond(mnemonic).is_some()
+        || find(&BCond::ALL, mnemonic, "b", "").is_some()
+        || find(&BZCond::ALL, mnemonic, "b", "").is_some()
+}
+
+struct Operands<'o, 'a>(&'o [&'a str]);
+
+impl<'a> Operands<'_, 'a> {
+    fn get<const N: usize>(&self) -> Result<[&'a str; N], String> {
+        self.0.try_into().map_err(|_| match N {
+            0 => "expected no operands".to_owned(),
+            1 => "expected one operand".to_owned(),
+            _ => format!("expected {N} operands"),
+        })
+    }
+}
+
+fn parse_reg(operand: &str) -> Result<Reg, String> {
+    if let Some(n) = operand.strip_prefix('@') {
+        return n
+            .parse()
+            .map(Reg::Virtual)
+            .map_err(|_| format!("invalid virtual register `{operand}`"));
+    }
+    if operand == "$s8" {
+        return Ok(Reg::S8);
+    }
+    (0..32)
+        .map(Reg::R)
+        .find(|reg| reg.to_string() == operand || format!("{reg:#}") == operand)
+        .ok_or_else(|| format!("expected a register, found `{operand}`"))
+}
+
+fn parse_freg(operand: &str) -> Result<FReg, String> {
+    let virtual_reg = |prefix, reg: fn(u32) -> FReg| {
+        operand
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse().ok())
+            .map(reg)
+    };
+    virtual_reg("@fs", FReg::VirtualSingle)
+        .or_else(|| virtual_reg("@fd", FReg::VirtualDouble))
+        .or_else(|| (0..32).map(FReg::F).find(|reg| reg.to_string() == operand))
+        .ok_or_else(|| format!("expected a floating-point register, found `{operand}`"))
+}
+
+/// Parses a 16-bit immediate, which can be written signed or unsigned.
+fn parse_imm(operand: &str) -> Result<u16, String> {
+    parse_int_of(operand, 16)
+        .map(|v| v as u16)
+        .ok_or_else(|| format!("invalid 16-bit immediate `{operand}`"))
+}
+
+/// Parses a memory operand like `8($sp)` or `($sp)`, returns the offset and base register.
+fn parse_mem(operand: &str) -> Result<(u16, Reg), String> {
+    let invalid = || format!("invalid memory operand `{operand}`");
+    let (offset, base) = operand
+        .strip_suffix(')')
+        .and_then(|o| o.split_once('('))
+        .ok_or_else(invalid)?;
+    let offset = match offset.trim() {
+        "" => 0,
+        offset => parse_imm(offset)?,
+    };
+    Ok((offset, parse_reg(base.trim())?))
+}
+
+/// Parses the operands of a data directive. Returns `Ok` if there's a single operand, and `Err`
+/// with all of them otherwise.
+fn parse_list<T>(
+    operands: &[&str],
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Result<T, Vec1<T>>, String> {
+    let mut values = operands
+        .iter()
+        .map(|o| parse(o).ok_or_else(|| format!("invalid value `{o}`")))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values.len() {
+        1 => Ok(Ok(values.remove(0))),
+        _ => Vec1::try_from_vec(values)
+            .map(Err)
+            .map_err(|_| "expected an operand".to_owned()),
+    }
+}
+
+/// Parses an integer that fits in `bits` bits, either signed or unsigned.
+fn parse_int_of(operand: &str, bits: u32) -> Option<i64> {
+    parse_int(operand).filter(|&v| v >= -(1 << (bits - 1)) && v < (1 << bits))
+}
+
+/// Parses a decimal, hexadecimal (`0x`) or character literal.
+fn parse_int(operand: &str) -> Option<i64> {
+    if let Some(literal) = operand.strip_prefix('\'') {
+        let bytes = unescape(literal.strip_suffix('\'')?)?;
+        return match bytes.as_slice() {
+            [byte] => Some(*byte as i64),
+            _ => None,
+        };
+    }
+    let (negative, digits) = match operand.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, operand),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok()?,
+        None => return None,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_float(operand: &str) -> Option<f64> {
+    // The outputter writes infinity as `inf.0`.
+    match operand.parse() {
+        Ok(value) => Some(value),
+        Err(_) => operand.strip_suffix(".0")?.parse().ok(),
+    }
+}
+
+fn parse_string(operand: &str) -> Option<Vec<u8>> {
+    unescape(operand.strip_prefix('"')?.strip_suffix('"')?)
+}
+
+fn unescape(literal: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(literal.len());
+    let mut chars = literal.bytes();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        bytes.push(match chars.next()? {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'0' => 0,
+            escaped @ (b'\\' | b'\'' | b'"') => escaped,
+            _ => return None,
+        });
+    }
+    Some(bytes)
+}
+
+fn is_label(operand: &str) -> bool {
+    !operand.is_empty()
+        && !operand.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && !operand.contains(|c: char| c.is_whitespace() || "'\"(),".contains(c))
+}
+
+/// Removes the comment (`# ...`) from the line, if any. A `#` in a string or character literal
+/// doesn't start a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            (None, _) => {}
+        }
+    }
+    line
+}
+
+/// Splits the operands on commas that aren't in a string or character literal.
+fn split_operands(operands: &str) -> Vec<&str> {
+    if operands.is_empty() {
+        return Vec::new();
+    }
+    let mut result = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in operands.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => {
+                result.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            (None, _) => {}
+        }
+    }
+    result.push(operands[start..].trim());
+    result
+}
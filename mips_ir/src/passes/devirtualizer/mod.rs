@@ -1,4 +1,5 @@
 use crate::{
+    calling_convention::ArgRegs,
     cfg::{BlockId, BlockRef},
     function::StackAddress,
     AnyReg, FFmt, FReg, Function, FunctionCall, Instruction, Reg, Root, StackInfo, Terminator,
@@ -104,10 +105,38 @@ impl Devirtualizer<'_, '_> {
             .stack_frame
             .call_arg_addrs(arguments.iter().map(|(_, info)| info));
 
-        for ((arg_reg, stack_info), (base, offset)) in arguments.iter().zip(call_arg_addrs) {
+        let call_arg_addrs: Vec<_> = call_arg_addrs.collect();
+        for ((arg_reg, stack_info), &(base, offset)) in arguments.iter().zip(&call_arg_addrs) {
             self.store_to_stack(*arg_reg, stack_info, base, offset);
         }
 
+        // Arguments that are passed in registers are loaded back from the stack. This way the moves
+        // into the argument registers can't overwrite each other.
+        let arg_regs = self.function.stack_frame.calling_convention().arg_regs(
+            arguments
+                .iter()
+                .map(|(reg, stack_info)| (matches!(reg, AnyReg::F(_)), stack_info)),
+        );
+        for ((arg_regs, (_, stack_info)), (base, offset)) in
+            arg_regs.into_iter().zip(&arguments).zip(call_arg_addrs)
+        {
+            match arg_regs {
+                Some(ArgRegs::Cpu(reg)) => {
+                    self.load_from_stack(reg.into(), stack_info, base, offset)
+                }
+                Some(ArgRegs::CpuPair(low, high)) => {
+                    self.instructions
+                        .push(crate::instr::load_word(low, base, offset));
+                    self.instructions
+                        .push(crate::instr::load_word(high, base, offset + 4));
+                }
+                Some(ArgRegs::Fpu(freg)) => {
+                    self.load_from_stack(freg.into(), stack_info, base, offset)
+                }
+                None => {}
+            }
+        }
+
         self.instructions.push(crate::instr::call(label));
 
         match return_reg {
@@ -1,32 +1,28 @@
 use super::util::alias_reg_from;
 use crate::{
+    arch::{Arch, ArchInstruction, ArchTerminator},
     cfg::{BasicBlock, BlockId, BlockRef},
     dfa::uda::{GlobalLocation, Location},
-    Function, FunctionCall, Instruction, VirtualInstruction,
+    Function,
 };
 
-pub(crate) fn isolate_calls(function: &mut Function) {
+pub(crate) fn isolate_calls<A: Arch>(function: &mut Function<A>) {
     let block_ids: Vec<_> = function.cfg.blocks().map(|(id, _)| id).collect();
     for block_id in block_ids {
         isolate_calls_in_block(function, block_id);
     }
 }
 
-fn isolate_calls_in_block(function: &mut Function, mut block_id: BlockId) {
+fn isolate_calls_in_block<A: Arch>(function: &mut Function<A>, mut block_id: BlockId) {
     'outer: loop {
         for (idx, instr) in function.cfg[block_id].instructions.iter().enumerate() {
-            // TODO: what about syscalls?
-            match instr {
-                Instruction::Call(_)
-                | Instruction::Virtual(VirtualInstruction::FunctionCall(FunctionCall { .. })) => {
-                    let (_, succ_id) = extract_call_to_block(
-                        function,
-                        GlobalLocation::new(block_id, Location(idx as isize)),
-                    );
-                    block_id = succ_id;
-                    continue 'outer;
-                }
-                _ => {}
+            if instr.is_call() {
+                let (_, succ_id) = extract_call_to_block(
+                    function,
+                    GlobalLocation::new(block_id, Location(idx as isize)),
+                );
+                block_id = succ_id;
+                continue 'outer;
             }
         }
         return;
@@ -35,7 +31,10 @@ fn isolate_calls_in_block(function: &mut Function, mut block_id: BlockId) {
 
 /// Returns the (connector id, successor id) pair. The id of the predecessor is the same as given
 /// by `location.block`.
-fn extract_call_to_block(function: &mut Function, location: GlobalLocation) -> (BlockId, BlockId) {
+fn extract_call_to_block<A: Arch>(
+    function: &mut Function<A>,
+    location: GlobalLocation,
+) -> (BlockId, BlockId) {
     let GlobalLocation { block_id, local } = location;
 
     if location.local.0 == function.cfg[block_id].instructions.len() as isize {
@@ -78,12 +77,12 @@ fn extract_call_to_block(function: &mut Function, location: GlobalLocation) -> (
     let connector_id = function.cfg.insert(BasicBlock {
         arguments: Vec::new(),
         instructions: vec![instruction_to_extract],
-        terminator: Some(crate::term::jump(BlockRef::new(succ_id, Vec::new()))),
+        terminator: Some(A::Terminator::jump(BlockRef::new(succ_id, Vec::new()))),
         is_call_block: true,
     });
 
     function.cfg[block_id].terminator =
-        Some(crate::term::jump(BlockRef::new(connector_id, Vec::new())));
+        Some(A::Terminator::jump(BlockRef::new(connector_id, Vec::new())));
 
     for &reg in &live_accross_call {
         let (alias, _aliases) = alias_reg_from(
@@ -1,6 +1,6 @@
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
-use crate::{cfg::Cfg, AnyReg};
+use crate::{arch::Arch, cfg::Cfg, AnyReg};
 
 use super::coloring::{Color, Coloring};
 
@@ -58,7 +58,11 @@ struct OptimizationUnit<'a> {
     arg: AnyReg,
 }
 
-pub fn coales(cfg: &Cfg, coloring: &Coloring, conflict_graph: &ConflictGraph) -> Coloring {
+pub fn coales<A: Arch>(
+    cfg: &Cfg<A>,
+    coloring: &Coloring,
+    conflict_graph: &ConflictGraph,
+) -> Coloring {
     let mut coloring = coloring.clone();
 
     let mut blocks: Vec<_> = cfg.blocks().collect();
@@ -81,7 +85,7 @@ pub fn coales(cfg: &Cfg, coloring: &Coloring, conflict_graph: &ConflictGraph) ->
                 arg,
             };
 
-            if let Some(c) = ou.optimize() {
+            if let Some(c) = ou.optimize::<A>() {
                 coloring = c;
             }
         }
@@ -117,18 +121,18 @@ impl Ord for Entry {
 }
 
 impl OptimizationUnit<'_> {
-    fn optimize(&self) -> Option<Coloring> {
+    fn optimize<A: Arch>(&self) -> Option<Coloring> {
         let mut heap = BinaryHeap::new();
 
         let color_options: Vec<AnyReg> = match self.arg {
-            AnyReg::R(_) => super::TEMP_CPU_REGS
+            AnyReg::R(_) => A::TEMP_CPU_REGS
                 .iter()
-                .chain(super::SAVED_CPU_REGS.iter())
+                .chain(A::SAVED_CPU_REGS)
                 .map(|&reg| reg.into())
                 .collect(),
-            AnyReg::F(_) => super::TEMP_FPU_D_REGS
+            AnyReg::F(_) => A::TEMP_FPU_REGS
                 .iter()
-                .chain(super::SAVED_FPU_D_REGS.iter())
+                .chain(A::SAVED_FPU_REGS)
                 .map(|&reg| reg.into())
                 .collect(),
         };
@@ -165,7 +169,7 @@ impl OptimizationUnit<'_> {
                     for (reg, color) in to_recolor.into_iter() {
                         let saved_pinned_to_unsaved =
                             inner_virtual_coloring.saved_pinned.contains(&reg)
-                                && !color.physical_reg.is_saved();
+                                && !A::is_saved(color.physical_reg);
                         let already_pinned = inner_virtual_coloring.pinned.contains(&reg);
                         if saved_pinned_to_unsaved || already_pinned {
                             // Register already pinned
@@ -1,7 +1,6 @@
-use super::{
-    coalescing::ConflictGraph, SAVED_CPU_REGS, SAVED_FPU_D_REGS, TEMP_CPU_REGS, TEMP_FPU_D_REGS,
-};
+use super::coalescing::ConflictGraph;
 use crate::{
+    arch::{Arch, ArchInstruction, ArchTerminator},
     cfg::Cfg,
     dfa::liveness,
     dfa::uda::{self, Location},
@@ -28,12 +27,12 @@ pub struct Coloring {
     pub saved_pinned: HashSet<AnyReg>,
 }
 
-pub fn color(cfg: &Cfg) -> (Coloring, ConflictGraph) {
+pub fn color<A: Arch>(cfg: &Cfg<A>) -> (Coloring, ConflictGraph) {
     Colorer::new(cfg).color()
 }
 
-struct Colorer<'a> {
-    cfg: &'a Cfg,
+struct Colorer<'a, A: Arch> {
+    cfg: &'a Cfg<A>,
     du_chains: uda::DuChains,
     live_sets: liveness::LiveSets,
     coloring: Coloring,
@@ -42,8 +41,8 @@ struct Colorer<'a> {
     conflict_graph: ConflictGraph,
 }
 
-impl<'a> Colorer<'a> {
-    fn new(cfg: &'a Cfg) -> Self {
+impl<'a, A: Arch> Colorer<'a, A> {
+    fn new(cfg: &'a Cfg<A>) -> Self {
         Self {
             cfg,
             du_chains: cfg.du_chains(),
@@ -137,13 +136,12 @@ impl<'a> Colorer<'a> {
 
     fn get_unassigned_cpu_color(&self, must_be_saved: bool) -> Color {
         match must_be_saved {
-            true => {
-                self.get_unassigned_color_from(SAVED_CPU_REGS.iter().map(|&r| Color::new(r.into())))
-            }
+            true => self
+                .get_unassigned_color_from(A::SAVED_CPU_REGS.iter().map(|&r| Color::new(r.into()))),
             false => self.get_unassigned_color_from(
-                TEMP_CPU_REGS
+                A::TEMP_CPU_REGS
                     .iter()
-                    .chain(&SAVED_CPU_REGS)
+                    .chain(A::SAVED_CPU_REGS)
                     .map(|&r| Color::new(r.into())),
             ),
         }
@@ -152,11 +150,11 @@ impl<'a> Colorer<'a> {
     fn get_unassigned_fpu_color(&self, must_be_saved: bool) -> Color {
         match must_be_saved {
             true => self
-                .get_unassigned_color_from(SAVED_FPU_D_REGS.iter().map(|&r| Color::new(r.into()))),
+                .get_unassigned_color_from(A::SAVED_FPU_REGS.iter().map(|&r| Color::new(r.into()))),
             false => self.get_unassigned_color_from(
-                TEMP_FPU_D_REGS
+                A::TEMP_FPU_REGS
                     .iter()
-                    .chain(&SAVED_FPU_D_REGS)
+                    .chain(A::SAVED_FPU_REGS)
                     .map(|&r| Color::new(r.into())),
             ),
         }
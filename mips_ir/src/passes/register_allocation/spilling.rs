@@ -1,10 +1,11 @@
-use super::{util::alias_reg_from, N_CPU_REGS, N_FPU_D_REGS, N_SAVED_CPU_REGS, N_SAVED_FPU_D_REGS};
+use super::util::alias_reg_from;
+use crate::arch::{Arch, ArchInstruction, ArchTerminator};
 use crate::cfg::BlockId;
 use crate::function::StackAddress;
 use crate::{
     cfg::Cfg,
     dfa::uda::{GlobalLocation, Location},
-    AnyReg, FReg, Function, Instruction, Reg,
+    AnyReg, FReg, Function, Reg,
 };
 use std::collections::{BTreeMap, HashMap, HashSet};
 
@@ -17,7 +18,7 @@ pub struct SpillingGroup {
 
 pub type SpillingResult = Vec<SpillingGroup>;
 
-pub fn spill_belady(function: &mut Function) {
+pub fn spill_belady<A: Arch>(function: &mut Function<A>) {
     let mut spilling_result = SpillingResult::default();
     let mut belady_info = BTreeMap::new();
 
@@ -53,9 +54,8 @@ pub fn spill_belady(function: &mut Function) {
             if to_reload.is_empty() {
                 continue;
             }
-            let nop_instructions: Vec<_> = std::iter::repeat(Instruction::Nop)
-                .take(to_reload.len())
-                .collect();
+            let nop_instructions: Vec<_> =
+                std::iter::repeat_n(A::Instruction::nop(), to_reload.len()).collect();
             let new_block_id = function
                 .cfg
                 .insert_on_edge(pred_id, block_id, &nop_instructions);
@@ -112,7 +112,7 @@ pub fn spill_belady(function: &mut Function) {
                     .unwrap()
                     .stack_address;
                 function.cfg[new_block_id].instructions[instr_idx] =
-                    crate::instr::virt::load_from_stack(alias, stack_address);
+                    A::Instruction::load_from_stack(alias, stack_address);
             }
         }
     }
@@ -124,14 +124,14 @@ pub fn spill_belady(function: &mut Function) {
             crate::dfa::uda::DefLocation::Instruction(location) => {
                 function.cfg[block_id].instructions.insert(
                     location + 1,
-                    crate::instr::virt::store_to_stack(group.def_reg, group.stack_address),
+                    A::Instruction::store_to_stack(group.def_reg, group.stack_address),
                 );
             }
             crate::dfa::uda::DefLocation::Argument(idx) => {
                 if belady_info[&block_id].in_regs.contains(&group.def_reg) {
                     function.cfg[block_id].instructions.insert(
                         0,
-                        crate::instr::virt::store_to_stack(group.def_reg, group.stack_address),
+                        A::Instruction::store_to_stack(group.def_reg, group.stack_address),
                     );
                 } else {
                     let mut visited = HashSet::<(BlockId, AnyReg)>::new();
@@ -153,7 +153,7 @@ pub fn spill_belady(function: &mut Function) {
                                         Some(info) if info.in_regs.contains(&alias) => {
                                             function.cfg[id].instructions.insert(
                                                 0,
-                                                crate::instr::virt::store_to_stack(
+                                                A::Instruction::store_to_stack(
                                                     alias,
                                                     group.stack_address,
                                                 ),
@@ -178,10 +178,7 @@ pub fn spill_belady(function: &mut Function) {
                                 crate::dfa::uda::DefLocation::Instruction(i) => {
                                     function.cfg[id].instructions.insert(
                                         i + 1,
-                                        crate::instr::virt::store_to_stack(
-                                            alias,
-                                            group.stack_address,
-                                        ),
+                                        A::Instruction::store_to_stack(alias, group.stack_address),
                                     );
                                 }
                             }
@@ -190,10 +187,7 @@ pub fn spill_belady(function: &mut Function) {
                                 Some(info) if info.in_regs.contains(&alias) => {
                                     function.cfg[id].instructions.insert(
                                         0,
-                                        crate::instr::virt::store_to_stack(
-                                            alias,
-                                            group.stack_address,
-                                        ),
+                                        A::Instruction::store_to_stack(alias, group.stack_address),
                                     );
                                 }
                                 Some(_) => {
@@ -257,11 +251,11 @@ struct BeladyBlockProps {
     out_regs: HashSet<AnyReg>,
 }
 
-pub struct BeladyBlock<'a, 'b, 'c> {
+pub struct BeladyBlock<'a, 'b, 'c, A: Arch> {
     block_id: BlockId,
     belady_info: &'c mut BTreeMap<BlockId, BeladyBlockProps>,
     spilling_result: &'b mut SpillingResult,
-    function: &'a mut Function,
+    function: &'a mut Function<A>,
     in_regs: HashSet<AnyReg>,
     current_cpu_regs: HashSet<Reg>,
     current_fpu_regs: HashSet<FReg>,
@@ -269,11 +263,14 @@ pub struct BeladyBlock<'a, 'b, 'c> {
     location: usize,
 }
 
-impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
+impl<'a, 'b, 'c, A: Arch> BeladyBlock<'a, 'b, 'c, A> {
+    const N_CPU_REGS: usize = A::SAVED_CPU_REGS.len() + A::TEMP_CPU_REGS.len();
+    const N_FPU_REGS: usize = A::SAVED_FPU_REGS.len() + A::TEMP_FPU_REGS.len();
+
     fn new(
         belady_info: &'c mut BTreeMap<BlockId, BeladyBlockProps>,
         spilling_result: &'b mut SpillingResult,
-        function: &'a mut Function,
+        function: &'a mut Function<A>,
         block_id: BlockId,
     ) -> Self {
         Self {
@@ -300,16 +297,16 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
         {
             let mut candidates = live_ins.clone();
             let mut n_in_saved_cpu_regs = 0; // saved
-            let mut n_in_saved_fpu_d_regs = 0; // saved
+            let mut n_in_saved_fpu_regs = 0; // saved
             while let Some(&candidate) = candidates.iter().min_by_key(|&&reg| {
                 self.distance_to_next_use_after(self.block_id, -1, reg)
                     .unwrap_or(usize::MAX)
             }) {
                 candidates.remove(&candidate);
                 match candidate {
-                    AnyReg::R(reg) if self.current_cpu_regs.len() < N_CPU_REGS => {
+                    AnyReg::R(reg) if self.current_cpu_regs.len() < Self::N_CPU_REGS => {
                         if is_call_block && live_outs.contains(&candidate) {
-                            if n_in_saved_cpu_regs < N_SAVED_CPU_REGS {
+                            if n_in_saved_cpu_regs < A::SAVED_CPU_REGS.len() {
                                 self.current_cpu_regs.insert(reg);
                                 n_in_saved_cpu_regs += 1;
                             }
@@ -317,11 +314,11 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
                             self.current_cpu_regs.insert(reg);
                         }
                     }
-                    AnyReg::F(freg) if self.current_fpu_regs.len() < N_FPU_D_REGS => {
+                    AnyReg::F(freg) if self.current_fpu_regs.len() < Self::N_FPU_REGS => {
                         if is_call_block && live_outs.contains(&candidate) {
-                            if n_in_saved_fpu_d_regs < N_SAVED_FPU_D_REGS {
+                            if n_in_saved_fpu_regs < A::SAVED_FPU_REGS.len() {
                                 self.current_fpu_regs.insert(freg);
-                                n_in_saved_fpu_d_regs += 1;
+                                n_in_saved_fpu_regs += 1;
                             }
                         } else {
                             self.current_fpu_regs.insert(freg);
@@ -329,8 +326,8 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
                     }
                     _ => (),
                 }
-                if self.current_cpu_regs.len() == N_CPU_REGS
-                    && self.current_fpu_regs.len() == N_FPU_D_REGS
+                if self.current_cpu_regs.len() == Self::N_CPU_REGS
+                    && self.current_fpu_regs.len() == Self::N_FPU_REGS
                 {
                     break;
                 }
@@ -364,7 +361,7 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
 
         if is_call_block {
             // After the call is added, only saved registers can be preserved.
-            while self.current_cpu_regs.len() > N_SAVED_CPU_REGS {
+            while self.current_cpu_regs.len() > A::SAVED_CPU_REGS.len() {
                 let to_remove = *self
                     .current_cpu_regs
                     .iter()
@@ -379,7 +376,7 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
                     .unwrap();
                 self.current_cpu_regs.remove(&to_remove);
             }
-            while self.current_fpu_regs.len() > N_SAVED_FPU_D_REGS {
+            while self.current_fpu_regs.len() > A::SAVED_FPU_REGS.len() {
                 let to_remove = *self
                     .current_fpu_regs
                     .iter()
@@ -439,11 +436,13 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
             }
         }
 
-        let n_cpu_regs_to_displace =
-            usize::saturating_sub(n_missing_cpu_regs + self.current_cpu_regs.len(), N_CPU_REGS);
+        let n_cpu_regs_to_displace = usize::saturating_sub(
+            n_missing_cpu_regs + self.current_cpu_regs.len(),
+            Self::N_CPU_REGS,
+        );
         let n_fpu_regs_to_displace = usize::saturating_sub(
             n_missing_fpu_regs + self.current_fpu_regs.len(),
-            N_FPU_D_REGS,
+            Self::N_FPU_REGS,
         );
 
         let mut displaced = HashSet::new();
@@ -537,7 +536,7 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
             .find(|g| g.def_reg == reg || g.load_regs.contains(&reg))
             .unwrap()
             .stack_address;
-        let reload_instr = crate::instr::virt::load_from_stack(alias, stack_address);
+        let reload_instr = A::Instruction::load_from_stack(alias, stack_address);
         let block = &mut self.function.cfg[self.block_id];
         block.instructions.insert(self.location, reload_instr);
         self.location += 1;
@@ -550,8 +549,8 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
         location: isize,
         reg: AnyReg,
     ) -> Option<usize> {
-        fn rec(
-            cfg: &Cfg,
+        fn rec<A: Arch>(
+            cfg: &Cfg<A>,
             block_id: BlockId,
             location: isize,
             reg: AnyReg,
@@ -612,8 +611,8 @@ impl<'a, 'b, 'c> BeladyBlock<'a, 'b, 'c> {
     }
 }
 
-fn add_spill_aliases(
-    function: &mut Function,
+fn add_spill_aliases<A: Arch>(
+    function: &mut Function<A>,
     spilling_result: &mut SpillingResult,
     reg: AnyReg,
     aliases: impl Iterator<Item = AnyReg>,
@@ -630,7 +629,7 @@ fn add_spill_aliases(
             return;
         }
     }
-    let stack_address = function.create_stack_address(reg.stack_info());
+    let stack_address = function.create_stack_address(A::spill_info(reg));
     function.reg_to_stack_address.insert(reg, stack_address);
     spilling_result.push(SpillingGroup {
         def_reg: reg,
@@ -1,9 +1,10 @@
 use super::coloring::Coloring;
+use crate::arch::{Arch, ArchInstruction, ArchTerminator};
 use crate::cfg::BasicBlock;
-use crate::{AnyReg, FReg, Function, Instruction, Reg};
+use crate::{AnyReg, Function};
 use std::collections::HashMap;
 
-pub fn apply_coloring(function: &mut Function, coloring: &Coloring) {
+pub fn apply_coloring<A: Arch>(function: &mut Function<A>, coloring: &Coloring) {
     for (_, block) in function.cfg.blocks_mut() {
         apply_coloring_to_block(block, coloring);
     }
@@ -15,7 +16,7 @@ pub fn apply_coloring(function: &mut Function, coloring: &Coloring) {
         for succ_id in succ_ids {
             let args = &function.cfg[block_id].successor(succ_id).unwrap().arguments;
             let params = &function.cfg[succ_id].arguments;
-            let perm_instrs = perm_color(params, args);
+            let perm_instrs = perm_color::<A>(params, args);
             if !perm_instrs.is_empty() {
                 function
                     .cfg
@@ -31,7 +32,7 @@ pub fn apply_coloring(function: &mut Function, coloring: &Coloring) {
     }
 }
 
-fn apply_coloring_to_block(block: &mut BasicBlock, coloring: &Coloring) {
+fn apply_coloring_to_block<A: Arch>(block: &mut BasicBlock<A>, coloring: &Coloring) {
     for arg in &mut block.arguments {
         if arg.is_virtual() {
             if let Some(reg) = reg_to_color(*arg, coloring) {
@@ -45,35 +46,28 @@ fn apply_coloring_to_block(block: &mut BasicBlock, coloring: &Coloring) {
         }
     }
     for instr in &mut block.instructions {
-        instr
-            .as_unhidden_mut()
-            .map_uses(|reg| match reg.is_virtual() {
-                true => {
-                    reg_to_color(reg, coloring).unwrap_or_else(|| panic!("missing color for {reg}"))
-                }
-                false => reg,
-            });
-        instr
-            .as_unhidden_mut()
-            .map_defs(|reg| match reg.is_virtual() {
-                true => match reg_to_color(reg, coloring) {
-                    Some(reg) => reg,
-                    // This can only be the case if the instruction defines a virtual register that
-                    // is never used (and thus never assigned a color). The optimizer should
-                    // optimize this away.
-                    None => match reg {
-                        AnyReg::R(_) => Reg::ZERO.into(),
-                        AnyReg::F(_) => {
-                            // This can only happen if there would exist an instruction with a
-                            // side-effect that stores its result in a floating-point register.
-                            // `ldc1` would be such an example...
-                            // FIXME: let the optimizer optimize these things away
-                            panic!("missing color for unused def of virtual fpu register")
-                        }
-                    },
-                },
-                false => reg,
-            });
+        instr.map_uses(|reg| match reg.is_virtual() {
+            true => {
+                reg_to_color(reg, coloring).unwrap_or_else(|| panic!("missing color for {reg}"))
+            }
+            false => reg,
+        });
+        instr.map_defs(|reg| match reg.is_virtual() {
+            true => match reg_to_color(reg, coloring) {
+                Some(reg) => reg,
+                // This can only be the case if the instruction defines a virtual register that
+                // is never used (and thus never assigned a color). The optimizer should
+                // optimize this away.
+                None => A::discard_reg(reg).unwrap_or_else(|| {
+                    // This can only happen if there would exist an instruction with a
+                    // side-effect that stores its result in a floating-point register.
+                    // `ldc1` would be such an example...
+                    // FIXME: let the optimizer optimize these things away
+                    panic!("missing color for unused def of virtual register {reg}")
+                }),
+            },
+            false => reg,
+        });
     }
     block
         .terminator_mut()
@@ -102,7 +96,7 @@ fn reg_to_color(reg: AnyReg, coloring: &Coloring) -> Option<AnyReg> {
 }
 
 /// Generates instructions to permutate the `args` vector to the `params` vector.
-fn perm_color(params: &[AnyReg], args: &[AnyReg]) -> Vec<Instruction> {
+fn perm_color<A: Arch>(params: &[AnyReg], args: &[AnyReg]) -> Vec<A::Instruction> {
     // Maps each param to its arg
     let mut mapping = HashMap::new();
     for (&arg, &param) in args.iter().zip(params) {
@@ -136,22 +130,8 @@ fn perm_color(params: &[AnyReg], args: &[AnyReg]) -> Vec<Instruction> {
             }
         }
         match swap {
-            true => match (param, arg) {
-                (AnyReg::R(p), AnyReg::R(a)) => {
-                    instructions.push(crate::instr::xor(p, p, a));
-                    instructions.push(crate::instr::xor(a, p, a));
-                    instructions.push(crate::instr::xor(p, p, a));
-                }
-                (AnyReg::F(p), AnyReg::F(a)) => {
-                    instructions.push(crate::instr::move_(a.ffmt(), FReg::F(2), a));
-                    instructions.push(crate::instr::move_(p.ffmt(), a, p));
-                    instructions.push(crate::instr::move_(p.ffmt(), p, FReg::F(2)));
-                }
-                (AnyReg::R(_), AnyReg::F(_)) | (AnyReg::F(_), AnyReg::R(_)) => {
-                    unreachable!()
-                }
-            },
-            false => instructions.push(crate::instr::virt::move_(param, arg).to_hidden()),
+            true => instructions.extend(A::Instruction::swap(param, arg)),
+            false => instructions.push(A::Instruction::move_(param, arg)),
         }
     }
     instructions
@@ -1,4 +1,9 @@
-use crate::{cfg::Cfg, dfa::uda::GlobalLocation, AnyReg, BlockId};
+use crate::{
+    arch::{Arch, ArchInstruction, ArchTerminator},
+    cfg::Cfg,
+    dfa::uda::GlobalLocation,
+    AnyReg, BlockId,
+};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 /// Returns the alias for `reg` for each block. If a block is not in this map, `reg` is not
@@ -7,8 +12,8 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 /// been aliased. In this case, the block specified by `location` will not be present in the
 /// returned map. The first element in the returned tuple will always be the original alias used
 /// from `location` onwards.
-pub fn alias_reg_from(
-    cfg: &mut Cfg,
+pub fn alias_reg_from<A: Arch>(
+    cfg: &mut Cfg<A>,
     reg: AnyReg,
     location: GlobalLocation,
 ) -> (AnyReg, BTreeMap<BlockId, AnyReg>) {
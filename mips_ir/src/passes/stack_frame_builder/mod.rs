@@ -1,12 +1,16 @@
-use crate::{scanner, AnyReg, Function, Reg, Root};
+use crate::{calling_convention::ArgRegs, scanner, AnyReg, CallingConvention, Function, Reg, Root};
 
 pub fn run(root: &mut Root) {
+    let calling_convention = root.calling_convention();
     for function in root.functions_mut() {
-        build_stack_frame(function);
+        build_stack_frame(function, calling_convention);
     }
 }
 
-fn build_stack_frame(function: &mut Function) {
+fn build_stack_frame(function: &mut Function, calling_convention: CallingConvention) {
+    function
+        .stack_frame
+        .set_calling_convention(calling_convention);
     generate_stack_frame(function);
     construct_stack_frame(function);
     destruct_stack_frame(function);
@@ -37,12 +41,12 @@ fn generate_stack_frame(function: &mut Function) {
     // Find out the max space needed for the arguments of any call within this function.
     let max_call_arg_space = scanner::function::function_calls(function)
         .map(|call| {
-            let mut size = 0;
-            for (_, stack_info) in &call.arguments {
-                size = stack_info.alignment.next_multiple_from(size);
-                size += stack_info.size as u32;
-            }
-            size
+            let arguments = call.arguments.iter().map(|(_, stack_info)| stack_info);
+            function
+                .stack_frame
+                .calling_convention()
+                .arg_layout(arguments)
+                .1
         })
         .max()
         .unwrap_or(0);
@@ -54,6 +58,38 @@ fn generate_stack_frame(function: &mut Function) {
 fn construct_stack_frame(function: &mut Function) {
     let mut instructions = Vec::new();
 
+    // Store the params passed in registers in their reserved space in the previous stack frame.
+    let calling_convention = function.stack_frame.calling_convention();
+    let (offsets, _) = calling_convention.arg_layout(function.params_info().map(|(_, info)| info));
+    let arg_regs = calling_convention.arg_regs(function.params_info());
+    for ((arg_regs, offset), (_, stack_info)) in arg_regs
+        .into_iter()
+        .zip(offsets)
+        .zip(function.params_info())
+    {
+        let offset = offset as u16;
+        match arg_regs {
+            Some(ArgRegs::Cpu(reg)) => {
+                instructions.push(crate::instr::store_word(reg, Reg::SP, offset));
+            }
+            Some(ArgRegs::CpuPair(low, high)) => {
+                instructions.push(crate::instr::store_word(low, Reg::SP, offset));
+                instructions.push(crate::instr::store_word(high, Reg::SP, offset + 4));
+            }
+            Some(ArgRegs::Fpu(freg)) if stack_info.size as u32 == crate::size::DOUBLE => {
+                instructions.push(crate::instr::store_doubleword_from_fpu(
+                    freg,
+                    Reg::SP,
+                    offset,
+                ));
+            }
+            Some(ArgRegs::Fpu(freg)) => {
+                instructions.push(crate::instr::store_word_from_fpu(freg, Reg::SP, offset));
+            }
+            None => {}
+        }
+    }
+
     // Allocate the stack frame: set the stack pointer.
     let stack_frame_size = function.stack_frame.byte_size();
     instructions.push(crate::instr::add_u_imm(
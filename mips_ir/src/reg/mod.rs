@@ -6,9 +6,10 @@ mod var_generator;
 pub use var_generator::*;
 
 use crate::StackInfo;
+use serde::{Deserialize, Serialize};
 
 /// Represents a (possibly virtual) MIPS register. Can be a CPU or a FPU register.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AnyReg {
     R(Reg),
     F(FReg),
@@ -104,7 +105,7 @@ impl std::fmt::Display for AnyReg {
 /// |`$29`        |`$sp`         | yes | stack pointer |
 /// |`$30`        |`$fp` or `$s8`| yes | frame pointer or another saved temporary |
 /// |`$31`        |`$ra`         | yes | return address (used by e.g. `jal`) |
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Reg {
     /// $0 - $31
     R(u8),
@@ -295,7 +296,7 @@ impl std::fmt::Display for Reg {
 /// |`$f16` - `$f18` | no  | temporaries |
 /// |`$f20` - `$f30` | yes | saved temporaries |
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FReg {
     F(u8),
     /// Virtual single-precision FPU register.
@@ -0,0 +1,563 @@
+#[cfg(test)]
+mod test;
+
+use crate::instruction::MemOp;
+use crate::{
+    BCond, BZCond, BZalCond, BlockId, DataDirective, FCmp, FImmOp, FReg, FRegOp2, FRegOp3,
+    Function, GlobalData, ImmOp1, ImmOp2, Instruction, Label, PseudoInstruction, Reg, RegOp1,
+    RegOp2, RegOp3, Root, Terminator, TrapCond, TrapCondImm,
+};
+use std::fmt::Result;
+
+/// Scratch register used to materialize immediates and addresses that don't fit in the 12-bit
+/// immediates of RV32I.
+const SCRATCH: &str = "tp";
+/// Holds the result of the last floating-point comparison (the MIPS FPU condition flag).
+const FCOND: &str = "gp";
+/// Holds the MIPS HI register.
+const HI: &str = "s9";
+/// Holds the MIPS LO register. Also used for the `ecall` number, so it doesn't survive syscalls.
+const LO: &str = "a7";
+
+/// Can be used to format compiled [`Root`]s as RV32IMFD assembly for the RARS simulator.
+///
+/// The root must be compiled (see [`crate::compile`]) and linked for [`crate::Isa::RiscV32`]: each
+/// MIPS instruction is translated to one or more RISC-V instructions, and the MIPS registers are
+/// mapped to RISC-V registers with the same role:
+///
+/// | MIPS          | RISC-V        |
+/// | ------------- | ------------- |
+/// |`$v0` - `$v1`  |`a0` - `a1`    |
+/// |`$a0` - `$a3`  |`a2` - `a5`    |
+/// |`$t0` - `$t6`  |`t0` - `t6`    |
+/// |`$t7`          |`a6`           |
+/// |`$t8` - `$t9`  |`s10` - `s11`  |
+/// |`$s0` - `$s7`  |`s1` - `s8`    |
+/// |`$fp`          |`s0`           |
+/// |`$fN`          |`fN`           |
+///
+/// `$zero`, `$sp` and `$ra` keep their names. `$at`, `$gp`, `$k0` and `$k1` aren't used by the
+/// compiler and can't be translated; their RISC-V counterparts are used by the translation itself
+/// (see the constants in this module).
+///
+/// Conditional branches only reach ±4 KiB. In functions that may be larger than that, they're
+/// output as the inverted branch over a `j` to the target.
+pub struct RiscVOutputter<'w, W: std::fmt::Write> {
+    writer: &'w mut W,
+    show_comments: bool,
+    /// Counter to create unique labels for the translation of traps, conditional calls and long
+    /// branches.
+    n_local_labels: usize,
+    /// Whether the conditional branches of the current function may not reach their target.
+    long_branches: bool,
+}
+
+impl<'w, W: std::fmt::Write> RiscVOutputter<'w, W> {
+    pub fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            show_comments: false,
+            n_local_labels: 0,
+            long_branches: false,
+        }
+    }
+
+    /// If `true`, comments will be printed.
+    pub fn with_comments(self, show_comments: bool) -> Self {
+        Self {
+            show_comments,
+            ..self
+        }
+    }
+
+    pub fn write_root(&mut self, value: &Root) -> Result {
+        for label in value.exported_labels() {
+            writeln!(self.writer, "\t.globl\t{}", label_name(label))?
+        }
+
+        if value.has_any_data() {
+            self.write_str("\t.data\n")?;
+        }
+        for data in value.data() {
+            self.writeln()?;
+            self.write_global_data(data)?;
+        }
+
+        if value.has_any_functions() {
+            self.write_str("\t.text\n")?;
+        }
+
+        for function in value.functions() {
+            self.writeln()?;
+            self.write_function(function)?;
+        }
+
+        for raw_text in value.raw_text() {
+            self.writeln()?;
+            self.write_str(raw_text)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_global_data(&mut self, value: &GlobalData) -> Result {
+        if *value.align() != 0 || value.align() != value.data().natural_align() {
+            writeln!(self.writer, "\t.align\t{}", *value.align())?;
+        }
+
+        writeln!(self.writer, "{}:", label_name(value.label()))?;
+
+        match value.data() {
+            DataDirective::Space(n) => writeln!(self.writer, "\t.space\t{n}"),
+            DataDirective::Ascii(string) => writeln!(
+                self.writer,
+                "\t.ascii\t\"{}\"",
+                std::str::from_utf8(string).expect("unimplemented: escaping .ascii strings")
+            ),
+            DataDirective::AsciiZ(string) => writeln!(
+                self.writer,
+                "\t.asciz\t\"{}\"",
+                std::str::from_utf8(string).expect("unimplemented: escaping .asciz strings")
+            ),
+            DataDirective::Byte(x) => writeln!(self.writer, "\t.byte\t{x}"),
+            DataDirective::Half(x) => writeln!(self.writer, "\t.half\t{x}"),
+            DataDirective::Word(x) => writeln!(self.writer, "\t.word\t{x}"),
+            DataDirective::Float(x) => writeln!(self.writer, "\t.float\t{}", float_constant(*x)),
+            DataDirective::Double(x) => {
+                writeln!(self.writer, "\t.double\t{}", double_constant(*x))
+            }
+            DataDirective::Bytes(xs) => writeln!(self.writer, "\t.byte\t{}", join(xs.iter())),
+            DataDirective::Halfs(xs) => writeln!(self.writer, "\t.half\t{}", join(xs.iter())),
+            DataDirective::Words(xs) => writeln!(self.writer, "\t.word\t{}", join(xs.iter())),
+            DataDirective::Floats(xs) => writeln!(
+                self.writer,
+                "\t.float\t{}",
+                join(xs.iter().map(|x| float_constant(*x)))
+            ),
+            DataDirective::Doubles(xs) => writeln!(
+                self.writer,
+                "\t.double\t{}",
+                join(xs.iter().map(|x| double_constant(*x)))
+            ),
+            DataDirective::LabelWord(label) => {
+                writeln!(self.writer, "\t.word\t{}", label_name(label))
+            }
+        }
+    }
+
+    pub fn write_function(&mut self, value: &Function) -> Result {
+        self.long_branches = may_exceed_branch_range(value);
+        self.write_function_text(value)
+    }
+
+    /// Writes the function, with long branches if `self.long_branches` is set.
+    fn write_function_text(&mut self, value: &Function) -> Result {
+        writeln!(self.writer, "{}:", label_name(value.label()))?;
+        for (id, _) in value.traverse() {
+            self.write_basic_block(value, id)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_basic_block(&mut self, function: &Function, block_id: BlockId) -> Result {
+        let block = &function.cfg[block_id];
+        if function.cfg.n_predecessors(block_id) != 0 {
+            writeln!(
+                self.writer,
+                "{}:",
+                label_name(&function.block_label(block_id))
+            )?;
+        }
+        for instruction in &block.instructions {
+            self.write_instruction(instruction)?;
+        }
+        self.write_terminator(function, block.terminator())
+    }
+
+    pub fn write_instruction(&mut self, value: &Instruction) -> Result {
+        match value {
+            Instruction::Nop => self.line("nop"),
+            &Instruction::Reg3(op, rd, rs, rt) => {
+                let (rd, rs, rt) = (reg(rd), reg(rs), reg(rt));
+                let op = match op {
+                    RegOp3::AddS | RegOp3::AddU => "add",
+                    RegOp3::SubS | RegOp3::SubU => "sub",
+                    RegOp3::And => "and",
+                    RegOp3::Or => "or",
+                    RegOp3::Xor => "xor",
+                    RegOp3::Nor => {
+                        self.line(&format!("or\t{rd}, {rs}, {rt}"))?;
+                        return self.line(&format!("not\t{rd}, {rd}"));
+                    }
+                    RegOp3::ShiftLeftLogical => "sll",
+                    RegOp3::ShiftRightLogical => "srl",
+                    RegOp3::ShiftRightArithmetic => "sra",
+                    RegOp3::SetLtS => "slt",
+                    RegOp3::SetLtU => "sltu",
+                };
+                self.line(&format!("{op}\t{rd}, {rs}, {rt}"))
+            }
+            &Instruction::Reg2(op, rs, rt) => {
+                let (rs, rt) = (reg(rs), reg(rt));
+                let (lo, hi) = match op {
+                    RegOp2::DivS => ("div", "rem"),
+                    RegOp2::DivU => ("divu", "remu"),
+                    RegOp2::MultS => ("mul", "mulh"),
+                    RegOp2::MultU => ("mul", "mulhu"),
+                    RegOp2::TrapIf(cond) => {
+                        let branch = match cond {
+                            TrapCond::Eq => "bne",
+                            TrapCond::Ne => "beq",
+                            TrapCond::GeS => "blt",
+                            TrapCond::GeU => "bltu",
+                            TrapCond::LtS => "bge",
+                            TrapCond::LtU => "bgeu",
+                        };
+                        return self.write_trap(branch, rs, rt);
+                    }
+                };
+                self.line(&format!("{hi}\t{HI}, {rs}, {rt}"))?;
+                self.line(&format!("{lo}\t{LO}, {rs}, {rt}"))
+            }
+            &Instruction::Reg1(op, rd) => {
+                let rd = reg(rd);
+                match op {
+                    RegOp1::MoveFromHi => self.line(&format!("mv\t{rd}, {HI}")),
+                    RegOp1::MoveFromLo => self.line(&format!("mv\t{rd}, {LO}")),
+                    RegOp1::MoveToHi => self.line(&format!("mv\t{HI}, {rd}")),
+                    RegOp1::MoveToLo => self.line(&format!("mv\t{LO}, {rd}")),
+                }
+            }
+            &Instruction::Imm2(op, rt, rs, imm) => {
+                let (rt, rs) = (reg(rt), reg(rs));
+                let (op, value) = match op {
+                    ImmOp2::AddS | ImmOp2::AddU => ("add", imm as i16 as i32),
+                    ImmOp2::SetLtS => ("slt", imm as i16 as i32),
+                    ImmOp2::SetLtU => ("sltu", imm as i16 as i32),
+                    ImmOp2::And => ("and", imm as i32),
+                    ImmOp2::Or => ("or", imm as i32),
+                    ImmOp2::Xor => ("xor", imm as i32),
+                    ImmOp2::ShiftLeftLogical => {
+                        return self.line(&format!("slli\t{rt}, {rs}, {imm}"))
+                    }
+                    ImmOp2::ShiftRightLogical => {
+                        return self.line(&format!("srli\t{rt}, {rs}, {imm}"))
+                    }
+                    ImmOp2::ShiftRightArithmetic => {
+                        return self.line(&format!("srai\t{rt}, {rs}, {imm}"))
+                    }
+                };
+                if fits_imm12(value) {
+                    self.line(&format!("{op}i\t{rt}, {rs}, {value}"))
+                } else {
+                    self.line(&format!("li\t{SCRATCH}, {value}"))?;
+                    self.line(&format!("{op}\t{rt}, {rs}, {SCRATCH}"))
+                }
+            }
+            &Instruction::Mem(op, rt, base, offset) => {
+                let op = match op {
+                    MemOp::LoadByteS => "lb",
+                    MemOp::LoadByteU => "lbu",
+                    MemOp::LoadHalfS => "lh",
+                    MemOp::LoadHalfU => "lhu",
+                    MemOp::LoadWord => "lw",
+                    MemOp::StoreByte => "sb",
+                    MemOp::StoreHalf => "sh",
+                    MemOp::StoreWord => "sw",
+                    MemOp::LoadWordLeft
+                    | MemOp::LoadWordRight
+                    | MemOp::StoreWordLeft
+                    | MemOp::StoreWordRight
+                    | MemOp::LoadLinkedWord
+                    | MemOp::StoreConditionalWord => {
+                        panic!("ICE: `{op}` has no RISC-V equivalent")
+                    }
+                };
+                self.write_memory_access(op, reg(rt), base, offset)
+            }
+            &Instruction::Imm1(op, rt, imm) => {
+                let rt = reg(rt);
+                match op {
+                    ImmOp1::LoadUpper => {
+                        // MIPS loads 16 upper bits, RISC-V loads 20 upper bits.
+                        self.line(&format!("lui\t{rt}, {}", (imm as u32) << 4))
+                    }
+                    ImmOp1::TrapIf(cond) => {
+                        let branch = match cond {
+                            TrapCondImm::Eq => "bne",
+                            TrapCondImm::Ne => "beq",
+                            TrapCondImm::GeS => "blt",
+                            TrapCondImm::GeU => "bltu",
+                            TrapCondImm::LtS => "bge",
+                            TrapCondImm::LtU => "bgeu",
+                        };
+                        self.line(&format!("li\t{SCRATCH}, {}", imm as i16))?;
+                        self.write_trap(branch, rt, SCRATCH)
+                    }
+                }
+            }
+            &Instruction::FReg3(op, fd, fs, ft) => {
+                let (op, fmt) = match op {
+                    FRegOp3::Add(fmt) => ("fadd", fmt),
+                    FRegOp3::Sub(fmt) => ("fsub", fmt),
+                    FRegOp3::Mul(fmt) => ("fmul", fmt),
+                    FRegOp3::Div(fmt) => ("fdiv", fmt),
+                };
+                let (fd, fs, ft) = (freg(fd), freg(fs), freg(ft));
+                self.line(&format!("{op}.{fmt}\t{fd}, {fs}, {ft}"))
+            }
+            &Instruction::FReg2(op, fd, fs) => {
+                let (fd, fs) = (freg(fd), freg(fs));
+                match op {
+                    FRegOp2::Abs(fmt) => self.line(&format!("fabs.{fmt}\t{fd}, {fs}")),
+                    FRegOp2::Neg(fmt) => self.line(&format!("fneg.{fmt}\t{fd}, {fs}")),
+                    FRegOp2::Sqrt(fmt) => self.line(&format!("fsqrt.{fmt}\t{fd}, {fs}")),
+                    FRegOp2::Move(fmt) => self.line(&format!("fmv.{fmt}\t{fd}, {fs}")),
+                    FRegOp2::Convert(to, from) => {
+                        self.line(&format!("fcvt.{to}.{from}\t{fd}, {fs}"))
+                    }
+                    // MIPS keeps converted integers in FPU registers, RISC-V converts directly
+                    // between integer and FPU registers.
+                    FRegOp2::ConvertToWord(fmt) => {
+                        self.line(&format!("fcvt.w.{fmt}\t{SCRATCH}, {fs}, rtz"))?;
+                        self.line(&format!("fmv.w.x\t{fd}, {SCRATCH}"))
+                    }
+                    FRegOp2::ConvertFromWord(fmt) => {
+                        self.line(&format!("fmv.x.w\t{SCRATCH}, {fs}"))?;
+                        self.line(&format!("fcvt.{fmt}.w\t{fd}, {SCRATCH}"))
+                    }
+                    FRegOp2::Cmp(cmp) => {
+                        let (op, fmt) = match cmp {
+                            FCmp::Eq(fmt) => ("feq", fmt),
+                            FCmp::Le(fmt) => ("fle", fmt),
+                            FCmp::Lt(fmt) => ("flt", fmt),
+                        };
+                        // `c.cond.fmt fs, ft` is stored with `fs` and `ft` as first and second
+                        // register.
+                        self.line(&format!("{op}.{fmt}\t{FCOND}, {fd}, {fs}"))
+                    }
+                }
+            }
+            &Instruction::FImm(op, ft, base, offset) => {
+                let op = match op {
+                    FImmOp::LoadWordToFpu => "flw",
+                    FImmOp::StoreWordFromFpu => "fsw",
+                    FImmOp::LoadDoublewordToFpu => "fld",
+                    FImmOp::StoreDoublewordFromFpu => "fsd",
+                };
+                self.write_memory_access(op, freg(ft), base, offset)
+            }
+            &Instruction::MoveFromFpu(rt, fs) => {
+                self.line(&format!("fmv.x.w\t{}, {}", reg(rt), freg(fs)))
+            }
+            &Instruction::MoveToFpu(rt, fs) => {
+                self.line(&format!("fmv.w.x\t{}, {}", freg(fs), reg(rt)))
+            }
+            Instruction::Break => self.line("ebreak"),
+            Instruction::Call(target) => self.line(&format!("jal\t{}", label_name(target))),
+            Instruction::Pseudo(PseudoInstruction::LoadAddress(rt, label)) => {
+                self.line(&format!("la\t{}, {}", reg(*rt), label_name(label)))
+            }
+            Instruction::Comment(comment) => {
+                if self.show_comments {
+                    for line in comment.lines() {
+                        self.line(&format!("#{line}"))?;
+                    }
+                }
+                Ok(())
+            }
+            Instruction::Virtual(_) => panic!("ICE: can't translate virtual instructions"),
+            Instruction::Hidden(_) => panic!("ICE: can't translate hidden instructions"),
+        }
+    }
+
+    pub fn write_terminator(&mut self, function: &Function, value: &Terminator) -> Result {
+        let block_label = |id| label_name(&function.block_label(id));
+        match value {
+            Terminator::BranchIf(cond, rs, rt, true_target, _) => {
+                let (op, inverse) = match cond {
+                    BCond::Eq => ("beq", "bne"),
+                    BCond::Ne => ("bne", "beq"),
+                };
+                let operands = format!("{}, {}", reg(*rs), reg(*rt));
+                self.write_branch(op, inverse, &operands, &block_label(true_target.id))
+            }
+            Terminator::BranchIfZ(cond, rs, true_target, _) => {
+                let (op, inverse) = match cond {
+                    BZCond::GeZ => ("bgez", "bltz"),
+                    BZCond::GtZ => ("bgtz", "blez"),
+                    BZCond::LeZ => ("blez", "bgtz"),
+                    BZCond::LtZ => ("bltz", "bgez"),
+                };
+                self.write_branch(op, inverse, reg(*rs), &block_label(true_target.id))
+            }
+            Terminator::BranchIfZAndLink(cond, rs, target, _) => {
+                let skip = match cond {
+                    BZalCond::GtZ => "blez",
+                    BZalCond::LtZ => "bgez",
+                };
+                let label = self.new_local_label();
+                self.line(&format!("{skip}\t{}, {label}", reg(*rs)))?;
+                self.line(&format!("jal\t{}", label_name(target)))?;
+                writeln!(self.writer, "{label}:")
+            }
+            Terminator::BranchIfFCond(b, true_target, _) => {
+                let (op, inverse) = if *b {
+                    ("bnez", "beqz")
+                } else {
+                    ("beqz", "bnez")
+                };
+                self.write_branch(op, inverse, FCOND, &block_label(true_target.id))
+            }
+            Terminator::Jump(target) => self.line(&format!("j\t{}", block_label(target.id))),
+            Terminator::ReturnToRa => self.line("ret"),
+            Terminator::Syscall(_) => {
+                // MIPS passes the service number in `$v0` and the arguments in `$a0` and `$a1`.
+                self.line(&format!("mv\t{LO}, {}", reg(Reg::V0)))?;
+                self.line(&format!("mv\t{}, {}", reg(Reg::V0), reg(Reg::A0)))?;
+                self.line(&format!("mv\t{}, {}", reg(Reg::V1), reg(Reg::A1)))?;
+                self.line("ecall")
+            }
+            Terminator::JumpAndLinkRa(rs, _) => self.line(&format!("jalr\t{}", reg(*rs))),
+            Terminator::JumpAndLinkReg(rd, rs, _) => {
+                self.line(&format!("jalr\t{}, {}, 0", reg(*rd), reg(*rs)))
+            }
+            Terminator::Virtual(_) => panic!("ICE: can't translate virtual terminators"),
+        }
+    }
+
+    /// Writes a load or store. Offsets that don't fit in 12 bits are added to the base first.
+    fn write_memory_access(&mut self, op: &str, rt: &str, base: Reg, offset: u16) -> Result {
+        let offset = offset as i16 as i32;
+        if fits_imm12(offset) {
+            self.line(&format!("{op}\t{rt}, {offset}({})", reg(base)))
+        } else {
+            self.line(&format!("li\t{SCRATCH}, {offset}"))?;
+            self.line(&format!("add\t{SCRATCH}, {SCRATCH}, {}", reg(base)))?;
+            self.line(&format!("{op}\t{rt}, 0({SCRATCH})"))
+        }
+    }
+
+    /// Writes a conditional branch `op` to `target`. If the target may be out of range, the
+    /// `inverse` branch skips a `j` to the target instead.
+    fn write_branch(&mut self, op: &str, inverse: &str, operands: &str, target: &str) -> Result {
+        if !self.long_branches {
+            return self.line(&format!("{op}\t{operands}, {target}"));
+        }
+        let label = self.new_local_label();
+        self.line(&format!("{inverse}\t{operands}, {label}"))?;
+        self.line(&format!("j\t{target}"))?;
+        writeln!(self.writer, "{label}:")
+    }
+
+    /// Writes a trap as a branch that skips an `ebreak` if the (inverted) condition holds.
+    fn write_trap(&mut self, skip: &str, rs: &str, rt: &str) -> Result {
+        let label = self.new_local_label();
+        self.line(&format!("{skip}\t{rs}, {rt}, {label}"))?;
+        self.line("ebreak")?;
+        writeln!(self.writer, "{label}:")
+    }
+
+    fn new_local_label(&mut self) -> String {
+        self.n_local_labels += 1;
+        format!("L_.local.{}", self.n_local_labels)
+    }
+
+    /// Writes an indented line.
+    fn line(&mut self, s: &str) -> Result {
+        writeln!(self.writer, "\t{s}")
+    }
+
+    /// Writes a single newline character.
+    #[inline]
+    fn writeln(&mut self) -> Result {
+        self.writer.write_char('\n')
+    }
+
+    /// Call to `self.writer.write_str`.
+    #[inline]
+    fn write_str(&mut self, s: &str) -> Result {
+        self.writer.write_str(s)
+    }
+}
+
+/// Returns the name of the label in RISC-V assembly. Labels starting with `$` (which are internal to
+/// the compiler) get the prefix `L_` instead.
+fn label_name(label: &Label) -> String {
+    match label.as_ref().strip_prefix('$') {
+        Some(rest) => format!("L_{rest}"),
+        None => label.to_string(),
+    }
+}
+
+/// Returns whether the function may be too large for a branch to reach every block, i.e. whether
+/// its translation with short branches may be larger than 4 KiB.
+fn may_exceed_branch_range(function: &Function) -> bool {
+    let mut output = String::new();
+    RiscVOutputter::new(&mut output)
+        .write_function_text(function)
+        .unwrap();
+    // `li` and `la` can expand to two instructions, the others are a single instruction.
+    let size: usize = output
+        .lines()
+        .filter_map(|line| line.strip_prefix('\t'))
+        .map(
+            |line| match line.starts_with("li\t") || line.starts_with("la\t") {
+                true => 8,
+                false => 4,
+            },
+        )
+        .sum();
+    size >= 4096
+}
+
+fn reg(reg: Reg) -> &'static str {
+    const NAMES: [&str; 32] = [
+        "zero", "", "a0", "a1", "a2", "a3", "a4", "a5", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+        "a6", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s10", "s11", "", "", "", "sp", "s0",
+        "ra",
+    ];
+    match reg {
+        Reg::R(n) if !NAMES[n as usize].is_empty() => NAMES[n as usize],
+        Reg::R(_) => panic!("ICE: {reg:#} has no RISC-V equivalent"),
+        Reg::Virtual(_) => panic!("ICE: can't translate virtual registers"),
+    }
+}
+
+fn freg(freg: FReg) -> &'static str {
+    const NAMES: [&str; 32] = [
+        "f0", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11", "f12", "f13",
+        "f14", "f15", "f16", "f17", "f18", "f19", "f20", "f21", "f22", "f23", "f24", "f25", "f26",
+        "f27", "f28", "f29", "f30", "f31",
+    ];
+    match freg {
+        FReg::F(n) => NAMES[n as usize],
+        FReg::VirtualSingle(_) | FReg::VirtualDouble(_) => {
+            panic!("ICE: can't translate virtual registers")
+        }
+    }
+}
+
+fn fits_imm12(value: i32) -> bool {
+    (-2048..=2047).contains(&value)
+}
+
+fn float_constant(value: f32) -> String {
+    let mut s = value.to_string();
+    if !s.contains('.') {
+        s += ".0";
+    }
+    s
+}
+
+fn double_constant(value: f64) -> String {
+    let mut s = value.to_string();
+    if !s.contains('.') {
+        s += ".0";
+    }
+    s
+}
+
+fn join<T: std::fmt::Display>(xs: impl Iterator<Item = T>) -> String {
+    xs.map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,188 @@
+use super::*;
+use crate::{FFmt, Function, GlobalData, Label};
+
+/// Outputs a root with a single function `main` containing `instructions`.
+fn output_main(instructions: Vec<Instruction>) -> String {
+    let mut root = Root::new();
+    let mut function = Function::new("main".into(), Vec::new());
+    let mut builder = function.start_entry_block(Vec::new());
+    for instruction in instructions {
+        builder.add_instruction(instruction);
+    }
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+    root.add_function(function);
+    // Merges the entry block of the CFG with the function's entry block.
+    crate::optimizer::simplifier::simplify_root(&mut root);
+    let mut output = String::new();
+    RiscVOutputter::new(&mut output).write_root(&root).unwrap();
+    output
+}
+
+#[test]
+pub fn outputs_global_data() {
+    let mut root = Root::new();
+    root.add_data(GlobalData::new(
+        "$.const.str.0".into(),
+        DataDirective::AsciiZ("Lorem ipsum".as_bytes().to_vec()),
+    ));
+    root.add_data(GlobalData::new(
+        "x".into(),
+        DataDirective::LabelWord(Label::from("$.const.str.0")),
+    ));
+    let mut output = String::new();
+    RiscVOutputter::new(&mut output).write_root(&root).unwrap();
+    assert_eq!(
+        "	.data
+
+L_.const.str.0:
+	.asciz	\"Lorem ipsum\"
+
+	.align	2
+x:
+	.word	L_.const.str.0
+",
+        output
+    );
+}
+
+#[test]
+pub fn maps_registers() {
+    let output = output_main(vec![
+        crate::instr::add_u(Reg::V0, Reg::A0, Reg::T7),
+        crate::instr::sub_u(Reg::S0, Reg::T9, Reg::FP),
+    ]);
+    assert_eq!(
+        "	.text
+
+main:
+	add	a0, a2, a6
+	sub	s1, s11, s0
+	ret
+",
+        output
+    );
+}
+
+#[test]
+pub fn splits_large_immediates() {
+    let output = output_main(vec![
+        crate::instr::add_u_imm(Reg::SP, Reg::SP, (-16i16) as u16),
+        crate::instr::add_u_imm(Reg::T0, Reg::T1, 3000),
+        crate::instr::or_imm(Reg::T0, Reg::ZERO, 0xffff),
+        crate::instr::load_word(Reg::T0, Reg::SP, (-4000i16) as u16),
+        crate::instr::load_upper(Reg::T0, 0x1234),
+    ]);
+    assert_eq!(
+        "	.text
+
+main:
+	addi	sp, sp, -16
+	li	tp, 3000
+	add	t0, t1, tp
+	li	tp, 65535
+	or	t0, zero, tp
+	li	tp, -4000
+	add	tp, tp, sp
+	lw	t0, 0(tp)
+	lui	t0, 74560
+	ret
+",
+        output
+    );
+}
+
+#[test]
+pub fn translates_hi_and_lo() {
+    let output = output_main(vec![
+        crate::instr::div_s(Reg::T0, Reg::T1),
+        crate::instr::move_from_lo(Reg::T2),
+        crate::instr::move_from_hi(Reg::T3),
+    ]);
+    assert_eq!(
+        "	.text
+
+main:
+	rem	s9, t0, t1
+	div	a7, t0, t1
+	mv	t2, a7
+	mv	t3, s9
+	ret
+",
+        output
+    );
+}
+
+#[test]
+pub fn translates_float_comparisons_and_conversions() {
+    let mut root = Root::new();
+    let mut function = Function::new("f".into(), Vec::new());
+    let true_block = function.create_block_label();
+    let false_block = function.create_block_label();
+
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(crate::instr::cmp(FCmp::Lt(FFmt::D), FReg::F(4), FReg::F(6)));
+    let entry = builder.terminate(crate::term::branch_if_f_cond(
+        true,
+        crate::BlockRef::new(true_block, Vec::new()),
+        crate::BlockRef::new(false_block, Vec::new()),
+    ));
+    function.add_block(entry);
+
+    let mut builder = function.start_block(true_block, Vec::new());
+    builder.add_instruction(crate::instr::convert_to_word(
+        FFmt::S,
+        FReg::F(8),
+        FReg::F(10),
+    ));
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+
+    let builder = function.start_block(false_block, Vec::new());
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+    root.add_function(function);
+
+    let mut output = String::new();
+    RiscVOutputter::new(&mut output).write_root(&root).unwrap();
+    let label = label_name(&root.function(&"f".into()).unwrap().block_label(true_block));
+    assert!(label.starts_with("L_f.bb"));
+    assert!(output.contains("\tflt.d\tgp, f4, f6\n\tbnez\tgp, L_f.bb"));
+    assert!(output.contains(&format!(
+        "{label}:\n\tfcvt.w.s\ttp, f10, rtz\n\tfmv.w.x\tf8, tp\n"
+    )));
+}
+
+#[test]
+pub fn jumps_to_far_branch_targets() {
+    let mut root = Root::new();
+    let mut function = Function::new("f".into(), Vec::new());
+    let far_block = function.create_block_label();
+    let near_block = function.create_block_label();
+
+    let builder = function.start_entry_block(Vec::new());
+    let entry = builder.terminate(crate::term::branch_if(
+        BCond::Eq,
+        Reg::T0,
+        Reg::T1,
+        crate::BlockRef::new(far_block, Vec::new()),
+        crate::BlockRef::new(near_block, Vec::new()),
+    ));
+    function.add_block(entry);
+
+    // More than 4 KiB of instructions between the branch and its target.
+    let mut builder = function.start_block(near_block, Vec::new());
+    for _ in 0..1100 {
+        builder.add_instruction(Instruction::Nop);
+    }
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+
+    let builder = function.start_block(far_block, Vec::new());
+    function.add_block(builder.terminate(crate::term::return_to_ra()));
+    root.add_function(function);
+
+    let mut output = String::new();
+    RiscVOutputter::new(&mut output).write_root(&root).unwrap();
+    let label = label_name(&root.function(&"f".into()).unwrap().block_label(far_block));
+    assert!(output.contains(&format!(
+        "\tbne\tt0, t1, L_.local.1\n\tj\t{label}\nL_.local.1:\n"
+    )));
+    assert!(!output.contains("\tbeq\t"));
+}
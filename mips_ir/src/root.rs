@@ -1,7 +1,8 @@
-use crate::{Function, GlobalData, Label};
+use crate::{CallingConvention, Function, GlobalData, Isa, Label};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Root {
     /// All labels that should be made available for linking.
     exported_labels: HashSet<Label>,
@@ -12,6 +13,10 @@ pub struct Root {
     /// All referenced labels that are not defined in this file.
     external_labels: HashSet<Label>,
     raw_text: Vec<String>,
+    /// The calling convention used by all functions in this root.
+    calling_convention: CallingConvention,
+    /// The instruction set this root is compiled for.
+    isa: Isa,
 }
 
 impl Root {
@@ -29,6 +34,11 @@ impl Root {
         self.exported_labels.contains(label)
     }
 
+    /// Returns an iterator over all labels created using [`create_external_label`].
+    pub fn external_labels(&self) -> impl Iterator<Item = &Label> {
+        self.external_labels.iter()
+    }
+
     /// Returns `true` if the label was created using [`create_external_label`].
     pub fn is_external(&self, label: &Label) -> bool {
         self.external_labels.contains(label)
@@ -144,6 +154,25 @@ impl Root {
         self.functions.remove(label)
     }
 
+    pub fn calling_convention(&self) -> CallingConvention {
+        self.calling_convention
+    }
+
+    /// Sets the calling convention of all functions. This must be done before compiling, since
+    /// it's used by the stack frame builder and the devirtualizer.
+    pub fn set_calling_convention(&mut self, calling_convention: CallingConvention) {
+        self.calling_convention = calling_convention;
+    }
+
+    pub fn isa(&self) -> Isa {
+        self.isa
+    }
+
+    /// Sets the instruction set, which decides the runtime routines added by the linker.
+    pub fn set_isa(&mut self, isa: Isa) {
+        self.isa = isa;
+    }
+
     pub fn raw_text(&self) -> &[String] {
         self.raw_text.as_slice()
     }
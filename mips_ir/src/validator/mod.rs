@@ -1,4 +1,15 @@
-use crate::{dfa::uda::UdAnalyzable, AnyReg, BasicBlock, FReg, Function, Root};
+//! Checks the invariants that the passes expect from a [`Root`] before it's compiled, e.g. to
+//! check the output of the code generator, or randomly generated input when fuzzing.
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    cfg::{BasicBlock, BlockId, Cfg},
+    dfa::uda::{DefUseError, DuChains},
+    scanner, AnyReg, FReg, Function, FunctionCall, Instruction, Label, PseudoInstruction, Root,
+    Terminator, VirtualInstruction,
+};
 use std::collections::HashSet;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,9 +35,12 @@ pub enum InvalidityReason {
     DuplicateArgument,
     /// If a virtual registers is defined more than once (SSA violation).
     MultipleDefs,
-    /// If a virtual register is used before it is defined, or not defined at all (SSA violation).
+    /// If a virtual register is used before it is defined, or not defined at all, or if its
+    /// definition doesn't dominate the use (SSA violation). Uses in unreachable blocks are only
+    /// checked for being defined.
     UndefUse,
-    /// If a block is referenced but not in the graph.
+    /// If a block is referenced but not in the graph, or if a block label was created but the
+    /// block was never added.
     MissingBlock,
     /// If a label is referenced but not in the root.
     MissingLabel,
@@ -41,20 +55,23 @@ pub enum InvalidityReason {
 pub fn validate_root(root: &Root) -> Result<(), InvalidityReason> {
     for function in root.functions() {
         validate_function(function)?;
-        if function
-            .referenced_labels()
-            .any(|label| !root.has_label(label))
-        {
+        if referenced_labels(function).any(|label| !root.has_label(label)) {
             return Err(InvalidityReason::MissingLabel);
         }
-        for call in function.function_calls() {
+        for call in scanner::function::function_calls(function) {
             let Some(callee) = root.function(&call.label) else {
-                if root.is_external(&call.label) { continue; }
+                // The params of external functions are unknown.
+                if root.is_external(&call.label) {
+                    continue;
+                }
                 return Err(InvalidityReason::MissingLabel);
             };
+            let Some(callee_entry_block) = callee.entry_block() else {
+                return Err(InvalidityReason::MissingEntryBlock);
+            };
             match check_arguments_match(
                 call.arguments.iter().map(|(r, _)| *r),
-                callee.entry_block().unwrap().arguments.iter().copied(),
+                callee_entry_block.arguments.iter().copied(),
             ) {
                 Ok(()) => {}
                 Err(ArgsMismatch::DifferentLengths) => {
@@ -73,57 +90,37 @@ pub fn validate_function(function: &Function) -> Result<(), InvalidityReason> {
     let Some(entry_block) = function.entry_block() else {
         return Err(InvalidityReason::MissingEntryBlock);
     };
-    if function.params_info().len() != entry_block.arguments.len() {
+    if function.params_info().count() != entry_block.arguments.len() {
         return Err(InvalidityReason::ParamsArgumentsMismatch);
     }
-    for block in function.blocks() {
-        validate_basic_block(function, block)?;
+    // The analyses below expect every block in the graph to be complete.
+    if function.cfg.blocks().any(|(_, block)| !block.is_complete()) {
+        return Err(InvalidityReason::MissingBlock);
     }
-    Ok(())
+    for (id, block) in function.cfg.blocks() {
+        validate_basic_block(&function.cfg, id, block)?;
+    }
+    validate_ssa(&function.cfg)
 }
 
-fn validate_basic_block(function: &Function, block: &BasicBlock) -> Result<(), InvalidityReason> {
-    let mut defs = HashSet::new();
-
+fn validate_basic_block(
+    cfg: &Cfg,
+    id: BlockId,
+    block: &BasicBlock,
+) -> Result<(), InvalidityReason> {
+    let mut args = HashSet::new();
     for &arg in &block.arguments {
-        if !defs.insert(arg) {
+        if !args.insert(arg) {
             return Err(InvalidityReason::DuplicateArgument);
         }
     }
 
-    let def_reg = |defs: &mut HashSet<_>, reg: AnyReg| match !reg.is_virtual() || defs.insert(reg) {
-        true => Ok(()),
-        false => Err(InvalidityReason::MultipleDefs),
-    };
-    let use_reg = |defs: &HashSet<_>, reg: AnyReg| match !reg.is_virtual() || defs.contains(&reg) {
-        true => Ok(()),
-        false => Err(InvalidityReason::UndefUse),
-    };
-
-    for instr in &block.instructions {
-        let usedefs = instr.ud_info();
-        for reg in usedefs.uses {
-            use_reg(&defs, reg)?;
-        }
-        for reg in usedefs.defs {
-            def_reg(&mut defs, reg)?;
-        }
-    }
-
-    let term_usedefs = block.terminator.ud_info();
-    for reg in term_usedefs.uses {
-        use_reg(&defs, reg)?;
-    }
-    for reg in term_usedefs.defs {
-        def_reg(&mut defs, reg)?;
-    }
-
     let mut checked_succ_ids = HashSet::new();
-    for bref in block.succidxs().map(|idx| block.succ_bref(idx)) {
-        if !checked_succ_ids.insert(bref.label.id()) {
+    for bref in block.successors() {
+        if !checked_succ_ids.insert(bref.id) {
             return Err(InvalidityReason::DuplicateSuccessor);
         }
-        let Some(succ) = function.get_block(bref.label.id()) else {
+        let Some(succ) = cfg.get(bref.id) else {
             return Err(InvalidityReason::MissingBlock);
         };
         match check_arguments_match(
@@ -134,6 +131,9 @@ fn validate_basic_block(function: &Function, block: &BasicBlock) -> Result<(), I
             Err(ArgsMismatch::DifferentLengths) => {
                 return Err(InvalidityReason::NrOfArgumentsMismatch)
             }
+            // The entry block of the graph passes `$zero` for every param until
+            // `Function::finish` loads the params.
+            Err(ArgsMismatch::TypeMismatch) if id == cfg.entry_block_id() => {}
             Err(ArgsMismatch::TypeMismatch) => return Err(InvalidityReason::ArgumentTypeMismatch),
         }
     }
@@ -141,6 +141,75 @@ fn validate_basic_block(function: &Function, block: &BasicBlock) -> Result<(), I
     Ok(())
 }
 
+/// Checks that every virtual register is defined exactly once, and that its definition dominates
+/// all its (reachable) uses.
+fn validate_ssa(cfg: &Cfg) -> Result<(), InvalidityReason> {
+    let du_chains = match DuChains::try_build_from(cfg) {
+        Ok(du_chains) => du_chains,
+        Err(DefUseError::MultipleDefs(_)) => return Err(InvalidityReason::MultipleDefs),
+        Err(DefUseError::UndefUses(_)) => return Err(InvalidityReason::UndefUse),
+    };
+    let reachable = reachable_blocks(cfg);
+    let dominator_tree = cfg.dominator_tree();
+
+    for reg in du_chains.defined_regs() {
+        let du_chain = &du_chains[reg];
+        let def_block = du_chain.def_block();
+        let def_location = du_chain.def_location().as_location();
+        for use_location in du_chain.uses().chain(du_chain.phi_uses()) {
+            if !reachable.contains(&use_location.block_id) {
+                continue;
+            }
+            let dominates = match use_location.block_id == def_block {
+                true => def_location < use_location.local,
+                false => dominator_tree.strictly_dominates(def_block, use_location.block_id),
+            };
+            if !dominates {
+                return Err(InvalidityReason::UndefUse);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn reachable_blocks(cfg: &Cfg) -> HashSet<BlockId> {
+    let mut reachable = HashSet::from([cfg.entry_block_id()]);
+    let mut stack = vec![cfg.entry_block_id()];
+    while let Some(id) = stack.pop() {
+        for succ in cfg.successor_ids(id) {
+            if reachable.insert(succ) {
+                stack.push(succ);
+            }
+        }
+    }
+    reachable
+}
+
+/// Returns every label the instructions and terminators of the function refer to, e.g. the
+/// functions it calls.
+fn referenced_labels(function: &Function) -> impl Iterator<Item = &Label> {
+    function.cfg.blocks().flat_map(|(_, block)| {
+        let instruction_labels =
+            block
+                .instructions
+                .iter()
+                .filter_map(|instruction| match instruction.as_unhidden() {
+                    Instruction::Call(label)
+                    | Instruction::Pseudo(PseudoInstruction::LoadAddress(_, label))
+                    | Instruction::Virtual(VirtualInstruction::FunctionCall(FunctionCall {
+                        label,
+                        ..
+                    })) => Some(label),
+                    _ => None,
+                });
+        let terminator_label = match block.terminator() {
+            Terminator::BranchIfZAndLink(_, _, label, _) => Some(label),
+            _ => None,
+        };
+        instruction_labels.chain(terminator_label)
+    })
+}
+
 enum ArgsMismatch {
     DifferentLengths,
     TypeMismatch,
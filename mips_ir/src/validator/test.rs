@@ -0,0 +1,202 @@
+use super::*;
+use crate::{instr, term, BCond, BlockRef, Reg};
+
+fn v(n: u32) -> Reg {
+    Reg::Virtual(n)
+}
+
+fn root_with(function: Function) -> Root {
+    let mut root = Root::new();
+    root.add_function(function);
+    root.export_label("main".into());
+    root
+}
+
+/// `main(a)`: loops while `a != 0`, passing a counter to the loop header.
+fn loop_function() -> Function {
+    let mut function = Function::new("main".into(), vec![v(1).stack_info()]);
+    let header = function.create_block_label();
+    let exit = function.create_block_label();
+
+    let mut builder = function.start_entry_block(vec![v(1).into()]);
+    builder.add_instruction(instr::or_imm(v(2), Reg::ZERO, 0));
+    function.add_block(builder.terminate(term::jump(BlockRef::new(header, vec![v(2).into()]))));
+
+    let mut builder = function.start_block(header, vec![v(3).into()]);
+    builder.add_instruction(instr::add_u_imm(v(4), v(3), 1));
+    function.add_block(builder.terminate(term::branch_if(
+        BCond::Ne,
+        v(1),
+        v(4),
+        BlockRef::new(header, vec![v(4).into()]),
+        BlockRef::new(exit, Vec::new()),
+    )));
+
+    let builder = function.start_block(exit, Vec::new());
+    // `$1` is defined in the entry block, which dominates this block.
+    function.add_block(builder.terminate(term::virt::return_(Some(v(1).into()))));
+    function
+}
+
+#[test]
+fn accepts_valid_functions() {
+    assert_eq!(validate_root(&root_with(loop_function())), Ok(()));
+}
+
+#[test]
+fn rejects_uses_that_arent_dominated_by_their_def() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let left = function.create_block_label();
+    let right = function.create_block_label();
+
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(instr::or_imm(v(1), Reg::ZERO, 0));
+    function.add_block(builder.terminate(term::branch_if(
+        BCond::Eq,
+        v(1),
+        Reg::ZERO,
+        BlockRef::new(left, Vec::new()),
+        BlockRef::new(right, Vec::new()),
+    )));
+
+    let mut builder = function.start_block(left, Vec::new());
+    builder.add_instruction(instr::or_imm(v(2), Reg::ZERO, 1));
+    function.add_block(builder.terminate(term::virt::return_(Some(v(2).into()))));
+
+    // `$2` is defined in `left`, which doesn't dominate `right`.
+    let builder = function.start_block(right, Vec::new());
+    function.add_block(builder.terminate(term::virt::return_(Some(v(2).into()))));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::UndefUse)
+    );
+}
+
+#[test]
+fn rejects_uses_before_the_def() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(instr::add_u_imm(v(1), v(2), 1));
+    builder.add_instruction(instr::or_imm(v(2), Reg::ZERO, 0));
+    function.add_block(builder.terminate(term::virt::return_(None)));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::UndefUse)
+    );
+}
+
+#[test]
+fn rejects_multiple_defs() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(instr::or_imm(v(1), Reg::ZERO, 0));
+    builder.add_instruction(instr::or_imm(v(1), Reg::ZERO, 1));
+    function.add_block(builder.terminate(term::virt::return_(None)));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::MultipleDefs)
+    );
+}
+
+#[test]
+fn rejects_mismatched_block_arguments() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let target = function.create_block_label();
+
+    let mut builder = function.start_entry_block(Vec::new());
+    builder.add_instruction(instr::or_imm(v(1), Reg::ZERO, 0));
+    function.add_block(builder.terminate(term::jump(BlockRef::new(target, Vec::new()))));
+    let builder = function.start_block(target, vec![v(2).into()]);
+    function.add_block(builder.terminate(term::virt::return_(None)));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::NrOfArgumentsMismatch)
+    );
+}
+
+#[test]
+fn rejects_missing_blocks() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let target = function.create_block_label();
+    let builder = function.start_entry_block(Vec::new());
+    function.add_block(builder.terminate(term::jump(BlockRef::new(target, Vec::new()))));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::MissingBlock)
+    );
+    assert_eq!(
+        validate_function(&Function::new("main".into(), Vec::new())),
+        Err(InvalidityReason::MissingEntryBlock)
+    );
+}
+
+#[test]
+fn rejects_duplicate_successors() {
+    let mut function = Function::new("main".into(), Vec::new());
+    let target = function.create_block_label();
+    let builder = function.start_entry_block(Vec::new());
+    function.add_block(builder.terminate(term::branch_if(
+        BCond::Eq,
+        Reg::ZERO,
+        Reg::ZERO,
+        BlockRef::new(target, Vec::new()),
+        BlockRef::new(target, Vec::new()),
+    )));
+    let builder = function.start_block(target, Vec::new());
+    function.add_block(builder.terminate(term::virt::return_(None)));
+
+    assert_eq!(
+        validate_function(&function),
+        Err(InvalidityReason::DuplicateSuccessor)
+    );
+}
+
+#[test]
+fn checks_calls_against_the_callee() {
+    let call = |label: &str, arguments: Vec<Reg>| {
+        let mut function = Function::new("main".into(), Vec::new());
+        let mut builder = function.start_entry_block(Vec::new());
+        builder.add_instruction(instr::or_imm(v(1), Reg::ZERO, 0));
+        builder.add_instruction(instr::virt::function_call(
+            label.into(),
+            None,
+            arguments
+                .into_iter()
+                .map(|reg| (reg.into(), reg.stack_info()))
+                .collect(),
+        ));
+        function.add_block(builder.terminate(term::virt::return_(None)));
+        function
+    };
+    let callee = || {
+        let mut function = Function::new("callee".into(), vec![v(1).stack_info()]);
+        let builder = function.start_entry_block(vec![v(1).into()]);
+        function.add_block(builder.terminate(term::virt::return_(None)));
+        function
+    };
+
+    let mut root = root_with(call("callee", vec![v(1)]));
+    root.add_function(callee());
+    assert_eq!(validate_root(&root), Ok(()));
+
+    let mut root = root_with(call("callee", Vec::new()));
+    root.add_function(callee());
+    assert_eq!(
+        validate_root(&root),
+        Err(InvalidityReason::NrOfParamsMismatch)
+    );
+
+    assert_eq!(
+        validate_root(&root_with(call("callee", vec![v(1)]))),
+        Err(InvalidityReason::MissingLabel)
+    );
+
+    let mut root = root_with(call("printf", vec![v(1)]));
+    root.create_external_label("printf");
+    assert_eq!(validate_root(&root), Ok(()));
+}
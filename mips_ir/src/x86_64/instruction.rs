@@ -0,0 +1,474 @@
+use crate::{
+    arch::{ArchInstruction, ArchTerminator},
+    dfa::uda::{Defs, Uses},
+    function::StackAddress,
+    AnyReg, BlockRef, FFmt, FReg, Label, Reg,
+};
+use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize};
+
+/// Size of an integer in memory, named after the suffixes of the AT&T mnemonics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Size {
+    Byte,
+    Word,
+    Long,
+    Quad,
+}
+
+impl Size {
+    /// Returns the size of `bytes` bytes. Panics if there's no such size.
+    pub fn from_bytes(bytes: u128) -> Self {
+        match bytes {
+            1 => Size::Byte,
+            2 => Size::Word,
+            4 => Size::Long,
+            8 => Size::Quad,
+            _ => panic!("ICE: no integer size of {bytes} bytes"),
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Size::Byte => "b",
+            Size::Word => "w",
+            Size::Long => "l",
+            Size::Quad => "q",
+        }
+    }
+}
+
+/// Operation on two 64-bit integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    DivS,
+    DivU,
+    RemS,
+    RemU,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRightArithmetic,
+    ShiftRightLogical,
+}
+
+/// Comparison of two 64-bit integers. The `S` and `U` variants compare signed and unsigned
+/// integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cond {
+    Eq,
+    Ne,
+    LtS,
+    LeS,
+    GtS,
+    GeS,
+    LtU,
+    LeU,
+    GtU,
+    GeU,
+}
+
+impl Cond {
+    /// The condition code used in `jcc` and `setcc`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Cond::Eq => "e",
+            Cond::Ne => "ne",
+            Cond::LtS => "l",
+            Cond::LeS => "le",
+            Cond::GtS => "g",
+            Cond::GeS => "ge",
+            Cond::LtU => "b",
+            Cond::LeU => "be",
+            Cond::GtU => "a",
+            Cond::GeU => "ae",
+        }
+    }
+
+    /// The condition that holds exactly when `self` doesn't.
+    pub fn inverse(self) -> Self {
+        match self {
+            Cond::Eq => Cond::Ne,
+            Cond::Ne => Cond::Eq,
+            Cond::LtS => Cond::GeS,
+            Cond::LeS => Cond::GtS,
+            Cond::GtS => Cond::LeS,
+            Cond::GeS => Cond::LtS,
+            Cond::LtU => Cond::GeU,
+            Cond::LeU => Cond::GtU,
+            Cond::GtU => Cond::LeU,
+            Cond::GeU => Cond::LtU,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FBinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Comparison of two floating point values. All comparisons with NaN are false, except for
+/// [`FCond::Ne`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FCond {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Call {
+    pub label: Label,
+    /// Passed according to the System V ABI, in order.
+    pub arguments: Vec<AnyReg>,
+    pub return_reg: Option<AnyReg>,
+    /// If `true`, `%al` is set to the number of arguments passed in sse registers.
+    pub variadic: bool,
+    /// If `true`, the function is called through the PLT, because it's defined in another object.
+    pub external: bool,
+}
+
+/// An x86-64 instruction on (virtual) registers. Instructions that only have a two-operand form on
+/// x86-64 are given three operands here; the outputter copies the left operand to the destination
+/// first.
+///
+/// Integers are kept in 64-bit registers, the instructions that operate on smaller integers
+/// sign- or zero-extend the result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Instruction {
+    Nop,
+    /// `dst = imm`
+    LoadImm(Reg, i64),
+    /// Loads the floating point value of the given format with the given bits.
+    FLoadImm(FFmt, FReg, u64),
+    LoadAddress(Reg, Label),
+    /// Loads the address of a stack slot, see [`crate::Function::create_stack_slot`].
+    LoadStackAddress(Reg, StackAddress),
+    /// `dst = *(base + offset)`, sign- or zero-extended to 64 bits.
+    Load {
+        size: Size,
+        signed: bool,
+        dst: Reg,
+        base: Reg,
+        offset: i32,
+    },
+    /// `*(base + offset) = src`, truncated to `size`.
+    Store(Size, Reg, Reg, i32),
+    /// `dst = *(base + offset)`
+    FLoad(FFmt, FReg, Reg, i32),
+    /// `*(base + offset) = src`
+    FStore(FFmt, FReg, Reg, i32),
+    LoadFromStack(AnyReg, StackAddress),
+    StoreToStack(AnyReg, StackAddress),
+    /// `dst = lhs op rhs`
+    Binary(BinaryOp, Reg, Reg, Reg),
+    /// `dst = -src`
+    Neg(Reg, Reg),
+    /// `dst = ~src`
+    Not(Reg, Reg),
+    /// Sign- or zero-extends the lowest `size` bytes of `src` to 64 bits.
+    Extend {
+        size: Size,
+        signed: bool,
+        dst: Reg,
+        src: Reg,
+    },
+    /// `dst = lhs cond rhs`, i.e. 1 or 0.
+    SetCond(Cond, Reg, Reg, Reg),
+    /// `dst = lhs op rhs`
+    FBinary(FBinaryOp, FFmt, FReg, FReg, FReg),
+    /// `dst = -src`
+    FNeg(FFmt, FReg, FReg),
+    /// `dst = lhs cond rhs`, i.e. 1 or 0.
+    FSetCond(FCond, FFmt, Reg, FReg, FReg),
+    /// Converts a signed or unsigned 64-bit integer to a floating point value.
+    IntToFloat {
+        fmt: FFmt,
+        unsigned: bool,
+        dst: FReg,
+        src: Reg,
+    },
+    /// Converts a floating point value to a signed or unsigned 64-bit integer, rounding towards
+    /// zero.
+    FloatToInt {
+        fmt: FFmt,
+        unsigned: bool,
+        dst: Reg,
+        src: FReg,
+    },
+    /// Converts `src` of the second format to `dst` of the first format.
+    FConvert(FFmt, FFmt, FReg, FReg),
+    Call(Call),
+    /// Copies a physical register to another one. Only used to destruct SSA, so it's ignored by
+    /// the use-def analysis.
+    Move(AnyReg, AnyReg),
+    /// Swaps two physical registers of the same kind. Only used to destruct SSA, so it's ignored
+    /// by the use-def analysis.
+    Swap(AnyReg, AnyReg),
+    Comment(String),
+}
+
+/// Terminates a block of x86-64 instructions. The outputter leaves out the jump to the default
+/// target if that target is the next block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Terminator {
+    Jump(BlockRef),
+    /// Jumps to the first target if `lhs cond rhs` holds, and to the second (default) target
+    /// otherwise.
+    BranchIf(Cond, Reg, Reg, BlockRef, BlockRef),
+    /// Returns from the function, with the value of the register if there is one.
+    Return(Option<AnyReg>),
+}
+
+impl ArchInstruction for Instruction {
+    fn defs(&self) -> Defs {
+        Defs(match *self {
+            Instruction::LoadImm(dst, _)
+            | Instruction::LoadAddress(dst, _)
+            | Instruction::LoadStackAddress(dst, _)
+            | Instruction::Load { dst, .. }
+            | Instruction::Binary(_, dst, _, _)
+            | Instruction::Neg(dst, _)
+            | Instruction::Not(dst, _)
+            | Instruction::Extend { dst, .. }
+            | Instruction::SetCond(_, dst, _, _)
+            | Instruction::FSetCond(_, _, dst, _, _)
+            | Instruction::FloatToInt { dst, .. } => Some(dst.into()),
+            Instruction::FLoadImm(_, dst, _)
+            | Instruction::FLoad(_, dst, _, _)
+            | Instruction::FBinary(_, _, dst, _, _)
+            | Instruction::FNeg(_, dst, _)
+            | Instruction::IntToFloat { dst, .. }
+            | Instruction::FConvert(_, _, dst, _) => Some(dst.into()),
+            Instruction::LoadFromStack(dst, _) => Some(dst),
+            Instruction::Call(Call { return_reg, .. }) => return_reg,
+            Instruction::Nop
+            | Instruction::Store(..)
+            | Instruction::FStore(..)
+            | Instruction::StoreToStack(..)
+            | Instruction::Move(..)
+            | Instruction::Swap(..)
+            | Instruction::Comment(_) => None,
+        })
+    }
+
+    fn uses(&self) -> Uses {
+        Uses(match *self {
+            Instruction::Nop
+            | Instruction::LoadImm(..)
+            | Instruction::FLoadImm(..)
+            | Instruction::LoadAddress(..)
+            | Instruction::LoadStackAddress(..)
+            | Instruction::LoadFromStack(..)
+            | Instruction::Move(..)
+            | Instruction::Swap(..)
+            | Instruction::Comment(_) => Vec::new().into_iter(),
+            Instruction::Load { base, .. } | Instruction::FLoad(_, _, base, _) => {
+                vec![base.into()].into_iter()
+            }
+            Instruction::Store(_, src, base, _) => vec![src.into(), base.into()].into_iter(),
+            Instruction::FStore(_, src, base, _) => vec![src.into(), base.into()].into_iter(),
+            Instruction::StoreToStack(src, _) => vec![src].into_iter(),
+            Instruction::Binary(_, _, lhs, rhs) | Instruction::SetCond(_, _, lhs, rhs) => {
+                vec![lhs.into(), rhs.into()].into_iter()
+            }
+            Instruction::Neg(_, src)
+            | Instruction::Not(_, src)
+            | Instruction::Extend { src, .. }
+            | Instruction::IntToFloat { src, .. } => vec![src.into()].into_iter(),
+            Instruction::FBinary(_, _, _, lhs, rhs) | Instruction::FSetCond(_, _, _, lhs, rhs) => {
+                vec![lhs.into(), rhs.into()].into_iter()
+            }
+            Instruction::FNeg(_, _, src)
+            | Instruction::FloatToInt { src, .. }
+            | Instruction::FConvert(_, _, _, src) => vec![src.into()].into_iter(),
+            Instruction::Call(Call { ref arguments, .. }) => arguments.clone().into_iter(),
+        })
+    }
+
+    fn map_defs(&mut self, mut f: impl FnMut(AnyReg) -> AnyReg) {
+        match self {
+            Instruction::LoadImm(dst, _)
+            | Instruction::LoadAddress(dst, _)
+            | Instruction::LoadStackAddress(dst, _)
+            | Instruction::Load { dst, .. }
+            | Instruction::Binary(_, dst, _, _)
+            | Instruction::Neg(dst, _)
+            | Instruction::Not(dst, _)
+            | Instruction::Extend { dst, .. }
+            | Instruction::SetCond(_, dst, _, _)
+            | Instruction::FSetCond(_, _, dst, _, _)
+            | Instruction::FloatToInt { dst, .. } => *dst = f((*dst).into()).try_into().unwrap(),
+            Instruction::FLoadImm(_, dst, _)
+            | Instruction::FLoad(_, dst, _, _)
+            | Instruction::FBinary(_, _, dst, _, _)
+            | Instruction::FNeg(_, dst, _)
+            | Instruction::IntToFloat { dst, .. }
+            | Instruction::FConvert(_, _, dst, _) => *dst = f((*dst).into()).try_into().unwrap(),
+            Instruction::LoadFromStack(dst, _) => *dst = f(*dst),
+            Instruction::Call(Call { return_reg, .. }) => {
+                if let Some(r) = return_reg.as_mut() {
+                    *r = f(*r)
+                }
+            }
+            Instruction::Nop
+            | Instruction::Store(..)
+            | Instruction::FStore(..)
+            | Instruction::StoreToStack(..)
+            | Instruction::Move(..)
+            | Instruction::Swap(..)
+            | Instruction::Comment(_) => (),
+        }
+    }
+
+    fn map_uses(&mut self, mut f: impl FnMut(AnyReg) -> AnyReg) {
+        match self {
+            Instruction::Nop
+            | Instruction::LoadImm(..)
+            | Instruction::FLoadImm(..)
+            | Instruction::LoadAddress(..)
+            | Instruction::LoadStackAddress(..)
+            | Instruction::LoadFromStack(..)
+            | Instruction::Move(..)
+            | Instruction::Swap(..)
+            | Instruction::Comment(_) => (),
+            Instruction::Load { base, .. } | Instruction::FLoad(_, _, base, _) => {
+                *base = f((*base).into()).try_into().unwrap()
+            }
+            Instruction::Store(_, src, base, _) => {
+                *src = f((*src).into()).try_into().unwrap();
+                *base = f((*base).into()).try_into().unwrap()
+            }
+            Instruction::FStore(_, src, base, _) => {
+                *src = f((*src).into()).try_into().unwrap();
+                *base = f((*base).into()).try_into().unwrap()
+            }
+            Instruction::StoreToStack(src, _) => *src = f(*src),
+            Instruction::Binary(_, _, lhs, rhs) | Instruction::SetCond(_, _, lhs, rhs) => {
+                *lhs = f((*lhs).into()).try_into().unwrap();
+                *rhs = f((*rhs).into()).try_into().unwrap()
+            }
+            Instruction::Neg(_, src)
+            | Instruction::Not(_, src)
+            | Instruction::Extend { src, .. }
+            | Instruction::IntToFloat { src, .. } => *src = f((*src).into()).try_into().unwrap(),
+            Instruction::FBinary(_, _, _, lhs, rhs) | Instruction::FSetCond(_, _, _, lhs, rhs) => {
+                *lhs = f((*lhs).into()).try_into().unwrap();
+                *rhs = f((*rhs).into()).try_into().unwrap()
+            }
+            Instruction::FNeg(_, _, src)
+            | Instruction::FloatToInt { src, .. }
+            | Instruction::FConvert(_, _, _, src) => *src = f((*src).into()).try_into().unwrap(),
+            Instruction::Call(Call { arguments, .. }) => {
+                for arg in arguments {
+                    *arg = f(*arg)
+                }
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Store(..)
+                | Instruction::FStore(..)
+                | Instruction::StoreToStack(..)
+                | Instruction::Call(_)
+                | Instruction::Move(..)
+                | Instruction::Swap(..)
+                | Instruction::Comment(_)
+        )
+    }
+
+    fn is_call(&self) -> bool {
+        matches!(self, Instruction::Call(_))
+    }
+
+    fn nop() -> Self {
+        Instruction::Nop
+    }
+
+    fn load_from_stack(reg: AnyReg, stack_address: StackAddress) -> Self {
+        Instruction::LoadFromStack(reg, stack_address)
+    }
+
+    fn store_to_stack(reg: AnyReg, stack_address: StackAddress) -> Self {
+        Instruction::StoreToStack(reg, stack_address)
+    }
+
+    fn move_(dst: AnyReg, src: AnyReg) -> Self {
+        Instruction::Move(dst, src)
+    }
+
+    fn swap(a: AnyReg, b: AnyReg) -> Vec<Self> {
+        vec![Instruction::Swap(a, b)]
+    }
+}
+
+impl ArchTerminator for Terminator {
+    fn jump(target: BlockRef) -> Self {
+        Terminator::Jump(target)
+    }
+
+    fn targets(&self) -> impl Iterator<Item = &BlockRef> {
+        let mut arr = ArrayVec::<&BlockRef, 2>::new();
+        match self {
+            Terminator::Jump(t) => arr.push(t),
+            Terminator::BranchIf(_, _, _, tt, ft) => {
+                arr.push(tt);
+                arr.push(ft);
+            }
+            Terminator::Return(_) => {}
+        }
+        arr.into_iter()
+    }
+
+    fn targets_mut(&mut self) -> impl Iterator<Item = &mut BlockRef> {
+        let mut arr = ArrayVec::<&mut BlockRef, 2>::new();
+        match self {
+            Terminator::Jump(t) => arr.push(t),
+            Terminator::BranchIf(_, _, _, tt, ft) => {
+                arr.push(tt);
+                arr.push(ft);
+            }
+            Terminator::Return(_) => {}
+        }
+        arr.into_iter()
+    }
+
+    fn default_target(&self) -> Option<&BlockRef> {
+        match self {
+            Terminator::Jump(t) | Terminator::BranchIf(_, _, _, _, t) => Some(t),
+            Terminator::Return(_) => None,
+        }
+    }
+
+    fn uses(&self) -> Uses {
+        Uses(match *self {
+            Terminator::Jump(_) | Terminator::Return(None) => Vec::new().into_iter(),
+            Terminator::BranchIf(_, lhs, rhs, _, _) => vec![lhs.into(), rhs.into()].into_iter(),
+            Terminator::Return(Some(reg)) => vec![reg].into_iter(),
+        })
+    }
+
+    fn map_uses(&mut self, mut f: impl FnMut(AnyReg) -> AnyReg) {
+        match self {
+            Terminator::Jump(_) | Terminator::Return(None) => (),
+            Terminator::BranchIf(_, lhs, rhs, _, _) => {
+                *lhs = f((*lhs).into()).try_into().unwrap();
+                *rhs = f((*rhs).into()).try_into().unwrap()
+            }
+            Terminator::Return(Some(reg)) => *reg = f(*reg),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! x86-64 instructions for the System V ABI.
+//!
+//! A [`Function<X86_64>`] is built like a MIPS [`Function`], with virtual registers and blocks
+//! that take arguments. [`compile`] allocates its registers with the same passes as MIPS, after
+//! which [`X86_64Outputter`] can write it as GNU assembly. There's no x86-64 [`crate::Root`], so
+//! global data has to be output separately.
+
+mod instruction;
+mod outputter;
+#[cfg(test)]
+mod test;
+
+pub use instruction::{BinaryOp, Call, Cond, FBinaryOp, FCond, Instruction, Size, Terminator};
+pub use outputter::X86_64Outputter;
+
+use crate::{
+    dfa, function::StackAddress, passes, AlignBoundary, AnyReg, Arch, FReg, Function, Reg,
+    StackInfo,
+};
+
+/// Marker for [`Function`]s with x86-64 instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct X86_64;
+
+// The general purpose registers are numbered by their encoding, the sse registers `%xmmN` are
+// `FReg::F(N)`.
+const RAX: Reg = Reg::R(0);
+const RCX: Reg = Reg::R(1);
+const RDX: Reg = Reg::R(2);
+const RBX: Reg = Reg::R(3);
+const RSI: Reg = Reg::R(6);
+const RDI: Reg = Reg::R(7);
+const R8: Reg = Reg::R(8);
+const R9: Reg = Reg::R(9);
+const R10: Reg = Reg::R(10);
+const R11: Reg = Reg::R(11);
+const R12: Reg = Reg::R(12);
+const R13: Reg = Reg::R(13);
+const R14: Reg = Reg::R(14);
+const R15: Reg = Reg::R(15);
+const XMM0: FReg = FReg::F(0);
+const XMM14: FReg = FReg::F(14);
+const XMM15: FReg = FReg::F(15);
+
+/// The registers integer arguments are passed in, in order.
+const INT_ARG_REGS: [Reg; 6] = [RDI, RSI, RDX, RCX, R8, R9];
+/// The number of sse registers floating point arguments are passed in (`%xmm0` to `%xmm7`).
+const N_SSE_ARG_REGS: u8 = 8;
+
+impl Arch for X86_64 {
+    type Instruction = Instruction;
+    type Terminator = Terminator;
+
+    const SAVED_CPU_REGS: &'static [Reg] = &[RBX, R12, R13, R14, R15];
+    // `%rax`, `%rcx`, `%rdx` and `%r11` are left out, so the outputter can use them for division,
+    // shifts and other instructions that need fixed or extra registers.
+    const TEMP_CPU_REGS: &'static [Reg] = &[RSI, RDI, R8, R9, R10];
+    // The System V ABI doesn't preserve any sse register across calls.
+    const SAVED_FPU_REGS: &'static [FReg] = &[];
+    // `%xmm14` and `%xmm15` are left out for the outputter.
+    const TEMP_FPU_REGS: &'static [FReg] = &[
+        FReg::F(0),
+        FReg::F(1),
+        FReg::F(2),
+        FReg::F(3),
+        FReg::F(4),
+        FReg::F(5),
+        FReg::F(6),
+        FReg::F(7),
+        FReg::F(8),
+        FReg::F(9),
+        FReg::F(10),
+        FReg::F(11),
+        FReg::F(12),
+        FReg::F(13),
+    ];
+
+    fn spill_info(_reg: AnyReg) -> StackInfo {
+        StackInfo {
+            size: 8,
+            alignment: AlignBoundary::DOUBLE,
+            signed: false,
+        }
+    }
+
+    fn discard_reg(reg: AnyReg) -> Option<AnyReg> {
+        match reg {
+            AnyReg::R(_) => Some(RAX.into()),
+            AnyReg::F(_) => Some(XMM15.into()),
+        }
+    }
+}
+
+impl Function<X86_64> {
+    /// Reserves space on the stack, e.g. for a variable whose address is taken. The address of
+    /// the space can be loaded with [`Instruction::LoadStackAddress`].
+    pub fn create_stack_slot(&mut self, stack_info: StackInfo) -> StackAddress {
+        self.create_stack_address(stack_info)
+    }
+}
+
+/// Runs all passes on `function`, which allocate its registers. Afterwards it can be written with
+/// [`X86_64Outputter`].
+pub fn compile(function: &mut Function<X86_64>) {
+    function.declare_params_in_entry_block();
+    dfa::dce::purge_function(function);
+    passes::register_allocation::run_function(function);
+}
+
+/// Where an argument is passed according to the System V ABI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgLocation {
+    Reg(AnyReg),
+    /// The index of the argument among those passed on the stack.
+    Stack(u32),
+}
+
+/// Returns where the arguments are passed, given for every argument whether it's a floating
+/// point value.
+fn arg_locations(is_float: impl IntoIterator<Item = bool>) -> Vec<ArgLocation> {
+    let mut n_int = 0;
+    let mut n_sse = 0;
+    let mut n_stack = 0;
+    is_float
+        .into_iter()
+        .map(|is_float| {
+            if is_float && n_sse < N_SSE_ARG_REGS {
+                n_sse += 1;
+                ArgLocation::Reg(FReg::F(n_sse - 1).into())
+            } else if !is_float && n_int < INT_ARG_REGS.len() {
+                n_int += 1;
+                ArgLocation::Reg(INT_ARG_REGS[n_int - 1].into())
+            } else {
+                n_stack += 1;
+                ArgLocation::Stack(n_stack - 1)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,797 @@
+use super::{
+    arg_locations, ArgLocation, BinaryOp, Call, FBinaryOp, FCond, Instruction, Size, Terminator,
+    R11, RAX, RCX, RDX, X86_64, XMM0, XMM14, XMM15,
+};
+use crate::{
+    arch::{Arch, ArchInstruction},
+    function::StackAddress,
+    AnyReg, BlockId, FFmt, FReg, Function, Reg,
+};
+use std::collections::HashMap;
+use std::fmt::Result;
+
+/// Can be used to format compiled (see [`super::compile`]) x86-64 functions as GNU assembly.
+///
+/// Every function gets a frame pointer. The saved registers the function uses are pushed right
+/// below it, followed by the stack slots (spilled registers, params passed in registers and the
+/// slots created with [`Function::create_stack_slot`]).
+pub struct X86_64Outputter<'w, W: std::fmt::Write> {
+    writer: &'w mut W,
+    show_comments: bool,
+    /// The label of the function that's being written, used as prefix of its local labels.
+    function_label: String,
+    frame: Frame,
+    /// Counter to create unique labels for the translation of conversions.
+    n_local_labels: usize,
+}
+
+impl<'w, W: std::fmt::Write> X86_64Outputter<'w, W> {
+    pub fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            show_comments: false,
+            function_label: String::new(),
+            frame: Frame::default(),
+            n_local_labels: 0,
+        }
+    }
+
+    /// If `true`, comments will be printed.
+    pub fn with_comments(self, show_comments: bool) -> Self {
+        Self {
+            show_comments,
+            ..self
+        }
+    }
+
+    /// Writes the function, including the `.text`, `.globl`, `.type` and `.size` directives.
+    pub fn write_function(&mut self, value: &Function<X86_64>) -> Result {
+        let label = value.label().to_string();
+        self.function_label = label.clone();
+        self.frame = Frame::new(value);
+        self.n_local_labels = 0;
+
+        self.line(".text")?;
+        self.line(&format!(".globl\t{label}"))?;
+        self.line(&format!(".type\t{label}, @function"))?;
+        writeln!(self.writer, "{label}:")?;
+        self.write_prologue(value)?;
+
+        let order: Vec<_> = value.traverse().map(|(id, _)| id).collect();
+        for (i, &id) in order.iter().enumerate() {
+            self.write_basic_block(value, id, order.get(i + 1).copied())?;
+        }
+
+        self.line(&format!(".size\t{label}, .-{label}"))
+    }
+
+    fn write_prologue(&mut self, function: &Function<X86_64>) -> Result {
+        self.line("pushq\t%rbp")?;
+        self.line("movq\t%rsp, %rbp")?;
+        for reg in self.frame.saved_regs.clone() {
+            self.line(&format!("pushq\t{}", reg64(reg)))?;
+        }
+        if self.frame.size > 0 {
+            self.line(&format!("subq\t${}, %rsp", self.frame.size))?;
+        }
+
+        // The params passed in registers are stored in their slot, from which they're loaded in
+        // the entry block.
+        let params_info: Vec<_> = function.params_info().collect();
+        let locations = arg_locations(params_info.iter().map(|(is_float, _)| *is_float));
+        for ((&stack_address, (_, stack_info)), location) in
+            function.params.iter().zip(params_info).zip(locations)
+        {
+            let address = self.frame.address(stack_address);
+            match location {
+                ArgLocation::Reg(AnyReg::R(reg)) => {
+                    let size = Size::from_bytes(stack_info.size);
+                    let reg = sized_reg(reg, size);
+                    self.line(&format!("mov{}\t{reg}, {address}", size.suffix()))?;
+                }
+                ArgLocation::Reg(AnyReg::F(freg)) => {
+                    let suffix = sse_suffix(stack_info_ffmt(stack_info.size));
+                    self.line(&format!("mov{suffix}\t{}, {address}", xmm(freg)))?;
+                }
+                ArgLocation::Stack(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn write_epilogue(&mut self) -> Result {
+        if self.frame.saved_regs.is_empty() {
+            self.line("leave")?;
+        } else {
+            let saved_size = 8 * self.frame.saved_regs.len();
+            self.line(&format!("leaq\t-{saved_size}(%rbp), %rsp"))?;
+            for reg in self.frame.saved_regs.clone().into_iter().rev() {
+                self.line(&format!("popq\t{}", reg64(reg)))?;
+            }
+            self.line("popq\t%rbp")?;
+        }
+        self.line("ret")
+    }
+
+    /// Writes the block, `next` is the block that will be written after it.
+    fn write_basic_block(
+        &mut self,
+        function: &Function<X86_64>,
+        block_id: BlockId,
+        next: Option<BlockId>,
+    ) -> Result {
+        let block = &function.cfg[block_id];
+        if function.cfg.n_predecessors(block_id) != 0 {
+            writeln!(self.writer, "{}:", self.block_label(function, block_id))?;
+        }
+        for instruction in &block.instructions {
+            self.write_instruction(function, instruction)?;
+        }
+        self.write_terminator(function, block.terminator(), next)
+    }
+
+    fn write_instruction(&mut self, function: &Function<X86_64>, value: &Instruction) -> Result {
+        match value {
+            // Only used as placeholder by the passes.
+            Instruction::Nop => Ok(()),
+            &Instruction::LoadImm(dst, imm) => self.write_load_imm(dst, imm),
+            &Instruction::FLoadImm(fmt, dst, bits) => {
+                let dst = xmm(dst);
+                match (fmt, bits) {
+                    (_, 0) => self.line(&format!("xorps\t{dst}, {dst}")),
+                    (FFmt::S, _) => {
+                        self.line(&format!("movl\t${bits:#x}, %eax"))?;
+                        self.line(&format!("movd\t%eax, {dst}"))
+                    }
+                    (FFmt::D, _) => {
+                        self.line(&format!("movabsq\t${bits:#x}, %rax"))?;
+                        self.line(&format!("movq\t%rax, {dst}"))
+                    }
+                }
+            }
+            Instruction::LoadAddress(dst, label) => {
+                self.line(&format!("leaq\t{label}(%rip), {}", reg64(*dst)))
+            }
+            &Instruction::LoadStackAddress(dst, stack_address) => {
+                let address = self.frame.address(stack_address);
+                self.line(&format!("leaq\t{address}, {}", reg64(dst)))
+            }
+            &Instruction::Load {
+                size,
+                signed,
+                dst,
+                base,
+                offset,
+            } => self.write_load(size, signed, dst, &address(base, offset)),
+            &Instruction::Store(size, src, base, offset) => {
+                let address = address(base, offset);
+                self.write_store(size, src, &address)
+            }
+            &Instruction::FLoad(fmt, dst, base, offset) => {
+                let suffix = sse_suffix(fmt);
+                let address = address(base, offset);
+                self.line(&format!("mov{suffix}\t{address}, {}", xmm(dst)))
+            }
+            &Instruction::FStore(fmt, src, base, offset) => {
+                let suffix = sse_suffix(fmt);
+                let address = address(base, offset);
+                self.line(&format!("mov{suffix}\t{}, {address}", xmm(src)))
+            }
+            &Instruction::LoadFromStack(dst, stack_address) => {
+                let address = self.frame.address(stack_address);
+                let stack_info = function.stack_info(stack_address);
+                match dst {
+                    AnyReg::R(dst) => self.write_load(
+                        Size::from_bytes(stack_info.size),
+                        stack_info.signed,
+                        dst,
+                        &address,
+                    ),
+                    AnyReg::F(dst) => {
+                        let suffix = sse_suffix(stack_info_ffmt(stack_info.size));
+                        self.line(&format!("mov{suffix}\t{address}, {}", xmm(dst)))
+                    }
+                }
+            }
+            &Instruction::StoreToStack(src, stack_address) => {
+                let address = self.frame.address(stack_address);
+                let stack_info = function.stack_info(stack_address);
+                match src {
+                    AnyReg::R(src) => {
+                        self.write_store(Size::from_bytes(stack_info.size), src, &address)
+                    }
+                    AnyReg::F(src) => {
+                        let suffix = sse_suffix(stack_info_ffmt(stack_info.size));
+                        self.line(&format!("mov{suffix}\t{}, {address}", xmm(src)))
+                    }
+                }
+            }
+            &Instruction::Binary(op, dst, lhs, rhs) => self.write_binary(op, dst, lhs, rhs),
+            &Instruction::Neg(dst, src) => {
+                self.write_move_reg(dst, src)?;
+                self.line(&format!("negq\t{}", reg64(dst)))
+            }
+            &Instruction::Not(dst, src) => {
+                self.write_move_reg(dst, src)?;
+                self.line(&format!("notq\t{}", reg64(dst)))
+            }
+            &Instruction::Extend {
+                size,
+                signed,
+                dst,
+                src,
+            } => match size {
+                Size::Quad => self.write_move_reg(dst, src),
+                size => self.write_load(size, signed, dst, sized_reg(src, size)),
+            },
+            &Instruction::SetCond(cond, dst, lhs, rhs) => {
+                self.line(&format!("cmpq\t{}, {}", reg64(rhs), reg64(lhs)))?;
+                self.line(&format!("set{}\t%al", cond.code()))?;
+                self.line(&format!("movzbl\t%al, {}", sized_reg(dst, Size::Long)))
+            }
+            &Instruction::FBinary(op, fmt, dst, lhs, rhs) => {
+                self.write_f_binary(op, fmt, dst, lhs, rhs)
+            }
+            &Instruction::FNeg(fmt, dst, src) => match fmt {
+                FFmt::S => {
+                    self.line(&format!("movd\t{}, %eax", xmm(src)))?;
+                    self.line("xorl\t$0x80000000, %eax")?;
+                    self.line(&format!("movd\t%eax, {}", xmm(dst)))
+                }
+                FFmt::D => {
+                    self.line(&format!("movq\t{}, %rax", xmm(src)))?;
+                    self.line("btcq\t$63, %rax")?;
+                    self.line(&format!("movq\t%rax, {}", xmm(dst)))
+                }
+            },
+            &Instruction::FSetCond(cond, fmt, dst, lhs, rhs) => {
+                self.write_f_set_cond(cond, fmt, dst, lhs, rhs)
+            }
+            &Instruction::IntToFloat {
+                fmt,
+                unsigned,
+                dst,
+                src,
+            } => self.write_int_to_float(fmt, unsigned, dst, src),
+            &Instruction::FloatToInt {
+                fmt,
+                unsigned,
+                dst,
+                src,
+            } => self.write_float_to_int(fmt, unsigned, dst, src),
+            &Instruction::FConvert(to, from, dst, src) => match (to, from) {
+                (FFmt::D, FFmt::S) => self.line(&format!("cvtss2sd\t{}, {}", xmm(src), xmm(dst))),
+                (FFmt::S, FFmt::D) => self.line(&format!("cvtsd2ss\t{}, {}", xmm(src), xmm(dst))),
+                _ => self.write_move_freg(dst, src),
+            },
+            Instruction::Call(call) => self.write_call(call),
+            &Instruction::Move(dst, src) => self.write_move(dst, src),
+            &Instruction::Swap(a, b) => match (a, b) {
+                (AnyReg::R(a), AnyReg::R(b)) => {
+                    self.line(&format!("xchgq\t{}, {}", reg64(a), reg64(b)))
+                }
+                (AnyReg::F(a), AnyReg::F(b)) => {
+                    self.write_move_freg(XMM15, a)?;
+                    self.write_move_freg(a, b)?;
+                    self.write_move_freg(b, XMM15)
+                }
+                _ => unreachable!("ICE: swap of a CPU and an FPU register"),
+            },
+            Instruction::Comment(comment) => {
+                if self.show_comments {
+                    for line in comment.lines() {
+                        writeln!(self.writer, "# {line}")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_terminator(
+        &mut self,
+        function: &Function<X86_64>,
+        value: &Terminator,
+        next: Option<BlockId>,
+    ) -> Result {
+        match value {
+            Terminator::Jump(target) => {
+                if next != Some(target.id) {
+                    let label = self.block_label(function, target.id);
+                    self.line(&format!("jmp\t{label}"))?;
+                }
+                Ok(())
+            }
+            &Terminator::BranchIf(cond, lhs, rhs, ref true_target, ref false_target) => {
+                self.line(&format!("cmpq\t{}, {}", reg64(rhs), reg64(lhs)))?;
+                let true_label = self.block_label(function, true_target.id);
+                let false_label = self.block_label(function, false_target.id);
+                if next == Some(true_target.id) {
+                    self.line(&format!("j{}\t{false_label}", cond.inverse().code()))
+                } else {
+                    self.line(&format!("j{}\t{true_label}", cond.code()))?;
+                    if next != Some(false_target.id) {
+                        self.line(&format!("jmp\t{false_label}"))?;
+                    }
+                    Ok(())
+                }
+            }
+            &Terminator::Return(value) => {
+                match value {
+                    Some(AnyReg::R(reg)) => self.write_move_reg(RAX, reg)?,
+                    Some(AnyReg::F(freg)) => self.write_move_freg(XMM0, freg)?,
+                    None => {}
+                }
+                self.write_epilogue()
+            }
+        }
+    }
+
+    fn write_load_imm(&mut self, dst: Reg, imm: i64) -> Result {
+        if i32::try_from(imm).is_ok() {
+            self.line(&format!("movq\t${imm}, {}", reg64(dst)))
+        } else {
+            self.line(&format!("movabsq\t${imm}, {}", reg64(dst)))
+        }
+    }
+
+    /// Loads an integer of `size` from `src` (an address or a register) and sign- or zero-extends
+    /// it to 64 bits.
+    fn write_load(&mut self, size: Size, signed: bool, dst: Reg, src: &str) -> Result {
+        let (op, dst) = match (size, signed) {
+            (Size::Byte, true) => ("movsbq", reg64(dst)),
+            (Size::Byte, false) => ("movzbl", sized_reg(dst, Size::Long)),
+            (Size::Word, true) => ("movswq", reg64(dst)),
+            (Size::Word, false) => ("movzwl", sized_reg(dst, Size::Long)),
+            (Size::Long, true) => ("movslq", reg64(dst)),
+            // Writing the lower 32 bits of a register clears the upper 32 bits.
+            (Size::Long, false) => ("movl", sized_reg(dst, Size::Long)),
+            (Size::Quad, _) => ("movq", reg64(dst)),
+        };
+        self.line(&format!("{op}\t{src}, {dst}"))
+    }
+
+    fn write_store(&mut self, size: Size, src: Reg, address: &str) -> Result {
+        let src = sized_reg(src, size);
+        self.line(&format!("mov{}\t{src}, {address}", size.suffix()))
+    }
+
+    fn write_binary(&mut self, op: BinaryOp, dst: Reg, lhs: Reg, rhs: Reg) -> Result {
+        let (op, commutative) = match op {
+            BinaryOp::DivS | BinaryOp::DivU | BinaryOp::RemS | BinaryOp::RemU => {
+                self.write_move_reg(RAX, lhs)?;
+                if matches!(op, BinaryOp::DivS | BinaryOp::RemS) {
+                    self.line("cqto")?;
+                    self.line(&format!("idivq\t{}", reg64(rhs)))?;
+                } else {
+                    self.line("xorl\t%edx, %edx")?;
+                    self.line(&format!("divq\t{}", reg64(rhs)))?;
+                }
+                let result = match op {
+                    BinaryOp::DivS | BinaryOp::DivU => RAX,
+                    _ => RDX,
+                };
+                return self.write_move_reg(dst, result);
+            }
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRightArithmetic | BinaryOp::ShiftRightLogical => {
+                let op = match op {
+                    BinaryOp::ShiftLeft => "shlq",
+                    BinaryOp::ShiftRightArithmetic => "sarq",
+                    _ => "shrq",
+                };
+                // The shift amount must be in `%cl`.
+                self.write_move_reg(RCX, rhs)?;
+                self.write_move_reg(dst, lhs)?;
+                return self.line(&format!("{op}\t%cl, {}", reg64(dst)));
+            }
+            BinaryOp::Add => ("addq", true),
+            BinaryOp::Sub => ("subq", false),
+            BinaryOp::Mul => ("imulq", true),
+            BinaryOp::And => ("andq", true),
+            BinaryOp::Or => ("orq", true),
+            BinaryOp::Xor => ("xorq", true),
+        };
+        if dst == rhs && dst != lhs {
+            if commutative {
+                return self.line(&format!("{op}\t{}, {}", reg64(lhs), reg64(dst)));
+            }
+            self.write_move_reg(R11, rhs)?;
+            self.write_move_reg(dst, lhs)?;
+            self.line(&format!("{op}\t{}, {}", reg64(R11), reg64(dst)))
+        } else {
+            self.write_move_reg(dst, lhs)?;
+            self.line(&format!("{op}\t{}, {}", reg64(rhs), reg64(dst)))
+        }
+    }
+
+    fn write_f_binary(
+        &mut self,
+        op: FBinaryOp,
+        fmt: FFmt,
+        dst: FReg,
+        lhs: FReg,
+        rhs: FReg,
+    ) -> Result {
+        let (op, commutative) = match op {
+            FBinaryOp::Add => ("add", true),
+            FBinaryOp::Sub => ("sub", false),
+            FBinaryOp::Mul => ("mul", true),
+            FBinaryOp::Div => ("div", false),
+        };
+        let suffix = sse_suffix(fmt);
+        if dst == rhs && dst != lhs {
+            if commutative {
+                return self.line(&format!("{op}{suffix}\t{}, {}", xmm(lhs), xmm(dst)));
+            }
+            self.write_move_freg(XMM15, rhs)?;
+            self.write_move_freg(dst, lhs)?;
+            self.line(&format!("{op}{suffix}\t{}, {}", xmm(XMM15), xmm(dst)))
+        } else {
+            self.write_move_freg(dst, lhs)?;
+            self.line(&format!("{op}{suffix}\t{}, {}", xmm(rhs), xmm(dst)))
+        }
+    }
+
+    fn write_f_set_cond(
+        &mut self,
+        cond: FCond,
+        fmt: FFmt,
+        dst: Reg,
+        lhs: FReg,
+        rhs: FReg,
+    ) -> Result {
+        let suffix = sse_suffix(fmt);
+        let (lhs, rhs) = (xmm(lhs), xmm(rhs));
+        // Comparisons with NaN are unordered, which sets the parity flag. The operands of `<` and
+        // `<=` are swapped so that `a` and `ae` can be used, which are false for unordered
+        // operands.
+        match cond {
+            FCond::Eq => {
+                self.line(&format!("ucomi{suffix}\t{rhs}, {lhs}"))?;
+                self.line("sete\t%al")?;
+                self.line("setnp\t%cl")?;
+                self.line("andb\t%cl, %al")?;
+            }
+            FCond::Ne => {
+                self.line(&format!("ucomi{suffix}\t{rhs}, {lhs}"))?;
+                self.line("setne\t%al")?;
+                self.line("setp\t%cl")?;
+                self.line("orb\t%cl, %al")?;
+            }
+            FCond::Gt => {
+                self.line(&format!("ucomi{suffix}\t{rhs}, {lhs}"))?;
+                self.line("seta\t%al")?;
+            }
+            FCond::Ge => {
+                self.line(&format!("ucomi{suffix}\t{rhs}, {lhs}"))?;
+                self.line("setae\t%al")?;
+            }
+            FCond::Lt => {
+                self.line(&format!("ucomi{suffix}\t{lhs}, {rhs}"))?;
+                self.line("seta\t%al")?;
+            }
+            FCond::Le => {
+                self.line(&format!("ucomi{suffix}\t{lhs}, {rhs}"))?;
+                self.line("setae\t%al")?;
+            }
+        }
+        self.line(&format!("movzbl\t%al, {}", sized_reg(dst, Size::Long)))
+    }
+
+    fn write_int_to_float(&mut self, fmt: FFmt, unsigned: bool, dst: FReg, src: Reg) -> Result {
+        let suffix = sse_suffix(fmt);
+        let (dst, src32, src) = (xmm(dst), sized_reg(src, Size::Long), reg64(src));
+        if !unsigned {
+            return self.line(&format!("cvtsi2{suffix}q\t{src}, {dst}"));
+        }
+        let large_label = self.new_local_label();
+        let end_label = self.new_local_label();
+        self.line(&format!("testq\t{src}, {src}"))?;
+        self.line(&format!("js\t{large_label}"))?;
+        self.line(&format!("cvtsi2{suffix}q\t{src}, {dst}"))?;
+        self.line(&format!("jmp\t{end_label}"))?;
+        // Halve the value (keeping the lowest bit for correct rounding), convert it and double it
+        // again.
+        writeln!(self.writer, "{large_label}:")?;
+        self.line(&format!("movq\t{src}, %rax"))?;
+        self.line("shrq\t%rax")?;
+        self.line(&format!("movl\t{src32}, %ecx"))?;
+        self.line("andl\t$1, %ecx")?;
+        self.line("orq\t%rcx, %rax")?;
+        self.line(&format!("cvtsi2{suffix}q\t%rax, {dst}"))?;
+        self.line(&format!("add{suffix}\t{dst}, {dst}"))?;
+        writeln!(self.writer, "{end_label}:")
+    }
+
+    fn write_float_to_int(&mut self, fmt: FFmt, unsigned: bool, dst: Reg, src: FReg) -> Result {
+        let suffix = sse_suffix(fmt);
+        if !unsigned {
+            return self.line(&format!("cvtt{suffix}2siq\t{}, {}", xmm(src), reg64(dst)));
+        }
+        let large_label = self.new_local_label();
+        let end_label = self.new_local_label();
+        // Values of 2^63 and above don't fit in a signed 64-bit integer, so 2^63 is subtracted
+        // first and the top bit is set afterwards.
+        match fmt {
+            FFmt::S => {
+                self.line(&format!("movl\t${:#x}, %eax", 2f32.powi(63).to_bits()))?;
+                self.line(&format!("movd\t%eax, {}", xmm(XMM15)))?;
+            }
+            FFmt::D => {
+                self.line(&format!("movabsq\t${:#x}, %rax", 2f64.powi(63).to_bits()))?;
+                self.line(&format!("movq\t%rax, {}", xmm(XMM15)))?;
+            }
+        }
+        self.line(&format!("ucomi{suffix}\t{}, {}", xmm(XMM15), xmm(src)))?;
+        self.line(&format!("jae\t{large_label}"))?;
+        self.line(&format!("cvtt{suffix}2siq\t{}, {}", xmm(src), reg64(dst)))?;
+        self.line(&format!("jmp\t{end_label}"))?;
+        writeln!(self.writer, "{large_label}:")?;
+        self.write_move_freg(XMM14, src)?;
+        self.line(&format!("sub{suffix}\t{}, {}", xmm(XMM15), xmm(XMM14)))?;
+        self.line(&format!("cvtt{suffix}2siq\t{}, {}", xmm(XMM14), reg64(dst)))?;
+        self.line(&format!("btcq\t$63, {}", reg64(dst)))?;
+        writeln!(self.writer, "{end_label}:")
+    }
+
+    fn write_call(&mut self, call: &Call) -> Result {
+        let locations = arg_locations(call.arguments.iter().map(|arg| matches!(arg, AnyReg::F(_))));
+        let stack_args: Vec<_> = call
+            .arguments
+            .iter()
+            .zip(&locations)
+            .filter(|(_, location)| matches!(location, ArgLocation::Stack(_)))
+            .map(|(&arg, _)| arg)
+            .collect();
+
+        // The stack must be 16-byte aligned at the call, which it is without the stack args.
+        let padding = 8 * (stack_args.len() % 2);
+        if padding > 0 {
+            self.line(&format!("subq\t${padding}, %rsp"))?;
+        }
+        // The first stack arg ends up at the lowest address.
+        for &arg in stack_args.iter().rev() {
+            match arg {
+                AnyReg::R(reg) => self.line(&format!("pushq\t{}", reg64(reg)))?,
+                AnyReg::F(freg) => {
+                    self.line("subq\t$8, %rsp")?;
+                    self.line(&format!("movsd\t{}, (%rsp)", xmm(freg)))?;
+                }
+            }
+        }
+
+        let moves = call
+            .arguments
+            .iter()
+            .zip(&locations)
+            .filter_map(|(&arg, location)| match location {
+                ArgLocation::Reg(reg) => Some((*reg, arg)),
+                ArgLocation::Stack(_) => None,
+            })
+            .collect();
+        self.write_parallel_move(moves)?;
+
+        if call.variadic {
+            let n_sse = locations
+                .iter()
+                .filter(|location| matches!(location, ArgLocation::Reg(AnyReg::F(_))))
+                .count();
+            self.line(&format!("movl\t${n_sse}, %eax"))?;
+        }
+        if call.external {
+            self.line(&format!("call\t{}@PLT", call.label))?;
+        } else {
+            self.line(&format!("call\t{}", call.label))?;
+        }
+
+        let cleanup = 8 * stack_args.len() + padding;
+        if cleanup > 0 {
+            self.line(&format!("addq\t${cleanup}, %rsp"))?;
+        }
+        match call.return_reg {
+            Some(AnyReg::R(reg)) => self.write_move_reg(reg, RAX),
+            Some(AnyReg::F(freg)) => self.write_move_freg(freg, XMM0),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the `(dst, src)` moves as if they happen at the same time, i.e. every source is read
+    /// before it's overwritten.
+    fn write_parallel_move(&mut self, mut moves: Vec<(AnyReg, AnyReg)>) -> Result {
+        moves.retain(|(dst, src)| dst != src);
+        while !moves.is_empty() {
+            let free = moves
+                .iter()
+                .position(|(dst, _)| moves.iter().all(|(_, src)| src != dst));
+            match free {
+                Some(i) => {
+                    let (dst, src) = moves.remove(i);
+                    self.write_move(dst, src)?;
+                }
+                None => {
+                    // Only cycles are left, one is broken by moving the value of a destination
+                    // out of the way.
+                    let (dst, _) = moves[0];
+                    let scratch = match dst {
+                        AnyReg::R(_) => R11.into(),
+                        AnyReg::F(_) => XMM15.into(),
+                    };
+                    self.write_move(scratch, dst)?;
+                    for (_, src) in &mut moves {
+                        if *src == dst {
+                            *src = scratch;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_move(&mut self, dst: AnyReg, src: AnyReg) -> Result {
+        match (dst, src) {
+            (AnyReg::R(dst), AnyReg::R(src)) => self.write_move_reg(dst, src),
+            (AnyReg::F(dst), AnyReg::F(src)) => self.write_move_freg(dst, src),
+            _ => unreachable!("ICE: move between a CPU and an FPU register"),
+        }
+    }
+
+    fn write_move_reg(&mut self, dst: Reg, src: Reg) -> Result {
+        if dst == src {
+            return Ok(());
+        }
+        self.line(&format!("movq\t{}, {}", reg64(src), reg64(dst)))
+    }
+
+    fn write_move_freg(&mut self, dst: FReg, src: FReg) -> Result {
+        if dst == src {
+            return Ok(());
+        }
+        self.line(&format!("movaps\t{}, {}", xmm(src), xmm(dst)))
+    }
+
+    fn block_label(&self, function: &Function<X86_64>, block_id: BlockId) -> String {
+        let n = block_id.as_id_in_cfg_of_len(function.cfg.len());
+        format!(".L{}.bb{n}", self.function_label)
+    }
+
+    fn new_local_label(&mut self) -> String {
+        self.n_local_labels += 1;
+        format!(".L{}.{}", self.function_label, self.n_local_labels)
+    }
+
+    /// Writes an indented line.
+    fn line(&mut self, s: &str) -> Result {
+        writeln!(self.writer, "\t{s}")
+    }
+}
+
+/// The stack frame of a function.
+#[derive(Debug, Default)]
+struct Frame {
+    /// The saved registers used by the function, in the order they're pushed.
+    saved_regs: Vec<Reg>,
+    /// The offset from `%rbp` of every stack address.
+    offsets: HashMap<StackAddress, i64>,
+    /// The number of bytes reserved below the pushed registers.
+    size: u128,
+}
+
+impl Frame {
+    fn new(function: &Function<X86_64>) -> Self {
+        let mut written = Vec::new();
+        for (_, block) in function.cfg.blocks() {
+            for instruction in &block.instructions {
+                match *instruction {
+                    Instruction::Move(dst, _) => written.push(dst),
+                    Instruction::Swap(a, b) => written.extend([a, b]),
+                    ref instruction => written.extend(instruction.defs()),
+                }
+            }
+        }
+        let saved_regs: Vec<_> = X86_64::SAVED_CPU_REGS
+            .iter()
+            .copied()
+            .filter(|&reg| written.contains(&reg.into()))
+            .collect();
+
+        let mut offsets = HashMap::new();
+        let params_info = function.params_info().map(|(is_float, _)| is_float);
+        let mut slots = Vec::new();
+        for (&stack_address, location) in function.params.iter().zip(arg_locations(params_info)) {
+            match location {
+                // Above the saved `%rbp` and the return address.
+                ArgLocation::Stack(i) => {
+                    offsets.insert(stack_address, 16 + 8 * i as i64);
+                }
+                ArgLocation::Reg(_) => slots.push(stack_address),
+            }
+        }
+        slots.extend(function.non_param_stack_addresses().copied());
+
+        // `%rbp` is 16-byte aligned, so aligning the offsets aligns the slots.
+        let saved_size = 8 * saved_regs.len() as u128;
+        let mut end = saved_size;
+        for stack_address in slots {
+            let stack_info = function.stack_info(stack_address);
+            end = (end + stack_info.size).next_multiple_of(stack_info.alignment.bytes());
+            offsets.insert(stack_address, -(end as i64));
+        }
+
+        Self {
+            saved_regs,
+            offsets,
+            // Calls need `%rsp` to be 16-byte aligned.
+            size: end.next_multiple_of(16) - saved_size,
+        }
+    }
+
+    fn address(&self, stack_address: StackAddress) -> String {
+        format!("{}(%rbp)", self.offsets[&stack_address])
+    }
+}
+
+fn address(base: Reg, offset: i32) -> String {
+    match offset {
+        0 => format!("({})", reg64(base)),
+        offset => format!("{offset}({})", reg64(base)),
+    }
+}
+
+fn sse_suffix(fmt: FFmt) -> &'static str {
+    match fmt {
+        FFmt::S => "ss",
+        FFmt::D => "sd",
+    }
+}
+
+/// The format of a floating point value that takes `size` bytes on the stack.
+fn stack_info_ffmt(size: u128) -> FFmt {
+    match size {
+        4 => FFmt::S,
+        _ => FFmt::D,
+    }
+}
+
+fn reg64(reg: Reg) -> &'static str {
+    sized_reg(reg, Size::Quad)
+}
+
+/// Returns the name of the part of `reg` that holds an integer of `size`.
+fn sized_reg(reg: Reg, size: Size) -> &'static str {
+    const NAMES: [[&str; 4]; 16] = [
+        ["%al", "%ax", "%eax", "%rax"],
+        ["%cl", "%cx", "%ecx", "%rcx"],
+        ["%dl", "%dx", "%edx", "%rdx"],
+        ["%bl", "%bx", "%ebx", "%rbx"],
+        ["%spl", "%sp", "%esp", "%rsp"],
+        ["%bpl", "%bp", "%ebp", "%rbp"],
+        ["%sil", "%si", "%esi", "%rsi"],
+        ["%dil", "%di", "%edi", "%rdi"],
+        ["%r8b", "%r8w", "%r8d", "%r8"],
+        ["%r9b", "%r9w", "%r9d", "%r9"],
+        ["%r10b", "%r10w", "%r10d", "%r10"],
+        ["%r11b", "%r11w", "%r11d", "%r11"],
+        ["%r12b", "%r12w", "%r12d", "%r12"],
+        ["%r13b", "%r13w", "%r13d", "%r13"],
+        ["%r14b", "%r14w", "%r14d", "%r14"],
+        ["%r15b", "%r15w", "%r15d", "%r15"],
+    ];
+    match reg {
+        Reg::R(n) => NAMES[n as usize][size as usize],
+        Reg::Virtual(_) => panic!("ICE: can't output virtual registers"),
+    }
+}
+
+fn xmm(freg: FReg) -> &'static str {
+    const NAMES: [&str; 16] = [
+        "%xmm0", "%xmm1", "%xmm2", "%xmm3", "%xmm4", "%xmm5", "%xmm6", "%xmm7", "%xmm8", "%xmm9",
+        "%xmm10", "%xmm11", "%xmm12", "%xmm13", "%xmm14", "%xmm15",
+    ];
+    match freg {
+        FReg::F(n) => NAMES[n as usize],
+        FReg::VirtualSingle(_) | FReg::VirtualDouble(_) => {
+            panic!("ICE: can't output virtual registers")
+        }
+    }
+}
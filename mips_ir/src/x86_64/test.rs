@@ -0,0 +1,164 @@
+use super::*;
+use crate::{FFmt, Label};
+
+fn quad() -> StackInfo {
+    StackInfo {
+        size: 8,
+        alignment: AlignBoundary::DOUBLE,
+        signed: true,
+    }
+}
+
+fn call(label: &str, arguments: Vec<AnyReg>, return_reg: Option<AnyReg>) -> Instruction {
+    Instruction::Call(Call {
+        label: Label::from(label),
+        arguments,
+        return_reg,
+        variadic: false,
+        external: false,
+    })
+}
+
+/// Compiles a function with a single block containing `instructions`, whose arguments are the
+/// params.
+fn output_function(
+    params_info: Vec<StackInfo>,
+    params: Vec<AnyReg>,
+    instructions: Vec<Instruction>,
+    terminator: Terminator,
+) -> String {
+    let mut function = Function::for_arch("f".into(), params_info);
+    let mut builder = function.start_entry_block(params);
+    for instruction in instructions {
+        builder.add_instruction(instruction);
+    }
+    function.add_block(builder.terminate(terminator));
+    compile(&mut function);
+    let mut output = String::new();
+    X86_64Outputter::new(&mut output)
+        .write_function(&function)
+        .unwrap();
+    output
+}
+
+#[test]
+pub fn outputs_function() {
+    let (a, b, c) = (Reg::Virtual(0), Reg::Virtual(1), Reg::Virtual(2));
+    let output = output_function(
+        vec![quad(), quad()],
+        vec![a.into(), b.into()],
+        vec![Instruction::Binary(BinaryOp::Sub, c, a, b)],
+        Terminator::Return(Some(c.into())),
+    );
+    assert_eq!(
+        "	.text
+	.globl	f
+	.type	f, @function
+f:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$16, %rsp
+	movq	%rdi, -8(%rbp)
+	movq	%rsi, -16(%rbp)
+	movq	-8(%rbp), %rsi
+	movq	-16(%rbp), %rdi
+.Lf.bb1:
+	subq	%rdi, %rsi
+	movq	%rsi, %rax
+	leave
+	ret
+	.size	f, .-f
+",
+        output
+    );
+}
+
+#[test]
+pub fn breaks_cycles_of_arguments() {
+    let output = output_function(
+        Vec::new(),
+        Vec::new(),
+        vec![call("g", vec![RSI.into(), RDI.into(), RDX.into()], None)],
+        Terminator::Return(None),
+    );
+    assert_eq!(
+        "	.text
+	.globl	f
+	.type	f, @function
+f:
+	pushq	%rbp
+	movq	%rsp, %rbp
+.Lf.bb1:
+.Lf.bb3:
+.Lf.bb5:
+	movq	%rdi, %r11
+	movq	%rsi, %rdi
+	movq	%r11, %rsi
+	call	g
+.Lf.bb4:
+.Lf.bb2:
+	leave
+	ret
+	.size	f, .-f
+",
+        output
+    );
+}
+
+#[test]
+pub fn spills_under_register_pressure() {
+    let n = 20;
+    let values: Vec<_> = (0..n).map(Reg::Virtual).collect();
+    let mut instructions: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &reg)| Instruction::LoadImm(reg, i as i64))
+        .collect();
+    let mut sum = Reg::Virtual(n);
+    instructions.push(Instruction::LoadImm(sum, 0));
+    for (i, &value) in values.iter().enumerate() {
+        let new_sum = Reg::Virtual(n + 1 + i as u32);
+        instructions.push(Instruction::Binary(BinaryOp::Add, new_sum, sum, value));
+        sum = new_sum;
+    }
+    let output = output_function(
+        Vec::new(),
+        Vec::new(),
+        instructions,
+        Terminator::Return(Some(sum.into())),
+    );
+    // All registers are used, the values that don't fit are spilled.
+    for reg in ["%rbx", "%r12", "%r13", "%r14", "%r15"] {
+        assert!(output.contains(&format!("pushq\t{reg}")));
+    }
+    assert!(output
+        .lines()
+        .any(|line| line.starts_with("\tmovq\t%") && line.ends_with("(%rbp)")));
+}
+
+#[test]
+pub fn spills_floats_live_across_calls() {
+    let (x, y, z) = (
+        FReg::VirtualDouble(0),
+        FReg::VirtualDouble(1),
+        FReg::VirtualDouble(2),
+    );
+    let output = output_function(
+        vec![quad()],
+        vec![x.into()],
+        vec![
+            call("g", Vec::new(), Some(y.into())),
+            Instruction::FBinary(FBinaryOp::Div, FFmt::D, z, y, x),
+        ],
+        Terminator::Return(Some(z.into())),
+    );
+    // No sse register is saved across calls, so both operands are loaded from the stack after it.
+    let after_call = output.split("\tcall\tg\n").nth(1).unwrap();
+    assert_eq!(
+        after_call
+            .lines()
+            .filter(|line| line.starts_with("\tmovsd\t-") && line.contains("(%rbp), %xmm"))
+            .count(),
+        2
+    );
+}
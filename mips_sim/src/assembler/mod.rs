@@ -0,0 +1,670 @@
+#[cfg(test)]
+mod test;
+
+use crate::instruction::Instruction;
+use mips_ir::{
+    BCond, BZCond, BZalCond, FImmOp, FRegOp2, FRegOp3, ImmOp1, ImmOp2, Isa, MemOp,
+    MipsOutputConfig, MipsOutputter, RegOp1, RegOp2, RegOp3, Root,
+};
+use std::collections::HashMap;
+
+/// The address of the first instruction.
+pub const TEXT_START: u32 = 0x0040_0000;
+/// The address of the first byte of the `.data` segment.
+pub const DATA_START: u32 = 0x1001_0000;
+
+/// An assembled program that can be run by a [`Machine`](crate::Machine).
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub(crate) text: Vec<Instruction>,
+    pub(crate) data: Vec<u8>,
+    /// The address where execution starts.
+    pub(crate) entry: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// The (1-based) line of the assembly text where the error occurred, if any.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl AssembleError {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+impl Program {
+    /// Assembles MARS-style MIPS assembly, as written by [`MipsOutputter`]. Besides the
+    /// instructions of `mips_ir`, the pseudo-instructions `la`, `li`, `move`, `b`, `beqz` and
+    /// `bnez` are supported.
+    ///
+    /// Every instruction, including the pseudo-instructions, takes up a single word. Immediates
+    /// are encoded in 16 bits and sign- or zero-extended by the instruction like the hardware
+    /// does, so e.g. an offset of `65532` is the same as `-4`.
+    pub fn assemble(source: &str) -> Result<Self, AssembleError> {
+        Assembler::default().assemble(source)
+    }
+
+    /// Assembles the output of a linked [`Root`]. Only roots for MIPS can be simulated.
+    pub fn from_root(root: &Root) -> Result<Self, AssembleError> {
+        if root.isa() != Isa::Mips32 {
+            return Err(AssembleError::new(
+                None,
+                "only programs for MIPS can be simulated",
+            ));
+        }
+        let mut asm = String::new();
+        MipsOutputter::new(&mut asm)
+            .with_config(MipsOutputConfig::default())
+            .write_root(root)
+            .expect("writing to a String can't fail");
+        Self::assemble(&asm)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Text,
+    Data,
+}
+
+/// An instruction of which the labels aren't resolved yet.
+struct PendingInstruction<'a> {
+    line: usize,
+    mnemonic: &'a str,
+    operands: Vec<&'a str>,
+}
+
+struct Assembler<'a> {
+    segment: Segment,
+    instructions: Vec<PendingInstruction<'a>>,
+    data: Vec<u8>,
+    labels: HashMap<&'a str, u32>,
+    /// Labels in the data segment get the address of the next data, after it is aligned.
+    unbound_data_labels: Vec<&'a str>,
+    /// `.word` directives with a label, as the offset in the data segment and the label.
+    label_words: Vec<(usize, usize, &'a str)>,
+}
+
+impl Default for Assembler<'_> {
+    fn default() -> Self {
+        Self {
+            segment: Segment::Text,
+            instructions: Vec::new(),
+            data: Vec::new(),
+            labels: HashMap::new(),
+            unbound_data_labels: Vec::new(),
+            label_words: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Assembler<'a> {
+    fn assemble(mut self, source: &'a str) -> Result<Program, AssembleError> {
+        for (i, line) in source.lines().enumerate() {
+            let line_nr = i + 1;
+            self.assemble_line(line_nr, line)
+                .map_err(|message| AssembleError::new(Some(line_nr), message))?;
+        }
+        self.bind_data_labels();
+
+        for &(line, offset, label) in &self.label_words {
+            let address = self
+                .resolve(label)
+                .map_err(|m| AssembleError::new(Some(line), m))?;
+            self.data[offset..offset + 4].copy_from_slice(&address.to_le_bytes());
+        }
+
+        let text = self
+            .instructions
+            .iter()
+            .map(|instruction| {
+                self.resolve_instruction(instruction)
+                    .map_err(|message| AssembleError::new(Some(instruction.line), message))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let entry = match self.labels.get("main") {
+            Some(&address) if (TEXT_START..DATA_START).contains(&address) => address,
+            _ => TEXT_START,
+        };
+
+        Ok(Program {
+            text,
+            data: self.data,
+            entry,
+        })
+    }
+
+    fn assemble_line(&mut self, line_nr: usize, line: &'a str) -> Result<(), String> {
+        let mut rest = strip_comment(line).trim();
+
+        // A line can start with any number of labels.
+        while let Some((label, after)) = rest.split_once(':') {
+            if label.is_empty() || label.contains(|c: char| c.is_whitespace() || "'\",".contains(c))
+            {
+                break;
+            }
+            self.define_label(label)?;
+            rest = after.trim_start();
+        }
+
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let (head, operands) = match rest.split_once(char::is_whitespace) {
+            Some((head, operands)) => (head, split_operands(operands.trim())),
+            None => (rest, Vec::new()),
+        };
+
+        if head.starts_with('.') {
+            self.directive(line_nr, head, &operands)
+        } else if self.segment == Segment::Text {
+            self.instructions.push(PendingInstruction {
+                line: line_nr,
+                mnemonic: head,
+                operands,
+            });
+            Ok(())
+        } else {
+            Err(format!("instruction `{head}` in the data segment"))
+        }
+    }
+
+    fn define_label(&mut self, label: &'a str) -> Result<(), String> {
+        if self.labels.contains_key(label) || self.unbound_data_labels.contains(&label) {
+            return Err(format!("label `{label}` is defined more than once"));
+        }
+        match self.segment {
+            Segment::Text => {
+                let address = TEXT_START + 4 * self.instructions.len() as u32;
+                self.labels.insert(label, address);
+            }
+            Segment::Data => self.unbound_data_labels.push(label),
+        }
+        Ok(())
+    }
+
+    fn bind_data_labels(&mut self) {
+        let address = DATA_START + self.data.len() as u32;
+        for label in self.unbound_data_labels.drain(..) {
+            self.labels.insert(label, address);
+        }
+    }
+
+    fn directive(
+        &mut self,
+        line_nr: usize,
+        name: &str,
+        operands: &[&'a str],
+    ) -> Result<(), String> {
+        match name {
+            ".globl" | ".global" | ".extern" => return Ok(()),
+            ".text" => {
+                self.bind_data_labels();
+                self.segment = Segment::Text;
+                return Ok(());
+            }
+            ".data" => {
+                self.segment = Segment::Data;
+                return Ok(());
+            }
+            ".align" if self.segment == Segment::Text => return Ok(()),
+            _ => {}
+        }
+        if self.segment != Segment::Data {
+            return Err(format!(
+                "directive `{name}` can only be used in the data segment"
+            ));
+        }
+
+        match name {
+            ".align" => {
+                let [n] = operands else {
+                    return Err("expected one operand".to_owned());
+                };
+                match parse_int(n) {
+                    Some(n @ 0..=15) => self.align(1 << n),
+                    _ => return Err(format!("invalid alignment `{n}`")),
+                }
+            }
+            ".space" => {
+                let [n] = operands else {
+                    return Err("expected one operand".to_owned());
+                };
+                let n = parse_int(n)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or_else(|| format!("invalid size `{n}`"))?;
+                self.bind_data_labels();
+                self.data.resize(self.data.len() + n, 0);
+            }
+            ".ascii" | ".asciiz" => {
+                self.bind_data_labels();
+                for operand in operands {
+                    let string = parse_string(operand)
+                        .ok_or_else(|| format!("invalid string literal {operand}"))?;
+                    self.data.extend(string);
+                    if name == ".asciiz" {
+                        self.data.push(0);
+                    }
+                }
+            }
+            ".byte" => self.integers(operands, 1)?,
+            ".half" => self.integers(operands, 2)?,
+            ".word" => {
+                self.align(4);
+                self.bind_data_labels();
+                for operand in operands {
+                    match parse_int(operand) {
+                        Some(_) => self.integers(std::slice::from_ref(operand), 4)?,
+                        None if is_label(operand) => {
+                            self.label_words.push((line_nr, self.data.len(), operand));
+                            self.data.extend([0; 4]);
+                        }
+                        None => return Err(format!("invalid word `{operand}`")),
+                    }
+                }
+            }
+            ".float" => {
+                self.align(4);
+                self.bind_data_labels();
+                for operand in operands {
+                    let value =
+                        parse_float(operand).ok_or_else(|| format!("invalid float `{operand}`"))?;
+                    self.data.extend((value as f32).to_le_bytes());
+                }
+            }
+            ".double" => {
+                self.align(8);
+                self.bind_data_labels();
+                for operand in operands {
+                    let value = parse_float(operand)
+                        .ok_or_else(|| format!("invalid double `{operand}`"))?;
+                    self.data.extend(value.to_le_bytes());
+                }
+            }
+            _ => return Err(format!("unknown directive `{name}`")),
+        }
+        Ok(())
+    }
+
+    /// Adds integers of `size` bytes to the data segment, after aligning it to the size.
+    fn integers(&mut self, operands: &[&str], size: usize) -> Result<(), String> {
+        self.align(size);
+        self.bind_data_labels();
+        let bits = 8 * size as u32;
+        for operand in operands {
+            let value = parse_int(operand)
+                .filter(|&v| v >= -(1 << (bits - 1)) && v < (1 << bits))
+                .ok_or_else(|| format!("invalid {bits}-bit integer `{operand}`"))?;
+            self.data.extend(&value.to_le_bytes()[..size]);
+        }
+        Ok(())
+    }
+
+    fn align(&mut self, align: usize) {
+        self.data.resize(self.data.len().next_multiple_of(align), 0);
+    }
+
+    fn resolve(&self, label: &str) -> Result<u32, String> {
+        self.labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| format!("undefined label `{label}`"))
+    }
+
+    fn resolve_instruction(&self, instruction: &PendingInstruction) -> Result<Instruction, String> {
+        let PendingInstruction {
+            mnemonic, operands, ..
+        } = instruction;
+        let operands = Operands(operands);
+
+        let instruction = match *mnemonic {
+            "nop" => operands.none(Instruction::Nop)?,
+            "break" => operands.none(Instruction::Break)?,
+            "syscall" => operands.none(Instruction::Syscall)?,
+            "la" => {
+                let [rt, label] = operands.get()?;
+                Instruction::LoadImm(parse_reg(rt)?, self.resolve_address(label)?)
+            }
+            "li" => {
+                let [rt, imm] = operands.get()?;
+                let value = parse_int(imm)
+                    .filter(|&v| v >= i32::MIN as i64 && v <= u32::MAX as i64)
+                    .ok_or_else(|| format!("invalid 32-bit immediate `{imm}`"))?;
+                Instruction::LoadImm(parse_reg(rt)?, value as u32)
+            }
+            "move" => {
+                let [rd, rs] = operands.get()?;
+                Instruction::Reg3(RegOp3::AddU, parse_reg(rd)?, parse_reg(rs)?, 0)
+            }
+            "b" => {
+                let [label] = operands.get()?;
+                Instruction::Branch(BCond::Eq, 0, 0, self.resolve(label)?)
+            }
+            "beqz" | "bnez" => {
+                let [rs, label] = operands.get()?;
+                let cond = if *mnemonic == "beqz" {
+                    BCond::Eq
+                } else {
+                    BCond::Ne
+                };
+                Instruction::Branch(cond, parse_reg(rs)?, 0, self.resolve(label)?)
+            }
+            "j" => {
+                let [label] = operands.get()?;
+                Instruction::Jump(self.resolve(label)?)
+            }
+            "jal" => {
+                let [label] = operands.get()?;
+                Instruction::JumpAndLink(self.resolve(label)?)
+            }
+            "jr" => {
+                let [rs] = operands.get()?;
+                Instruction::JumpReg(parse_reg(rs)?)
+            }
+            "jalr" => match operands.0 {
+                [rs] => Instruction::JumpAndLinkReg(31, parse_reg(rs)?),
+                [rd, rs] => Instruction::JumpAndLinkReg(parse_reg(rd)?, parse_reg(rs)?),
+                _ => return Err("expected one or two operands".to_owned()),
+            },
+            "mfc1" | "mtc1" => {
+                let [rt, fs] = operands.get()?;
+                let (rt, fs) = (parse_reg(rt)?, parse_freg(fs)?);
+                match *mnemonic {
+                    "mfc1" => Instruction::MoveFromFpu(rt, fs),
+                    _ => Instruction::MoveToFpu(rt, fs),
+                }
+            }
+            "bc1t" | "bc1f" => {
+                let [label] = operands.get()?;
+                Instruction::BranchFpu(*mnemonic == "bc1t", self.resolve(label)?)
+            }
+            _ => self.resolve_op(mnemonic, operands)?,
+        };
+        Ok(instruction)
+    }
+
+    /// Resolves the instructions that are named after one of the operation enums of `mips_ir`.
+    fn resolve_op(&self, mnemonic: &str, operands: Operands) -> Result<Instruction, String> {
+        let find = |name: String| name == mnemonic;
+
+        if let Some(&op) = RegOp3::ALL.iter().find(|op| find(op.to_string())) {
+            let [rd, rs, rt] = operands.get()?;
+            return Ok(Instruction::Reg3(
+                op,
+                parse_reg(rd)?,
+                parse_reg(rs)?,
+                parse_reg(rt)?,
+            ));
+        }
+        if let Some(&op) = RegOp2::ALL.iter().find(|op| find(op.to_string())) {
+            let [rs, rt] = operands.get()?;
+            return Ok(Instruction::Reg2(op, parse_reg(rs)?, parse_reg(rt)?));
+        }
+        if let Some(&op) = RegOp1::ALL.iter().find(|op| find(op.to_string())) {
+            let [rd] = operands.get()?;
+            return Ok(Instruction::Reg1(op, parse_reg(rd)?));
+        }
+        if let Some(&op) = ImmOp2::ALL.iter().find(|op| find(op.to_string())) {
+            let [rt, rs, imm] = operands.get()?;
+            return Ok(Instruction::Imm2(
+                op,
+                parse_reg(rt)?,
+                parse_reg(rs)?,
+                parse_imm(imm)?,
+            ));
+        }
+        if let Some(&op) = MemOp::ALL.iter().find(|op| find(op.to_string())) {
+            let [rt, address] = operands.get()?;
+            let (offset, base) = parse_mem(address)?;
+            return Ok(Instruction::Mem(op, parse_reg(rt)?, base, offset));
+        }
+        if let Some(&op) = ImmOp1::ALL.iter().find(|op| find(op.to_string())) {
+            let [rt, imm] = operands.get()?;
+            return Ok(Instruction::Imm1(op, parse_reg(rt)?, parse_imm(imm)?));
+        }
+        if let Some(&op) = FRegOp3::ALL.iter().find(|op| find(op.to_string())) {
+            let [fd, fs, ft] = operands.get()?;
+            return Ok(Instruction::FReg3(
+                op,
+                parse_freg(fd)?,
+                parse_freg(fs)?,
+                parse_freg(ft)?,
+            ));
+        }
+        if let Some(&op) = FRegOp2::ALL.iter().find(|op| find(op.to_string())) {
+            let [fd, fs] = operands.get()?;
+            return Ok(Instruction::FReg2(op, parse_freg(fd)?, parse_freg(fs)?));
+        }
+        if let Some(&op) = FImmOp::ALL.iter().find(|op| find(op.to_string())) {
+            let [ft, address] = operands.get()?;
+            let (offset, base) = parse_mem(address)?;
+            return Ok(Instruction::FImm(op, parse_freg(ft)?, base, offset));
+        }
+        if let Some(&cond) = BCond::ALL.iter().find(|cond| find(format!("b{cond}"))) {
+            let [rs, rt, label] = operands.get()?;
+            return Ok(Instruction::Branch(
+                cond,
+                parse_reg(rs)?,
+                parse_reg(rt)?,
+                self.resolve(label)?,
+            ));
+        }
+        if let Some(&cond) = BZCond::ALL.iter().find(|cond| find(format!("b{cond}"))) {
+            let [rs, label] = operands.get()?;
+            return Ok(Instruction::BranchZ(
+                cond,
+                parse_reg(rs)?,
+                self.resolve(label)?,
+            ));
+        }
+        if let Some(&cond) = BZalCond::ALL.iter().find(|cond| find(format!("b{cond}al"))) {
+            let [rs, label] = operands.get()?;
+            return Ok(Instruction::BranchZAndLink(
+                cond,
+                parse_reg(rs)?,
+                self.resolve(label)?,
+            ));
+        }
+        Err(format!("unknown instruction `{mnemonic}`"))
+    }
+
+    /// Resolves a label, or an absolute address for `la`.
+    fn resolve_address(&self, operand: &str) -> Result<u32, String> {
+        match parse_int(operand) {
+            Some(address) => Ok(address as u32),
+            None => self.resolve(operand),
+        }
+    }
+}
+
+struct Operands<'o, 'a>(&'o [&'a str]);
+
+impl<'a> Operands<'_, 'a> {
+    fn get<const N: usize>(&self) -> Result<[&'a str; N], String> {
+        self.0.try_into().map_err(|_| match N {
+            1 => "expected one operand".to_owned(),
+            _ => format!("expected {N} operands"),
+        })
+    }
+
+    fn none(&self, instruction: Instruction) -> Result<Instruction, String> {
+        self.get::<0>().map(|_| instruction)
+    }
+}
+
+const REG_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra",
+];
+
+fn parse_reg(operand: &str) -> Result<u8, String> {
+    let name = operand
+        .strip_prefix('$')
+        .ok_or_else(|| format!("expected a register, found `{operand}`"))?;
+    let number = match name.parse::<u8>() {
+        Ok(n) => Some(n),
+        Err(_) if name == "s8" => Some(30),
+        Err(_) => REG_NAMES.iter().position(|r| *r == name).map(|n| n as u8),
+    };
+    number
+        .filter(|&n| n < 32)
+        .ok_or_else(|| format!("unknown register `{operand}`"))
+}
+
+fn parse_freg(operand: &str) -> Result<u8, String> {
+    operand
+        .strip_prefix("$f")
+        .and_then(|n| n.parse::<u8>().ok())
+        .filter(|&n| n < 32)
+        .ok_or_else(|| format!("expected a floating-point register, found `{operand}`"))
+}
+
+/// Parses a 16-bit immediate, which can be written signed or unsigned.
+fn parse_imm(operand: &str) -> Result<u16, String> {
+    parse_int(operand)
+        .filter(|&v| (i16::MIN as i64..=u16::MAX as i64).contains(&v))
+        .map(|v| v as u16)
+        .ok_or_else(|| format!("invalid 16-bit immediate `{operand}`"))
+}
+
+/// Parses a memory operand like `8($sp)` or `($sp)`, returns the offset and base register.
+fn parse_mem(operand: &str) -> Result<(u16, u8), String> {
+    let invalid = || format!("invalid memory operand `{operand}`");
+    let (offset, base) = operand
+        .strip_suffix(')')
+        .and_then(|o| o.split_once('('))
+        .ok_or_else(invalid)?;
+    let offset = match offset.trim() {
+        "" => 0,
+        offset => parse_imm(offset)?,
+    };
+    Ok((offset, parse_reg(base.trim())?))
+}
+
+/// Parses a decimal, hexadecimal (`0x`) or character literal.
+fn parse_int(operand: &str) -> Option<i64> {
+    if let Some(literal) = operand.strip_prefix('\'') {
+        let bytes = unescape(literal.strip_suffix('\'')?)?;
+        return match bytes.as_slice() {
+            [byte] => Some(*byte as i64),
+            _ => None,
+        };
+    }
+    let (negative, digits) = match operand.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, operand),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok()?,
+        None => return None,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_float(operand: &str) -> Option<f64> {
+    // The outputter writes infinity as `inf.0`.
+    match operand.parse() {
+        Ok(value) => Some(value),
+        Err(_) => operand.strip_suffix(".0")?.parse().ok(),
+    }
+}
+
+fn parse_string(operand: &str) -> Option<Vec<u8>> {
+    unescape(operand.strip_prefix('"')?.strip_suffix('"')?)
+}
+
+fn unescape(literal: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(literal.len());
+    let mut chars = literal.bytes();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        bytes.push(match chars.next()? {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'0' => 0,
+            escaped @ (b'\\' | b'\'' | b'"') => escaped,
+            _ => return None,
+        });
+    }
+    Some(bytes)
+}
+
+fn is_label(operand: &str) -> bool {
+    !operand.is_empty()
+        && !operand.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && !operand.contains(|c: char| c.is_whitespace() || "'\"(),".contains(c))
+}
+
+/// Removes the comment (`# ...`) from the line, if any. A `#` in a string or character literal
+/// doesn't start a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            (None, _) => {}
+        }
+    }
+    line
+}
+
+/// Splits the operands on commas that aren't in a string or character literal.
+fn split_operands(operands: &str) -> Vec<&str> {
+    if operands.is_empty() {
+        return Vec::new();
+    }
+    let mut result = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in operands.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => {
+                result.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            (None, _) => {}
+        }
+    }
+    result.push(operands[start..].trim());
+    result
+}
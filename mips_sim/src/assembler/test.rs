@@ -0,0 +1,81 @@
+use super::*;
+use mips_ir::{FCmp, FFmt};
+
+fn assemble(asm: &str) -> Program {
+    Program::assemble(asm).unwrap_or_else(|e| panic!("{e}"))
+}
+
+#[test]
+fn labels_resolve_to_addresses() {
+    let program = assemble(
+        "\t.data\nx:\t.byte\t1\ny:\t.word\t2\n\t.text\nfoo:\tnop\nmain:\tla\t$t0, y\n\tj\tfoo\n",
+    );
+    assert_eq!(program.entry, TEXT_START + 4);
+    assert_eq!(program.text[1], Instruction::LoadImm(8, DATA_START + 4));
+    assert_eq!(program.text[2], Instruction::Jump(TEXT_START));
+}
+
+#[test]
+fn data_is_aligned_and_little_endian() {
+    let program = assemble(
+        "\t.data\na:\t.byte\t1, 255\nb:\t.half\t-2\n\t.align\t3\nc:\t.space\t1\nd:\t.word\t258, a\n",
+    );
+    assert_eq!(
+        program.data,
+        [
+            1, 255, 0xfe, 0xff, 0, 0, 0, 0, // a, b and the alignment
+            0, 0, 0, 0, // c and the alignment of d
+            2, 1, 0, 0, // 258
+            0x00, 0x00, 0x01, 0x10, // the address of a
+        ]
+    );
+}
+
+#[test]
+fn strings_and_char_literals_are_unescaped() {
+    let program = assemble(
+        "\t.data\ns:\t.asciiz\t\"a#\\n\\\"\"\n\t.text\n\tori\t$t6, $zero, '#' # comment\n\tori\t$t7, $zero, '\\n'\n",
+    );
+    assert_eq!(program.data, b"a#\n\"\0");
+    assert_eq!(
+        program.text[0],
+        Instruction::Imm2(ImmOp2::Or, 14, 0, b'#' as u16)
+    );
+    assert_eq!(
+        program.text[1],
+        Instruction::Imm2(ImmOp2::Or, 15, 0, b'\n' as u16)
+    );
+}
+
+#[test]
+fn operands_use_register_names_or_numbers() {
+    let program = assemble(
+        "\tlw\t$ra, 65532($sp)\n\tsw\t$31, -4($29)\n\tldc1\t$f12, ($s8)\n\tc.lt.d\t$f0, $f2\n",
+    );
+    assert_eq!(
+        program.text[0],
+        Instruction::Mem(MemOp::LoadWord, 31, 29, 65532)
+    );
+    assert_eq!(
+        program.text[1],
+        Instruction::Mem(MemOp::StoreWord, 31, 29, 65532)
+    );
+    assert_eq!(
+        program.text[2],
+        Instruction::FImm(FImmOp::LoadDoublewordToFpu, 12, 30, 0)
+    );
+    assert_eq!(
+        program.text[3],
+        Instruction::FReg2(FRegOp2::Cmp(FCmp::Lt(FFmt::D)), 0, 2)
+    );
+}
+
+#[test]
+fn errors_have_the_line() {
+    let err = Program::assemble("\tnop\n\tfoo\t$t0\n").unwrap_err();
+    assert_eq!(err.line, Some(2));
+    let err = Program::assemble("\tj\tnowhere\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined label `nowhere`");
+    let err = Program::assemble("\taddiu\t$t0, $t0, 70000\n").unwrap_err();
+    assert_eq!(err.line, Some(1));
+}
@@ -0,0 +1,58 @@
+//! Formats floating-point values like Java's `Double.toString` and `Float.toString`, which MARS
+//! uses to print them.
+
+pub fn format_double(value: f64) -> String {
+    format_java(value, value.abs(), &format!("{value:e}"))
+}
+
+pub fn format_float(value: f32) -> String {
+    format_java(value as f64, value.abs() as f64, &format!("{value:e}"))
+}
+
+/// `scientific` is the shortest representation that converts back to the same value, in Rust's
+/// scientific notation (e.g. `-1.25e-3`).
+fn format_java(value: f64, abs: f64, scientific: &str) -> String {
+    if value.is_nan() {
+        return "NaN".to_owned();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_owned();
+    }
+    if abs == 0.0 {
+        return if value.is_sign_negative() {
+            "-0.0"
+        } else {
+            "0.0"
+        }
+        .to_owned();
+    }
+
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+
+    if (1e-3..1e7).contains(&abs) {
+        if exponent >= 0 {
+            let int_len = exponent as usize + 1;
+            let (int, frac) = if digits.len() > int_len {
+                (digits[..int_len].to_owned(), digits[int_len..].to_owned())
+            } else {
+                (format!("{digits:0<int_len$}"), "0".to_owned())
+            };
+            format!("{sign}{int}.{frac}")
+        } else {
+            let zeros = "0".repeat((-exponent - 1) as usize);
+            format!("{sign}0.{zeros}{digits}")
+        }
+    } else {
+        let frac = match &digits[1..] {
+            "" => "0",
+            frac => frac,
+        };
+        format!("{sign}{}.{frac}E{exponent}", &digits[..1])
+    }
+}
@@ -0,0 +1,37 @@
+use mips_ir::{
+    BCond, BZCond, BZalCond, FImmOp, FRegOp2, FRegOp3, ImmOp1, ImmOp2, MemOp, RegOp1, RegOp2,
+    RegOp3,
+};
+
+/// An assembled instruction. Registers are stored by number and labels are resolved to
+/// addresses. The operations reuse the enums of `mips_ir`, so their meaning is the same as in
+/// [`mips_ir::Instruction`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Nop,
+    Reg3(RegOp3, u8, u8, u8),
+    Reg2(RegOp2, u8, u8),
+    Reg1(RegOp1, u8),
+    Imm2(ImmOp2, u8, u8, u16),
+    Mem(MemOp, u8, u8, u16),
+    Imm1(ImmOp1, u8, u16),
+    FReg3(FRegOp3, u8, u8, u8),
+    FReg2(FRegOp2, u8, u8),
+    FImm(FImmOp, u8, u8, u16),
+    MoveFromFpu(u8, u8),
+    MoveToFpu(u8, u8),
+    Break,
+    Syscall,
+    /// Set the register to a 32-bit value, this is `la` or `li`.
+    LoadImm(u8, u32),
+    Jump(u32),
+    JumpAndLink(u32),
+    JumpReg(u8),
+    /// Jump to the address in the second register and store the return address in the first.
+    JumpAndLinkReg(u8, u8),
+    Branch(BCond, u8, u8, u32),
+    BranchZ(BZCond, u8, u32),
+    BranchZAndLink(BZalCond, u8, u32),
+    /// Branch if the FPU condition flag is equal to the bool.
+    BranchFpu(bool, u32),
+}
@@ -0,0 +1,21 @@
+//! A simulator for the MIPS assembly generated by `mips_ir`, to run programs without MARS.
+//!
+//! The assembly text is first assembled into a [`Program`], which can then be executed by a
+//! [`Machine`]. The simulator follows the behaviour of MARS where it matters for the generated
+//! code:
+//!
+//!  - The memory layout is the same: `.text` starts at `0x00400000`, `.data` at `0x10010000` and
+//!    the stack pointer starts at `0x7fffeffc`. Memory is little-endian.
+//!  - Branches and jumps don't have delay slots.
+//!  - The syscalls used by the premade `printf` and `scanf` (and a few others) are supported, and
+//!    floating-point values are printed like Java does.
+//!  - Execution starts at `main` if that label exists, and stops normally when it runs past the
+//!    last instruction.
+
+mod assembler;
+mod float_fmt;
+mod instruction;
+mod machine;
+
+pub use assembler::{AssembleError, Program};
+pub use machine::{ErrorKind, Exit, Machine, RuntimeError};
@@ -0,0 +1,74 @@
+use super::ErrorKind;
+use crate::assembler::DATA_START;
+use std::collections::HashMap;
+
+/// The start of the memory that can be read and written, `.extern` in MARS.
+const MEMORY_START: u32 = 0x1000_0000;
+/// The end of the stack segment, the kernel segments after it can't be used.
+const MEMORY_END: u32 = 0x8000_0000;
+
+const PAGE_SIZE: usize = 4096;
+
+/// A sparse little-endian memory, of which pages are allocated when they are first written.
+/// Unwritten memory reads as zero.
+pub struct Memory {
+    pages: HashMap<u32, Box<[u8; PAGE_SIZE]>>,
+}
+
+impl Memory {
+    /// Creates a memory with `data` at the start of the data segment.
+    pub fn new(data: &[u8]) -> Self {
+        let mut memory = Self {
+            pages: HashMap::new(),
+        };
+        for (address, &byte) in (DATA_START..).zip(data) {
+            memory.write_byte(address, byte);
+        }
+        memory
+    }
+
+    /// Reads a value of `size` (1, 2 or 4) bytes, zero-extended.
+    pub fn read(&self, address: u32, size: u32) -> Result<u32, ErrorKind> {
+        check(address, size)?;
+        let mut value = 0;
+        for i in (0..size).rev() {
+            value = value << 8 | self.read_byte(address + i) as u32;
+        }
+        Ok(value)
+    }
+
+    /// Writes the low `size` (1, 2 or 4) bytes of the value.
+    pub fn write(&mut self, address: u32, size: u32, value: u32) -> Result<(), ErrorKind> {
+        check(address, size)?;
+        for i in 0..size {
+            self.write_byte(address + i, (value >> (8 * i)) as u8);
+        }
+        Ok(())
+    }
+
+    fn read_byte(&self, address: u32) -> u8 {
+        let (page, offset) = split(address);
+        self.pages.get(&page).map_or(0, |page| page[offset])
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8) {
+        let (page, offset) = split(address);
+        self.pages
+            .entry(page)
+            .or_insert_with(|| Box::new([0; PAGE_SIZE]))[offset] = byte;
+    }
+}
+
+fn check(address: u32, size: u32) -> Result<(), ErrorKind> {
+    if !(MEMORY_START..MEMORY_END).contains(&address) {
+        Err(ErrorKind::AddressOutOfRange(address))
+    } else if !address.is_multiple_of(size) {
+        Err(ErrorKind::UnalignedAddress(address))
+    } else {
+        Ok(())
+    }
+}
+
+fn split(address: u32) -> (u32, usize) {
+    (address / PAGE_SIZE as u32, address as usize % PAGE_SIZE)
+}
@@ -0,0 +1,571 @@
+#[cfg(test)]
+mod test;
+
+mod memory;
+mod syscall;
+
+use crate::{
+    assembler::{Program, TEXT_START},
+    instruction::Instruction,
+};
+use memory::Memory;
+use mips_ir::{
+    BCond, BZCond, BZalCond, FCmp, FFmt, FImmOp, FRegOp2, FRegOp3, ImmOp1, ImmOp2, MemOp, RegOp1,
+    RegOp2, RegOp3, TrapCond, TrapCondImm,
+};
+use std::io::{BufRead, Write};
+
+const GLOBAL_POINTER: u32 = 0x1000_8000;
+const STACK_POINTER: u32 = 0x7fff_effc;
+
+const GP: usize = 28;
+const SP: usize = 29;
+const RA: usize = 31;
+
+/// How the program stopped without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exit {
+    /// The exit code passed to the exit syscall, or 0 if the program ran past its last
+    /// instruction.
+    pub code: i32,
+    /// The number of executed instructions.
+    pub steps: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    /// The address of the instruction that caused the error.
+    pub pc: u32,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The step limit set with [`Machine::with_step_limit`] was reached.
+    StepLimit(u64),
+    /// A trap instruction (e.g. `teq`) was executed and its condition was true.
+    Trap,
+    Break,
+    /// A signed `add`, `addi` or `sub` overflowed.
+    Overflow,
+    /// The address is outside of the data, heap and stack segments.
+    AddressOutOfRange(u32),
+    /// The address isn't aligned to the size of the accessed data.
+    UnalignedAddress(u32),
+    /// The target of a jump or branch isn't an instruction.
+    InvalidJump(u32),
+    /// A double-precision instruction used an odd FPU register.
+    OddFpuRegister(u8),
+    UnknownSyscall(u32),
+    /// The input for the syscall (e.g. read int) couldn't be parsed.
+    InvalidInput(u32),
+    /// Reading the input or writing the output failed.
+    Io(String),
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "runtime error at 0x{:08x}: ", self.pc)?;
+        match &self.kind {
+            ErrorKind::StepLimit(limit) => write!(f, "reached the limit of {limit} steps"),
+            ErrorKind::Trap => f.write_str("trap"),
+            ErrorKind::Break => f.write_str("break instruction executed"),
+            ErrorKind::Overflow => f.write_str("arithmetic overflow"),
+            ErrorKind::AddressOutOfRange(address) => {
+                write!(f, "address 0x{address:08x} out of range")
+            }
+            ErrorKind::UnalignedAddress(address) => {
+                write!(f, "address 0x{address:08x} is not aligned")
+            }
+            ErrorKind::InvalidJump(address) => {
+                write!(f, "jump to 0x{address:08x}, which is not an instruction")
+            }
+            ErrorKind::OddFpuRegister(n) => {
+                write!(f, "$f{n} can't be used for a double, it must be even")
+            }
+            ErrorKind::UnknownSyscall(n) => write!(f, "unknown syscall {n}"),
+            ErrorKind::InvalidInput(n) => write!(f, "invalid input for syscall {n}"),
+            ErrorKind::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// What to do after an instruction.
+enum Flow {
+    Next,
+    Jump(u32),
+    Exit(i32),
+}
+
+/// Executes a [`Program`]. The syscalls read from `input` and write to `output`.
+///
+/// ```skip
+/// let program = Program::assemble(asm)?;
+/// let mut output = Vec::new();
+/// let exit = Machine::new(&program, std::io::empty(), &mut output)
+///     .with_step_limit(1_000_000)
+///     .run()?;
+/// ```
+pub struct Machine<'p, R, W> {
+    program: &'p Program,
+    input: R,
+    output: W,
+    step_limit: Option<u64>,
+    steps: u64,
+    pc: u32,
+    regs: [u32; 32],
+    hi: u32,
+    lo: u32,
+    fregs: [u32; 32],
+    /// FPU condition flag 0, set by the compare instructions.
+    fcc: bool,
+    memory: Memory,
+    /// The end of the memory allocated by `sbrk`.
+    heap_end: u32,
+}
+
+impl<'p, R: BufRead, W: Write> Machine<'p, R, W> {
+    pub fn new(program: &'p Program, input: R, output: W) -> Self {
+        let mut regs = [0; 32];
+        regs[GP] = GLOBAL_POINTER;
+        regs[SP] = STACK_POINTER;
+        Self {
+            program,
+            input,
+            output,
+            step_limit: None,
+            steps: 0,
+            pc: program.entry,
+            regs,
+            hi: 0,
+            lo: 0,
+            fregs: [0; 32],
+            fcc: false,
+            memory: Memory::new(&program.data),
+            heap_end: syscall::HEAP_START,
+        }
+    }
+
+    /// Stops the program with [`ErrorKind::StepLimit`] when it tries to execute more than
+    /// `limit` instructions, e.g. to catch infinite loops.
+    pub fn with_step_limit(self, limit: u64) -> Self {
+        Self {
+            step_limit: Some(limit),
+            ..self
+        }
+    }
+
+    /// Runs the program until it exits.
+    pub fn run(&mut self) -> Result<Exit, RuntimeError> {
+        let text_end = TEXT_START + 4 * self.program.text.len() as u32;
+        loop {
+            if self.pc == text_end {
+                // Like MARS, running past the last instruction ends the program.
+                return Ok(Exit {
+                    code: 0,
+                    steps: self.steps,
+                });
+            }
+            if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+                return Err(self.error(ErrorKind::StepLimit(self.steps)));
+            }
+            self.steps += 1;
+
+            let instruction = self.program.text[((self.pc - TEXT_START) / 4) as usize];
+            match self.execute(instruction) {
+                Ok(Flow::Next) => self.pc += 4,
+                Ok(Flow::Jump(target)) => {
+                    if target < TEXT_START || target > text_end || !target.is_multiple_of(4) {
+                        return Err(self.error(ErrorKind::InvalidJump(target)));
+                    }
+                    self.pc = target;
+                }
+                Ok(Flow::Exit(code)) => {
+                    return Ok(Exit {
+                        code,
+                        steps: self.steps,
+                    })
+                }
+                Err(kind) => return Err(self.error(kind)),
+            }
+            self.regs[0] = 0;
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> RuntimeError {
+        RuntimeError { pc: self.pc, kind }
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<Flow, ErrorKind> {
+        match instruction {
+            Instruction::Nop => {}
+            Instruction::Reg3(op, rd, rs, rt) => {
+                let (a, b) = (self.reg(rs), self.reg(rt));
+                let value = match op {
+                    RegOp3::AddS => signed_add(a, b)?,
+                    RegOp3::AddU => a.wrapping_add(b),
+                    RegOp3::SubS => (a as i32)
+                        .checked_sub(b as i32)
+                        .ok_or(ErrorKind::Overflow)? as u32,
+                    RegOp3::SubU => a.wrapping_sub(b),
+                    RegOp3::And => a & b,
+                    RegOp3::Or => a | b,
+                    RegOp3::Nor => !(a | b),
+                    RegOp3::Xor => a ^ b,
+                    RegOp3::ShiftLeftLogical => a << (b & 31),
+                    RegOp3::ShiftRightLogical => a >> (b & 31),
+                    RegOp3::ShiftRightArithmetic => ((a as i32) >> (b & 31)) as u32,
+                    RegOp3::SetLtS => ((a as i32) < (b as i32)) as u32,
+                    RegOp3::SetLtU => (a < b) as u32,
+                };
+                self.set_reg(rd, value);
+            }
+            Instruction::Reg2(op, rs, rt) => {
+                let (a, b) = (self.reg(rs), self.reg(rt));
+                match op {
+                    // Like MARS, dividing by zero leaves HI and LO unchanged.
+                    RegOp2::DivS if b != 0 => {
+                        self.lo = (a as i32).wrapping_div(b as i32) as u32;
+                        self.hi = (a as i32).wrapping_rem(b as i32) as u32;
+                    }
+                    RegOp2::DivU if b != 0 => {
+                        self.lo = a / b;
+                        self.hi = a % b;
+                    }
+                    RegOp2::DivS | RegOp2::DivU => {}
+                    RegOp2::MultS => {
+                        self.set_hi_lo((a as i32 as i64 * b as i32 as i64) as u64);
+                    }
+                    RegOp2::MultU => self.set_hi_lo(a as u64 * b as u64),
+                    RegOp2::TrapIf(cond) => {
+                        let trap = match cond {
+                            TrapCond::Eq => a == b,
+                            TrapCond::Ne => a != b,
+                            TrapCond::GeS => a as i32 >= b as i32,
+                            TrapCond::GeU => a >= b,
+                            TrapCond::LtS => (a as i32) < b as i32,
+                            TrapCond::LtU => a < b,
+                        };
+                        if trap {
+                            return Err(ErrorKind::Trap);
+                        }
+                    }
+                }
+            }
+            Instruction::Reg1(op, r) => match op {
+                RegOp1::MoveFromHi => self.set_reg(r, self.hi),
+                RegOp1::MoveFromLo => self.set_reg(r, self.lo),
+                RegOp1::MoveToHi => self.hi = self.reg(r),
+                RegOp1::MoveToLo => self.lo = self.reg(r),
+            },
+            Instruction::Imm2(op, rt, rs, imm) => {
+                let a = self.reg(rs);
+                let (sext, zext) = (imm as i16 as u32, imm as u32);
+                let value = match op {
+                    ImmOp2::AddS => signed_add(a, sext)?,
+                    ImmOp2::AddU => a.wrapping_add(sext),
+                    ImmOp2::And => a & zext,
+                    ImmOp2::Or => a | zext,
+                    ImmOp2::Xor => a ^ zext,
+                    ImmOp2::ShiftLeftLogical => a << (imm & 31),
+                    ImmOp2::ShiftRightLogical => a >> (imm & 31),
+                    ImmOp2::ShiftRightArithmetic => ((a as i32) >> (imm & 31)) as u32,
+                    ImmOp2::SetLtS => ((a as i32) < sext as i32) as u32,
+                    ImmOp2::SetLtU => (a < sext) as u32,
+                };
+                self.set_reg(rt, value);
+            }
+            Instruction::Mem(op, rt, base, offset) => {
+                let address = self.address(base, offset);
+                self.execute_mem(op, rt, address)?;
+            }
+            Instruction::Imm1(op, rt, imm) => {
+                let a = self.reg(rt);
+                let sext = imm as i16 as u32;
+                match op {
+                    ImmOp1::LoadUpper => self.set_reg(rt, (imm as u32) << 16),
+                    ImmOp1::TrapIf(cond) => {
+                        let trap = match cond {
+                            TrapCondImm::Eq => a == sext,
+                            TrapCondImm::Ne => a != sext,
+                            TrapCondImm::GeS => a as i32 >= sext as i32,
+                            TrapCondImm::GeU => a >= sext,
+                            TrapCondImm::LtS => (a as i32) < sext as i32,
+                            TrapCondImm::LtU => a < sext,
+                        };
+                        if trap {
+                            return Err(ErrorKind::Trap);
+                        }
+                    }
+                }
+            }
+            Instruction::FReg3(op, fd, fs, ft) => {
+                let (fmt, f): (_, fn(f64, f64) -> f64) = match op {
+                    FRegOp3::Add(fmt) => (fmt, |a, b| a + b),
+                    FRegOp3::Sub(fmt) => (fmt, |a, b| a - b),
+                    FRegOp3::Div(fmt) => (fmt, |a, b| a / b),
+                    FRegOp3::Mul(fmt) => (fmt, |a, b| a * b),
+                };
+                match fmt {
+                    // Calculating in double precision and then rounding gives the same result
+                    // for these operations.
+                    FFmt::S => {
+                        let value = f(self.single(fs) as f64, self.single(ft) as f64);
+                        self.set_single(fd, value as f32);
+                    }
+                    FFmt::D => {
+                        let value = f(self.double(fs)?, self.double(ft)?);
+                        self.set_double(fd, value)?;
+                    }
+                }
+            }
+            Instruction::FReg2(op, fd, fs) => self.execute_freg2(op, fd, fs)?,
+            Instruction::FImm(op, ft, base, offset) => {
+                let address = self.address(base, offset);
+                match op {
+                    FImmOp::LoadWordToFpu => {
+                        self.fregs[ft as usize] = self.memory.read(address, 4)?
+                    }
+                    FImmOp::StoreWordFromFpu => {
+                        self.memory.write(address, 4, self.fregs[ft as usize])?
+                    }
+                    FImmOp::LoadDoublewordToFpu => {
+                        check_even(ft)?;
+                        let low = self.memory.read(address, 4)?;
+                        let high = self.memory.read(address.wrapping_add(4), 4)?;
+                        self.fregs[ft as usize] = low;
+                        self.fregs[ft as usize + 1] = high;
+                    }
+                    FImmOp::StoreDoublewordFromFpu => {
+                        check_even(ft)?;
+                        self.memory.write(address, 4, self.fregs[ft as usize])?;
+                        let high = self.fregs[ft as usize + 1];
+                        self.memory.write(address.wrapping_add(4), 4, high)?;
+                    }
+                }
+            }
+            Instruction::MoveFromFpu(rt, fs) => self.set_reg(rt, self.fregs[fs as usize]),
+            Instruction::MoveToFpu(rt, fs) => self.fregs[fs as usize] = self.reg(rt),
+            Instruction::Break => return Err(ErrorKind::Break),
+            Instruction::Syscall => return self.syscall(),
+            Instruction::LoadImm(rt, value) => self.set_reg(rt, value),
+            Instruction::Jump(target) => return Ok(Flow::Jump(target)),
+            Instruction::JumpAndLink(target) => {
+                self.regs[RA] = self.pc + 4;
+                return Ok(Flow::Jump(target));
+            }
+            Instruction::JumpReg(rs) => return Ok(Flow::Jump(self.reg(rs))),
+            Instruction::JumpAndLinkReg(rd, rs) => {
+                let target = self.reg(rs);
+                self.set_reg(rd, self.pc + 4);
+                return Ok(Flow::Jump(target));
+            }
+            Instruction::Branch(cond, rs, rt, target) => {
+                let equal = self.reg(rs) == self.reg(rt);
+                if equal == (cond == BCond::Eq) {
+                    return Ok(Flow::Jump(target));
+                }
+            }
+            Instruction::BranchZ(cond, rs, target) => {
+                let value = self.reg(rs) as i32;
+                let taken = match cond {
+                    BZCond::GeZ => value >= 0,
+                    BZCond::GtZ => value > 0,
+                    BZCond::LeZ => value <= 0,
+                    BZCond::LtZ => value < 0,
+                };
+                if taken {
+                    return Ok(Flow::Jump(target));
+                }
+            }
+            Instruction::BranchZAndLink(cond, rs, target) => {
+                let value = self.reg(rs) as i32;
+                // The return address is set, even if the branch isn't taken.
+                self.regs[RA] = self.pc + 4;
+                let taken = match cond {
+                    BZalCond::GtZ => value > 0,
+                    BZalCond::LtZ => value < 0,
+                };
+                if taken {
+                    return Ok(Flow::Jump(target));
+                }
+            }
+            Instruction::BranchFpu(flag, target) => {
+                if self.fcc == flag {
+                    return Ok(Flow::Jump(target));
+                }
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn execute_mem(&mut self, op: MemOp, rt: u8, address: u32) -> Result<(), ErrorKind> {
+        let value = self.reg(rt);
+        match op {
+            MemOp::LoadByteS => self.set_reg(rt, self.memory.read(address, 1)? as i8 as u32),
+            MemOp::LoadByteU => self.set_reg(rt, self.memory.read(address, 1)?),
+            MemOp::LoadHalfS => self.set_reg(rt, self.memory.read(address, 2)? as i16 as u32),
+            MemOp::LoadHalfU => self.set_reg(rt, self.memory.read(address, 2)?),
+            MemOp::LoadWord | MemOp::LoadLinkedWord => {
+                self.set_reg(rt, self.memory.read(address, 4)?)
+            }
+            MemOp::StoreByte => self.memory.write(address, 1, value)?,
+            MemOp::StoreHalf => self.memory.write(address, 2, value)?,
+            MemOp::StoreWord => self.memory.write(address, 4, value)?,
+            MemOp::StoreConditionalWord => {
+                // There is only one thread, so the store always succeeds.
+                self.memory.write(address, 4, value)?;
+                self.set_reg(rt, 1);
+            }
+            // The unaligned loads and stores, for a little-endian memory. `byte` is the offset of
+            // the address in its word.
+            MemOp::LoadWordLeft => {
+                let (word, byte) = (self.memory.read(address & !3, 4)?, address & 3);
+                let shift = (3 - byte) * 8;
+                let kept = (1u64 << shift) as u32 - 1;
+                self.set_reg(rt, (value & kept) | (word << shift));
+            }
+            MemOp::LoadWordRight => {
+                let (word, byte) = (self.memory.read(address & !3, 4)?, address & 3);
+                let shift = byte * 8;
+                let replaced = u32::MAX >> shift;
+                self.set_reg(rt, (value & !replaced) | (word >> shift));
+            }
+            MemOp::StoreWordLeft => {
+                let (word, byte) = (self.memory.read(address & !3, 4)?, address & 3);
+                let shift = (3 - byte) * 8;
+                let replaced = u32::MAX >> shift;
+                self.memory
+                    .write(address & !3, 4, (word & !replaced) | (value >> shift))?;
+            }
+            MemOp::StoreWordRight => {
+                let (word, byte) = (self.memory.read(address & !3, 4)?, address & 3);
+                let shift = byte * 8;
+                let replaced = u32::MAX << shift;
+                self.memory
+                    .write(address & !3, 4, (word & !replaced) | (value << shift))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_freg2(&mut self, op: FRegOp2, fd: u8, fs: u8) -> Result<(), ErrorKind> {
+        match op {
+            FRegOp2::Abs(fmt) | FRegOp2::Neg(fmt) | FRegOp2::Sqrt(fmt) | FRegOp2::Move(fmt) => {
+                let f: fn(f64) -> f64 = match op {
+                    FRegOp2::Abs(_) => f64::abs,
+                    FRegOp2::Neg(_) => |x| -x,
+                    FRegOp2::Sqrt(_) => f64::sqrt,
+                    _ => |x| x,
+                };
+                match fmt {
+                    FFmt::S if matches!(op, FRegOp2::Move(_)) => {
+                        self.fregs[fd as usize] = self.fregs[fs as usize]
+                    }
+                    FFmt::S => self.set_single(fd, f(self.single(fs) as f64) as f32),
+                    FFmt::D if matches!(op, FRegOp2::Move(_)) => {
+                        check_even(fd)?;
+                        check_even(fs)?;
+                        self.fregs[fd as usize] = self.fregs[fs as usize];
+                        self.fregs[fd as usize + 1] = self.fregs[fs as usize + 1];
+                    }
+                    FFmt::D => {
+                        let value = f(self.double(fs)?);
+                        self.set_double(fd, value)?;
+                    }
+                }
+            }
+            FRegOp2::Cmp(cmp) => {
+                let (fmt, f): (_, fn(f64, f64) -> bool) = match cmp {
+                    FCmp::Eq(fmt) => (fmt, |a, b| a == b),
+                    FCmp::Le(fmt) => (fmt, |a, b| a <= b),
+                    FCmp::Lt(fmt) => (fmt, |a, b| a < b),
+                };
+                self.fcc = match fmt {
+                    FFmt::S => f(self.single(fd) as f64, self.single(fs) as f64),
+                    FFmt::D => f(self.double(fd)?, self.double(fs)?),
+                };
+            }
+            FRegOp2::Convert(to, from) => match (to, from) {
+                (FFmt::S, FFmt::D) => {
+                    let value = self.double(fs)? as f32;
+                    self.set_single(fd, value);
+                }
+                (FFmt::D, FFmt::S) => self.set_double(fd, self.single(fs) as f64)?,
+                _ => self.execute_freg2(FRegOp2::Move(to), fd, fs)?,
+            },
+            // Like MARS (Java), the conversion truncates and saturates, NaN becomes 0.
+            FRegOp2::ConvertToWord(FFmt::S) => {
+                self.fregs[fd as usize] = self.single(fs) as i32 as u32
+            }
+            FRegOp2::ConvertToWord(FFmt::D) => {
+                self.fregs[fd as usize] = self.double(fs)? as i32 as u32
+            }
+            FRegOp2::ConvertFromWord(fmt) => {
+                let value = self.fregs[fs as usize] as i32;
+                match fmt {
+                    FFmt::S => self.set_single(fd, value as f32),
+                    FFmt::D => self.set_double(fd, value as f64)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn reg(&self, r: u8) -> u32 {
+        self.regs[r as usize]
+    }
+
+    fn set_reg(&mut self, r: u8, value: u32) {
+        self.regs[r as usize] = value;
+    }
+
+    fn set_hi_lo(&mut self, product: u64) {
+        self.hi = (product >> 32) as u32;
+        self.lo = product as u32;
+    }
+
+    fn address(&self, base: u8, offset: u16) -> u32 {
+        self.reg(base).wrapping_add(offset as i16 as u32)
+    }
+
+    fn single(&self, f: u8) -> f32 {
+        f32::from_bits(self.fregs[f as usize])
+    }
+
+    fn set_single(&mut self, f: u8, value: f32) {
+        self.fregs[f as usize] = value.to_bits();
+    }
+
+    /// Doubles are stored in an even register and the next one, the low word first.
+    fn double(&self, f: u8) -> Result<f64, ErrorKind> {
+        check_even(f)?;
+        let low = self.fregs[f as usize] as u64;
+        let high = self.fregs[f as usize + 1] as u64;
+        Ok(f64::from_bits(high << 32 | low))
+    }
+
+    fn set_double(&mut self, f: u8, value: f64) -> Result<(), ErrorKind> {
+        check_even(f)?;
+        let bits = value.to_bits();
+        self.fregs[f as usize] = bits as u32;
+        self.fregs[f as usize + 1] = (bits >> 32) as u32;
+        Ok(())
+    }
+}
+
+fn signed_add(a: u32, b: u32) -> Result<u32, ErrorKind> {
+    (a as i32)
+        .checked_add(b as i32)
+        .map(|v| v as u32)
+        .ok_or(ErrorKind::Overflow)
+}
+
+fn check_even(f: u8) -> Result<(), ErrorKind> {
+    match f % 2 {
+        0 => Ok(()),
+        _ => Err(ErrorKind::OddFpuRegister(f)),
+    }
+}
@@ -0,0 +1,131 @@
+//! The MARS syscalls. The number of the syscall is in `$v0`, the arguments are in `$a0`-`$a1`
+//! or `$f12`, and the results are stored in `$v0` or `$f0`.
+
+use super::{ErrorKind, Flow, Machine};
+use crate::float_fmt;
+use std::io::{BufRead, Write};
+
+const V0: usize = 2;
+const A0: usize = 4;
+const A1: usize = 5;
+
+/// The start of the heap, used by `sbrk`.
+pub(super) const HEAP_START: u32 = 0x1004_0000;
+
+impl<R: BufRead, W: Write> Machine<'_, R, W> {
+    pub(super) fn syscall(&mut self) -> Result<Flow, ErrorKind> {
+        let number = self.regs[V0];
+        let a0 = self.regs[A0];
+        match number {
+            // print int
+            1 => self.print((a0 as i32).to_string().as_bytes())?,
+            // print float
+            2 => self.print(float_fmt::format_float(self.single(12)).as_bytes())?,
+            // print double
+            3 => self.print(float_fmt::format_double(self.double(12)?).as_bytes())?,
+            // print string
+            4 => {
+                let mut string = Vec::new();
+                for address in a0.. {
+                    match self.memory.read(address, 1)? {
+                        0 => break,
+                        byte => string.push(byte as u8),
+                    }
+                }
+                self.print(&string)?;
+            }
+            // read int
+            5 => {
+                let value: i32 = self.read_value(number)?;
+                self.regs[V0] = value as u32;
+            }
+            // read float
+            6 => {
+                let value = self.read_value(number)?;
+                self.set_single(0, value);
+            }
+            // read double
+            7 => {
+                let value = self.read_value(number)?;
+                self.set_double(0, value)?;
+            }
+            // read string: reads at most `$a1 - 1` characters of a line, followed by the newline
+            // if it fits, and a null byte.
+            8 => {
+                let max_length = self.regs[A1] as i32;
+                if max_length > 0 {
+                    let line = self.read_line()?;
+                    let length = line.len().min(max_length as usize - 1);
+                    let mut string = line[..length].to_vec();
+                    if length < max_length as usize - 1 {
+                        string.push(b'\n');
+                    }
+                    string.push(0);
+                    for (address, byte) in (a0..).zip(string) {
+                        self.memory.write(address, 1, byte as u32)?;
+                    }
+                }
+            }
+            // sbrk: allocate `$a0` bytes of heap memory
+            9 => {
+                self.regs[V0] = self.heap_end;
+                self.heap_end = self.heap_end.wrapping_add(a0).next_multiple_of(4);
+            }
+            // exit
+            10 => return Ok(Flow::Exit(0)),
+            // print char
+            11 => self.print(&[a0 as u8])?,
+            // read char, at the end of the input 0 is read
+            12 => {
+                let byte = self.read_byte()?.unwrap_or(0);
+                self.regs[V0] = byte as u32;
+            }
+            // exit2: exit with `$a0` as exit code
+            17 => return Ok(Flow::Exit(a0 as i32)),
+            _ => return Err(ErrorKind::UnknownSyscall(number)),
+        }
+        Ok(Flow::Next)
+    }
+
+    fn print(&mut self, bytes: &[u8]) -> Result<(), ErrorKind> {
+        self.output.write_all(bytes).map_err(io_error)
+    }
+
+    /// Reads a single byte, or `None` at the end of the input. Like [`Self::read_line`], the
+    /// output is flushed first so that a prompt is visible before waiting for input.
+    fn read_byte(&mut self) -> Result<Option<u8>, ErrorKind> {
+        self.output.flush().map_err(io_error)?;
+        let byte = self.input.fill_buf().map_err(io_error)?.first().copied();
+        if byte.is_some() {
+            self.input.consume(1);
+        }
+        Ok(byte)
+    }
+
+    /// Reads a line without the line ending.
+    fn read_line(&mut self) -> Result<Vec<u8>, ErrorKind> {
+        self.output.flush().map_err(io_error)?;
+        let mut line = Vec::new();
+        self.input.read_until(b'\n', &mut line).map_err(io_error)?;
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        Ok(line)
+    }
+
+    /// Reads a line and parses it, like MARS does for the syscalls that read numbers.
+    fn read_value<T: std::str::FromStr>(&mut self, number: u32) -> Result<T, ErrorKind> {
+        let line = self.read_line()?;
+        std::str::from_utf8(&line)
+            .ok()
+            .and_then(|line| line.trim().parse().ok())
+            .ok_or(ErrorKind::InvalidInput(number))
+    }
+}
+
+fn io_error(err: std::io::Error) -> ErrorKind {
+    ErrorKind::Io(err.to_string())
+}
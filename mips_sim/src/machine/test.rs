@@ -0,0 +1,278 @@
+use super::*;
+use mips_ir::{instr, term, DataDirective, Function, GlobalData, Reg, Root};
+
+fn run_with_input(asm: &str, input: &str) -> (Result<Exit, RuntimeError>, String) {
+    let program = Program::assemble(asm).unwrap_or_else(|e| panic!("{e}"));
+    let mut output = Vec::new();
+    let result = Machine::new(&program, input.as_bytes(), &mut output)
+        .with_step_limit(10_000)
+        .run();
+    (result, String::from_utf8(output).unwrap())
+}
+
+fn run(asm: &str) -> String {
+    match run_with_input(asm, "") {
+        (Ok(_), output) => output,
+        (Err(err), _) => panic!("{err}"),
+    }
+}
+
+fn run_err(asm: &str) -> ErrorKind {
+    run_with_input(asm, "").0.unwrap_err().kind
+}
+
+#[test]
+fn integer_arithmetic() {
+    let output = run("
+main:
+	ori	$t0, $zero, 7
+	addiu	$t1, $zero, -2
+	mult	$t0, $t1
+	mflo	$a0
+	ori	$v0, $zero, 1
+	syscall
+	ori	$a0, $zero, ' '
+	ori	$v0, $zero, 11
+	syscall
+	div	$t0, $t1
+	mfhi	$a0
+	ori	$v0, $zero, 1
+	syscall
+	sra	$a0, $t1, 1
+	syscall
+	srl	$a0, $t1, 28
+	syscall
+	sltu	$a0, $t0, $t1
+	syscall
+");
+    assert_eq!(output, "-14 1-1151");
+}
+
+#[test]
+fn loops_and_calls() {
+    // Prints the sum of 1 to 10 with a function that doubles its argument.
+    let output = run("
+double:
+	addu	$v0, $a0, $a0
+	jr	$ra
+main:
+	ori	$t0, $zero, 10
+	ori	$t1, $zero, 0
+$main.loop:
+	addu	$t1, $t1, $t0
+	addiu	$t0, $t0, -1
+	bgtz	$t0, $main.loop
+	or	$a0, $zero, $t1
+	jal	double
+	or	$a0, $zero, $v0
+	ori	$v0, $zero, 1
+	syscall
+");
+    assert_eq!(output, "110");
+}
+
+#[test]
+fn memory_and_strings() {
+    let output = run("
+	.data
+msg:	.asciiz	\"x = \"
+	.align	2
+buf:	.space	8
+	.text
+main:
+	la	$a0, msg
+	ori	$v0, $zero, 4
+	syscall
+	la	$t0, buf
+	addiu	$t1, $zero, -3
+	sh	$t1, 2($t0)
+	lh	$a0, 2($t0)
+	ori	$v0, $zero, 1
+	syscall
+	lbu	$a0, 3($t0)
+	syscall
+	sw	$t1, -4($sp)
+	lw	$a0, 65532($sp)
+	syscall
+");
+    assert_eq!(output, "x = -3255-3");
+}
+
+#[test]
+fn floating_point_is_printed_like_mars() {
+    let output = run("
+	.data
+a:	.double	0.1
+b:	.double	1.3
+c:	.float	9.0
+	.text
+main:
+	la	$t0, a
+	ldc1	$f0, 0($t0)
+	ldc1	$f2, 8($t0)
+	add.d	$f12, $f0, $f2
+	ori	$v0, $zero, 3
+	syscall
+	ori	$a0, $zero, ' '
+	ori	$v0, $zero, 11
+	syscall
+	lwc1	$f4, 16($t0)
+	cvt.d.s	$f12, $f4
+	ori	$v0, $zero, 3
+	syscall
+	ori	$v0, $zero, 11
+	syscall
+	ori	$t1, $zero, 10000
+	mult	$t1, $t1
+	mflo	$t1
+	mtc1	$t1, $f6
+	cvt.s.w	$f12, $f6
+	ori	$v0, $zero, 2
+	syscall
+	ori	$v0, $zero, 11
+	syscall
+	div.d	$f12, $f0, $f2
+	mul.d	$f12, $f12, $f0
+	mul.d	$f12, $f12, $f0
+	ori	$v0, $zero, 3
+	syscall
+	ori	$v0, $zero, 11
+	syscall
+	c.lt.d	$f2, $f0
+	bc1t	$main.wrong
+	cvt.w.d	$f8, $f2
+	mfc1	$a0, $f8
+	ori	$v0, $zero, 1
+	syscall
+$main.wrong:
+");
+    assert_eq!(
+        output,
+        "1.4000000000000001 9.0 1.0E8 7.692307692307693E-4 1"
+    );
+}
+
+#[test]
+fn java_float_formatting() {
+    use crate::float_fmt::{format_double, format_float};
+    assert_eq!(format_double(0.0), "0.0");
+    assert_eq!(format_double(-0.0), "-0.0");
+    assert_eq!(format_double(100.0), "100.0");
+    assert_eq!(format_double(0.001), "0.001");
+    assert_eq!(format_double(0.0001), "1.0E-4");
+    assert_eq!(format_double(1234567.5), "1234567.5");
+    assert_eq!(format_double(1e7), "1.0E7");
+    assert_eq!(format_double(-1.25e21), "-1.25E21");
+    assert_eq!(format_double(f64::NAN), "NaN");
+    assert_eq!(format_double(f64::NEG_INFINITY), "-Infinity");
+    assert_eq!(format_float(0.1), "0.1");
+    assert_eq!(format_float(0.1), format_double(0.1));
+    assert_eq!(format_double(0.1f32 as f64), "0.10000000149011612");
+}
+
+#[test]
+fn reads_input() {
+    let asm = "
+main:
+	ori	$v0, $zero, 5
+	syscall
+	or	$t0, $zero, $v0
+	ori	$v0, $zero, 12
+	syscall
+	or	$t1, $zero, $v0
+	ori	$v0, $zero, 7
+	syscall
+	or	$a0, $zero, $t0
+	ori	$v0, $zero, 1
+	syscall
+	or	$a0, $zero, $t1
+	ori	$v0, $zero, 11
+	syscall
+	mov.d	$f12, $f0
+	ori	$v0, $zero, 3
+	syscall
+	ori	$v0, $zero, 12
+	syscall
+	or	$a0, $zero, $v0
+	ori	$v0, $zero, 1
+	syscall
+";
+    let (result, output) = run_with_input(asm, " 42 \nx2.5\n");
+    assert!(result.is_ok());
+    assert_eq!(output, "42x2.50");
+
+    let (result, _) = run_with_input(asm, "forty-two\n");
+    assert_eq!(result.unwrap_err().kind, ErrorKind::InvalidInput(5));
+}
+
+#[test]
+fn exit_codes() {
+    let (result, _) = run_with_input(
+        "main:\n\tori\t$a0, $zero, 3\n\tori\t$v0, $zero, 17\n\tsyscall\n\tbreak\n",
+        "",
+    );
+    assert_eq!(result.unwrap(), Exit { code: 3, steps: 3 });
+
+    let (result, _) = run_with_input("main:\n\tnop\n", "");
+    assert_eq!(result.unwrap(), Exit { code: 0, steps: 1 });
+}
+
+#[test]
+fn runtime_errors() {
+    assert_eq!(run_err("main:\n\tteq\t$zero, $zero\n"), ErrorKind::Trap);
+    assert_eq!(run_err("main:\n\tbreak\n"), ErrorKind::Break);
+    assert_eq!(
+        run_err("main:\n\tlui\t$t0, 0x7fff\n\tadd\t$t0, $t0, $t0\n"),
+        ErrorKind::Overflow
+    );
+    assert_eq!(
+        run_err("main:\n\tlw\t$t0, 0($zero)\n"),
+        ErrorKind::AddressOutOfRange(0)
+    );
+    assert_eq!(
+        run_err("main:\n\tlw\t$t0, 2($sp)\n"),
+        ErrorKind::UnalignedAddress(0x7fff_effe)
+    );
+    assert_eq!(run_err("main:\n\tjr\t$ra\n"), ErrorKind::InvalidJump(0));
+    assert_eq!(
+        run_err("main:\n\tmov.d\t$f0, $f1\n"),
+        ErrorKind::OddFpuRegister(1)
+    );
+    assert_eq!(
+        run_err("main:\n\tori\t$v0, $zero, 99\n\tsyscall\n"),
+        ErrorKind::UnknownSyscall(99)
+    );
+    assert_eq!(run_err("main:\n\tj\tmain\n"), ErrorKind::StepLimit(10_000));
+}
+
+#[test]
+fn runs_linked_root_with_printf() {
+    let mut root = Root::new();
+    root.add_data(GlobalData::new(
+        "fmt".into(),
+        DataDirective::Bytes(b"%d!\n\0".to_vec().try_into().unwrap()),
+    ));
+    let printf = root.create_external_label("printf");
+    let mut main = Function::new("main".into(), Vec::new());
+    let mut builder = main.start_entry_block(Vec::new());
+    builder.add_instruction(instr::pseudo::load_address(Reg::T0, "fmt".into()));
+    builder.add_instruction(instr::add_u_imm(Reg::SP, Reg::SP, (-8i16) as u16));
+    builder.add_instruction(instr::store_word(Reg::T0, Reg::SP, 0));
+    builder.add_instruction(instr::or_imm(Reg::T1, Reg::ZERO, 42));
+    builder.add_instruction(instr::store_word(Reg::T1, Reg::SP, 4));
+    builder.add_instruction(instr::call(printf));
+    builder.add_instruction(instr::or_imm(Reg::A0, Reg::ZERO, 3));
+    builder.add_instruction(instr::or_imm(Reg::V0, Reg::ZERO, 17));
+    main.add_block(builder.terminate(term::syscall(None)));
+    root.add_function(main);
+    root.export_label("main".into());
+    let root = mips_ir::link([root]).unwrap();
+
+    let program = Program::from_root(&root).unwrap_or_else(|e| panic!("{e}"));
+    let mut output = Vec::new();
+    let exit = Machine::new(&program, std::io::empty(), &mut output)
+        .run()
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(exit.code, 3);
+    assert_eq!(String::from_utf8(output).unwrap(), "42!\n");
+}
@@ -0,0 +1,31 @@
+//warn:
+//ArrayOutOfBounds
+//ArrayOutOfBounds
+//ArrayOutOfBounds
+//NullDereference
+//NullDereference
+
+int table[3];
+
+int main() {
+    int values[4];
+    int *end = &values[4]; // The address one past the end can be computed
+    int *p = 0;
+    int *q = 0;
+    int i;
+
+    values[0] = 1;
+    values[4] = 2;
+    table[-1] = 3;
+    i = *(values + 5) + *end;
+
+    *p = i;
+    q = values;
+    *q = 4; // Not null anymore
+    p[1] = 5;
+
+    if (i) {
+        q = 0;
+    }
+    return *q + values[3] + table[0];
+}
@@ -0,0 +1,30 @@
+//warn:
+//IntegerOverflow
+//IntegerOverflow
+//ConstantConversion
+//DivisionByZero
+//DivisionByZero
+//DivisionByZero
+//ShiftCountOverflow
+//ShiftCountOverflow
+//ConstantConversion
+
+char letter = 300;
+
+int main() {
+    int big = 2000000000 + 2000000000;
+    int smallest = -(-2147483647 - 1);
+    unsigned int seconds = (unsigned int)2000000000 + 2000000000; // Unsigned arithmetic wraps
+    int flags = 1 << 31;                                          // Shifting into the sign bit is allowed
+    int total = 10;
+    int average = total / 0;
+    int remainder = total % 0;
+    double ratio = 1.0 / 0.0;
+    int mask = 1 << 32;
+    int shifted = flags >> -1;
+    char c = 1000;
+    char fits = 100;
+
+    return big + smallest + (int)seconds + average + remainder + (int)ratio + mask + shifted + c +
+           fits + letter;
+}
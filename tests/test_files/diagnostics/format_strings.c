@@ -0,0 +1,30 @@
+//warn:
+//FormatArgCount
+//FormatArgType
+//FormatArgType
+//ScanfMissingAddress
+//UnsupportedFormat
+//FormatArgCount
+//FormatArgType
+
+#include <stdio.h>
+
+int main() {
+    int count = 2;
+    double average = 1.5;
+    char name[10];
+    float ratio;
+
+    printf("%d and %d\n", count);
+    printf("%d\n", average);
+    printf("%s\n", count);
+    scanf("%d", count);
+    printf("%x\n", count);
+    printf("%i%%\n", count, average);
+    scanf("%d", &ratio);
+
+    // Only what the MIPS runtime supports
+    scanf("%9s %f", name, &ratio);
+    printf("%s: %c %f %i%%\n", name, 'a', ratio, count);
+    return 0;
+}
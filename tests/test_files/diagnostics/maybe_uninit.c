@@ -0,0 +1,59 @@
+//warn:
+//MaybeUninit
+//MaybeUninit
+//MaybeUninit
+//UsingUninit
+
+#include <stdio.h>
+
+int sign(int x) {
+    int result;
+    if (x < 0) {
+        result = -1;
+    } else if (x > 0) {
+        result = 1;
+    }
+    return result;
+}
+
+int first_even(int n) {
+    int i;
+    int found;
+    for (i = 0; i < n; i++) {
+        if (i % 2 == 0) {
+            found = i;
+            break;
+        }
+    }
+    return found;
+}
+
+// Initialized on every path, also through the fallthrough from `case 4`.
+int month_days(int month) {
+    int days;
+    switch (month) {
+    case 2:
+        days = 28;
+        break;
+    case 4:
+    case 6:
+        days = 30;
+        break;
+    default:
+        days = 31;
+    }
+    return days;
+}
+
+int main() {
+    int n;
+    int total;
+    int step;
+    // `scanf` initializes `n` through its address
+    scanf("%d", &n);
+    while (n > 0) {
+        total = total + n;
+        n = n - step;
+    }
+    return sign(n) + first_even(n) + month_days(n);
+}
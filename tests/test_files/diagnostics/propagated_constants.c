@@ -0,0 +1,32 @@
+//warn:
+//LossyImplicitReturn
+//LossyImplicitAssign
+//LossyImplicitAssign
+//LossyImplicitArg
+
+// The values of the variables are known while folding, the conversions are still lossy like they
+// are without folding.
+
+char to_char() {
+    int value = 65;
+    return value;
+}
+
+char identity(char c) {
+    return c;
+}
+
+int main() {
+    int small = 4;
+    char c = small;
+    char known = 4 + 4; // A constant that fits doesn't warn
+    {
+        int other = 100;
+        c = other;
+    }
+    {
+        int count = 5;
+        c = identity(count);
+    }
+    return c + known + to_char();
+}
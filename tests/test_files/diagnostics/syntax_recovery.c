@@ -0,0 +1,24 @@
+//fail:
+//SyntaxError
+//SyntaxError
+//SyntaxError
+//ConstantConversion
+
+// Every statement or declaration with a syntax error is reported, and the rest is still checked.
+// Using the names of the removed parts isn't reported again.
+
+int twice(int x) {
+    return x * 2
+}
+
+int broken(int a b) {
+    return a;
+}
+
+int main() {
+    int total = 0;
+    int x = 3 +;
+    char c = 1000;
+    total = c + twice(x) + broken(1, 2);
+    return total;
+}
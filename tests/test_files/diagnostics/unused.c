@@ -0,0 +1,33 @@
+//warn:
+//UnusedVariable
+//UnusedVariable
+//NoEffect
+//NoEffect
+//NoEffect
+
+#include <stdio.h>
+
+#pragma comp diagnostic warning "-Wunused-variable"
+#pragma comp diagnostic warning "-Wunused-value"
+
+int twice(int x, int unused) { // Unused parameters are only reported with -Wextra
+    return x * 2;
+}
+
+int main() {
+    int never;
+    int assigned = 1;
+    int read = 2;
+    int scanned;
+    assigned = read + 1;
+    scanf("%d", &scanned); // Taking the address counts as a use
+
+    read == 3;
+    read + 1;
+    -read;
+    (void)read; // No warning when the value is ignored on purpose
+    read++;
+    twice(read, 0);
+
+    return 0;
+}
@@ -0,0 +1,36 @@
+//output:
+//21
+//153
+//42
+//26
+//o 32 abi -1 42 !
+
+#include <stdio.h>
+
+int sum(int a, int b, int c, int d, int e, int f) {
+    return a + b + c + d + e + f;
+}
+
+// Floats and integers are mixed, so with `--o32` some are passed in registers and some aren't.
+int mixed(float a, int b, double c, char d, float e, double f, int g) {
+    return (int)(a * 10) + b + (int)(c * 100) + d + (int)e + (int)f + g;
+}
+
+int *last(int *a, int *b, int *c, int *d, int *e) {
+    return e;
+}
+
+int nested(int a, int b, int c, int d, int e) {
+    return sum(a, b, c, d, e, sum(e, d, c, b, a, 0)) + a - e;
+}
+
+int main() {
+    int x = 42;
+    int y = 0;
+    printf("%d\n", sum(1, 2, 3, 4, 5, 6));
+    printf("%d\n", mixed((float)1.5, 2, 0.25, 'a', (float)3.75, 4.5, 7));
+    printf("%d\n", *last(&y, &y, &y, &y, &x));
+    printf("%d\n", nested(1, 2, 3, 4, 5));
+    printf("%c %d %s %d %d %c\n", 'o', 32, "abi", -1, x, '!');
+    return 0;
+}
@@ -6,11 +6,14 @@ use std::{
 };
 
 use comp_lib::{
-    compile::{CompileOptsBuilder, Target},
+    compile::{CompileOptsBuilder, OutputFormat, Target},
     diagnostic::{AggregateResult, Code, DiagnosticKind},
+    interpreter::Interpreter,
 };
 use temp_file::TempFileBuilder;
 
+mod wasm;
+
 include! {concat!(env!("OUT_DIR"), "/tests.rs")}
 
 pub fn compile(target: Target, file_name: &str, source: &str) -> AggregateResult<Vec<u8>> {
@@ -23,6 +26,22 @@ pub fn compile(target: Target, file_name: &str, source: &str) -> AggregateResult
     comp_lib::compile::compile(source, file_name, &opts)
 }
 
+pub fn compile_to_format(
+    target: Target,
+    format: OutputFormat,
+    file_name: &str,
+    source: &str,
+) -> AggregateResult<Vec<u8>> {
+    let opts = CompileOptsBuilder::new()
+        .target(target)
+        .output_format(format)
+        .for_assignments()
+        .build()
+        .unwrap();
+
+    comp_lib::compile::compile(source, file_name, &opts)
+}
+
 fn run_lli(input_ir: Vec<u8>) -> String {
     let lli_bin = std::env::var_os("LLI_BIN").unwrap_or_else(|| "lli".into());
     let mut lli = Command::new(lli_bin)
@@ -49,17 +68,39 @@ fn run_lli(input_ir: Vec<u8>) -> String {
     String::from_utf8_lossy(&output.stdout).into_owned()
 }
 
-fn run_mars(input_asm: Vec<u8>) -> String {
-    let mars_bin = std::env::var_os("MARS_BIN").unwrap_or_else(|| "mars-mips".into());
+/// Runs the assembly in the MIPS simulator, with `input` as stdin.
+fn run_mips(input_asm: Vec<u8>, input: &[u8]) -> String {
+    let input_asm = String::from_utf8(input_asm).unwrap();
+    let program = mips_sim::Program::assemble(&input_asm)
+        .unwrap_or_else(|e| panic!("Failed to assemble the MIPS output: {e}"));
 
+    let mut output = Vec::new();
+    let exit = mips_sim::Machine::new(&program, input, &mut output)
+        .with_step_limit(20_000_000)
+        .run()
+        .unwrap_or_else(|e| panic!("The MIPS simulator stopped with an error: {e}"));
+    if exit.code != 0 {
+        panic!("The MIPS program returned with a non successfull code!");
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn run_rars(input_asm: Vec<u8>) -> String {
+    let rars_bin = std::env::var_os("RARS_BIN").unwrap();
+    run_simulator(rars_bin, "rars", ".s", input_asm)
+}
+
+/// Runs the assembly in RARS (or any simulator with the same command line interface as MARS).
+fn run_simulator(bin: std::ffi::OsString, name: &str, suffix: &str, input_asm: Vec<u8>) -> String {
     let temp_file = TempFileBuilder::new()
-        .suffix(".asm") // Need .asm suffix for mars to work
+        .suffix(suffix)
         .build()
         .unwrap()
         .with_contents(&input_asm)
         .unwrap();
 
-    let mut mars = Command::new(mars_bin)
+    let mut simulator = Command::new(bin)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -67,51 +108,175 @@ fn run_mars(input_asm: Vec<u8>) -> String {
         .arg("sm") // Start execution in main label
         .arg(temp_file.path())
         .spawn()
-        .expect("Failed to spawn mars process");
+        .unwrap_or_else(|_| panic!("Failed to spawn {name} process"));
 
     use wait_timeout::ChildExt;
-    match mars.wait_timeout(Duration::from_secs(10)) {
+    match simulator.wait_timeout(Duration::from_secs(10)) {
         Ok(Some(status)) => {
             if !status.success() {
-                panic!("mars returned with a non successfull code!");
+                panic!("{name} returned with a non successfull code!");
             }
         }
         Ok(None) => {
-            mars.kill().ok();
-            panic!("Hit timeout while running mars")
+            simulator.kill().ok();
+            panic!("Hit timeout while running {name}")
         }
-        Err(_) => panic!("Faild to run mars"),
+        Err(_) => panic!("Faild to run {name}"),
     }
 
     let mut output = String::new();
-    mars.stdout.unwrap().read_to_string(&mut output).unwrap();
+    simulator
+        .stdout
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
 
     output
 }
 
-fn output_test(file: &str, expected_llvm: &str, expected_mips: &str) {
-    for target in [Target::X86_64, Target::Mips] {
-        let source = fs::read(file).unwrap();
-        let source = String::from_utf8(source).unwrap();
-        let res = compile(target, file, &source);
-        if res.is_err() {
-            println!(
-                "Expected file `{}` to compile successfully but got the following diagnostics:",
-                file
-            );
-            for (t, d) in res.diagnostics() {
-                match t {
-                    DiagnosticKind::Rec => println!("Rec: {d:?}"),
-                    DiagnosticKind::Err => println!("Err: {d:?}"),
-                }
+/// Builds the program with the native C compiler, `suffix` is `.s` for assembly or `.c` for C.
+fn run_native(input: Vec<u8>, suffix: &str) -> String {
+    let cc_bin = std::env::var_os("CC_BIN").unwrap();
+
+    let input_file = TempFileBuilder::new()
+        .suffix(suffix)
+        .build()
+        .unwrap()
+        .with_contents(&input)
+        .unwrap();
+    let exe_file = TempFileBuilder::new().build().unwrap();
+
+    let output = Command::new(cc_bin)
+        .arg("-w")
+        .arg(input_file.path())
+        .arg("-o")
+        .arg(exe_file.path())
+        .output()
+        .expect("Failed to spawn cc process");
+    if !output.status.success() {
+        println!("cc stderr:\n{}", String::from_utf8_lossy(&output.stderr));
+        panic!("cc returned with a non successfull code!");
+    }
+
+    let output = Command::new(exe_file.path())
+        .stdin(Stdio::null())
+        .output()
+        .expect("Failed to run the assembled program");
+    if !output.status.success() {
+        panic!("the native program returned with a non successfull code!");
+    }
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// The environment variable with the tool that runs the output of `format`, if it isn't run by
+/// `lli` or in-tree. Those backends are only tested if it's set, e.g. `CC_BIN=cc`.
+fn external_runner(format: OutputFormat) -> Option<&'static str> {
+    match format {
+        OutputFormat::X86Asm | OutputFormat::IrC => Some("CC_BIN"),
+        OutputFormat::RiscVAsm => Some("RARS_BIN"),
+        _ => None,
+    }
+}
+
+fn run_wasm(input_wat: Vec<u8>) -> String {
+    match wasm::run_wasm(&input_wat, b"") {
+        Ok((output, _)) => output,
+        Err(e) => panic!("wasmi failed to run the module: {e}"),
+    }
+}
+
+/// Runs the program in the reference interpreter, which has the semantics of C on X86_64, so its
+/// output should match lli.
+fn run_interpreter(file: &str, source: &str) -> String {
+    let opts = CompileOptsBuilder::new()
+        .target(Target::X86_64)
+        .for_assignments()
+        .build()
+        .unwrap();
+    let root = expect_compiled(file, comp_lib::compile::compile_to_ir(source, &opts));
+
+    let mut output = Vec::new();
+    let code = Interpreter::new(&root, Target::X86_64, std::io::empty(), &mut output)
+        .with_step_limit(20_000_000)
+        .run()
+        .unwrap_or_else(|e| {
+            let range: std::ops::Range<usize> = e.span.into();
+            panic!(
+                "The interpreter stopped with an error: {e} (`{}`)",
+                &source[range]
+            )
+        });
+    if code != 0 {
+        panic!("The interpreted program returned with a non successfull code!");
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn expect_compiled<T>(file: &str, res: AggregateResult<T>) -> T {
+    if res.is_err() {
+        println!(
+            "Expected file `{}` to compile successfully but got the following diagnostics:",
+            file
+        );
+        for (t, d) in res.diagnostics() {
+            match t {
+                DiagnosticKind::Rec => println!("Rec: {d:?}"),
+                DiagnosticKind::Err => println!("Err: {d:?}"),
             }
-            panic!();
         }
-        let comp_output = res.into_value().unwrap();
-        let (output, expected, runner) = if target == Target::X86_64 {
-            (run_lli(comp_output), expected_llvm, "lli")
-        } else {
-            (run_mars(comp_output), expected_mips, "mars")
+        panic!();
+    }
+    res.into_value().unwrap()
+}
+
+fn output_test(file: &str, expected_llvm: &str, expected_mips: &str) {
+    let source = fs::read(file).unwrap();
+    let source = String::from_utf8(source).unwrap();
+
+    // The interpreter is the golden model, so the backends are only tested against an expected
+    // output that it agrees with.
+    pretty_assertions::assert_str_eq!(
+        run_interpreter(file, &source).trim_end(),
+        expected_llvm.trim_end(),
+        "The output of the interpreter (left) does not match the expected output (right)",
+    );
+
+    for (target, format) in [
+        (Target::X86_64, OutputFormat::LlvmIr),
+        (Target::X86_64, OutputFormat::X86Asm),
+        (Target::X86_64, OutputFormat::IrC),
+        (Target::Mips, OutputFormat::MipsAsm),
+        (Target::RiscV32, OutputFormat::RiscVAsm),
+        (Target::Wasm32, OutputFormat::Wat),
+    ] {
+        if external_runner(format).is_some_and(|var| std::env::var_os(var).is_none()) {
+            continue;
+        }
+        let res = compile_to_format(target, format, file, &source);
+        let comp_output = expect_compiled(file, res);
+        let (output, expected, runner) = match format {
+            OutputFormat::LlvmIr => (run_lli(comp_output), expected_llvm, "lli"),
+            OutputFormat::X86Asm => (
+                run_native(comp_output, ".s"),
+                expected_llvm,
+                "the native program",
+            ),
+            // The lowered IR is compiled by the native compiler, which checks it against another
+            // implementation of C.
+            OutputFormat::IrC => (
+                run_native(comp_output, ".c"),
+                expected_llvm,
+                "the program compiled from the C output",
+            ),
+            OutputFormat::RiscVAsm => (run_rars(comp_output), expected_mips, "rars"),
+            // The host implements printf like C does, so the output matches lli.
+            OutputFormat::Wat => (run_wasm(comp_output), expected_llvm, "wasmi"),
+            _ => (
+                run_mips(comp_output, &[]),
+                expected_mips,
+                "the MIPS simulator",
+            ),
         };
 
         pretty_assertions::assert_str_eq!(
@@ -120,6 +285,27 @@ fn output_test(file: &str, expected_llvm: &str, expected_mips: &str) {
             "The output of {runner} (left) does not match the expected output (right)",
         );
     }
+
+    let output = run_mips(expect_compiled(file, compile_o32(file, &source)), &[]);
+    pretty_assertions::assert_str_eq!(
+        output.trim_end(),
+        expected_mips.trim_end(),
+        "The output of the MIPS simulator with --o32 (left) does not match the expected output \
+         (right)",
+    );
+}
+
+/// Compiles for MIPS with the O32 calling convention, which passes the first arguments in
+/// registers.
+fn compile_o32(file_name: &str, source: &str) -> AggregateResult<Vec<u8>> {
+    let opts = CompileOptsBuilder::new()
+        .target(Target::Mips)
+        .o32_abi(true)
+        .for_assignments()
+        .build()
+        .unwrap();
+
+    comp_lib::compile::compile(source, file_name, &opts)
 }
 
 fn diagnostics_test(file: &str, expected_codes: Vec<Code>, needs_err: bool) {
@@ -177,3 +363,54 @@ fn diagnostics_any_test(file: &str, needs_err: bool) {
         }
     }
 }
+
+/// Runs a program compiled for MIPS with runtime bounds checks on every array subscript.
+fn run_bounds_checked(source: &str) -> Result<mips_sim::Exit, mips_sim::RuntimeError> {
+    let opts = CompileOptsBuilder::new()
+        .target(Target::Mips)
+        .bounds_check(true)
+        .build()
+        .unwrap();
+    let asm = expect_compiled(
+        "bounds_check.c",
+        comp_lib::compile::compile(source, "", &opts),
+    );
+    let program = mips_sim::Program::assemble(&String::from_utf8(asm).unwrap()).unwrap();
+    mips_sim::Machine::new(&program, std::io::empty(), std::io::sink())
+        .with_step_limit(20_000)
+        .run()
+}
+
+#[test]
+fn bounds_check_traps() {
+    let source = "int main() {
+    int values[4];
+    int i;
+    for (i = 0; i < LIMIT; i++) {
+        values[i] = i;
+    }
+    return values[3];
+}";
+    let exit = run_bounds_checked(&source.replace("LIMIT", "4")).unwrap();
+    assert_eq!(exit.code, 3);
+    let err = run_bounds_checked(&source.replace("LIMIT", "5")).unwrap_err();
+    assert_eq!(err.kind, mips_sim::ErrorKind::Trap);
+}
+
+#[test]
+fn o32_scanf_reads_every_argument() {
+    let source = r#"#include <stdio.h>
+
+int main() {
+    int a;
+    int b;
+    int c;
+    int d;
+    int e;
+    int read = scanf("%d %d %d %d %d", &a, &b, &c, &d, &e);
+    printf("%d: %d %d %d %d %d\n", read, e, d, c, b, a);
+    return 0;
+}"#;
+    let asm = expect_compiled("scanf.c", compile_o32("scanf.c", source));
+    assert_eq!(run_mips(asm, b"1 2 3 4 5\n"), "5: 5 4 3 2 1\n");
+}
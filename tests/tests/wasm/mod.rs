@@ -0,0 +1,502 @@
+//! A host for the WebAssembly target, that runs the generated module in the wasmi interpreter and
+//! implements the imported `printf` and `scanf` functions.
+
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store, Val};
+
+struct Host {
+    input: Vec<u8>,
+    input_pos: usize,
+    output: Vec<u8>,
+}
+
+/// Runs the `main` function of the module in the text format, with `input` as stdin. Returns the
+/// output and the return value of `main`.
+pub fn run_wasm(wat: &[u8], input: &[u8]) -> Result<(String, i32), String> {
+    let wasm = wat::parse_bytes(wat).map_err(|e| format!("invalid module: {e}"))?;
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..]).map_err(|e| format!("invalid module: {e}"))?;
+    let host = Host {
+        input: input.to_vec(),
+        input_pos: 0,
+        output: Vec::new(),
+    };
+    let mut store = Store::new(&engine, host);
+
+    let mut linker = <Linker<Host>>::new(&engine);
+    linker
+        .func_wrap(
+            "env",
+            "printf",
+            |mut caller: Caller<'_, Host>, format: i32, args: i32| -> i32 {
+                let memory = memory(&caller);
+                let (memory, host) = memory.data_and_store_mut(&mut caller);
+                let output = printf(memory, format as u32, args as u32);
+                host.output.extend_from_slice(&output);
+                output.len() as i32
+            },
+        )
+        .unwrap();
+    linker
+        .func_wrap(
+            "env",
+            "scanf",
+            |mut caller: Caller<'_, Host>, format: i32, args: i32| -> i32 {
+                let memory = memory(&caller);
+                let (memory, host) = memory.data_and_store_mut(&mut caller);
+                let mut input = Input {
+                    bytes: &host.input,
+                    pos: host.input_pos,
+                };
+                let assigned = scanf(memory, &mut input, format as u32, args as u32);
+                host.input_pos = input.pos;
+                assigned
+            },
+        )
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|pre| pre.start(&mut store))
+        .map_err(|e| format!("failed to instantiate: {e}"))?;
+    let main = instance
+        .get_func(&store, "main")
+        .ok_or("the module has no main function")?;
+    // Params of main (if any) are zero.
+    let params: Vec<_> = main
+        .ty(&store)
+        .params()
+        .iter()
+        .map(|ty| Val::default(*ty))
+        .collect();
+    let mut result = [Val::I32(0)];
+    let trap = main.call(&mut store, &params, &mut result).err();
+
+    let output = String::from_utf8_lossy(&store.data().output).into_owned();
+    match trap {
+        Some(trap) => Err(format!("trapped: {trap}\noutput:\n{output}")),
+        None => Ok((output, result[0].i32().unwrap_or(0))),
+    }
+}
+
+fn memory(caller: &Caller<'_, Host>) -> wasmi::Memory {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .expect("the module should export its memory")
+}
+
+/// Reads the variadic arguments from the buffer passed to `printf` and `scanf`. Every argument
+/// is aligned to its size.
+struct VarArgs<'m> {
+    memory: &'m [u8],
+    address: usize,
+}
+
+impl VarArgs<'_> {
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        self.address = self.address.next_multiple_of(N);
+        let bytes = self.memory[self.address..self.address + N]
+            .try_into()
+            .unwrap();
+        self.address += N;
+        bytes
+    }
+
+    fn int(&mut self) -> i32 {
+        i32::from_le_bytes(self.bytes())
+    }
+
+    fn double(&mut self) -> f64 {
+        f64::from_le_bytes(self.bytes())
+    }
+}
+
+fn c_string(memory: &[u8], address: u32) -> &[u8] {
+    let start = address as usize;
+    let len = memory[start..].iter().position(|&b| b == 0).unwrap();
+    &memory[start..start + len]
+}
+
+/// A conversion specification of `printf`.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn printf(memory: &[u8], format: u32, args: u32) -> Vec<u8> {
+    let format = c_string(memory, format);
+    let mut args = VarArgs {
+        memory,
+        address: args as usize,
+    };
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            out.push(format[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+
+        let mut spec = Spec::default();
+        while let Some(flag) = format.get(i) {
+            match flag {
+                b'-' => spec.left = true,
+                b'+' => spec.plus = true,
+                b' ' => spec.space = true,
+                b'#' => spec.alternate = true,
+                b'0' => spec.zero = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        if format.get(i) == Some(&b'*') {
+            let width = args.int();
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs() as usize;
+            i += 1;
+        }
+        while let Some(digit @ b'0'..=b'9') = format.get(i) {
+            spec.width = spec.width * 10 + (digit - b'0') as usize;
+            i += 1;
+        }
+        if format.get(i) == Some(&b'.') {
+            i += 1;
+            let mut precision = 0;
+            if format.get(i) == Some(&b'*') {
+                precision = args.int().max(0) as usize;
+                i += 1;
+            }
+            while let Some(digit @ b'0'..=b'9') = format.get(i) {
+                precision = precision * 10 + (digit - b'0') as usize;
+                i += 1;
+            }
+            spec.precision = Some(precision);
+        }
+        // Every integer is 32 bits and every floating point argument is a double.
+        while let Some(b'h' | b'l' | b'L') = format.get(i) {
+            i += 1;
+        }
+
+        let Some(&conversion) = format.get(i) else {
+            break;
+        };
+        i += 1;
+        let (sign, body): (&str, Vec<u8>) = match conversion {
+            b'd' | b'i' => {
+                let value = args.int();
+                let sign = if value < 0 { "-" } else { spec.sign() };
+                (sign, spec.int_digits(value.unsigned_abs().to_string()))
+            }
+            b'u' => ("", spec.int_digits((args.int() as u32).to_string())),
+            b'o' => {
+                let mut digits = format!("{:o}", args.int() as u32);
+                if spec.alternate && !digits.starts_with('0') {
+                    digits.insert(0, '0');
+                }
+                ("", spec.int_digits(digits))
+            }
+            b'x' | b'X' => {
+                let value = args.int() as u32;
+                let mut digits = spec.int_digits(format!("{value:x}"));
+                if spec.alternate && value != 0 {
+                    digits.splice(0..0, *b"0x");
+                }
+                if conversion == b'X' {
+                    digits.make_ascii_uppercase();
+                }
+                ("", digits)
+            }
+            b'p' => ("", format!("0x{:x}", args.int() as u32).into_bytes()),
+            b'c' => ("", vec![args.int() as u8]),
+            b's' => {
+                let string = c_string(memory, args.int() as u32);
+                let len = spec.precision.map_or(string.len(), |p| p.min(string.len()));
+                ("", string[..len].to_vec())
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                let value = args.double();
+                let sign = if value.is_sign_negative() && !value.is_nan() {
+                    "-"
+                } else {
+                    spec.sign()
+                };
+                let mut body = spec.float(value.abs(), conversion.to_ascii_lowercase());
+                if conversion.is_ascii_uppercase() {
+                    body.make_ascii_uppercase();
+                }
+                (sign, body)
+            }
+            b'%' => {
+                out.push(b'%');
+                continue;
+            }
+            other => {
+                // Unknown conversions are printed as is.
+                out.push(b'%');
+                out.push(other);
+                continue;
+            }
+        };
+
+        let len = sign.len() + body.len();
+        let padding = spec.width.saturating_sub(len);
+        // The 0 flag is ignored for strings, and for integers with a precision.
+        let ignores_zero = matches!(conversion, b'c' | b's' | b'p')
+            || (spec.precision.is_some() && b"diouxX".contains(&conversion));
+        let zero_pad = spec.zero && !spec.left && !ignores_zero;
+        if spec.left {
+            out.extend_from_slice(sign.as_bytes());
+            out.extend_from_slice(&body);
+            out.extend(std::iter::repeat_n(b' ', padding));
+        } else if zero_pad {
+            out.extend_from_slice(sign.as_bytes());
+            out.extend(std::iter::repeat_n(b'0', padding));
+            out.extend_from_slice(&body);
+        } else {
+            out.extend(std::iter::repeat_n(b' ', padding));
+            out.extend_from_slice(sign.as_bytes());
+            out.extend_from_slice(&body);
+        }
+    }
+    out
+}
+
+impl Spec {
+    fn sign(&self) -> &'static str {
+        if self.plus {
+            "+"
+        } else if self.space {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Applies the precision (the minimum number of digits) to the digits of an integer.
+    fn int_digits(&self, digits: String) -> Vec<u8> {
+        match self.precision {
+            Some(0) if digits == "0" => Vec::new(),
+            Some(precision) => format!("{digits:0>precision$}").into_bytes(),
+            None => digits.into_bytes(),
+        }
+    }
+
+    /// Formats a non-negative floating point value for the `f`, `e` or `g` conversion.
+    fn float(&self, value: f64, conversion: u8) -> Vec<u8> {
+        if value.is_nan() {
+            return b"nan".to_vec();
+        }
+        if value.is_infinite() {
+            return b"inf".to_vec();
+        }
+        let precision = self.precision.unwrap_or(6);
+        let mut out = match conversion {
+            b'f' => format!("{value:.precision$}"),
+            b'e' => exponent_format(value, precision),
+            _ => {
+                // The precision is the number of significant digits, the style depends on the
+                // exponent of the value rounded to that precision.
+                let precision = precision.max(1);
+                let rounded = exponent_format(value, precision - 1);
+                let exponent: i32 = rounded[rounded.find('e').unwrap() + 1..].parse().unwrap();
+                let mut out = if exponent < -4 || exponent >= precision as i32 {
+                    rounded
+                } else {
+                    let decimals = (precision as i32 - 1 - exponent) as usize;
+                    format!("{value:.decimals$}")
+                };
+                if !self.alternate && out.contains('.') {
+                    let exponent_start = out.find('e').unwrap_or(out.len());
+                    let exponent = out.split_off(exponent_start);
+                    out.truncate(out.trim_end_matches('0').trim_end_matches('.').len());
+                    out += &exponent;
+                }
+                out
+            }
+        };
+        if self.alternate && !out.contains('.') {
+            let exponent_start = out.find('e').unwrap_or(out.len());
+            out.insert(exponent_start, '.');
+        }
+        out.into_bytes()
+    }
+}
+
+/// Formats like `%.<precision>e`, with at least two digits in the exponent.
+fn exponent_format(value: f64, precision: usize) -> String {
+    let formatted = format!("{value:.precision$e}");
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+}
+
+struct Input<'i> {
+    bytes: &'i [u8],
+    pos: usize,
+}
+
+impl Input<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Takes the longest prefix (of at most `width` bytes) for which `accept` returns true. The
+    /// closure gets the bytes taken so far and the next byte.
+    fn take_while(&mut self, width: usize, mut accept: impl FnMut(&[u8], u8) -> bool) -> &[u8] {
+        let start = self.pos;
+        while self.pos - start < width
+            && self
+                .peek()
+                .is_some_and(|b| accept(&self.bytes[start..self.pos], b))
+        {
+            self.pos += 1;
+        }
+        &self.bytes[start..self.pos]
+    }
+}
+
+fn scanf(memory: &mut [u8], input: &mut Input, format: u32, args: u32) -> i32 {
+    let format = c_string(memory, format).to_vec();
+    let mut args_address = args as usize;
+    let mut assigned = 0;
+    let mut i = 0;
+    while i < format.len() {
+        let c = format[i];
+        i += 1;
+        if c.is_ascii_whitespace() {
+            input.skip_whitespace();
+            continue;
+        }
+        if c != b'%' || format.get(i) == Some(&b'%') {
+            if c == b'%' {
+                i += 1;
+                input.skip_whitespace();
+            }
+            if input.peek() != Some(c) {
+                break;
+            }
+            input.pos += 1;
+            continue;
+        }
+
+        let suppress = format.get(i) == Some(&b'*');
+        if suppress {
+            i += 1;
+        }
+        let mut width = 0;
+        while let Some(digit @ b'0'..=b'9') = format.get(i) {
+            width = width * 10 + (digit - b'0') as usize;
+            i += 1;
+        }
+        let mut size = 4;
+        while let Some(modifier @ (b'h' | b'l' | b'L')) = format.get(i) {
+            size = match modifier {
+                b'h' => 2,
+                _ => 8,
+            };
+            i += 1;
+        }
+        let Some(&conversion) = format.get(i) else {
+            break;
+        };
+        i += 1;
+
+        if conversion != b'c' {
+            input.skip_whitespace();
+        }
+        if input.peek().is_none() {
+            return if assigned == 0 { -1 } else { assigned };
+        }
+        let width = if width == 0 { usize::MAX } else { width };
+
+        let mut pointer = |memory: &[u8]| {
+            args_address = args_address.next_multiple_of(4);
+            let bytes = memory[args_address..args_address + 4].try_into().unwrap();
+            args_address += 4;
+            u32::from_le_bytes(bytes) as usize
+        };
+        match conversion {
+            b'd' | b'i' | b'u' | b'o' | b'x' | b'X' => {
+                let radix = match conversion {
+                    b'o' => 8,
+                    b'x' | b'X' => 16,
+                    _ => 10,
+                };
+                let text = input.take_while(width, |taken, b| {
+                    (taken.is_empty() && (b == b'-' || b == b'+')) || (b as char).is_digit(radix)
+                });
+                let Ok(value) = i64::from_str_radix(&String::from_utf8_lossy(text), radix) else {
+                    break;
+                };
+                if !suppress {
+                    let address = pointer(memory);
+                    let bytes = (value as i32).to_le_bytes();
+                    let size = if size == 8 { 4 } else { size };
+                    memory[address..address + size].copy_from_slice(&bytes[..size]);
+                }
+            }
+            b'f' | b'e' | b'g' | b'E' | b'G' => {
+                let text = input.take_while(width, |taken, b| {
+                    let last = taken.last().copied();
+                    b.is_ascii_digit()
+                        || (matches!(b, b'-' | b'+')
+                            && (taken.is_empty() || matches!(last, Some(b'e' | b'E'))))
+                        || (b == b'.' && !taken.contains(&b'.'))
+                        || (matches!(b, b'e' | b'E')
+                            && !taken.iter().any(|t| matches!(t, b'e' | b'E'))
+                            && taken.iter().any(u8::is_ascii_digit))
+                });
+                let Ok(value) = String::from_utf8_lossy(text).parse::<f64>() else {
+                    break;
+                };
+                if !suppress {
+                    let address = pointer(memory);
+                    if size == 8 {
+                        memory[address..address + 8].copy_from_slice(&value.to_le_bytes());
+                    } else {
+                        let bytes = (value as f32).to_le_bytes();
+                        memory[address..address + 4].copy_from_slice(&bytes);
+                    }
+                }
+            }
+            b'c' => {
+                let width = if width == usize::MAX { 1 } else { width };
+                let text = input.take_while(width, |_, _| true).to_vec();
+                if !suppress {
+                    let address = pointer(memory);
+                    memory[address..address + text.len()].copy_from_slice(&text);
+                }
+            }
+            b's' => {
+                let text = input
+                    .take_while(width, |_, b| !b.is_ascii_whitespace())
+                    .to_vec();
+                if !suppress {
+                    let address = pointer(memory);
+                    memory[address..address + text.len()].copy_from_slice(&text);
+                    memory[address + text.len()] = 0;
+                }
+            }
+            _ => break,
+        }
+        if !suppress {
+            assigned += 1;
+        }
+    }
+    assigned
+}